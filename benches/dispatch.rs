@@ -0,0 +1,69 @@
+//! Criterion benchmarks for tool dispatch through `ToolService::call_tool`,
+//! the path every transport funnels through.
+//!
+//! Run with `cargo bench --bench dispatch`; criterion keeps the previous run
+//! under `target/criterion` and reports the change against it.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use inferenco_mcp::server::ToolService;
+use serde_json::json;
+use tokio::runtime::Runtime;
+
+/// Calls issued at once by the parallel benchmark.
+const PARALLEL_CALLS: usize = 64;
+
+fn dispatch(c: &mut Criterion) {
+    let runtime = Runtime::new().expect("tokio runtime");
+    let service = ToolService::new();
+    let mut group = c.benchmark_group("dispatch");
+
+    group.bench_function("echo", |b| {
+        b.to_async(&runtime).iter(|| async {
+            service
+                .call_tool("echo", json!({ "message": "hello" }))
+                .await
+                .expect("echo succeeds")
+        })
+    });
+    group.bench_function("kv_set", |b| {
+        b.to_async(&runtime).iter(|| async {
+            service
+                .call_tool("kv_set", json!({ "key": "bench", "value": { "n": 1 } }))
+                .await
+                .expect("kv_set succeeds")
+        })
+    });
+    group.bench_function("unknown_tool", |b| {
+        b.to_async(&runtime).iter(|| async {
+            service
+                .call_tool("no_such_tool", json!({}))
+                .await
+                .expect_err("unknown tools fail")
+        })
+    });
+
+    let message = "lorem ipsum ".repeat(400);
+    group.throughput(Throughput::Bytes(message.len() as u64));
+    group.bench_function("echo_5kb", |b| {
+        b.to_async(&runtime).iter_batched(
+            || json!({ "message": message }),
+            |arguments| async { service.call_tool("echo", arguments).await },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.throughput(Throughput::Elements(PARALLEL_CALLS as u64));
+    group.bench_function("echo_parallel_64", |b| {
+        b.to_async(&runtime).iter(|| {
+            futures::future::join_all(
+                (0..PARALLEL_CALLS).map(|index| {
+                    service.call_tool("echo", json!({ "message": index.to_string() }))
+                }),
+            )
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, dispatch);
+criterion_main!(benches);
@@ -0,0 +1,149 @@
+//! Load generator for the HTTP transport: keeps `--concurrency` `tools/call`
+//! requests in flight against a running server and reports throughput and
+//! latency percentiles.
+//!
+//! Start a server with `cargo run --release -- serve --transport http`, then
+//! run `cargo run --release --example load_test -- --concurrency 32`.
+
+use clap::Parser;
+use serde_json::{json, Value};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+#[derive(Parser)]
+#[command(about = "Drive /rpc with concurrent tool calls")]
+struct Args {
+    /// JSON-RPC endpoint of the server under test.
+    #[arg(long, default_value = "http://127.0.0.1:8080/rpc")]
+    url: String,
+    /// Requests kept in flight at once.
+    #[arg(long, default_value_t = 16)]
+    concurrency: usize,
+    /// Total requests to send.
+    #[arg(long, default_value_t = 10_000)]
+    requests: usize,
+    /// Tool to call.
+    #[arg(long, default_value = "echo")]
+    tool: String,
+    /// Tool arguments as JSON.
+    #[arg(long, default_value = r#"{"message":"load test"}"#)]
+    arguments: String,
+    /// API key, when the server requires one.
+    #[arg(long, env = "INFERENCO_MCP_API_KEY")]
+    api_key: Option<String>,
+    /// Header the API key is sent in.
+    #[arg(long, default_value = "x-api-key")]
+    auth_header: String,
+}
+
+/// Outcome of one request: its latency, or why it failed.
+type Sample = Result<Duration, String>;
+
+async fn call(client: &reqwest::Client, args: &Args, body: &Value) -> Sample {
+    let mut request = client.post(&args.url).json(body);
+    if let Some(key) = &args.api_key {
+        request = request.header(args.auth_header.as_str(), key);
+    }
+    let started = Instant::now();
+    let response = request.send().await.map_err(|error| error.to_string())?;
+    let status = response.status();
+    let reply: Value = response.json().await.map_err(|error| error.to_string())?;
+    let elapsed = started.elapsed();
+    if !status.is_success() {
+        return Err(format!("HTTP {status}"));
+    }
+    match reply.get("error") {
+        Some(error) => Err(format!("JSON-RPC error {}", error["code"])),
+        None => Ok(elapsed),
+    }
+}
+
+/// The `quantile` (0.0..=1.0) of `sorted` latencies, nearest-rank.
+fn percentile(sorted: &[Duration], quantile: f64) -> Duration {
+    let rank = (quantile * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Arc::new(Args::parse());
+    let arguments: Value = serde_json::from_str(&args.arguments)?;
+    let client = reqwest::Client::new();
+    let next = Arc::new(AtomicUsize::new(0));
+    println!(
+        "{} x {} to {} at concurrency {}",
+        args.requests, args.tool, args.url, args.concurrency
+    );
+
+    let started = Instant::now();
+    let workers: Vec<_> = (0..args.concurrency.max(1))
+        .map(|_| {
+            let (args, client, next) = (Arc::clone(&args), client.clone(), Arc::clone(&next));
+            let arguments = arguments.clone();
+            tokio::spawn(async move {
+                let mut samples = Vec::new();
+                loop {
+                    let id = next.fetch_add(1, Ordering::Relaxed);
+                    if id >= args.requests {
+                        break samples;
+                    }
+                    let body = json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "method": "tools/call",
+                        "params": { "name": args.tool, "arguments": arguments },
+                    });
+                    samples.push(call(&client, &args, &body).await);
+                }
+            })
+        })
+        .collect();
+    let mut samples: Vec<Sample> = Vec::with_capacity(args.requests);
+    for worker in workers {
+        samples.extend(worker.await?);
+    }
+    let elapsed = started.elapsed();
+
+    let (mut latencies, mut errors) = (Vec::new(), Vec::new());
+    for sample in samples {
+        match sample {
+            Ok(latency) => latencies.push(latency),
+            Err(error) => errors.push(error),
+        }
+    }
+    println!(
+        "{} ok, {} failed in {:.2}s ({:.0} requests/s)",
+        latencies.len(),
+        errors.len(),
+        elapsed.as_secs_f64(),
+        (latencies.len() + errors.len()) as f64 / elapsed.as_secs_f64()
+    );
+    if let Some(first) = errors.first() {
+        println!("first failure: {first}");
+    }
+    if latencies.is_empty() {
+        return Ok(());
+    }
+    latencies.sort();
+    for (label, quantile) in [("p50", 0.5), ("p90", 0.9), ("p99", 0.99), ("p99.9", 0.999)] {
+        println!(
+            "{label:>6}: {:>8.2} ms",
+            millis(percentile(&latencies, quantile))
+        );
+    }
+    println!(
+        "{:>6}: {:>8.2} ms",
+        "max",
+        millis(*latencies.last().expect("non-empty"))
+    );
+    Ok(())
+}
@@ -0,0 +1,280 @@
+//! Remote client for the HTTP transport: opens an SSE session, performs the
+//! MCP handshake, lists the server's tools and resources, and calls a few
+//! tools within the session. Requests are POSTed to the endpoint the stream
+//! announces and their responses come back on the stream, along with
+//! whatever else the server pushes, which is printed as it arrives.
+//!
+//! Start a server with `cargo run -- serve --transport http`, then run
+//! `cargo run --example remote_client`.
+
+use clap::Parser;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::oneshot;
+
+#[derive(Parser)]
+#[command(about = "Talk to a running server over HTTP and SSE")]
+struct Args {
+    /// Base URL of the server.
+    #[arg(long, default_value = "http://127.0.0.1:8080")]
+    url: String,
+    /// API key, when the server requires one.
+    #[arg(long, env = "INFERENCO_MCP_API_KEY")]
+    api_key: Option<String>,
+    /// Header the API key is sent in.
+    #[arg(long, default_value = "x-api-key")]
+    auth_header: String,
+    /// Subscribe to every resource and print updates for this many seconds
+    /// before disconnecting.
+    #[arg(long, default_value_t = 0)]
+    watch_secs: u64,
+}
+
+/// Requests waiting for their response to arrive on the stream, by ID.
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Value>>>>;
+
+/// A JSON-RPC connection bound to one SSE session.
+struct Session {
+    client: reqwest::Client,
+    args: Args,
+    /// Where messages are POSTed, as announced by the `endpoint` event.
+    endpoint: String,
+    pending: Pending,
+    next_id: u64,
+}
+
+impl Session {
+    /// POST a message to the session's endpoint, returning the status and
+    /// the body (`Null` when there is none, as on success).
+    async fn post(&self, message: &Value) -> Result<(StatusCode, Value), reqwest::Error> {
+        let mut request = self
+            .client
+            .post(format!("{}{}", self.args.url, self.endpoint))
+            .json(message);
+        if let Some(key) = &self.args.api_key {
+            request = request.header(self.args.auth_header.as_str(), key);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        let body = response.bytes().await?;
+        Ok((status, serde_json::from_slice(&body).unwrap_or(Value::Null)))
+    }
+
+    /// Send a request and return its `result`, or the error it got.
+    async fn request(&mut self, method: &str, params: Value) -> Result<Value, String> {
+        self.next_id += 1;
+        let message =
+            json!({ "jsonrpc": "2.0", "id": self.next_id, "method": method, "params": params });
+        let (sender, response) = oneshot::channel();
+        self.pending
+            .lock()
+            .expect("pending lock poisoned")
+            .insert(self.next_id, sender);
+        let (status, reply) = self
+            .post(&message)
+            .await
+            .map_err(|error| error.to_string())?;
+        if status != StatusCode::ACCEPTED {
+            self.pending
+                .lock()
+                .expect("pending lock poisoned")
+                .remove(&self.next_id);
+            return Err(format!("{method}: HTTP {status} {reply}"));
+        }
+        let mut reply = response
+            .await
+            .map_err(|_| format!("{method}: stream closed before the response"))?;
+        match reply.get("error") {
+            Some(error) => Err(format!("{method}: {error}")),
+            None => Ok(reply["result"].take()),
+        }
+    }
+
+    async fn notify(&self, method: &str) -> Result<(), String> {
+        let message = json!({ "jsonrpc": "2.0", "method": method });
+        match self.post(&message).await {
+            Ok((StatusCode::ACCEPTED, _)) => Ok(()),
+            Ok((status, reply)) => Err(format!("{method}: HTTP {status} {reply}")),
+            Err(error) => Err(error.to_string()),
+        }
+    }
+
+    /// Call `name` and return the text of its result.
+    async fn call(&mut self, name: &str, arguments: Value) -> Result<String, String> {
+        let result = self
+            .request(
+                "tools/call",
+                json!({ "name": name, "arguments": arguments }),
+            )
+            .await?;
+        Ok(result["content"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|item| item["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
+
+/// Split complete `data:` events off the front of `buffer`, as pairs of
+/// event name and data.
+fn drain_events(buffer: &mut String) -> Vec<(String, String)> {
+    let mut events = Vec::new();
+    while let Some(end) = buffer.find("\n\n") {
+        let event: String = buffer.drain(..end + 2).collect();
+        if let Some(data) = event.lines().find_map(|line| line.strip_prefix("data: ")) {
+            let name = event
+                .lines()
+                .find_map(|line| line.strip_prefix("event: "))
+                .unwrap_or("message");
+            events.push((name.to_string(), data.to_string()));
+        }
+    }
+    events
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let client = reqwest::Client::new();
+
+    // The stream authenticates with `?token=`, since browsers' EventSource
+    // cannot set headers.
+    let mut stream = client.get(format!("{}/sse", args.url));
+    if let Some(key) = &args.api_key {
+        stream = stream.query(&[("token", key)]);
+    }
+    let mut stream = stream.send().await?.error_for_status()?;
+    let mut buffer = String::new();
+    let first = loop {
+        if let Some(event) = drain_events(&mut buffer).into_iter().next() {
+            break event;
+        }
+        let chunk = stream
+            .chunk()
+            .await?
+            .ok_or("stream closed before its first event")?;
+        buffer.push_str(&String::from_utf8_lossy(&chunk));
+    };
+    let endpoint = match first {
+        (name, endpoint) if name == "endpoint" => endpoint,
+        (_, data) => return Err(format!("stream refused: {data}").into()),
+    };
+    println!("session open, posting to {endpoint}");
+
+    // Hand responses to the requests waiting for them and print everything
+    // else the server pushes (resource updates, progress).
+    let pending = Pending::default();
+    let reader = {
+        let pending = pending.clone();
+        tokio::spawn(async move {
+            while let Ok(Some(chunk)) = stream.chunk().await {
+                buffer.push_str(&String::from_utf8_lossy(&chunk));
+                for (_, data) in drain_events(&mut buffer) {
+                    let Ok(message) = serde_json::from_str::<Value>(&data) else {
+                        continue;
+                    };
+                    let waiting = message["id"]
+                        .as_u64()
+                        .and_then(|id| pending.lock().expect("pending lock poisoned").remove(&id));
+                    match waiting {
+                        Some(sender) => {
+                            let _ = sender.send(message);
+                        }
+                        None => println!("  <- {message}"),
+                    }
+                }
+            }
+            // Requests still waiting see the stream close.
+            pending.lock().expect("pending lock poisoned").clear();
+        })
+    };
+
+    let watch = Duration::from_secs(args.watch_secs);
+    let mut session = Session {
+        client,
+        args,
+        endpoint,
+        pending,
+        next_id: 0,
+    };
+    let init = session
+        .request(
+            "initialize",
+            json!({
+                "protocolVersion": "2025-03-26",
+                "capabilities": {},
+                "clientInfo": { "name": "remote_client", "version": env!("CARGO_PKG_VERSION") }
+            }),
+        )
+        .await?;
+    println!(
+        "connected to {} {} (protocol {})",
+        init["serverInfo"]["name"], init["serverInfo"]["version"], init["protocolVersion"]
+    );
+    session.notify("notifications/initialized").await?;
+
+    let tools = session.request("tools/list", json!({})).await?;
+    println!("tools:");
+    for tool in tools["tools"].as_array().into_iter().flatten() {
+        println!(
+            " - {}: {}",
+            tool["name"].as_str().unwrap_or("?"),
+            tool["description"].as_str().unwrap_or("")
+        );
+    }
+
+    println!(
+        "echo -> {}",
+        session
+            .call("echo", json!({ "message": "hello over HTTP" }))
+            .await?
+    );
+    println!(
+        "roll_dice -> {}",
+        session
+            .call("roll_dice", json!({ "notation": "2d20" }))
+            .await?
+    );
+    // Without an API key, key-value state is private to this session.
+    session
+        .call("kv_set", json!({ "key": "greeting", "value": "hi" }))
+        .await?;
+    println!(
+        "kv_get -> {}",
+        session.call("kv_get", json!({ "key": "greeting" })).await?
+    );
+    match session.call("no_such_tool", json!({})).await {
+        Ok(text) => println!("no_such_tool -> {text}"),
+        Err(error) => println!("no_such_tool failed as expected: {error}"),
+    }
+
+    let resources = session.request("resources/list", json!({})).await?;
+    let uris: Vec<String> = resources["resources"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|resource| resource["uri"].as_str().map(str::to_string))
+        .collect();
+    println!("resources: {uris:?}");
+    if !watch.is_zero() {
+        for uri in &uris {
+            session
+                .request("resources/subscribe", json!({ "uri": uri }))
+                .await?;
+        }
+        println!("watching for {}s", watch.as_secs());
+        tokio::time::sleep(watch).await;
+    }
+
+    // Dropping the stream ends the session on the server.
+    reader.abort();
+    println!("done");
+    Ok(())
+}
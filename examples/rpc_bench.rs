@@ -0,0 +1,68 @@
+//! Benchmark for the `/rpc` request path: `rpc::process_rpc_body` answering
+//! a single `tools/call` and a mixed batch, from raw body bytes to the
+//! responses `handle_rpc` writes, with auth off.
+//!
+//! Run with `cargo run --release --example rpc_bench`.
+
+use axum::{body::Bytes, http::HeaderMap};
+use inferenco_mcp::{reload::ReloadableConfig, rpc, server::ToolService};
+use serde_json::{json, Value};
+use std::{hint::black_box, time::Instant};
+use tokio::runtime::Runtime;
+
+const ITERATIONS: u32 = 20_000;
+
+/// Answer `body` [`ITERATIONS`] times and print the rate of requests in it.
+fn measure(runtime: &Runtime, service: &ToolService, name: &str, body: &Value) {
+    let config = ReloadableConfig {
+        auth_enabled: false,
+        ..ReloadableConfig::default()
+    };
+    let headers = HeaderMap::new();
+    let requests = body.as_array().map_or(1, Vec::len);
+    let body = Bytes::from(serde_json::to_vec(body).expect("request serializes"));
+    let answer = || rpc::process_rpc_body(service, &config, &headers, body.clone());
+    runtime.block_on(async {
+        let reply = answer().await.expect("auth is off");
+        assert_eq!(reply.calls.len(), requests, "every request is answered");
+        let started = Instant::now();
+        for _ in 0..ITERATIONS {
+            black_box(answer().await.expect("auth is off"));
+        }
+        let elapsed = started.elapsed().as_secs_f64();
+        let per_second = f64::from(ITERATIONS) * requests as f64 / elapsed;
+        println!(
+            "{name:>7}: {:>6} bytes, {per_second:>10.0} requests/s",
+            body.len()
+        );
+    });
+}
+
+fn main() {
+    let runtime = Runtime::new().expect("tokio runtime");
+    let service = ToolService::new();
+    let call = |id: usize, name: &str, arguments: Value| {
+        json!({
+            "jsonrpc": "2.0", "id": id, "method": "tools/call",
+            "params": { "name": name, "arguments": arguments }
+        })
+    };
+
+    let message = "lorem ipsum ".repeat(400);
+    let single = call(1, "echo", json!({ "message": message }));
+    let batch: Vec<Value> = (0..20)
+        .map(|id| match id % 4 {
+            0 => call(id, "echo", json!({ "message": "hello" })),
+            1 => call(id, "calculate", json!({ "expression": "(2 + 3) * 7 / 4" })),
+            2 => call(
+                id,
+                "kv_set",
+                json!({ "key": "bench", "value": { "n": id } }),
+            ),
+            _ => json!({ "jsonrpc": "2.0", "id": id, "method": "tools/list" }),
+        })
+        .collect();
+    println!("{ITERATIONS} iterations each");
+    measure(&runtime, &service, "single", &single);
+    measure(&runtime, &service, "batch", &Value::from(batch));
+}
@@ -1,4 +1,4 @@
-use inferenco_mcp::server::{DiceArgs, EchoArgs, ReverseArgs, ToolService};
+use inferenco_mcp::server::{CurrentTimeArgs, DiceArgs, EchoArgs, ReverseArgs, ToolService};
 use rmcp::handler::server::wrapper::Parameters;
 
 #[tokio::main]
@@ -34,12 +34,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("reverse_text -> {:?}", reversed.content);
 
     // Get the current UTC time
-    let clock = service.current_time().await?;
+    let clock = service
+        .current_time(Parameters(CurrentTimeArgs::default()))
+        .await?;
     println!("current_time -> {:?}", clock.content);
 
-    // Roll a 20-sided die
+    // Roll three six-sided dice plus two
     let roll = service
-        .roll_dice(Parameters(DiceArgs { sides: 20 }))
+        .roll_dice(Parameters(DiceArgs {
+            notation: Some("3d6+2".into()),
+            sides: 6,
+            count: None,
+            seed: None,
+        }))
         .await?;
     println!("roll_dice -> {:?}", roll.content);
 
@@ -0,0 +1,23 @@
+//! Arbitrary bytes as a `/rpc` body: whatever arrives, dispatch must answer
+//! or reject it without panicking or hanging.
+
+#![no_main]
+
+use axum::{body::Bytes, http::HeaderMap};
+use inferenco_mcp::rpc::process_rpc_body;
+use inferenco_mcp_fuzz::{config, run, service};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let body = Bytes::copy_from_slice(data);
+    let config = config();
+    if let Ok(reply) = run(process_rpc_body(
+        service(),
+        &config,
+        &HeaderMap::new(),
+        body,
+    )) {
+        assert!(reply.batch || reply.calls.len() <= 1);
+        let _ = reply.to_value();
+    }
+});
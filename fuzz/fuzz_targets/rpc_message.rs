@@ -0,0 +1,85 @@
+//! Well-formed JSON shaped like JSON-RPC, so inputs get past parsing and
+//! into each method's handling of its params.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use axum::{body::Bytes, http::HeaderMap};
+use inferenco_mcp::rpc::process_rpc_body;
+use inferenco_mcp_fuzz::{config, run, service, Json};
+use libfuzzer_sys::fuzz_target;
+use serde_json::{json, Value};
+
+/// Methods `/rpc` handles, so most inputs reach a real handler.
+const METHODS: &[&str] = &[
+    "initialize",
+    "ping",
+    "tools/list",
+    "tools/call",
+    "resources/list",
+    "resources/templates/list",
+    "resources/read",
+    "resources/subscribe",
+    "resources/unsubscribe",
+    "notifications/initialized",
+];
+
+#[derive(Arbitrary, Debug)]
+enum Body {
+    Single(Message),
+    Batch(Vec<Message>),
+}
+
+#[derive(Arbitrary, Debug)]
+struct Message {
+    version: Option<String>,
+    id: Option<Json>,
+    method: Method,
+    params: Option<Json>,
+}
+
+#[derive(Arbitrary, Debug)]
+enum Method {
+    Known(u8),
+    Other(String),
+}
+
+impl From<Message> for Value {
+    fn from(message: Message) -> Self {
+        let method = match message.method {
+            Method::Known(index) => METHODS[usize::from(index) % METHODS.len()].to_string(),
+            Method::Other(method) => method,
+        };
+        let mut value = json!({
+            "jsonrpc": message.version.unwrap_or_else(|| "2.0".to_string()),
+            "method": method,
+        });
+        if let Some(id) = message.id {
+            value["id"] = id.into();
+        }
+        if let Some(params) = message.params {
+            value["params"] = params.into();
+        }
+        value
+    }
+}
+
+fuzz_target!(|body: Body| {
+    let (batch, value) = match body {
+        Body::Single(message) => (false, Value::from(message)),
+        Body::Batch(messages) => (true, messages.into_iter().map(Value::from).collect()),
+    };
+    let entries = value.as_array().map_or(1, Vec::len);
+    let body = Bytes::from(value.to_string());
+    let config = config();
+    if let Ok(reply) = run(process_rpc_body(
+        service(),
+        &config,
+        &HeaderMap::new(),
+        body,
+    )) {
+        assert_eq!(reply.batch, batch);
+        assert!(reply.calls.len() <= entries);
+        let _ = reply.to_value();
+    }
+});
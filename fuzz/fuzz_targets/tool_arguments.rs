@@ -0,0 +1,22 @@
+//! Arbitrary arguments for every tool: bad input must come back as an
+//! error result, never a panic or a hang.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use inferenco_mcp_fuzz::{run, service, Json};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+struct Call {
+    /// Picks one of the service's tools.
+    tool: u8,
+    arguments: Json,
+}
+
+fuzz_target!(|call: Call| {
+    let service = service();
+    let tools = service.available_tools();
+    let tool = &tools[usize::from(call.tool) % tools.len()];
+    let _ = run(service.call_tool(&tool.name, call.arguments.into()));
+});
@@ -0,0 +1,75 @@
+//! Shared setup for the fuzz targets: one service and runtime per process,
+//! a JSON value `arbitrary` can generate, and a deadline so that a call
+//! which hangs is reported like a panic.
+
+use arbitrary::Arbitrary;
+use inferenco_mcp::{reload::ReloadableConfig, server::ToolService};
+use std::{future::Future, sync::OnceLock, time::Duration};
+use tokio::runtime::Runtime;
+
+/// Longest a single input may take before it counts as a hang. Generous,
+/// since the slowest tools are bounded well below it.
+const DEADLINE: Duration = Duration::from_secs(5);
+
+/// The service every input is dispatched to. It is shared, like a running
+/// server's, so state left by one input is seen by the next.
+pub fn service() -> &'static ToolService {
+    static SERVICE: OnceLock<ToolService> = OnceLock::new();
+    SERVICE.get_or_init(ToolService::new)
+}
+
+/// Per-request settings for `process_rpc_body`: no auth, so every input
+/// reaches dispatch, and the default batch parallelism.
+pub fn config() -> ReloadableConfig {
+    ReloadableConfig {
+        auth_enabled: false,
+        ..ReloadableConfig::default()
+    }
+}
+
+/// Run `future` to completion, panicking if it outlives [`DEADLINE`].
+pub fn run<F: Future>(future: F) -> F::Output {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    let runtime = RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("tokio runtime")
+    });
+    runtime
+        .block_on(async { tokio::time::timeout(DEADLINE, future).await })
+        .expect("input did not finish within the deadline")
+}
+
+/// A JSON value. Object keys may repeat, as they can on the wire.
+#[derive(Arbitrary, Debug)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl From<Json> for serde_json::Value {
+    fn from(json: Json) -> Self {
+        match json {
+            Json::Null => serde_json::Value::Null,
+            Json::Bool(value) => value.into(),
+            Json::Integer(value) => value.into(),
+            // NaN and infinities have no JSON form.
+            Json::Float(value) => serde_json::Number::from_f64(value)
+                .map(serde_json::Value::Number)
+                .unwrap_or_default(),
+            Json::String(value) => value.into(),
+            Json::Array(items) => items.into_iter().map(serde_json::Value::from).collect(),
+            Json::Object(entries) => entries
+                .into_iter()
+                .map(|(key, value)| (key, value.into()))
+                .collect::<serde_json::Map<_, _>>()
+                .into(),
+        }
+    }
+}
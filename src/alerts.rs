@@ -0,0 +1,365 @@
+//! Operator alerts: a few rules evaluated as calls happen, delivered to a
+//! webhook (plain JSON or Slack-compatible) with per-rule cooldowns.
+
+use crate::{
+    config::{parse_env, parse_url},
+    http_client::HttpClient,
+};
+use chrono::Utc;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{HashMap, VecDeque},
+    env,
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Header carrying `sha256=<hex HMAC of the body>` when a secret is set.
+pub const SIGNATURE_HEADER: &str = "x-inferenco-signature";
+
+/// Body layout of alert notifications.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AlertFormat {
+    #[default]
+    Json,
+    /// `{"text": ...}`, accepted by Slack and most chat incoming webhooks.
+    Slack,
+}
+
+impl FromStr for AlertFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "slack" => Ok(Self::Slack),
+            other => Err(format!(
+                "unknown alert format '{other}' (expected 'json' or 'slack')"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AlertConfig {
+    pub webhook: String,
+    pub format: AlertFormat,
+    pub secret: Option<String>,
+    pub cooldown: Duration,
+    pub window: Duration,
+    /// Tool error ratio (0.0-1.0) that fires `error_rate`.
+    pub error_rate: Option<f64>,
+    /// Calls required in the window before the error rate is considered.
+    pub min_calls: u64,
+    /// Failures of one upstream within the window that fire
+    /// `upstream_failures`.
+    pub upstream_failures: Option<u64>,
+}
+
+impl AlertConfig {
+    /// Read `INFERENCO_MCP_ALERT_*`. Alerts are off unless
+    /// `INFERENCO_MCP_ALERT_WEBHOOK` is set.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let webhook = match env::var("INFERENCO_MCP_ALERT_WEBHOOK") {
+            Ok(url) if !url.trim().is_empty() => url.trim().to_string(),
+            _ => return Ok(None),
+        };
+        parse_url("INFERENCO_MCP_ALERT_WEBHOOK", &webhook)?;
+        let format = match env::var("INFERENCO_MCP_ALERT_FORMAT") {
+            Ok(value) => value.parse()?,
+            Err(_) => AlertFormat::default(),
+        };
+        let error_rate = parse_env::<f64>("INFERENCO_MCP_ALERT_ERROR_RATE", "a ratio")?
+            .or(Some(0.5))
+            .filter(|rate| *rate > 0.0);
+        if error_rate.is_some_and(|rate| rate > 1.0) {
+            return Err("INFERENCO_MCP_ALERT_ERROR_RATE must be between 0 and 1".into());
+        }
+        Ok(Some(Self {
+            webhook,
+            format,
+            secret: env::var("INFERENCO_MCP_ALERT_SECRET")
+                .ok()
+                .filter(|secret| !secret.is_empty()),
+            cooldown: Duration::from_secs(
+                parse_env("INFERENCO_MCP_ALERT_COOLDOWN_SECS", "a number of seconds")?
+                    .unwrap_or(900),
+            ),
+            window: Duration::from_secs(
+                parse_env("INFERENCO_MCP_ALERT_WINDOW_SECS", "a number of seconds")?.unwrap_or(300),
+            ),
+            error_rate,
+            min_calls: parse_env("INFERENCO_MCP_ALERT_MIN_CALLS", "a number of calls")?
+                .unwrap_or(20),
+            upstream_failures: parse_env(
+                "INFERENCO_MCP_ALERT_UPSTREAM_FAILURES",
+                "a number of failures",
+            )?
+            .or(Some(5))
+            .filter(|failures| *failures > 0),
+        }))
+    }
+}
+
+/// One notification.
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub rule: String,
+    pub message: String,
+    pub timestamp: String,
+    pub service: &'static str,
+}
+
+/// Per-second counts over a sliding window.
+#[derive(Default)]
+struct Window {
+    buckets: VecDeque<(u64, u64, u64)>,
+}
+
+impl Window {
+    fn record(&mut self, now: u64, window: u64, failed: bool) -> (u64, u64) {
+        match self.buckets.back_mut() {
+            Some((second, total, failures)) if *second == now => {
+                *total += 1;
+                *failures += u64::from(failed);
+            }
+            _ => self.buckets.push_back((now, 1, u64::from(failed))),
+        }
+        while self
+            .buckets
+            .front()
+            .is_some_and(|(second, _, _)| *second + window <= now)
+        {
+            self.buckets.pop_front();
+        }
+        self.buckets
+            .iter()
+            .fold((0, 0), |(total, failures), (_, calls, failed)| {
+                (total + calls, failures + failed)
+            })
+    }
+}
+
+#[derive(Default)]
+struct AlertState {
+    tool_calls: Window,
+    upstreams: HashMap<String, Window>,
+    last_fired: HashMap<String, Instant>,
+}
+
+/// Evaluates alert rules and delivers notifications.
+pub struct AlertEngine {
+    config: AlertConfig,
+    client: HttpClient,
+    started: Instant,
+    state: Mutex<AlertState>,
+}
+
+impl AlertEngine {
+    pub fn new(config: AlertConfig) -> Self {
+        Self {
+            config,
+            client: HttpClient::new(Some(Duration::from_secs(10))),
+            started: Instant::now(),
+            state: Mutex::new(AlertState::default()),
+        }
+    }
+
+    /// Record a finished tool call for the `error_rate` rule.
+    pub fn record_tool_call(&self, failed: bool) {
+        let Some(threshold) = self.config.error_rate else {
+            return;
+        };
+        let (calls, failures) = {
+            let mut state = self.state.lock().expect("alert lock poisoned");
+            state
+                .tool_calls
+                .record(self.now(), self.config.window.as_secs(), failed)
+        };
+        if calls >= self.config.min_calls && failures as f64 / calls as f64 >= threshold {
+            self.fire(
+                "error_rate",
+                format!(
+                    "tool error rate {:.0}% over the last {}s ({failures}/{calls} calls, threshold {:.0}%)",
+                    failures as f64 / calls as f64 * 100.0,
+                    self.config.window.as_secs(),
+                    threshold * 100.0
+                ),
+            );
+        }
+    }
+
+    /// Record the outcome of a call to `upstream` for the
+    /// `upstream_failures` rule.
+    pub fn record_upstream(&self, upstream: &str, failed: bool) {
+        let Some(threshold) = self.config.upstream_failures else {
+            return;
+        };
+        let (_, failures) = {
+            let mut state = self.state.lock().expect("alert lock poisoned");
+            state
+                .upstreams
+                .entry(upstream.to_string())
+                .or_default()
+                .record(self.now(), self.config.window.as_secs(), failed)
+        };
+        if failed && failures >= threshold {
+            self.fire(
+                &format!("upstream_failures:{upstream}"),
+                format!(
+                    "upstream '{upstream}' failed {failures} times in the last {}s",
+                    self.config.window.as_secs()
+                ),
+            );
+        }
+    }
+
+    /// Send an alert for `rule` unless it fired within the cooldown. Other
+    /// subsystems (circuit breakers, quotas) raise their alerts through here.
+    pub fn fire(&self, rule: &str, message: String) {
+        if !self.try_start_cooldown(rule) {
+            return;
+        }
+        let alert = Alert {
+            rule: rule.to_string(),
+            message,
+            timestamp: Utc::now().to_rfc3339(),
+            service: env!("CARGO_PKG_NAME"),
+        };
+        tracing::warn!(rule, message = %alert.message, "alert fired");
+
+        let body = self.render(&alert);
+        let mut request = self
+            .client
+            .post(&self.config.webhook)
+            .header(reqwest::header::CONTENT_TYPE, "application/json");
+        if let Some(secret) = &self.config.secret {
+            request = request.header(
+                SIGNATURE_HEADER,
+                format!("sha256={}", hex(&hmac_sha256(secret.as_bytes(), &body))),
+            );
+        }
+        let request = request.body(body);
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            let delivered = client
+                .send("alert_webhook", request)
+                .await
+                .and_then(|response| response.error_for_status());
+            if let Err(error) = delivered {
+                tracing::warn!(%error, "failed to deliver alert");
+            }
+        });
+    }
+
+    fn try_start_cooldown(&self, rule: &str) -> bool {
+        let mut state = self.state.lock().expect("alert lock poisoned");
+        let now = Instant::now();
+        if state
+            .last_fired
+            .get(rule)
+            .is_some_and(|fired| now.duration_since(*fired) < self.config.cooldown)
+        {
+            return false;
+        }
+        state.last_fired.insert(rule.to_string(), now);
+        true
+    }
+
+    fn render(&self, alert: &Alert) -> Vec<u8> {
+        let body = match self.config.format {
+            AlertFormat::Json => serde_json::to_value(alert).expect("alerts serialize"),
+            AlertFormat::Slack => serde_json::json!({
+                "text": format!(":rotating_light: *{}* `{}`: {}", alert.service, alert.rule, alert.message),
+            }),
+        };
+        serde_json::to_vec(&body).expect("alerts serialize")
+    }
+
+    fn now(&self) -> u64 {
+        self.started.elapsed().as_secs()
+    }
+}
+
+/// HMAC-SHA256 (RFC 2104).
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK: usize = 64;
+    let mut block = [0u8; BLOCK];
+    if key.len() > BLOCK {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.map(|key_byte| key_byte ^ byte);
+    let inner = Sha256::new()
+        .chain_update(pad(0x36))
+        .chain_update(message)
+        .finalize();
+    Sha256::new()
+        .chain_update(pad(0x5c))
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> AlertConfig {
+        AlertConfig {
+            webhook: "http://127.0.0.1:9/".to_string(),
+            format: AlertFormat::Slack,
+            secret: None,
+            cooldown: Duration::from_secs(60),
+            window: Duration::from_secs(60),
+            error_rate: Some(0.5),
+            min_calls: 4,
+            upstream_failures: Some(2),
+        }
+    }
+
+    #[test]
+    fn hmac_matches_rfc_4231() {
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn window_counts_calls_and_expires_old_buckets() {
+        let mut window = Window::default();
+        assert_eq!(window.record(0, 10, true), (1, 1));
+        assert_eq!(window.record(0, 10, false), (2, 1));
+        assert_eq!(window.record(5, 10, false), (3, 1));
+        assert_eq!(window.record(12, 10, false), (2, 0));
+    }
+
+    #[tokio::test]
+    async fn rules_fire_once_per_cooldown() {
+        let engine = AlertEngine::new(config());
+        for _ in 0..3 {
+            engine.record_tool_call(true);
+        }
+        assert!(engine.state.lock().unwrap().last_fired.is_empty());
+        engine.record_tool_call(false);
+        assert!(engine
+            .state
+            .lock()
+            .unwrap()
+            .last_fired
+            .contains_key("error_rate"));
+        assert!(!engine.try_start_cooldown("error_rate"));
+
+        engine.record_upstream("docs", true);
+        engine.record_upstream("docs", true);
+        assert!(!engine.try_start_cooldown("upstream_failures:docs"));
+    }
+}
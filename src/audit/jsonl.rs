@@ -0,0 +1,65 @@
+use super::{AuditQuery, AuditRecord, AuditSink};
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Audit records as JSON Lines, one record per line, appended to a file
+/// that is never rewritten.
+pub struct JsonlSink {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl JsonlSink {
+    /// Open `path` for appending, creating it if needed.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|error| format!("cannot open audit log {}: {error}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl AuditSink for JsonlSink {
+    fn append(&self, record: &AuditRecord) -> Result<(), String> {
+        let mut line = serde_json::to_vec(record).expect("audit records serialize");
+        line.push(b'\n');
+        // One write per line, so concurrent records never interleave.
+        self.file
+            .lock()
+            .expect("audit log lock poisoned")
+            .write_all(&line)
+            .map_err(|error| format!("cannot write {}: {error}", self.path.display()))
+    }
+
+    fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, String> {
+        let file = File::open(&self.path)
+            .map_err(|error| format!("cannot open audit log {}: {error}", self.path.display()))?;
+        let mut newest = VecDeque::with_capacity(query.limit);
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line =
+                line.map_err(|error| format!("cannot read {}: {error}", self.path.display()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: AuditRecord = serde_json::from_str(&line)
+                .map_err(|error| format!("{}:{}: {error}", self.path.display(), index + 1))?;
+            if query.limit > 0 && query.matches(&record) {
+                if newest.len() == query.limit {
+                    newest.pop_front();
+                }
+                newest.push_back(record);
+            }
+        }
+        Ok(newest.into_iter().rev().collect())
+    }
+}
@@ -0,0 +1,289 @@
+//! Append-only audit log of tool calls.
+//!
+//! With `INFERENCO_MCP_AUDIT_PATH` set, every `tools/call` on every
+//! transport is recorded, once it finishes, as an [`AuditRecord`]: when,
+//! in which session, by whom, which tool with which (redacted) arguments,
+//! for how long, and with what outcome. Records go to a JSON Lines file or,
+//! with `INFERENCO_MCP_AUDIT_BACKEND=sqlite` in a build with the `sqlite`
+//! feature, a SQLite table that refuses updates and deletes.
+//! `inferenco-mcp audit query` reads them back through [`AuditSink::query`].
+
+mod jsonl;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+pub use jsonl::JsonlSink;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteSink;
+
+use crate::auth::{AuthContext, AuthMethod};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    env,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Records [`AuditSink::query`] returns when no limit is given.
+pub const DEFAULT_QUERY_LIMIT: usize = 100;
+
+/// One finished tool call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// When the call finished, RFC 3339 in UTC with milliseconds.
+    pub at: String,
+    /// The MCP session the call was made in, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    /// [`AuthContext::subject`] of the caller; `None` without auth.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    /// `configured_key`, `issued_key`, `jwt`, or `client_certificate`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_method: Option<String>,
+    /// Name of the issued key the call was made with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_name: Option<String>,
+    pub tool: String,
+    /// The call's arguments with credentials redacted.
+    pub arguments: Value,
+    pub duration_ms: u64,
+    /// `success`, `tool_error`, or `error`.
+    pub outcome: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_class: Option<String>,
+}
+
+impl AuditRecord {
+    /// A record of a call to `tool` finishing now, made by `auth` in
+    /// `session`.
+    pub fn new(
+        tool: &str,
+        mut arguments: Value,
+        session: Option<&str>,
+        auth: Option<&AuthContext>,
+    ) -> Self {
+        crate::recording::redact(&mut arguments);
+        let (auth_method, key_name) = match auth.map(|auth| &auth.method) {
+            Some(AuthMethod::ConfiguredKey) => (Some("configured_key"), None),
+            Some(AuthMethod::IssuedKey { name, .. }) => (Some("issued_key"), Some(name.clone())),
+            Some(AuthMethod::Jwt) => (Some("jwt"), None),
+            Some(AuthMethod::ClientCertificate { .. }) => (Some("client_certificate"), None),
+            None => (None, None),
+        };
+        Self {
+            at: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            session: session.map(str::to_string),
+            subject: auth.map(|auth| auth.subject.clone()),
+            auth_method: auth_method.map(str::to_string),
+            key_name,
+            tool: tool.to_string(),
+            arguments,
+            duration_ms: 0,
+            outcome: String::new(),
+            error_class: None,
+        }
+    }
+
+    /// When the call finished; the epoch for a malformed `at`.
+    pub fn finished_at(&self) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(&self.at)
+            .map(|at| at.with_timezone(&Utc))
+            .unwrap_or_default()
+    }
+}
+
+/// Which records to read back; unset fields match everything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditQuery {
+    pub tool: Option<String>,
+    pub subject: Option<String>,
+    pub session: Option<String>,
+    pub outcome: Option<String>,
+    /// Only calls that finished at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Most records returned, newest first.
+    pub limit: usize,
+}
+
+impl Default for AuditQuery {
+    fn default() -> Self {
+        Self {
+            tool: None,
+            subject: None,
+            session: None,
+            outcome: None,
+            since: None,
+            limit: DEFAULT_QUERY_LIMIT,
+        }
+    }
+}
+
+impl AuditQuery {
+    pub fn matches(&self, record: &AuditRecord) -> bool {
+        let field = |wanted: &Option<String>, actual: Option<&str>| {
+            wanted
+                .as_deref()
+                .is_none_or(|wanted| Some(wanted) == actual)
+        };
+        field(&self.tool, Some(&record.tool))
+            && field(&self.subject, record.subject.as_deref())
+            && field(&self.session, record.session.as_deref())
+            && field(&self.outcome, Some(&record.outcome))
+            && self.since.is_none_or(|since| record.finished_at() >= since)
+    }
+}
+
+/// Where audit records are kept. Records are only ever appended.
+pub trait AuditSink: Send + Sync {
+    fn append(&self, record: &AuditRecord) -> Result<(), String>;
+
+    /// The newest records matching `query`, newest first.
+    fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, String>;
+}
+
+/// The configured audit sink.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditBackend {
+    Jsonl(PathBuf),
+    #[cfg(feature = "sqlite")]
+    Sqlite(PathBuf),
+}
+
+impl AuditBackend {
+    /// Read `INFERENCO_MCP_AUDIT_PATH` and `INFERENCO_MCP_AUDIT_BACKEND`;
+    /// `None` when no path is set.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let backend = env::var("INFERENCO_MCP_AUDIT_BACKEND")
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        let path = env::var("INFERENCO_MCP_AUDIT_PATH")
+            .ok()
+            .map(|path| path.trim().to_string())
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from);
+        let Some(path) = path else {
+            if !backend.is_empty() {
+                return Err(format!(
+                    "INFERENCO_MCP_AUDIT_BACKEND={backend} needs INFERENCO_MCP_AUDIT_PATH"
+                ));
+            }
+            return Ok(None);
+        };
+        match backend.as_str() {
+            "" | "jsonl" => Ok(Some(Self::Jsonl(path))),
+            #[cfg(feature = "sqlite")]
+            "sqlite" => Ok(Some(Self::Sqlite(path))),
+            #[cfg(not(feature = "sqlite"))]
+            "sqlite" => Err(
+                "INFERENCO_MCP_AUDIT_BACKEND=sqlite requires building with `--features sqlite`"
+                    .to_string(),
+            ),
+            other => Err(format!(
+                "INFERENCO_MCP_AUDIT_BACKEND must be jsonl or sqlite, got '{other}'"
+            )),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Jsonl(path) => path,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(path) => path,
+        }
+    }
+
+    /// Open the sink, creating the file if needed.
+    pub fn open(&self) -> Result<Arc<dyn AuditSink>, String> {
+        match self {
+            Self::Jsonl(path) => Ok(Arc::new(JsonlSink::open(path)?)),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(path) => Ok(Arc::new(SqliteSink::open(path)?)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exercise(sink: &dyn AuditSink) {
+        let auth = AuthContext::for_key(
+            "imcp_x",
+            AuthMethod::IssuedKey {
+                id: "k1".to_string(),
+                name: "ci".to_string(),
+            },
+            Vec::new(),
+        );
+        for (tool, session, outcome) in [
+            ("echo", Some("s1"), "success"),
+            ("fetch_url", Some("s1"), "error"),
+            ("echo", None, "success"),
+        ] {
+            let arguments = serde_json::json!({ "message": "hi", "api_key": "secret" });
+            let mut record = AuditRecord::new(tool, arguments, session, Some(&auth));
+            record.duration_ms = 3;
+            record.outcome = outcome.to_string();
+            sink.append(&record).expect("appends");
+        }
+
+        let all = sink.query(&AuditQuery::default()).expect("queries");
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].session, None, "newest first");
+        assert_eq!(all[0].subject.as_deref(), Some(auth.subject.as_str()));
+        assert_eq!(all[0].auth_method.as_deref(), Some("issued_key"));
+        assert_eq!(all[0].key_name.as_deref(), Some("ci"));
+        assert_eq!(all[0].arguments["api_key"], "REDACTED");
+
+        let echoes = AuditQuery {
+            tool: Some("echo".to_string()),
+            session: Some("s1".to_string()),
+            ..AuditQuery::default()
+        };
+        assert_eq!(sink.query(&echoes).expect("queries").len(), 1);
+        let errors = AuditQuery {
+            outcome: Some("error".to_string()),
+            ..AuditQuery::default()
+        };
+        assert_eq!(sink.query(&errors).expect("queries")[0].tool, "fetch_url");
+        let latest = AuditQuery {
+            limit: 1,
+            since: Some(Utc::now() - chrono::TimeDelta::minutes(1)),
+            ..AuditQuery::default()
+        };
+        assert_eq!(sink.query(&latest).expect("queries").len(), 1);
+        let future = AuditQuery {
+            since: Some(Utc::now() + chrono::TimeDelta::minutes(1)),
+            ..AuditQuery::default()
+        };
+        assert!(sink.query(&future).expect("queries").is_empty());
+    }
+
+    #[test]
+    fn jsonl_records_survive_reopening() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let path = dir.path().join("audit.jsonl");
+        exercise(&JsonlSink::open(&path).expect("open"));
+        let reopened = JsonlSink::open(&path).expect("reopen");
+        assert_eq!(reopened.query(&AuditQuery::default()).unwrap().len(), 3);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite_records_survive_reopening_and_cannot_change() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let path = dir.path().join("audit.db");
+        exercise(&SqliteSink::open(&path).expect("open"));
+        let reopened = SqliteSink::open(&path).expect("reopen");
+        assert_eq!(reopened.query(&AuditQuery::default()).unwrap().len(), 3);
+        let connection = rusqlite::Connection::open(&path).expect("open directly");
+        assert!(connection.execute("DELETE FROM audit", []).is_err());
+        assert!(connection
+            .execute("UPDATE audit SET tool = 'other'", [])
+            .is_err());
+    }
+}
@@ -0,0 +1,131 @@
+use super::{AuditQuery, AuditRecord, AuditSink};
+use rusqlite::{params, Connection, ToSql};
+use std::{path::Path, sync::Mutex, time::Duration};
+
+/// Audit records in a SQLite table. Triggers refuse updates and deletes,
+/// so records can only be added.
+pub struct SqliteSink {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteSink {
+    /// Open or create the database at `path`.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let fail = |error: rusqlite::Error| format!("cannot open {}: {error}", path.display());
+        let connection = Connection::open(path).map_err(fail)?;
+        connection
+            .busy_timeout(Duration::from_secs(5))
+            .map_err(fail)?;
+        connection
+            .execute_batch(
+                "PRAGMA journal_mode = WAL;
+                 CREATE TABLE IF NOT EXISTS audit (
+                     id INTEGER PRIMARY KEY AUTOINCREMENT,
+                     at TEXT NOT NULL,
+                     session TEXT,
+                     subject TEXT,
+                     auth_method TEXT,
+                     key_name TEXT,
+                     tool TEXT NOT NULL,
+                     arguments TEXT NOT NULL,
+                     duration_ms INTEGER NOT NULL,
+                     outcome TEXT NOT NULL,
+                     error_class TEXT
+                 );
+                 CREATE INDEX IF NOT EXISTS audit_by_tool ON audit (tool, id);
+                 CREATE INDEX IF NOT EXISTS audit_by_subject ON audit (subject, id);
+                 CREATE TRIGGER IF NOT EXISTS audit_no_update BEFORE UPDATE ON audit
+                 BEGIN SELECT RAISE(ABORT, 'the audit log is append-only'); END;
+                 CREATE TRIGGER IF NOT EXISTS audit_no_delete BEFORE DELETE ON audit
+                 BEGIN SELECT RAISE(ABORT, 'the audit log is append-only'); END;",
+            )
+            .map_err(fail)?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+}
+
+impl AuditSink for SqliteSink {
+    fn append(&self, record: &AuditRecord) -> Result<(), String> {
+        let connection = self.connection.lock().expect("audit lock poisoned");
+        connection
+            .execute(
+                "INSERT INTO audit (at, session, subject, auth_method, key_name, tool,
+                                    arguments, duration_ms, outcome, error_class)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    record.at,
+                    record.session,
+                    record.subject,
+                    record.auth_method,
+                    record.key_name,
+                    record.tool,
+                    record.arguments.to_string(),
+                    record.duration_ms as i64,
+                    record.outcome,
+                    record.error_class,
+                ],
+            )
+            .map(drop)
+            .map_err(|error| format!("cannot append audit record: {error}"))
+    }
+
+    fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, String> {
+        let fail = |error: rusqlite::Error| format!("cannot query the audit log: {error}");
+        // `at` is always UTC with milliseconds, so it sorts as text.
+        let since = query
+            .since
+            .map(|since| since.to_rfc3339_opts(chrono::SecondsFormat::Millis, true));
+        let limit = query.limit as i64;
+        let mut conditions = Vec::new();
+        let mut values: Vec<&dyn ToSql> = Vec::new();
+        for (column, value) in [
+            ("tool", &query.tool),
+            ("subject", &query.subject),
+            ("session", &query.session),
+            ("outcome", &query.outcome),
+        ] {
+            if let Some(value) = value {
+                conditions.push(format!("{column} = ?"));
+                values.push(value);
+            }
+        }
+        if let Some(since) = &since {
+            conditions.push("at >= ?".to_string());
+            values.push(since);
+        }
+        values.push(&limit);
+        let filter = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        let sql = format!(
+            "SELECT at, session, subject, auth_method, key_name, tool, arguments,
+                    duration_ms, outcome, error_class
+             FROM audit {filter} ORDER BY id DESC LIMIT ?"
+        );
+
+        let connection = self.connection.lock().expect("audit lock poisoned");
+        let mut statement = connection.prepare(&sql).map_err(fail)?;
+        let rows = statement
+            .query_map(values.as_slice(), |row| {
+                let arguments: String = row.get(6)?;
+                Ok(AuditRecord {
+                    at: row.get(0)?,
+                    session: row.get(1)?,
+                    subject: row.get(2)?,
+                    auth_method: row.get(3)?,
+                    key_name: row.get(4)?,
+                    tool: row.get(5)?,
+                    arguments: serde_json::from_str(&arguments).unwrap_or_default(),
+                    duration_ms: row.get::<_, i64>(7)? as u64,
+                    outcome: row.get(8)?,
+                    error_class: row.get(9)?,
+                })
+            })
+            .map_err(fail)?;
+        rows.collect::<Result<_, _>>().map_err(fail)
+    }
+}
@@ -0,0 +1,444 @@
+//! API keys kept by hash in a JSON file, `INFERENCO_MCP_KEYS_FILE`.
+//!
+//! Only the SHA-256 of a key is stored, so the file can be read and backed
+//! up without exposing keys; the key itself is shown once, when it is
+//! created. Each key names the tools it may call (`*` for every tool) and
+//! may expire. Revoked keys stay in the file, marked, so their IDs keep
+//! their meaning in logs.
+//!
+//! ```json
+//! { "keys": [{ "id": "3f9a2c1b", "name": "ci", "sha256": "…", "scopes": ["roll_dice"],
+//!              "created_at": 1767225600, "expires_at": 1798761600 }] }
+//! ```
+//!
+//! The server re-reads the file when it changes, so keys created or revoked
+//! with the `keys` subcommand apply without a restart.
+
+use super::{AuthContext, AuthMethod};
+use crate::server::ToolService;
+use base64::Engine as _;
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use rand::Rng as _;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    env, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+/// Every issued key starts with this, so leaked keys are easy to scan for.
+pub const KEY_PREFIX: &str = "imcp_";
+/// The scope that allows every tool.
+pub const ALL_TOOLS: &str = "*";
+/// How often the server checks the file for changes made elsewhere.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// One key as kept in the file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredKey {
+    pub id: String,
+    pub name: String,
+    /// Hex SHA-256 of the key.
+    pub sha256: String,
+    /// Tools the key may call; `*` allows every tool.
+    pub scopes: Vec<String>,
+    /// Unix seconds, as are the other times.
+    pub created_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<i64>,
+}
+
+impl StoredKey {
+    /// `active`, `expired`, or `revoked` at `now`.
+    pub fn status(&self, now: i64) -> &'static str {
+        if self.revoked_at.is_some() {
+            "revoked"
+        } else if self.expires_at.is_some_and(|expires_at| expires_at <= now) {
+            "expired"
+        } else {
+            "active"
+        }
+    }
+
+    /// The key without its hash, with RFC 3339 times, for listings.
+    pub fn summary(&self, now: i64) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "name": self.name,
+            "scopes": self.scopes,
+            "status": self.status(now),
+            "created_at": rfc3339(self.created_at),
+            "expires_at": self.expires_at.map(rfc3339),
+            "revoked_at": self.revoked_at.map(rfc3339),
+        })
+    }
+}
+
+/// A key to create.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct NewKey {
+    pub name: String,
+    /// Tools the key may call; every tool when empty.
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// When the key stops working, as accepted by [`parse_expiry`].
+    #[serde(default)]
+    pub expires_at: Option<String>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct KeyFile {
+    #[serde(default)]
+    keys: Vec<StoredKey>,
+}
+
+struct Loaded {
+    keys: Vec<StoredKey>,
+    /// Modification time of the file as last read or written.
+    modified: Option<SystemTime>,
+}
+
+/// The keys in one file. Changes are written straight back.
+pub struct KeyStore {
+    path: PathBuf,
+    loaded: Mutex<Loaded>,
+}
+
+impl KeyStore {
+    /// The store at `path`; empty until a key is created when the file does
+    /// not exist yet.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        let loaded = read(&path)?;
+        Ok(Self {
+            path,
+            loaded: Mutex::new(loaded),
+        })
+    }
+
+    /// The store named by `INFERENCO_MCP_KEYS_FILE`, if set.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Some(path) = env::var_os("INFERENCO_MCP_KEYS_FILE").filter(|path| !path.is_empty())
+        else {
+            return Ok(None);
+        };
+        Self::open(PathBuf::from(path))
+            .map(Some)
+            .map_err(|error| format!("INFERENCO_MCP_KEYS_FILE: {error}"))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Who presents `key`, if it is an active key.
+    pub fn verify(&self, key: &str, now: i64) -> Option<AuthContext> {
+        let hash = digest(key);
+        let loaded = self.loaded.lock().expect("key store lock poisoned");
+        let stored = loaded.keys.iter().find(|stored| stored.sha256 == hash)?;
+        let method = AuthMethod::IssuedKey {
+            id: stored.id.clone(),
+            name: stored.name.clone(),
+        };
+        (stored.status(now) == "active")
+            .then(|| AuthContext::for_key(key, method, stored.scopes.clone()))
+    }
+
+    /// Every key, revoked and expired ones included, oldest first.
+    pub fn list(&self) -> Vec<StoredKey> {
+        self.loaded
+            .lock()
+            .expect("key store lock poisoned")
+            .keys
+            .clone()
+    }
+
+    /// Issue a key, returning it as stored and the key itself, which is not
+    /// kept anywhere.
+    pub fn create(&self, new: NewKey, now: i64) -> Result<(StoredKey, String), String> {
+        let name = new.name.trim();
+        if name.is_empty() {
+            return Err("a key needs a name".to_string());
+        }
+        let scopes = if new.scopes.is_empty() {
+            vec![ALL_TOOLS.to_string()]
+        } else {
+            new.scopes
+        };
+        let unknown = unknown_scopes(&scopes);
+        if !unknown.is_empty() {
+            return Err(format!("unknown tools in scopes: {}", unknown.join(", ")));
+        }
+        let expires_at = new
+            .expires_at
+            .as_deref()
+            .map(|expiry| parse_expiry(expiry, now))
+            .transpose()?;
+
+        self.update(|keys| {
+            let mut id = random_id();
+            while keys.iter().any(|key| key.id == id) {
+                id = random_id();
+            }
+            let secret = format!(
+                "{KEY_PREFIX}{}",
+                base64::engine::general_purpose::URL_SAFE_NO_PAD
+                    .encode(rand::thread_rng().gen::<[u8; 32]>())
+            );
+            let stored = StoredKey {
+                id,
+                name: name.to_string(),
+                sha256: digest(&secret),
+                scopes,
+                created_at: now,
+                expires_at,
+                revoked_at: None,
+            };
+            keys.push(stored.clone());
+            Ok((stored, secret))
+        })
+    }
+
+    /// Revoke key `id`. `None` when there is no such key, otherwise whether
+    /// it was still unrevoked.
+    pub fn revoke(&self, id: &str, now: i64) -> Result<Option<bool>, String> {
+        self.update(|keys| {
+            let Some(key) = keys.iter_mut().find(|key| key.id == id) else {
+                return Ok(None);
+            };
+            if key.revoked_at.is_some() {
+                return Ok(Some(false));
+            }
+            key.revoked_at = Some(now);
+            Ok(Some(true))
+        })
+    }
+
+    /// Re-read the file if it changed since it was last read or written.
+    pub fn refresh(&self) -> Result<bool, String> {
+        let modified = modified(&self.path);
+        let mut loaded = self.loaded.lock().expect("key store lock poisoned");
+        if modified == loaded.modified {
+            return Ok(false);
+        }
+        *loaded = read(&self.path)?;
+        Ok(true)
+    }
+
+    /// Pick up changes made to the file by other processes, such as the
+    /// `keys` subcommand, for as long as the store lives.
+    pub fn spawn_refresh(self: &Arc<Self>) {
+        let store = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut poll = tokio::time::interval(REFRESH_INTERVAL);
+            loop {
+                poll.tick().await;
+                let Some(store) = store.upgrade() else {
+                    return;
+                };
+                match store.refresh() {
+                    Ok(true) => tracing::info!(path = %store.path.display(), "API keys reloaded"),
+                    Ok(false) => {}
+                    Err(error) => tracing::warn!(%error, "API keys not reloaded"),
+                }
+            }
+        });
+    }
+
+    /// Apply `change` to the keys as currently on disk and write them back.
+    fn update<T>(
+        &self,
+        change: impl FnOnce(&mut Vec<StoredKey>) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut loaded = self.loaded.lock().expect("key store lock poisoned");
+        // Start from the file, so changes made elsewhere are kept.
+        *loaded = read(&self.path)?;
+        let mut keys = loaded.keys.clone();
+        let result = change(&mut keys)?;
+        write(&self.path, &keys)
+            .map_err(|error| format!("cannot write {}: {error}", self.path.display()))?;
+        *loaded = Loaded {
+            keys,
+            modified: modified(&self.path),
+        };
+        Ok(result)
+    }
+}
+
+/// Scopes that name no tool this server has.
+pub fn unknown_scopes(scopes: &[String]) -> Vec<&str> {
+    let router = ToolService::tool_router();
+    scopes
+        .iter()
+        .map(String::as_str)
+        .filter(|scope| *scope != ALL_TOOLS && !router.has_route(scope))
+        .collect()
+}
+
+/// An expiry as unix seconds: an RFC 3339 time, a date (`2027-01-01`,
+/// midnight UTC), or a number of days from `now` (`90d`).
+pub fn parse_expiry(value: &str, now: i64) -> Result<i64, String> {
+    let value = value.trim();
+    let expires_at = if let Some(days) = value.strip_suffix('d') {
+        let days: i64 = days
+            .parse()
+            .map_err(|_| format!("'{value}' is not a number of days"))?;
+        now + days * 24 * 60 * 60
+    } else if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        time.timestamp()
+    } else {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map_err(|_| {
+                format!("'{value}' is not a date, an RFC 3339 time, or a number of days like 90d")
+            })?
+            .and_hms_opt(0, 0, 0)
+            .expect("midnight exists")
+            .and_utc()
+            .timestamp()
+    };
+    if expires_at <= now {
+        return Err(format!("expiry {value} is in the past"));
+    }
+    Ok(expires_at)
+}
+
+/// Unix seconds as RFC 3339.
+pub fn rfc3339(secs: i64) -> String {
+    DateTime::<Utc>::from_timestamp(secs, 0)
+        .unwrap_or_default()
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn digest(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+fn random_id() -> String {
+    hex::encode(rand::thread_rng().gen::<[u8; 4]>())
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).ok()?.modified().ok()
+}
+
+fn read(path: &Path) -> Result<Loaded, String> {
+    let modified = modified(path);
+    let file: KeyFile = match std::fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map_err(|error| format!("{} is not a key file: {error}", path.display()))?,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => KeyFile::default(),
+        Err(error) => return Err(format!("cannot read {}: {error}", path.display())),
+    };
+    Ok(Loaded {
+        keys: file.keys,
+        modified,
+    })
+}
+
+fn write(path: &Path, keys: &[StoredKey]) -> io::Result<()> {
+    let file = KeyFile {
+        keys: keys.to_vec(),
+    };
+    let json = serde_json::to_vec_pretty(&file)?;
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+    // Write then rename so a crash never leaves a truncated file behind.
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_767_225_600; // 2026-01-01T00:00:00Z
+
+    fn new_key(name: &str, scopes: &[&str], expires_at: Option<&str>) -> NewKey {
+        NewKey {
+            name: name.to_string(),
+            scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+            expires_at: expires_at.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn keys_are_stored_hashed_and_verified_until_revoked_or_expired() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let path = dir.path().join("keys.json");
+        let store = KeyStore::open(&path).expect("missing files are empty");
+
+        let (ci, secret) = store
+            .create(new_key("ci", &["roll_dice"], Some("30d")), NOW)
+            .expect("valid key");
+        assert!(secret.starts_with(KEY_PREFIX));
+        let file = std::fs::read_to_string(&path).unwrap();
+        assert!(!file.contains(&secret));
+        assert!(file.contains(&ci.sha256));
+
+        let key = store.verify(&secret, NOW).expect("active key");
+        assert_eq!(
+            key.method,
+            AuthMethod::IssuedKey {
+                id: ci.id.clone(),
+                name: "ci".to_string()
+            }
+        );
+        assert!(key.allows("roll_dice"));
+        assert!(!key.allows("echo"));
+        assert!(store.verify("imcp_wrong", NOW).is_none());
+        assert!(store.verify(&secret, NOW + 31 * 24 * 60 * 60).is_none());
+
+        // Another process, like the CLI, sees the key and revokes it.
+        let other = KeyStore::open(&path).expect("readable file");
+        assert_eq!(other.revoke(&ci.id, NOW), Ok(Some(true)));
+        assert_eq!(other.revoke(&ci.id, NOW), Ok(Some(false)));
+        assert_eq!(other.revoke("nope", NOW), Ok(None));
+        assert_eq!(store.refresh(), Ok(true));
+        assert!(store.verify(&secret, NOW).is_none());
+        assert_eq!(store.list()[0].status(NOW), "revoked");
+
+        let (_, all) = store
+            .create(new_key("admin", &[], None), NOW)
+            .expect("valid key");
+        assert!(store.verify(&all, NOW).expect("active key").allows("echo"));
+        assert_eq!(store.list().len(), 2);
+    }
+
+    #[test]
+    fn bad_keys_are_refused() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let store = KeyStore::open(dir.path().join("keys.json")).expect("empty store");
+        assert!(store.create(new_key(" ", &[], None), NOW).is_err());
+        assert_eq!(
+            store.create(new_key("ci", &["roll_dcie"], None), NOW),
+            Err("unknown tools in scopes: roll_dcie".to_string())
+        );
+        assert!(store
+            .create(new_key("ci", &[], Some("2020-01-01")), NOW)
+            .is_err());
+
+        std::fs::write(dir.path().join("bad.json"), "[").unwrap();
+        assert!(KeyStore::open(dir.path().join("bad.json")).is_err());
+    }
+
+    #[test]
+    fn expiries_take_dates_times_and_days() {
+        assert_eq!(parse_expiry("2027-01-01", NOW), Ok(1_798_761_600));
+        assert_eq!(
+            parse_expiry("2027-01-01T12:00:00+02:00", NOW),
+            Ok(1_798_761_600 + 10 * 60 * 60)
+        );
+        assert_eq!(parse_expiry("1d", NOW), Ok(NOW + 86_400));
+        assert!(parse_expiry("soon", NOW).is_err());
+        assert_eq!(rfc3339(NOW), "2026-01-01T00:00:00Z");
+    }
+}
@@ -0,0 +1,202 @@
+//! Who a request comes from and what it may do.
+//!
+//! A request authenticates with an API key, configured in
+//! `INFERENCO_MCP_API_KEYS` or issued into the [`keys`] store, with a
+//! bearer token checked by [`jwt`], which [`oauth`] lets clients discover
+//! how to obtain, or with the TLS client certificate its connection
+//! presented, found with [`certificate`]. Either way it gets an
+//! [`AuthContext`], put in scope for the request with [`with_context`] so
+//! tools and the code around them can read it with
+//! [`AuthContext::current`].
+//! [`check_tool`] refuses the tools an issued key's scopes leave out, and
+//! those the [`policy`] keeps from the caller.
+
+pub mod jwt;
+pub mod keys;
+pub mod oauth;
+pub mod policy;
+
+use policy::Policy;
+use rmcp::{model::ErrorCode, ErrorData as McpError};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::{future::Future, sync::Arc};
+
+/// JSON-RPC error code returned by `tools/call` for a tool the caller may
+/// not use.
+pub const FORBIDDEN_ERROR_CODE: ErrorCode = ErrorCode(-32002);
+
+/// JSON-RPC error code of requests without valid credentials, where there is
+/// no HTTP status to carry a `401`, as on `/ws`.
+pub const UNAUTHORIZED_ERROR_CODE: ErrorCode = ErrorCode(-32005);
+
+tokio::task_local! {
+    static CURRENT: Option<AuthContext>;
+    static CERTIFICATE: Option<AuthContext>;
+}
+
+/// Run `future` with `auth` as the [`AuthContext::current`] of its tool
+/// calls.
+pub async fn with_context<F: Future>(auth: Option<AuthContext>, future: F) -> F::Output {
+    CURRENT.scope(auth, future).await
+}
+
+/// Run `future`, a request over a connection whose verified client
+/// certificate identifies it as `certificate`.
+pub async fn with_certificate<F: Future>(certificate: Option<AuthContext>, future: F) -> F::Output {
+    CERTIFICATE.scope(certificate, future).await
+}
+
+/// Who the current request's client certificate says it comes from, if its
+/// connection presented one.
+pub fn certificate() -> Option<AuthContext> {
+    CERTIFICATE.try_with(Clone::clone).ok().flatten()
+}
+
+/// Who a request authenticated as.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthContext {
+    /// The caller's stable name: the fingerprint of its API key, the
+    /// token's `sub`, or its certificate's subject.
+    pub subject: String,
+    pub method: AuthMethod,
+    /// The tools an issued key may call, or the token's OAuth scopes.
+    pub scopes: Arc<[String]>,
+    /// The token's claims; empty for API keys.
+    pub claims: Arc<Map<String, Value>>,
+}
+
+/// How a request authenticated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthMethod {
+    /// A key from `INFERENCO_MCP_API_KEYS`.
+    ConfiguredKey,
+    /// A key from the key store.
+    IssuedKey { id: String, name: String },
+    /// A bearer JWT.
+    Jwt,
+    /// A TLS client certificate signed by `INFERENCO_MCP_TLS_CLIENT_CA`,
+    /// with its subject alternative names, as in `DNS:agent.internal`.
+    ClientCertificate { sans: Vec<String> },
+}
+
+impl AuthContext {
+    /// The context of the current request, if it authenticated.
+    pub fn current() -> Option<Self> {
+        CURRENT.try_with(Clone::clone).ok().flatten()
+    }
+
+    /// A request that presented API `key`, limited to `scopes` if it is an
+    /// issued key.
+    pub fn for_key(key: &str, method: AuthMethod, scopes: Vec<String>) -> Self {
+        Self {
+            subject: fingerprint(key),
+            method,
+            scopes: scopes.into(),
+            claims: Arc::default(),
+        }
+    }
+
+    /// A client whose certificate names `subject`, such as
+    /// `CN=build-agent, O=Inferenco`, and `sans`.
+    pub fn for_certificate(subject: String, sans: Vec<String>) -> Self {
+        Self {
+            subject,
+            method: AuthMethod::ClientCertificate { sans },
+            scopes: Arc::default(),
+            claims: Arc::default(),
+        }
+    }
+
+    /// Whether the caller may call `tool`. Only issued keys are limited.
+    pub fn allows(&self, tool: &str) -> bool {
+        match self.method {
+            AuthMethod::IssuedKey { .. } => self
+                .scopes
+                .iter()
+                .any(|scope| scope == keys::ALL_TOOLS || scope == tool),
+            AuthMethod::ConfiguredKey | AuthMethod::Jwt | AuthMethod::ClientCertificate { .. } => {
+                true
+            }
+        }
+    }
+
+    /// The key-value namespace private to the caller.
+    pub fn namespace(&self) -> String {
+        match self.method {
+            AuthMethod::Jwt => format!("user:{}", self.subject),
+            AuthMethod::ClientCertificate { .. } => format!("cert:{}", self.subject),
+            AuthMethod::ConfiguredKey | AuthMethod::IssuedKey { .. } => {
+                format!("key:{}", self.subject)
+            }
+        }
+    }
+}
+
+/// Short, non-reversible fingerprint of an API key, safe to write to logs.
+pub fn fingerprint(key: &str) -> String {
+    Sha256::digest(key.as_bytes())[..6]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Refuse `tool` when the current request's key is not scoped for it, or
+/// `policy` does not let its caller use it.
+pub fn check_tool(tool: &str, policy: Option<&Policy>) -> Result<(), McpError> {
+    let Some(auth) = AuthContext::current() else {
+        return Ok(());
+    };
+    if let (false, AuthMethod::IssuedKey { id, name }) = (auth.allows(tool), &auth.method) {
+        return Err(McpError::new(
+            FORBIDDEN_ERROR_CODE,
+            format!("Forbidden: API key '{name}' may not call {tool}"),
+            Some(serde_json::json!({ "tool": tool, "key_id": id })),
+        ));
+    }
+    if policy.is_some_and(|policy| !policy.allows(&auth, tool)) {
+        return Err(McpError::new(
+            FORBIDDEN_ERROR_CODE,
+            format!("Forbidden: {} may not call {tool}", auth.subject),
+            Some(serde_json::json!({ "tool": tool, "subject": auth.subject })),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn contexts_are_scoped_to_their_requests() {
+        let issued = AuthMethod::IssuedKey {
+            id: "1".to_string(),
+            name: "ci".to_string(),
+        };
+        let auth = AuthContext::for_key("imcp_x", issued, vec!["echo".to_string()]);
+        assert!(AuthContext::current().is_none());
+        let current = with_context(Some(auth.clone()), async { AuthContext::current() }).await;
+        assert_eq!(current, Some(auth.clone()));
+
+        let refused = with_context(Some(auth), async { check_tool("roll_dice", None) }).await;
+        assert_eq!(refused.unwrap_err().code, FORBIDDEN_ERROR_CODE);
+        let policy = Policy::parse(r#"{ "tools": { "roll_dice": ["user:alice"] } }"#).unwrap();
+        let jwt = AuthContext {
+            subject: "bob".to_string(),
+            method: AuthMethod::Jwt,
+            scopes: Arc::default(),
+            claims: Arc::default(),
+        };
+        let refused =
+            with_context(Some(jwt), async { check_tool("roll_dice", Some(&policy)) }).await;
+        assert_eq!(refused.unwrap_err().code, FORBIDDEN_ERROR_CODE);
+        assert!(check_tool("roll_dice", Some(&policy)).is_ok(), "no caller");
+        let configured = AuthContext::for_key("imcp_x", AuthMethod::ConfiguredKey, Vec::new());
+        assert!(configured.allows("roll_dice"));
+        assert_eq!(
+            configured.namespace(),
+            format!("key:{}", fingerprint("imcp_x"))
+        );
+    }
+}
@@ -0,0 +1,196 @@
+//! The resource-server side of MCP's OAuth authorization.
+//!
+//! With `INFERENCO_MCP_OAUTH_RESOURCE` set to the server's canonical URL,
+//! `/.well-known/oauth-protected-resource` serves the RFC 9728 metadata
+//! that tells clients which authorization servers issue its tokens, and
+//! refused requests carry a [`challenge`] pointing at it. Access tokens are
+//! bearer JWTs checked by [`super::jwt`]; their audience defaults to the
+//! resource (RFC 8707), and they must carry every scope in
+//! `INFERENCO_MCP_OAUTH_SCOPES`.
+
+use super::{jwt::JwtSettings, AuthContext};
+use crate::config::parse_url;
+use reqwest::Url;
+use serde_json::Value;
+use std::env;
+
+/// Where the metadata is served; a resource with a path also has it under
+/// that path appended, as RFC 9728 asks clients to look there first.
+pub const METADATA_PATH: &str = "/.well-known/oauth-protected-resource";
+
+/// What this server tells OAuth clients about itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OAuthSettings {
+    /// The canonical URL of this MCP server, which tokens must be issued
+    /// for.
+    pub resource: String,
+    pub authorization_servers: Vec<String>,
+    /// Scopes every token must carry.
+    pub scopes: Vec<String>,
+}
+
+/// Why a bearer token was refused, as RFC 6750 names it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BearerError {
+    /// Expired, badly signed, or for another issuer or audience.
+    InvalidToken,
+    /// Valid, but without every required scope.
+    InsufficientScope,
+}
+
+impl OAuthSettings {
+    /// Read the `INFERENCO_MCP_OAUTH_*` variables; `None` when no resource
+    /// is set. Tokens are checked with `jwt`, which must be configured.
+    pub fn from_env(jwt: Option<&JwtSettings>) -> Result<Option<Self>, String> {
+        let list = |name: &str| -> Vec<String> {
+            env::var(name)
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+        let Some(resource) = env::var("INFERENCO_MCP_OAUTH_RESOURCE")
+            .ok()
+            .map(|resource| resource.trim().to_string())
+            .filter(|resource| !resource.is_empty())
+        else {
+            return Ok(None);
+        };
+        let resource = parse_url("INFERENCO_MCP_OAUTH_RESOURCE", &resource)?;
+        let Some(jwt) = jwt else {
+            return Err(
+                "INFERENCO_MCP_OAUTH_RESOURCE needs INFERENCO_MCP_JWT_JWKS_URL and INFERENCO_MCP_JWT_ISSUER"
+                    .to_string(),
+            );
+        };
+        let mut authorization_servers = list("INFERENCO_MCP_OAUTH_AUTHORIZATION_SERVERS");
+        for server in &authorization_servers {
+            parse_url("INFERENCO_MCP_OAUTH_AUTHORIZATION_SERVERS", server)?;
+        }
+        if authorization_servers.is_empty() {
+            authorization_servers.push(jwt.issuer.clone());
+        }
+        Ok(Some(Self {
+            resource,
+            authorization_servers,
+            scopes: list("INFERENCO_MCP_OAUTH_SCOPES"),
+        }))
+    }
+
+    /// The protected resource metadata document.
+    pub fn metadata(&self) -> Value {
+        serde_json::json!({
+            "resource": self.resource,
+            "authorization_servers": self.authorization_servers,
+            "scopes_supported": self.scopes,
+            "bearer_methods_supported": ["header"],
+            "resource_name": "inferenco-mcp",
+        })
+    }
+
+    /// Where clients find [`Self::metadata`].
+    pub fn metadata_url(&self) -> String {
+        let url = Url::parse(&self.resource).expect("validated in settings");
+        let path = url.path().trim_end_matches('/');
+        format!(
+            "{}{METADATA_PATH}{path}",
+            url.origin().ascii_serialization()
+        )
+    }
+
+    /// The required scopes `auth` lacks.
+    pub fn missing_scopes(&self, auth: &AuthContext) -> Vec<&str> {
+        self.scopes
+            .iter()
+            .filter(|scope| !auth.scopes.contains(*scope))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// The `WWW-Authenticate` value for a request refused with `error`, or for
+/// lack of credentials when `None`.
+pub fn challenge(oauth: Option<&OAuthSettings>, error: Option<BearerError>) -> String {
+    let mut params = Vec::new();
+    match error {
+        Some(BearerError::InvalidToken) => params.push(r#"error="invalid_token""#.to_string()),
+        Some(BearerError::InsufficientScope) => {
+            params.push(r#"error="insufficient_scope""#.to_string());
+            if let Some(oauth) = oauth {
+                params.push(format!(r#"scope="{}""#, oauth.scopes.join(" ")));
+            }
+        }
+        None => {}
+    }
+    if let Some(oauth) = oauth {
+        params.push(format!(r#"resource_metadata="{}""#, oauth.metadata_url()));
+    }
+    if params.is_empty() {
+        "Bearer".to_string()
+    } else {
+        format!("Bearer {}", params.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::AuthMethod;
+    use std::sync::Arc;
+
+    fn settings(resource: &str) -> OAuthSettings {
+        OAuthSettings {
+            resource: resource.to_string(),
+            authorization_servers: vec!["https://id.example.com/".to_string()],
+            scopes: vec!["mcp:tools".to_string()],
+        }
+    }
+
+    #[test]
+    fn metadata_lives_beside_the_resource() {
+        let oauth = settings("https://mcp.example.com/mcp");
+        assert_eq!(
+            oauth.metadata_url(),
+            "https://mcp.example.com/.well-known/oauth-protected-resource/mcp"
+        );
+        assert_eq!(
+            settings("https://mcp.example.com/").metadata_url(),
+            "https://mcp.example.com/.well-known/oauth-protected-resource"
+        );
+        let metadata = oauth.metadata();
+        assert_eq!(metadata["resource"], "https://mcp.example.com/mcp");
+        assert_eq!(
+            metadata["authorization_servers"][0],
+            "https://id.example.com/"
+        );
+        assert_eq!(metadata["scopes_supported"][0], "mcp:tools");
+    }
+
+    #[test]
+    fn challenges_name_the_error_and_the_metadata() {
+        let oauth = settings("https://mcp.example.com/mcp");
+        assert_eq!(challenge(None, None), "Bearer");
+        assert_eq!(
+            challenge(Some(&oauth), None),
+            r#"Bearer resource_metadata="https://mcp.example.com/.well-known/oauth-protected-resource/mcp""#
+        );
+        assert_eq!(
+            challenge(None, Some(BearerError::InvalidToken)),
+            r#"Bearer error="invalid_token""#
+        );
+        assert!(
+            challenge(Some(&oauth), Some(BearerError::InsufficientScope))
+                .starts_with(r#"Bearer error="insufficient_scope", scope="mcp:tools", "#)
+        );
+
+        let auth = AuthContext {
+            subject: "user-7".to_string(),
+            method: AuthMethod::Jwt,
+            scopes: vec!["profile".to_string()].into(),
+            claims: Arc::default(),
+        };
+        assert_eq!(oauth.missing_scopes(&auth), ["mcp:tools"]);
+    }
+}
@@ -0,0 +1,168 @@
+//! Which callers may use which tools, from `INFERENCO_MCP_POLICY_FILE`.
+//!
+//! The file names groups of caller identities and, for each restricted
+//! tool, the groups (or single identities) allowed to call it. Tools it
+//! does not list stay open to every caller.
+//!
+//! ```json
+//! { "groups": { "admins": ["cert:CN=ops, O=Inferenco", "key_name:deploy"],
+//!               "readers": ["scope:docs:read", "san:DNS:agent.internal"] },
+//!   "tools": { "read_cedra_docs": ["readers", "admins"],
+//!              "cedra_submit_tx": ["admins"] } }
+//! ```
+//!
+//! A caller's identities are its namespace (`key:<fingerprint>`,
+//! `user:<sub>`, or `cert:<subject>`), `key_name:<name>` for an issued key,
+//! `scope:<scope>` for each of a token's scopes, and `san:<name>` for each
+//! of a certificate's subject alternative names. Requests that did not
+//! authenticate are not checked.
+
+use super::{AuthContext, AuthMethod};
+use serde::Deserialize;
+use std::{collections::BTreeMap, env, fs, path::PathBuf};
+
+/// Groups of identities, and the tools limited to some of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    #[serde(default)]
+    pub groups: BTreeMap<String, Vec<String>>,
+    /// Tool name to the groups and identities that may call it.
+    #[serde(default)]
+    pub tools: BTreeMap<String, Vec<String>>,
+}
+
+impl Policy {
+    /// Read the file named by `INFERENCO_MCP_POLICY_FILE`; `None` when it
+    /// is not set.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Some(path) = env::var_os("INFERENCO_MCP_POLICY_FILE").filter(|path| !path.is_empty())
+        else {
+            return Ok(None);
+        };
+        let path = PathBuf::from(path);
+        let json = fs::read_to_string(&path).map_err(|error| {
+            format!(
+                "INFERENCO_MCP_POLICY_FILE: cannot read {}: {error}",
+                path.display()
+            )
+        })?;
+        Self::parse(&json)
+            .map(Some)
+            .map_err(|error| format!("INFERENCO_MCP_POLICY_FILE: {error}"))
+    }
+
+    /// Parse and check a policy file's contents. Identities contain a `:`
+    /// and group names do not, so a tool's entries can mix the two.
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let policy: Self = serde_json::from_str(json).map_err(|error| error.to_string())?;
+        for (group, members) in &policy.groups {
+            if group.is_empty() || group.contains(':') {
+                return Err(format!(
+                    "group name '{group}' must be non-empty without ':'"
+                ));
+            }
+            if let Some(member) = members.iter().find(|member| !member.contains(':')) {
+                return Err(format!(
+                    "group '{group}' lists '{member}', which is not an identity such as key_name:ci"
+                ));
+            }
+        }
+        for (tool, entries) in &policy.tools {
+            if let Some(entry) = entries
+                .iter()
+                .find(|entry| !entry.contains(':') && !policy.groups.contains_key(*entry))
+            {
+                return Err(format!("tool '{tool}' names unknown group '{entry}'"));
+            }
+        }
+        Ok(policy)
+    }
+
+    /// Whether `auth` may call `tool`.
+    pub fn allows(&self, auth: &AuthContext, tool: &str) -> bool {
+        let Some(entries) = self.tools.get(tool) else {
+            return true;
+        };
+        let identities = identities(auth);
+        let matches = |identity: &String| identities.contains(identity);
+        entries.iter().any(|entry| match self.groups.get(entry) {
+            Some(members) => members.iter().any(matches),
+            None => matches(entry),
+        })
+    }
+}
+
+/// Every identity policy entries can name `auth` by.
+pub fn identities(auth: &AuthContext) -> Vec<String> {
+    let mut identities = vec![auth.namespace()];
+    match &auth.method {
+        AuthMethod::IssuedKey { name, .. } => identities.push(format!("key_name:{name}")),
+        AuthMethod::Jwt => {
+            identities.extend(auth.scopes.iter().map(|scope| format!("scope:{scope}")))
+        }
+        AuthMethod::ClientCertificate { sans } => {
+            identities.extend(sans.iter().map(|san| format!("san:{san}")))
+        }
+        AuthMethod::ConfiguredKey => {}
+    }
+    identities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn tools_are_limited_to_their_groups_and_identities() {
+        let policy = Policy::parse(
+            r#"{ "groups": { "admins": ["cert:CN=ops", "key_name:deploy"],
+                             "readers": ["scope:docs:read", "san:DNS:agent.internal"] },
+                 "tools": { "read_cedra_docs": ["readers", "admins"],
+                            "cedra_submit_tx": ["admins", "user:alice"] } }"#,
+        )
+        .expect("valid policy");
+        let reader = AuthContext {
+            subject: "bob".to_string(),
+            method: AuthMethod::Jwt,
+            scopes: vec!["docs:read".to_string()].into(),
+            claims: Arc::default(),
+        };
+        assert!(policy.allows(&reader, "read_cedra_docs"));
+        assert!(!policy.allows(&reader, "cedra_submit_tx"));
+        assert!(policy.allows(&reader, "echo"), "unlisted tools are open");
+        let alice = AuthContext {
+            subject: "alice".to_string(),
+            scopes: Arc::default(),
+            ..reader
+        };
+        assert!(policy.allows(&alice, "cedra_submit_tx"));
+        assert!(!policy.allows(&alice, "read_cedra_docs"));
+
+        let ops = AuthContext::for_certificate("CN=ops".to_string(), Vec::new());
+        assert!(policy.allows(&ops, "cedra_submit_tx"));
+        let agent = AuthContext::for_certificate(
+            "CN=agent".to_string(),
+            vec!["DNS:agent.internal".to_string()],
+        );
+        assert!(policy.allows(&agent, "read_cedra_docs"));
+        let deploy = AuthMethod::IssuedKey {
+            id: "1".to_string(),
+            name: "deploy".to_string(),
+        };
+        let deploy = AuthContext::for_key("imcp_x", deploy, vec!["*".to_string()]);
+        assert!(policy.allows(&deploy, "cedra_submit_tx"));
+        let configured = AuthContext::for_key("imcp_x", AuthMethod::ConfiguredKey, Vec::new());
+        assert!(!policy.allows(&configured, "read_cedra_docs"));
+
+        for bad in [
+            r#"{ "tools": { "echo": ["nobody"] } }"#,
+            r#"{ "groups": { "a:b": [] } }"#,
+            r#"{ "groups": { "admins": ["alice"] } }"#,
+            r#"{ "tool": {} }"#,
+        ] {
+            assert!(Policy::parse(bad).is_err(), "{bad}");
+        }
+    }
+}
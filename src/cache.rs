@@ -0,0 +1,629 @@
+use crate::{
+    config::parse_env,
+    metrics::{CacheMetrics, Metrics},
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
+
+/// File in a cache directory that lists the snapshots written with it.
+const INDEX_FILE: &str = "index.json";
+
+/// Size and effectiveness of a cache as reported to the admin API. Caches
+/// that don't count lookups report zero hits and misses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub bytes: usize,
+    #[serde(default)]
+    pub hits: u64,
+    #[serde(default)]
+    pub misses: u64,
+    /// Entries dropped to stay within the cache's limits.
+    #[serde(default)]
+    pub evictions: u64,
+}
+
+/// Bounds on an [`LruCache`]. Each cache has its own; the registry's
+/// defaults come from the environment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheLimits {
+    /// Entries kept before the least recently used is evicted; 0 removes
+    /// the limit.
+    pub max_entries: usize,
+    /// Bytes of keys and values kept before the least recently used entry
+    /// is evicted; 0 removes the limit.
+    pub max_bytes: usize,
+}
+
+impl Default for CacheLimits {
+    fn default() -> Self {
+        Self {
+            max_entries: 10_000,
+            max_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+
+impl CacheLimits {
+    /// Read `INFERENCO_MCP_CACHE_MAX_ENTRIES` and
+    /// `INFERENCO_MCP_CACHE_MAX_BYTES`.
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = Self::default();
+        Ok(Self {
+            max_entries: parse_env("INFERENCO_MCP_CACHE_MAX_ENTRIES", "a number of entries")?
+                .unwrap_or(defaults.max_entries),
+            max_bytes: parse_env("INFERENCO_MCP_CACHE_MAX_BYTES", "a number of bytes")?
+                .unwrap_or(defaults.max_bytes),
+        })
+    }
+}
+
+/// A cache the admin API can inspect and flush.
+pub trait ManagedCache: Send + Sync {
+    fn name(&self) -> &str;
+    fn stats(&self) -> CacheStats;
+    fn flush(&self);
+
+    /// Bounds the cache enforces, if any, shown next to its stats.
+    fn limits(&self) -> Option<CacheLimits> {
+        None
+    }
+
+    /// Serialized contents to keep across restarts, or `None` (the default)
+    /// for caches that always start cold.
+    fn snapshot(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Load contents written by [`snapshot`](Self::snapshot). Caches that
+    /// expire entries should drop the stale ones here.
+    fn restore(&self, _data: &[u8]) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheReport {
+    pub name: String,
+    #[serde(flatten)]
+    pub stats: CacheStats,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limits: Option<CacheLimits>,
+}
+
+/// Bytes a cached value counts against [`CacheLimits::max_bytes`].
+pub trait Weigh {
+    fn weigh(&self) -> usize;
+}
+
+impl Weigh for String {
+    fn weigh(&self) -> usize {
+        self.len()
+    }
+}
+
+impl Weigh for Vec<u8> {
+    fn weigh(&self) -> usize {
+        self.len()
+    }
+}
+
+impl Weigh for serde_json::Value {
+    /// The serialized length, measured without building the string.
+    fn weigh(&self) -> usize {
+        struct Count(usize);
+        impl io::Write for Count {
+            fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+                self.0 += bytes.len();
+                Ok(bytes.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        let mut count = Count(0);
+        serde_json::to_writer(&mut count, self).expect("JSON values serialize");
+        count.0
+    }
+}
+
+/// A string-keyed cache that evicts its least recently used entries to stay
+/// within [`CacheLimits`]. Register it with [`CacheRegistry::lru`] so the
+/// admin API can inspect and flush it.
+pub struct LruCache<V> {
+    name: String,
+    limits: CacheLimits,
+    entries: Mutex<Lru<V>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    metrics: Option<CacheMetrics>,
+}
+
+struct Lru<V> {
+    slots: HashMap<String, Slot<V>>,
+    /// Last use -> key, least recent first.
+    order: BTreeMap<u64, String>,
+    clock: u64,
+    bytes: usize,
+}
+
+struct Slot<V> {
+    value: V,
+    bytes: usize,
+    used: u64,
+}
+
+impl<V> Lru<V> {
+    fn touch(&mut self, key: &str) -> Option<&Slot<V>> {
+        self.clock += 1;
+        let slot = self.slots.get_mut(key)?;
+        let key = self.order.remove(&slot.used).expect("slots are ordered");
+        slot.used = self.clock;
+        self.order.insert(self.clock, key);
+        Some(slot)
+    }
+
+    fn remove(&mut self, key: &str) -> Option<Slot<V>> {
+        let slot = self.slots.remove(key)?;
+        self.order.remove(&slot.used);
+        self.bytes -= slot.bytes;
+        Some(slot)
+    }
+
+    /// Drop least recently used entries until both limits hold; returns
+    /// how many were dropped.
+    fn evict(&mut self, limits: CacheLimits) -> u64 {
+        let mut evicted = 0;
+        while (limits.max_entries > 0 && self.slots.len() > limits.max_entries)
+            || (limits.max_bytes > 0 && self.bytes > limits.max_bytes)
+        {
+            let Some((_, key)) = self.order.pop_first() else {
+                break;
+            };
+            let slot = self.slots.remove(&key).expect("ordered keys are cached");
+            self.bytes -= slot.bytes;
+            evicted += 1;
+        }
+        evicted
+    }
+}
+
+impl<V: Weigh + Clone> LruCache<V> {
+    pub fn new(name: impl Into<String>, limits: CacheLimits) -> Self {
+        Self {
+            name: name.into(),
+            limits,
+            entries: Mutex::new(Lru {
+                slots: HashMap::new(),
+                order: BTreeMap::new(),
+                clock: 0,
+                bytes: 0,
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            metrics: None,
+        }
+    }
+
+    /// Also export hits, misses, evictions, and size to `metrics`.
+    pub fn with_metrics(mut self, metrics: &Metrics) -> Self {
+        self.metrics = Some(metrics.cache(&self.name));
+        self
+    }
+
+    /// The cached value for `key`, which becomes the most recently used.
+    pub fn get(&self, key: &str) -> Option<V> {
+        let value = self
+            .entries
+            .lock()
+            .expect("cache lock poisoned")
+            .touch(key)
+            .map(|slot| slot.value.clone());
+        let (counter, hit) = match value {
+            Some(_) => (&self.hits, true),
+            None => (&self.misses, false),
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        if let Some(metrics) = &self.metrics {
+            metrics.lookup(hit);
+        }
+        value
+    }
+
+    /// Cache `value` under `key`, evicting older entries as needed. A value
+    /// that alone exceeds the byte limit is not cached, though it still
+    /// replaces the stale one; returns whether it was stored.
+    pub fn insert(&self, key: impl Into<String>, value: V) -> bool {
+        let key = key.into();
+        let bytes = key.len() + value.weigh();
+        let mut lru = self.entries.lock().expect("cache lock poisoned");
+        lru.remove(&key);
+        let stored = self.limits.max_bytes == 0 || bytes <= self.limits.max_bytes;
+        if stored {
+            lru.clock += 1;
+            let used = lru.clock;
+            lru.order.insert(used, key.clone());
+            lru.slots.insert(key, Slot { value, bytes, used });
+            lru.bytes += bytes;
+            let evicted = lru.evict(self.limits);
+            self.evictions.fetch_add(evicted, Ordering::Relaxed);
+            if let Some(metrics) = &self.metrics {
+                metrics.evicted(evicted);
+            }
+        }
+        self.record_size(&lru);
+        stored
+    }
+
+    /// Every cached key, least recently used first. Neither counts as a
+    /// lookup nor changes the order.
+    pub fn keys(&self) -> Vec<String> {
+        let lru = self.entries.lock().expect("cache lock poisoned");
+        lru.order.values().cloned().collect()
+    }
+
+    /// Drop `key`, returning its value if it was cached.
+    pub fn remove(&self, key: &str) -> Option<V> {
+        let mut lru = self.entries.lock().expect("cache lock poisoned");
+        let slot = lru.remove(key);
+        self.record_size(&lru);
+        slot.map(|slot| slot.value)
+    }
+
+    fn record_size(&self, lru: &Lru<V>) {
+        if let Some(metrics) = &self.metrics {
+            metrics.resized(lru.slots.len(), lru.bytes);
+        }
+    }
+}
+
+impl<V> ManagedCache for LruCache<V>
+where
+    V: Weigh + Clone + Serialize + DeserializeOwned + Send + Sync,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn stats(&self) -> CacheStats {
+        let lru = self.entries.lock().expect("cache lock poisoned");
+        CacheStats {
+            entries: lru.slots.len(),
+            bytes: lru.bytes,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+
+    fn flush(&self) {
+        let mut lru = self.entries.lock().expect("cache lock poisoned");
+        lru.slots.clear();
+        lru.order.clear();
+        lru.bytes = 0;
+        self.record_size(&lru);
+    }
+
+    fn limits(&self) -> Option<CacheLimits> {
+        Some(self.limits)
+    }
+
+    /// Entries least recently used first, so restoring them in order keeps
+    /// their recency.
+    fn snapshot(&self) -> Option<Vec<u8>> {
+        let lru = self.entries.lock().expect("cache lock poisoned");
+        let entries: Vec<(&String, &V)> = lru
+            .order
+            .values()
+            .map(|key| (key, &lru.slots[key].value))
+            .collect();
+        Some(serde_json::to_vec(&entries).expect("cached values serialize"))
+    }
+
+    /// Re-applies the current limits, so a snapshot taken with larger ones
+    /// keeps only its most recently used entries.
+    fn restore(&self, data: &[u8]) -> Result<(), String> {
+        let entries: Vec<(String, V)> =
+            serde_json::from_slice(data).map_err(|error| error.to_string())?;
+        self.flush();
+        for (key, value) in entries {
+            self.insert(key, value);
+        }
+        Ok(())
+    }
+}
+
+/// One snapshot in a cache directory's index.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct IndexEntry {
+    name: String,
+    file: String,
+    entries: usize,
+    bytes: usize,
+    saved_at: String,
+}
+
+/// File name for a cache's snapshot; names are free-form, files are not.
+fn snapshot_file(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect();
+    format!("{stem}.cache")
+}
+
+/// Write then rename so a crash never leaves a truncated file behind.
+fn write_atomically(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, bytes)?;
+    fs::rename(tmp, path)
+}
+
+/// Caches registered by tools, exposed under `/admin/caches`.
+#[derive(Default)]
+pub struct CacheRegistry {
+    caches: RwLock<Vec<Arc<dyn ManagedCache>>>,
+    /// Limits of caches created with [`lru`](Self::lru).
+    limits: CacheLimits,
+    metrics: Option<Arc<Metrics>>,
+}
+
+impl CacheRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bound caches created with [`lru`](Self::lru) by `limits`.
+    pub fn with_limits(mut self, limits: CacheLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Export the lookups and size of caches created here to `metrics`.
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Create and register an LRU cache bounded by the registry's limits.
+    pub fn lru<V>(&self, name: &str) -> Arc<LruCache<V>>
+    where
+        V: Weigh + Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        self.lru_with_limits(name, self.limits)
+    }
+
+    /// Create and register an LRU cache with its own `limits`.
+    pub fn lru_with_limits<V>(&self, name: &str, limits: CacheLimits) -> Arc<LruCache<V>>
+    where
+        V: Weigh + Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        let cache = LruCache::new(name, limits);
+        let cache = Arc::new(match &self.metrics {
+            Some(metrics) => cache.with_metrics(metrics),
+            None => cache,
+        });
+        self.register(Arc::clone(&cache) as Arc<dyn ManagedCache>);
+        cache
+    }
+
+    pub fn register(&self, cache: Arc<dyn ManagedCache>) {
+        self.caches
+            .write()
+            .expect("cache registry lock poisoned")
+            .push(cache);
+    }
+
+    pub fn reports(&self) -> Vec<CacheReport> {
+        self.caches
+            .read()
+            .expect("cache registry lock poisoned")
+            .iter()
+            .map(|cache| CacheReport {
+                name: cache.name().to_string(),
+                stats: cache.stats(),
+                limits: cache.limits(),
+            })
+            .collect()
+    }
+
+    /// Flush the named cache, or every cache when `name` is `None`. Returns
+    /// the names of the caches that were flushed.
+    pub fn flush(&self, name: Option<&str>) -> Vec<String> {
+        self.caches
+            .read()
+            .expect("cache registry lock poisoned")
+            .iter()
+            .filter(|cache| name.is_none_or(|name| cache.name() == name))
+            .map(|cache| {
+                cache.flush();
+                tracing::info!(cache = cache.name(), "cache flushed");
+                cache.name().to_string()
+            })
+            .collect()
+    }
+
+    /// Write a snapshot of every cache that keeps one to `dir`, followed by
+    /// the index [`warm`](Self::warm) reads. Returns the names written.
+    pub fn spill(&self, dir: &Path) -> Result<Vec<String>, String> {
+        let fail = |error: io::Error| format!("cannot write caches to {}: {error}", dir.display());
+        fs::create_dir_all(dir).map_err(fail)?;
+        let mut index = Vec::new();
+        for cache in self
+            .caches
+            .read()
+            .expect("cache registry lock poisoned")
+            .iter()
+        {
+            let Some(data) = cache.snapshot() else {
+                continue;
+            };
+            let file = snapshot_file(cache.name());
+            write_atomically(&dir.join(&file), &data).map_err(fail)?;
+            index.push(IndexEntry {
+                name: cache.name().to_string(),
+                file,
+                entries: cache.stats().entries,
+                bytes: data.len(),
+                saved_at: chrono::Utc::now().to_rfc3339(),
+            });
+        }
+        let bytes = serde_json::to_vec_pretty(&index).expect("cache index serializes");
+        write_atomically(&dir.join(INDEX_FILE), &bytes).map_err(fail)?;
+        Ok(index.into_iter().map(|entry| entry.name).collect())
+    }
+
+    /// Restore registered caches from the snapshots indexed in `dir`. A
+    /// missing directory means a first start; a snapshot that fails to load
+    /// is logged and its cache starts cold. Returns the names restored.
+    pub fn warm(&self, dir: &Path) -> Result<Vec<String>, String> {
+        let path = dir.join(INDEX_FILE);
+        let index: Vec<IndexEntry> = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|error| format!("invalid cache index {}: {error}", path.display()))?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(format!("cannot read {}: {error}", path.display())),
+        };
+        let caches = self.caches.read().expect("cache registry lock poisoned");
+        let mut restored = Vec::new();
+        for entry in index {
+            let Some(cache) = caches.iter().find(|cache| cache.name() == entry.name) else {
+                continue;
+            };
+            let loaded = fs::read(dir.join(&entry.file))
+                .map_err(|error| error.to_string())
+                .and_then(|data| cache.restore(&data));
+            match loaded {
+                Ok(()) => restored.push(entry.name),
+                Err(error) => tracing::warn!(cache = %entry.name, %error, "cache starts cold"),
+            }
+        }
+        Ok(restored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct TestCache(Mutex<Vec<u8>>);
+
+    impl ManagedCache for TestCache {
+        fn name(&self) -> &str {
+            "test"
+        }
+
+        fn stats(&self) -> CacheStats {
+            let data = self.0.lock().unwrap();
+            CacheStats {
+                entries: data.len(),
+                bytes: data.len(),
+                ..CacheStats::default()
+            }
+        }
+
+        fn flush(&self) {
+            self.0.lock().unwrap().clear();
+        }
+
+        fn snapshot(&self) -> Option<Vec<u8>> {
+            Some(self.0.lock().unwrap().clone())
+        }
+
+        fn restore(&self, data: &[u8]) -> Result<(), String> {
+            *self.0.lock().unwrap() = data.to_vec();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn flushes_only_the_named_cache() {
+        let registry = CacheRegistry::new();
+        registry.register(Arc::new(TestCache(Mutex::new(vec![1, 2, 3]))));
+        assert_eq!(registry.reports()[0].stats.entries, 3);
+
+        assert!(registry.flush(Some("other")).is_empty());
+        assert_eq!(registry.flush(Some("test")), vec!["test".to_string()]);
+        assert_eq!(registry.reports()[0].stats.entries, 0);
+    }
+
+    #[test]
+    fn spilled_caches_warm_a_fresh_registry() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let registry = CacheRegistry::new();
+        registry.register(Arc::new(TestCache(Mutex::new(vec![1, 2, 3]))));
+        assert_eq!(registry.spill(dir.path()), Ok(vec!["test".to_string()]));
+
+        let restarted = CacheRegistry::new();
+        assert_eq!(restarted.warm(&dir.path().join("missing")), Ok(Vec::new()));
+        restarted.register(Arc::new(TestCache(Mutex::new(Vec::new()))));
+        assert_eq!(restarted.warm(dir.path()), Ok(vec!["test".to_string()]));
+        assert_eq!(restarted.reports()[0].stats.entries, 3);
+    }
+
+    #[test]
+    fn lru_evicts_least_recently_used_within_limits() {
+        let metrics = Arc::new(Metrics::new());
+        let registry = CacheRegistry::new().with_metrics(Arc::clone(&metrics));
+        let limits = CacheLimits {
+            max_entries: 2,
+            max_bytes: 16,
+        };
+        let pages = registry.lru_with_limits::<String>("pages", limits);
+        assert!(pages.insert("a", "one".to_string()));
+        assert!(pages.insert("b", "two".to_string()));
+        assert_eq!(pages.get("a").as_deref(), Some("one"));
+        // Over the entry limit: "b" is now the least recently used.
+        assert!(pages.insert("c", "three".to_string()));
+        assert_eq!(pages.get("b"), None);
+        // Over the byte limit (2 + 4 + 2 + 7 > 16): "a" goes.
+        assert!(pages.insert("cc", "seventy".to_string()));
+        assert_eq!(pages.get("a"), None);
+        assert!(!pages.insert("huge", "x".repeat(16)));
+
+        let report = &registry.reports()[0];
+        assert_eq!(
+            report.stats,
+            CacheStats {
+                entries: 2,
+                bytes: 15,
+                hits: 1,
+                misses: 2,
+                evictions: 2,
+            }
+        );
+        assert_eq!(report.limits, Some(limits));
+        let output = metrics.render();
+        assert!(output.contains("inferenco_mcp_cache_evictions_total{cache=\"pages\"} 2"));
+        assert!(output.contains("inferenco_mcp_cache_bytes{cache=\"pages\"} 15"));
+
+        let restarted = LruCache::<String>::new("pages", limits);
+        restarted
+            .restore(&pages.snapshot().expect("snapshot"))
+            .expect("restore");
+        assert!(restarted.insert("d", "four".to_string()));
+        // Recency survives the round trip: "c" was older than "cc".
+        assert_eq!(restarted.get("c"), None);
+        assert_eq!(restarted.get("cc").as_deref(), Some("seventy"));
+
+        assert_eq!(registry.flush(Some("pages")), vec!["pages".to_string()]);
+        assert_eq!(pages.stats().entries, 0);
+        assert!(metrics
+            .render()
+            .contains("inferenco_mcp_cache_entries{cache=\"pages\"} 0"));
+    }
+}
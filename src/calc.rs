@@ -0,0 +1,704 @@
+//! Arithmetic for the `calculate` tool: a parser and evaluator over exact
+//! decimals, so `0.1 + 0.2` is `0.3` and money-like sums never pick up
+//! binary floating-point noise.
+//!
+//! Numbers keep up to [`MAX_SCALE`] fractional digits and about 38
+//! significant digits; intermediate products and quotients are computed in
+//! 256 bits and rounded half away from zero. Percentages are a unit, as on
+//! a desk calculator: `200 + 10%` is `220`, `10% of 200` is `20`.
+
+use std::{cmp::Ordering, fmt};
+
+/// Fractional digits kept after rounding.
+pub const MAX_SCALE: u32 = 28;
+/// Longest expression accepted, in characters.
+pub const MAX_EXPRESSION_CHARS: usize = 1000;
+/// Deepest nesting of parentheses and unary signs.
+const MAX_DEPTH: usize = 64;
+
+/// An exact decimal: `magnitude / 10^scale`, with a sign.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decimal {
+    negative: bool,
+    magnitude: u128,
+    scale: u32,
+}
+
+/// The result of [`evaluate`]: a number, or a percentage of one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Value {
+    pub number: Decimal,
+    /// `number` is in percent, e.g. `15` for `15%`.
+    pub percent: bool,
+}
+
+/// Evaluate `expression`, e.g. `(1.5 + 2) * 3`, `2^64`, or `80 - 15%`.
+/// Errors name what went wrong and where.
+pub fn evaluate(expression: &str) -> Result<Value, String> {
+    if expression.chars().count() > MAX_EXPRESSION_CHARS {
+        return Err(format!(
+            "expression is longer than {MAX_EXPRESSION_CHARS} characters"
+        ));
+    }
+    let tokens = tokenize(expression)?;
+    let mut parser = Parser {
+        tokens: &tokens,
+        next: 0,
+        depth: 0,
+    };
+    let value = parser.expression()?;
+    match parser.peek() {
+        None => Ok(value),
+        Some((token, position)) => Err(format!("unexpected {token} at position {position}")),
+    }
+}
+
+impl Decimal {
+    pub const ZERO: Decimal = Decimal {
+        negative: false,
+        magnitude: 0,
+        scale: 0,
+    };
+    const HUNDRED: Decimal = Decimal {
+        negative: false,
+        magnitude: 100,
+        scale: 0,
+    };
+    const ONE: Decimal = Decimal {
+        negative: false,
+        magnitude: 1,
+        scale: 0,
+    };
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude == 0
+    }
+
+    /// Round a wide magnitude until it fits in 128 bits and [`MAX_SCALE`].
+    fn fit(negative: bool, mut magnitude: Wide, mut scale: u32) -> Result<Decimal, String> {
+        while scale > MAX_SCALE || magnitude.to_u128().is_none() {
+            if scale == 0 {
+                return Err("result is too large".to_string());
+            }
+            let (quotient, digit) = magnitude.div_small(10);
+            magnitude = quotient;
+            if digit >= 5 {
+                magnitude = magnitude.add(Wide::from(1)).ok_or("result is too large")?;
+            }
+            scale -= 1;
+        }
+        let mut magnitude = magnitude.to_u128().expect("checked above");
+        // Trailing zeros carry no information; dropping them keeps the
+        // mantissa small for the next operation.
+        while scale > 0 && magnitude.is_multiple_of(10) {
+            magnitude /= 10;
+            scale -= 1;
+        }
+        Ok(Decimal {
+            negative: negative && magnitude != 0,
+            magnitude,
+            scale,
+        })
+    }
+
+    /// Both magnitudes at the larger scale.
+    fn aligned(self, other: Decimal) -> (Wide, Wide, u32) {
+        let scale = self.scale.max(other.scale);
+        let widen = |value: Decimal| {
+            Wide::from(value.magnitude)
+                .mul_pow10(scale - value.scale)
+                .expect("38 digits shifted by at most 28 fit in 256 bits")
+        };
+        (widen(self), widen(other), scale)
+    }
+
+    pub fn checked_add(self, other: Decimal) -> Result<Decimal, String> {
+        let (a, b, scale) = self.aligned(other);
+        if self.negative == other.negative {
+            let sum = a.add(b).expect("two aligned magnitudes fit in 256 bits");
+            return Decimal::fit(self.negative, sum, scale);
+        }
+        match a.cmp(&b) {
+            Ordering::Less => Decimal::fit(other.negative, b.sub(a), scale),
+            _ => Decimal::fit(self.negative, a.sub(b), scale),
+        }
+    }
+
+    pub fn checked_sub(self, other: Decimal) -> Result<Decimal, String> {
+        self.checked_add(-other)
+    }
+
+    pub fn checked_mul(self, other: Decimal) -> Result<Decimal, String> {
+        Decimal::fit(
+            self.negative != other.negative,
+            Wide::mul(self.magnitude, other.magnitude),
+            self.scale + other.scale,
+        )
+    }
+
+    pub fn checked_div(self, other: Decimal) -> Result<Decimal, String> {
+        if other.is_zero() {
+            return Err("division by zero".to_string());
+        }
+        let (a, b, _) = self.aligned(other);
+        let (mut quotient, mut remainder) = a.div_rem(b);
+        // One digit past MAX_SCALE, for `fit` to round on.
+        let mut scale = 0;
+        while !remainder.is_zero() && scale <= MAX_SCALE {
+            let Some(shifted) = quotient.mul_pow10(1) else {
+                break;
+            };
+            let (digit, rest) = remainder
+                .mul_pow10(1)
+                .expect("remainder is below the divisor")
+                .div_rem(b);
+            quotient = shifted.add(digit).expect("a digit fits");
+            remainder = rest;
+            scale += 1;
+        }
+        Decimal::fit(self.negative != other.negative, quotient, scale)
+    }
+
+    /// `self` to a whole power.
+    pub fn powi(self, exponent: i64) -> Result<Decimal, String> {
+        let power = self.pow_unsigned(exponent.unsigned_abs())?;
+        if exponent < 0 {
+            Decimal::ONE.checked_div(power)
+        } else {
+            Ok(power)
+        }
+    }
+
+    /// `self` to a non-negative power, by repeated squaring.
+    fn pow_unsigned(self, exponent: u64) -> Result<Decimal, String> {
+        let (mut base, mut remaining, mut result) = (self, exponent, Decimal::ONE);
+        while remaining > 0 {
+            if remaining & 1 == 1 {
+                result = result.checked_mul(base)?;
+            }
+            remaining >>= 1;
+            if remaining > 0 {
+                base = base.checked_mul(base)?;
+            }
+        }
+        Ok(result)
+    }
+
+    /// The value as a whole number, if it is one.
+    fn to_i64(self) -> Option<i64> {
+        if self.scale != 0 {
+            return None;
+        }
+        let magnitude = i64::try_from(self.magnitude).ok()?;
+        Some(if self.negative { -magnitude } else { magnitude })
+    }
+
+    fn parse(digits: &str) -> Result<Decimal, String> {
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        let mut magnitude = Wide::from(0);
+        for digit in whole.chars().chain(fraction.chars()) {
+            let digit = digit
+                .to_digit(10)
+                .expect("the tokenizer only passes digits");
+            magnitude = magnitude
+                .mul_pow10(1)
+                .and_then(|shifted| shifted.add(Wide::from(u128::from(digit))))
+                .ok_or_else(|| format!("{digits} is too large"))?;
+        }
+        Decimal::fit(false, magnitude, fraction.len() as u32)
+            .map_err(|_| format!("{digits} is too large"))
+    }
+}
+
+impl std::ops::Neg for Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        Decimal {
+            negative: !self.negative && self.magnitude != 0,
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.magnitude.to_string();
+        let scale = self.scale as usize;
+        if self.negative {
+            f.write_str("-")?;
+        }
+        if scale == 0 {
+            return f.write_str(&digits);
+        }
+        let digits = format!("{digits:0>width$}", width = scale + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - scale);
+        write!(f, "{whole}.{fraction}")
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.number)?;
+        if self.percent {
+            f.write_str("%")?;
+        }
+        Ok(())
+    }
+}
+
+impl Value {
+    fn number(number: Decimal) -> Value {
+        Value {
+            number,
+            percent: false,
+        }
+    }
+
+    fn percent(number: Decimal) -> Value {
+        Value {
+            number,
+            percent: true,
+        }
+    }
+
+    /// The value as a plain number: `15%` is `0.15`.
+    fn fraction(self) -> Result<Decimal, String> {
+        if self.percent {
+            self.number.checked_div(Decimal::HUNDRED)
+        } else {
+            Ok(self.number)
+        }
+    }
+
+    /// `self + other`, or `self - other` when `subtract`. A percentage on
+    /// the right scales the left: `200 + 10%` is `220`.
+    fn add(self, other: Value, subtract: bool) -> Result<Value, String> {
+        let other_number = if subtract {
+            -other.number
+        } else {
+            other.number
+        };
+        match (self.percent, other.percent) {
+            (false, true) => {
+                let factor = Decimal::HUNDRED
+                    .checked_add(other_number)?
+                    .checked_div(Decimal::HUNDRED)?;
+                Ok(Value::number(self.number.checked_mul(factor)?))
+            }
+            (true, true) => Ok(Value::percent(self.number.checked_add(other_number)?)),
+            (_, false) => Ok(Value::number(self.fraction()?.checked_add(other_number)?)),
+        }
+    }
+
+    fn mul(self, other: Value) -> Result<Value, String> {
+        let product = self.number.checked_mul(other.number)?;
+        match (self.percent, other.percent) {
+            (false, false) => Ok(Value::number(product)),
+            (true, true) => Ok(Value::percent(product.checked_div(Decimal::HUNDRED)?)),
+            _ => Ok(Value::number(product.checked_div(Decimal::HUNDRED)?)),
+        }
+    }
+
+    fn div(self, other: Value) -> Result<Value, String> {
+        match (self.percent, other.percent) {
+            (true, false) => Ok(Value::percent(self.number.checked_div(other.number)?)),
+            _ => Ok(Value::number(
+                self.fraction()?.checked_div(other.fraction()?)?,
+            )),
+        }
+    }
+
+    fn pow(self, exponent: Value, position: usize) -> Result<Value, String> {
+        let whole = (!exponent.percent)
+            .then(|| exponent.number.to_i64())
+            .flatten()
+            .ok_or_else(|| format!("the exponent at position {position} must be a whole number"))?;
+        Ok(Value::number(self.fraction()?.powi(whole)?))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(String),
+    Plus,
+    Minus,
+    Times,
+    Divide,
+    Power,
+    Percent,
+    Of,
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(digits) => write!(f, "number {digits}"),
+            Token::Plus => f.write_str("'+'"),
+            Token::Minus => f.write_str("'-'"),
+            Token::Times => f.write_str("'*'"),
+            Token::Divide => f.write_str("'/'"),
+            Token::Power => f.write_str("'^'"),
+            Token::Percent => f.write_str("'%'"),
+            Token::Of => f.write_str("'of'"),
+            Token::Open => f.write_str("'('"),
+            Token::Close => f.write_str("')'"),
+        }
+    }
+}
+
+/// Split `expression` into tokens, each with its 1-based character position.
+/// Underscores and commas inside numbers are digit separators.
+fn tokenize(expression: &str) -> Result<Vec<(Token, usize)>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().enumerate().peekable();
+    while let Some((index, c)) = chars.next() {
+        let position = index + 1;
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '0'..='9' | '.' => {
+                let mut digits = c.to_string();
+                while let Some(&(_, c)) = chars.peek() {
+                    match c {
+                        '0'..='9' | '.' => digits.push(c),
+                        '_' | ',' => {}
+                        _ => break,
+                    }
+                    chars.next();
+                }
+                if digits.matches('.').count() > 1 || digits == "." {
+                    return Err(format!("malformed number {digits} at position {position}"));
+                }
+                Token::Number(digits)
+            }
+            '+' => Token::Plus,
+            '-' | '−' => Token::Minus,
+            '*' | '×' => Token::Times,
+            '/' | '÷' => Token::Divide,
+            '^' => Token::Power,
+            '%' => Token::Percent,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            'o' if chars.peek().map(|&(_, c)| c) == Some('f') => {
+                chars.next();
+                Token::Of
+            }
+            c => return Err(format!("unexpected '{c}' at position {position}")),
+        };
+        tokens.push((token, position));
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent, lowest precedence first:
+/// `+ -`, then `* / of`, then unary signs, then right-associative `^`,
+/// then a trailing `%`.
+struct Parser<'a> {
+    tokens: &'a [(Token, usize)],
+    next: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&(Token, usize)> {
+        self.tokens.get(self.next)
+    }
+
+    fn eat(&mut self, token: &Token) -> Option<usize> {
+        match self.peek() {
+            Some((next, position)) if next == token => {
+                let position = *position;
+                self.next += 1;
+                Some(position)
+            }
+            _ => None,
+        }
+    }
+
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, String>,
+    ) -> Result<T, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err(format!(
+                "expression is nested more than {MAX_DEPTH} levels deep"
+            ));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn expression(&mut self) -> Result<Value, String> {
+        let mut value = self.term()?;
+        loop {
+            if self.eat(&Token::Plus).is_some() {
+                value = value.add(self.term()?, false)?;
+            } else if self.eat(&Token::Minus).is_some() {
+                value = value.add(self.term()?, true)?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<Value, String> {
+        let mut value = self.unary()?;
+        loop {
+            if self.eat(&Token::Times).is_some() {
+                value = value.mul(self.unary()?)?;
+            } else if let Some(position) = self.eat(&Token::Divide) {
+                let divisor = self.unary()?;
+                if divisor.number.is_zero() {
+                    return Err(format!("division by zero at position {position}"));
+                }
+                value = value.div(divisor)?;
+            } else if let Some(position) = self.eat(&Token::Of) {
+                if !value.percent {
+                    return Err(format!(
+                        "'of' at position {position} needs a percentage before it, as in 10% of 200"
+                    ));
+                }
+                value = value.mul(self.unary()?)?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Value, String> {
+        if self.eat(&Token::Minus).is_some() {
+            let value = self.nested(Self::unary)?;
+            return Ok(Value {
+                number: -value.number,
+                ..value
+            });
+        }
+        if self.eat(&Token::Plus).is_some() {
+            return self.nested(Self::unary);
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Value, String> {
+        let base = self.postfix()?;
+        match self.eat(&Token::Power) {
+            Some(position) => {
+                let exponent = self.nested(Self::unary)?;
+                base.pow(exponent, position)
+            }
+            None => Ok(base),
+        }
+    }
+
+    fn postfix(&mut self) -> Result<Value, String> {
+        let value = self.primary()?;
+        match self.eat(&Token::Percent) {
+            Some(position) if value.percent => {
+                Err(format!("unexpected '%' at position {position}"))
+            }
+            Some(_) => Ok(Value::percent(value.number)),
+            None => Ok(value),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Value, String> {
+        match self.tokens.get(self.next).cloned() {
+            Some((Token::Number(digits), _)) => {
+                self.next += 1;
+                Ok(Value::number(Decimal::parse(&digits)?))
+            }
+            Some((Token::Open, position)) => {
+                self.next += 1;
+                let value = self.nested(Self::expression)?;
+                self.eat(&Token::Close)
+                    .ok_or_else(|| format!("'(' at position {position} is never closed"))?;
+                Ok(value)
+            }
+            Some((token, position)) => Err(format!(
+                "expected a number or '(' at position {position}, found {token}"
+            )),
+            None => Err("expected a number at the end of the expression".to_string()),
+        }
+    }
+}
+
+/// The low 64 bits of a `u128`.
+const MASK: u128 = u64::MAX as u128;
+
+/// An unsigned 256-bit integer, wide enough for the product of two
+/// magnitudes or one shifted by [`MAX_SCALE`] digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Wide {
+    high: u128,
+    low: u128,
+}
+
+impl Wide {
+    fn from(value: u128) -> Wide {
+        Wide {
+            high: 0,
+            low: value,
+        }
+    }
+
+    fn is_zero(self) -> bool {
+        self.high == 0 && self.low == 0
+    }
+
+    fn to_u128(self) -> Option<u128> {
+        (self.high == 0).then_some(self.low)
+    }
+
+    fn mul(a: u128, b: u128) -> Wide {
+        let (a_high, a_low) = (a >> 64, a & MASK);
+        let (b_high, b_low) = (b >> 64, b & MASK);
+        let low_low = a_low * b_low;
+        let high_low = a_high * b_low;
+        let low_high = a_low * b_high;
+        let middle = (low_low >> 64) + (high_low & MASK) + (low_high & MASK);
+        Wide {
+            high: a_high * b_high + (high_low >> 64) + (low_high >> 64) + (middle >> 64),
+            low: (low_low & MASK) | (middle << 64),
+        }
+    }
+
+    fn add(self, other: Wide) -> Option<Wide> {
+        let (low, carry) = self.low.overflowing_add(other.low);
+        let high = self
+            .high
+            .checked_add(other.high)?
+            .checked_add(u128::from(carry))?;
+        Some(Wide { high, low })
+    }
+
+    /// `self - other`, for `self >= other`.
+    fn sub(self, other: Wide) -> Wide {
+        let (low, borrow) = self.low.overflowing_sub(other.low);
+        Wide {
+            high: self.high - other.high - u128::from(borrow),
+            low,
+        }
+    }
+
+    fn mul_pow10(self, exponent: u32) -> Option<Wide> {
+        let mut value = self;
+        for _ in 0..exponent {
+            let low = Wide::mul(value.low, 10);
+            let high = value.high.checked_mul(10)?;
+            value = Wide {
+                high: high.checked_add(low.high)?,
+                low: low.low,
+            };
+        }
+        Some(value)
+    }
+
+    fn div_small(self, divisor: u64) -> (Wide, u64) {
+        let divisor = u128::from(divisor);
+        let mut remainder = 0u128;
+        let mut limbs = [
+            self.high >> 64,
+            self.high & MASK,
+            self.low >> 64,
+            self.low & MASK,
+        ];
+        for limb in &mut limbs {
+            let current = (remainder << 64) | *limb;
+            *limb = current / divisor;
+            remainder = current % divisor;
+        }
+        let quotient = Wide {
+            high: (limbs[0] << 64) | limbs[1],
+            low: (limbs[2] << 64) | limbs[3],
+        };
+        (quotient, remainder as u64)
+    }
+
+    fn shl1(self) -> Wide {
+        Wide {
+            high: (self.high << 1) | (self.low >> 127),
+            low: self.low << 1,
+        }
+    }
+
+    fn bit(self, index: u32) -> bool {
+        if index >= 128 {
+            (self.high >> (index - 128)) & 1 == 1
+        } else {
+            (self.low >> index) & 1 == 1
+        }
+    }
+
+    /// Quotient and remainder by binary long division.
+    fn div_rem(self, divisor: Wide) -> (Wide, Wide) {
+        let (mut quotient, mut remainder) = (Wide::from(0), Wide::from(0));
+        for index in (0..256).rev() {
+            remainder = remainder.shl1();
+            if self.bit(index) {
+                remainder.low |= 1;
+            }
+            quotient = quotient.shl1();
+            if remainder >= divisor {
+                remainder = remainder.sub(divisor);
+                quotient.low |= 1;
+            }
+        }
+        (quotient, remainder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(expression: &str) -> String {
+        evaluate(expression)
+            .unwrap_or_else(|error| panic!("{expression}: {error}"))
+            .to_string()
+    }
+
+    #[test]
+    fn evaluates_exactly_with_precedence_and_percentages() {
+        assert_eq!(eval("0.1 + 0.2"), "0.3");
+        assert_eq!(eval("1 + 2 * 3 - 4 / 8"), "6.5");
+        assert_eq!(eval("-(2 + 3) ^ 2"), "-25");
+        assert_eq!(eval("2 ^ 3 ^ 2"), "512");
+        assert_eq!(eval("2^100"), "1267650600228229401496703205376");
+        assert_eq!(eval("2^-2"), "0.25");
+        assert_eq!(eval("1 / 3"), "0.3333333333333333333333333333");
+        assert_eq!(eval("2 / 3"), "0.6666666666666666666666666667");
+        assert_eq!(eval("1,000,000 * 1.5"), "1500000");
+        assert_eq!(
+            eval("99999999999999999999.99 + 0.01"),
+            "100000000000000000000"
+        );
+        assert_eq!(eval("200 + 10%"), "220");
+        assert_eq!(eval("200 - 15%"), "170");
+        assert_eq!(eval("10% of 200"), "20");
+        assert_eq!(eval("50 * 10%"), "5");
+        assert_eq!(eval("50 / 10%"), "500");
+        assert_eq!(eval("10% + 5%"), "15%");
+        assert_eq!(eval("10% * 50%"), "5%");
+    }
+
+    #[test]
+    fn errors_say_what_and_where() {
+        let error = |expression: &str| evaluate(expression).unwrap_err();
+        assert_eq!(error("1 / (2 - 2)"), "division by zero at position 3");
+        assert_eq!(error("2 * (3 + 4"), "'(' at position 5 is never closed");
+        assert_eq!(error("3 $ 4"), "unexpected '$' at position 3");
+        assert_eq!(error("1 2"), "unexpected number 2 at position 3");
+        assert_eq!(
+            error("2 ^ 0.5"),
+            "the exponent at position 3 must be a whole number"
+        );
+        assert_eq!(error("10^400"), "result is too large");
+        assert!(error("3 of 4").contains("needs a percentage"));
+        assert!(error(&"(".repeat(100)).contains("nested"));
+        assert_eq!(error(""), "expected a number at the end of the expression");
+    }
+}
@@ -0,0 +1,148 @@
+//! Cancellation of in-flight requests (`notifications/cancelled`).
+//!
+//! Requests run through [`CancellationRegistry::run`] under their JSON-RPC ID
+//! and a scope naming the connection or session they arrived on, since IDs
+//! are only unique per client. A `notifications/cancelled` naming the same
+//! scope and ID drops the request's future wherever it is waiting, which
+//! releases its concurrency permit and aborts its upstream calls, and the
+//! request is answered with [`CANCELLED_ERROR_CODE`].
+
+use futures::future::{AbortHandle, Abortable};
+use rmcp::{model::ErrorCode, ErrorData as McpError};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+/// JSON-RPC error code a cancelled request is answered with, if the client
+/// is still waiting for one.
+pub const CANCELLED_ERROR_CODE: ErrorCode = ErrorCode(-32800);
+/// Scope of requests arriving over stdio, the process's one stdio client.
+pub const STDIO_SCOPE: &str = "stdio";
+
+/// The error a cancelled request is answered with.
+pub fn cancelled() -> McpError {
+    McpError::new(CANCELLED_ERROR_CODE, "Request cancelled", None)
+}
+
+/// In-flight requests by scope and ID.
+#[derive(Default)]
+pub struct CancellationRegistry {
+    requests: Mutex<HashMap<(String, String), Running>>,
+    next: AtomicU64,
+}
+
+struct Running {
+    /// Tells a request apart from a later one reusing its ID.
+    serial: u64,
+    abort: AbortHandle,
+}
+
+/// Unregisters a request when it finishes or is dropped.
+struct Registration<'a> {
+    registry: &'a CancellationRegistry,
+    key: (String, String),
+    serial: u64,
+}
+
+impl Drop for Registration<'_> {
+    fn drop(&mut self) {
+        let mut requests = self.registry.lock();
+        if requests
+            .get(&self.key)
+            .is_some_and(|running| running.serial == self.serial)
+        {
+            requests.remove(&self.key);
+        }
+    }
+}
+
+impl CancellationRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run request `id` of `scope` until it finishes or is cancelled.
+    pub async fn run<F: Future>(&self, scope: &str, id: &Value, request: F) -> Option<F::Output> {
+        let (abort, registration) = AbortHandle::new_pair();
+        let serial = self.next.fetch_add(1, Ordering::Relaxed);
+        let key = (scope.to_string(), id.to_string());
+        self.lock().insert(key.clone(), Running { serial, abort });
+        let _registration = Registration {
+            registry: self,
+            key,
+            serial,
+        };
+        Abortable::new(request, registration).await.ok()
+    }
+
+    /// Cancel request `id` of `scope`. Returns whether it was still running.
+    pub fn cancel(&self, scope: &str, id: &Value) -> bool {
+        let running = self.lock().remove(&(scope.to_string(), id.to_string()));
+        match running {
+            Some(running) => {
+                running.abort.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Requests running now.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<(String, String), Running>> {
+        self.requests.lock().expect("cancellation lock poisoned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::{sync::Arc, time::Duration};
+
+    #[tokio::test]
+    async fn cancelling_drops_only_the_named_request() {
+        let registry = Arc::new(CancellationRegistry::new());
+        let slow = |registry: Arc<CancellationRegistry>, scope: &'static str| {
+            tokio::spawn(async move {
+                registry
+                    .run(
+                        scope,
+                        &json!(1),
+                        tokio::time::sleep(Duration::from_secs(60)),
+                    )
+                    .await
+            })
+        };
+        let first = slow(registry.clone(), "a");
+        let second = slow(registry.clone(), "b");
+        while registry.len() < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        assert!(!registry.cancel("a", &json!("1")));
+        assert!(registry.cancel("a", &json!(1)));
+        assert_eq!(first.await.unwrap(), None);
+        assert_eq!(registry.len(), 1);
+        second.abort();
+        assert!(second.await.unwrap_err().is_cancelled());
+
+        let done = registry.run("a", &json!(1), async { 7 }).await;
+        assert_eq!(done, Some(7));
+        assert!(registry.is_empty());
+        assert!(!registry.cancel("a", &json!(1)));
+    }
+}
@@ -0,0 +1,886 @@
+//! Command-line interface. Every flag falls back to its `INFERENCO_MCP_*`
+//! environment variable, so existing env-only deployments keep working and
+//! running the binary without a subcommand still starts the server.
+
+pub use crate::config::Transport;
+
+use crate::{
+    audit::{AuditBackend, AuditQuery, DEFAULT_QUERY_LIMIT},
+    auth::keys::{self, KeyStore, NewKey},
+    config::{
+        parse_url, EffectiveSetting, Listeners, Profile, DEFAULT_BIND_ADDR, DEFAULT_TCP_ADDR,
+    },
+    http_client::HttpClient,
+    server::ToolService,
+    store::{self, StateBackend, StateLimits, StateSnapshot},
+};
+use clap::{Args, Parser, Subcommand};
+use reqwest::{StatusCode, Url};
+use rmcp::model::{CallToolResult, RawContent};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Upper bound on a `call --remote` round trip.
+const REMOTE_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Parser)]
+#[command(name = "inferenco-mcp", version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Preset defaults for variables that are not set otherwise.
+    #[arg(long, global = true, env = "INFERENCO_MCP_PROFILE", value_enum)]
+    pub profile: Option<Profile>,
+
+    /// Env file to load instead of `.env` in the working directory, e.g. a
+    /// mounted ConfigMap.
+    #[arg(long, global = true, env = "INFERENCO_MCP_CONFIG", value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub serve: ServeArgs,
+}
+
+impl Cli {
+    /// The subcommand to run, defaulting to `serve`.
+    pub fn into_command(self) -> Command {
+        self.command.unwrap_or(Command::Serve(self.serve))
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Start the MCP server (the default).
+    Serve(ServeArgs),
+    /// Work with the registered tools without starting a server.
+    #[command(subcommand)]
+    Tools(ToolsCommand),
+    /// Same as `tools list`, kept for existing scripts.
+    #[command(hide = true)]
+    ListTools,
+    /// Call a tool, in-process or on a running server, and print its result.
+    Call(CallArgs),
+    /// Show tools with their input/output schemas and annotations.
+    Inspect {
+        /// Only show this tool.
+        tool: Option<String>,
+        /// Print the tool definitions as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Answer one JSON-RPC request (or batch) read from stdin, then exit.
+    Once,
+    /// Interactive prompt for calling tools with completion and history.
+    Repl,
+    /// Print every setting with its effective value and where it came from.
+    Config {
+        /// Print the settings as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print a JSON Schema for configuration files, for editor validation.
+    ConfigSchema,
+    /// Check the configuration file and environment, then exit: 0 when the
+    /// server would start, 2 otherwise.
+    ValidateConfig,
+    /// Re-run a recording from INFERENCO_MCP_RECORD_PATH and diff the responses.
+    Replay {
+        /// Recording file to replay.
+        file: PathBuf,
+    },
+    /// Export or import the persistent tool state (counters, key-value
+    /// entries, notes, and call history).
+    #[command(subcommand)]
+    State(StateCommand),
+    /// Issue, list, or revoke the API keys in INFERENCO_MCP_KEYS_FILE.
+    #[command(subcommand)]
+    Keys(KeysCommand),
+    /// Read the tool calls recorded in INFERENCO_MCP_AUDIT_PATH.
+    #[command(subcommand)]
+    Audit(AuditCommand),
+    /// Install, remove, or run as a Windows service.
+    #[cfg(windows)]
+    #[command(subcommand)]
+    Service(ServiceCommand),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ToolsCommand {
+    /// List the registered tools with their descriptions.
+    List {
+        /// Print the tool names and descriptions as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum StateCommand {
+    /// Write the configured state backend to a JSON archive.
+    Export {
+        /// Archive to write; standard output when omitted.
+        file: Option<PathBuf>,
+    },
+    /// Merge an archive from `state export` into the configured backend.
+    Import {
+        /// Archive to read.
+        file: PathBuf,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum KeysCommand {
+    /// Issue a key and print it; it cannot be shown again.
+    Create {
+        /// Who or what the key is for, e.g. `ci`.
+        #[arg(long)]
+        name: String,
+        /// A tool the key may call; repeat for more. Every tool when omitted.
+        #[arg(long = "scope", value_name = "TOOL")]
+        scopes: Vec<String>,
+        /// When the key stops working: a date (`2027-01-01`), an RFC 3339
+        /// time, or a number of days (`90d`). Never when omitted.
+        #[arg(long, value_name = "WHEN")]
+        expires: Option<String>,
+    },
+    /// List keys with their scopes and status, without the keys themselves.
+    List {
+        /// Print the keys as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Revoke a key by ID; a running server stops accepting it within seconds.
+    Revoke {
+        /// ID shown by `keys list`.
+        id: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AuditCommand {
+    /// Print the newest recorded calls matching every filter given.
+    Query {
+        /// Only calls to this tool.
+        #[arg(long)]
+        tool: Option<String>,
+        /// Only calls by this caller, as shown in the `subject` column.
+        #[arg(long)]
+        subject: Option<String>,
+        /// Only calls in this MCP session.
+        #[arg(long)]
+        session: Option<String>,
+        /// Only calls with this outcome: `success`, `tool_error`, or `error`.
+        #[arg(long)]
+        outcome: Option<String>,
+        /// Only calls since this time: RFC 3339, a date, an epoch, or an
+        /// expression like `2 hours ago`.
+        #[arg(long, value_name = "WHEN")]
+        since: Option<String>,
+        /// Most calls to print.
+        #[arg(long, default_value_t = DEFAULT_QUERY_LIMIT)]
+        limit: usize,
+        /// Print the records as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[cfg(windows)]
+#[derive(Debug, Subcommand)]
+pub enum ServiceCommand {
+    /// Register the server to start with Windows, serving HTTP on `--port`.
+    Install {
+        #[arg(long, default_value_t = 8080, value_parser = clap::value_parser!(u16).range(1..))]
+        port: u16,
+    },
+    /// Stop and unregister the service.
+    Uninstall,
+    /// Entry point used by the service control manager.
+    #[command(hide = true)]
+    Run(ServeArgs),
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct CallArgs {
+    /// Tool name, e.g. `roll_dice`.
+    pub tool: String,
+
+    /// Tool arguments as a JSON object.
+    #[arg(long, default_value = "{}")]
+    pub args: String,
+
+    /// Print the whole tool result as JSON instead of its text content.
+    #[arg(long)]
+    pub json: bool,
+
+    /// Base URL or `/rpc` endpoint of a running HTTP server to call instead
+    /// of running the tool in-process.
+    #[arg(long, value_name = "URL")]
+    pub remote: Option<String>,
+
+    /// API key sent to the remote server.
+    #[arg(long, requires = "remote")]
+    pub api_key: Option<String>,
+
+    /// Header that carries `--api-key`.
+    #[arg(long, default_value = "x-api-key")]
+    pub auth_header: String,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ServeArgs {
+    /// Transports to serve, comma-separated (`stdio,http`). They share one
+    /// tool service; `http` and `ws` share the HTTP port.
+    #[arg(
+        long,
+        env = "INFERENCO_MCP_TRANSPORT",
+        value_enum,
+        value_delimiter = ',',
+        default_values_t = [Transport::Stdio]
+    )]
+    pub transport: Vec<Transport>,
+
+    /// HTTP port (only used with `--transport http` or `ws`). Falls back to the
+    /// platform's `PORT` variable, then 8080. `0` picks a free port, which
+    /// the `server ready` log line reports.
+    #[arg(long, env = "INFERENCO_MCP_PORT")]
+    pub port: Option<u16>,
+
+    /// Address the HTTP port listens on. `127.0.0.1` keeps the server to
+    /// this machine and, unless `INFERENCO_MCP_ALLOWED_HOSTS` says
+    /// otherwise, to requests for `localhost`.
+    #[arg(long, env = "INFERENCO_MCP_BIND_ADDR", default_value = DEFAULT_BIND_ADDR)]
+    pub bind_addr: std::net::IpAddr,
+
+    /// Address the `tcp` transport listens on. Connections are not
+    /// authenticated, so it stays on loopback unless told otherwise; port 0
+    /// picks a free port, which the `tcp ready` log line reports.
+    #[arg(long, env = "INFERENCO_MCP_TCP_ADDR", default_value = DEFAULT_TCP_ADDR)]
+    pub tcp_addr: std::net::SocketAddr,
+
+    /// Validate configuration, the port, upstreams, and the tool registry,
+    /// then exit without serving.
+    #[arg(long)]
+    pub check: bool,
+
+    /// Detach from the terminal and keep serving in the background.
+    #[cfg(unix)]
+    #[arg(long)]
+    pub daemon: bool,
+
+    /// Write the daemon's PID to this file; refuses to start while the
+    /// process it names is running.
+    #[cfg(unix)]
+    #[arg(long, value_name = "PATH", requires = "daemon")]
+    pub pid_file: Option<std::path::PathBuf>,
+
+    /// Append the daemon's stdout and stderr to this file instead of
+    /// discarding them.
+    #[cfg(unix)]
+    #[arg(long, value_name = "PATH", requires = "daemon")]
+    pub log_output: Option<std::path::PathBuf>,
+}
+
+impl ServeArgs {
+    /// The transports and addresses, as [`Settings::load`](crate::config::Settings::load)
+    /// takes them.
+    pub fn listeners(&self) -> Listeners {
+        Listeners {
+            transport: self.transport.clone(),
+            port: self.port,
+            bind_addr: self.bind_addr,
+            tcp_addr: self.tcp_addr,
+        }
+    }
+}
+
+/// One line per tool, sorted by name: name and description. As JSON, an
+/// array of `{name, description}` objects.
+pub fn list_tools(service: &ToolService, json: bool) -> String {
+    let mut tools = service.available_tools();
+    tools.sort_by(|a, b| a.name.cmp(&b.name));
+    if json {
+        let tools: Vec<_> = tools
+            .iter()
+            .map(|tool| serde_json::json!({ "name": tool.name, "description": tool.description }))
+            .collect();
+        return serde_json::to_string_pretty(&tools).expect("JSON values serialize");
+    }
+    let width = tools.iter().map(|tool| tool.name.len()).max().unwrap_or(0);
+    tools
+        .iter()
+        .map(|tool| {
+            format!(
+                "{:<width$}  {}",
+                tool.name,
+                tool.description.as_deref().unwrap_or("")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Tool definitions as clients see them in `tools/list`, either as JSON or
+/// as an indented human-readable listing.
+pub fn inspect(service: &ToolService, tool: Option<&str>, json: bool) -> Result<String, String> {
+    let mut tools = service.available_tools();
+    tools.sort_by(|a, b| a.name.cmp(&b.name));
+    if let Some(name) = tool {
+        tools.retain(|candidate| candidate.name == name);
+        if tools.is_empty() {
+            return Err(format!(
+                "unknown tool '{name}'; run `tools list` to see what is available"
+            ));
+        }
+    }
+
+    if json {
+        let value = match tool {
+            Some(_) => serde_json::to_value(&tools[0]),
+            None => serde_json::to_value(&tools),
+        };
+        return Ok(
+            serde_json::to_string_pretty(&value.expect("tools serialize"))
+                .expect("JSON values serialize"),
+        );
+    }
+
+    let sections: Vec<String> = tools
+        .iter()
+        .map(|tool| {
+            let mut lines = vec![tool.name.to_string()];
+            if let Some(description) = &tool.description {
+                lines.push(format!("  {description}"));
+            }
+            let annotations = tool
+                .annotations
+                .as_ref()
+                .and_then(|annotations| serde_json::to_value(annotations).ok())
+                .and_then(|value| value.as_object().cloned())
+                .unwrap_or_default();
+            if !annotations.is_empty() {
+                let hints: Vec<String> = annotations
+                    .iter()
+                    .map(|(name, value)| format!("{name}={value}"))
+                    .collect();
+                lines.push(format!("  annotations: {}", hints.join(", ")));
+            }
+            lines.push(format!(
+                "  input schema:\n{}",
+                indent(
+                    &serde_json::to_string_pretty(&tool.input_schema).expect("schemas serialize")
+                )
+            ));
+            match &tool.output_schema {
+                Some(schema) => lines.push(format!(
+                    "  output schema:\n{}",
+                    indent(&serde_json::to_string_pretty(schema).expect("schemas serialize"))
+                )),
+                None => lines.push("  output schema: none (text content)".to_string()),
+            }
+            lines.join("\n")
+        })
+        .collect();
+    Ok(sections.join("\n\n"))
+}
+
+/// The `config` listing: one line per setting, then any validation errors.
+pub fn effective_config(
+    profile: Option<Profile>,
+    settings: &[EffectiveSetting],
+    errors: &[String],
+    json: bool,
+) -> String {
+    if json {
+        return serde_json::to_string_pretty(&serde_json::json!({
+            "profile": profile,
+            "settings": settings,
+            "errors": errors,
+        }))
+        .expect("settings serialize");
+    }
+
+    let width = settings
+        .iter()
+        .map(|setting| setting.name.len())
+        .max()
+        .unwrap_or(0);
+    let mut lines = vec![format!(
+        "profile: {}",
+        profile
+            .and_then(|profile| profile.to_possible_value())
+            .map_or("none".to_string(), |value| value.get_name().to_string())
+    )];
+    lines.extend(settings.iter().map(|setting| {
+        format!(
+            "{:<width$}  {}  ({})",
+            setting.name,
+            setting.value.as_deref().unwrap_or("-"),
+            setting.source
+        )
+    }));
+    if errors.is_empty() {
+        lines.push("configuration is valid".to_string());
+    } else {
+        lines.push("invalid configuration:".to_string());
+        lines.extend(errors.iter().map(|error| format!("  - {error}")));
+    }
+    lines.join("\n")
+}
+
+/// The `validate-config` report: the file that was read, then whether the
+/// settings it and the environment produce are valid.
+pub fn validate_config(file: Option<&Path>, errors: &[String]) -> String {
+    let mut lines = vec![match file {
+        Some(file) => format!("config file: {}", file.display()),
+        None => "config file: none (environment only)".to_string(),
+    }];
+    if errors.is_empty() {
+        lines.push("configuration is valid".to_string());
+    } else {
+        lines.push("invalid configuration:".to_string());
+        lines.extend(errors.iter().map(|error| format!("  - {error}")));
+    }
+    lines.join("\n")
+}
+
+fn indent(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("    {line}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Export or import the backend selected by `INFERENCO_MCP_STATE_BACKEND`
+/// and return what to print. sled allows one process at a time, so use the
+/// admin endpoints instead while a sled-backed server is running.
+pub fn state(command: &StateCommand) -> Result<String, String> {
+    let backend = StateBackend::from_env()?.open()?;
+    match command {
+        StateCommand::Export { file } => {
+            let snapshot = store::export(backend.as_ref(), Vec::new())?;
+            let json = serde_json::to_string_pretty(&snapshot).expect("snapshots serialize");
+            let Some(file) = file else {
+                return Ok(json);
+            };
+            std::fs::write(file, json)
+                .map_err(|error| format!("cannot write {}: {error}", file.display()))?;
+            Ok(format!(
+                "exported {} counters, {} keys, {} notes, and {} history entries to {}",
+                snapshot.counters.len(),
+                snapshot.kv.len(),
+                snapshot.notes.len(),
+                snapshot.history.len(),
+                file.display()
+            ))
+        }
+        StateCommand::Import { file } => {
+            let text = std::fs::read_to_string(file)
+                .map_err(|error| format!("cannot read {}: {error}", file.display()))?;
+            let snapshot: StateSnapshot = serde_json::from_str(&text)
+                .map_err(|error| format!("{} is not a state archive: {error}", file.display()))?;
+            let summary = store::import(
+                backend.as_ref(),
+                &snapshot,
+                StateLimits::from_env()?,
+                chrono::Utc::now().timestamp_millis(),
+            )
+            .map_err(|error| error.to_string())?;
+            Ok(format!(
+                "imported {} counters, {} keys, {} notes, and {} history entries",
+                summary.counters, summary.kv, summary.notes, summary.history
+            ))
+        }
+    }
+}
+
+/// Manage the key file named by `INFERENCO_MCP_KEYS_FILE` and return what to
+/// print. A running server picks up the changes on its own.
+pub fn keys(command: KeysCommand) -> Result<String, String> {
+    let store =
+        KeyStore::from_env()?.ok_or("set INFERENCO_MCP_KEYS_FILE to the key file to manage")?;
+    let now = chrono::Utc::now().timestamp();
+    match command {
+        KeysCommand::Create {
+            name,
+            scopes,
+            expires,
+        } => {
+            let (key, secret) = store.create(
+                NewKey {
+                    name,
+                    scopes,
+                    expires_at: expires,
+                },
+                now,
+            )?;
+            Ok(format!(
+                "created key {} ({}) for {}, expiring {}\n{secret}\nstore it now; it cannot be shown again",
+                key.id,
+                key.name,
+                key.scopes.join(", "),
+                key.expires_at.map_or("never".to_string(), keys::rfc3339),
+            ))
+        }
+        KeysCommand::List { json } => {
+            let stored = store.list();
+            if json {
+                let summaries: Vec<_> = stored.iter().map(|key| key.summary(now)).collect();
+                return Ok(serde_json::to_string_pretty(&summaries).expect("JSON values serialize"));
+            }
+            if stored.is_empty() {
+                return Ok(format!("no keys in {}", store.path().display()));
+            }
+            let width = stored.iter().map(|key| key.name.len()).max().unwrap_or(0);
+            Ok(stored
+                .iter()
+                .map(|key| {
+                    format!(
+                        "{}  {:<width$}  {:<7}  expires {:<20}  {}",
+                        key.id,
+                        key.name,
+                        key.status(now),
+                        key.expires_at.map_or("never".to_string(), keys::rfc3339),
+                        key.scopes.join(","),
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        KeysCommand::Revoke { id } => match store.revoke(&id, now)? {
+            Some(true) => Ok(format!("revoked key {id}")),
+            Some(false) => Ok(format!("key {id} was already revoked")),
+            None => Err(format!("no key {id} in {}", store.path().display())),
+        },
+    }
+}
+
+/// Query the audit log named by `INFERENCO_MCP_AUDIT_PATH` and return what
+/// to print, newest call first.
+pub fn audit(command: AuditCommand) -> Result<String, String> {
+    let backend =
+        AuditBackend::from_env()?.ok_or("set INFERENCO_MCP_AUDIT_PATH to the audit log to read")?;
+    let AuditCommand::Query {
+        tool,
+        subject,
+        session,
+        outcome,
+        since,
+        limit,
+        json,
+    } = command;
+    let since = since
+        .map(|since| {
+            crate::clock::parse_time(&since, chrono_tz::UTC, None, chrono::Utc::now())
+                .map_err(|error| format!("--since: {error}"))
+        })
+        .transpose()?;
+    let records = backend.open()?.query(&AuditQuery {
+        tool,
+        subject,
+        session,
+        outcome,
+        since,
+        limit,
+    })?;
+    if json {
+        return Ok(serde_json::to_string_pretty(&records).expect("audit records serialize"));
+    }
+    if records.is_empty() {
+        return Ok(format!("no matching calls in {}", backend.path().display()));
+    }
+    let tool_width = records
+        .iter()
+        .map(|record| record.tool.len())
+        .max()
+        .unwrap_or(0);
+    Ok(records
+        .iter()
+        .map(|record| {
+            format!(
+                "{}  {:<tool_width$}  {:<10}  {:>6}ms  {}  {}",
+                record.at,
+                record.tool,
+                record.outcome,
+                record.duration_ms,
+                record.subject.as_deref().unwrap_or("-"),
+                record.arguments,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Run the call described by `args`, on the service `local` builds unless it
+/// is remote, and return what to print. Failed calls, including tool errors,
+/// are returned as `Err` so the caller can exit non-zero.
+pub async fn call(
+    args: &CallArgs,
+    local: impl FnOnce() -> Result<ToolService, String>,
+) -> Result<String, String> {
+    let arguments: serde_json::Value = serde_json::from_str(&args.args)
+        .map_err(|error| format!("--args is not valid JSON: {error}"))?;
+    if !arguments.is_object() {
+        return Err("--args must be a JSON object".to_string());
+    }
+    let result = match &args.remote {
+        Some(url) => call_remote(args, url, arguments).await?,
+        None => local()?
+            .call_tool(&args.tool, arguments)
+            .await
+            .map_err(|error| format!("{} failed: {}", args.tool, error.message))?,
+    };
+    let output = if args.json {
+        serde_json::to_string_pretty(&result).expect("tool results serialize")
+    } else {
+        text_output(&result)
+    };
+    if result.is_error == Some(true) {
+        Err(output)
+    } else {
+        Ok(output)
+    }
+}
+
+async fn call_remote(
+    args: &CallArgs,
+    url: &str,
+    arguments: serde_json::Value,
+) -> Result<CallToolResult, String> {
+    let endpoint = rpc_endpoint(url)?;
+    let client = HttpClient::new(Some(REMOTE_TIMEOUT));
+    let mut request = client.post(&endpoint).json(&serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "tools/call",
+        "params": { "name": args.tool, "arguments": arguments },
+    }));
+    if let Some(key) = &args.api_key {
+        request = request.header(&args.auth_header, key);
+    }
+    let response = client
+        .send("remote", request)
+        .await
+        .map_err(|error| format!("request to {endpoint} failed: {error}"))?;
+    match response.status() {
+        StatusCode::UNAUTHORIZED => {
+            return Err(format!("{endpoint} rejected the request; check --api-key"))
+        }
+        status if !status.is_success() => {
+            return Err(format!("{endpoint} returned {status}"));
+        }
+        _ => {}
+    }
+    let body: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|error| format!("{endpoint} returned invalid JSON: {error}"))?;
+    if let Some(error) = body.get("error") {
+        let message = error["message"].as_str().unwrap_or("unknown error");
+        return Err(format!("{} failed: {message}", args.tool));
+    }
+    serde_json::from_value(body["result"].clone())
+        .map_err(|error| format!("{endpoint} returned an unexpected result: {error}"))
+}
+
+/// `url` itself if it names a path, otherwise its `/rpc` endpoint.
+fn rpc_endpoint(url: &str) -> Result<String, String> {
+    let mut endpoint = Url::parse(&parse_url("--remote", url)?).expect("validated above");
+    if endpoint.path() == "/" {
+        endpoint.set_path("/rpc");
+    }
+    Ok(endpoint.to_string())
+}
+
+fn text_output(result: &CallToolResult) -> String {
+    result
+        .content
+        .iter()
+        .filter_map(|content| match &content.raw {
+            RawContent::Text(text) => Some(text.text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn parses_subcommands_and_defaults_to_serve() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from([
+            "inferenco-mcp",
+            "serve",
+            "--transport",
+            "stdio,http",
+            "--port",
+            "9000",
+        ])
+        .expect("valid serve arguments");
+        match cli.into_command() {
+            Command::Serve(args) => {
+                assert_eq!(args.transport, [Transport::Stdio, Transport::Http]);
+                assert!(args.listeners().uses_port());
+                assert_eq!(args.listeners().http_port(), Ok(9000));
+            }
+            other => panic!("expected serve, got {other:?}"),
+        }
+
+        let cli = Cli::try_parse_from(["inferenco-mcp", "--check"]).expect("valid flags");
+        assert!(matches!(cli.into_command(), Command::Serve(args) if args.check));
+
+        let cli = Cli::try_parse_from([
+            "inferenco-mcp",
+            "call",
+            "roll_dice",
+            "--args",
+            r#"{"sides":20}"#,
+        ])
+        .expect("valid call arguments");
+        assert!(matches!(cli.into_command(), Command::Call(args) if args.tool == "roll_dice"));
+
+        let cli = Cli::try_parse_from(["inferenco-mcp", "--profile", "prod", "config", "--json"])
+            .expect("valid config arguments");
+        assert_eq!(cli.profile, Some(Profile::Prod));
+        assert!(matches!(cli.into_command(), Command::Config { json: true }));
+
+        let cli = Cli::try_parse_from(["inferenco-mcp", "tools", "list", "--json"])
+            .expect("valid tools arguments");
+        assert!(matches!(
+            cli.into_command(),
+            Command::Tools(ToolsCommand::List { json: true })
+        ));
+        let cli = Cli::try_parse_from(["inferenco-mcp", "--config", "mcp.toml", "validate-config"])
+            .expect("valid validate-config arguments");
+        assert_eq!(cli.config.as_deref(), Some(Path::new("mcp.toml")));
+        assert!(matches!(cli.into_command(), Command::ValidateConfig));
+
+        let cli = Cli::try_parse_from([
+            "inferenco-mcp",
+            "keys",
+            "create",
+            "--name",
+            "ci",
+            "--scope",
+            "echo",
+            "--scope",
+            "roll_dice",
+        ])
+        .expect("valid keys arguments");
+        assert!(matches!(
+            cli.into_command(),
+            Command::Keys(KeysCommand::Create { scopes, expires: None, .. }) if scopes == ["echo", "roll_dice"]
+        ));
+
+        let cli = Cli::try_parse_from(["inferenco-mcp", "state", "export"])
+            .expect("valid state arguments");
+        assert!(matches!(
+            cli.into_command(),
+            Command::State(StateCommand::Export { file: None })
+        ));
+
+        let cli = Cli::try_parse_from([
+            "inferenco-mcp",
+            "audit",
+            "query",
+            "--tool",
+            "echo",
+            "--since",
+            "1 hour ago",
+        ])
+        .expect("valid audit arguments");
+        assert!(matches!(
+            cli.into_command(),
+            Command::Audit(AuditCommand::Query { tool: Some(tool), limit: DEFAULT_QUERY_LIMIT, .. })
+                if tool == "echo"
+        ));
+    }
+
+    #[tokio::test]
+    async fn call_runs_tools_in_process() {
+        let Command::Call(mut args) = Cli::parse_from([
+            "inferenco-mcp",
+            "call",
+            "reverse_text",
+            "--args",
+            r#"{"text":"abc"}"#,
+        ])
+        .into_command() else {
+            unreachable!();
+        };
+        let local = || Ok(ToolService::new());
+        assert_eq!(call(&args, local).await.as_deref(), Ok("cba"));
+
+        args.json = true;
+        let output: serde_json::Value =
+            serde_json::from_str(&call(&args, local).await.expect("call succeeds"))
+                .expect("JSON output");
+        assert_eq!(output["content"][0]["text"], "cba");
+
+        args.args = "[]".to_string();
+        assert!(call(&args, local).await.is_err());
+    }
+
+    #[test]
+    fn inspect_shows_schemas_and_annotations() {
+        let service = ToolService::new();
+        let text = inspect(&service, Some("roll_dice"), false).expect("known tool");
+        assert!(text.starts_with("roll_dice\n"));
+        assert!(text.contains("readOnlyHint=true"));
+        assert!(text.contains("\"sides\""));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&inspect(&service, None, true).expect("all tools"))
+                .expect("JSON output");
+        assert_eq!(
+            json.as_array().map(Vec::len),
+            Some(service.available_tools().len())
+        );
+        assert!(inspect(&service, Some("nope"), false).is_err());
+    }
+
+    #[test]
+    fn tool_lists_and_config_reports() {
+        let service = ToolService::new();
+        let text = list_tools(&service, false);
+        assert!(text.lines().any(|line| line.starts_with("roll_dice ")));
+        let json: serde_json::Value =
+            serde_json::from_str(&list_tools(&service, true)).expect("JSON output");
+        assert!(json
+            .as_array()
+            .expect("an array")
+            .iter()
+            .any(|tool| tool["name"] == "roll_dice"));
+
+        assert_eq!(
+            validate_config(None, &[]),
+            "config file: none (environment only)\nconfiguration is valid"
+        );
+        let report = validate_config(
+            Some(Path::new("inferenco-mcp.toml")),
+            &["INFERENCO_MCP_PORT must be a port number".to_string()],
+        );
+        assert!(report.starts_with("config file: inferenco-mcp.toml\ninvalid configuration:"));
+    }
+
+    #[test]
+    fn remote_base_urls_target_the_rpc_endpoint() {
+        assert_eq!(
+            rpc_endpoint("http://localhost:8080").as_deref(),
+            Ok("http://localhost:8080/rpc")
+        );
+        assert_eq!(
+            rpc_endpoint("https://mcp.example.com/v1/rpc").as_deref(),
+            Ok("https://mcp.example.com/v1/rpc")
+        );
+        assert!(rpc_endpoint("localhost:8080").is_err());
+    }
+}
@@ -0,0 +1,464 @@
+//! Typed client for the built-in tools.
+//!
+//! [`InferencoClient`] wraps `tools/call` so Rust callers get typed
+//! arguments and results instead of hand-built JSON. The same methods work
+//! against a [`ToolService`] in this process or a server reached over HTTP
+//! (`POST /rpc`); errors are the [`McpError`] the tool returned, or an
+//! internal error for transport failures and unexpected output.
+
+use crate::{
+    clock::Conversion,
+    dice,
+    fetch::Page,
+    http_client::HttpClient,
+    json_query::{Difference, Match},
+    server::{CodecOp, CounterOp, ToolService},
+};
+use chrono::{DateTime, Utc};
+use rmcp::{
+    model::{CallToolResult, RawContent},
+    ErrorData as McpError,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// Longest a remote call may take, unless set with
+/// [`InferencoClient::with_timeout`].
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Result of [`InferencoClient::kv_set`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct KvStored {
+    pub key: String,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// A value read by [`InferencoClient::kv_get`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KvValue<T> {
+    pub value: T,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Typed access to the built-in tools, in process or over HTTP.
+pub struct InferencoClient {
+    backend: Backend,
+}
+
+enum Backend {
+    Local(Box<ToolService>),
+    Remote(Remote),
+}
+
+struct Remote {
+    http: HttpClient,
+    url: String,
+    auth_header: String,
+    api_key: Option<String>,
+    session: Option<String>,
+    next_id: AtomicU64,
+}
+
+impl InferencoClient {
+    /// Call the tools of `service` directly, in the default namespace.
+    pub fn local(service: ToolService) -> Self {
+        Self {
+            backend: Backend::Local(Box::new(service)),
+        }
+    }
+
+    /// Call the tools of the server at `url` (e.g. `http://127.0.0.1:8080`)
+    /// through its `/rpc` endpoint.
+    pub fn remote(url: impl Into<String>) -> Self {
+        let url = url.into();
+        Self {
+            backend: Backend::Remote(Remote {
+                http: HttpClient::new(Some(DEFAULT_TIMEOUT)),
+                url: format!("{}/rpc", url.trim_end_matches('/')),
+                auth_header: "x-api-key".to_string(),
+                api_key: None,
+                session: None,
+                next_id: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Send `key` with every remote call. Ignored in process.
+    pub fn with_api_key(mut self, key: impl Into<String>) -> Self {
+        if let Backend::Remote(remote) = &mut self.backend {
+            remote.api_key = Some(key.into());
+        }
+        self
+    }
+
+    /// Send the API key in `header` instead of `x-api-key`, matching the
+    /// server's `INFERENCO_MCP_AUTH_HEADER`. Ignored in process.
+    pub fn with_auth_header(mut self, header: impl Into<String>) -> Self {
+        if let Backend::Remote(remote) = &mut self.backend {
+            remote.auth_header = header.into();
+        }
+        self
+    }
+
+    /// Make remote calls in the SSE session `id`, so state without an API
+    /// key is scoped to it. Ignored in process.
+    pub fn with_session(mut self, id: impl Into<String>) -> Self {
+        if let Backend::Remote(remote) = &mut self.backend {
+            remote.session = Some(id.into());
+        }
+        self
+    }
+
+    /// Bound each remote call by `timeout`. Ignored in process.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        if let Backend::Remote(remote) = &mut self.backend {
+            remote.http = HttpClient::new(Some(timeout));
+        }
+        self
+    }
+
+    /// Call any tool by name with raw JSON arguments.
+    pub async fn call_tool(
+        &self,
+        name: &str,
+        arguments: serde_json::Value,
+    ) -> Result<CallToolResult, McpError> {
+        match &self.backend {
+            Backend::Local(service) => service.call_tool(name, arguments).await,
+            Backend::Remote(remote) => remote.call_tool(name, arguments).await,
+        }
+    }
+
+    /// Call `name` and return the text of its result. A result flagged as
+    /// an error becomes an internal error carrying that text.
+    pub async fn call_text(
+        &self,
+        name: &str,
+        arguments: serde_json::Value,
+    ) -> Result<String, McpError> {
+        let result = self.call_tool(name, arguments).await?;
+        let text = result
+            .content
+            .into_iter()
+            .filter_map(|content| match content.raw {
+                RawContent::Text(text) => Some(text.text),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        if result.is_error == Some(true) {
+            return Err(McpError::internal_error(format!("{name}: {text}"), None));
+        }
+        Ok(text)
+    }
+
+    /// Call `name` and parse the JSON text of its result.
+    pub async fn call_json<T: DeserializeOwned>(
+        &self,
+        name: &str,
+        arguments: serde_json::Value,
+    ) -> Result<T, McpError> {
+        let text = self.call_text(name, arguments).await?;
+        serde_json::from_str(&text).map_err(|error| unexpected(name, error))
+    }
+
+    pub async fn echo(&self, message: &str) -> Result<String, McpError> {
+        self.call_text("echo", serde_json::json!({ "message": message }))
+            .await
+    }
+
+    pub async fn reverse_text(&self, text: &str) -> Result<String, McpError> {
+        self.call_text("reverse_text", serde_json::json!({ "text": text }))
+            .await
+    }
+
+    /// Evaluate `expression` exactly. The answer is decimal text, ending
+    /// in `%` when it is a percentage.
+    pub async fn calculate(&self, expression: &str) -> Result<String, McpError> {
+        #[derive(Deserialize)]
+        struct Calculation {
+            result: String,
+        }
+        let calculation: Calculation = self
+            .call_json("calculate", serde_json::json!({ "expression": expression }))
+            .await?;
+        Ok(calculation.result)
+    }
+
+    /// Apply `op` to `input` read as text. Hashes and encodings come back
+    /// as their text form; decoded bytes as text, or hex if not UTF-8.
+    pub async fn codec(&self, op: CodecOp, input: &str) -> Result<String, McpError> {
+        #[derive(Deserialize)]
+        struct Coded {
+            output: String,
+        }
+        let coded: Coded = self
+            .call_json("codec", serde_json::json!({ "op": op, "input": input }))
+            .await?;
+        Ok(coded.output)
+    }
+
+    /// Values in `document` selected by a JSONPath `query`, with their
+    /// paths; at most 500, as the tool truncates.
+    pub async fn json_query(
+        &self,
+        document: &serde_json::Value,
+        query: &str,
+    ) -> Result<Vec<Match>, McpError> {
+        #[derive(Deserialize)]
+        struct Selection {
+            matches: Vec<Match>,
+        }
+        let selection: Selection = self
+            .call_json(
+                "json_query",
+                serde_json::json!({ "document": document, "query": query }),
+            )
+            .await?;
+        Ok(selection.matches)
+    }
+
+    /// What changed from `before` to `after`, by JSON Pointer.
+    pub async fn json_diff(
+        &self,
+        before: &serde_json::Value,
+        after: &serde_json::Value,
+    ) -> Result<Vec<Difference>, McpError> {
+        #[derive(Deserialize)]
+        struct Comparison {
+            differences: Vec<Difference>,
+        }
+        let comparison: Comparison = self
+            .call_json(
+                "json_query",
+                serde_json::json!({ "mode": "diff", "document": before, "other": after }),
+            )
+            .await?;
+        Ok(comparison.differences)
+    }
+
+    /// Roll dice in notation such as `3d6+2` or `d20`.
+    pub async fn roll_dice(&self, notation: &str) -> Result<dice::Outcome, McpError> {
+        self.call_json("roll_dice", serde_json::json!({ "notation": notation }))
+            .await
+    }
+
+    /// Fetch `url` through `fetch_url`, which the server must have enabled
+    /// for its host.
+    pub async fn fetch_url(&self, url: &str) -> Result<Page, McpError> {
+        self.call_json("fetch_url", serde_json::json!({ "url": url }))
+            .await
+    }
+
+    pub async fn current_time(&self) -> Result<DateTime<Utc>, McpError> {
+        let text = self
+            .call_text("current_time", serde_json::json!({}))
+            .await?;
+        DateTime::parse_from_rfc3339(&text)
+            .map(|time| time.with_timezone(&Utc))
+            .map_err(|error| unexpected("current_time", error))
+    }
+
+    /// Read `time` (RFC 3339, an epoch, or an expression such as
+    /// `3 hours ago`) and express it in the IANA `timezone`, or UTC.
+    pub async fn convert_time(
+        &self,
+        time: &str,
+        timezone: Option<&str>,
+    ) -> Result<Conversion, McpError> {
+        self.call_json(
+            "convert_time",
+            serde_json::json!({ "time": time, "timezone": timezone }),
+        )
+        .await
+    }
+
+    /// Add one to the session's `increment` counter and return its new value.
+    pub async fn increment(&self) -> Result<i64, McpError> {
+        let text = self.call_text("increment", serde_json::json!({})).await?;
+        text.parse().map_err(|error| unexpected("increment", error))
+    }
+
+    /// Apply `op` to the counter `name` and return its value afterwards.
+    /// `amount` is only used by [`CounterOp::Add`]; use [`Self::counters`]
+    /// to list them.
+    pub async fn counter(
+        &self,
+        name: &str,
+        op: CounterOp,
+        amount: Option<i64>,
+    ) -> Result<i64, McpError> {
+        #[derive(Deserialize)]
+        struct Counter {
+            value: i64,
+        }
+        let arguments = serde_json::json!({ "op": op, "name": name, "amount": amount });
+        let counter: Counter = self.call_json("counter", arguments).await?;
+        Ok(counter.value)
+    }
+
+    /// Every counter with its value.
+    pub async fn counters(&self) -> Result<BTreeMap<String, i64>, McpError> {
+        #[derive(Deserialize)]
+        struct Counters {
+            counters: BTreeMap<String, i64>,
+        }
+        let arguments = serde_json::json!({ "op": CounterOp::List });
+        let list: Counters = self.call_json("counter", arguments).await?;
+        Ok(list.counters)
+    }
+
+    /// Store `value` under `key`, forgetting it after `ttl` when given.
+    pub async fn kv_set(
+        &self,
+        key: &str,
+        value: &impl Serialize,
+        ttl: Option<Duration>,
+    ) -> Result<KvStored, McpError> {
+        let value = serde_json::to_value(value)
+            .map_err(|error| McpError::invalid_params(error.to_string(), None))?;
+        let arguments = serde_json::json!({
+            "key": key,
+            "value": value,
+            "ttl_seconds": ttl.map(|ttl| ttl.as_secs().max(1)),
+        });
+        self.call_json("kv_set", arguments).await
+    }
+
+    /// The value under `key`, or `None` when nothing is stored there.
+    pub async fn kv_get<T: DeserializeOwned>(
+        &self,
+        key: &str,
+    ) -> Result<Option<KvValue<T>>, McpError> {
+        #[derive(Deserialize)]
+        struct Lookup {
+            found: bool,
+            #[serde(default)]
+            value: serde_json::Value,
+            #[serde(default)]
+            expires_at: Option<DateTime<Utc>>,
+        }
+        let lookup: Lookup = self
+            .call_json("kv_get", serde_json::json!({ "key": key }))
+            .await?;
+        if !lookup.found {
+            return Ok(None);
+        }
+        let value =
+            serde_json::from_value(lookup.value).map_err(|error| unexpected("kv_get", error))?;
+        Ok(Some(KvValue {
+            value,
+            expires_at: lookup.expires_at,
+        }))
+    }
+
+    /// Delete `key`, returning whether anything was stored there.
+    pub async fn kv_delete(&self, key: &str) -> Result<bool, McpError> {
+        #[derive(Deserialize)]
+        struct Deleted {
+            deleted: bool,
+        }
+        let deleted: Deleted = self
+            .call_json("kv_delete", serde_json::json!({ "key": key }))
+            .await?;
+        Ok(deleted.deleted)
+    }
+}
+
+impl Remote {
+    async fn call_tool(
+        &self,
+        name: &str,
+        arguments: serde_json::Value,
+    ) -> Result<CallToolResult, McpError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut request = self.http.post(&self.url).json(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "tools/call",
+            "params": { "name": name, "arguments": arguments },
+        }));
+        if let Some(key) = &self.api_key {
+            request = request.header(self.auth_header.as_str(), key);
+        }
+        if let Some(session) = &self.session {
+            request = request.header("mcp-session-id", session);
+        }
+        let response = self
+            .http
+            .send("inferenco-mcp", request)
+            .await
+            .map_err(|error| McpError::internal_error(error.to_string(), None))?;
+        let status = response.status();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|error| McpError::internal_error(error.to_string(), None))?;
+        #[derive(Deserialize)]
+        struct Reply {
+            result: Option<CallToolResult>,
+            error: Option<McpError>,
+        }
+        match serde_json::from_slice::<Reply>(&body) {
+            Ok(Reply {
+                error: Some(error), ..
+            }) => Err(error),
+            Ok(Reply {
+                result: Some(result),
+                ..
+            }) if status.is_success() => Ok(result),
+            _ => Err(McpError::internal_error(
+                format!("{name}: HTTP {status} from {}", self.url),
+                None,
+            )),
+        }
+    }
+}
+
+fn unexpected(tool: &str, detail: impl std::fmt::Display) -> McpError {
+    McpError::internal_error(format!("unexpected {tool} output: {detail}"), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn typed_calls_round_trip_in_process() {
+        let client = InferencoClient::local(ToolService::new());
+        assert_eq!(client.echo("hi").await.unwrap(), "hi");
+        let roll = client.roll_dice("d20").await.unwrap();
+        assert!(
+            roll.notation == "1d20" && (1..=20).contains(&roll.total),
+            "{roll:?}"
+        );
+
+        let stored = client.kv_set("k", &vec![1, 2], None).await.unwrap();
+        assert_eq!(stored.key, "k");
+        let value = client.kv_get::<Vec<u8>>("k").await.unwrap().unwrap();
+        assert_eq!(value.value, vec![1, 2]);
+        assert!(client.kv_delete("k").await.unwrap());
+        assert_eq!(client.kv_get::<Vec<u8>>("k").await.unwrap(), None);
+
+        assert_eq!(
+            client.counter("c", CounterOp::Add, Some(5)).await.unwrap(),
+            5
+        );
+        assert_eq!(client.counters().await.unwrap().get("c"), Some(&5));
+
+        let document = serde_json::json!({ "a": [1, 2] });
+        let matches = client.json_query(&document, "$.a[1]").await.unwrap();
+        assert_eq!(matches[0].path, "$.a[1]");
+        let differences = client
+            .json_diff(&document, &serde_json::json!({ "a": [1] }))
+            .await
+            .unwrap();
+        assert_eq!(differences[0].path, "/a/1");
+        let error = client.counter("", CounterOp::Get, None).await.unwrap_err();
+        assert_eq!(error.code, rmcp::model::ErrorCode::INVALID_PARAMS);
+    }
+}
@@ -0,0 +1,195 @@
+//! Server log messages for MCP clients (`logging/setLevel` and
+//! `notifications/message`).
+//!
+//! Every session carries a [`ClientLog`] in its [`SessionState`]. Once the
+//! client picks a level with `logging/setLevel`, [`ClientLogLayer`] turns the
+//! tracing events emitted while serving that session's requests, at or above
+//! the level, into `notifications/message` on the session's own channel: the
+//! stdio or TCP peer, an `/sse` or `/mcp` event stream, or a `/ws`
+//! connection. Clients never see events from other sessions' requests, and
+//! only events the server logs at all reach them, so `debug` messages need
+//! `INFERENCO_MCP_LOG_LEVEL=debug` too.
+
+use crate::{crash_report::FieldVisitor, sessions::SessionState};
+use rmcp::model::{LoggingLevel, LoggingMessageNotificationParam};
+use serde_json::Value;
+use std::sync::atomic::{AtomicU8, Ordering};
+use tokio::sync::broadcast;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::{layer::Context, Layer};
+
+/// Messages a slow connection may fall behind by before it skips some.
+const MESSAGE_BUFFER: usize = 256;
+/// Rank of a log no level was set for.
+const OFF: u8 = 0;
+
+/// The level a session asked for and where its messages go.
+pub struct ClientLog {
+    /// [`rank`] of the lowest level sent, or [`OFF`].
+    level: AtomicU8,
+    messages: broadcast::WeakSender<Value>,
+    /// Keeps the channel of a log that is not a registered session's open.
+    _channel: Option<broadcast::Sender<Value>>,
+}
+
+impl Default for ClientLog {
+    fn default() -> Self {
+        let channel = broadcast::channel(MESSAGE_BUFFER).0;
+        Self {
+            level: AtomicU8::new(OFF),
+            messages: channel.downgrade(),
+            _channel: Some(channel),
+        }
+    }
+}
+
+impl ClientLog {
+    /// A log with a channel of its own, as for stdio.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A log sent on `messages`, a registered session's notifications,
+    /// without keeping them open after the session ends.
+    pub fn sent_on(messages: &broadcast::Sender<Value>) -> Self {
+        Self {
+            level: AtomicU8::new(OFF),
+            messages: messages.downgrade(),
+            _channel: None,
+        }
+    }
+
+    /// The lowest level sent, or `None` until the client sets one.
+    pub fn level(&self) -> Option<LoggingLevel> {
+        LEVELS
+            .iter()
+            .copied()
+            .find(|level| rank(*level) == self.level.load(Ordering::Acquire))
+    }
+
+    pub fn set_level(&self, level: LoggingLevel) {
+        self.level.store(rank(level), Ordering::Release);
+    }
+
+    /// Whether messages at `level` are sent.
+    pub fn is_enabled(&self, level: LoggingLevel) -> bool {
+        let threshold = self.level.load(Ordering::Acquire);
+        threshold != OFF && rank(level) >= threshold
+    }
+
+    /// Messages as JSON-RPC notifications, or `None` once the session ended.
+    pub fn messages(&self) -> Option<broadcast::Receiver<Value>> {
+        Some(self.messages.upgrade()?.subscribe())
+    }
+
+    /// Send a message if its level is enabled. Returns whether anyone was
+    /// listening.
+    pub fn send(&self, message: LoggingMessageNotificationParam) -> bool {
+        if !self.is_enabled(message.level) {
+            return false;
+        }
+        let Some(messages) = self.messages.upgrade() else {
+            return false;
+        };
+        messages
+            .send(serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/message",
+                "params": message
+            }))
+            .is_ok()
+    }
+}
+
+const LEVELS: [LoggingLevel; 8] = [
+    LoggingLevel::Debug,
+    LoggingLevel::Info,
+    LoggingLevel::Notice,
+    LoggingLevel::Warning,
+    LoggingLevel::Error,
+    LoggingLevel::Critical,
+    LoggingLevel::Alert,
+    LoggingLevel::Emergency,
+];
+
+/// Position of `level` in [`LEVELS`], counting from one.
+fn rank(level: LoggingLevel) -> u8 {
+    LEVELS
+        .iter()
+        .position(|known| *known == level)
+        .map_or(OFF, |index| index as u8 + 1)
+}
+
+/// The MCP level a tracing level is sent as.
+fn mcp_level(level: Level) -> LoggingLevel {
+    match level {
+        Level::ERROR => LoggingLevel::Error,
+        Level::WARN => LoggingLevel::Warning,
+        Level::INFO => LoggingLevel::Info,
+        _ => LoggingLevel::Debug,
+    }
+}
+
+/// Tracing layer that sends events to the [`ClientLog`] of the session
+/// being served, if any.
+pub struct ClientLogLayer;
+
+impl<S: Subscriber> Layer<S> for ClientLogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let Some(state) = SessionState::current() else {
+            return;
+        };
+        let metadata = event.metadata();
+        let level = mcp_level(*metadata.level());
+        if !state.log().is_enabled(level) {
+            return;
+        }
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let mut data = visitor.fields;
+        data.insert("message".to_string(), visitor.message.into());
+        state.log().send(LoggingMessageNotificationParam {
+            level,
+            logger: Some(metadata.target().to_string()),
+            data: Value::Object(data),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sessions;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[tokio::test]
+    async fn sessions_receive_their_own_events_at_their_level() {
+        let subscriber = tracing_subscriber::registry().with(ClientLogLayer);
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let state = SessionState::default();
+        let mut messages = state.log().messages().expect("the log owns its channel");
+        let log_in_session = |state: SessionState| {
+            sessions::in_session(Some(state), async {
+                tracing::info!("call started");
+                tracing::warn!(tool = "echo", "upstream slow");
+            })
+        };
+
+        log_in_session(state.clone()).await;
+        assert!(messages.try_recv().is_err());
+        assert_eq!(state.log().level(), None);
+
+        state.log().set_level(LoggingLevel::Warning);
+        log_in_session(state.clone()).await;
+        log_in_session(SessionState::default()).await;
+        tracing::error!("not in a session");
+        let message = messages.try_recv().expect("the warning is sent");
+        assert_eq!(message["method"], "notifications/message");
+        assert_eq!(message["params"]["level"], "warning");
+        assert_eq!(message["params"]["logger"], module_path!());
+        assert_eq!(message["params"]["data"]["message"], "upstream slow");
+        assert_eq!(message["params"]["data"]["tool"], "echo");
+        assert!(messages.try_recv().is_err());
+        assert_eq!(state.log().level(), Some(LoggingLevel::Warning));
+    }
+}
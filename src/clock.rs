@@ -0,0 +1,364 @@
+//! Parsing, converting, and formatting times for the clock tools.
+//!
+//! [`parse_time`] reads what people and other tools tend to hand over:
+//! RFC 3339, plain dates and times in a given zone, Unix epochs in seconds
+//! or milliseconds, and relative expressions like `3 hours ago` or
+//! `in 2 weeks`. Zones are IANA names from `chrono-tz`.
+
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, Months, NaiveDate, NaiveDateTime, Offset, SecondsFormat, TimeDelta, TimeZone, Utc,
+};
+use chrono_tz::Tz;
+use rmcp::schemars::{self, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+/// Longest accepted time expression or format string, in characters.
+pub const MAX_INPUT_CHARS: usize = 200;
+
+/// Epochs with at least this many integer digits are read as milliseconds;
+/// as seconds they would fall after the year 5000.
+const MILLIS_DIGITS: usize = 12;
+
+/// A point in time as seen from one zone.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Conversion {
+    /// RFC 3339 with the zone's offset.
+    pub rfc3339: String,
+    /// RFC 3339 in UTC, with a `Z`.
+    pub utc: String,
+    pub timezone: String,
+    /// Such as `CET` or `JST`; some zones only have a numeric one.
+    pub abbreviation: String,
+    pub utc_offset: String,
+    pub epoch_seconds: i64,
+    pub epoch_millis: i64,
+    /// How far from now, such as `3 hours ago` or `in 2 days`.
+    pub relative: String,
+    /// The time rendered with the requested format string.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub formatted: Option<String>,
+}
+
+/// An IANA zone name such as `Europe/Berlin`, or `UTC`.
+pub fn parse_zone(name: &str) -> Result<Tz, String> {
+    name.trim().parse().map_err(|_| {
+        format!("unknown timezone '{name}'; use an IANA name such as Europe/Berlin or UTC")
+    })
+}
+
+/// Read `input` as a time. Dates and times without an offset are in
+/// `zone`; `input_format`, a strftime string, overrides the guessing.
+pub fn parse_time(
+    input: &str,
+    zone: Tz,
+    input_format: Option<&str>,
+    now: DateTime<Utc>,
+) -> Result<DateTime<Utc>, String> {
+    let input = input.trim();
+    if input.chars().count() > MAX_INPUT_CHARS {
+        return Err(format!("time is longer than {MAX_INPUT_CHARS} characters"));
+    }
+    if let Some(format) = input_format {
+        checked_items(format)?;
+        if let Ok(time) = DateTime::parse_from_str(input, format) {
+            return Ok(time.with_timezone(&Utc));
+        }
+        if let Ok(naive) = NaiveDateTime::parse_from_str(input, format) {
+            return in_zone(naive, zone);
+        }
+        return NaiveDate::parse_from_str(input, format)
+            .map_err(|error| format!("'{input}' does not match '{format}': {error}"))
+            .and_then(|date| in_zone(date.and_time(Default::default()), zone));
+    }
+    if let Some(time) = parse_epoch(input)? {
+        return Ok(time);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(input) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    for format in [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(input, format) {
+            return in_zone(naive, zone);
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        return in_zone(date.and_time(Default::default()), zone);
+    }
+    parse_relative(input, now)
+}
+
+/// Describe `time` from `zone`, rendering `format` too when given.
+pub fn convert(
+    time: DateTime<Utc>,
+    zone: Tz,
+    format: Option<&str>,
+    now: DateTime<Utc>,
+) -> Result<Conversion, String> {
+    let local = time.with_timezone(&zone);
+    Ok(Conversion {
+        rfc3339: local.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        utc: time.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        timezone: zone.name().to_string(),
+        abbreviation: local.format("%Z").to_string(),
+        utc_offset: local.offset().fix().to_string(),
+        epoch_seconds: time.timestamp(),
+        epoch_millis: time.timestamp_millis(),
+        relative: describe_relative(time, now),
+        formatted: format
+            .map(|format| format_time(&local, format))
+            .transpose()?,
+    })
+}
+
+/// Render `time` with a strftime `format`, rejecting unknown specifiers
+/// rather than panicking on them.
+pub fn format_time(time: &DateTime<Tz>, format: &str) -> Result<String, String> {
+    let items = checked_items(format)?;
+    Ok(time.format_with_items(items.into_iter()).to_string())
+}
+
+fn checked_items(format: &str) -> Result<Vec<Item<'_>>, String> {
+    if format.chars().count() > MAX_INPUT_CHARS {
+        return Err(format!(
+            "format is longer than {MAX_INPUT_CHARS} characters"
+        ));
+    }
+    let items: Vec<Item<'_>> = StrftimeItems::new(format).collect();
+    if items.iter().any(|item| matches!(item, Item::Error)) {
+        return Err(format!("'{format}' is not a valid strftime format"));
+    }
+    Ok(items)
+}
+
+/// The one instant `naive` names in `zone`; the earlier one when clocks
+/// fall back.
+fn in_zone(naive: NaiveDateTime, zone: Tz) -> Result<DateTime<Utc>, String> {
+    zone.from_local_datetime(&naive)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+        .ok_or_else(|| format!("{naive} does not exist in {}; clocks skip it", zone.name()))
+}
+
+/// Seconds, optionally fractional, or milliseconds when long enough.
+fn parse_epoch(input: &str) -> Result<Option<DateTime<Utc>>, String> {
+    let digits = input.strip_prefix('-').unwrap_or(input);
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let numeric = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    if whole.is_empty() || !numeric(whole) || !numeric(fraction) || fraction.len() > 9 {
+        return Ok(None);
+    }
+    let out_of_range = || format!("epoch {input} is out of range");
+    let sign = if input.starts_with('-') { -1 } else { 1 };
+    let whole: i64 = whole.parse().map_err(|_| out_of_range())?;
+    if digits.len() >= MILLIS_DIGITS && fraction.is_empty() {
+        return DateTime::from_timestamp_millis(sign * whole)
+            .map(Some)
+            .ok_or_else(out_of_range);
+    }
+    let nanos = format!("{fraction:0<9}").parse::<i64>().unwrap_or(0);
+    let (seconds, nanos) = if sign < 0 && nanos > 0 {
+        (-whole - 1, 1_000_000_000 - nanos)
+    } else {
+        (sign * whole, nanos)
+    };
+    DateTime::from_timestamp(seconds, nanos as u32)
+        .map(Some)
+        .ok_or_else(out_of_range)
+}
+
+enum Span {
+    Months(u32),
+    Delta(TimeDelta),
+}
+
+/// `now`, `today`, `yesterday`, `tomorrow`, `3 hours ago`, `in 2 days`,
+/// `1h 30m ago`, and `an hour ago`.
+fn parse_relative(input: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let lower = input.to_lowercase();
+    match lower.as_str() {
+        "now" | "today" => return Ok(now),
+        "yesterday" => return Ok(now - TimeDelta::days(1)),
+        "tomorrow" => return Ok(now + TimeDelta::days(1)),
+        _ => {}
+    }
+    let unrecognized = || {
+        format!(
+            "could not read '{input}' as a time; try RFC 3339, YYYY-MM-DD HH:MM, \
+             an epoch, or an expression like '3 hours ago'"
+        )
+    };
+    let mut words = split_amounts(&lower);
+    let future = match (
+        words.first().map(String::as_str),
+        words.last().map(String::as_str),
+    ) {
+        (Some("in"), _) => {
+            words.remove(0);
+            true
+        }
+        (_, Some("ago")) => {
+            words.pop();
+            false
+        }
+        _ => return Err(unrecognized()),
+    };
+    if words.is_empty() || !words.len().is_multiple_of(2) {
+        return Err(unrecognized());
+    }
+    let mut time = now;
+    for pair in words.chunks(2) {
+        let amount: u32 = match pair[0].as_str() {
+            "a" | "an" => 1,
+            amount => amount.parse().map_err(|_| unrecognized())?,
+        };
+        let span = match pair[1].trim_end_matches(',') {
+            "s" | "sec" | "secs" | "second" | "seconds" => {
+                Span::Delta(TimeDelta::seconds(amount.into()))
+            }
+            "m" | "min" | "mins" | "minute" | "minutes" => {
+                Span::Delta(TimeDelta::minutes(amount.into()))
+            }
+            "h" | "hr" | "hrs" | "hour" | "hours" => Span::Delta(TimeDelta::hours(amount.into())),
+            "d" | "day" | "days" => Span::Delta(TimeDelta::days(amount.into())),
+            "w" | "wk" | "wks" | "week" | "weeks" => Span::Delta(TimeDelta::weeks(amount.into())),
+            "mo" | "month" | "months" => Span::Months(amount),
+            "y" | "yr" | "yrs" | "year" | "years" => {
+                Span::Months(amount.checked_mul(12).ok_or_else(unrecognized)?)
+            }
+            _ => return Err(unrecognized()),
+        };
+        let shifted = match (span, future) {
+            (Span::Months(months), true) => time.checked_add_months(Months::new(months)),
+            (Span::Months(months), false) => time.checked_sub_months(Months::new(months)),
+            (Span::Delta(delta), true) => time.checked_add_signed(delta),
+            (Span::Delta(delta), false) => time.checked_sub_signed(delta),
+        };
+        time = shifted.ok_or_else(|| format!("'{input}' is out of range"))?;
+    }
+    Ok(time)
+}
+
+/// Words, with amounts split from attached units (`3h` becomes `3`, `h`).
+fn split_amounts(input: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for word in input.split_whitespace() {
+        match word.find(|c: char| !c.is_ascii_digit()) {
+            Some(split) if split > 0 => {
+                words.push(word[..split].to_string());
+                words.push(word[split..].to_string());
+            }
+            _ => words.push(word.to_string()),
+        }
+    }
+    words
+}
+
+/// The largest whole unit between `time` and `now`.
+pub fn describe_relative(time: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let seconds = (time - now).num_seconds();
+    let magnitude = seconds.unsigned_abs();
+    if magnitude == 0 {
+        return "now".to_string();
+    }
+    let (amount, unit) = [
+        (86_400, "day"),
+        (3_600, "hour"),
+        (60, "minute"),
+        (1, "second"),
+    ]
+    .into_iter()
+    .find(|(size, _)| magnitude >= *size)
+    .map(|(size, unit)| (magnitude / size, unit))
+    .unwrap_or((magnitude, "second"));
+    let plural = if amount == 1 { "" } else { "s" };
+    if seconds < 0 {
+        format!("{amount} {unit}{plural} ago")
+    } else {
+        format!("in {amount} {unit}{plural}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-03-10T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn parse(input: &str, zone: &str) -> Result<String, String> {
+        parse_time(input, parse_zone(zone).unwrap(), None, now())
+            .map(|time| time.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+    }
+
+    #[test]
+    fn reads_epochs_local_times_and_relative_expressions() {
+        assert_eq!(parse("1700000000", "UTC").unwrap(), "2023-11-14T22:13:20Z");
+        assert_eq!(
+            parse("1700000000123", "UTC").unwrap(),
+            "2023-11-14T22:13:20.123Z"
+        );
+        assert_eq!(parse("-1.5", "UTC").unwrap(), "1969-12-31T23:59:58.500Z");
+        assert_eq!(
+            parse("2024-07-01 09:30", "Europe/Berlin").unwrap(),
+            "2024-07-01T07:30:00Z"
+        );
+        assert_eq!(
+            parse("2024-01-01", "Asia/Tokyo").unwrap(),
+            "2023-12-31T15:00:00Z"
+        );
+        assert_eq!(
+            parse("2024-01-01T00:00:00+02:00", "Asia/Tokyo").unwrap(),
+            "2023-12-31T22:00:00Z"
+        );
+        assert_eq!(parse("3 hours ago", "UTC").unwrap(), "2024-03-10T09:00:00Z");
+        assert_eq!(parse("in 1h 30m", "UTC").unwrap(), "2024-03-10T13:30:00Z");
+        assert_eq!(parse("a month ago", "UTC").unwrap(), "2024-02-10T12:00:00Z");
+        // 02:30 is skipped when New York springs forward on this day.
+        assert_eq!(
+            parse("2024-03-10 02:30", "America/New_York").unwrap_err(),
+            "2024-03-10 02:30:00 does not exist in America/New_York; clocks skip it"
+        );
+        assert!(parse("next tuesday", "UTC")
+            .unwrap_err()
+            .starts_with("could not read"));
+        assert!(parse_zone("Mars/Olympus").is_err());
+    }
+
+    #[test]
+    fn converts_into_zones_with_formats_and_relative_descriptions() {
+        let time = parse_time("2024-03-10T09:00:00Z", Tz::UTC, None, now()).unwrap();
+        let converted = convert(
+            time,
+            parse_zone("Asia/Kolkata").unwrap(),
+            Some("%d %b %Y %H:%M %Z"),
+            now(),
+        )
+        .unwrap();
+        assert_eq!(converted.rfc3339, "2024-03-10T14:30:00+05:30");
+        assert_eq!(converted.utc_offset, "+05:30");
+        assert_eq!(converted.abbreviation, "IST");
+        assert_eq!(converted.relative, "3 hours ago");
+        assert_eq!(
+            converted.formatted.as_deref(),
+            Some("10 Mar 2024 14:30 IST")
+        );
+        assert_eq!(converted.epoch_millis, 1_710_061_200_000);
+
+        assert_eq!(
+            describe_relative(now() + TimeDelta::days(2), now()),
+            "in 2 days"
+        );
+        assert_eq!(describe_relative(now(), now()), "now");
+        let error = convert(time, Tz::UTC, Some("%Q"), now()).unwrap_err();
+        assert_eq!(error, "'%Q' is not a valid strftime format");
+    }
+}
@@ -0,0 +1,114 @@
+//! Byte encodings and hashes for the `codec` tool.
+//!
+//! Decoders are lenient about the forms people paste: hex may carry a `0x`
+//! prefix, and base64 may use either alphabet, with or without padding.
+
+use base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine,
+};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC};
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
+
+/// Everything but the RFC 3986 unreserved characters is escaped.
+const URL_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+pub fn encode_hex(bytes: &[u8]) -> String {
+    hex::encode(bytes)
+}
+
+/// Hex digits, optionally prefixed with `0x`, in either case.
+pub fn decode_hex(text: &str) -> Result<Vec<u8>, String> {
+    let text = text.trim();
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+    hex::decode(digits).map_err(|error| format!("invalid hex: {error}"))
+}
+
+/// Standard alphabet, padded.
+pub fn encode_base64(bytes: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+/// Standard or URL-safe base64; padding is optional and whitespace, as in
+/// wrapped PEM-style text, is ignored.
+pub fn decode_base64(text: &str) -> Result<Vec<u8>, String> {
+    let compact: String = text.split_whitespace().collect();
+    let alphabet = if compact.contains(['-', '_']) {
+        &alphabet::URL_SAFE
+    } else {
+        &alphabet::STANDARD
+    };
+    let config =
+        GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent);
+    GeneralPurpose::new(alphabet, config)
+        .decode(compact)
+        .map_err(|error| format!("invalid base64: {error}"))
+}
+
+/// Percent-encode for use as a URL path segment or query value.
+pub fn encode_url(bytes: &[u8]) -> String {
+    percent_encoding::percent_encode(bytes, URL_COMPONENT).to_string()
+}
+
+/// Undo percent-encoding; `+` is read as a space, as in form data.
+pub fn decode_url(text: &str) -> Vec<u8> {
+    percent_encoding::percent_decode_str(&text.replace('+', " ")).collect()
+}
+
+pub fn sha256(bytes: &[u8]) -> [u8; 32] {
+    Sha256::digest(bytes).into()
+}
+
+/// Keccak-256 as Ethereum uses it, which pads differently from SHA3-256.
+pub fn keccak256(bytes: &[u8]) -> [u8; 32] {
+    Keccak256::digest(bytes).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_match_published_vectors() {
+        assert_eq!(
+            encode_hex(&sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            encode_hex(&keccak256(b"")),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        // The selector of ERC-20 `transfer`.
+        assert_eq!(
+            encode_hex(&keccak256(b"transfer(address,uint256)")[..4]),
+            "a9059cbb"
+        );
+    }
+
+    #[test]
+    fn decoders_accept_common_variants_and_reject_garbage() {
+        assert_eq!(decode_hex("0xDEADbeef").unwrap(), [0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(
+            decode_hex("abc").unwrap_err(),
+            "invalid hex: Odd number of digits"
+        );
+        let bytes = [0xfb, 0xff, 0x01];
+        assert_eq!(encode_base64(&bytes), "+/8B");
+        assert_eq!(decode_base64("+/8B").unwrap(), bytes);
+        assert_eq!(decode_base64("-_8B").unwrap(), bytes);
+        assert_eq!(decode_base64("aGk").unwrap(), b"hi");
+        assert_eq!(decode_base64("aG\n k=").unwrap(), b"hi");
+        assert!(decode_base64("a*b").is_err());
+        assert_eq!(encode_url("a b/c?d=é".as_bytes()), "a%20b%2Fc%3Fd%3D%C3%A9");
+        assert_eq!(decode_url("a+b%2Fc%C3%A9"), "a b/cé".as_bytes());
+    }
+}
@@ -0,0 +1,753 @@
+//! Typed server settings, read from the environment once at startup.
+//!
+//! Each module still owns the parsing of its own variables; [`Settings::load`]
+//! runs them all, collects every problem so the server refuses to start with
+//! a list of offending variables instead of quietly running on defaults, and
+//! hands the result to the transports and [`ToolService`](crate::server::ToolService).
+
+use crate::{
+    alerts::AlertConfig,
+    audit::AuditBackend,
+    auth::{jwt::JwtSettings, keys::KeyStore, oauth::OAuthSettings, policy::Policy},
+    cache::CacheLimits,
+    cors::CorsSettings,
+    crash_report::CrashSettings,
+    fetch::FetchSettings,
+    health::HealthSettings,
+    http_client::HttpClientSettings,
+    ip_filter::IpFilter,
+    limiter::ConcurrencyLimits,
+    logging::LogSettings,
+    rate_limit::RateLimits,
+    rebinding,
+    reload::{EnvFile, ReloadableConfig},
+    scheduler::{self, ScheduledJob},
+    server::DEFAULT_TOOLS_PAGE_SIZE,
+    slow_calls::SlowCallSettings,
+    store::{HistoryLimits, KvLimits, NoteLimits, StateBackend},
+    tls::TlsSettings,
+};
+use reqwest::{header::HeaderName, Url};
+use serde::Serialize;
+use std::{
+    env,
+    net::{IpAddr, SocketAddr},
+    num::NonZeroU16,
+    path::PathBuf,
+    str::FromStr,
+    time::Duration,
+};
+
+/// Every `INFERENCO_MCP_*` variable the server reads at runtime.
+pub const KNOWN_VARS: &[&str] = &[
+    "INFERENCO_MCP_PROFILE",
+    "INFERENCO_MCP_CONFIG",
+    "INFERENCO_MCP_TRANSPORT",
+    "INFERENCO_MCP_PORT",
+    "INFERENCO_MCP_TCP_ADDR",
+    "INFERENCO_MCP_LOG_LEVEL",
+    "INFERENCO_MCP_LOG_FORMAT",
+    "INFERENCO_MCP_LOG_FILE",
+    "INFERENCO_MCP_LOG_ROTATION",
+    "INFERENCO_MCP_LOG_MAX_FILES",
+    "INFERENCO_MCP_LOG_MAX_BYTES",
+    "INFERENCO_MCP_CRASH_WEBHOOK",
+    "INFERENCO_MCP_ENVIRONMENT",
+    "INFERENCO_MCP_ALERT_WEBHOOK",
+    "INFERENCO_MCP_ALERT_FORMAT",
+    "INFERENCO_MCP_ALERT_SECRET",
+    "INFERENCO_MCP_ALERT_ERROR_RATE",
+    "INFERENCO_MCP_ALERT_MIN_CALLS",
+    "INFERENCO_MCP_ALERT_UPSTREAM_FAILURES",
+    "INFERENCO_MCP_ALERT_WINDOW_SECS",
+    "INFERENCO_MCP_ALERT_COOLDOWN_SECS",
+    "INFERENCO_MCP_AUTH_ENABLED",
+    "INFERENCO_MCP_API_KEYS",
+    "INFERENCO_MCP_AUTH_HEADER",
+    "INFERENCO_MCP_ADMIN_TOKEN",
+    "INFERENCO_MCP_KEYS_FILE",
+    "INFERENCO_MCP_POLICY_FILE",
+    "INFERENCO_MCP_JWT_ISSUER",
+    "INFERENCO_MCP_JWT_AUDIENCE",
+    "INFERENCO_MCP_JWT_JWKS_URL",
+    "INFERENCO_MCP_OAUTH_RESOURCE",
+    "INFERENCO_MCP_OAUTH_AUTHORIZATION_SERVERS",
+    "INFERENCO_MCP_OAUTH_SCOPES",
+    "INFERENCO_MCP_CORS_ORIGINS",
+    "INFERENCO_MCP_CORS_HEADERS",
+    "INFERENCO_MCP_CORS_METHODS",
+    "INFERENCO_MCP_BIND_ADDR",
+    "INFERENCO_MCP_ALLOWED_HOSTS",
+    "INFERENCO_MCP_ALLOWED_IPS",
+    "INFERENCO_MCP_DENIED_IPS",
+    "INFERENCO_MCP_TRUSTED_PROXIES",
+    "INFERENCO_MCP_TRUSTED_PROXY_HEADER",
+    "INFERENCO_MCP_TLS_CERT",
+    "INFERENCO_MCP_TLS_KEY",
+    "INFERENCO_MCP_TLS_CLIENT_CA",
+    "INFERENCO_MCP_TLS_CLIENT_AUTH",
+    "INFERENCO_MCP_HEALTH_PROBES",
+    "INFERENCO_MCP_HEALTH_OPTIONAL",
+    "INFERENCO_MCP_HEALTH_TIMEOUT_MS",
+    "INFERENCO_MCP_HTTP_CONNECT_TIMEOUT_MS",
+    "INFERENCO_MCP_HTTP_READ_TIMEOUT_MS",
+    "INFERENCO_MCP_HTTP_POOL_MAX_IDLE",
+    "INFERENCO_MCP_HTTP_POOL_IDLE_SECS",
+    "INFERENCO_MCP_HTTP_KEEPALIVE_SECS",
+    "INFERENCO_MCP_HTTP_PROXY",
+    "INFERENCO_MCP_HTTP_USER_AGENT",
+    "INFERENCO_MCP_HTTP_DNS_TTL_SECS",
+    "INFERENCO_MCP_HTTP_DNS_CACHE_HOSTS",
+    "INFERENCO_MCP_FETCH_ALLOWED_HOSTS",
+    "INFERENCO_MCP_FETCH_MAX_BYTES",
+    "INFERENCO_MCP_FETCH_TIMEOUT_MS",
+    "INFERENCO_MCP_DOCS_URL",
+    "INFERENCO_MCP_DOCS_REFRESH_SECS",
+    "INFERENCO_MCP_DRAIN_SECONDS",
+    "INFERENCO_MCP_READY_FILE",
+    "INFERENCO_MCP_USAGE_PATH",
+    "INFERENCO_MCP_RECORD_PATH",
+    "INFERENCO_MCP_AUDIT_PATH",
+    "INFERENCO_MCP_AUDIT_BACKEND",
+    "INFERENCO_MCP_CACHE_DIR",
+    "INFERENCO_MCP_CACHE_MAX_ENTRIES",
+    "INFERENCO_MCP_CACHE_MAX_BYTES",
+    "INFERENCO_MCP_SCHEDULE_FILE",
+    "INFERENCO_MCP_STATE_BACKEND",
+    "INFERENCO_MCP_STATE_PATH",
+    "INFERENCO_MCP_KV_MAX_KEYS",
+    "INFERENCO_MCP_KV_MAX_VALUE_BYTES",
+    "INFERENCO_MCP_NOTES_MAX_COUNT",
+    "INFERENCO_MCP_NOTES_MAX_BYTES",
+    "INFERENCO_MCP_HISTORY_MAX_ENTRIES",
+    "INFERENCO_MCP_HISTORY_MAX_AGE_SECS",
+    "INFERENCO_MCP_MAX_CONCURRENT_CALLS",
+    "INFERENCO_MCP_MAX_QUEUED_CALLS",
+    "INFERENCO_MCP_MAX_SESSION_CALLS",
+    "INFERENCO_MCP_RATE_LIMIT_KEY",
+    "INFERENCO_MCP_RATE_LIMIT_IP",
+    "INFERENCO_MCP_RATE_LIMIT_GLOBAL",
+    "INFERENCO_MCP_SLOW_CALL_MS",
+    "INFERENCO_MCP_SLOW_CALL_BUFFER",
+    "INFERENCO_MCP_SSE_MAX_AGE_SECS",
+    "INFERENCO_MCP_SESSION_IDLE_SECS",
+    "INFERENCO_MCP_MAX_SESSIONS",
+    "INFERENCO_MCP_BATCH_PARALLELISM",
+    "INFERENCO_MCP_MAX_BODY_BYTES",
+    "INFERENCO_MCP_TOOLS_PAGE_SIZE",
+    "INFERENCO_MCP_DISABLED_TOOLS",
+    // Build-time only; tolerated so images built with it set still start.
+    "INFERENCO_MCP_GIT_COMMIT",
+    LISTEN_FD_VAR,
+    PARENT_PID_VAR,
+];
+
+/// File descriptor of a listening socket inherited from a predecessor during
+/// a zero-downtime restart.
+pub const LISTEN_FD_VAR: &str = "INFERENCO_MCP_LISTEN_FD";
+/// PID of the predecessor to signal once the successor is ready.
+pub const PARENT_PID_VAR: &str = "INFERENCO_MCP_UPGRADE_PARENT";
+
+/// Variables whose values are never printed.
+const SECRET_VARS: &[&str] = &[
+    "INFERENCO_MCP_API_KEYS",
+    "INFERENCO_MCP_ADMIN_TOKEN",
+    "INFERENCO_MCP_ALERT_SECRET",
+    "INFERENCO_MCP_ALERT_WEBHOOK",
+    "INFERENCO_MCP_CRASH_WEBHOOK",
+    // Proxy URLs often carry credentials.
+    "INFERENCO_MCP_HTTP_PROXY",
+];
+
+/// Named presets for common deployments. A profile only supplies values for
+/// variables that neither the environment nor `.env` set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "server-bin", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    /// Verbose text logs, no auth.
+    Dev,
+    /// JSON logs and API-key auth.
+    Staging,
+    /// JSON logs, API-key auth, and a shutdown drain for rolling deploys.
+    Prod,
+}
+
+impl Profile {
+    pub fn presets(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::Dev => &[
+                ("INFERENCO_MCP_LOG_LEVEL", "debug"),
+                ("INFERENCO_MCP_LOG_FORMAT", "text"),
+                ("INFERENCO_MCP_AUTH_ENABLED", "false"),
+            ],
+            Self::Staging => &[
+                ("INFERENCO_MCP_LOG_LEVEL", "info"),
+                ("INFERENCO_MCP_LOG_FORMAT", "json"),
+                ("INFERENCO_MCP_AUTH_ENABLED", "true"),
+            ],
+            Self::Prod => &[
+                ("INFERENCO_MCP_LOG_LEVEL", "info"),
+                ("INFERENCO_MCP_LOG_FORMAT", "json"),
+                ("INFERENCO_MCP_AUTH_ENABLED", "true"),
+                ("INFERENCO_MCP_DRAIN_SECONDS", "15"),
+            ],
+        }
+    }
+}
+
+/// One variable as the server will see it.
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectiveSetting {
+    pub name: &'static str,
+    /// `None` when unset; secrets are replaced with `<redacted>`.
+    pub value: Option<String>,
+    /// `environment`, `.env`, `config file`, `profile`, or `default`.
+    pub source: &'static str,
+}
+
+/// Every user-facing setting with its current value and origin.
+pub fn effective(env_file: &EnvFile) -> Vec<EffectiveSetting> {
+    KNOWN_VARS
+        .iter()
+        .filter(|name| {
+            ![
+                "INFERENCO_MCP_PROFILE",
+                "INFERENCO_MCP_GIT_COMMIT",
+                LISTEN_FD_VAR,
+                PARENT_PID_VAR,
+            ]
+            .contains(name)
+        })
+        .map(|name| EffectiveSetting {
+            name,
+            value: env::var(name).ok().map(|value| {
+                if SECRET_VARS.contains(name) && !value.is_empty() {
+                    "<redacted>".to_string()
+                } else {
+                    value
+                }
+            }),
+            source: env_file.source(name),
+        })
+        .collect()
+}
+
+/// Parse `name` if set, naming the variable and the expected form on error.
+pub fn parse_env<T: FromStr>(name: &str, expected: &str) -> Result<Option<T>, String> {
+    match env::var(name) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("{name} must be {expected}, got '{value}'")),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Check that `value` is an absolute http(s) URL with a host.
+pub fn parse_url(name: &str, value: &str) -> Result<String, String> {
+    let url = Url::parse(value).map_err(|error| format!("{name} is not a valid URL: {error}"))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("{name} must be an http:// or https:// URL"));
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        return Err(format!("{name} must include a host"));
+    }
+    Ok(value.to_string())
+}
+
+/// Default HTTP port, when neither `INFERENCO_MCP_PORT` nor `PORT` is set.
+pub const DEFAULT_PORT: u16 = 8080;
+/// Default of `INFERENCO_MCP_BIND_ADDR`.
+pub const DEFAULT_BIND_ADDR: &str = "0.0.0.0";
+/// Default of `INFERENCO_MCP_TCP_ADDR`.
+pub const DEFAULT_TCP_ADDR: &str = "127.0.0.1:8090";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "server-bin", derive(clap::ValueEnum))]
+pub enum Transport {
+    Stdio,
+    /// `/rpc`, `/sse`, and `/mcp` on the HTTP port.
+    Http,
+    /// `/ws` on the HTTP port.
+    Ws,
+    /// Newline-delimited JSON-RPC on its own TCP port.
+    Tcp,
+}
+
+impl Transport {
+    /// Whether the transport listens on the HTTP port.
+    pub fn uses_port(self) -> bool {
+        matches!(self, Transport::Http | Transport::Ws)
+    }
+}
+
+/// The transports to serve and where they listen, as `serve` takes them
+/// from its flags or the environment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listeners {
+    pub transport: Vec<Transport>,
+    /// `None` falls back to `PORT`, then [`DEFAULT_PORT`].
+    pub port: Option<u16>,
+    pub bind_addr: IpAddr,
+    pub tcp_addr: SocketAddr,
+}
+
+impl Default for Listeners {
+    fn default() -> Self {
+        Self {
+            transport: vec![Transport::Stdio],
+            port: None,
+            bind_addr: DEFAULT_BIND_ADDR.parse().expect("valid default address"),
+            tcp_addr: DEFAULT_TCP_ADDR.parse().expect("valid default address"),
+        }
+    }
+}
+
+impl Listeners {
+    /// Whether any of the transports listens on the HTTP port.
+    pub fn uses_port(&self) -> bool {
+        self.transport.iter().any(|transport| transport.uses_port())
+    }
+
+    /// The port to listen on: `--port`, `INFERENCO_MCP_PORT`, then `PORT` as
+    /// set by Cloud Run, Heroku, and similar platforms.
+    pub fn http_port(&self) -> Result<u16, String> {
+        match self.port {
+            Some(port) => Ok(port),
+            None => Ok(parse_env::<NonZeroU16>("PORT", "a port number")?
+                .map_or(DEFAULT_PORT, NonZeroU16::get)),
+        }
+    }
+}
+
+/// Default of `INFERENCO_MCP_DOCS_REFRESH_SECS`.
+const DEFAULT_DOCS_REFRESH_SECS: u64 = 5 * 60;
+
+/// Default of `INFERENCO_MCP_MAX_BODY_BYTES`, axum's own default.
+const DEFAULT_MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// Default of `INFERENCO_MCP_SESSION_IDLE_SECS`.
+const DEFAULT_SESSION_IDLE_SECS: u64 = 30 * 60;
+/// Default of `INFERENCO_MCP_MAX_SESSIONS`.
+const DEFAULT_MAX_SESSIONS: usize = 10_000;
+
+/// Everything the server reads from its environment.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// The transports to serve, each once, in the order given.
+    pub transport: Vec<Transport>,
+    pub port: u16,
+    /// Address the HTTP port listens on.
+    pub bind_addr: IpAddr,
+    /// Where the `tcp` transport listens.
+    pub tcp_addr: SocketAddr,
+    pub log: LogSettings,
+    pub crash: Option<CrashSettings>,
+    pub alerts: Option<AlertConfig>,
+    pub health: HealthSettings,
+    /// Cross-origin access to the HTTP endpoints; off if `None`.
+    pub cors: Option<CorsSettings>,
+    /// Hosts the HTTP transports answer to; any if `None`.
+    pub allowed_hosts: Option<Vec<String>>,
+    /// Client addresses the HTTP transports admit, and the proxies whose
+    /// forwarding headers name the client.
+    pub ip_filter: IpFilter,
+    /// Certificate and client CAs of the HTTP server; plain HTTP if `None`.
+    pub tls: Option<TlsSettings>,
+    pub http: HttpClientSettings,
+    /// Hosts and limits for `fetch_url`, which is off without an allowlist.
+    pub fetch: FetchSettings,
+    /// Root of the Cedra docs served as `cedra-docs://` resources.
+    pub docs_url: Option<Url>,
+    /// How often cached docs pages are re-read for changes; never if `None`.
+    pub docs_refresh: Option<Duration>,
+    pub slow_calls: SlowCallSettings,
+    pub usage_path: Option<PathBuf>,
+    /// Append every HTTP JSON-RPC exchange to this file for `replay`.
+    pub record_path: Option<PathBuf>,
+    /// Where caches are spilled on shutdown and warmed from at startup.
+    pub cache_dir: Option<PathBuf>,
+    /// Default bounds of caches tools create.
+    pub cache_limits: CacheLimits,
+    pub state: StateBackend,
+    pub kv_limits: KvLimits,
+    pub note_limits: NoteLimits,
+    pub history_limits: HistoryLimits,
+    /// Cap on tool calls running and waiting at once.
+    pub concurrency: ConcurrencyLimits,
+    /// Requests the transport endpoints admit per credential, per IP, and
+    /// overall.
+    pub rate_limits: RateLimits,
+    /// Where every tool call is recorded; calls are not audited if `None`.
+    pub audit: Option<AuditBackend>,
+    /// Tool calls to run on a cron schedule.
+    pub schedule: Vec<ScheduledJob>,
+    /// Where issued API keys are kept; only `INFERENCO_MCP_API_KEYS` are
+    /// accepted if `None`.
+    pub keys_file: Option<PathBuf>,
+    /// Which bearer JWTs to accept; none if `None`.
+    pub jwt: Option<JwtSettings>,
+    /// What OAuth clients are told about this server; no metadata is
+    /// served if `None`.
+    pub oauth: Option<OAuthSettings>,
+    /// Which callers may use which tools; every caller any tool if `None`.
+    pub policy: Option<Policy>,
+    /// How long the HTTP server keeps serving after `SIGTERM`.
+    pub drain_delay: Duration,
+    /// How long an unused `/mcp` session is kept.
+    pub session_idle: Duration,
+    /// Most `/mcp` sessions kept at once; 0 for no limit.
+    pub max_sessions: usize,
+    /// Largest JSON-RPC request body or WebSocket message accepted.
+    pub max_body_bytes: usize,
+    /// Most tools one `tools/list` page holds.
+    pub tools_page_size: usize,
+    /// Created once the HTTP server accepts connections and removed when it
+    /// starts draining, for exec-based startup probes.
+    pub ready_file: Option<PathBuf>,
+    /// Initial auth and session settings; later values come from reloads.
+    pub access: ReloadableConfig,
+}
+
+impl Settings {
+    /// Read and validate every setting. `args` carries the transport and
+    /// port, which may come from flags as well as the environment.
+    pub fn load(args: &Listeners) -> Result<Self, Vec<String>> {
+        let mut errors = unknown_vars();
+        errors.extend(access_errors());
+
+        let log = collect(&mut errors, LogSettings::from_env());
+        let crash = collect(&mut errors, CrashSettings::from_env());
+        let alerts = collect(&mut errors, AlertConfig::from_env());
+        let health = collect(&mut errors, HealthSettings::from_env());
+        let cors = collect(&mut errors, CorsSettings::from_env());
+        let allowed_hosts = collect(
+            &mut errors,
+            rebinding::allowed_hosts_from_env(args.bind_addr),
+        );
+        let ip_filter = collect(&mut errors, IpFilter::from_env());
+        let tls = collect(&mut errors, TlsSettings::from_env());
+        let http = collect(&mut errors, HttpClientSettings::from_env());
+        let fetch = collect(&mut errors, FetchSettings::from_env());
+        let docs_url = collect(
+            &mut errors,
+            env::var("INFERENCO_MCP_DOCS_URL")
+                .ok()
+                .map(|url| url.trim().to_string())
+                .filter(|url| !url.is_empty())
+                .map(|url| parse_url("INFERENCO_MCP_DOCS_URL", &url))
+                .transpose(),
+        );
+        let docs_refresh_secs = collect(
+            &mut errors,
+            parse_env::<u64>("INFERENCO_MCP_DOCS_REFRESH_SECS", "a number of seconds"),
+        );
+        let slow_calls = collect(&mut errors, SlowCallSettings::from_env());
+        let state = collect(&mut errors, StateBackend::from_env());
+        let kv_limits = collect(&mut errors, KvLimits::from_env());
+        let note_limits = collect(&mut errors, NoteLimits::from_env());
+        let history_limits = collect(&mut errors, HistoryLimits::from_env());
+        let concurrency = collect(&mut errors, ConcurrencyLimits::from_env());
+        let rate_limits = collect(&mut errors, RateLimits::from_env());
+        let cache_limits = collect(&mut errors, CacheLimits::from_env());
+        let audit = collect(&mut errors, AuditBackend::from_env());
+        let schedule = collect(&mut errors, scheduler::jobs_from_env());
+        let keys = collect(&mut errors, KeyStore::from_env());
+        let jwt = collect(&mut errors, JwtSettings::from_env());
+        let policy = collect(&mut errors, Policy::from_env());
+        let oauth = match &jwt {
+            Some(jwt) => collect(&mut errors, OAuthSettings::from_env(jwt.as_ref())),
+            None => Some(None),
+        };
+        let port = collect(&mut errors, args.http_port());
+        let drain_secs = collect(
+            &mut errors,
+            parse_env::<u64>("INFERENCO_MCP_DRAIN_SECONDS", "a number of seconds"),
+        );
+        collect(
+            &mut errors,
+            parse_env::<u64>("INFERENCO_MCP_SSE_MAX_AGE_SECS", "a number of seconds"),
+        );
+        let session_idle_secs = collect(
+            &mut errors,
+            parse_env::<u64>("INFERENCO_MCP_SESSION_IDLE_SECS", "a number of seconds"),
+        );
+        if let Some(Some(0)) = session_idle_secs {
+            errors.push("INFERENCO_MCP_SESSION_IDLE_SECS must be at least 1".to_string());
+        }
+        let max_sessions = collect(
+            &mut errors,
+            parse_env::<usize>("INFERENCO_MCP_MAX_SESSIONS", "a number of sessions"),
+        );
+        let max_body_bytes = collect(
+            &mut errors,
+            parse_env::<usize>("INFERENCO_MCP_MAX_BODY_BYTES", "a number of bytes"),
+        );
+        if let Some(Some(0)) = max_body_bytes {
+            errors.push("INFERENCO_MCP_MAX_BODY_BYTES must be at least 1".to_string());
+        }
+        let tools_page_size = collect(
+            &mut errors,
+            parse_env::<usize>("INFERENCO_MCP_TOOLS_PAGE_SIZE", "a number of tools"),
+        );
+        if let Some(Some(0)) = tools_page_size {
+            errors.push("INFERENCO_MCP_TOOLS_PAGE_SIZE must be at least 1".to_string());
+        }
+        if let Some(Some(0)) = collect(
+            &mut errors,
+            parse_env::<usize>("INFERENCO_MCP_BATCH_PARALLELISM", "a number of calls"),
+        ) {
+            errors.push("INFERENCO_MCP_BATCH_PARALLELISM must be at least 1".to_string());
+        }
+
+        let (
+            Some(log),
+            Some(crash),
+            Some(alerts),
+            Some(health),
+            Some(cors),
+            Some(allowed_hosts),
+            Some(ip_filter),
+            Some(tls),
+            Some(http),
+            Some(fetch),
+            Some(docs_url),
+            Some(docs_refresh_secs),
+            Some(slow_calls),
+            Some(state),
+            Some(kv_limits),
+            Some(note_limits),
+            Some(history_limits),
+            Some(concurrency),
+            Some(rate_limits),
+            Some(cache_limits),
+            Some(audit),
+            Some(schedule),
+            Some(keys),
+            Some(jwt),
+            Some(oauth),
+            Some(policy),
+            Some(port),
+            Some(drain_secs),
+            Some(session_idle_secs),
+            Some(max_sessions),
+            Some(max_body_bytes),
+            Some(tools_page_size),
+        ) = (
+            log,
+            crash,
+            alerts,
+            health,
+            cors,
+            allowed_hosts,
+            ip_filter,
+            tls,
+            http,
+            fetch,
+            docs_url,
+            docs_refresh_secs,
+            slow_calls,
+            state,
+            kv_limits,
+            note_limits,
+            history_limits,
+            concurrency,
+            rate_limits,
+            cache_limits,
+            audit,
+            schedule,
+            keys,
+            jwt,
+            oauth,
+            policy,
+            port,
+            drain_secs,
+            session_idle_secs,
+            max_sessions,
+            max_body_bytes,
+            tools_page_size,
+        )
+        else {
+            return Err(errors);
+        };
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(Self {
+            transport: args
+                .transport
+                .iter()
+                .fold(Vec::new(), |mut unique, transport| {
+                    if !unique.contains(transport) {
+                        unique.push(*transport);
+                    }
+                    unique
+                }),
+            port,
+            bind_addr: args.bind_addr,
+            tcp_addr: args.tcp_addr,
+            log,
+            crash,
+            alerts,
+            health,
+            cors,
+            allowed_hosts,
+            ip_filter,
+            tls,
+            http,
+            fetch,
+            docs_url: docs_url.map(|url| Url::parse(&url).expect("validated above")),
+            docs_refresh: match docs_refresh_secs.unwrap_or(DEFAULT_DOCS_REFRESH_SECS) {
+                0 => None,
+                secs => Some(Duration::from_secs(secs)),
+            },
+            slow_calls,
+            usage_path: env::var("INFERENCO_MCP_USAGE_PATH")
+                .ok()
+                .map(|path| path.trim().to_string())
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from),
+            record_path: env::var_os("INFERENCO_MCP_RECORD_PATH")
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from),
+            cache_dir: env::var_os("INFERENCO_MCP_CACHE_DIR")
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from),
+            cache_limits,
+            state,
+            kv_limits,
+            note_limits,
+            history_limits,
+            concurrency,
+            rate_limits,
+            audit,
+            schedule,
+            keys_file: keys.map(|keys| keys.path().to_path_buf()),
+            jwt,
+            oauth,
+            policy,
+            drain_delay: Duration::from_secs(drain_secs.unwrap_or(0)),
+            session_idle: Duration::from_secs(
+                session_idle_secs.unwrap_or(DEFAULT_SESSION_IDLE_SECS),
+            ),
+            max_sessions: max_sessions.unwrap_or(DEFAULT_MAX_SESSIONS),
+            max_body_bytes: max_body_bytes.unwrap_or(DEFAULT_MAX_BODY_BYTES),
+            tools_page_size: tools_page_size.unwrap_or(DEFAULT_TOOLS_PAGE_SIZE),
+            ready_file: env::var_os("INFERENCO_MCP_READY_FILE")
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from),
+            access: ReloadableConfig::from_env(),
+        })
+    }
+}
+
+/// The value of `result`, or `None` after recording its error.
+fn collect<T>(errors: &mut Vec<String>, result: Result<T, String>) -> Option<T> {
+    result.map_err(|error| errors.push(error)).ok()
+}
+
+/// `INFERENCO_MCP_*` variables the server does not read, usually typos.
+fn unknown_vars() -> Vec<String> {
+    let mut errors: Vec<String> = env::vars()
+        .map(|(name, _)| name)
+        .filter(|name| name.starts_with("INFERENCO_MCP_") && !KNOWN_VARS.contains(&name.as_str()))
+        .map(|name| match suggestion(&name) {
+            Some(known) => format!("unknown setting {name}; did you mean {known}?"),
+            None => format!("unknown setting {name}"),
+        })
+        .collect();
+    errors.sort();
+    errors
+}
+
+/// Problems [`ReloadableConfig::from_env`] would otherwise paper over.
+fn access_errors() -> Vec<String> {
+    let mut errors = Vec::new();
+    let auth_enabled = match env::var("INFERENCO_MCP_AUTH_ENABLED").as_deref() {
+        Ok("true") => true,
+        Ok("false") | Err(_) => false,
+        Ok(other) => {
+            errors.push(format!(
+                "INFERENCO_MCP_AUTH_ENABLED must be 'true' or 'false', got '{other}'"
+            ));
+            false
+        }
+    };
+    if auth_enabled
+        && env::var("INFERENCO_MCP_API_KEYS")
+            .unwrap_or_default()
+            .split(',')
+            .all(|key| key.trim().is_empty())
+        && env::var_os("INFERENCO_MCP_KEYS_FILE").is_none_or(|path| path.is_empty())
+        && env::var("INFERENCO_MCP_JWT_JWKS_URL")
+            .unwrap_or_default()
+            .trim()
+            .is_empty()
+    {
+        errors.push(
+            "INFERENCO_MCP_AUTH_ENABLED is true but none of INFERENCO_MCP_API_KEYS, INFERENCO_MCP_KEYS_FILE, or INFERENCO_MCP_JWT_JWKS_URL is set"
+                .to_string(),
+        );
+    }
+    if let Ok(header) = env::var("INFERENCO_MCP_AUTH_HEADER") {
+        if HeaderName::from_str(&header).is_err() {
+            errors.push(format!(
+                "INFERENCO_MCP_AUTH_HEADER is not a valid header name: '{header}'"
+            ));
+        }
+    }
+    errors
+}
+
+/// The closest known variable to a misspelled one.
+pub(crate) fn suggestion(name: &str) -> Option<&'static str> {
+    KNOWN_VARS
+        .iter()
+        .map(|known| (edit_distance(name, known), *known))
+        .filter(|(distance, _)| *distance <= 3)
+        .min()
+        .map(|(_, known)| known)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggests_close_variable_names() {
+        assert_eq!(
+            suggestion("INFERENCO_MCP_API_KEY"),
+            Some("INFERENCO_MCP_API_KEYS")
+        );
+        assert_eq!(suggestion("INFERENCO_MCP_PROT"), Some("INFERENCO_MCP_PORT"));
+        assert_eq!(suggestion("INFERENCO_MCP_SOMETHING_ELSE"), None);
+    }
+
+    #[test]
+    fn urls_must_be_absolute_http() {
+        assert!(parse_url("X", "https://hooks.example.com/a").is_ok());
+        assert_eq!(
+            parse_url("X", "ftp://example.com").unwrap_err(),
+            "X must be an http:// or https:// URL"
+        );
+        assert!(parse_url("X", "http//missing-colon")
+            .unwrap_err()
+            .starts_with("X is not a valid URL"));
+    }
+}
@@ -0,0 +1,168 @@
+//! TOML configuration files.
+//!
+//! A TOML file is another way of writing the `INFERENCO_MCP_*` variables, so
+//! it goes through the same validation and precedence as `.env`: a key in a
+//! table names the variable `INFERENCO_MCP_<TABLE>_<KEY>`, a top-level key
+//! `INFERENCO_MCP_<KEY>`, and the environment wins over the file.
+//!
+//! ```toml
+//! transport = ["stdio", "http"]
+//! port = 9090
+//!
+//! [auth]
+//! enabled = true        # INFERENCO_MCP_AUTH_ENABLED
+//! ```
+//!
+//! Arrays become comma-separated lists. Unknown keys are errors, with the
+//! closest known key suggested.
+
+use crate::config::{suggestion, KNOWN_VARS};
+use std::{collections::BTreeMap, path::Path};
+use toml::Value;
+
+/// Loaded when no `--config` is given and there is no `.env`.
+pub const DEFAULT_PATH: &str = "inferenco-mcp.toml";
+
+const PREFIX: &str = "INFERENCO_MCP_";
+
+/// Whether `path` names a TOML file rather than an env file.
+pub fn is_toml(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "toml")
+}
+
+/// The variables the TOML file at `path` sets.
+pub fn read(path: &Path) -> Result<BTreeMap<String, String>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|error| format!("cannot read {}: {error}", path.display()))?;
+    variables(&text).map_err(|errors| format!("{}: {}", path.display(), errors.join("; ")))
+}
+
+/// The variables a TOML document sets, or every problem with it.
+pub fn variables(text: &str) -> Result<BTreeMap<String, String>, Vec<String>> {
+    let table: toml::Table = text.parse().map_err(|error| vec![format!("{error}")])?;
+    let mut variables = BTreeMap::new();
+    let mut errors = Vec::new();
+    for (key, value) in table {
+        match value {
+            Value::Table(table) => {
+                for (inner, value) in table {
+                    let key = format!("{key}.{inner}");
+                    collect(&mut variables, &mut errors, &key, value);
+                }
+            }
+            value => collect(&mut variables, &mut errors, &key, value),
+        }
+    }
+    if errors.is_empty() {
+        Ok(variables)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Record the variable `key` names, or why it cannot be set.
+fn collect(
+    variables: &mut BTreeMap<String, String>,
+    errors: &mut Vec<String>,
+    key: &str,
+    value: Value,
+) {
+    let name = format!("{PREFIX}{}", key.replace(['.', '-'], "_").to_uppercase());
+    if !KNOWN_VARS.contains(&name.as_str()) {
+        errors.push(match suggestion(&name) {
+            Some(known) => format!("unknown key {key}; did you mean {}?", key_for(known, key)),
+            None => format!("unknown key {key}"),
+        });
+        return;
+    }
+    match scalar(&value).or_else(|| list(&value)) {
+        Some(value) => {
+            variables.insert(name, value);
+        }
+        None => errors.push(format!(
+            "{key} must be a string, number, boolean, or array of them"
+        )),
+    }
+}
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(value) => Some(value.clone()),
+        Value::Integer(value) => Some(value.to_string()),
+        Value::Float(value) => Some(value.to_string()),
+        Value::Boolean(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+fn list(value: &Value) -> Option<String> {
+    let Value::Array(items) = value else {
+        return None;
+    };
+    let items: Option<Vec<String>> = items.iter().map(scalar).collect();
+    Some(items?.join(","))
+}
+
+/// How the file would spell the variable `name`, in the table of `like`
+/// when it belongs there.
+fn key_for(name: &str, like: &str) -> String {
+    let key = name.trim_start_matches(PREFIX).to_lowercase();
+    match like.split_once('.') {
+        Some((table, _)) => match key.strip_prefix(&format!("{table}_")) {
+            Some(inner) => format!("{table}.{inner}"),
+            None => key,
+        },
+        None => key,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tables_and_arrays_become_variables() {
+        let variables = variables(
+            r#"
+            transport = ["stdio", "http"]
+            port = 9090
+
+            [auth]
+            enabled = true
+
+            [docs]
+            refresh_secs = 0
+            "#,
+        )
+        .expect("valid file");
+        let get = |name: &str| variables.get(name).map(String::as_str);
+        assert_eq!(get("INFERENCO_MCP_TRANSPORT"), Some("stdio,http"));
+        assert_eq!(get("INFERENCO_MCP_PORT"), Some("9090"));
+        assert_eq!(get("INFERENCO_MCP_AUTH_ENABLED"), Some("true"));
+        assert_eq!(get("INFERENCO_MCP_DOCS_REFRESH_SECS"), Some("0"));
+    }
+
+    #[test]
+    fn unknown_keys_and_nested_tables_are_reported() {
+        let errors = variables(
+            r#"
+            prot = 8080
+            [auth]
+            enabeld = true
+            [log.file]
+            path = "x"
+            "#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                "unknown key auth.enabeld; did you mean auth.enabled?",
+                "log.file must be a string, number, boolean, or array of them",
+                "unknown key prot; did you mean port?",
+            ]
+        );
+        assert!(variables("port = ").is_err());
+    }
+}
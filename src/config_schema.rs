@@ -0,0 +1,758 @@
+//! JSON Schema for configuration files, printed by `inferenco-mcp config-schema`.
+//!
+//! The schema describes an env file (`.env` or `--config`) as a flat object
+//! of variable names to values, which is also the shape of a Kubernetes
+//! ConfigMap's `data` and a compose `environment` map. Editors that validate
+//! YAML or JSON against it flag misspelled `INFERENCO_MCP_*` keys and
+//! malformed values before deploy; other variables are left alone.
+
+use serde_json::{json, Map, Value};
+
+/// The expected form of a variable's value.
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Text,
+    Path,
+    Url,
+    Bool,
+    /// A whole number no smaller than the bound.
+    Integer(u64),
+    Ratio,
+    /// Requests per period, as in `60/min`.
+    Rate,
+    /// Comma-separated values.
+    List,
+    Choice(&'static [&'static str]),
+    /// Comma-separated values, each one of these.
+    Choices(&'static [&'static str]),
+}
+
+struct Variable {
+    name: &'static str,
+    kind: Kind,
+    default: Option<&'static str>,
+    description: &'static str,
+}
+
+const fn var(
+    name: &'static str,
+    kind: Kind,
+    default: Option<&'static str>,
+    description: &'static str,
+) -> Variable {
+    Variable {
+        name,
+        kind,
+        default,
+        description,
+    }
+}
+
+/// Every variable an operator may set.
+const VARIABLES: &[Variable] = &[
+    var(
+        "INFERENCO_MCP_PROFILE",
+        Kind::Choice(&["dev", "staging", "prod"]),
+        None,
+        "Preset defaults for variables that are not set otherwise.",
+    ),
+    var(
+        "INFERENCO_MCP_CONFIG",
+        Kind::Path,
+        None,
+        "Env file to load instead of .env in the working directory.",
+    ),
+    var(
+        "INFERENCO_MCP_TRANSPORT",
+        Kind::Choices(&["stdio", "http", "ws", "tcp"]),
+        Some("stdio"),
+        "Transports to start, comma-separated.",
+    ),
+    var(
+        "INFERENCO_MCP_PORT",
+        Kind::Integer(0),
+        None,
+        "HTTP port; falls back to PORT, then 8080. 0 picks a free port.",
+    ),
+    var(
+        "INFERENCO_MCP_BIND_ADDR",
+        Kind::Text,
+        Some("0.0.0.0"),
+        "Address the HTTP port listens on; 127.0.0.1 keeps the server local.",
+    ),
+    var(
+        "INFERENCO_MCP_TCP_ADDR",
+        Kind::Text,
+        Some("127.0.0.1:8090"),
+        "Address the tcp transport listens on. 0 as the port picks a free one.",
+    ),
+    var(
+        "PORT",
+        Kind::Integer(1),
+        Some("8080"),
+        "HTTP port set by container platforms when INFERENCO_MCP_PORT is unset.",
+    ),
+    var(
+        "INFERENCO_MCP_LOG_LEVEL",
+        Kind::Text,
+        Some("info"),
+        "tracing-subscriber filter directives, used when RUST_LOG is unset.",
+    ),
+    var(
+        "INFERENCO_MCP_LOG_FORMAT",
+        Kind::Choice(&["text", "json"]),
+        Some("text"),
+        "Log output format.",
+    ),
+    var(
+        "INFERENCO_MCP_LOG_FILE",
+        Kind::Path,
+        None,
+        "Also write logs to this rotating file.",
+    ),
+    var(
+        "INFERENCO_MCP_LOG_ROTATION",
+        Kind::Choice(&["minutely", "hourly", "daily", "never"]),
+        Some("daily"),
+        "How often the log file rolls over.",
+    ),
+    var(
+        "INFERENCO_MCP_LOG_MAX_FILES",
+        Kind::Integer(1),
+        None,
+        "Rotated log files to keep; unlimited when unset, or 5 when rotating by size.",
+    ),
+    var(
+        "INFERENCO_MCP_LOG_MAX_BYTES",
+        Kind::Integer(1),
+        None,
+        "Roll the log file over before it grows past this many bytes, instead of on a schedule.",
+    ),
+    var(
+        "INFERENCO_MCP_CRASH_WEBHOOK",
+        Kind::Url,
+        None,
+        "Receives panics and error-level events as JSON.",
+    ),
+    var(
+        "INFERENCO_MCP_ENVIRONMENT",
+        Kind::Text,
+        None,
+        "Environment tag attached to crash reports.",
+    ),
+    var(
+        "INFERENCO_MCP_ALERT_WEBHOOK",
+        Kind::Url,
+        None,
+        "Receives operator alerts; alerts are off when unset.",
+    ),
+    var(
+        "INFERENCO_MCP_ALERT_FORMAT",
+        Kind::Choice(&["json", "slack"]),
+        Some("json"),
+        "Body layout of alert notifications.",
+    ),
+    var(
+        "INFERENCO_MCP_ALERT_SECRET",
+        Kind::Text,
+        None,
+        "HMAC-SHA256 key for the x-inferenco-signature header.",
+    ),
+    var(
+        "INFERENCO_MCP_ALERT_ERROR_RATE",
+        Kind::Ratio,
+        Some("0.5"),
+        "Share of failing tool calls that raises an alert; 0 disables the rule.",
+    ),
+    var(
+        "INFERENCO_MCP_ALERT_MIN_CALLS",
+        Kind::Integer(0),
+        Some("20"),
+        "Calls in the window before the error rate is evaluated.",
+    ),
+    var(
+        "INFERENCO_MCP_ALERT_UPSTREAM_FAILURES",
+        Kind::Integer(0),
+        Some("5"),
+        "Failures of one upstream in the window that raise an alert; 0 disables the rule.",
+    ),
+    var(
+        "INFERENCO_MCP_ALERT_WINDOW_SECS",
+        Kind::Integer(0),
+        Some("300"),
+        "Sliding window for both alert rules.",
+    ),
+    var(
+        "INFERENCO_MCP_ALERT_COOLDOWN_SECS",
+        Kind::Integer(0),
+        Some("900"),
+        "Minimum time between alerts for the same rule.",
+    ),
+    var(
+        "INFERENCO_MCP_AUTH_ENABLED",
+        Kind::Bool,
+        Some("false"),
+        "Require an API key on HTTP requests.",
+    ),
+    var(
+        "INFERENCO_MCP_API_KEYS",
+        Kind::List,
+        None,
+        "Comma-separated API keys accepted when auth is enabled.",
+    ),
+    var(
+        "INFERENCO_MCP_AUTH_HEADER",
+        Kind::Text,
+        Some("x-api-key"),
+        "HTTP header that carries the API key.",
+    ),
+    var(
+        "INFERENCO_MCP_ADMIN_TOKEN",
+        Kind::Text,
+        None,
+        "Bearer token for /admin/* endpoints, which are disabled when unset.",
+    ),
+    var(
+        "INFERENCO_MCP_KEYS_FILE",
+        Kind::Path,
+        None,
+        "JSON file of hashed API keys with scopes and expiry, managed with the keys subcommand or /admin/keys.",
+    ),
+    var(
+        "INFERENCO_MCP_POLICY_FILE",
+        Kind::Path,
+        None,
+        "JSON file of identity groups and the tools limited to them; other tools stay open.",
+    ),
+    var(
+        "INFERENCO_MCP_JWT_ISSUER",
+        Kind::Text,
+        None,
+        "Required iss of bearer JWTs.",
+    ),
+    var(
+        "INFERENCO_MCP_JWT_AUDIENCE",
+        Kind::List,
+        None,
+        "Accepted aud values of bearer JWTs.",
+    ),
+    var(
+        "INFERENCO_MCP_JWT_JWKS_URL",
+        Kind::Url,
+        None,
+        "JWKS with the keys bearer JWTs are signed with; JWTs are refused when unset.",
+    ),
+    var(
+        "INFERENCO_MCP_OAUTH_RESOURCE",
+        Kind::Url,
+        None,
+        "Canonical URL of this server; serves OAuth protected resource metadata and is the default JWT audience.",
+    ),
+    var(
+        "INFERENCO_MCP_OAUTH_AUTHORIZATION_SERVERS",
+        Kind::List,
+        None,
+        "Authorization servers named in the metadata; defaults to the JWT issuer.",
+    ),
+    var(
+        "INFERENCO_MCP_OAUTH_SCOPES",
+        Kind::List,
+        None,
+        "Scopes every bearer token must carry, advertised as scopes_supported.",
+    ),
+    var(
+        "INFERENCO_MCP_CORS_ORIGINS",
+        Kind::List,
+        None,
+        "Origins browsers may call the HTTP endpoints from, or * for any; CORS is off when unset.",
+    ),
+    var(
+        "INFERENCO_MCP_CORS_HEADERS",
+        Kind::List,
+        None,
+        "Request headers CORS preflights allow; defaults to those MCP clients send plus the auth header.",
+    ),
+    var(
+        "INFERENCO_MCP_CORS_METHODS",
+        Kind::List,
+        Some("GET,POST,DELETE"),
+        "HTTP methods CORS preflights allow.",
+    ),
+    var(
+        "INFERENCO_MCP_ALLOWED_HOSTS",
+        Kind::List,
+        None,
+        "Host names the transport endpoints answer to, or * for any; localhost names when bound to loopback, any host otherwise.",
+    ),
+    var(
+        "INFERENCO_MCP_ALLOWED_IPS",
+        Kind::List,
+        None,
+        "CIDR ranges of clients the transport endpoints admit; any address when unset.",
+    ),
+    var(
+        "INFERENCO_MCP_DENIED_IPS",
+        Kind::List,
+        None,
+        "CIDR ranges of clients the transport endpoints refuse, even if allowed.",
+    ),
+    var(
+        "INFERENCO_MCP_TRUSTED_PROXIES",
+        Kind::List,
+        None,
+        "CIDR ranges of reverse proxies whose forwarding header names the client.",
+    ),
+    var(
+        "INFERENCO_MCP_TRUSTED_PROXY_HEADER",
+        Kind::Choice(&["x-forwarded-for", "forwarded"]),
+        Some("x-forwarded-for"),
+        "The header trusted proxies write the client into; the other one is ignored.",
+    ),
+    var(
+        "INFERENCO_MCP_TLS_CERT",
+        Kind::Path,
+        None,
+        "PEM certificate chain the HTTP server presents; serves HTTPS only, and needs the tls feature.",
+    ),
+    var(
+        "INFERENCO_MCP_TLS_KEY",
+        Kind::Path,
+        None,
+        "PEM private key of INFERENCO_MCP_TLS_CERT.",
+    ),
+    var(
+        "INFERENCO_MCP_TLS_CLIENT_CA",
+        Kind::Path,
+        None,
+        "PEM CAs that sign client certificates; a verified certificate authenticates its requests.",
+    ),
+    var(
+        "INFERENCO_MCP_TLS_CLIENT_AUTH",
+        Kind::Choice(&["required", "optional"]),
+        Some("required"),
+        "Whether clients must present a certificate when INFERENCO_MCP_TLS_CLIENT_CA is set.",
+    ),
+    var(
+        "INFERENCO_MCP_HEALTH_PROBES",
+        Kind::List,
+        None,
+        "Comma-separated name=url upstreams checked by /health?deep=true.",
+    ),
+    var(
+        "INFERENCO_MCP_HEALTH_OPTIONAL",
+        Kind::List,
+        None,
+        "Probe names whose failure only degrades health.",
+    ),
+    var(
+        "INFERENCO_MCP_HEALTH_TIMEOUT_MS",
+        Kind::Integer(1),
+        Some("5000"),
+        "Per-probe timeout.",
+    ),
+    var(
+        "INFERENCO_MCP_HTTP_CONNECT_TIMEOUT_MS",
+        Kind::Integer(1),
+        Some("5000"),
+        "Outbound HTTP connect timeout.",
+    ),
+    var(
+        "INFERENCO_MCP_HTTP_READ_TIMEOUT_MS",
+        Kind::Integer(1),
+        None,
+        "Longest wait for the next chunk of an outbound response.",
+    ),
+    var(
+        "INFERENCO_MCP_HTTP_POOL_MAX_IDLE",
+        Kind::Integer(0),
+        Some("16"),
+        "Idle outbound connections kept per host.",
+    ),
+    var(
+        "INFERENCO_MCP_HTTP_POOL_IDLE_SECS",
+        Kind::Integer(0),
+        Some("90"),
+        "How long an idle outbound connection is kept.",
+    ),
+    var(
+        "INFERENCO_MCP_HTTP_KEEPALIVE_SECS",
+        Kind::Integer(0),
+        Some("60"),
+        "TCP keepalive interval for outbound connections; 0 turns it off.",
+    ),
+    var(
+        "INFERENCO_MCP_HTTP_PROXY",
+        Kind::Url,
+        None,
+        "Proxy for all outbound HTTP requests.",
+    ),
+    var(
+        "INFERENCO_MCP_HTTP_USER_AGENT",
+        Kind::Text,
+        Some("inferenco-mcp/<version>"),
+        "User-Agent sent on outbound HTTP requests.",
+    ),
+    var(
+        "INFERENCO_MCP_HTTP_DNS_TTL_SECS",
+        Kind::Integer(0),
+        Some("60"),
+        "How long resolved upstream addresses are reused; 0 resolves every new connection.",
+    ),
+    var(
+        "INFERENCO_MCP_HTTP_DNS_CACHE_HOSTS",
+        Kind::Integer(1),
+        Some("1024"),
+        "Upstream hosts whose resolved addresses are kept at once.",
+    ),
+    var(
+        "INFERENCO_MCP_FETCH_ALLOWED_HOSTS",
+        Kind::List,
+        None,
+        "Hosts fetch_url may reach, exact or *.domain; the tool is disabled when unset.",
+    ),
+    var(
+        "INFERENCO_MCP_FETCH_MAX_BYTES",
+        Kind::Integer(1),
+        Some("1048576"),
+        "Response body bytes fetch_url reads before truncating.",
+    ),
+    var(
+        "INFERENCO_MCP_FETCH_TIMEOUT_MS",
+        Kind::Integer(1),
+        Some("10000"),
+        "Overall timeout of one fetch_url request, redirects included.",
+    ),
+    var(
+        "INFERENCO_MCP_DOCS_URL",
+        Kind::Url,
+        None,
+        "Root of the Cedra docs served as cedra-docs:// resources; off when unset.",
+    ),
+    var(
+        "INFERENCO_MCP_DOCS_REFRESH_SECS",
+        Kind::Integer(0),
+        Some("300"),
+        "How often read docs pages are re-read to notify subscribers of changes; 0 turns it off.",
+    ),
+    var(
+        "INFERENCO_MCP_DRAIN_SECONDS",
+        Kind::Integer(0),
+        Some("0"),
+        "Seconds to report not-ready before shutting down.",
+    ),
+    var(
+        "INFERENCO_MCP_READY_FILE",
+        Kind::Path,
+        None,
+        "Created once the HTTP listener is up and removed when draining starts.",
+    ),
+    var(
+        "INFERENCO_MCP_USAGE_PATH",
+        Kind::Path,
+        None,
+        "JSON file where per-key usage is persisted; in-memory when unset.",
+    ),
+    var(
+        "INFERENCO_MCP_RECORD_PATH",
+        Kind::Path,
+        None,
+        "Append every HTTP JSON-RPC exchange, redacted, to this file for replay.",
+    ),
+    var(
+        "INFERENCO_MCP_AUDIT_PATH",
+        Kind::Path,
+        None,
+        "Record every tool call, with caller and redacted arguments, in this append-only log.",
+    ),
+    var(
+        "INFERENCO_MCP_AUDIT_BACKEND",
+        Kind::Choice(&["jsonl", "sqlite"]),
+        Some("jsonl"),
+        "Format of the audit log; sqlite needs the sqlite feature.",
+    ),
+    var(
+        "INFERENCO_MCP_CACHE_DIR",
+        Kind::Path,
+        None,
+        "Directory caches are written to on shutdown and warmed from at startup.",
+    ),
+    var(
+        "INFERENCO_MCP_CACHE_MAX_ENTRIES",
+        Kind::Integer(0),
+        Some("10000"),
+        "Entries each cache keeps before evicting the least recently used; 0 removes the limit.",
+    ),
+    var(
+        "INFERENCO_MCP_CACHE_MAX_BYTES",
+        Kind::Integer(0),
+        Some("67108864"),
+        "Bytes of keys and values each cache keeps before evicting; 0 removes the limit.",
+    ),
+    var(
+        "INFERENCO_MCP_SCHEDULE_FILE",
+        Kind::Path,
+        None,
+        "JSON list of tool calls to run on cron schedules; results become schedule:// resources.",
+    ),
+    var(
+        "INFERENCO_MCP_STATE_BACKEND",
+        Kind::Choice(&["memory", "sqlite", "sled"]),
+        Some("memory"),
+        "Where tool state such as counters is kept; sqlite and sled need the matching build feature.",
+    ),
+    var(
+        "INFERENCO_MCP_STATE_PATH",
+        Kind::Path,
+        None,
+        "SQLite database file or sled directory for the state backend.",
+    ),
+    var(
+        "INFERENCO_MCP_KV_MAX_KEYS",
+        Kind::Integer(0),
+        Some("1000"),
+        "Keys each API key or session may keep in the key-value store.",
+    ),
+    var(
+        "INFERENCO_MCP_KV_MAX_VALUE_BYTES",
+        Kind::Integer(0),
+        Some("65536"),
+        "Largest value kv_set accepts, measured as serialized JSON.",
+    ),
+    var(
+        "INFERENCO_MCP_NOTES_MAX_COUNT",
+        Kind::Integer(0),
+        Some("1000"),
+        "Notes each API key or session may keep.",
+    ),
+    var(
+        "INFERENCO_MCP_NOTES_MAX_BYTES",
+        Kind::Integer(1),
+        Some("4096"),
+        "Longest note text note_add accepts, in bytes.",
+    ),
+    var(
+        "INFERENCO_MCP_HISTORY_MAX_ENTRIES",
+        Kind::Integer(0),
+        Some("100"),
+        "Tool calls kept per API key or session for call_history; 0 turns history off.",
+    ),
+    var(
+        "INFERENCO_MCP_HISTORY_MAX_AGE_SECS",
+        Kind::Integer(1),
+        None,
+        "Forget history entries older than this; kept until displaced when unset.",
+    ),
+    var(
+        "INFERENCO_MCP_MAX_CONCURRENT_CALLS",
+        Kind::Integer(0),
+        Some("64"),
+        "Tool calls executing at once; 0 removes the limit.",
+    ),
+    var(
+        "INFERENCO_MCP_MAX_QUEUED_CALLS",
+        Kind::Integer(0),
+        Some("256"),
+        "Tool calls waiting for a slot before new calls get a busy error.",
+    ),
+    var(
+        "INFERENCO_MCP_MAX_SESSION_CALLS",
+        Kind::Integer(0),
+        Some("8"),
+        "Tool calls one session may have running or queued; 0 removes the limit.",
+    ),
+    var(
+        "INFERENCO_MCP_RATE_LIMIT_KEY",
+        Kind::Rate,
+        None,
+        "Requests per API key or bearer token on the transport endpoints, as in 120/min.",
+    ),
+    var(
+        "INFERENCO_MCP_RATE_LIMIT_IP",
+        Kind::Rate,
+        None,
+        "Requests per client IP on the transport endpoints.",
+    ),
+    var(
+        "INFERENCO_MCP_RATE_LIMIT_GLOBAL",
+        Kind::Rate,
+        None,
+        "Requests from all clients together on the transport endpoints.",
+    ),
+    var(
+        "INFERENCO_MCP_SLOW_CALL_MS",
+        Kind::Integer(0),
+        Some("1000"),
+        "Tool calls at or above this duration are logged as slow.",
+    ),
+    var(
+        "INFERENCO_MCP_SLOW_CALL_BUFFER",
+        Kind::Integer(0),
+        Some("50"),
+        "How many recent slow calls /admin/slow-calls keeps.",
+    ),
+    var(
+        "INFERENCO_MCP_SSE_MAX_AGE_SECS",
+        Kind::Integer(0),
+        None,
+        "Evict SSE sessions older than this; never when unset.",
+    ),
+    var(
+        "INFERENCO_MCP_SESSION_IDLE_SECS",
+        Kind::Integer(1),
+        Some("1800"),
+        "How long an unused Streamable HTTP (/mcp) session is kept before it expires.",
+    ),
+    var(
+        "INFERENCO_MCP_MAX_SESSIONS",
+        Kind::Integer(0),
+        Some("10000"),
+        "Streamable HTTP sessions kept at once; opening another evicts the least recently used. 0 removes the limit.",
+    ),
+    var(
+        "INFERENCO_MCP_MAX_BODY_BYTES",
+        Kind::Integer(1),
+        Some("2097152"),
+        "Largest JSON-RPC request body or WebSocket message accepted; larger ones get 413.",
+    ),
+    var(
+        "INFERENCO_MCP_TOOLS_PAGE_SIZE",
+        Kind::Integer(1),
+        Some("100"),
+        "Most tools one tools/list page holds; longer lists continue at nextCursor.",
+    ),
+    var(
+        "INFERENCO_MCP_DISABLED_TOOLS",
+        Kind::List,
+        None,
+        "Tools left out of tools/list and refused by tools/call; reloadable.",
+    ),
+    var(
+        "INFERENCO_MCP_BATCH_PARALLELISM",
+        Kind::Integer(1),
+        Some("8"),
+        "Calls of one JSON-RPC batch that run at once; 1 runs them in order.",
+    ),
+    var(
+        "RUST_LOG",
+        Kind::Text,
+        None,
+        "Standard tracing filter; overrides INFERENCO_MCP_LOG_LEVEL.",
+    ),
+];
+
+/// Subschema for one value. Env files only hold strings, but YAML and JSON
+/// sources may carry numbers and booleans, so both spellings are accepted.
+fn value_schema(kind: Kind) -> Value {
+    match kind {
+        Kind::Text | Kind::List => json!({ "type": "string" }),
+        Kind::Path => json!({ "type": "string", "minLength": 1 }),
+        Kind::Url => json!({ "type": "string", "pattern": "^https?://[^/?#]+" }),
+        Kind::Bool => json!({ "enum": [true, false, "true", "false"] }),
+        Kind::Integer(minimum) => json!({
+            "type": ["integer", "string"],
+            "minimum": minimum,
+            "pattern": "^\\s*[0-9]+\\s*$",
+        }),
+        Kind::Ratio => json!({
+            "type": ["number", "string"],
+            "minimum": 0,
+            "maximum": 1,
+            "pattern": "^\\s*(0|1)?(\\.[0-9]+)?\\s*$",
+        }),
+        Kind::Rate => json!({
+            "type": "string",
+            "pattern": "^\\s*[0-9]+\\s*/\\s*(s|sec|m|min|h|hour)\\s*$",
+        }),
+        Kind::Choice(choices) => json!({ "enum": choices }),
+        Kind::Choices(choices) => {
+            let choice = choices.join("|");
+            json!({
+                "type": "string",
+                "pattern": format!("^\\s*({choice})(\\s*,\\s*({choice}))*\\s*$"),
+            })
+        }
+    }
+}
+
+/// The schema as a JSON document.
+pub fn schema() -> Value {
+    let mut properties = Map::new();
+    for variable in VARIABLES {
+        let mut property = value_schema(variable.kind);
+        property["description"] = json!(variable.description);
+        if let Some(default) = variable.default {
+            property["default"] = json!(default);
+        }
+        properties.insert(variable.name.to_string(), property);
+    }
+    let prefixed: Vec<&str> = VARIABLES
+        .iter()
+        .map(|variable| variable.name)
+        .filter(|name| name.starts_with("INFERENCO_MCP_"))
+        .collect();
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "inferenco-mcp configuration",
+        "description": "Environment variables read by inferenco-mcp, as in an env file.",
+        "type": "object",
+        "properties": properties,
+        // Unknown INFERENCO_MCP_* keys are typos; the server refuses them too.
+        "propertyNames": {
+            "anyOf": [
+                { "enum": prefixed },
+                { "not": { "pattern": "^INFERENCO_MCP_" } },
+            ],
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        alerts::AlertFormat,
+        config::{Profile, Transport, KNOWN_VARS, LISTEN_FD_VAR, PARENT_PID_VAR},
+        logging::{LogFormat, LogRotation},
+    };
+
+    #[test]
+    fn covers_every_known_variable() {
+        let internal = ["INFERENCO_MCP_GIT_COMMIT", LISTEN_FD_VAR, PARENT_PID_VAR];
+        let schema = schema();
+        for name in KNOWN_VARS.iter().filter(|name| !internal.contains(name)) {
+            assert!(schema["properties"].get(*name).is_some(), "{name} missing");
+        }
+    }
+
+    #[test]
+    fn choices_parse_as_their_settings_types() {
+        let choices = |name: &str| match VARIABLES.iter().find(|v| v.name == name) {
+            Some(Variable {
+                kind: Kind::Choice(choices) | Kind::Choices(choices),
+                ..
+            }) => *choices,
+            _ => panic!("{name} is not a choice"),
+        };
+        // Profiles and transports are parsed by clap, which only the binary
+        // builds with.
+        #[cfg(feature = "server-bin")]
+        {
+            use clap::ValueEnum;
+            for choice in choices("INFERENCO_MCP_PROFILE") {
+                assert!(Profile::from_str(choice, false).is_ok(), "{choice}");
+            }
+            for choice in choices("INFERENCO_MCP_TRANSPORT") {
+                assert!(Transport::from_str(choice, false).is_ok(), "{choice}");
+            }
+        }
+        for choice in choices("INFERENCO_MCP_LOG_FORMAT") {
+            assert!(choice.parse::<LogFormat>().is_ok(), "{choice}");
+        }
+        for choice in choices("INFERENCO_MCP_LOG_ROTATION") {
+            assert!(choice.parse::<LogRotation>().is_ok(), "{choice}");
+        }
+        for choice in choices("INFERENCO_MCP_ALERT_FORMAT") {
+            assert!(choice.parse::<AlertFormat>().is_ok(), "{choice}");
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! Cross-origin access for browser-based MCP clients.
+//!
+//! CORS is off until `INFERENCO_MCP_CORS_ORIGINS` names the origins allowed
+//! to call the HTTP endpoints, or is `*` for any origin. Preflights then
+//! allow the request headers MCP clients send (the session, protocol
+//! version, and API key headers among them) and the methods of `/rpc`,
+//! `/sse`, and `/mcp`; both lists can be replaced. Responses always expose
+//! `Mcp-Session-Id`, `X-Request-Id`, and `WWW-Authenticate` so scripts can
+//! read them. Browsers never send credentials, since the API key travels in
+//! a header.
+
+use reqwest::{header::HeaderName, Method, Url};
+use std::{env, str::FromStr, time::Duration};
+
+/// Request headers allowed when `INFERENCO_MCP_CORS_HEADERS` is unset, next
+/// to the configured auth header.
+const DEFAULT_HEADERS: &[&str] = &[
+    "accept",
+    "authorization",
+    "content-type",
+    "last-event-id",
+    "mcp-protocol-version",
+    "mcp-session-id",
+    "x-request-id",
+];
+/// Methods allowed when `INFERENCO_MCP_CORS_METHODS` is unset.
+const DEFAULT_METHODS: &[&str] = &["GET", "POST", "DELETE"];
+/// Response headers scripts on another origin may read.
+pub const EXPOSED_HEADERS: [&str; 3] = ["mcp-session-id", "x-request-id", "www-authenticate"];
+/// How long browsers may cache a preflight answer.
+pub const PREFLIGHT_MAX_AGE: Duration = Duration::from_secs(10 * 60);
+
+/// Origins allowed to make cross-origin requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AllowedOrigins {
+    Any,
+    /// Serialized origins such as `https://app.example.com`.
+    List(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorsSettings {
+    pub origins: AllowedOrigins,
+    /// Lowercase request header names preflights allow.
+    pub headers: Vec<String>,
+    /// Uppercase method names preflights allow.
+    pub methods: Vec<String>,
+}
+
+impl CorsSettings {
+    /// Read the `INFERENCO_MCP_CORS_*` variables; `None` when CORS is off.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        Self::from_lookup(|name| env::var(name).ok())
+    }
+
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Option<Self>, String> {
+        let origins = list(lookup("INFERENCO_MCP_CORS_ORIGINS"));
+        let origins = match origins.as_slice() {
+            [] => return Ok(None),
+            [any] if any == "*" => AllowedOrigins::Any,
+            _ => AllowedOrigins::List(
+                origins
+                    .iter()
+                    .map(|entry| origin(entry))
+                    .collect::<Result<_, _>>()?,
+            ),
+        };
+
+        let headers = match lookup("INFERENCO_MCP_CORS_HEADERS") {
+            Some(headers) => list(Some(headers)),
+            None => {
+                let auth_header =
+                    lookup("INFERENCO_MCP_AUTH_HEADER").unwrap_or_else(|| "x-api-key".to_string());
+                let mut headers: Vec<String> = DEFAULT_HEADERS
+                    .iter()
+                    .map(|header| header.to_string())
+                    .collect();
+                headers.push(auth_header.trim().to_string());
+                headers
+            }
+        };
+        let mut headers = headers
+            .into_iter()
+            .map(|header| {
+                HeaderName::from_str(&header)
+                    .map(|name| name.as_str().to_string())
+                    .map_err(|_| {
+                        format!("INFERENCO_MCP_CORS_HEADERS entry '{header}' is not a header name")
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        headers.sort();
+        headers.dedup();
+
+        let methods = match lookup("INFERENCO_MCP_CORS_METHODS") {
+            Some(methods) => list(Some(methods)),
+            None => DEFAULT_METHODS
+                .iter()
+                .map(|method| method.to_string())
+                .collect(),
+        };
+        let methods = methods
+            .into_iter()
+            .map(|method| {
+                Method::from_str(&method.to_ascii_uppercase())
+                    .map(|method| method.as_str().to_string())
+                    .map_err(|_| {
+                        format!("INFERENCO_MCP_CORS_METHODS entry '{method}' is not an HTTP method")
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Some(Self {
+            origins,
+            headers,
+            methods,
+        }))
+    }
+}
+
+/// The non-empty entries of a comma-separated value.
+fn list(value: Option<String>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// `entry` as a serialized origin: scheme, host, and port, nothing else.
+fn origin(entry: &str) -> Result<String, String> {
+    let invalid = || {
+        format!(
+            "INFERENCO_MCP_CORS_ORIGINS entry '{entry}' is not an origin such as https://app.example.com"
+        )
+    };
+    let url = Url::parse(entry).map_err(|_| invalid())?;
+    let origin = url.origin().ascii_serialization();
+    if !matches!(url.scheme(), "http" | "https") || origin != entry.trim_end_matches('/') {
+        return Err(invalid());
+    }
+    Ok(origin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn settings(vars: &[(&str, &str)]) -> Result<Option<CorsSettings>, String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        CorsSettings::from_lookup(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn origins_turn_cors_on_and_lists_are_validated() {
+        assert_eq!(settings(&[]), Ok(None));
+        assert_eq!(settings(&[("INFERENCO_MCP_CORS_ORIGINS", " ")]), Ok(None));
+
+        let cors = settings(&[
+            (
+                "INFERENCO_MCP_CORS_ORIGINS",
+                "https://app.example.com/, http://localhost:5173",
+            ),
+            ("INFERENCO_MCP_AUTH_HEADER", "X-Team-Key"),
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            cors.origins,
+            AllowedOrigins::List(vec![
+                "https://app.example.com".to_string(),
+                "http://localhost:5173".to_string()
+            ])
+        );
+        assert!(cors.headers.contains(&"mcp-session-id".to_string()));
+        assert!(cors.headers.contains(&"x-team-key".to_string()));
+        assert_eq!(cors.methods, ["GET", "POST", "DELETE"]);
+
+        let cors = settings(&[
+            ("INFERENCO_MCP_CORS_ORIGINS", "*"),
+            ("INFERENCO_MCP_CORS_HEADERS", "Content-Type"),
+            ("INFERENCO_MCP_CORS_METHODS", "post"),
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(cors.origins, AllowedOrigins::Any);
+        assert_eq!(cors.headers, ["content-type"]);
+        assert_eq!(cors.methods, ["POST"]);
+
+        for (name, value) in [
+            ("INFERENCO_MCP_CORS_ORIGINS", "https://app.example.com/path"),
+            ("INFERENCO_MCP_CORS_ORIGINS", "app.example.com"),
+            ("INFERENCO_MCP_CORS_ORIGINS", "*, https://app.example.com"),
+            ("INFERENCO_MCP_CORS_HEADERS", "bad header"),
+            ("INFERENCO_MCP_CORS_METHODS", "GET POST"),
+        ] {
+            let mut vars = vec![(name, value)];
+            if name != "INFERENCO_MCP_CORS_ORIGINS" {
+                vars.push(("INFERENCO_MCP_CORS_ORIGINS", "*"));
+            }
+            assert!(settings(&vars).unwrap_err().contains(name), "{value}");
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use crate::{config::parse_url, http_client::HttpClient};
+use chrono::Utc;
+use serde::Serialize;
+use std::{env, fmt, future::Future};
+use tokio::sync::mpsc;
+use tracing::{
+    field::{Field, Visit},
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::{layer::Context, Layer};
+
+tokio::task_local! {
+    static CURRENT_TOOL: String;
+}
+
+/// A panic or error-level event forwarded to the crash webhook.
+#[derive(Debug, Clone, Serialize)]
+pub struct CrashEvent {
+    /// `panic` or `error`.
+    pub kind: &'static str,
+    pub message: String,
+    pub timestamp: String,
+    pub release: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+    /// Tool being served when the event fired, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(skip_serializing_if = "serde_json::Map::is_empty")]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Forwards panics and error-level tracing events to a webhook.
+///
+/// Events are queued and delivered by a background task, so reporting never
+/// blocks the code that failed. A panic that takes the whole process down may
+/// exit before its report is delivered.
+#[derive(Clone)]
+pub struct CrashReporter {
+    sender: mpsc::UnboundedSender<CrashEvent>,
+    release: String,
+    environment: Option<String>,
+}
+
+impl CrashReporter {
+    /// Start delivering events to `webhook` as JSON POSTs. Must be called
+    /// inside the tokio runtime.
+    pub fn spawn(webhook: String, environment: Option<String>) -> Self {
+        let (reporter, mut receiver) = Self::channel(environment);
+        let client = HttpClient::default();
+        tokio::spawn(async move {
+            while let Some(event) = receiver.recv().await {
+                let delivered = client
+                    .send("crash_webhook", client.post(&webhook).json(&event))
+                    .await
+                    .and_then(|response| response.error_for_status());
+                if let Err(error) = delivered {
+                    tracing::warn!(%error, kind = event.kind, "failed to deliver crash report");
+                }
+            }
+        });
+        reporter
+    }
+
+    fn channel(environment: Option<String>) -> (Self, mpsc::UnboundedReceiver<CrashEvent>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let reporter = Self {
+            sender,
+            release: format!("{}@{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            environment,
+        };
+        (reporter, receiver)
+    }
+
+    /// Report panics, then hand over to the previously installed hook.
+    pub fn install_panic_hook(&self) {
+        let reporter = self.clone();
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let payload = info.payload();
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "panic with non-string payload".to_string());
+            let mut event = reporter.event("panic", message);
+            event.location = info
+                .location()
+                .map(|location| format!("{}:{}", location.file(), location.line()));
+            reporter.send(event);
+            previous(info);
+        }));
+    }
+
+    /// Tracing layer that reports every error-level event.
+    pub fn layer(&self) -> CrashReportLayer {
+        CrashReportLayer {
+            reporter: self.clone(),
+        }
+    }
+
+    fn event(&self, kind: &'static str, message: String) -> CrashEvent {
+        CrashEvent {
+            kind,
+            message,
+            timestamp: Utc::now().to_rfc3339(),
+            release: self.release.clone(),
+            environment: self.environment.clone(),
+            tool: CURRENT_TOOL.try_with(Clone::clone).ok(),
+            location: None,
+            fields: serde_json::Map::new(),
+        }
+    }
+
+    fn send(&self, event: CrashEvent) {
+        // The receiver only goes away when the runtime shuts down.
+        let _ = self.sender.send(event);
+    }
+}
+
+/// Where crash reports go and how they are tagged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrashSettings {
+    pub webhook: String,
+    pub environment: Option<String>,
+}
+
+impl CrashSettings {
+    /// Read `INFERENCO_MCP_CRASH_WEBHOOK` and `INFERENCO_MCP_ENVIRONMENT`.
+    /// Crash reporting is off unless the webhook is set.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let webhook = match env::var("INFERENCO_MCP_CRASH_WEBHOOK") {
+            Ok(url) if !url.trim().is_empty() => url.trim().to_string(),
+            _ => return Ok(None),
+        };
+        parse_url("INFERENCO_MCP_CRASH_WEBHOOK", &webhook)?;
+        Ok(Some(Self {
+            webhook,
+            environment: env::var("INFERENCO_MCP_ENVIRONMENT")
+                .ok()
+                .filter(|value| !value.trim().is_empty()),
+        }))
+    }
+}
+
+/// See [`CrashReporter::layer`].
+pub struct CrashReportLayer {
+    reporter: CrashReporter,
+}
+
+impl<S: Subscriber> Layer<S> for CrashReportLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        // Skip our own delivery failures so a broken webhook cannot loop.
+        if *metadata.level() != Level::ERROR || metadata.target() == module_path!() {
+            return;
+        }
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let mut report = self.reporter.event("error", visitor.message);
+        report.location = metadata
+            .file()
+            .zip(metadata.line())
+            .map(|(file, line)| format!("{file}:{line}"));
+        report.fields = visitor.fields;
+        self.reporter.send(report);
+    }
+}
+
+/// An event's message and its other fields as JSON.
+#[derive(Default)]
+pub(crate) struct FieldVisitor {
+    pub(crate) message: String,
+    pub(crate) fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.record(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.record(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.record(field, value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record(field, format!("{value:?}").into());
+    }
+}
+
+impl FieldVisitor {
+    fn record(&mut self, field: &Field, value: serde_json::Value) {
+        if field.name() == "message" {
+            self.message = match value {
+                serde_json::Value::String(message) => message,
+                other => other.to_string(),
+            };
+        } else {
+            self.fields.insert(field.name().to_string(), value);
+        }
+    }
+}
+
+/// Run `future` with `tool` attached to any crash report it triggers.
+pub async fn with_tool_context<F: Future>(tool: &str, future: F) -> F::Output {
+    CURRENT_TOOL.scope(tool.to_string(), future).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[tokio::test]
+    async fn reports_error_events_with_tool_context() {
+        let (reporter, mut receiver) = CrashReporter::channel(Some("staging".to_string()));
+        let subscriber = tracing_subscriber::registry().with(reporter.layer());
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        tracing::warn!("not reported");
+        with_tool_context("echo", async {
+            tracing::error!(attempt = 3, "upstream exploded");
+        })
+        .await;
+
+        let event = receiver.try_recv().expect("error event reported");
+        assert_eq!(event.kind, "error");
+        assert_eq!(event.message, "upstream exploded");
+        assert_eq!(event.tool.as_deref(), Some("echo"));
+        assert_eq!(event.environment.as_deref(), Some("staging"));
+        assert_eq!(event.fields["attempt"], 3);
+        assert!(event.release.starts_with("inferenco-mcp@"));
+        assert!(receiver.try_recv().is_err());
+    }
+}
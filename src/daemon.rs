@@ -0,0 +1,145 @@
+//! Running as a background service.
+//!
+//! On Unix, `serve --daemon` detaches from the terminal before the tokio
+//! runtime starts (forking a multi-threaded process is unsound), optionally
+//! writing a PID file and redirecting output to a log file. On Windows the
+//! service control manager's stop request is delivered through
+//! [`request_stop`].
+
+use std::{fs, io, path::Path, path::PathBuf};
+use tokio::sync::Notify;
+
+static STOP: Notify = Notify::const_new();
+
+/// Ask the server to drain and exit, as `SIGTERM` does.
+pub fn request_stop() {
+    STOP.notify_one();
+}
+
+/// Resolves once [`request_stop`] has been called.
+pub async fn stop_requested() {
+    STOP.notified().await;
+}
+
+/// The PID file written by [`daemonize`]; removed on drop unless a successor
+/// process has taken it over.
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        if read_pid(&self.path) == Some(std::process::id()) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn read_pid(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Detach from the controlling terminal: fork twice around `setsid`, point
+/// stdin at `/dev/null` and stdout/stderr at `log_output` (or `/dev/null`),
+/// then record the daemon's PID in `pid_file`. The original process exits.
+///
+/// Fails without forking if `pid_file` names a running process, other than
+/// the predecessor of a zero-downtime restart.
+#[cfg(unix)]
+pub fn daemonize(
+    pid_file: Option<&Path>,
+    log_output: Option<&Path>,
+) -> io::Result<Option<PidFile>> {
+    use std::os::fd::AsRawFd;
+
+    if let Some(path) = pid_file {
+        if let Some(pid) = read_pid(path).filter(|pid| is_running(*pid)) {
+            let predecessor = std::env::var(crate::upgrade::PARENT_PID_VAR)
+                .ok()
+                .and_then(|parent| parent.parse().ok());
+            if predecessor != Some(pid) {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("already running as pid {pid} ({})", path.display()),
+                ));
+            }
+        }
+    }
+    // Opened before forking so a bad path is reported on the terminal.
+    let output = match log_output {
+        Some(path) => fs::OpenOptions::new().create(true).append(true).open(path),
+        None => fs::OpenOptions::new().write(true).open("/dev/null"),
+    }
+    .map_err(|error| io::Error::new(error.kind(), format!("cannot open log output: {error}")))?;
+    let null = fs::File::open("/dev/null")?;
+
+    fork_and_exit_parent()?;
+    // SAFETY: no preconditions; fails only if already a group leader, which
+    // the fork above rules out.
+    if unsafe { libc::setsid() } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // A second fork means the daemon is not a session leader and can never
+    // reacquire a controlling terminal.
+    fork_and_exit_parent()?;
+
+    for (from, to) in [
+        (null.as_raw_fd(), libc::STDIN_FILENO),
+        (output.as_raw_fd(), libc::STDOUT_FILENO),
+        (output.as_raw_fd(), libc::STDERR_FILENO),
+    ] {
+        // SAFETY: both descriptors are open for the duration of the call.
+        if unsafe { libc::dup2(from, to) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    let Some(path) = pid_file else {
+        return Ok(None);
+    };
+    fs::write(path, format!("{}\n", std::process::id()))?;
+    Ok(Some(PidFile {
+        path: path.to_path_buf(),
+    }))
+}
+
+#[cfg(unix)]
+fn fork_and_exit_parent() -> io::Result<()> {
+    // SAFETY: called before any other thread is started.
+    match unsafe { libc::fork() } {
+        -1 => Err(io::Error::last_os_error()),
+        0 => Ok(()),
+        _ => std::process::exit(0),
+    }
+}
+
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // SAFETY: signal 0 only checks that the process exists.
+    let delivered = unsafe { libc::kill(pid, 0) } == 0;
+    delivered || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn refuses_to_start_over_a_running_pid() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let path = dir.path().join("mcp.pid");
+        fs::write(&path, format!("{}\n", std::process::id())).unwrap();
+
+        let error = daemonize(Some(&path), None).expect_err("this process is running");
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+
+        // Only the process named in the file removes it.
+        drop(PidFile { path: path.clone() });
+        assert!(!path.exists());
+    }
+}
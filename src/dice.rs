@@ -0,0 +1,176 @@
+//! Dice notation and rolling for `roll_dice`.
+//!
+//! Notation is `NdS` with an optional `+M` or `-M`: `3d6+2`, `d20`, `2d10-1`,
+//! or `d%` for a hundred-sided die. Seeded rolls repeat exactly for a given
+//! build, which keeps tests and demos stable.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rmcp::schemars::{self, JsonSchema};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Most dice in one roll, and the largest die.
+pub const MAX_DICE: u32 = 100;
+pub const MAX_SIDES: u32 = 1000;
+/// Largest modifier, either way.
+pub const MAX_MODIFIER: i64 = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dice {
+    pub count: u32,
+    pub sides: u32,
+    pub modifier: i64,
+}
+
+/// One roll of every die, plus the modifier.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Roll {
+    pub dice: Vec<u32>,
+    pub total: i64,
+}
+
+/// Every roll made for one request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Outcome {
+    pub notation: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    pub rolls: Vec<Roll>,
+    /// Sum of every roll's total.
+    pub total: i64,
+}
+
+impl Dice {
+    pub fn parse(notation: &str) -> Result<Dice, String> {
+        let compact: String = notation
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_lowercase();
+        let malformed = || format!("expected dice notation like 3d6+2, found '{notation}'");
+        let (count, rest) = compact.split_once('d').ok_or_else(malformed)?;
+        let count = match count {
+            "" => 1,
+            count => count.parse().map_err(|_| malformed())?,
+        };
+        let (sides, modifier) = match rest.find(['+', '-']) {
+            Some(at) => rest.split_at(at),
+            None => (rest, ""),
+        };
+        let sides = match sides {
+            "%" => 100,
+            sides => sides.parse().map_err(|_| malformed())?,
+        };
+        let modifier = match modifier {
+            "" => 0,
+            modifier => modifier
+                .trim_start_matches('+')
+                .parse::<i64>()
+                .map_err(|_| malformed())?,
+        };
+        if !(1..=MAX_DICE).contains(&count) {
+            return Err(format!("roll 1 to {MAX_DICE} dice at a time"));
+        }
+        if !(2..=MAX_SIDES).contains(&sides) {
+            return Err(format!("dice need 2 to {MAX_SIDES} sides"));
+        }
+        if modifier.unsigned_abs() > MAX_MODIFIER.unsigned_abs() {
+            return Err(format!("modifiers are limited to ±{MAX_MODIFIER}"));
+        }
+        Ok(Dice {
+            count,
+            sides,
+            modifier,
+        })
+    }
+
+    fn roll(&self, rng: &mut impl Rng) -> Roll {
+        let dice: Vec<u32> = (0..self.count)
+            .map(|_| rng.gen_range(1..=self.sides))
+            .collect();
+        let total = dice.iter().map(|&value| i64::from(value)).sum::<i64>() + self.modifier;
+        Roll { dice, total }
+    }
+}
+
+impl fmt::Display for Dice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}d{}", self.count, self.sides)?;
+        match self.modifier {
+            0 => Ok(()),
+            modifier if modifier > 0 => write!(f, "+{modifier}"),
+            modifier => write!(f, "{modifier}"),
+        }
+    }
+}
+
+/// Roll `dice` `times` times, from `seed` when given.
+pub fn roll(dice: &Dice, times: u32, seed: Option<u64>) -> Outcome {
+    let rolls: Vec<Roll> = match seed {
+        Some(seed) => {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..times).map(|_| dice.roll(&mut rng)).collect()
+        }
+        None => {
+            let mut rng = rand::thread_rng();
+            (0..times).map(|_| dice.roll(&mut rng)).collect()
+        }
+    };
+    Outcome {
+        notation: dice.to_string(),
+        seed,
+        total: rolls.iter().map(|roll| roll.total).sum(),
+        rolls,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_notation_and_rejects_out_of_range_dice() {
+        let parse = |notation| Dice::parse(notation).map(|dice| dice.to_string());
+        assert_eq!(parse("3d6+2").unwrap(), "3d6+2");
+        assert_eq!(parse(" D20 ").unwrap(), "1d20");
+        assert_eq!(parse("2d% - 1").unwrap(), "2d100-1");
+        assert_eq!(parse("d1").unwrap_err(), "dice need 2 to 1000 sides");
+        assert_eq!(parse("0d6").unwrap_err(), "roll 1 to 100 dice at a time");
+        assert_eq!(
+            parse("3x6").unwrap_err(),
+            "expected dice notation like 3d6+2, found '3x6'"
+        );
+        for notation in ["1d6-9223372036854775808", "1d6+1000001"] {
+            assert_eq!(
+                parse(notation).unwrap_err(),
+                "modifiers are limited to ±1000000"
+            );
+        }
+    }
+
+    #[test]
+    fn seeded_rolls_repeat_and_stay_in_range() {
+        let dice = Dice::parse("4d6-1").unwrap();
+        let first = roll(&dice, 10, Some(7));
+        assert_eq!(first, roll(&dice, 10, Some(7)));
+        assert_eq!(first.rolls.len(), 10);
+        for outcome in [first, roll(&dice, 10, None)] {
+            for single in &outcome.rolls {
+                assert!(single.dice.iter().all(|value| (1..=6).contains(value)));
+                assert_eq!(
+                    single.total,
+                    single
+                        .dice
+                        .iter()
+                        .map(|&value| i64::from(value))
+                        .sum::<i64>()
+                        - 1
+                );
+            }
+            assert_eq!(
+                outcome.total,
+                outcome.rolls.iter().map(|single| single.total).sum::<i64>()
+            );
+        }
+    }
+}
@@ -0,0 +1,191 @@
+//! Caching DNS resolver for the shared outbound client.
+//!
+//! reqwest asks the system resolver again for every new connection, yet tools
+//! call the same few upstream hosts over and over. Answers are kept for a
+//! fixed TTL (the system resolver doesn't expose record TTLs), and when a
+//! refresh fails the last good answer is used instead, so a resolver hiccup
+//! doesn't fail calls to a host that was reachable moments ago. That fallback
+//! lasts [`STALE_TTL`] past the TTL; after that a failed lookup fails the
+//! call, so an outage cannot pin a host to old addresses for good.
+
+use crate::cache::{CacheLimits, LruCache, Weigh};
+use futures::future::{self, BoxFuture, FutureExt};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::{
+    io,
+    mem::size_of,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// How long past its TTL an answer may stand in for a failed lookup.
+pub const STALE_TTL: Duration = Duration::from_secs(5 * 60);
+
+type Lookup = Arc<dyn Fn(String) -> BoxFuture<'static, io::Result<Vec<SocketAddr>>> + Send + Sync>;
+
+#[derive(Clone)]
+struct Resolved {
+    addrs: Arc<[SocketAddr]>,
+    at: Instant,
+}
+
+impl Resolved {
+    fn iter(&self) -> Addrs {
+        let addrs = Arc::clone(&self.addrs);
+        Box::new((0..addrs.len()).map(move |index| addrs[index]))
+    }
+}
+
+impl Weigh for Resolved {
+    fn weigh(&self) -> usize {
+        self.addrs.len() * size_of::<SocketAddr>()
+    }
+}
+
+/// Resolves through the system resolver and remembers answers for `ttl`.
+pub struct CachingResolver {
+    ttl: Duration,
+    stale_ttl: Duration,
+    hosts: Arc<LruCache<Resolved>>,
+    lookup: Lookup,
+}
+
+impl CachingResolver {
+    /// Keep answers for `ttl`, for at most `max_hosts` hosts (at least one;
+    /// the cache treats 0 as no limit).
+    pub fn new(ttl: Duration, max_hosts: usize) -> Self {
+        Self::with_lookup(
+            ttl,
+            STALE_TTL,
+            max_hosts,
+            Arc::new(|host| {
+                async move {
+                    tokio::net::lookup_host((host, 0))
+                        .await
+                        .map(Iterator::collect)
+                }
+                .boxed()
+            }),
+        )
+    }
+
+    fn with_lookup(ttl: Duration, stale_ttl: Duration, max_hosts: usize, lookup: Lookup) -> Self {
+        let limits = CacheLimits {
+            max_entries: max_hosts.max(1),
+            max_bytes: 0,
+        };
+        Self {
+            ttl,
+            stale_ttl,
+            hosts: Arc::new(LruCache::new("dns", limits)),
+            lookup,
+        }
+    }
+}
+
+impl Resolve for CachingResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_ascii_lowercase();
+        let cached = self.hosts.get(&host);
+        if let Some(fresh) = cached
+            .as_ref()
+            .filter(|cached| cached.at.elapsed() < self.ttl)
+        {
+            return future::ready(Ok(fresh.iter())).boxed();
+        }
+        let usable_until = self.ttl.saturating_add(self.stale_ttl);
+        let cached = cached.filter(|cached| cached.at.elapsed() < usable_until);
+        let (hosts, lookup) = (Arc::clone(&self.hosts), Arc::clone(&self.lookup));
+        async move {
+            match lookup(host.clone()).await {
+                Ok(addrs) if !addrs.is_empty() => {
+                    let resolved = Resolved {
+                        addrs: addrs.into(),
+                        at: Instant::now(),
+                    };
+                    hosts.insert(host, resolved.clone());
+                    Ok(resolved.iter())
+                }
+                outcome => {
+                    let error = match outcome {
+                        Err(error) => error,
+                        Ok(_) => io::Error::new(io::ErrorKind::NotFound, "no addresses"),
+                    };
+                    let Some(stale) = cached else {
+                        hosts.remove(&host);
+                        return Err(error.into());
+                    };
+                    tracing::warn!(%host, %error, "DNS lookup failed; using the previous answer");
+                    Ok(stale.iter())
+                }
+            }
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn answers_are_reused_until_they_expire_and_kept_when_refresh_fails() {
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let lookup: Lookup = Arc::new({
+            let lookups = Arc::clone(&lookups);
+            move |_host| {
+                let answer = match lookups.fetch_add(1, Ordering::SeqCst) {
+                    0 => Ok(vec![SocketAddr::from(([10, 0, 0, 1], 0))]),
+                    _ => Err(io::Error::other("resolver down")),
+                };
+                future::ready(answer).boxed()
+            }
+        });
+        let name = |host: &str| host.parse::<Name>().expect("valid name");
+        let resolve = |resolver: &CachingResolver, host| {
+            let answer = resolver.resolve(name(host));
+            async move { answer.await.map(Iterator::collect::<Vec<_>>) }
+        };
+        let expected = vec![SocketAddr::from(([10, 0, 0, 1], 0))];
+
+        let resolver = CachingResolver::with_lookup(
+            Duration::from_secs(60),
+            STALE_TTL,
+            8,
+            Arc::clone(&lookup),
+        );
+        for _ in 0..3 {
+            assert_eq!(
+                resolve(&resolver, "Node.example.com")
+                    .await
+                    .expect("resolved"),
+                expected
+            );
+        }
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+
+        // Expired: looked up again, and the failure falls back to the old answer.
+        let resolver = CachingResolver::with_lookup(Duration::ZERO, STALE_TTL, 8, lookup.clone());
+        lookups.store(0, Ordering::SeqCst);
+        resolve(&resolver, "node.example.com")
+            .await
+            .expect("resolved");
+        assert_eq!(
+            resolve(&resolver, "node.example.com").await.expect("stale"),
+            expected
+        );
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+        assert!(resolve(&resolver, "other.example.com").await.is_err());
+
+        // Past the stale TTL too, the failure is reported.
+        let resolver = CachingResolver::with_lookup(Duration::ZERO, Duration::ZERO, 0, lookup);
+        lookups.store(0, Ordering::SeqCst);
+        resolve(&resolver, "node.example.com")
+            .await
+            .expect("resolved");
+        assert!(resolve(&resolver, "node.example.com").await.is_err());
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+    }
+}
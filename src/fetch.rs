@@ -0,0 +1,464 @@
+//! Outbound page retrieval for the `fetch_url` tool.
+//!
+//! Fetching is off until `INFERENCO_MCP_FETCH_ALLOWED_HOSTS` names the hosts
+//! it may reach, and redirects are only followed while they stay on that
+//! list. Bodies are read up to a byte cap; HTML is then reduced to readable
+//! text with [`html::extract`], JSON is parsed, and plain text is passed
+//! through, all clipped to a character budget.
+
+use crate::{
+    config::parse_env,
+    html,
+    http_client::{redact_url, HttpClient},
+};
+use reqwest::{header::CONTENT_TYPE, Url};
+use serde::{Deserialize, Serialize};
+use std::{env, time::Duration};
+
+/// Characters of text returned when the caller does not ask for fewer.
+pub const DEFAULT_MAX_CHARS: usize = 20_000;
+/// Most characters of text one call may ask for.
+pub const MAX_CHARS: usize = 100_000;
+/// Links kept from one HTML page.
+const MAX_LINKS: usize = 100;
+/// Leading bytes checked for NULs, which only binary formats contain.
+const SNIFF_BYTES: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchSettings {
+    /// Exact host names, or `*.example.com` for any subdomain of
+    /// `example.com`. Empty disables fetching.
+    pub allowed_hosts: Vec<String>,
+    /// Body bytes read before the rest is dropped.
+    pub max_bytes: usize,
+    pub timeout: Duration,
+}
+
+impl Default for FetchSettings {
+    fn default() -> Self {
+        Self {
+            allowed_hosts: Vec::new(),
+            max_bytes: 1024 * 1024,
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl FetchSettings {
+    /// Read the `INFERENCO_MCP_FETCH_*` variables.
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = Self::default();
+        let allowed_hosts = env::var("INFERENCO_MCP_FETCH_ALLOWED_HOSTS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(host_pattern)
+            .collect::<Result<_, _>>()?;
+        let max_bytes = parse_env("INFERENCO_MCP_FETCH_MAX_BYTES", "a number of bytes")?
+            .unwrap_or(defaults.max_bytes);
+        if max_bytes == 0 {
+            return Err("INFERENCO_MCP_FETCH_MAX_BYTES must be at least 1".to_string());
+        }
+        let timeout = match parse_env("INFERENCO_MCP_FETCH_TIMEOUT_MS", "a number of milliseconds")?
+        {
+            Some(0) => return Err("INFERENCO_MCP_FETCH_TIMEOUT_MS must be at least 1".to_string()),
+            Some(millis) => Duration::from_millis(millis),
+            None => defaults.timeout,
+        };
+        Ok(Self {
+            allowed_hosts,
+            max_bytes,
+            timeout,
+        })
+    }
+
+    pub fn enabled(&self) -> bool {
+        !self.allowed_hosts.is_empty()
+    }
+
+    /// Whether `url` is http(s) on an allowed host.
+    pub fn allows(&self, url: &Url) -> bool {
+        if !matches!(url.scheme(), "http" | "https") {
+            return false;
+        }
+        let Some(host) = url.host_str().map(str::to_ascii_lowercase) else {
+            return false;
+        };
+        self.allowed_hosts
+            .iter()
+            .any(|pattern| match pattern.strip_prefix("*.") {
+                Some(domain) => host
+                    .strip_suffix(domain)
+                    .is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+                None => host == *pattern,
+            })
+    }
+}
+
+/// Normalise one allowlist entry, rejecting anything that is not a bare
+/// host name, IP address, or `*.domain`.
+fn host_pattern(entry: &str) -> Result<String, String> {
+    let pattern = entry.to_ascii_lowercase();
+    let host = pattern.strip_prefix("*.").unwrap_or(&pattern);
+    let parsed = Url::parse(&format!("http://{host}/"))
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string));
+    if host.is_empty() || parsed.as_deref() != Some(host) {
+        return Err(format!(
+            "INFERENCO_MCP_FETCH_ALLOWED_HOSTS entry '{entry}' is not a host name or *.domain pattern"
+        ));
+    }
+    Ok(pattern)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageKind {
+    Html,
+    Json,
+    Text,
+}
+
+/// What one fetch returned.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Page {
+    /// Where the content came from, after redirects.
+    pub url: String,
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    pub kind: PageKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Readable text for HTML and plain text, and for JSON too large or too
+    /// truncated to return parsed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json: Option<serde_json::Value>,
+    /// Absolute http(s) links found in HTML.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<String>,
+    /// Body bytes read.
+    pub bytes: usize,
+    /// Whether the body or the text was cut short.
+    pub truncated: bool,
+}
+
+#[derive(Clone)]
+pub struct Fetcher {
+    settings: FetchSettings,
+    client: HttpClient,
+}
+
+impl Fetcher {
+    pub fn new(settings: FetchSettings) -> Result<Self, String> {
+        let allowlist = settings.clone();
+        let client = HttpClient::with_redirect_filter(Some(settings.timeout), move |url| {
+            allowlist.allows(url)
+        })?;
+        Ok(Self { settings, client })
+    }
+
+    pub fn settings(&self) -> &FetchSettings {
+        &self.settings
+    }
+
+    /// Parse `url` and check it against the allowlist.
+    pub fn check(&self, url: &str) -> Result<Url, String> {
+        let parsed = Url::parse(url.trim()).map_err(|error| format!("invalid URL: {error}"))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(format!(
+                "only http and https URLs can be fetched, not {}:",
+                parsed.scheme()
+            ));
+        }
+        if !self.settings.allows(&parsed) {
+            return Err(format!(
+                "{} is not on the fetch allowlist",
+                parsed.host_str().unwrap_or("this host")
+            ));
+        }
+        Ok(parsed)
+    }
+
+    /// Retrieve `url`, which [`check`](Self::check) accepted, keeping at
+    /// most `max_chars` characters of text.
+    pub async fn fetch(&self, url: Url, max_chars: usize) -> Result<Page, String> {
+        let mut response = self
+            .client
+            .send("fetch_url", self.client.get(url.as_str()))
+            .await
+            .map_err(|error| format!("cannot fetch {}: {error}", redact_url(&url)))?;
+        let status = response.status();
+        if status.is_redirection() {
+            return Err(format!(
+                "{} redirected to a host not on the fetch allowlist",
+                redact_url(response.url())
+            ));
+        }
+        if !status.is_success() {
+            return Err(format!(
+                "{} returned HTTP {status}",
+                redact_url(response.url())
+            ));
+        }
+        let final_url = response.url().clone();
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let declared = match content_type.as_deref() {
+            Some(content_type) => declared_kind(content_type)?,
+            None => None,
+        };
+
+        let mut body = Vec::new();
+        let mut truncated = false;
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|error| format!("cannot read {}: {error}", redact_url(&final_url)))?
+        {
+            let room = self.settings.max_bytes - body.len();
+            if chunk.len() > room {
+                body.extend_from_slice(&chunk[..room]);
+                truncated = true;
+                break;
+            }
+            body.extend_from_slice(&chunk);
+        }
+        if body[..body.len().min(SNIFF_BYTES)].contains(&0) {
+            return Err(format!(
+                "{} looks like binary content",
+                redact_url(&final_url)
+            ));
+        }
+        // Extracting text from up to `max_bytes` of HTML, or parsing as much
+        // JSON, would hold up the runtime thread.
+        let status = status.as_u16();
+        tokio::task::spawn_blocking(move || {
+            read_page(
+                final_url,
+                status,
+                content_type,
+                declared,
+                &body,
+                truncated,
+                max_chars,
+            )
+        })
+        .await
+        .map_err(|error| error.to_string())
+    }
+}
+
+/// The page a fetched `body` holds, of the `declared` kind or else the
+/// sniffed one, keeping at most `max_chars` characters of text.
+fn read_page(
+    url: Url,
+    status: u16,
+    content_type: Option<String>,
+    declared: Option<PageKind>,
+    body: &[u8],
+    truncated: bool,
+    max_chars: usize,
+) -> Page {
+    let raw = String::from_utf8_lossy(body);
+    let kind = declared.unwrap_or_else(|| sniff(&raw));
+    let mut page = Page {
+        url: url.to_string(),
+        status,
+        content_type,
+        kind,
+        title: None,
+        text: None,
+        json: None,
+        links: Vec::new(),
+        bytes: body.len(),
+        truncated,
+    };
+    let text = match kind {
+        PageKind::Html => {
+            let extracted = html::extract(&raw);
+            page.title = extracted.title;
+            page.links = resolve_links(&url, &extracted.links);
+            extracted.text
+        }
+        PageKind::Json => {
+            let parsed = (!truncated)
+                .then(|| serde_json::from_str::<serde_json::Value>(&raw).ok())
+                .flatten()
+                .filter(|value| value.to_string().chars().count() <= max_chars);
+            match parsed {
+                Some(value) => {
+                    page.json = Some(value);
+                    return page;
+                }
+                None => raw.into_owned(),
+            }
+        }
+        PageKind::Text => raw.into_owned(),
+    };
+    let (text, clipped) = clip(text, max_chars);
+    page.text = Some(text);
+    page.truncated |= clipped;
+    page
+}
+
+/// The kind a `Content-Type` names, `None` when it leaves it to sniffing,
+/// or an error for binary types.
+fn declared_kind(content_type: &str) -> Result<Option<PageKind>, String> {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    Ok(Some(match mime.as_str() {
+        "" | "application/octet-stream" => return Ok(None),
+        "text/html" | "application/xhtml+xml" => PageKind::Html,
+        "application/json" => PageKind::Json,
+        mime if mime.ends_with("+json") => PageKind::Json,
+        "application/xml" | "application/javascript" | "application/x-ndjson" => PageKind::Text,
+        mime if mime.starts_with("text/") || mime.ends_with("+xml") => PageKind::Text,
+        mime => return Err(format!("cannot read {mime} content")),
+    }))
+}
+
+/// Guess the kind of an undeclared body from how it starts.
+fn sniff(body: &str) -> PageKind {
+    let start = body.trim_start();
+    let head = start
+        .char_indices()
+        .nth(SNIFF_BYTES)
+        .map_or(start, |(end, _)| &start[..end])
+        .to_ascii_lowercase();
+    if head.starts_with("<!doctype html") || head.contains("<html") {
+        PageKind::Html
+    } else if start.starts_with(['{', '[']) {
+        PageKind::Json
+    } else {
+        PageKind::Text
+    }
+}
+
+fn resolve_links(base: &Url, hrefs: &[String]) -> Vec<String> {
+    let mut links = Vec::new();
+    for href in hrefs {
+        let Ok(url) = base.join(href) else { continue };
+        let link = url.to_string();
+        if matches!(url.scheme(), "http" | "https") && !links.contains(&link) {
+            links.push(link);
+            if links.len() == MAX_LINKS {
+                break;
+            }
+        }
+    }
+    links
+}
+
+/// The first `max_chars` characters of `text`, and whether any were cut.
+fn clip(mut text: String, max_chars: usize) -> (String, bool) {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => {
+            text.truncate(end);
+            (text, true)
+        }
+        None => (text, false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    #[test]
+    fn allowlist_matches_exact_hosts_and_subdomain_patterns() {
+        let settings = FetchSettings {
+            allowed_hosts: ["docs.rs", "*.example.com"]
+                .iter()
+                .map(|entry| host_pattern(entry).unwrap())
+                .collect(),
+            ..FetchSettings::default()
+        };
+        let allows = |url: &str| settings.allows(&Url::parse(url).unwrap());
+        assert!(allows("https://docs.rs/serde"));
+        assert!(allows("http://api.Example.com/v1"));
+        assert!(!allows("https://example.com/"));
+        assert!(!allows("https://badexample.com/"));
+        assert!(!allows("https://docs.rs.evil.net/"));
+        assert!(!allows("ftp://docs.rs/"));
+        assert!(host_pattern("docs.rs:443").is_err());
+        assert!(host_pattern("https://docs.rs").is_err());
+        assert!(host_pattern("*.").is_err());
+    }
+
+    /// Serves `/page` as HTML, redirects `/start` there, and redirects
+    /// `/away` to the same page under a host name the allowlist lacks.
+    async fn serve() -> std::net::SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let address = listener.local_addr().expect("address");
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = vec![0; 4096];
+                let read = socket.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..read]);
+                let path = request.split_whitespace().nth(1).unwrap_or("/");
+                let response = match path {
+                    "/start" => format!(
+                        "HTTP/1.1 302 Found\r\nLocation: http://{address}/page\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    ),
+                    "/away" => format!(
+                        "HTTP/1.1 302 Found\r\nLocation: http://localhost:{}/page\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        address.port()
+                    ),
+                    _ => {
+                        let body = "<html><head><title>Guide</title></head><body>\
+                                    <p>Read <a href=\"next\">on</a> or <a href=\"mailto:x@y.z\">write</a>.</p>\
+                                    </body></html>";
+                        format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                            body.len()
+                        )
+                    }
+                };
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        address
+    }
+
+    #[tokio::test]
+    async fn follows_allowed_redirects_and_extracts_html() {
+        let address = serve().await;
+        let fetcher = Fetcher::new(FetchSettings {
+            allowed_hosts: vec!["127.0.0.1".to_string()],
+            ..FetchSettings::default()
+        })
+        .expect("fetcher");
+
+        let url = fetcher.check(&format!("http://{address}/start")).unwrap();
+        let page = fetcher.fetch(url, 7).await.expect("page");
+        assert_eq!(page.url, format!("http://{address}/page"));
+        assert_eq!(page.kind, PageKind::Html);
+        assert_eq!(page.title.as_deref(), Some("Guide"));
+        assert_eq!(page.text.as_deref(), Some("Read on"));
+        assert!(page.truncated);
+        assert_eq!(page.links, vec![format!("http://{address}/next")]);
+
+        let url = fetcher.check(&format!("http://{address}/away")).unwrap();
+        let error = fetcher.fetch(url, 100).await.unwrap_err();
+        assert!(
+            error.ends_with("redirected to a host not on the fetch allowlist"),
+            "{error}"
+        );
+        assert!(fetcher.check("http://localhost/").is_err());
+    }
+}
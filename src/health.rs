@@ -0,0 +1,392 @@
+use crate::{
+    alerts::AlertEngine, config::parse_url, http_client::HttpClient, maintenance::MaintenanceMode,
+};
+use serde::Serialize;
+use std::{
+    env,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+const DEFAULT_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An upstream dependency checked by the deep health endpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthProbe {
+    pub name: String,
+    pub url: String,
+    /// A failing critical probe makes the server unhealthy; a failing
+    /// non-critical probe only degrades it.
+    pub critical: bool,
+}
+
+/// Probe configuration for [`HealthChecker`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthSettings {
+    pub probes: Vec<HealthProbe>,
+    pub timeout: Duration,
+}
+
+impl HealthSettings {
+    /// Read `INFERENCO_MCP_HEALTH_PROBES` (comma-separated `name=url` pairs),
+    /// `INFERENCO_MCP_HEALTH_OPTIONAL` (names that only degrade health), and
+    /// `INFERENCO_MCP_HEALTH_TIMEOUT_MS`.
+    pub fn from_env() -> Result<Self, String> {
+        let optional: Vec<String> = env::var("INFERENCO_MCP_HEALTH_OPTIONAL")
+            .unwrap_or_default()
+            .split(',')
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .collect();
+        let mut probes =
+            parse_probes(&env::var("INFERENCO_MCP_HEALTH_PROBES").unwrap_or_default())?;
+        for probe in &mut probes {
+            probe.critical = !optional.contains(&probe.name);
+        }
+
+        let timeout = match env::var("INFERENCO_MCP_HEALTH_TIMEOUT_MS") {
+            Ok(value) => Duration::from_millis(value.parse().map_err(|_| {
+                format!("INFERENCO_MCP_HEALTH_TIMEOUT_MS must be a number of milliseconds, got '{value}'")
+            })?),
+            Err(_) => DEFAULT_PROBE_TIMEOUT,
+        };
+
+        Ok(Self { probes, timeout })
+    }
+}
+
+/// Overall health classification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Ok,
+    Degraded,
+    Unhealthy,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DependencyReport {
+    pub name: String,
+    pub status: HealthStatus,
+    pub critical: bool,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub dependencies: Vec<DependencyReport>,
+}
+
+/// Runs the configured upstream probes concurrently.
+pub struct HealthChecker {
+    client: HttpClient,
+    probes: Vec<HealthProbe>,
+}
+
+impl HealthChecker {
+    pub fn new(probes: Vec<HealthProbe>, timeout: Duration) -> Self {
+        Self {
+            client: HttpClient::new(Some(timeout)),
+            probes,
+        }
+    }
+
+    pub fn from_settings(settings: &HealthSettings) -> Self {
+        Self::new(settings.probes.clone(), settings.timeout)
+    }
+
+    /// Count failed probes towards upstream failure alerts.
+    pub fn with_alerts(mut self, alerts: Arc<AlertEngine>) -> Self {
+        self.client = self.client.with_alerts(alerts);
+        self
+    }
+
+    pub fn probes(&self) -> &[HealthProbe] {
+        &self.probes
+    }
+
+    /// Probe every dependency and classify the result.
+    pub async fn check(&self) -> HealthReport {
+        let dependencies =
+            futures::future::join_all(self.probes.iter().map(|probe| self.probe(probe))).await;
+        HealthReport {
+            status: classify(&dependencies),
+            dependencies,
+        }
+    }
+
+    async fn probe(&self, probe: &HealthProbe) -> DependencyReport {
+        let started = Instant::now();
+        let outcome = self
+            .client
+            .send(&probe.name, self.client.get(&probe.url))
+            .await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+
+        let (status, detail) = match outcome {
+            Ok(response) if response.status().is_success() => (HealthStatus::Ok, None),
+            Ok(response) => (
+                HealthStatus::Unhealthy,
+                Some(format!("unexpected status {}", response.status())),
+            ),
+            Err(error) if error.is_timeout() => {
+                (HealthStatus::Unhealthy, Some("timed out".to_string()))
+            }
+            Err(error) if error.is_connect() => (
+                HealthStatus::Unhealthy,
+                Some("connection failed".to_string()),
+            ),
+            Err(_) => (HealthStatus::Unhealthy, Some("request failed".to_string())),
+        };
+
+        if status != HealthStatus::Ok {
+            tracing::warn!(
+                dependency = %probe.name,
+                latency_ms,
+                detail = detail.as_deref().unwrap_or_default(),
+                "health probe failed"
+            );
+        }
+
+        DependencyReport {
+            name: probe.name.clone(),
+            status,
+            critical: probe.critical,
+            latency_ms,
+            detail,
+        }
+    }
+}
+
+/// Process-wide readiness flags consulted by `/readyz`.
+///
+/// Liveness only says the process is running; readiness additionally
+/// requires the tool registry to be built, the configuration to be valid,
+/// critical upstreams to be reachable, and the server to be neither draining
+/// nor in maintenance.
+#[derive(Debug, Default)]
+pub struct Readiness {
+    registry_built: AtomicBool,
+    config_valid: AtomicBool,
+    draining: AtomicBool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadinessCheck {
+    pub name: &'static str,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadinessReport {
+    pub ready: bool,
+    pub checks: Vec<ReadinessCheck>,
+}
+
+impl Readiness {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mark_registry_built(&self) {
+        self.registry_built.store(true, Ordering::Release);
+    }
+
+    pub fn set_config_valid(&self, valid: bool) {
+        self.config_valid.store(valid, Ordering::Release);
+    }
+
+    /// Stop advertising readiness so load balancers drain traffic away.
+    pub fn start_draining(&self) {
+        self.draining.store(true, Ordering::Release);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Acquire)
+    }
+
+    /// Evaluate every readiness condition, probing critical upstreams.
+    pub async fn evaluate(
+        &self,
+        health: &HealthChecker,
+        maintenance: &MaintenanceMode,
+    ) -> ReadinessReport {
+        let check = |name, ok: bool, detail: &str| ReadinessCheck {
+            name,
+            ok,
+            detail: (!ok).then(|| detail.to_string()),
+        };
+        let mut checks = vec![
+            check(
+                "registry",
+                self.registry_built.load(Ordering::Acquire),
+                "tool registry not built yet",
+            ),
+            check(
+                "config",
+                self.config_valid.load(Ordering::Acquire),
+                "configuration invalid",
+            ),
+            check("draining", !self.is_draining(), "server is draining"),
+            check(
+                "maintenance",
+                !maintenance.is_enabled(),
+                "server is in maintenance",
+            ),
+        ];
+
+        let failing: Vec<String> = health
+            .check()
+            .await
+            .dependencies
+            .into_iter()
+            .filter(|dep| dep.critical && dep.status != HealthStatus::Ok)
+            .map(|dep| dep.name)
+            .collect();
+        checks.push(ReadinessCheck {
+            name: "upstreams",
+            ok: failing.is_empty(),
+            detail: (!failing.is_empty()).then(|| format!("unreachable: {}", failing.join(", "))),
+        });
+
+        ReadinessReport {
+            ready: checks.iter().all(|check| check.ok),
+            checks,
+        }
+    }
+}
+
+/// Parse `name=url` pairs separated by commas. Probes default to critical.
+pub fn parse_probes(spec: &str) -> Result<Vec<HealthProbe>, String> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (name, url) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("health probe '{entry}' must be written as name=url"))?;
+            let (name, url) = (name.trim(), url.trim());
+            if name.is_empty() {
+                return Err(format!("health probe '{entry}' is missing a name"));
+            }
+            parse_url(&format!("health probe '{name}'"), url)?;
+            Ok(HealthProbe {
+                name: name.to_string(),
+                url: url.to_string(),
+                critical: true,
+            })
+        })
+        .collect()
+}
+
+fn classify(dependencies: &[DependencyReport]) -> HealthStatus {
+    let failing = |critical: bool| {
+        dependencies
+            .iter()
+            .any(|dep| dep.critical == critical && dep.status != HealthStatus::Ok)
+    };
+    if failing(true) {
+        HealthStatus::Unhealthy
+    } else if failing(false) {
+        HealthStatus::Degraded
+    } else {
+        HealthStatus::Ok
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maintenance::MaintenanceState;
+
+    fn report(critical: bool, status: HealthStatus) -> DependencyReport {
+        DependencyReport {
+            name: "dep".to_string(),
+            status,
+            critical,
+            latency_ms: 0,
+            detail: None,
+        }
+    }
+
+    #[test]
+    fn parses_probe_list() {
+        let probes = parse_probes("docs=https://docs.example.com, node=http://localhost:8080/v1")
+            .expect("valid spec");
+        assert_eq!(probes.len(), 2);
+        assert_eq!(probes[0].name, "docs");
+        assert_eq!(probes[1].url, "http://localhost:8080/v1");
+        assert!(parse_probes("docs").is_err());
+        assert!(parse_probes("docs=ftp://example.com").is_err());
+        assert!(parse_probes("").expect("empty spec").is_empty());
+    }
+
+    #[test]
+    fn classification_distinguishes_critical_failures() {
+        assert_eq!(classify(&[]), HealthStatus::Ok);
+        assert_eq!(
+            classify(&[
+                report(true, HealthStatus::Ok),
+                report(false, HealthStatus::Unhealthy)
+            ]),
+            HealthStatus::Degraded
+        );
+        assert_eq!(
+            classify(&[
+                report(true, HealthStatus::Unhealthy),
+                report(false, HealthStatus::Ok)
+            ]),
+            HealthStatus::Unhealthy
+        );
+    }
+
+    #[tokio::test]
+    async fn readiness_requires_registry_config_and_no_draining() {
+        let health = HealthChecker::new(Vec::new(), Duration::from_secs(1));
+        let maintenance = MaintenanceMode::new();
+        let readiness = Readiness::new();
+        assert!(!readiness.evaluate(&health, &maintenance).await.ready);
+
+        readiness.mark_registry_built();
+        readiness.set_config_valid(true);
+        assert!(readiness.evaluate(&health, &maintenance).await.ready);
+
+        maintenance.set(MaintenanceState {
+            enabled: true,
+            ..Default::default()
+        });
+        assert!(!readiness.evaluate(&health, &maintenance).await.ready);
+        maintenance.set(MaintenanceState::default());
+
+        readiness.start_draining();
+        let report = readiness.evaluate(&health, &maintenance).await;
+        assert!(!report.ready);
+        assert!(report
+            .checks
+            .iter()
+            .any(|check| check.name == "draining" && !check.ok));
+    }
+
+    #[tokio::test]
+    async fn unreachable_dependency_is_reported() {
+        let checker = HealthChecker::new(
+            vec![HealthProbe {
+                name: "closed".to_string(),
+                url: "http://127.0.0.1:9/".to_string(),
+                critical: false,
+            }],
+            Duration::from_millis(500),
+        );
+        let report = checker.check().await;
+        assert_eq!(report.status, HealthStatus::Degraded);
+        assert_eq!(report.dependencies[0].status, HealthStatus::Unhealthy);
+    }
+}
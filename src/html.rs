@@ -0,0 +1,341 @@
+//! Readable text from HTML, for `fetch_url`.
+//!
+//! This is a tag scanner, not a browser: it drops scripts, styles, and
+//! comments, turns block elements into line breaks, headings into `#` lines
+//! and list items into `- ` lines, decodes entities, and keeps the title and
+//! link targets. That is enough to read documentation and articles without
+//! pulling in a full HTML parser.
+
+/// Elements whose content is never text.
+const SKIPPED: &[&str] = &[
+    "script", "style", "noscript", "template", "svg", "iframe", "object", "canvas",
+];
+
+/// Elements that start a new line.
+const BLOCKS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "header",
+    "hr",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "tr",
+    "ul",
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Extracted {
+    pub title: Option<String>,
+    pub text: String,
+    /// `href` values in document order, without duplicates, fragments, or
+    /// `javascript:` links; relative ones are left for the caller to resolve.
+    pub links: Vec<String>,
+}
+
+pub fn extract(html: &str) -> Extracted {
+    let mut extracted = Extracted::default();
+    let mut text = String::new();
+    let mut rest = html;
+    while let Some(open) = rest.find('<') {
+        push_text(&mut text, &rest[..open]);
+        rest = &rest[open..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let Some(tag) = Tag::parse(rest) else {
+            // A stray `<`, as in `a < b`.
+            push_text(&mut text, "<");
+            rest = &rest[1..];
+            continue;
+        };
+        rest = &rest[tag.len..];
+        if tag.closing {
+            if BLOCKS.contains(&tag.name.as_str()) || heading_level(&tag.name).is_some() {
+                text.push('\n');
+            }
+            continue;
+        }
+        if SKIPPED.contains(&tag.name.as_str()) || tag.name == "title" {
+            let (inner, after) = until_closing(rest, &tag.name);
+            if tag.name == "title" && extracted.title.is_none() {
+                let mut title = String::new();
+                push_text(&mut title, inner);
+                let title = collapse(&title);
+                extracted.title = (!title.is_empty()).then_some(title);
+            }
+            rest = after;
+            continue;
+        }
+        match tag.name.as_str() {
+            "li" => text.push_str("\n- "),
+            "td" | "th" => text.push(' '),
+            "a" => {
+                if let Some(href) = attribute(&tag.attributes, "href") {
+                    let href = decode_entities(href.trim());
+                    let usable = !href.is_empty()
+                        && !href.starts_with('#')
+                        && !href.to_ascii_lowercase().starts_with("javascript:");
+                    if usable && !extracted.links.contains(&href) {
+                        extracted.links.push(href);
+                    }
+                }
+            }
+            name => {
+                if let Some(level) = heading_level(name) {
+                    text.push_str("\n\n");
+                    text.push_str(&"#".repeat(level));
+                    text.push(' ');
+                } else if BLOCKS.contains(&name) {
+                    text.push('\n');
+                }
+            }
+        }
+    }
+    push_text(&mut text, rest);
+    extracted.text = tidy(&text);
+    extracted
+}
+
+struct Tag {
+    name: String,
+    closing: bool,
+    attributes: String,
+    /// Bytes from `<` through `>`.
+    len: usize,
+}
+
+impl Tag {
+    /// The tag at the start of `input`, which begins with `<`.
+    fn parse(input: &str) -> Option<Tag> {
+        let body = &input[1..];
+        let (closing, body) = match body.strip_prefix('/') {
+            Some(body) => (true, body),
+            None => (false, body),
+        };
+        // Doctypes and processing instructions are tags without text.
+        let special = body.starts_with(['!', '?']);
+        if !special && !body.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            return None;
+        }
+        let mut quote = None;
+        let end = body.char_indices().find_map(|(index, c)| {
+            match (quote, c) {
+                (Some(open), c) if c == open => quote = None,
+                (None, '"' | '\'') => quote = Some(c),
+                (None, '>') => return Some(index),
+                _ => {}
+            }
+            None
+        })?;
+        let inner = &body[..end];
+        let name_end = inner
+            .find(|c: char| c.is_whitespace() || c == '/')
+            .unwrap_or(inner.len());
+        Some(Tag {
+            name: if special {
+                String::new()
+            } else {
+                inner[..name_end].to_ascii_lowercase()
+            },
+            closing,
+            attributes: inner[name_end..].to_string(),
+            len: input.len() - body.len() + end + 1,
+        })
+    }
+}
+
+fn heading_level(name: &str) -> Option<usize> {
+    match name.as_bytes() {
+        [b'h', level @ b'1'..=b'6'] => Some(usize::from(level - b'0')),
+        _ => None,
+    }
+}
+
+/// The text before `</name>` and what follows it, ignoring case.
+fn until_closing<'a>(input: &'a str, name: &str) -> (&'a str, &'a str) {
+    let closing = format!("</{name}");
+    let lower = input.to_ascii_lowercase();
+    match lower.find(&closing) {
+        Some(start) => {
+            let after = &input[start..];
+            let end = after.find('>').map_or(after.len(), |end| end + 1);
+            (&input[..start], &after[end..])
+        }
+        None => (input, ""),
+    }
+}
+
+/// The value of `name` in a tag's attribute text.
+fn attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    let lower = attributes.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(found) = lower[from..].find(name) {
+        let start = from + found;
+        from = start + name.len();
+        let preceded = lower[..start]
+            .chars()
+            .next_back()
+            .is_none_or(char::is_whitespace);
+        let rest = attributes[from..].trim_start();
+        let Some(value) = rest.strip_prefix('=') else {
+            continue;
+        };
+        if !preceded {
+            continue;
+        }
+        let value = value.trim_start();
+        return Some(match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let value = &value[1..];
+                &value[..value.find(quote).unwrap_or(value.len())]
+            }
+            _ => &value[..value.find(char::is_whitespace).unwrap_or(value.len())],
+        });
+    }
+    None
+}
+
+fn push_text(out: &mut String, raw: &str) {
+    if raw.is_empty() {
+        return;
+    }
+    // Source line breaks are just spaces; structure comes from tags.
+    for c in decode_entities(raw).chars() {
+        out.push(if c.is_whitespace() { ' ' } else { c });
+    }
+}
+
+/// Replace character references such as `&amp;`, `&#39;`, and `&#x2014;`.
+pub fn decode_entities(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| {
+                let name = &rest[1..=end];
+                let c = match name.strip_prefix('#') {
+                    Some(number) => match number.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => number.parse().ok(),
+                    }
+                    .and_then(char::from_u32),
+                    None => named_entity(name),
+                }?;
+                Some((c, end + 2))
+            });
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn named_entity(name: &str) -> Option<char> {
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "mdash" => '—',
+        "ndash" => '–',
+        "hellip" => '…',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "laquo" => '«',
+        "raquo" => '»',
+        "bull" => '•',
+        "middot" => '·',
+        "times" => '×',
+        "deg" => '°',
+        "euro" => '€',
+        _ => return None,
+    })
+}
+
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Collapse spaces within lines and drop empty ones, leaving a blank line
+/// only before headings.
+fn tidy(text: &str) -> String {
+    let mut out = String::new();
+    for line in text.lines().map(collapse).filter(|line| !line.is_empty()) {
+        if !out.is_empty() {
+            out.push_str(if line.starts_with('#') { "\n\n" } else { "\n" });
+        }
+        out.push_str(&line);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_title_structure_and_links_without_scripts() {
+        let html = r##"<!DOCTYPE html>
+            <html><head><title>Docs &amp; Guides</title>
+            <style>body { color: red }</style>
+            <script>if (a < b) { document.write("<p>nope</p>") }</script></head>
+            <body>
+              <!-- navigation -->
+              <h1>Getting   started</h1>
+              <p>Install it with <code>cargo</code>,
+                 then read <a href="/guide">the guide</a> or <a href='https://x.dev/api?a=1&amp;b=2'>API</a>.</p>
+              <ul><li>One</li><li>Two &mdash; 2</li></ul>
+              <p>1 < 2 &#38;&#x26; done</p>
+              <a href="#top">top</a><a href="/guide">again</a>
+            </body></html>"##;
+        let extracted = extract(html);
+        assert_eq!(extracted.title.as_deref(), Some("Docs & Guides"));
+        assert_eq!(
+            extracted.text,
+            "# Getting started\n\
+             Install it with cargo, then read the guide or API.\n\
+             - One\n\
+             - Two — 2\n\
+             1 < 2 && done\n\
+             topagain"
+        );
+        assert_eq!(extracted.links, vec!["/guide", "https://x.dev/api?a=1&b=2"]);
+    }
+}
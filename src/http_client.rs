@@ -0,0 +1,438 @@
+use crate::{
+    alerts::AlertEngine,
+    config::{parse_env, parse_url},
+    dns::CachingResolver,
+    slow_calls,
+};
+use reqwest::{header::HeaderMap, RequestBuilder, Response, Url};
+use std::{
+    env,
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
+use tracing::Instrument;
+
+/// Query parameters whose values never reach logs or error messages.
+const SENSITIVE_PARAMS: &[&str] = &[
+    "access_token",
+    "api_key",
+    "apikey",
+    "auth",
+    "code",
+    "key",
+    "password",
+    "secret",
+    "sig",
+    "signature",
+    "token",
+];
+
+/// Headers whose values never reach logs.
+const SENSITIVE_HEADERS: &[&str] = &[
+    "authorization",
+    "cookie",
+    "proxy-authorization",
+    "set-cookie",
+    "x-api-key",
+];
+
+pub(crate) const REDACTED: &str = "REDACTED";
+
+/// The connection pool every [`HttpClient`] draws from.
+static SHARED: OnceLock<reqwest::Client> = OnceLock::new();
+/// What [`init`] was given, for clients that need a pool of their own.
+static SETTINGS: OnceLock<HttpClientSettings> = OnceLock::new();
+
+/// Redirects followed before giving up, as reqwest does by default.
+const MAX_REDIRECTS: usize = 10;
+
+/// How the shared outbound client connects and identifies itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpClientSettings {
+    pub connect_timeout: Duration,
+    /// Longest wait for the next chunk of a response; `None` leaves it to
+    /// each client's overall timeout.
+    pub read_timeout: Option<Duration>,
+    pub pool_max_idle_per_host: usize,
+    pub pool_idle_timeout: Duration,
+    /// `None` turns TCP keepalive probes off.
+    pub tcp_keepalive: Option<Duration>,
+    /// Proxy URL for every outbound request.
+    pub proxy: Option<String>,
+    pub user_agent: String,
+    /// How long resolved addresses are reused; `None` resolves every new
+    /// connection.
+    pub dns_cache_ttl: Option<Duration>,
+    /// Hosts whose addresses are kept at once.
+    pub dns_cache_max_hosts: usize,
+}
+
+impl Default for HttpClientSettings {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(5),
+            read_timeout: None,
+            pool_max_idle_per_host: 16,
+            pool_idle_timeout: Duration::from_secs(90),
+            tcp_keepalive: Some(Duration::from_secs(60)),
+            proxy: None,
+            user_agent: concat!("inferenco-mcp/", env!("CARGO_PKG_VERSION")).to_string(),
+            dns_cache_ttl: Some(Duration::from_secs(60)),
+            dns_cache_max_hosts: 1024,
+        }
+    }
+}
+
+impl HttpClientSettings {
+    /// Read the `INFERENCO_MCP_HTTP_*` variables.
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = Self::default();
+        let millis = |name| parse_env(name, "a number of milliseconds");
+        let secs = |name| parse_env(name, "a number of seconds");
+        let text = |name| {
+            env::var(name)
+                .ok()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        Ok(Self {
+            connect_timeout: millis("INFERENCO_MCP_HTTP_CONNECT_TIMEOUT_MS")?
+                .map_or(defaults.connect_timeout, Duration::from_millis),
+            read_timeout: millis("INFERENCO_MCP_HTTP_READ_TIMEOUT_MS")?.map(Duration::from_millis),
+            pool_max_idle_per_host: parse_env(
+                "INFERENCO_MCP_HTTP_POOL_MAX_IDLE",
+                "a number of connections",
+            )?
+            .unwrap_or(defaults.pool_max_idle_per_host),
+            pool_idle_timeout: secs("INFERENCO_MCP_HTTP_POOL_IDLE_SECS")?
+                .map_or(defaults.pool_idle_timeout, Duration::from_secs),
+            tcp_keepalive: match secs("INFERENCO_MCP_HTTP_KEEPALIVE_SECS")? {
+                Some(0) => None,
+                Some(secs) => Some(Duration::from_secs(secs)),
+                None => defaults.tcp_keepalive,
+            },
+            proxy: text("INFERENCO_MCP_HTTP_PROXY")
+                .map(|url| parse_url("INFERENCO_MCP_HTTP_PROXY", &url))
+                .transpose()?,
+            user_agent: text("INFERENCO_MCP_HTTP_USER_AGENT").unwrap_or(defaults.user_agent),
+            dns_cache_ttl: match secs("INFERENCO_MCP_HTTP_DNS_TTL_SECS")? {
+                Some(0) => None,
+                Some(secs) => Some(Duration::from_secs(secs)),
+                None => defaults.dns_cache_ttl,
+            },
+            dns_cache_max_hosts: match parse_env(
+                "INFERENCO_MCP_HTTP_DNS_CACHE_HOSTS",
+                "a number of hosts",
+            )? {
+                Some(0) => {
+                    return Err("INFERENCO_MCP_HTTP_DNS_CACHE_HOSTS must be at least 1".to_string())
+                }
+                hosts => hosts.unwrap_or(defaults.dns_cache_max_hosts),
+            },
+        })
+    }
+
+    fn build(&self) -> Result<reqwest::Client, String> {
+        self.builder()?
+            .build()
+            .map_err(|error| format!("cannot build the outbound HTTP client: {error}"))
+    }
+
+    fn builder(&self) -> Result<reqwest::ClientBuilder, String> {
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(self.connect_timeout)
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .tcp_keepalive(self.tcp_keepalive)
+            .user_agent(&self.user_agent);
+        if let Some(timeout) = self.read_timeout {
+            builder = builder.read_timeout(timeout);
+        }
+        if let Some(ttl) = self.dns_cache_ttl {
+            let resolver = CachingResolver::new(ttl, self.dns_cache_max_hosts);
+            builder = builder.dns_resolver(Arc::new(resolver));
+        }
+        if let Some(proxy) = &self.proxy {
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|error| format!("invalid INFERENCO_MCP_HTTP_PROXY: {error}"))?;
+            builder = builder.proxy(proxy);
+        }
+        Ok(builder)
+    }
+}
+
+/// Configure the client every [`HttpClient`] shares. Call it at startup,
+/// before any client is created; once the shared client exists, later
+/// settings are ignored.
+pub fn init(settings: &HttpClientSettings) -> Result<(), String> {
+    let client = settings.build()?;
+    let _ = SETTINGS.set(settings.clone());
+    let _ = SHARED.set(client);
+    Ok(())
+}
+
+/// The settings [`init`] was given, or else those in the environment.
+fn current_settings() -> HttpClientSettings {
+    SETTINGS
+        .get()
+        .cloned()
+        .or_else(|| HttpClientSettings::from_env().ok())
+        .unwrap_or_default()
+}
+
+fn shared() -> reqwest::Client {
+    SHARED
+        .get_or_init(|| {
+            // Without `init` (CLI commands, embedders), read the environment;
+            // `Settings::load` has already reported anything invalid in it.
+            HttpClientSettings::from_env()
+                .and_then(|settings| settings.build())
+                .or_else(|_| HttpClientSettings::default().build())
+                .expect("default reqwest configuration is valid")
+        })
+        .clone()
+}
+
+/// Outbound HTTP client shared by everything that calls upstreams.
+///
+/// Every request runs inside an `outbound_http` span carrying the upstream
+/// name, method, host, redacted path, status, and duration, and its timing is
+/// attributed to the current tool call for slow-call reports. Connection
+/// errors and 5xx responses count towards upstream failure alerts when an
+/// [`AlertEngine`] is attached.
+///
+/// All clients share one connection pool configured by [`init`]; `timeout`
+/// bounds each request made through this handle.
+#[derive(Clone)]
+pub struct HttpClient {
+    inner: reqwest::Client,
+    timeout: Option<Duration>,
+    alerts: Option<Arc<AlertEngine>>,
+}
+
+impl HttpClient {
+    pub fn new(timeout: Option<Duration>) -> Self {
+        Self::with_client(shared(), timeout)
+    }
+
+    /// A client with its own connection pool, otherwise configured like the
+    /// shared one, that only follows redirects to URLs `allow` accepts. A
+    /// refused redirect comes back as the 3xx response itself.
+    pub fn with_redirect_filter(
+        timeout: Option<Duration>,
+        allow: impl Fn(&Url) -> bool + Send + Sync + 'static,
+    ) -> Result<Self, String> {
+        let policy = reqwest::redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else if allow(attempt.url()) {
+                attempt.follow()
+            } else {
+                attempt.stop()
+            }
+        });
+        let inner = current_settings()
+            .builder()?
+            .redirect(policy)
+            .build()
+            .map_err(|error| format!("cannot build the outbound HTTP client: {error}"))?;
+        Ok(Self::with_client(inner, timeout))
+    }
+
+    fn with_client(inner: reqwest::Client, timeout: Option<Duration>) -> Self {
+        Self {
+            inner,
+            timeout,
+            alerts: None,
+        }
+    }
+
+    /// Report upstream failures to `alerts`.
+    pub fn with_alerts(mut self, alerts: Arc<AlertEngine>) -> Self {
+        self.alerts = Some(alerts);
+        self
+    }
+
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        self.with_timeout(self.inner.get(url))
+    }
+
+    pub fn post(&self, url: &str) -> RequestBuilder {
+        self.with_timeout(self.inner.post(url))
+    }
+
+    fn with_timeout(&self, request: RequestBuilder) -> RequestBuilder {
+        match self.timeout {
+            Some(timeout) => request.timeout(timeout),
+            None => request,
+        }
+    }
+
+    /// Send `request`, recording it against `upstream`. Errors carry no URL,
+    /// so they are safe to log or return to clients.
+    pub async fn send(
+        &self,
+        upstream: &str,
+        request: RequestBuilder,
+    ) -> Result<Response, reqwest::Error> {
+        let request = request.build().map_err(reqwest::Error::without_url)?;
+        let url = request.url();
+        let span = tracing::info_span!(
+            "outbound_http",
+            upstream,
+            method = %request.method(),
+            host = url.host_str().unwrap_or("-"),
+            url = %redact_url(url),
+            status = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+        );
+        tracing::debug!(
+            parent: &span,
+            headers = ?redact_headers(request.headers()),
+            "sending outbound request"
+        );
+
+        let started = Instant::now();
+        let outcome = self.inner.execute(request).instrument(span.clone()).await;
+        let elapsed = started.elapsed();
+        slow_calls::record_upstream_timing(upstream, elapsed);
+
+        span.record("duration_ms", elapsed.as_millis() as u64);
+        if let Some(alerts) = &self.alerts {
+            let failed = outcome
+                .as_ref()
+                .map_or(true, |response| response.status().is_server_error());
+            alerts.record_upstream(upstream, failed);
+        }
+        match outcome {
+            Ok(response) => {
+                span.record("status", response.status().as_u16());
+                tracing::debug!(parent: &span, "outbound request completed");
+                Ok(response)
+            }
+            Err(error) => {
+                let error = error.without_url();
+                tracing::warn!(parent: &span, %error, "outbound request failed");
+                Err(error)
+            }
+        }
+    }
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+/// Render `url` without credentials or sensitive query values.
+pub fn redact_url(url: &Url) -> String {
+    let mut url = url.clone();
+    if !url.username().is_empty() || url.password().is_some() {
+        let _ = url.set_username(REDACTED);
+        let _ = url.set_password(None);
+    }
+    if url.query().is_some() {
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(name, value)| {
+                let value = if is_sensitive_param(&name) {
+                    REDACTED.to_string()
+                } else {
+                    value.into_owned()
+                };
+                (name.into_owned(), value)
+            })
+            .collect();
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    url.to_string()
+}
+
+fn is_sensitive_param(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    SENSITIVE_PARAMS.contains(&name.as_str())
+        || name.ends_with("_token")
+        || name.ends_with("_key")
+        || name.ends_with("_secret")
+}
+
+/// Header names and values, with credentials replaced.
+pub fn redact_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if SENSITIVE_HEADERS.contains(&name.as_str()) {
+                REDACTED.to_string()
+            } else {
+                value.to_str().unwrap_or("<binary>").to_string()
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+
+    #[test]
+    fn redacts_credentials_and_sensitive_query_values() {
+        let url = Url::parse(
+            "https://user:pw@node.example.com/v1/accounts?limit=5&api_key=abc&refresh_token=xyz",
+        )
+        .expect("valid url");
+        let redacted = redact_url(&url);
+        assert_eq!(
+            redacted,
+            "https://REDACTED@node.example.com/v1/accounts?limit=5&api_key=REDACTED&refresh_token=REDACTED"
+        );
+    }
+
+    #[tokio::test]
+    async fn requests_use_the_configured_user_agent_and_client_timeout() {
+        use tokio::io::AsyncReadExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind");
+        let address = listener.local_addr().expect("address");
+        // Reads the request and then never answers, holding the socket open.
+        let upstream = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.expect("accept");
+            let mut request = vec![0; 4096];
+            let read = socket.read(&mut request).await.expect("read");
+            (
+                socket,
+                String::from_utf8_lossy(&request[..read]).to_lowercase(),
+            )
+        });
+
+        let settings = HttpClientSettings {
+            user_agent: "probe/1".to_string(),
+            ..HttpClientSettings::default()
+        };
+        let client = HttpClient::with_client(
+            settings.build().expect("valid settings"),
+            Some(Duration::from_millis(200)),
+        );
+        let error = client
+            .send("test", client.get(&format!("http://{address}/")))
+            .await
+            .expect_err("upstream never answers");
+        assert!(error.is_timeout());
+        let (_socket, request) = upstream.await.expect("upstream task");
+        assert!(request.contains("user-agent: probe/1"), "{request}");
+    }
+
+    #[test]
+    fn redacts_auth_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        let redacted = redact_headers(&headers);
+        assert!(redacted.contains(&("authorization".to_string(), REDACTED.to_string())));
+        assert!(redacted.contains(&("content-type".to_string(), "application/json".to_string())));
+    }
+}
@@ -0,0 +1,263 @@
+//! Client address restrictions on the transport endpoints.
+//!
+//! `INFERENCO_MCP_ALLOWED_IPS` and `INFERENCO_MCP_DENIED_IPS` list CIDR
+//! ranges (or single addresses); a denied address is refused even if it is
+//! also allowed, and with an allow list any address not on it is refused.
+//! Behind a reverse proxy every request comes from the proxy, so requests
+//! from an address in `INFERENCO_MCP_TRUSTED_PROXIES` are attributed to the
+//! client named in the header the proxy writes, `X-Forwarded-For` or, with
+//! `INFERENCO_MCP_TRUSTED_PROXY_HEADER=forwarded`, `Forwarded`. Only that
+//! header is read: proxies pass the other one through as the client sent
+//! it. It is ignored from anyone else, who could otherwise claim any
+//! address.
+
+use std::{
+    env,
+    net::{IpAddr, SocketAddr},
+};
+
+/// An address range such as `10.0.0.0/8` or `2001:db8::/32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Parse `<address>/<prefix>`, or a bare address as a range of one.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let (address, prefix) = match value.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (value, None),
+        };
+        let network: IpAddr = address
+            .trim()
+            .parse()
+            .map_err(|_| format!("'{value}' is not an IP address or CIDR range"))?;
+        let network = network.to_canonical();
+        let bits = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .trim()
+                .parse::<u8>()
+                .ok()
+                .filter(|prefix| *prefix <= bits)
+                .ok_or_else(|| format!("'{value}' needs a prefix length of 0 to {bits}"))?,
+            None => bits,
+        };
+        Ok(Self { network, prefix })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The header trusted proxies name the client in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProxyHeader {
+    /// `X-Forwarded-For: <client>, <proxy>, …`
+    #[default]
+    XForwardedFor,
+    /// RFC 7239 `Forwarded: for=<client>, for=<proxy>, …`
+    Forwarded,
+}
+
+impl ProxyHeader {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::XForwardedFor => "x-forwarded-for",
+            Self::Forwarded => "forwarded",
+        }
+    }
+}
+
+/// Which client addresses may reach the transport endpoints, and which
+/// peers are proxies whose forwarding header is believed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IpFilter {
+    /// Any address not denied when empty.
+    pub allowed: Vec<Cidr>,
+    pub denied: Vec<Cidr>,
+    pub trusted_proxies: Vec<Cidr>,
+    pub proxy_header: ProxyHeader,
+}
+
+impl IpFilter {
+    /// Read `INFERENCO_MCP_ALLOWED_IPS`, `INFERENCO_MCP_DENIED_IPS`,
+    /// `INFERENCO_MCP_TRUSTED_PROXIES`, and
+    /// `INFERENCO_MCP_TRUSTED_PROXY_HEADER`.
+    pub fn from_env() -> Result<Self, String> {
+        let ranges = |name: &str| {
+            env::var(name)
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .map(|entry| Cidr::parse(entry).map_err(|error| format!("{name}: {error}")))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let proxy_header = match env::var("INFERENCO_MCP_TRUSTED_PROXY_HEADER")
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "" | "x-forwarded-for" => ProxyHeader::XForwardedFor,
+            "forwarded" => ProxyHeader::Forwarded,
+            other => {
+                return Err(format!(
+                    "INFERENCO_MCP_TRUSTED_PROXY_HEADER must be x-forwarded-for or forwarded, got '{other}'"
+                ))
+            }
+        };
+        Ok(Self {
+            allowed: ranges("INFERENCO_MCP_ALLOWED_IPS")?,
+            denied: ranges("INFERENCO_MCP_DENIED_IPS")?,
+            trusted_proxies: ranges("INFERENCO_MCP_TRUSTED_PROXIES")?,
+            proxy_header,
+        })
+    }
+
+    /// Whether requests need their client address resolved at all.
+    pub fn is_enabled(&self) -> bool {
+        !self.allowed.is_empty() || !self.denied.is_empty() || !self.trusted_proxies.is_empty()
+    }
+
+    pub fn permits(&self, ip: IpAddr) -> bool {
+        let within = |ranges: &[Cidr]| ranges.iter().any(|range| range.contains(ip));
+        !within(&self.denied) && (self.allowed.is_empty() || within(&self.allowed))
+    }
+
+    /// The client behind a request from `peer` whose [`ProxyHeader`] has
+    /// this value (its lines joined with commas). Hops are read from the
+    /// nearest back, and the first one that is not a trusted proxy is the
+    /// client.
+    pub fn client_ip(&self, peer: IpAddr, header: Option<&str>) -> IpAddr {
+        let mut client = peer.to_canonical();
+        let Some(header) = header.filter(|_| self.is_trusted(client)) else {
+            return client;
+        };
+        let hops: Vec<Option<IpAddr>> = match self.proxy_header {
+            ProxyHeader::Forwarded => header.split(',').map(forwarded_for_param).collect(),
+            ProxyHeader::XForwardedFor => header.split(',').map(node).collect(),
+        };
+        for hop in hops.into_iter().rev() {
+            // An obfuscated or unknown hop hides everything before it, so
+            // the proxy that reported it is the furthest we can tell.
+            let Some(hop) = hop else { break };
+            client = hop.to_canonical();
+            if !self.is_trusted(client) {
+                break;
+            }
+        }
+        client
+    }
+
+    fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|range| range.contains(ip))
+    }
+}
+
+/// The address in the `for=` parameter of one `Forwarded` element.
+fn forwarded_for_param(element: &str) -> Option<IpAddr> {
+    element.split(';').find_map(|pair| {
+        let (name, value) = pair.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("for")
+            .then(|| node(value))?
+    })
+}
+
+/// An address as proxies write it: bare, with a port, or bracketed IPv6,
+/// possibly quoted. `None` for `unknown` and obfuscated identifiers.
+fn node(value: &str) -> Option<IpAddr> {
+    let value = value.trim().trim_matches('"');
+    value
+        .parse::<IpAddr>()
+        .ok()
+        .or_else(|| value.parse::<SocketAddr>().ok().map(|address| address.ip()))
+        .or_else(|| value.strip_prefix('[')?.split_once(']')?.0.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(entries: &[&str]) -> Vec<Cidr> {
+        entries
+            .iter()
+            .map(|entry| Cidr::parse(entry).unwrap())
+            .collect()
+    }
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn denied_ranges_win_and_allow_lists_exclude_the_rest() {
+        let filter = IpFilter {
+            allowed: ranges(&["10.0.0.0/8", "2001:db8::/32"]),
+            denied: ranges(&["10.6.6.6"]),
+            ..IpFilter::default()
+        };
+        assert!(filter.permits(ip("10.1.2.3")));
+        assert!(filter.permits(ip("::ffff:10.1.2.3")), "IPv4-mapped");
+        assert!(filter.permits(ip("2001:db8::1")));
+        assert!(!filter.permits(ip("10.6.6.6")));
+        assert!(!filter.permits(ip("192.0.2.1")));
+        assert!(IpFilter::default().permits(ip("192.0.2.1")));
+        assert!(ranges(&["0.0.0.0/0"])[0].contains(ip("203.0.113.9")));
+        for bad in ["10.0.0.0/33", "example.com", "10.0.0.0/x", "::/129"] {
+            assert!(Cidr::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn forwarded_clients_are_believed_only_from_trusted_proxies() {
+        let filter = IpFilter {
+            trusted_proxies: ranges(&["10.0.0.0/8"]),
+            ..IpFilter::default()
+        };
+        let proxy = ip("10.0.0.1");
+        let stranger = ip("198.51.100.7");
+        assert_eq!(filter.client_ip(stranger, Some("203.0.113.9")), stranger);
+        assert_eq!(
+            filter.client_ip(proxy, Some("6.6.6.6, 203.0.113.9, 10.0.0.2")),
+            ip("203.0.113.9"),
+            "a client cannot prepend its way past the nearest untrusted hop"
+        );
+        assert_eq!(filter.client_ip(proxy, None), proxy);
+
+        let forwarded = IpFilter {
+            proxy_header: ProxyHeader::Forwarded,
+            ..filter
+        };
+        assert_eq!(
+            forwarded.client_ip(
+                proxy,
+                Some(r#"for="[2001:db8::7]:4711";proto=https, For=10.0.0.2"#)
+            ),
+            ip("2001:db8::7")
+        );
+        assert_eq!(
+            forwarded.client_ip(proxy, Some("for=unknown, for=10.0.0.2")),
+            ip("10.0.0.2")
+        );
+    }
+}
@@ -0,0 +1,769 @@
+//! Querying and diffing JSON for the `json_query` tool.
+//!
+//! [`query`] takes a JSONPath subset: `$` (optional), `.name`, `['name']`,
+//! `[0]`, `[-1]`, `[1:3]`, `*`, recursive `..name`, and filters such as
+//! `[?(@.price < 10)]` or `[?(@.tags)]`. [`diff`] lists what changed between
+//! two documents as JSON Pointers, like the recording differ.
+//!
+//! Queries are evaluated depth first, so they stop as soon as enough matches
+//! are found, and paths are only spelled out for the matches returned.
+//! Chained descendant segments such as `$..*..*` grow with a power of the
+//! document size, so a query gives up after visiting [`MAX_QUERY_VISITS`]
+//! values.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{fmt::Write as _, rc::Rc};
+
+/// Longest query accepted, in characters.
+pub const MAX_QUERY_CHARS: usize = 1000;
+/// Values a query may visit, counting each time a value is reached again
+/// through another segment.
+pub const MAX_QUERY_VISITS: usize = 1_000_000;
+
+/// A value selected by [`query`], with its normalized path.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Match {
+    pub path: String,
+    pub value: Value,
+}
+
+/// How a value differs between the two documents given to [`diff`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Difference {
+    /// JSON Pointer to the value; empty for the whole document.
+    pub path: String,
+    pub change: Change,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Change {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// Select from `document` with `expression`, in document order, stopping
+/// after `limit` matches. Returns the matches and whether any were cut off.
+pub fn query(
+    document: &Value,
+    expression: &str,
+    limit: usize,
+) -> Result<(Vec<Match>, bool), String> {
+    if expression.chars().count() > MAX_QUERY_CHARS {
+        return Err(format!("query is longer than {MAX_QUERY_CHARS} characters"));
+    }
+    let segments = Parser::new(expression).segments()?;
+    let mut walk = Walk {
+        segments: &segments,
+        limit,
+        visits: MAX_QUERY_VISITS,
+        found: Vec::new(),
+    };
+    let truncated = match walk.visit(0, Rc::new(Path::Root), document) {
+        Ok(()) => false,
+        Err(Stop::Full) => {
+            walk.found.truncate(limit);
+            true
+        }
+        Err(Stop::TooCostly) => {
+            return Err(format!(
+                "query visits more than {MAX_QUERY_VISITS} values; make it more specific"
+            ))
+        }
+    };
+    let matches = walk
+        .found
+        .into_iter()
+        .map(|(path, value)| Match {
+            path: path.render(),
+            value: value.clone(),
+        })
+        .collect();
+    Ok((matches, truncated))
+}
+
+/// Where a value sits, built as the walk goes down and spelled out only for
+/// matches.
+enum Path<'a> {
+    Root,
+    Name(Rc<Path<'a>>, &'a str),
+    Index(Rc<Path<'a>>, usize),
+}
+
+impl Path<'_> {
+    fn render(&self) -> String {
+        match self {
+            Path::Root => "$".to_string(),
+            Path::Name(parent, name) => child_path(&parent.render(), name),
+            Path::Index(parent, index) => format!("{}[{index}]", parent.render()),
+        }
+    }
+}
+
+/// Why a walk ended early.
+enum Stop {
+    /// One match more than the limit was found.
+    Full,
+    /// The visit budget ran out.
+    TooCostly,
+}
+
+struct Walk<'s, 'a> {
+    segments: &'s [Segment],
+    limit: usize,
+    /// Visits left.
+    visits: usize,
+    found: Vec<(Rc<Path<'a>>, &'a Value)>,
+}
+
+impl<'a> Walk<'_, 'a> {
+    /// Apply the segments from `depth` on to `value`, in document order.
+    fn visit(&mut self, depth: usize, path: Rc<Path<'a>>, value: &'a Value) -> Result<(), Stop> {
+        self.spend()?;
+        let Some(segment) = self.segments.get(depth) else {
+            self.found.push((path, value));
+            if self.found.len() > self.limit {
+                return Err(Stop::Full);
+            }
+            return Ok(());
+        };
+        match segment {
+            Segment::Child(selector) => self.select(selector, depth + 1, &path, value),
+            Segment::Descendant(selector) => {
+                // `value` and everything below it, in document order.
+                let mut stack = vec![(path, value)];
+                while let Some((path, value)) = stack.pop() {
+                    self.spend()?;
+                    self.select(selector, depth + 1, &path, value)?;
+                    match value {
+                        Value::Object(map) => {
+                            stack.extend(map.iter().rev().map(|(name, child)| {
+                                (Rc::new(Path::Name(path.clone(), name)), child)
+                            }))
+                        }
+                        Value::Array(items) => {
+                            stack.extend(items.iter().enumerate().rev().map(|(index, child)| {
+                                (Rc::new(Path::Index(path.clone(), index)), child)
+                            }))
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn spend(&mut self) -> Result<(), Stop> {
+        self.visits = self.visits.checked_sub(1).ok_or(Stop::TooCostly)?;
+        Ok(())
+    }
+
+    /// Visit the children of `value` that `selector` picks, at `depth`.
+    fn select(
+        &mut self,
+        selector: &Selector,
+        depth: usize,
+        path: &Rc<Path<'a>>,
+        value: &'a Value,
+    ) -> Result<(), Stop> {
+        let name = |name: &'a str| Rc::new(Path::Name(path.clone(), name));
+        let index = |index: usize| Rc::new(Path::Index(path.clone(), index));
+        match (selector, value) {
+            (Selector::Name(wanted), Value::Object(map)) => {
+                if let Some((key, child)) = map.get_key_value(wanted) {
+                    self.visit(depth, name(key), child)?;
+                }
+            }
+            (Selector::Index(wanted), Value::Array(items)) => {
+                if let Some(wanted) = resolve_index(*wanted, items.len()) {
+                    self.visit(depth, index(wanted), &items[wanted])?;
+                }
+            }
+            (Selector::Slice(start, end), Value::Array(items)) => {
+                let len = items.len() as i64;
+                let clamp = |bound: i64| {
+                    let bound = if bound < 0 { len + bound } else { bound };
+                    bound.clamp(0, len) as usize
+                };
+                let start = start.map_or(0, clamp);
+                let end = end.map_or(items.len(), clamp);
+                for position in start..end.max(start) {
+                    self.visit(depth, index(position), &items[position])?;
+                }
+            }
+            (Selector::Wildcard | Selector::Filter(_), Value::Object(map)) => {
+                for (key, child) in map {
+                    if selector.accepts(child) {
+                        self.visit(depth, name(key), child)?;
+                    }
+                }
+            }
+            (Selector::Wildcard | Selector::Filter(_), Value::Array(items)) => {
+                for (position, child) in items.iter().enumerate() {
+                    if selector.accepts(child) {
+                        self.visit(depth, index(position), child)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// What changed from `before` to `after`, up to `limit` differences.
+/// Arrays are compared index by index. Returns whether any were cut off.
+pub fn diff(before: &Value, after: &Value, limit: usize) -> (Vec<Difference>, bool) {
+    let mut differences = Vec::new();
+    let complete = diff_at(String::new(), before, after, limit, &mut differences);
+    (differences, !complete)
+}
+
+/// Returns false once `limit` differences have been collected and more
+/// remain.
+fn diff_at(
+    pointer: String,
+    before: &Value,
+    after: &Value,
+    limit: usize,
+    out: &mut Vec<Difference>,
+) -> bool {
+    match (before, after) {
+        (Value::Object(before), Value::Object(after)) => {
+            for (name, old) in before {
+                let child = format!("{pointer}/{}", escape_pointer(name));
+                let complete = match after.get(name) {
+                    Some(new) => diff_at(child, old, new, limit, out),
+                    None => push(out, Difference::removed(child, old), limit),
+                };
+                if !complete {
+                    return false;
+                }
+            }
+            for (name, new) in after {
+                if !before.contains_key(name) {
+                    let child = format!("{pointer}/{}", escape_pointer(name));
+                    if !push(out, Difference::added(child, new), limit) {
+                        return false;
+                    }
+                }
+            }
+            true
+        }
+        (Value::Array(before), Value::Array(after)) => {
+            for index in 0..before.len().max(after.len()) {
+                let child = format!("{pointer}/{index}");
+                let complete = match (before.get(index), after.get(index)) {
+                    (Some(old), Some(new)) => diff_at(child, old, new, limit, out),
+                    (Some(old), None) => push(out, Difference::removed(child, old), limit),
+                    (None, Some(new)) => push(out, Difference::added(child, new), limit),
+                    (None, None) => unreachable!("index is below one of the lengths"),
+                };
+                if !complete {
+                    return false;
+                }
+            }
+            true
+        }
+        _ if before != after => push(
+            out,
+            Difference {
+                path: pointer,
+                change: Change::Changed,
+                from: Some(before.clone()),
+                to: Some(after.clone()),
+            },
+            limit,
+        ),
+        _ => true,
+    }
+}
+
+impl Difference {
+    fn added(path: String, value: &Value) -> Self {
+        Difference {
+            path,
+            change: Change::Added,
+            from: None,
+            to: Some(value.clone()),
+        }
+    }
+
+    fn removed(path: String, value: &Value) -> Self {
+        Difference {
+            path,
+            change: Change::Removed,
+            from: Some(value.clone()),
+            to: None,
+        }
+    }
+}
+
+/// Add `difference` unless `out` already holds `limit`; false if it did.
+fn push(out: &mut Vec<Difference>, difference: Difference, limit: usize) -> bool {
+    if out.len() == limit {
+        return false;
+    }
+    out.push(difference);
+    true
+}
+
+fn escape_pointer(name: &str) -> String {
+    name.replace('~', "~0").replace('/', "~1")
+}
+
+#[derive(Debug)]
+enum Segment {
+    Child(Selector),
+    Descendant(Selector),
+}
+
+#[derive(Debug)]
+enum Selector {
+    Name(String),
+    Index(i64),
+    Slice(Option<i64>, Option<i64>),
+    Wildcard,
+    Filter(Filter),
+}
+
+/// `[?(@.path)]` or `[?(@.path <op> literal)]`.
+#[derive(Debug)]
+struct Filter {
+    path: Vec<Step>,
+    comparison: Option<(Comparison, Value)>,
+}
+
+#[derive(Debug)]
+enum Step {
+    Name(String),
+    Index(i64),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+fn child_path(path: &str, name: &str) -> String {
+    let simple = !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if simple {
+        format!("{path}.{name}")
+    } else {
+        let mut quoted = format!("{path}['");
+        for c in name.chars() {
+            if c == '\'' || c == '\\' {
+                quoted.push('\\');
+            }
+            quoted.push(c);
+        }
+        let _ = write!(quoted, "']");
+        quoted
+    }
+}
+
+/// `index` counted from the end when negative, if it is within `len`.
+fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    let len = i64::try_from(len).ok()?;
+    let index = if index < 0 { len + index } else { index };
+    (0..len).contains(&index).then_some(index as usize)
+}
+
+impl Selector {
+    fn accepts(&self, value: &Value) -> bool {
+        match self {
+            Selector::Filter(filter) => filter.accepts(value),
+            _ => true,
+        }
+    }
+}
+
+impl Filter {
+    fn accepts(&self, value: &Value) -> bool {
+        let mut current = value;
+        for step in &self.path {
+            let next = match (step, current) {
+                (Step::Name(name), Value::Object(map)) => map.get(name),
+                (Step::Index(index), Value::Array(items)) => {
+                    resolve_index(*index, items.len()).map(|index| &items[index])
+                }
+                _ => None,
+            };
+            match next {
+                Some(next) => current = next,
+                None => return false,
+            }
+        }
+        let Some((comparison, literal)) = &self.comparison else {
+            return true;
+        };
+        let ordering = match (current, literal) {
+            (Value::Number(a), Value::Number(b)) => a.as_f64().partial_cmp(&b.as_f64()),
+            (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+            (a, b) if a == b => Some(std::cmp::Ordering::Equal),
+            _ => None,
+        };
+        use std::cmp::Ordering::*;
+        match comparison {
+            Comparison::Equal => ordering == Some(Equal),
+            Comparison::NotEqual => ordering != Some(Equal),
+            Comparison::Less => ordering == Some(Less),
+            Comparison::LessOrEqual => matches!(ordering, Some(Less | Equal)),
+            Comparison::Greater => ordering == Some(Greater),
+            Comparison::GreaterOrEqual => matches!(ordering, Some(Greater | Equal)),
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    next: usize,
+}
+
+impl Parser {
+    fn new(expression: &str) -> Self {
+        Parser {
+            chars: expression.trim().chars().collect(),
+            next: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.next).copied()
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.next += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.next += 1;
+        }
+    }
+
+    /// An error at the current character, 1-based.
+    fn error(&self, message: &str) -> String {
+        match self.peek() {
+            Some(c) => format!("{message} at position {}, found '{c}'", self.next + 1),
+            None => format!("{message} at the end of the query"),
+        }
+    }
+
+    fn segments(&mut self) -> Result<Vec<Segment>, String> {
+        self.eat('$');
+        let mut segments = Vec::new();
+        while self.peek().is_some() {
+            if self.eat('.') {
+                let descendant = self.eat('.');
+                let selector = if self.eat('*') {
+                    Selector::Wildcard
+                } else if descendant && self.peek() == Some('[') {
+                    self.bracket()?
+                } else {
+                    Selector::Name(self.name()?)
+                };
+                segments.push(if descendant {
+                    Segment::Descendant(selector)
+                } else {
+                    Segment::Child(selector)
+                });
+            } else if self.peek() == Some('[') {
+                segments.push(Segment::Child(self.bracket()?));
+            } else if segments.is_empty() && self.next == 0 {
+                // A bare name starts at the root, as in `items[0]`.
+                segments.push(Segment::Child(Selector::Name(self.name()?)));
+            } else {
+                return Err(self.error("expected '.' or '['"));
+            }
+        }
+        Ok(segments)
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        let start = self.next;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '$')
+        {
+            self.next += 1;
+        }
+        if self.next == start {
+            return Err(self.error("expected a name"));
+        }
+        Ok(self.chars[start..self.next].iter().collect())
+    }
+
+    fn bracket(&mut self) -> Result<Selector, String> {
+        self.eat('[');
+        self.skip_spaces();
+        let selector = match self.peek() {
+            Some('*') => {
+                self.next += 1;
+                Selector::Wildcard
+            }
+            Some('\'' | '"') => Selector::Name(self.string()?),
+            Some('?') => {
+                self.next += 1;
+                Selector::Filter(self.filter()?)
+            }
+            _ => {
+                let start = self.optional_integer()?;
+                self.skip_spaces();
+                if self.eat(':') {
+                    self.skip_spaces();
+                    Selector::Slice(start, self.optional_integer()?)
+                } else {
+                    Selector::Index(
+                        start
+                            .ok_or_else(|| self.error("expected an index, name, '*', or filter"))?,
+                    )
+                }
+            }
+        };
+        self.skip_spaces();
+        if !self.eat(']') {
+            return Err(self.error("expected ']'"));
+        }
+        Ok(selector)
+    }
+
+    fn optional_integer(&mut self) -> Result<Option<i64>, String> {
+        let start = self.next;
+        self.eat('-');
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.next += 1;
+        }
+        let digits: String = self.chars[start..self.next].iter().collect();
+        match digits.as_str() {
+            "" => Ok(None),
+            _ => digits
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("bad index {digits} at position {}", start + 1)),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let quote = self.peek().expect("called on a quote");
+        let start = self.next + 1;
+        self.next += 1;
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(format!("string at position {start} is never closed")),
+                Some(c) if c == quote => {
+                    self.next += 1;
+                    return Ok(text);
+                }
+                Some('\\') => {
+                    self.next += 1;
+                    text.extend(self.peek());
+                    self.next += 1;
+                }
+                Some(c) => {
+                    text.push(c);
+                    self.next += 1;
+                }
+            }
+        }
+    }
+
+    /// The part of `[?( ... )]` after `?`.
+    fn filter(&mut self) -> Result<Filter, String> {
+        let parenthesized = self.eat('(');
+        self.skip_spaces();
+        if !self.eat('@') {
+            return Err(self.error("expected '@' to start the filter"));
+        }
+        let mut path = Vec::new();
+        loop {
+            if self.eat('.') {
+                path.push(Step::Name(self.name()?));
+            } else if self.peek() == Some('[') {
+                match self.bracket()? {
+                    Selector::Name(name) => path.push(Step::Name(name)),
+                    Selector::Index(index) => path.push(Step::Index(index)),
+                    _ => return Err("filters only take names and indices after '@'".to_string()),
+                }
+            } else {
+                break;
+            }
+        }
+        self.skip_spaces();
+        let comparison = match self.comparison() {
+            Some(comparison) => {
+                self.skip_spaces();
+                Some((comparison, self.literal()?))
+            }
+            None => None,
+        };
+        self.skip_spaces();
+        if parenthesized && !self.eat(')') {
+            return Err(self.error("expected ')'"));
+        }
+        Ok(Filter { path, comparison })
+    }
+
+    fn comparison(&mut self) -> Option<Comparison> {
+        let two: String = self.chars[self.next..].iter().take(2).collect();
+        let (comparison, width) = match two.as_str() {
+            "==" => (Comparison::Equal, 2),
+            "!=" => (Comparison::NotEqual, 2),
+            "<=" => (Comparison::LessOrEqual, 2),
+            ">=" => (Comparison::GreaterOrEqual, 2),
+            _ if two.starts_with('<') => (Comparison::Less, 1),
+            _ if two.starts_with('>') => (Comparison::Greater, 1),
+            _ => return None,
+        };
+        self.next += width;
+        Some(comparison)
+    }
+
+    fn literal(&mut self) -> Result<Value, String> {
+        if matches!(self.peek(), Some('\'' | '"')) {
+            return self.string().map(Value::String);
+        }
+        let start = self.next;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || matches!(c, '-' | '+' | '.'))
+        {
+            self.next += 1;
+        }
+        let word: String = self.chars[start..self.next].iter().collect();
+        match serde_json::from_str::<Value>(&word) {
+            Ok(value @ (Value::Number(_) | Value::Bool(_) | Value::Null)) => Ok(value),
+            _ => Err(format!(
+                "expected a number, string, true, false, or null at position {}",
+                start + 1
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn select(document: &Value, expression: &str) -> Vec<(String, Value)> {
+        let (matches, _) = query(document, expression, 100)
+            .unwrap_or_else(|error| panic!("{expression}: {error}"));
+        matches
+            .into_iter()
+            .map(|found| (found.path, found.value))
+            .collect()
+    }
+
+    #[test]
+    fn queries_select_paths_slices_descendants_and_filters() {
+        let document = json!({
+            "store": {
+                "books": [
+                    { "title": "A", "price": 8.5, "tags": ["x"] },
+                    { "title": "B", "price": 12 },
+                    { "title": "C", "price": 30, "tags": [] }
+                ],
+                "owner name": "Ada"
+            }
+        });
+        assert_eq!(
+            select(&document, "$.store.books[-1].title"),
+            vec![("$.store.books[2].title".to_string(), json!("C"))]
+        );
+        let titles = |expression| {
+            select(&document, expression)
+                .into_iter()
+                .map(|(_, value)| value)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            titles("store.books[0:2].title"),
+            vec![json!("A"), json!("B")]
+        );
+        assert_eq!(titles("$..title"), vec![json!("A"), json!("B"), json!("C")]);
+        assert_eq!(
+            titles("$.store.books[?(@.price < 20)].title"),
+            vec![json!("A"), json!("B")]
+        );
+        assert_eq!(
+            titles("$.store.books[?(@.tags)].title"),
+            vec![json!("A"), json!("C")]
+        );
+        assert_eq!(
+            titles("$.store.books[?(@.title == 'B')].price"),
+            vec![json!(12)]
+        );
+        assert_eq!(
+            select(&document, "$.store['owner name']"),
+            vec![("$.store['owner name']".to_string(), json!("Ada"))]
+        );
+        assert!(query(&document, "$..*", 2).unwrap().1);
+        assert_eq!(
+            query(&document, "$.store.books[0", 10).unwrap_err(),
+            "expected ']' at the end of the query"
+        );
+        assert_eq!(
+            query(&document, "$.store!", 10).unwrap_err(),
+            "expected '.' or '[' at position 8, found '!'"
+        );
+    }
+
+    #[test]
+    fn chained_descendant_queries_stop_at_the_limit_or_the_visit_budget() {
+        // Each `..*` multiplies the work by the nesting depth.
+        let mut document = json!(1);
+        for _ in 0..200 {
+            document = json!([document]);
+        }
+        let (matches, truncated) = query(&document, "$..*..*..*", 5).expect("stops early");
+        assert!(truncated);
+        assert_eq!(matches.len(), 5);
+        assert_eq!(matches[0].path, "$[0][0][0]");
+        let error = query(&document, "$..*..*..*..*.missing", 5).unwrap_err();
+        assert!(error.starts_with("query visits more than"), "{error}");
+    }
+
+    #[test]
+    fn diff_reports_pointers_for_added_removed_and_changed_values() {
+        let before = json!({ "a": 1, "b": { "c": [1, 2, 3] }, "gone": true });
+        let after = json!({ "a": 2, "b": { "c": [1, 2] }, "new/key": null });
+        let (differences, truncated) = diff(&before, &after, 10);
+        assert!(!truncated);
+        let summary: Vec<_> = differences
+            .iter()
+            .map(|difference| (difference.path.as_str(), difference.change))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("/a", Change::Changed),
+                ("/b/c/2", Change::Removed),
+                ("/gone", Change::Removed),
+                ("/new~1key", Change::Added),
+            ]
+        );
+        assert_eq!(diff(&before, &after, 2), (differences[..2].to_vec(), true));
+        assert!(diff(&before, &before, 10).0.is_empty());
+    }
+}
@@ -1 +1,57 @@
+pub mod alerts;
+pub mod audit;
+pub mod auth;
+pub mod cache;
+pub mod calc;
+pub mod cancellation;
+#[cfg(feature = "server-bin")]
+pub mod cli;
+pub mod client;
+pub mod client_log;
+pub mod clock;
+pub mod codec;
+pub mod config;
+pub mod config_file;
+pub mod config_schema;
+pub mod cors;
+pub mod crash_report;
+#[cfg(feature = "server-bin")]
+pub mod daemon;
+pub mod dice;
+pub mod dns;
+pub mod fetch;
+pub mod health;
+pub mod html;
+pub mod http_client;
+pub mod ip_filter;
+pub mod json_query;
+pub mod limiter;
+pub mod logging;
+pub mod maintenance;
+pub mod metrics;
+pub mod progress;
+pub mod protocol;
+pub mod rate_limit;
+pub mod rebinding;
+pub mod recording;
+pub mod reload;
+#[cfg(feature = "server-bin")]
+pub mod repl;
+#[cfg(feature = "server-bin")]
+pub mod rpc;
+pub mod runtime_stats;
+pub mod scheduler;
+pub mod self_check;
 pub mod server;
+pub mod sessions;
+pub mod slow_calls;
+pub mod store;
+pub mod tls;
+#[cfg(feature = "server-bin")]
+pub mod transport;
+#[cfg(feature = "server-bin")]
+pub mod upgrade;
+pub mod usage;
+pub mod version;
+#[cfg(all(windows, feature = "server-bin"))]
+pub mod winservice;
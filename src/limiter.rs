@@ -0,0 +1,243 @@
+//! Global cap on concurrently executing tool calls.
+//!
+//! Calls beyond the cap wait in a bounded queue instead of all fanning out
+//! to upstreams at once; once the queue is full, further calls are turned
+//! away with [`BUSY_ERROR_CODE`] so clients can back off and retry. One
+//! session may also only have so many calls running, so a single client
+//! cannot take every slot; its calls beyond that are turned away at once.
+
+use crate::{
+    config::parse_env,
+    metrics::Metrics,
+    sessions::{SessionCall, SessionState},
+};
+use rmcp::{model::ErrorCode, ErrorData as McpError};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// JSON-RPC error code returned by `tools/call` when the queue is full.
+pub const BUSY_ERROR_CODE: ErrorCode = ErrorCode(-32004);
+
+/// How many tool calls may run and wait at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConcurrencyLimits {
+    /// Calls executing at once; 0 removes the limit.
+    pub max_concurrent: usize,
+    /// Calls waiting for a slot before new ones are rejected.
+    pub max_queued: usize,
+    /// Calls one session may have running or queued; 0 removes the limit.
+    pub max_per_session: usize,
+}
+
+impl Default for ConcurrencyLimits {
+    fn default() -> Self {
+        Self {
+            max_concurrent: 64,
+            max_queued: 256,
+            max_per_session: 8,
+        }
+    }
+}
+
+impl ConcurrencyLimits {
+    /// Read `INFERENCO_MCP_MAX_CONCURRENT_CALLS`,
+    /// `INFERENCO_MCP_MAX_QUEUED_CALLS`, and
+    /// `INFERENCO_MCP_MAX_SESSION_CALLS`.
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = Self::default();
+        Ok(Self {
+            max_concurrent: parse_env("INFERENCO_MCP_MAX_CONCURRENT_CALLS", "a number of calls")?
+                .unwrap_or(defaults.max_concurrent),
+            max_queued: parse_env("INFERENCO_MCP_MAX_QUEUED_CALLS", "a number of calls")?
+                .unwrap_or(defaults.max_queued),
+            max_per_session: parse_env("INFERENCO_MCP_MAX_SESSION_CALLS", "a number of calls")?
+                .unwrap_or(defaults.max_per_session),
+        })
+    }
+}
+
+/// Admits tool calls within [`ConcurrencyLimits`].
+#[derive(Debug)]
+pub struct CallLimiter {
+    limits: ConcurrencyLimits,
+    /// `None` when concurrency is unlimited.
+    permits: Option<Arc<Semaphore>>,
+    queued: AtomicUsize,
+}
+
+impl CallLimiter {
+    pub fn new(limits: ConcurrencyLimits) -> Self {
+        Self {
+            limits,
+            permits: (limits.max_concurrent > 0)
+                .then(|| Arc::new(Semaphore::new(limits.max_concurrent))),
+            queued: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn limits(&self) -> ConcurrencyLimits {
+        self.limits
+    }
+
+    /// Calls currently waiting for a slot.
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// Wait for a slot, or fail with a busy error if the queue is full. The
+    /// slot is held until the returned permit is dropped.
+    pub async fn acquire(
+        &self,
+        metrics: &Metrics,
+    ) -> Result<Option<OwnedSemaphorePermit>, McpError> {
+        let Some(permits) = &self.permits else {
+            return Ok(None);
+        };
+        if let Ok(permit) = Arc::clone(permits).try_acquire_owned() {
+            metrics.record_queue_wait(Duration::ZERO);
+            return Ok(Some(permit));
+        }
+        let reserved = self
+            .queued
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |queued| {
+                (queued < self.limits.max_queued).then_some(queued + 1)
+            });
+        if reserved.is_err() {
+            metrics.call_rejected();
+            return Err(self.busy());
+        }
+        // Released on drop, so a caller that gives up still frees its place.
+        let _slot = QueueSlot(&self.queued);
+        let _queued = metrics.call_queued();
+        let started = Instant::now();
+        let permit = Arc::clone(permits)
+            .acquire_owned()
+            .await
+            .expect("call semaphore is never closed");
+        metrics.record_queue_wait(started.elapsed());
+        Ok(Some(permit))
+    }
+
+    /// Count a call against `session`, or fail with a busy error if it
+    /// already has [`ConcurrencyLimits::max_per_session`] calls. Calls
+    /// outside a session are not limited here.
+    pub fn admit_session(
+        &self,
+        session: Option<&SessionState>,
+        metrics: &Metrics,
+    ) -> Result<Option<SessionCall>, McpError> {
+        let Some(session) = session else {
+            return Ok(None);
+        };
+        match session.start_call(self.limits.max_per_session) {
+            Some(call) => Ok(Some(call)),
+            None => {
+                metrics.call_rejected();
+                Err(McpError::new(
+                    BUSY_ERROR_CODE,
+                    "too many calls in this session; retry later",
+                    Some(serde_json::json!({
+                        "busy": true,
+                        "max_per_session": self.limits.max_per_session,
+                    })),
+                ))
+            }
+        }
+    }
+
+    fn busy(&self) -> McpError {
+        McpError::new(
+            BUSY_ERROR_CODE,
+            "server busy; retry later",
+            Some(serde_json::json!({
+                "busy": true,
+                "max_concurrent": self.limits.max_concurrent,
+                "max_queued": self.limits.max_queued,
+            })),
+        )
+    }
+}
+
+impl Default for CallLimiter {
+    fn default() -> Self {
+        Self::new(ConcurrencyLimits::default())
+    }
+}
+
+struct QueueSlot<'a>(&'a AtomicUsize);
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn queues_up_to_the_limit_then_reports_busy() {
+        let limiter = Arc::new(CallLimiter::new(ConcurrencyLimits {
+            max_concurrent: 1,
+            max_queued: 1,
+            max_per_session: 0,
+        }));
+        let metrics = Arc::new(Metrics::new());
+        let running = limiter.acquire(&metrics).await.expect("free slot");
+
+        let waiter = tokio::spawn({
+            let (limiter, metrics) = (Arc::clone(&limiter), Arc::clone(&metrics));
+            async move {
+                limiter
+                    .acquire(&metrics)
+                    .await
+                    .map(|permit| permit.is_some())
+            }
+        });
+        while limiter.queued() == 0 {
+            tokio::task::yield_now().await;
+        }
+        let error = limiter.acquire(&metrics).await.expect_err("queue is full");
+        assert_eq!(error.code, BUSY_ERROR_CODE);
+
+        drop(running);
+        assert!(waiter
+            .await
+            .expect("waiter")
+            .expect("admitted once a slot frees"));
+        assert_eq!(limiter.queued(), 0);
+        assert!(metrics
+            .render()
+            .contains("inferenco_mcp_tool_calls_rejected_total 1"));
+    }
+
+    #[test]
+    fn sessions_are_limited_separately() {
+        let limiter = CallLimiter::new(ConcurrencyLimits {
+            max_per_session: 1,
+            ..ConcurrencyLimits::default()
+        });
+        let metrics = Metrics::new();
+        let (first, second) = (SessionState::default(), SessionState::default());
+        let running = limiter
+            .admit_session(Some(&first), &metrics)
+            .expect("first call");
+        let error = limiter
+            .admit_session(Some(&first), &metrics)
+            .expect_err("session is at its limit");
+        assert_eq!(error.code, BUSY_ERROR_CODE);
+        assert_eq!(error.data.expect("data")["max_per_session"], 1);
+        assert!(limiter.admit_session(Some(&second), &metrics).is_ok());
+        assert!(limiter.admit_session(None, &metrics).is_ok());
+
+        drop(running);
+        assert!(limiter.admit_session(Some(&first), &metrics).is_ok());
+    }
+}
@@ -0,0 +1,433 @@
+use crate::{client_log::ClientLogLayer, crash_report::CrashReporter};
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use tracing::Subscriber;
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{RollingFileAppender, Rotation},
+};
+use tracing_subscriber::{
+    fmt::MakeWriter, layer::SubscriberExt, registry::LookupSpan, reload, util::SubscriberInitExt,
+    EnvFilter, Layer, Registry,
+};
+
+/// Level used when neither `RUST_LOG` nor `INFERENCO_MCP_LOG_LEVEL` is set.
+const DEFAULT_LEVEL: &str = "info";
+
+/// Output format for the tracing subscriber.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// Human-readable lines (the default).
+    #[default]
+    Text,
+    /// One JSON object per line, suitable for Loki/ELK ingestion.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "text" | "pretty" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => Err(format!(
+                "unknown log format '{other}' (expected 'text' or 'json')"
+            )),
+        }
+    }
+}
+
+/// Logging settings read from the environment.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogSettings {
+    pub format: LogFormat,
+    /// `EnvFilter` directives used when `RUST_LOG` is unset.
+    pub level: Option<String>,
+    pub file: Option<FileLogConfig>,
+}
+
+impl LogSettings {
+    /// Read `INFERENCO_MCP_LOG_FORMAT`, `INFERENCO_MCP_LOG_LEVEL`, and the
+    /// log file settings.
+    pub fn from_env() -> Result<Self, String> {
+        let format = match env::var("INFERENCO_MCP_LOG_FORMAT") {
+            Ok(value) => value.parse()?,
+            Err(_) => LogFormat::default(),
+        };
+        let level = env::var("INFERENCO_MCP_LOG_LEVEL")
+            .ok()
+            .map(|level| level.trim().to_string())
+            .filter(|level| !level.is_empty());
+        if let Some(level) = &level {
+            level_filter(level)?;
+        }
+        Ok(Self {
+            format,
+            level,
+            file: FileLogConfig::from_env()?,
+        })
+    }
+}
+
+/// `level` as a filter, or why `INFERENCO_MCP_LOG_LEVEL` cannot hold it.
+pub fn level_filter(level: &str) -> Result<EnvFilter, String> {
+    EnvFilter::try_new(level).map_err(|error| {
+        format!("INFERENCO_MCP_LOG_LEVEL is not a valid filter '{level}': {error}")
+    })
+}
+
+/// Changes the level of the installed subscriber, as a configuration
+/// reload does. Does nothing while `RUST_LOG` is set, which takes precedence.
+#[derive(Clone)]
+pub struct LevelHandle {
+    filter: Option<reload::Handle<EnvFilter, Registry>>,
+}
+
+impl LevelHandle {
+    /// Filter with `level`, or `info` when it is `None`.
+    pub fn set(&self, level: Option<&str>) -> Result<(), String> {
+        let Some(filter) = &self.filter else {
+            return Ok(());
+        };
+        let level = level_filter(level.unwrap_or(DEFAULT_LEVEL))?;
+        filter
+            .reload(level)
+            .map_err(|error| format!("cannot change the log level: {error}"))
+    }
+}
+
+/// How often the log file rolls over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+impl FromStr for LogRotation {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "minutely" => Ok(Self::Minutely),
+            "hourly" => Ok(Self::Hourly),
+            "daily" => Ok(Self::Daily),
+            "never" => Ok(Self::Never),
+            other => Err(format!(
+                "unknown log rotation '{other}' (expected minutely, hourly, daily, or never)"
+            )),
+        }
+    }
+}
+
+impl From<LogRotation> for Rotation {
+    fn from(rotation: LogRotation) -> Self {
+        match rotation {
+            LogRotation::Minutely => Rotation::MINUTELY,
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Never => Rotation::NEVER,
+        }
+    }
+}
+
+/// Rotated files kept by size-based rotation when
+/// `INFERENCO_MCP_LOG_MAX_FILES` is unset.
+const DEFAULT_SIZE_ROTATED_FILES: usize = 5;
+
+/// Optional log file written alongside stderr.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileLogConfig {
+    /// Base path; rotated files are named `<stem>.<period>.<extension>`, or
+    /// `<path>.1` (newest) to `<path>.<max_files>` when rotating by size.
+    pub path: PathBuf,
+    pub rotation: LogRotation,
+    /// Number of rotated files to keep; older ones are deleted.
+    pub max_files: Option<usize>,
+    /// Roll the file over before it grows past this many bytes, instead of
+    /// on a schedule.
+    pub max_bytes: Option<u64>,
+}
+
+impl FileLogConfig {
+    /// Read `INFERENCO_MCP_LOG_FILE`, `INFERENCO_MCP_LOG_ROTATION`,
+    /// `INFERENCO_MCP_LOG_MAX_FILES`, and `INFERENCO_MCP_LOG_MAX_BYTES`.
+    /// Returns `None` when no file is set.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let path = match env::var("INFERENCO_MCP_LOG_FILE") {
+            Ok(path) if !path.trim().is_empty() => PathBuf::from(path.trim()),
+            _ => return Ok(None),
+        };
+        let rotation = match env::var("INFERENCO_MCP_LOG_ROTATION") {
+            Ok(value) => Some(value.parse()?),
+            Err(_) => None,
+        };
+        let max_files = match env::var("INFERENCO_MCP_LOG_MAX_FILES") {
+            Ok(value) => Some(value.parse().map_err(|_| {
+                format!("INFERENCO_MCP_LOG_MAX_FILES must be a number of files, got '{value}'")
+            })?),
+            Err(_) => None,
+        };
+        let max_bytes = match env::var("INFERENCO_MCP_LOG_MAX_BYTES") {
+            Ok(value) => Some(
+                value
+                    .trim()
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|bytes| *bytes > 0)
+                    .ok_or_else(|| {
+                        format!(
+                            "INFERENCO_MCP_LOG_MAX_BYTES must be a positive number, got '{value}'"
+                        )
+                    })?,
+            ),
+            Err(_) => None,
+        };
+        if max_bytes.is_some() && rotation.is_some_and(|rotation| rotation != LogRotation::Never) {
+            return Err("INFERENCO_MCP_LOG_MAX_BYTES rotates by size; \
+                 leave INFERENCO_MCP_LOG_ROTATION unset or set it to never"
+                .to_string());
+        }
+        Ok(Some(Self {
+            path,
+            rotation: rotation.unwrap_or_default(),
+            max_files,
+            max_bytes,
+        }))
+    }
+
+    fn writer(&self) -> Result<Box<dyn Write + Send>, String> {
+        match self.max_bytes {
+            Some(max_bytes) => {
+                let max_files = self.max_files.unwrap_or(DEFAULT_SIZE_ROTATED_FILES);
+                let file = SizeRotatingFile::open(self.path.clone(), max_bytes, max_files)
+                    .map_err(|error| {
+                        format!("cannot open log file '{}': {error}", self.path.display())
+                    })?;
+                Ok(Box::new(file))
+            }
+            None => Ok(Box::new(self.appender()?)),
+        }
+    }
+
+    fn appender(&self) -> Result<RollingFileAppender, String> {
+        let directory = self
+            .path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        let stem = self
+            .path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| format!("log file path '{}' has no file name", self.path.display()))?;
+
+        let mut builder = RollingFileAppender::builder()
+            .rotation(self.rotation.into())
+            .filename_prefix(stem);
+        if let Some(extension) = self.path.extension().and_then(|ext| ext.to_str()) {
+            builder = builder.filename_suffix(extension);
+        }
+        if let Some(max_files) = self.max_files {
+            builder = builder.max_log_files(max_files);
+        }
+        builder
+            .build(directory)
+            .map_err(|error| format!("cannot open log file in '{}': {error}", directory.display()))
+    }
+}
+
+/// A log file that is renamed to `<path>.1` before a write would take it
+/// past `max_bytes`, shifting older files up and deleting the one past
+/// `max_files`. A single write larger than `max_bytes` still goes out whole.
+struct SizeRotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: File,
+    written: u64,
+}
+
+impl SizeRotatingFile {
+    fn open(path: PathBuf, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        let file = File::options().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path,
+            max_bytes,
+            max_files,
+            file,
+            written,
+        })
+    }
+
+    fn rotated(&self, index: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{index}"));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let ignore_missing = |result: io::Result<()>| match result {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        };
+        if self.max_files == 0 {
+            ignore_missing(fs::remove_file(&self.path))?;
+        } else {
+            ignore_missing(fs::remove_file(self.rotated(self.max_files)))?;
+            for index in (1..self.max_files).rev() {
+                ignore_missing(fs::rename(self.rotated(index), self.rotated(index + 1)))?;
+            }
+            fs::rename(&self.path, self.rotated(1))?;
+        }
+        self.file = File::options().create(true).append(true).open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for SizeRotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Keeps the background file writer alive; logs buffered for the file are
+/// flushed when it is dropped, so hold it for the lifetime of `main`.
+pub type LogGuard = Option<WorkerGuard>;
+
+/// Install the global tracing subscriber.
+///
+/// Logs are written to stderr so they never interleave with JSON-RPC frames on
+/// the stdio transport, and additionally to a rotating file when one is
+/// configured. `RUST_LOG` takes precedence over the configured level. In JSON mode, event fields (`tool`, `duration_ms`, `outcome`, ...) are
+/// flattened to the top level and the enclosing span's fields (`request_id`,
+/// `session_id`) are emitted under `span`. Error-level events are also handed
+/// to `crash_reporter` when one is configured. Events of a session's requests
+/// also go to the session as log messages once it sets a level. The
+/// returned [`LevelHandle`] changes the level afterwards.
+pub fn init(
+    settings: &LogSettings,
+    crash_reporter: Option<&CrashReporter>,
+) -> Result<(LogGuard, LevelHandle), String> {
+    let rust_log = env::var(EnvFilter::DEFAULT_ENV)
+        .ok()
+        .filter(|value| !value.trim().is_empty());
+    let fixed = rust_log.is_some();
+    let directives = rust_log
+        .or_else(|| settings.level.clone())
+        .unwrap_or_else(|| DEFAULT_LEVEL.to_string());
+    let filter = EnvFilter::try_new(&directives)
+        .map_err(|error| format!("invalid log filter '{directives}': {error}"))?;
+    let (filter, handle) = reload::Layer::new(filter);
+    let level = LevelHandle {
+        filter: (!fixed).then_some(handle),
+    };
+    let format = settings.format;
+
+    let mut layers = vec![fmt_layer(format, std::io::stderr, true)];
+    let guard = match &settings.file {
+        Some(file) => {
+            let (writer, guard) = tracing_appender::non_blocking(file.writer()?);
+            layers.push(fmt_layer(format, writer, false));
+            Some(guard)
+        }
+        None => None,
+    };
+    if let Some(reporter) = crash_reporter {
+        layers.push(reporter.layer().boxed());
+    }
+    layers.push(ClientLogLayer.boxed());
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(layers)
+        .init();
+    Ok((guard, level))
+}
+
+fn fmt_layer<S, W>(format: LogFormat, writer: W, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_ansi(ansi)
+        .with_writer(writer);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_known_formats_case_insensitively() {
+        assert_eq!("json".parse::<LogFormat>(), Ok(LogFormat::Json));
+        assert_eq!("JSON".parse::<LogFormat>(), Ok(LogFormat::Json));
+        assert_eq!("text".parse::<LogFormat>(), Ok(LogFormat::Text));
+        assert!("yaml".parse::<LogFormat>().is_err());
+    }
+
+    #[test]
+    fn builds_rotating_appender_next_to_configured_path() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let config = FileLogConfig {
+            path: dir.path().join("inferenco.log"),
+            rotation: "hourly".parse().expect("valid rotation"),
+            max_files: Some(3),
+            max_bytes: None,
+        };
+        config.appender().expect("appender builds");
+        assert!("weekly-ish".parse::<LogRotation>().is_err());
+    }
+
+    #[test]
+    fn size_rotation_keeps_the_newest_files_under_the_limit() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let path = dir.path().join("inferenco.log");
+        let mut file = SizeRotatingFile::open(path.clone(), 10, 2).expect("file opens");
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_all(line.as_bytes()).expect("written");
+        }
+        file.flush().expect("flushed");
+        let read = |name: &str| fs::read_to_string(dir.path().join(name)).ok();
+        assert_eq!(read("inferenco.log").as_deref(), Some("fourth\n"));
+        assert_eq!(read("inferenco.log.1").as_deref(), Some("third\n"));
+        assert_eq!(read("inferenco.log.2").as_deref(), Some("second\n"));
+        assert_eq!(read("inferenco.log.3"), None, "older files are deleted");
+
+        // Reopening carries on from the size already written.
+        drop(file);
+        let mut file = SizeRotatingFile::open(path, 10, 2).expect("file opens");
+        file.write_all(b"fifth\n").expect("written");
+        assert_eq!(read("inferenco.log").as_deref(), Some("fifth\n"));
+        assert_eq!(read("inferenco.log.1").as_deref(), Some("fourth\n"));
+    }
+}
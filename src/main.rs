@@ -1,232 +1,368 @@
 use axum::body::Bytes;
 use axum::{
-    extract::{Query, State},
-    http::{HeaderMap, StatusCode},
-    response::{sse::Event, IntoResponse, Json, Sse},
-    routing::{get, post},
+    extract::{
+        ws::WebSocketUpgrade, ConnectInfo, DefaultBodyLimit, Extension, FromRef, Query, Request,
+        State,
+    },
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::{self, Next},
+    response::{sse::Event, IntoResponse, Json, Response, Sse},
+    routing::{delete, get, post, put},
     Router,
 };
-use dotenvy::dotenv;
-use inferenco_mcp::server::ToolService;
+use clap::Parser;
+use futures::stream::BoxStream;
+use inferenco_mcp::{
+    auth::{
+        self,
+        keys::{KeyStore, NewKey},
+        oauth::{self, BearerError},
+    },
+    cli::{self, Cli, Command, ServeArgs, ToolsCommand, Transport},
+    config::{self, Profile, Settings},
+    config_schema,
+    cors::{self, AllowedOrigins, CorsSettings},
+    crash_report::CrashReporter,
+    daemon,
+    health::{HealthChecker, HealthStatus, Readiness},
+    http_client,
+    ip_filter::IpFilter,
+    logging,
+    maintenance::MaintenanceState,
+    metrics::SessionEnd,
+    rate_limit::{RateLimiter, RATE_LIMITED_ERROR_CODE},
+    rebinding::RebindingGuard,
+    recording::{self, Exchange, Recorder},
+    reload::{EnvFile, LiveConfig, ReloadableConfig},
+    repl,
+    rpc::{self, process_rpc_body, session_id, Rejection},
+    runtime_stats, scheduler, self_check,
+    server::ToolService,
+    sessions::{self, CallGuard, ClientInfo, SessionHandle, SessionState},
+    store,
+    transport::{
+        resource_update_stream, resource_updates, session_notification_stream,
+        session_notifications,
+        sse::{self, SseStreams, Undeliverable},
+        streamable_http::{self, Admission, Refusal, StreamableSessions},
+        tcp, tool_list_change_stream, tool_list_changes, ws,
+    },
+    upgrade,
+    usage::{self, UsageTracker},
+    version,
+};
+#[cfg(windows)]
+use inferenco_mcp::{cli::ServiceCommand, winservice};
 use rmcp::{transport::stdio, ServiceExt};
-use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, convert::Infallible, env, sync::Arc, time::Duration};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::io::AsyncReadExt as _;
 use tokio_stream::{Stream, StreamExt as _};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tower_http::{
+    compression::CompressionLayer,
+    cors::{AllowOrigin, CorsLayer},
+    limit::RequestBodyLimitLayer,
+};
+use tracing::Instrument;
+use uuid::Uuid;
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+/// Largest archive `/admin/state/import` accepts.
+const MAX_STATE_ARCHIVE_BYTES: usize = 64 * 1024 * 1024;
+const USAGE_FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+/// How long a successor started by SIGUSR2 has to take over the socket.
+#[cfg(unix)]
+const UPGRADE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Shared state for the HTTP router. Handlers extract the piece they need
+/// through `FromRef`.
+#[derive(Clone)]
+struct AppState {
+    service: Arc<ToolService>,
+    health: Arc<HealthChecker>,
+    readiness: Arc<Readiness>,
+    usage: Arc<UsageTracker>,
+    config: Arc<LiveConfig>,
+    recorder: Option<Arc<Recorder>>,
+    sse: Arc<SseStreams>,
+    streamable: Arc<StreamableSessions>,
+    /// Largest WebSocket message accepted.
+    max_body_bytes: usize,
+}
+
+impl FromRef<AppState> for Arc<ToolService> {
+    fn from_ref(state: &AppState) -> Self {
+        state.service.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<HealthChecker> {
+    fn from_ref(state: &AppState) -> Self {
+        state.health.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Readiness> {
+    fn from_ref(state: &AppState) -> Self {
+        state.readiness.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<UsageTracker> {
+    fn from_ref(state: &AppState) -> Self {
+        state.usage.clone()
+    }
+}
+
+impl FromRef<AppState> for Option<Arc<Recorder>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.recorder.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<LiveConfig> {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<SseStreams> {
+    fn from_ref(state: &AppState) -> Self {
+        state.sse.clone()
+    }
+}
 
-#[derive(Deserialize)]
-struct JsonRpcRequest {
-    jsonrpc: String,
-    #[serde(default)]
-    id: Option<serde_json::Value>,
-    method: String,
-    #[serde(default)]
-    params: Option<serde_json::Value>,
+impl FromRef<AppState> for Arc<StreamableSessions> {
+    fn from_ref(state: &AppState) -> Self {
+        state.streamable.clone()
+    }
+}
+
+/// Correlation ID for an HTTP request: the caller's `x-request-id` when
+/// present, otherwise a freshly generated UUID.
+fn request_id(headers: &HeaderMap) -> String {
+    headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= 128)
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
 }
 
-#[derive(Serialize)]
-struct JsonRpcResponse {
-    jsonrpc: String,
-    id: serde_json::Value,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    result: Option<serde_json::Value>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    error: Option<serde_json::Value>,
+/// Short, non-reversible fingerprint of the API key presented on a request,
+/// safe to write to logs.
+fn api_key_fingerprint(config: &ReloadableConfig, headers: &HeaderMap) -> Option<String> {
+    let key = headers.get(&config.auth_header)?.to_str().ok()?;
+    Some(auth::fingerprint(key))
+}
+
+/// What a session records about the client opening it.
+fn client_info(config: &ReloadableConfig, headers: &HeaderMap) -> ClientInfo {
+    ClientInfo {
+        user_agent: headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+        key_fingerprint: api_key_fingerprint(config, headers),
+    }
 }
 
 async fn handle_rpc(
     State(service): State<Arc<ToolService>>,
+    State(usage): State<Arc<UsageTracker>>,
+    State(config): State<Arc<LiveConfig>>,
+    State(recorder): State<Option<Arc<Recorder>>>,
     headers: HeaderMap,
     body: Bytes,
-) -> Result<Json<JsonRpcResponse>, StatusCode> {
-    let body = String::from_utf8(body.to_vec()).map_err(|_| StatusCode::BAD_REQUEST)?;
-    // Check authentication if enabled
-    if env::var("INFERENCO_MCP_AUTH_ENABLED").unwrap_or_else(|_| "false".to_string()) == "true" {
-        let auth_header =
-            env::var("INFERENCO_MCP_AUTH_HEADER").unwrap_or_else(|_| "x-api-key".to_string());
-        let api_keys = env::var("INFERENCO_MCP_API_KEYS")
-            .unwrap_or_default()
-            .split(',')
-            .map(|s| s.trim().to_string())
-            .collect::<Vec<_>>();
-
-        if let Some(header_value) = headers.get(&auth_header) {
-            let provided_key = header_value.to_str().unwrap_or("");
-            if !api_keys.contains(&provided_key.to_string()) {
-                return Err(StatusCode::UNAUTHORIZED);
-            }
-        } else {
-            return Err(StatusCode::UNAUTHORIZED);
-        }
-    }
-
-    let request: JsonRpcRequest =
-        serde_json::from_str(&body).map_err(|_| StatusCode::BAD_REQUEST)?;
-
-    if request.jsonrpc != "2.0" {
-        return Ok(Json(JsonRpcResponse {
-            jsonrpc: "2.0".to_string(),
-            id: request.id.unwrap_or(serde_json::Value::Null),
-            result: None,
-            error: Some(serde_json::json!({
-                "code": -32600,
-                "message": "Invalid Request"
-            })),
-        }));
-    }
-
-    // Handle notifications (requests without id) - just acknowledge, don't respond
-    if request.id.is_none() {
-        // For notifications, we still process them but return empty response or 204
-        // Actually, JSON-RPC 2.0 says notifications should not receive a response
-        // But HTTP requires a response, so we'll return a minimal one
-        match request.method.as_str() {
-            "notifications/initialized" => {
-                // Client is notifying us that initialization is complete
-                // Return empty response for notifications
-                return Ok(Json(JsonRpcResponse {
-                    jsonrpc: "2.0".to_string(),
-                    id: serde_json::Value::Null,
-                    result: Some(serde_json::json!({})),
-                    error: None,
-                }));
-            }
-            _ => {
-                // Unknown notification, just acknowledge
-                return Ok(Json(JsonRpcResponse {
-                    jsonrpc: "2.0".to_string(),
-                    id: serde_json::Value::Null,
-                    result: Some(serde_json::json!({})),
-                    error: None,
-                }));
-            }
-        }
-    }
+) -> Response {
+    // One snapshot per request, so a reload never applies halfway through.
+    let config = config.get();
+    let request_id = request_id(&headers);
+    let authenticated = rpc::authenticate(&service, &config, &headers).await;
+    let auth = authenticated.as_ref().ok().and_then(Option::as_ref);
+    let fingerprint = api_key_fingerprint(&config, &headers);
+    // Token holders are known by their subject, key holders by the key.
+    let subject = auth
+        .map(|auth| auth.subject.clone())
+        .or_else(|| fingerprint.clone());
+    let span = tracing::info_span!(
+        "rpc",
+        request_id = %request_id,
+        session_id = session_id(&headers).unwrap_or("-"),
+        key_fingerprint = fingerprint.as_deref().unwrap_or("-"),
+        subject = subject.as_deref().unwrap_or("-"),
+    );
+    let usage_key = subject.as_deref().unwrap_or(usage::ANONYMOUS);
+    let bytes_in = body.len() as u64;
+    let _in_flight: Option<CallGuard> =
+        session_id(&headers).and_then(|id| service.sessions().track_call(id));
 
-    let response = match request.method.as_str() {
-        "initialize" => {
-            let server_info = service.get_server_info();
-            JsonRpcResponse {
-                jsonrpc: "2.0".to_string(),
-                id: request.id.unwrap_or(serde_json::Value::Null),
-                result: Some(serde_json::json!({
-                    "protocolVersion": server_info.protocol_version.to_string(),
-                    "capabilities": {
-                        "tools": {}
-                    },
-                    "serverInfo": {
-                        "name": server_info.server_info.name,
-                        "version": server_info.server_info.version
-                    }
-                })),
-                error: None,
-            }
-        }
-        "tools/list" => {
-            let tools = service.available_tools();
-            JsonRpcResponse {
-                jsonrpc: "2.0".to_string(),
-                id: request.id.unwrap_or(serde_json::Value::Null),
-                result: Some(serde_json::json!({
-                    "tools": tools
-                })),
-                error: None,
+    // Key-value state is private to the caller, else to the MCP session.
+    let namespace = match (auth, &fingerprint, session_id(&headers)) {
+        (Some(auth), _, _) => auth.namespace(),
+        (None, Some(fingerprint), _) => format!("key:{fingerprint}"),
+        (None, None, Some(session)) => format!("session:{session}"),
+        (None, None, None) => store::DEFAULT_NAMESPACE.to_string(),
+    };
+    let recorded_request = recorder.is_some().then(|| body.clone());
+    let session_state = session_id(&headers).and_then(|id| service.sessions().state(id));
+    let call = store::in_namespace(namespace.clone(), async {
+        rpc::process_authenticated(&service, &config, &headers, authenticated?, body).await
+    });
+    let mut response = match sessions::in_session(session_state, call)
+        .instrument(span)
+        .await
+    {
+        Ok(mut reply) => {
+            if let (Some(recorder), Some(request)) = (&recorder, recorded_request) {
+                recorder.record(Exchange {
+                    recorded_at: chrono::Utc::now().to_rfc3339(),
+                    request_id: request_id.clone(),
+                    namespace,
+                    session: session_id(&headers).map(str::to_string),
+                    request: serde_json::from_slice(&request).unwrap_or_default(),
+                    response: reply.to_value(),
+                });
             }
-        }
-        "tools/call" => {
-            if let Some(params) = request.params {
-                if let (Some(name), args) = (
-                    params.get("name").and_then(|v| v.as_str()),
-                    params
-                        .get("arguments")
-                        .cloned()
-                        .unwrap_or(serde_json::json!({})),
-                ) {
-                    match service.call_tool(name, args).await {
-                        Ok(result) => {
-                            // Convert CallToolResult to MCP response format
-                            let content: Vec<serde_json::Value> = result
-                                .content
-                                .into_iter()
-                                .map(|c| match c.raw {
-                                    rmcp::model::RawContent::Text(text) => {
-                                        serde_json::json!({"type": "text", "text": text.text})
-                                    }
-                                    rmcp::model::RawContent::Resource(_)
-                                    | rmcp::model::RawContent::Image(_)
-                                    | rmcp::model::RawContent::Audio(_)
-                                    | rmcp::model::RawContent::ResourceLink(_) => {
-                                        // Other content types not fully implemented yet
-                                        serde_json::json!({
-                                            "type": "text",
-                                            "text": "Content type not supported"
-                                        })
-                                    }
-                                })
-                                .collect();
-
-                            JsonRpcResponse {
-                                jsonrpc: "2.0".to_string(),
-                                id: request.id.unwrap_or(serde_json::Value::Null),
-                                result: Some(serde_json::json!({
-                                    "content": content
-                                })),
-                                error: None,
-                            }
+            for call in &mut reply.calls {
+                if let Some(serde_json::Value::Object(error)) = call.response.error.as_mut() {
+                    match error.get_mut("data") {
+                        Some(serde_json::Value::Object(data)) => {
+                            data.insert("request_id".to_string(), request_id.clone().into());
+                        }
+                        _ => {
+                            error.insert(
+                                "data".to_string(),
+                                serde_json::json!({ "request_id": request_id }),
+                            );
                         }
-                        Err(e) => JsonRpcResponse {
-                            jsonrpc: "2.0".to_string(),
-                            id: request.id.unwrap_or(serde_json::Value::Null),
-                            result: None,
-                            error: Some(serde_json::json!({
-                                "code": -32603,
-                                "message": e.to_string()
-                            })),
-                        },
-                    }
-                } else {
-                    JsonRpcResponse {
-                        jsonrpc: "2.0".to_string(),
-                        id: request.id.unwrap_or(serde_json::Value::Null),
-                        result: None,
-                        error: Some(serde_json::json!({
-                            "code": -32602,
-                            "message": "Invalid params"
-                        })),
                     }
                 }
+            }
+            if reply.calls.is_empty() {
+                // Notifications, alone or in a batch, get no response body.
+                StatusCode::ACCEPTED.into_response()
+            } else if !reply.batch {
+                let call = &reply.calls[0];
+                let body =
+                    serde_json::to_vec(&call.response).expect("JSON-RPC response serializes");
+                usage.record(
+                    usage_key,
+                    &call.operation,
+                    call.response.error.is_some(),
+                    bytes_in,
+                    body.len() as u64,
+                );
+                ([(header::CONTENT_TYPE, "application/json")], body).into_response()
             } else {
-                JsonRpcResponse {
-                    jsonrpc: "2.0".to_string(),
-                    id: request.id.unwrap_or(serde_json::Value::Null),
-                    result: None,
-                    error: Some(serde_json::json!({
-                        "code": -32602,
-                        "message": "Invalid params"
-                    })),
+                // Batch calls are accounted one by one, by their own sizes.
+                for call in &reply.calls {
+                    let bytes_out = serde_json::to_vec(&call.response)
+                        .expect("JSON-RPC response serializes")
+                        .len() as u64;
+                    usage.record(
+                        usage_key,
+                        &call.operation,
+                        call.response.error.is_some(),
+                        call.bytes_in,
+                        bytes_out,
+                    );
                 }
+                let body =
+                    serde_json::to_vec(&reply.to_value()).expect("JSON-RPC responses serialize");
+                ([(header::CONTENT_TYPE, "application/json")], body).into_response()
             }
         }
-        _ => JsonRpcResponse {
-            jsonrpc: "2.0".to_string(),
-            id: request.id.unwrap_or(serde_json::Value::Null),
-            result: None,
-            error: Some(serde_json::json!({
-                "code": -32601,
-                "message": "Method not found"
-            })),
-        },
+        // Rejected keys are not attributed usage; they never reached dispatch.
+        Err(rejection @ (Rejection::Unauthorized | Rejection::Forbidden)) => {
+            refuse_credentials(&service, &headers, rejection)
+        }
+        Err(rejection) => {
+            let body =
+                serde_json::to_vec(&rejection.response()).expect("JSON-RPC response serializes");
+            usage.record(usage_key, "invalid", true, bytes_in, body.len() as u64);
+            (
+                StatusCode::BAD_REQUEST,
+                [(header::CONTENT_TYPE, "application/json")],
+                body,
+            )
+                .into_response()
+        }
     };
-
-    Ok(Json(response))
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
 }
 
-async fn handle_health() -> impl IntoResponse {
-    Json(serde_json::json!({
+async fn handle_health(
+    State(health): State<Arc<HealthChecker>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let mut body = serde_json::json!({
         "status": "ok",
         "service": "inferenco-mcp",
         "protocol_version": rmcp::model::ProtocolVersion::LATEST.to_string()
-    }))
+    });
+
+    // Upstream probes only run when explicitly requested so that cheap
+    // container healthchecks never depend on third-party availability.
+    let deep = params
+        .get("deep")
+        .is_some_and(|value| value == "true" || value == "1");
+    if !deep {
+        return Json(body).into_response();
+    }
+
+    let report = health.check().await;
+    body["status"] = serde_json::json!(report.status);
+    body["dependencies"] = serde_json::json!(report.dependencies);
+    let status = match report.status {
+        HealthStatus::Unhealthy => StatusCode::SERVICE_UNAVAILABLE,
+        HealthStatus::Ok | HealthStatus::Degraded => StatusCode::OK,
+    };
+    (status, Json(body)).into_response()
+}
+
+async fn handle_version() -> impl IntoResponse {
+    Json(version::build_info())
+}
+
+async fn handle_livez() -> impl IntoResponse {
+    Json(serde_json::json!({ "status": "alive" }))
+}
+
+async fn handle_readyz(
+    State(readiness): State<Arc<Readiness>>,
+    State(health): State<Arc<HealthChecker>>,
+    State(service): State<Arc<ToolService>>,
+) -> Response {
+    let report = readiness.evaluate(&health, service.maintenance()).await;
+    let status = if report.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report)).into_response()
+}
+
+async fn handle_metrics(State(service): State<Arc<ToolService>>) -> impl IntoResponse {
+    (
+        [(
+            header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        service.metrics().render(),
+    )
 }
 
 fn create_keepalive_stream() -> impl Stream<Item = Result<Event, Infallible>> + Send + 'static {
@@ -234,26 +370,67 @@ fn create_keepalive_stream() -> impl Stream<Item = Result<Event, Infallible>> +
         .map(|_| Ok(Event::default().comment("keepalive")))
 }
 
+/// Tracks the session of one `/sse` or `/ws` connection; dropping it (when
+/// the connection ends) records the close in metrics and logs.
+struct ConnectionSession {
+    session: SessionHandle,
+    transport: &'static str,
+    opened: Instant,
+    evicted: Arc<AtomicBool>,
+    service: Arc<ToolService>,
+}
+
+impl ConnectionSession {
+    fn open(service: Arc<ToolService>, transport: &'static str, client: ClientInfo) -> Self {
+        let session = service
+            .sessions()
+            .register(Uuid::new_v4().to_string(), transport, client);
+        service.metrics().session_opened();
+        tracing::info!(session_id = %session.id(), event = "opened", "{transport} session opened");
+        Self {
+            session,
+            transport,
+            opened: Instant::now(),
+            evicted: Arc::new(AtomicBool::new(false)),
+            service,
+        }
+    }
+}
+
+impl Drop for ConnectionSession {
+    fn drop(&mut self) {
+        let age = self.opened.elapsed();
+        let end = if self.evicted.load(Ordering::Acquire) {
+            SessionEnd::Evicted
+        } else {
+            SessionEnd::Disconnected
+        };
+        self.service.metrics().session_closed(age, end);
+        tracing::info!(
+            session_id = %self.session.id(),
+            event = if end == SessionEnd::Evicted { "evicted" } else { "disconnected" },
+            age_secs = age.as_secs(),
+            "{} session closed",
+            self.transport
+        );
+    }
+}
+
 async fn handle_sse(
     State(service): State<Arc<ToolService>>,
+    State(config): State<Arc<LiveConfig>>,
+    State(streams): State<Arc<SseStreams>>,
+    headers: HeaderMap,
     Query(params): Query<HashMap<String, String>>,
-) -> Sse<impl Stream<Item = Result<Event, Infallible>> + Send + 'static> {
-    let service_clone = service.clone();
-
-    // Handle authentication if enabled
-    let auth_enabled =
-        env::var("INFERENCO_MCP_AUTH_ENABLED").unwrap_or_else(|_| "false".to_string()) == "true";
+) -> Response {
+    let config = config.get();
 
     // Check authentication first
-    if auth_enabled {
-        let api_keys: Vec<String> = env::var("INFERENCO_MCP_API_KEYS")
-            .unwrap_or_default()
-            .split(',')
-            .map(|s| s.trim().to_string())
-            .collect();
-
+    if config.auth_enabled && auth::certificate().is_none() {
         let is_authorized = if let Some(token) = params.get("token") {
-            api_keys.contains(token)
+            rpc::authenticate_token(&service, &config, token)
+                .await
+                .is_ok()
         } else {
             false
         };
@@ -271,137 +448,1658 @@ async fn handle_sse(
                 .unwrap();
             let error_stream = tokio_stream::once(Ok(error_event));
             let stream = error_stream.chain(create_keepalive_stream());
-            return Sse::new(stream).keep_alive(
-                axum::response::sse::KeepAlive::new()
-                    .interval(Duration::from_secs(15))
-                    .text("keep-alive-text"),
-            );
+            return Sse::new(stream)
+                .keep_alive(
+                    axum::response::sse::KeepAlive::new()
+                        .interval(Duration::from_secs(15))
+                        .text("keep-alive-text"),
+                )
+                .into_response();
         }
     }
 
-    // Send initial connection event
-    let server_info = service_clone.get_server_info();
-    let init_event = Event::default()
-        .json_data(serde_json::json!({
-            "jsonrpc": "2.0",
-            "result": {
-                "protocolVersion": server_info.protocol_version.to_string(),
-                "capabilities": {
-                    "tools": {}
-                },
-                "serverInfo": {
-                    "name": server_info.server_info.name,
-                    "version": server_info.server_info.version
+    let guard = ConnectionSession::open(service.clone(), "sse", client_info(&config, &headers));
+    let session_id = HeaderValue::from_str(guard.session.id()).expect("UUIDs are valid headers");
+    let state = service
+        .sessions()
+        .state(guard.session.id())
+        .expect("the guard keeps the session registered");
+    let notifications = service
+        .sessions()
+        .notifications(guard.session.id())
+        .expect("the guard keeps the session registered");
+    // The endpoint event tells the client where to POST; responses to those
+    // POSTs come back through the outbox.
+    let outbox = streams.open(guard.session.id());
+    let stream = tokio_stream::once(Ok(sse::endpoint_event(guard.session.id())))
+        .chain(create_keepalive_stream())
+        .merge(outbox.into_stream())
+        .merge(resource_update_stream(&service, state))
+        .merge(tool_list_change_stream(&service))
+        .merge(session_notification_stream(notifications));
+    // Sessions live until the client disconnects unless a maximum age is set.
+    let stream: BoxStream<'static, _> = match config.sse_max_age {
+        Some(max_age) => {
+            // Close the stream once the session outlives its maximum age.
+            let evicted = guard.evicted.clone();
+            let deadline = async move {
+                tokio::time::sleep(max_age).await;
+                evicted.store(true, Ordering::Release);
+            };
+            Box::pin(
+                futures::StreamExt::take_until(stream, deadline).map(move |event| {
+                    let _ = &guard;
+                    event
+                }),
+            )
+        }
+        None => Box::pin(stream.map(move |event| {
+            let _ = &guard;
+            event
+        })),
+    };
+
+    // The ID is also a header, for clients that send it back in
+    // `mcp-session-id` rather than the endpoint's query.
+    (
+        [("mcp-session-id", session_id)],
+        Sse::new(stream).keep_alive(
+            axum::response::sse::KeepAlive::new()
+                .interval(Duration::from_secs(15))
+                .text("keep-alive-text"),
+        ),
+    )
+        .into_response()
+}
+
+/// HTTP+SSE: a message for the session named by the `sessionId` query
+/// parameter or the `mcp-session-id` header. It is answered like `/rpc`
+/// within the session, but the response goes out on the session's stream
+/// and the POST itself only gets `202 Accepted`. Sessions without an open
+/// stream get 404; requests refused before dispatch are answered in the body.
+async fn handle_sse_message(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+    mut headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let session = params
+        .get("sessionId")
+        .cloned()
+        .or_else(|| session_id(&headers).map(str::to_string));
+    let Some(session) = session.filter(|id| state.sse.contains(id)) else {
+        return sse_refusal();
+    };
+    let value = HeaderValue::from_str(&session).expect("open session IDs are valid headers");
+    headers.insert("mcp-session-id", value);
+    let response = handle_rpc(
+        State(state.service),
+        State(state.usage),
+        State(state.config),
+        State(state.recorder),
+        headers,
+        body,
+    )
+    .await;
+    // Notifications (202) and rejections have nothing to deliver.
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    let Ok(body) = axum::body::to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let message = String::from_utf8(body.to_vec()).expect("JSON-RPC responses are UTF-8");
+    match state.sse.deliver(&session, message).await {
+        Ok(()) => {
+            parts.status = StatusCode::ACCEPTED;
+            parts.headers.remove(header::CONTENT_TYPE);
+            Response::from_parts(parts, axum::body::Body::empty())
+        }
+        Err(Undeliverable::NoStream) => sse_refusal(),
+    }
+}
+
+/// The answer to an `/sse` message for a session with no open stream.
+fn sse_refusal() -> Response {
+    let error = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": null,
+        "error": { "code": -32000, "message": "No open /sse stream for this session; connect to /sse first" }
+    });
+    (StatusCode::NOT_FOUND, Json(error)).into_response()
+}
+
+/// WebSocket: the whole JSON-RPC conversation over one connection, in a
+/// session that lasts as long as it. Browsers cannot set headers on the
+/// upgrade, so the API key may also be given as `?token=`.
+async fn handle_ws(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+    rate_limit: Option<Extension<RateLimitScope>>,
+    mut headers: HeaderMap,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let config = state.config.get();
+    if let Some(token) = params.get("token") {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(config.auth_header.as_bytes()),
+            HeaderValue::from_str(token),
+        ) {
+            headers.insert(name, value);
+        }
+    }
+    // Checked before the upgrade; every message is checked again, so a key
+    // revoked by a reload stops working mid-connection.
+    if let Err(rejection) = rpc::authenticate(&state.service, &config, &headers).await {
+        return refuse_credentials(&state.service, &headers, rejection);
+    }
+    // Each message gets its own request ID.
+    headers.remove(REQUEST_ID_HEADER);
+    let client = client_info(&config, &headers);
+    // The socket is served on a task of its own, outside the connection's
+    // certificate scope.
+    let certificate = auth::certificate();
+    let upgrade = upgrade.max_message_size(state.max_body_bytes);
+    upgrade.on_upgrade(move |socket| async move {
+        let service = state.service.clone();
+        let guard = ConnectionSession::open(service.clone(), ws::TRANSPORT, client);
+        let id = guard.session.id();
+        let value = HeaderValue::from_str(id).expect("UUIDs are valid headers");
+        headers.insert("mcp-session-id", value);
+        let session_state = service
+            .sessions()
+            .state(id)
+            .expect("the guard keeps the session registered");
+        let notifications = service
+            .sessions()
+            .notifications(id)
+            .expect("the guard keeps the session registered");
+        let pushes = futures::stream::select(
+            futures::stream::select(
+                resource_updates(&service, session_state),
+                tool_list_changes(&service),
+            ),
+            session_notifications(notifications),
+        );
+        let handle = |message: String| {
+            let state = state.clone();
+            let headers = headers.clone();
+            let rate_limit = rate_limit.clone();
+            async move {
+                // Each message counts against the limits the upgrade did.
+                if let Some(Extension(scope)) = rate_limit {
+                    let request = serde_json::from_str::<serde_json::Value>(&message).ok();
+                    let id = request.as_ref().and_then(|request| request.get("id"));
+                    if let Err((error, _)) = scope.check(id.cloned().unwrap_or_default()) {
+                        // Notifications get no answer, refused or not.
+                        let notification = request
+                            .as_ref()
+                            .is_some_and(|request| request.is_object() && id.is_none());
+                        return (!notification).then(|| error.to_string());
+                    }
+                }
+                let response = handle_rpc(
+                    State(state.service),
+                    State(state.usage),
+                    State(state.config),
+                    State(state.recorder),
+                    headers,
+                    Bytes::from(message),
+                )
+                .await;
+                match response.status() {
+                    StatusCode::ACCEPTED => None,
+                    StatusCode::UNAUTHORIZED => Some(
+                        serde_json::to_string(&Rejection::Unauthorized.response())
+                            .expect("JSON-RPC responses serialize"),
+                    ),
+                    StatusCode::FORBIDDEN => Some(
+                        serde_json::to_string(&Rejection::Forbidden.response())
+                            .expect("JSON-RPC responses serialize"),
+                    ),
+                    _ => {
+                        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                            .await
+                            .ok()?;
+                        Some(
+                            String::from_utf8(body.to_vec()).expect("JSON-RPC responses are UTF-8"),
+                        )
+                    }
                 }
             }
-        }))
-        .unwrap();
+        };
+        auth::with_certificate(certificate, ws::serve(socket, pushes, handle)).await;
+        drop(guard);
+    })
+}
 
-    // Create a stream that sends the initial event and then keeps connection alive
-    let init_stream = tokio_stream::once(Ok(init_event));
-    let stream = init_stream.chain(create_keepalive_stream());
+/// The answer to a request refused for its credentials. When bearer tokens
+/// are accepted it carries the `WWW-Authenticate` challenge OAuth clients
+/// start from.
+fn refuse_credentials(
+    service: &ToolService,
+    headers: &HeaderMap,
+    rejection: Rejection,
+) -> Response {
+    let (status, error) = match rejection {
+        Rejection::Forbidden => (StatusCode::FORBIDDEN, Some(BearerError::InsufficientScope)),
+        _ if headers.contains_key(header::AUTHORIZATION) => {
+            (StatusCode::UNAUTHORIZED, Some(BearerError::InvalidToken))
+        }
+        _ => (StatusCode::UNAUTHORIZED, None),
+    };
+    let mut response = status.into_response();
+    if service.jwt().is_some() {
+        let challenge = oauth::challenge(service.oauth(), error);
+        if let Ok(value) = HeaderValue::from_str(&challenge) {
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, value);
+        }
+    }
+    response
+}
+
+/// RFC 9728 protected resource metadata: which authorization servers issue
+/// tokens for this server. 404 unless `INFERENCO_MCP_OAUTH_RESOURCE` is set.
+async fn handle_oauth_metadata(State(service): State<Arc<ToolService>>) -> Response {
+    match service.oauth() {
+        Some(oauth) => Json(oauth.metadata()).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
 
-    Sse::new(stream).keep_alive(
-        axum::response::sse::KeepAlive::new()
-            .interval(Duration::from_secs(15))
-            .text("keep-alive-text"),
+/// A request to `/mcp` turned away before dispatch.
+fn refuse(refusal: Refusal) -> Response {
+    (refusal.status(), Json(refusal.response())).into_response()
+}
+
+/// Streamable HTTP: JSON-RPC messages from the client. `initialize` opens
+/// a session, named in the response's `mcp-session-id`; everything else
+/// must carry that header and is answered like `/rpc` within the session.
+async fn handle_mcp_post(
+    State(service): State<Arc<ToolService>>,
+    State(usage): State<Arc<UsageTracker>>,
+    State(config): State<Arc<LiveConfig>>,
+    State(recorder): State<Option<Arc<Recorder>>>,
+    State(streamable): State<Arc<StreamableSessions>>,
+    mut headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let snapshot = config.get();
+    // Checked here as well as in dispatch so refused keys never open sessions.
+    if let Err(rejection) = rpc::authenticate(&service, &snapshot, &headers).await {
+        return refuse_credentials(&service, &headers, rejection);
+    }
+    let opened = match streamable.admit(&headers, &body) {
+        Ok(Admission::Initialize) => {
+            let id = match streamable.open(client_info(&snapshot, &headers)) {
+                Ok(id) => id,
+                Err(refusal) => return refuse(refusal),
+            };
+            let value = HeaderValue::from_str(&id).expect("UUIDs are valid headers");
+            headers.insert(streamable_http::SESSION_HEADER, value);
+            Some(id)
+        }
+        Ok(Admission::Session(_)) => None,
+        Err(refusal) => return refuse(refusal),
+    };
+    let mut response = handle_rpc(
+        State(service),
+        State(usage),
+        State(config),
+        State(recorder),
+        headers,
+        body,
     )
+    .await;
+    if let Some(id) = opened {
+        if response.status().is_success() {
+            let value = HeaderValue::from_str(&id).expect("UUIDs are valid headers");
+            response
+                .headers_mut()
+                .insert(streamable_http::SESSION_HEADER, value);
+        } else {
+            streamable.close(&id);
+        }
+    }
+    response
 }
 
-async fn handle_sse_message(
+/// Streamable HTTP: the event stream of server-initiated messages for the
+/// session in `mcp-session-id`.
+async fn handle_mcp_get(
     State(service): State<Arc<ToolService>>,
+    State(config): State<Arc<LiveConfig>>,
+    State(streamable): State<Arc<StreamableSessions>>,
     headers: HeaderMap,
-    body: Bytes,
-) -> Result<Json<JsonRpcResponse>, StatusCode> {
-    // SSE messages can also be sent via POST to /sse endpoint
-    // This allows bidirectional communication
-    handle_rpc(State(service), headers, body).await
+) -> Response {
+    if let Err(rejection) = rpc::authenticate(&service, &config.get(), &headers).await {
+        return refuse_credentials(&service, &headers, rejection);
+    }
+    let Some(id) = session_id(&headers) else {
+        return refuse(Refusal::MissingSession);
+    };
+    match streamable.events(id) {
+        Some(events) => Sse::new(events)
+            .keep_alive(
+                axum::response::sse::KeepAlive::new()
+                    .interval(Duration::from_secs(15))
+                    .text("keep-alive-text"),
+            )
+            .into_response(),
+        None => refuse(Refusal::UnknownSession),
+    }
+}
+
+/// Streamable HTTP: end the session in `mcp-session-id`.
+async fn handle_mcp_delete(
+    State(service): State<Arc<ToolService>>,
+    State(config): State<Arc<LiveConfig>>,
+    State(streamable): State<Arc<StreamableSessions>>,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(rejection) = rpc::authenticate(&service, &config.get(), &headers).await {
+        return refuse_credentials(&service, &headers, rejection);
+    }
+    match session_id(&headers) {
+        Some(id) if streamable.close(id) => StatusCode::NO_CONTENT.into_response(),
+        Some(_) => refuse(Refusal::UnknownSession),
+        None => refuse(Refusal::MissingSession),
+    }
+}
+
+/// Gate for `/admin/*` endpoints. Admin access is disabled (404) unless
+/// `INFERENCO_MCP_ADMIN_TOKEN` is set, and otherwise requires it as a bearer
+/// token.
+fn authorize_admin(config: &LiveConfig, headers: &HeaderMap) -> Result<(), StatusCode> {
+    let config = config.get();
+    let Some(expected) = &config.admin_token else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    // Compare digests so the comparison time doesn't depend on the token.
+    if Sha256::digest(provided.as_bytes()) == Sha256::digest(expected.as_bytes()) {
+        Ok(())
+    } else {
+        Err(StatusCode::UNAUTHORIZED)
+    }
+}
+
+async fn handle_admin_slow_calls(
+    State(service): State<Arc<ToolService>>,
+    State(config): State<Arc<LiveConfig>>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    authorize_admin(&config, &headers)?;
+    let slow_calls = service.slow_calls();
+    Ok(Json(serde_json::json!({
+        "threshold_ms": slow_calls.threshold().as_millis() as u64,
+        "calls": slow_calls.recent(),
+    })))
+}
+
+async fn handle_admin_sessions(
+    State(service): State<Arc<ToolService>>,
+    State(config): State<Arc<LiveConfig>>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    authorize_admin(&config, &headers)?;
+    Ok(Json(serde_json::json!({
+        "sessions": service.sessions().list(),
+    })))
 }
 
-async fn start_http_server(service: ToolService) -> Result<(), Box<dyn std::error::Error>> {
-    let port = env::var("INFERENCO_MCP_PORT")
-        .unwrap_or_else(|_| "8080".to_string())
-        .parse::<u16>()
-        .unwrap_or(8080);
+async fn handle_admin_tools(
+    State(service): State<Arc<ToolService>>,
+    State(config): State<Arc<LiveConfig>>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    authorize_admin(&config, &headers)?;
+    let tools: Vec<serde_json::Value> = service
+        .registered_tools()
+        .into_iter()
+        .map(|tool| {
+            serde_json::json!({
+                "enabled": service.is_tool_enabled(&tool.name),
+                "name": tool.name,
+                "description": tool.description,
+                "source": "builtin",
+                "input_schema": tool.input_schema,
+            })
+        })
+        .collect();
+    Ok(Json(serde_json::json!({ "tools": tools })))
+}
+
+#[derive(serde::Deserialize)]
+struct ToolSwitch {
+    enabled: bool,
+}
+
+/// Enable or disable one tool, e.g. `{"enabled": false}`. Connected clients
+/// are told the tool list changed.
+async fn handle_admin_set_tool(
+    State(service): State<Arc<ToolService>>,
+    State(config): State<Arc<LiveConfig>>,
+    headers: HeaderMap,
+    axum::extract::Path(name): axum::extract::Path<String>,
+    Json(switch): Json<ToolSwitch>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    authorize_admin(&config, &headers)?;
+    let changed = service
+        .set_tool_enabled(&name, switch.enabled)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(serde_json::json!({
+        "name": name,
+        "enabled": switch.enabled,
+        "changed": changed,
+    })))
+}
+
+/// The key store, or 404 when no `INFERENCO_MCP_KEYS_FILE` is configured.
+fn key_store(service: &ToolService) -> Result<Arc<KeyStore>, StatusCode> {
+    service.key_store().cloned().ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Issued API keys, without their hashes.
+async fn handle_admin_keys(
+    State(service): State<Arc<ToolService>>,
+    State(config): State<Arc<LiveConfig>>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    authorize_admin(&config, &headers)?;
+    let now = chrono::Utc::now().timestamp();
+    let keys: Vec<serde_json::Value> = key_store(&service)?
+        .list()
+        .iter()
+        .map(|key| key.summary(now))
+        .collect();
+    Ok(Json(serde_json::json!({ "keys": keys })))
+}
+
+/// Issue a key, e.g. `{"name": "ci", "scopes": ["roll_dice"], "expires_at":
+/// "90d"}`. The key is in the response and nowhere else.
+async fn handle_admin_create_key(
+    State(service): State<Arc<ToolService>>,
+    State(config): State<Arc<LiveConfig>>,
+    headers: HeaderMap,
+    Json(new): Json<NewKey>,
+) -> Result<Response, StatusCode> {
+    authorize_admin(&config, &headers)?;
+    let store = key_store(&service)?;
+    let now = chrono::Utc::now().timestamp();
+    match store.create(new, now) {
+        Ok((key, secret)) => {
+            tracing::info!(key_id = %key.id, name = %key.name, "API key created");
+            let mut body = key.summary(now);
+            body["key"] = secret.into();
+            Ok((StatusCode::CREATED, Json(body)).into_response())
+        }
+        Err(error) => Ok((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": error })),
+        )
+            .into_response()),
+    }
+}
+
+async fn handle_admin_revoke_key(
+    State(service): State<Arc<ToolService>>,
+    State(config): State<Arc<LiveConfig>>,
+    headers: HeaderMap,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    authorize_admin(&config, &headers)?;
+    let store = key_store(&service)?;
+    let revoked = store
+        .revoke(&id, chrono::Utc::now().timestamp())
+        .map_err(|error| {
+            tracing::error!(%error, "API key not revoked");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if revoked {
+        tracing::info!(key_id = %id, "API key revoked");
+    }
+    Ok(Json(serde_json::json!({ "id": id, "revoked": revoked })))
+}
+
+async fn handle_admin_caches(
+    State(service): State<Arc<ToolService>>,
+    State(config): State<Arc<LiveConfig>>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    authorize_admin(&config, &headers)?;
+    Ok(Json(serde_json::json!({
+        "caches": service.caches().reports(),
+    })))
+}
+
+/// Flush `?name=<cache>`, or every cache when no name is given.
+async fn handle_admin_cache_flush(
+    State(service): State<Arc<ToolService>>,
+    State(config): State<Arc<LiveConfig>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    authorize_admin(&config, &headers)?;
+    let name = params.get("name").map(String::as_str);
+    let flushed = service.caches().flush(name);
+    if name.is_some() && flushed.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(Json(serde_json::json!({ "flushed": flushed })))
+}
+
+/// The whole state store as a `state import` archive.
+async fn handle_admin_state_export(
+    State(service): State<Arc<ToolService>>,
+    State(config): State<Arc<LiveConfig>>,
+    headers: HeaderMap,
+) -> Result<Json<store::StateSnapshot>, StatusCode> {
+    authorize_admin(&config, &headers)?;
+    let state = Arc::clone(service.state_store());
+    let caches = service.caches().reports();
+    tokio::task::spawn_blocking(move || store::export(state.as_ref(), caches))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map(Json)
+        .map_err(|error| {
+            tracing::error!(%error, "state export failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+/// Merge an archive from `state export` into the running store.
+async fn handle_admin_state_import(
+    State(service): State<Arc<ToolService>>,
+    State(config): State<Arc<LiveConfig>>,
+    headers: HeaderMap,
+    Json(snapshot): Json<store::StateSnapshot>,
+) -> Result<Response, StatusCode> {
+    authorize_admin(&config, &headers)?;
+    let state = Arc::clone(service.state_store());
+    let limits = service.state_limits();
+    let now = chrono::Utc::now().timestamp_millis();
+    let imported =
+        tokio::task::spawn_blocking(move || store::import(state.as_ref(), &snapshot, limits, now))
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    match imported {
+        Ok(summary) => {
+            tracing::info!(?summary, "state imported");
+            Ok(Json(summary).into_response())
+        }
+        Err(store::ImportError::Rejected(error)) => Ok((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": error })),
+        )
+            .into_response()),
+        Err(store::ImportError::Store(error)) => {
+            tracing::error!(%error, "state import failed");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn handle_admin_maintenance(
+    State(service): State<Arc<ToolService>>,
+    State(config): State<Arc<LiveConfig>>,
+    headers: HeaderMap,
+) -> Result<Json<MaintenanceState>, StatusCode> {
+    authorize_admin(&config, &headers)?;
+    Ok(Json(service.maintenance().state()))
+}
+
+/// Switch maintenance mode, e.g. `{"enabled": true, "allow_read_only": true}`.
+async fn handle_admin_set_maintenance(
+    State(service): State<Arc<ToolService>>,
+    State(config): State<Arc<LiveConfig>>,
+    headers: HeaderMap,
+    Json(state): Json<MaintenanceState>,
+) -> Result<Json<MaintenanceState>, StatusCode> {
+    authorize_admin(&config, &headers)?;
+    service.maintenance().set(state);
+    Ok(Json(service.maintenance().state()))
+}
+
+/// Runtime, process, and cache statistics. `?sample_ms=` sets how long worker
+/// utilization is measured for (default 1000, at most 10000).
+async fn handle_admin_runtime(
+    State(service): State<Arc<ToolService>>,
+    State(config): State<Arc<LiveConfig>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    authorize_admin(&config, &headers)?;
+    let sample = match params.get("sample_ms") {
+        Some(value) => Duration::from_millis(value.parse().map_err(|_| StatusCode::BAD_REQUEST)?),
+        None => Duration::from_secs(1),
+    };
+    Ok(Json(serde_json::json!({
+        "runtime": runtime_stats::sample_runtime(sample).await,
+        "process": runtime_stats::process_stats(),
+        "sessions": service.sessions().len(),
+        "caches": service.caches().reports(),
+    })))
+}
+
+async fn handle_admin_usage(
+    State(usage): State<Arc<UsageTracker>>,
+    State(config): State<Arc<LiveConfig>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Response, StatusCode> {
+    authorize_admin(&config, &headers)?;
+    let window = match params.get("window") {
+        Some(value) => usage::parse_window(value).ok_or(StatusCode::BAD_REQUEST)?,
+        None => Duration::from_secs(24 * 3600),
+    };
+    let summary = usage.summary(window);
+
+    if params.get("format").map(String::as_str) == Some("csv") {
+        return Ok((
+            [(header::CONTENT_TYPE, "text/csv; charset=utf-8")],
+            usage::to_csv(&summary),
+        )
+            .into_response());
+    }
+    Ok(Json(serde_json::json!({
+        "window_seconds": window.as_secs(),
+        "keys": summary,
+    }))
+    .into_response())
+}
+
+/// Answer the JSON-RPC request or batch on stdin through the same dispatch
+/// as `/rpc`, without auth, and print the response. Returns whether every
+/// response was a success.
+async fn run_once(service: &ToolService) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut input = String::new();
+    tokio::io::stdin().read_to_string(&mut input).await?;
+    let config = ReloadableConfig {
+        auth_enabled: false,
+        ..ReloadableConfig::from_env()
+    };
+
+    let (batch, responses) =
+        match process_rpc_body(service, &config, &HeaderMap::new(), Bytes::from(input)).await {
+            Ok(reply) => (
+                reply.batch,
+                reply.calls.into_iter().map(|call| call.response).collect(),
+            ),
+            Err(rejection) => (false, vec![rejection.response()]),
+        };
+    let ok = responses.iter().all(|response| response.error.is_none());
+    let output = match (batch, responses.as_slice()) {
+        (_, []) => return Ok(true),
+        (false, [response]) => serde_json::to_string(response),
+        _ => serde_json::to_string(&responses),
+    };
+    println!("{}", output.expect("JSON-RPC responses serialize"));
+    Ok(ok)
+}
+
+/// Re-run the requests in a recording against a fresh in-process service,
+/// in their original namespaces and sessions, and print how each response
+/// differs. Returns whether all of them matched.
+async fn replay(path: &Path) -> Result<bool, String> {
+    let exchanges = recording::read(path)?;
+    let service = ToolService::new();
+    let config = ReloadableConfig {
+        auth_enabled: false,
+        ..ReloadableConfig::from_env()
+    };
+    let headers = HeaderMap::new();
+    let mut states: HashMap<String, SessionState> = HashMap::new();
+    let mut differing = 0;
+    for (index, exchange) in exchanges.iter().enumerate() {
+        let state = exchange
+            .session
+            .as_ref()
+            .map(|id| states.entry(id.clone()).or_default().clone());
+        let body =
+            Bytes::from(serde_json::to_vec(&exchange.request).expect("JSON values serialize"));
+        let call = store::in_namespace(
+            exchange.namespace.clone(),
+            process_rpc_body(&service, &config, &headers, body),
+        );
+        let actual = match sessions::in_session(state, call).await {
+            Ok(reply) => reply.to_value(),
+            Err(Rejection::Unauthorized) => serde_json::json!({ "http_status": 401 }),
+            Err(Rejection::Forbidden) => serde_json::json!({ "http_status": 403 }),
+            Err(rejection) => serde_json::to_value(rejection.response()).expect("serializes"),
+        };
+        let method = exchange.request["method"].as_str().unwrap_or("?");
+        let label = match (
+            &exchange.request,
+            exchange.request["params"]["name"].as_str(),
+        ) {
+            (serde_json::Value::Array(requests), _) => format!("batch of {}", requests.len()),
+            (_, Some(tool)) if method == "tools/call" => format!("{method} {tool}"),
+            _ => method.to_string(),
+        };
+        let differences = recording::diff(&exchange.response, &actual);
+        if differences.is_empty() {
+            println!("#{} {label} ({}): same", index + 1, exchange.request_id);
+        } else {
+            differing += 1;
+            println!("#{} {label} ({}): differs", index + 1, exchange.request_id);
+            for difference in differences {
+                println!("    {difference}");
+            }
+        }
+    }
+    println!(
+        "{differing} of {} responses differ from {}",
+        exchanges.len(),
+        path.display()
+    );
+    Ok(differing == 0)
+}
+
+async fn start_http_server(
+    service: ToolService,
+    settings: &Settings,
+    config: Arc<LiveConfig>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let port = settings.port;
+    let drain_delay = settings.drain_delay;
+    let readiness = Arc::new(Readiness::new());
+    let mut health = HealthChecker::from_settings(&settings.health);
+    if let Some(alerts) = service.alerts() {
+        health = health.with_alerts(alerts.clone());
+    }
+    let health = Arc::new(health);
+    readiness.set_config_valid(true);
+    let tools = tool_names(&service);
+    if !tools.is_empty() {
+        readiness.mark_registry_built();
+    }
+
+    // Read the certificates before binding, so a bad file stops startup.
+    #[cfg(feature = "tls")]
+    let tls = settings
+        .tls
+        .as_ref()
+        .map(|tls| tls.server_config())
+        .transpose()?;
+    let (http, ws) = match settings.tls {
+        Some(_) => ("https", "wss"),
+        None => ("http", "ws"),
+    };
+    let usage = Arc::new(UsageTracker::open(settings.usage_path.as_deref())?);
+    if usage.path().is_some() {
+        let usage = usage.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(USAGE_FLUSH_INTERVAL);
+            loop {
+                interval.tick().await;
+                save_usage(usage.clone()).await;
+            }
+        });
+    }
+
+    let recorder = match &settings.record_path {
+        Some(path) => {
+            let recorder = Recorder::open(path)?;
+            tracing::warn!(path = %path.display(), "recording JSON-RPC traffic");
+            Some(Arc::new(recorder))
+        }
+        None => None,
+    };
 
     let service = Arc::new(service);
+    let streamable = Arc::new(StreamableSessions::new(
+        service.clone(),
+        settings.session_idle,
+        settings.max_sessions,
+    ));
+    streamable.spawn_sweeper();
+    let state = AppState {
+        service,
+        health,
+        readiness: readiness.clone(),
+        usage: usage.clone(),
+        config,
+        recorder,
+        sse: Arc::new(SseStreams::new()),
+        streamable,
+        max_body_bytes: settings.max_body_bytes,
+    };
 
-    let app = Router::new()
-        .route("/rpc", post(handle_rpc))
-        .route("/sse", get(handle_sse).post(handle_sse_message))
+    let mut endpoints = Router::new();
+    if settings.transport.contains(&Transport::Http) {
+        endpoints = endpoints
+            .route("/rpc", post(handle_rpc))
+            .route("/sse", get(handle_sse).post(handle_sse_message))
+            .route(
+                "/mcp",
+                post(handle_mcp_post)
+                    .get(handle_mcp_get)
+                    .delete(handle_mcp_delete),
+            );
+    }
+    if settings.transport.contains(&Transport::Ws) {
+        endpoints = endpoints.route("/ws", get(handle_ws));
+    }
+    // Bodies are refused by their length before they are read, and the
+    // limit replaces axum's own; the admin import sets a larger one.
+    let guard = Arc::new(RebindingGuard::new(
+        settings.cors.as_ref(),
+        settings.allowed_hosts.clone(),
+    ));
+    let mut endpoints = endpoints
+        .layer(RequestBodyLimitLayer::new(settings.max_body_bytes))
+        .layer(DefaultBodyLimit::disable());
+    if settings.rate_limits.is_enabled() {
+        let limiter = Arc::new(RateLimiter::new(settings.rate_limits));
+        tracing::info!(limits = ?settings.rate_limits, "rate limits enabled");
+        endpoints = endpoints.layer(middleware::from_fn_with_state(
+            (limiter, state.config.clone(), state.service.clone()),
+            limit_rate,
+        ));
+    }
+    // Outside the rate limit, so refused addresses use up no tokens and
+    // the per-IP limit counts clients rather than proxies.
+    if settings.ip_filter.is_enabled() {
+        let filter = &settings.ip_filter;
+        tracing::info!(
+            allowed = filter.allowed.len(),
+            denied = filter.denied.len(),
+            trusted_proxies = filter.trusted_proxies.len(),
+            "client address filter enabled"
+        );
+        endpoints = endpoints.layer(middleware::from_fn_with_state(
+            Arc::new(filter.clone()),
+            filter_ip,
+        ));
+    }
+    let endpoints = endpoints.layer(middleware::from_fn_with_state(guard, guard_rebinding));
+    let app = endpoints
         .route("/health", get(handle_health))
+        .route("/version", get(handle_version))
+        .route("/livez", get(handle_livez))
+        .route("/readyz", get(handle_readyz))
+        .route("/metrics", get(handle_metrics))
+        .route(oauth::METADATA_PATH, get(handle_oauth_metadata))
+        .route(
+            "/.well-known/oauth-protected-resource/{*resource}",
+            get(handle_oauth_metadata),
+        )
+        .route("/admin/usage", get(handle_admin_usage))
+        .route("/admin/slow-calls", get(handle_admin_slow_calls))
+        .route(
+            "/admin/maintenance",
+            get(handle_admin_maintenance).put(handle_admin_set_maintenance),
+        )
+        .route("/admin/runtime", get(handle_admin_runtime))
+        .route("/admin/sessions", get(handle_admin_sessions))
+        .route("/admin/tools", get(handle_admin_tools))
+        .route("/admin/tools/{name}", put(handle_admin_set_tool))
+        .route(
+            "/admin/keys",
+            get(handle_admin_keys).post(handle_admin_create_key),
+        )
+        .route("/admin/keys/{id}", delete(handle_admin_revoke_key))
+        .route("/admin/caches", get(handle_admin_caches))
+        .route("/admin/caches/flush", post(handle_admin_cache_flush))
+        .route("/admin/state/export", get(handle_admin_state_export))
+        .route(
+            "/admin/state/import",
+            post(handle_admin_state_import).layer(DefaultBodyLimit::max(MAX_STATE_ARCHIVE_BYTES)),
+        )
         .route("/", get(handle_health))
-        .with_state(service);
+        .with_state(state)
+        // gzip or brotli as the client accepts; event streams stay as they
+        // are, so events are not held back in a compression buffer.
+        .layer(CompressionLayer::new());
+    let app = match &settings.cors {
+        Some(cors) => {
+            tracing::info!(origins = ?cors.origins, "CORS enabled");
+            app.layer(cors_layer(cors))
+        }
+        None => app,
+    };
 
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
+    let listener = match upgrade::inherited_listener()? {
+        Some(listener) => {
+            tracing::info!("took over listening socket from previous process");
+            tokio::net::TcpListener::from_std(listener)?
+        }
+        None => tokio::net::TcpListener::bind((settings.bind_addr, port)).await?,
+    };
+    // Differs from the setting when it is 0 or the socket was inherited.
+    let port = listener.local_addr()?.port();
+    #[cfg(unix)]
+    spawn_upgrade_handler(
+        std::os::fd::AsRawFd::as_raw_fd(&listener),
+        readiness.clone(),
+    );
 
-    tracing::info!("Inferenco MCP server listening on http://0.0.0.0:{}", port);
-    tracing::info!("  - JSON-RPC endpoint: http://0.0.0.0:{}/rpc", port);
-    tracing::info!("  - SSE endpoint: http://0.0.0.0:{}/sse", port);
-    tracing::info!("  - Health endpoint: http://0.0.0.0:{}/health", port);
+    // Reported as the client would write it, brackets around IPv6.
+    let address = std::net::SocketAddr::new(settings.bind_addr, port);
+    tracing::info!("Inferenco MCP server listening on {http}://{address}");
+    if settings.transport.contains(&Transport::Http) {
+        tracing::info!("  - JSON-RPC endpoint: {http}://{address}/rpc");
+        tracing::info!("  - SSE endpoint: {http}://{address}/sse");
+        tracing::info!("  - Streamable HTTP endpoint: {http}://{address}/mcp");
+    }
+    if settings.transport.contains(&Transport::Ws) {
+        tracing::info!("  - WebSocket endpoint: {ws}://{address}/ws");
+    }
+    tracing::info!("  - Health endpoint: {http}://{address}/health");
+    tracing::info!("  - Liveness/readiness: {http}://{address}/livez, /readyz");
+    tracing::info!("  - Metrics endpoint: {http}://{address}/metrics");
     tracing::info!(
         "Inferenco MCP server is running with protocol version {}",
         rmcp::model::ProtocolVersion::LATEST
     );
-    tracing::info!("Available tools: echo, reverse_text, increment, current_time, roll_dice");
+    tracing::info!("Available tools: {tools}");
 
-    axum::serve(listener, app).await?;
+    upgrade::notify_parent_ready();
+    let ready_file = settings.ready_file.clone();
+    if let Some(path) = &ready_file {
+        if let Err(error) = std::fs::write(path, format!("{}\n", std::process::id())) {
+            tracing::warn!(path = %path.display(), %error, "cannot write ready file");
+        }
+    }
+    tracing::info!(port, "server ready");
+    let shutdown = shutdown_signal(readiness, drain_delay, ready_file);
+    #[cfg(feature = "tls")]
+    if let Some(tls) = tls {
+        inferenco_mcp::transport::tls::serve(listener, tls, app, shutdown).await;
+        save_usage(usage).await;
+        tracing::info!("HTTPS server stopped");
+        return Ok(());
+    }
+    // The peer address feeds the client address filter and the per-IP
+    // rate limit.
+    let app = app.into_make_service_with_connect_info::<std::net::SocketAddr>();
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown)
+        .await?;
+    save_usage(usage).await;
+    tracing::info!("HTTP server stopped");
     Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    dotenv().ok();
+/// Refuses transport requests from origins or for hosts `guard` does not
+/// allow, which is what a DNS-rebinding page's requests look like.
+async fn guard_rebinding(
+    State(guard): State<Arc<RebindingGuard>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let headers = request.headers();
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    let (origin, host) = (header(header::ORIGIN), header(header::HOST));
+    match guard.check(origin, host) {
+        Ok(()) => next.run(request).await,
+        Err(reason) => {
+            tracing::warn!(origin, host, reason, "refused request");
+            let error = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": -32000, "message": reason }
+            });
+            (StatusCode::FORBIDDEN, Json(error)).into_response()
+        }
+    }
+}
 
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+/// The address of the client behind a request, as `filter_ip` resolved it.
+#[derive(Debug, Clone, Copy)]
+struct ClientIp(std::net::IpAddr);
 
-    let transport = env::var("INFERENCO_MCP_TRANSPORT").unwrap_or_else(|_| "stdio".to_string());
-    let service = ToolService::new();
+/// Refuses transport requests from client addresses `filter` does not
+/// permit, after looking past trusted proxies, and leaves the client's
+/// address to later middleware as [`ClientIp`].
+async fn filter_ip(
+    State(filter): State<Arc<IpFilter>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    // A header sent on several lines counts as one comma-separated list.
+    let lines: Vec<&str> = request
+        .headers()
+        .get_all(filter.proxy_header.name())
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect();
+    let forwarded = (!lines.is_empty()).then(|| lines.join(","));
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<std::net::SocketAddr>>()
+        .map(|ConnectInfo(peer)| filter.client_ip(peer.ip(), forwarded.as_deref()));
+    match client {
+        Some(ip) if filter.permits(ip) => {
+            request.extensions_mut().insert(ClientIp(ip));
+            next.run(request).await
+        }
+        _ => {
+            tracing::warn!(client = ?client, "refused client address");
+            let error = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": -32000, "message": "Client address not allowed" }
+            });
+            (StatusCode::FORBIDDEN, Json(error)).into_response()
+        }
+    }
+}
+
+/// The buckets [`limit_rate`] counted a request in, left on the request so
+/// `/ws` can count each message against them too.
+#[derive(Clone)]
+struct RateLimitScope {
+    limiter: Arc<RateLimiter>,
+    service: Arc<ToolService>,
+    key: Option<String>,
+    ip: Option<std::net::IpAddr>,
+}
 
-    match transport.as_str() {
-        "http" => {
-            start_http_server(service).await?;
+impl RateLimitScope {
+    /// Count one more request, or return the JSON-RPC error refusing it
+    /// and the `Retry-After` in whole seconds.
+    fn check(&self, id: serde_json::Value) -> Result<(), (serde_json::Value, u64)> {
+        let Err(exceeded) = self.limiter.check(self.key.as_deref(), self.ip) else {
+            return Ok(());
+        };
+        let limit = exceeded.limit.as_str();
+        self.service.metrics().rate_limited(limit);
+        let retry_after = exceeded.retry_after.as_secs_f64().ceil().max(1.0) as u64;
+        tracing::debug!(
+            limit,
+            ip = ?self.ip,
+            key = self.key.as_deref(),
+            retry_after,
+            "rate limited"
+        );
+        let error = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": {
+                "code": RATE_LIMITED_ERROR_CODE.0,
+                "message": "Rate limit exceeded",
+                "data": { "limit": limit, "retry_after_secs": retry_after }
+            }
+        });
+        Err((error, retry_after))
+    }
+}
+
+/// Refuses transport requests beyond the rate limits with `429` and a
+/// `Retry-After` in whole seconds.
+async fn limit_rate(
+    State((limiter, config, service)): State<(Arc<RateLimiter>, Arc<LiveConfig>, Arc<ToolService>)>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    // `/sse` and `/ws` clients may send their key in the query string.
+    let query = Query::<HashMap<String, String>>::try_from_uri(request.uri()).ok();
+    let key = rpc::credential(&config.get(), request.headers())
+        .or_else(|| query.as_ref()?.get("token").map(String::as_str))
+        .map(auth::fingerprint)
+        .or_else(|| auth::certificate().map(|certificate| certificate.subject));
+    let ip = request
+        .extensions()
+        .get::<ClientIp>()
+        .map(|ClientIp(ip)| *ip)
+        .or_else(|| {
+            request
+                .extensions()
+                .get::<ConnectInfo<std::net::SocketAddr>>()
+                .map(|ConnectInfo(peer)| peer.ip())
+        });
+    let scope = RateLimitScope {
+        limiter,
+        service,
+        key,
+        ip,
+    };
+    match scope.check(serde_json::Value::Null) {
+        Ok(()) => {
+            request.extensions_mut().insert(scope);
+            next.run(request).await
         }
-        "stdio" => {
-            let server = service.serve(stdio()).await.inspect_err(|error| {
-                tracing::error!(%error, "failed to start MCP server");
-            })?;
+        Err((error, retry_after)) => (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after.to_string())],
+            Json(error),
+        )
+            .into_response(),
+    }
+}
 
+/// Answers preflights and adds CORS headers for the origins in `cors`.
+fn cors_layer(cors: &CorsSettings) -> CorsLayer {
+    let origins = match &cors.origins {
+        AllowedOrigins::Any => AllowOrigin::any(),
+        AllowedOrigins::List(origins) => AllowOrigin::list(
+            origins
+                .iter()
+                .map(|origin| HeaderValue::from_str(origin).expect("validated in settings")),
+        ),
+    };
+    let headers: Vec<HeaderName> = cors
+        .headers
+        .iter()
+        .map(|name| name.parse().expect("validated in settings"))
+        .collect();
+    let methods: Vec<Method> = cors
+        .methods
+        .iter()
+        .map(|method| method.parse().expect("validated in settings"))
+        .collect();
+    CorsLayer::new()
+        .allow_origin(origins)
+        .allow_headers(headers)
+        .allow_methods(methods)
+        .expose_headers(cors::EXPOSED_HEADERS.map(HeaderName::from_static))
+        .max_age(cors::PREFLIGHT_MAX_AGE)
+}
+
+/// On SIGUSR2, start a successor process that inherits `listener`. The
+/// successor signals us with SIGTERM once it is serving, which runs the normal
+/// drain sequence; if it never does, we kill it and keep serving.
+#[cfg(unix)]
+fn spawn_upgrade_handler(listener: std::os::fd::RawFd, readiness: Arc<Readiness>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    tokio::spawn(async move {
+        let mut upgrades = match signal(SignalKind::user_defined2()) {
+            Ok(upgrades) => upgrades,
+            Err(error) => {
+                tracing::warn!(%error, "zero-downtime restarts unavailable");
+                return;
+            }
+        };
+        while upgrades.recv().await.is_some() {
+            if readiness.is_draining() {
+                continue;
+            }
+            let mut successor = match upgrade::spawn_successor(listener) {
+                Ok(successor) => successor,
+                Err(error) => {
+                    tracing::error!(%error, "failed to start successor process");
+                    continue;
+                }
+            };
             tracing::info!(
-                "Inferenco MCP server is running with protocol version {}",
-                rmcp::model::ProtocolVersion::LATEST
-            );
-            tracing::info!(
-                "Available tools: echo, reverse_text, increment, current_time, roll_dice"
+                pid = successor.id(),
+                "started successor, waiting for handover"
             );
+            tokio::time::sleep(UPGRADE_TIMEOUT).await;
+            if !readiness.is_draining() {
+                tracing::error!(
+                    pid = successor.id(),
+                    timeout_secs = UPGRADE_TIMEOUT.as_secs(),
+                    "successor did not take over; killing it and continuing to serve"
+                );
+                // Reaping the child blocks until it has exited.
+                let _ = tokio::task::spawn_blocking(move || {
+                    let _ = successor.kill();
+                    successor.wait()
+                })
+                .await;
+            }
+        }
+    });
+}
+
+async fn save_usage(usage: Arc<UsageTracker>) {
+    match tokio::task::spawn_blocking(move || usage.save()).await {
+        Ok(Ok(())) => {}
+        Ok(Err(error)) => tracing::warn!(%error, "failed to persist usage data"),
+        Err(error) => tracing::warn!(%error, "usage persistence task failed"),
+    }
+}
 
-            // This will never return for stdio transport
-            server.waiting().await?;
+/// Resolve on SIGINT/SIGTERM after flipping readiness to draining and
+/// waiting `drain_delay` so load balancers stop routing new traffic before
+/// the listener closes. In-flight requests are then allowed to finish.
+async fn shutdown_signal(
+    readiness: Arc<Readiness>,
+    drain_delay: Duration,
+    ready_file: Option<PathBuf>,
+) {
+    shutdown_requested().await;
+    readiness.start_draining();
+    if let Some(path) = ready_file {
+        let _ = std::fs::remove_file(path);
+    }
+    tracing::info!(
+        drain_seconds = drain_delay.as_secs(),
+        "shutdown requested, draining"
+    );
+    tokio::time::sleep(drain_delay).await;
+}
+
+/// Resolve on SIGINT or SIGTERM, or when the Windows service is stopped.
+async fn shutdown_requested() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = daemon::stop_requested();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Services start in the system directory; read `.env` next to the binary.
+    #[cfg(windows)]
+    if winservice::is_service_invocation() {
+        if let Some(dir) = std::env::current_exe()?.parent() {
+            std::env::set_current_dir(dir)?;
         }
-        _ => {
-            // Default to stdio for unknown transport values
-            tracing::warn!("Unknown transport '{}', defaulting to stdio", transport);
-            let server = service.serve(stdio()).await.inspect_err(|error| {
-                tracing::error!(%error, "failed to start MCP server");
-            })?;
+    }
+    // `--config` decides which env file to load, and the env file can set
+    // defaults for other flags, so look for it before the real parse.
+    let config_path = Cli::try_parse().ok().and_then(|cli| cli.config);
+    let mut env_file = match EnvFile::load(config_path.as_deref()) {
+        Ok(env_file) => env_file,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(2);
+        }
+    };
+    let cli = Cli::parse();
+    let profile = cli.profile;
+    if let Some(profile) = profile {
+        env_file.apply_profile(profile);
+    }
+    let serve_args = cli.serve.clone();
+    let command = cli.into_command();
 
-            tracing::info!(
-                "Inferenco MCP server is running with protocol version {}",
-                rmcp::model::ProtocolVersion::LATEST
+    // Forking is only sound before the runtime starts its worker threads.
+    #[cfg(unix)]
+    let _pid_file = match &command {
+        Command::Serve(args) if args.daemon && !args.check => detach(args)?,
+        _ => None,
+    };
+    #[cfg(windows)]
+    if let Command::Service(command) = command {
+        return run_service_command(command, profile, env_file);
+    }
+
+    upgrade::take_handover();
+    tokio::runtime::Runtime::new()?.block_on(run(command, serve_args, env_file, profile))
+}
+
+/// The enabled tools, comma separated, for the startup log.
+fn tool_names(service: &ToolService) -> String {
+    service
+        .available_tools()
+        .iter()
+        .map(|tool| tool.name.as_ref())
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
+
+/// Serve MCP over stdin and stdout until the client closes stdin.
+async fn serve_stdio(service: ToolService) -> Result<(), Box<dyn std::error::Error>> {
+    let tools = tool_names(&service);
+    let server = service.serve(stdio()).await.inspect_err(|error| {
+        tracing::error!(%error, "failed to start MCP server");
+    })?;
+
+    tracing::info!(
+        "Inferenco MCP server is running with protocol version {}",
+        rmcp::model::ProtocolVersion::LATEST
+    );
+    tracing::info!("Available tools: {tools}");
+
+    server.waiting().await?;
+    Ok(())
+}
+
+/// Validate the configuration while errors can still reach the terminal,
+/// then detach.
+#[cfg(unix)]
+fn detach(args: &ServeArgs) -> Result<Option<daemon::PidFile>, Box<dyn std::error::Error>> {
+    if !args.listeners().uses_port() && !args.transport.contains(&Transport::Tcp) {
+        return Err("--daemon requires --transport http, ws, or tcp".into());
+    }
+    if args.transport.contains(&Transport::Stdio) {
+        return Err("--daemon cannot serve the stdio transport".into());
+    }
+    if let Err(errors) = Settings::load(&args.listeners()) {
+        exit_invalid_config(&errors);
+    }
+    daemon::daemonize(args.pid_file.as_deref(), args.log_output.as_deref())
+        .map_err(|error| format!("cannot daemonize: {error}").into())
+}
+
+#[cfg(windows)]
+fn run_service_command(
+    command: ServiceCommand,
+    profile: Option<Profile>,
+    env_file: EnvFile,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use clap::ValueEnum;
+    use std::ffi::OsString;
+
+    match command {
+        ServiceCommand::Install { port } => {
+            let mut arguments: Vec<OsString> = vec![
+                "--transport".into(),
+                "http".into(),
+                "--port".into(),
+                port.to_string().into(),
+            ];
+            if let Some(value) = profile.and_then(|profile| profile.to_possible_value()) {
+                arguments.extend(["--profile".into(), value.get_name().into()]);
+            }
+            winservice::install(arguments)?;
+            println!(
+                "installed {0}; start it with `sc start {0}`",
+                winservice::SERVICE_NAME
             );
-            tracing::info!(
-                "Available tools: echo, reverse_text, increment, current_time, roll_dice"
+        }
+        ServiceCommand::Uninstall => winservice::uninstall()?,
+        ServiceCommand::Run(args) => winservice::run(Box::new(move || {
+            tokio::runtime::Runtime::new()
+                .map_err(|error| error.to_string())?
+                .block_on(serve(args, env_file))
+                .map_err(|error| error.to_string())
+        }))?,
+    }
+    Ok(())
+}
+
+/// The configuration `serve` would run with, exiting as it does when it is
+/// invalid.
+fn settings(args: &ServeArgs) -> Settings {
+    match Settings::load(&args.listeners()) {
+        Ok(settings) => settings,
+        Err(errors) => exit_invalid_config(&errors),
+    }
+}
+
+/// The service `serve` would build, for the commands that call tools
+/// in-process. Listing and inspecting tools leave the state store closed, so
+/// they work next to a running server holding it.
+fn local_service(args: &ServeArgs) -> Result<ToolService, Box<dyn std::error::Error>> {
+    let settings = settings(args);
+    http_client::init(&settings.http)?;
+    let state = settings.state.open()?;
+    Ok(ToolService::from_settings(&settings).with_state_store(state))
+}
+
+fn exit_invalid_config(errors: &[String]) -> ! {
+    eprintln!("invalid configuration:");
+    for error in errors {
+        eprintln!("  - {error}");
+    }
+    std::process::exit(2);
+}
+
+async fn run(
+    command: Command,
+    serve_args: ServeArgs,
+    env_file: EnvFile,
+    profile: Option<Profile>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        Command::Serve(args) => serve(args, env_file).await,
+        #[cfg(windows)]
+        Command::Service(_) => unreachable!("handled before the runtime starts"),
+        Command::Tools(ToolsCommand::List { json }) => {
+            println!(
+                "{}",
+                cli::list_tools(&ToolService::from_settings(&settings(&serve_args)), json)
             );
+            Ok(())
+        }
+        Command::ListTools => {
+            println!(
+                "{}",
+                cli::list_tools(&ToolService::from_settings(&settings(&serve_args)), false)
+            );
+            Ok(())
+        }
+        Command::Inspect { tool, json } => {
+            let service = ToolService::from_settings(&settings(&serve_args));
+            match cli::inspect(&service, tool.as_deref(), json) {
+                Ok(output) => println!("{output}"),
+                Err(error) => {
+                    eprintln!("{error}");
+                    std::process::exit(1);
+                }
+            }
+            Ok(())
+        }
+        Command::Once => {
+            if !run_once(&local_service(&serve_args)?).await? {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Command::Repl => {
+            if let Err(error) = repl::run(local_service(&serve_args)?).await {
+                eprintln!("{error}");
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Command::Config { json } => {
+            let errors = Settings::load(&serve_args.listeners())
+                .err()
+                .unwrap_or_default();
+            println!(
+                "{}",
+                cli::effective_config(profile, &config::effective(&env_file), &errors, json)
+            );
+            if !errors.is_empty() {
+                std::process::exit(2);
+            }
+            Ok(())
+        }
+        Command::Replay { file } => match replay(&file).await {
+            Ok(true) => Ok(()),
+            Ok(false) => std::process::exit(1),
+            Err(error) => {
+                eprintln!("{error}");
+                std::process::exit(2);
+            }
+        },
+        Command::State(command) => {
+            match cli::state(&command) {
+                Ok(output) => println!("{output}"),
+                Err(error) => {
+                    eprintln!("{error}");
+                    std::process::exit(1);
+                }
+            }
+            Ok(())
+        }
+        Command::Keys(command) => {
+            match cli::keys(command) {
+                Ok(output) => println!("{output}"),
+                Err(error) => {
+                    eprintln!("{error}");
+                    std::process::exit(1);
+                }
+            }
+            Ok(())
+        }
+        Command::Audit(command) => {
+            match cli::audit(command) {
+                Ok(output) => println!("{output}"),
+                Err(error) => {
+                    eprintln!("{error}");
+                    std::process::exit(1);
+                }
+            }
+            Ok(())
+        }
+        Command::ValidateConfig => {
+            let errors = Settings::load(&serve_args.listeners())
+                .err()
+                .unwrap_or_default();
+            println!("{}", cli::validate_config(env_file.path(), &errors));
+            if !errors.is_empty() {
+                std::process::exit(2);
+            }
+            Ok(())
+        }
+        Command::ConfigSchema => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&config_schema::schema())
+                    .expect("JSON values serialize")
+            );
+            Ok(())
+        }
+        Command::Call(args) => {
+            let local = || local_service(&serve_args).map_err(|error| error.to_string());
+            match cli::call(&args, local).await {
+                Ok(output) => println!("{output}"),
+                Err(error) => {
+                    eprintln!("{error}");
+                    std::process::exit(1);
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+async fn serve(args: ServeArgs, env_file: EnvFile) -> Result<(), Box<dyn std::error::Error>> {
+    // Runs before logging is set up so that invalid logging configuration is
+    // reported by the check instead of aborting it.
+    if args.check {
+        let report = self_check::run(&args.listeners()).await;
+        println!("{report}");
+        std::process::exit(if report.ok { 0 } else { 1 });
+    }
+
+    let settings = settings(&args);
+
+    http_client::init(&settings.http)?;
+    let crash_reporter = settings
+        .crash
+        .clone()
+        .map(|crash| CrashReporter::spawn(crash.webhook, crash.environment));
+    let (_log_guard, log_level) = logging::init(&settings.log, crash_reporter.as_ref())?;
+    if let Some(reporter) = &crash_reporter {
+        reporter.install_panic_hook();
+    }
+
+    let state = settings.state.open().inspect_err(|error| {
+        tracing::error!(%error, "failed to open the state store");
+    })?;
+    let mut service = ToolService::from_settings(&settings).with_state_store(state);
+    if let Some(path) = &settings.keys_file {
+        let keys = Arc::new(KeyStore::open(path).inspect_err(|error| {
+            tracing::error!(%error, "failed to open the key store");
+        })?);
+        keys.spawn_refresh();
+        service = service.with_key_store(keys);
+    }
+    if let Some(audit) = &settings.audit {
+        let sink = audit.open().inspect_err(|error| {
+            tracing::error!(%error, "failed to open the audit log");
+        })?;
+        tracing::info!(path = %audit.path().display(), "auditing tool calls");
+        service = service.with_audit_sink(sink);
+    }
+    scheduler::spawn(&service).inspect_err(|error| {
+        tracing::error!(%error, "failed to start the scheduler");
+    })?;
+    service.resources().spawn_refresh();
+    if let Some(dir) = &settings.cache_dir {
+        match service.caches().warm(dir) {
+            Ok(warmed) => tracing::info!(dir = %dir.display(), ?warmed, "caches warmed"),
+            Err(error) => tracing::warn!(%error, "caches start cold"),
+        }
+    }
+    let caches = Arc::clone(service.caches());
+    let config = Arc::new(
+        LiveConfig::new(env_file, settings.access.clone())
+            .with_service(service.clone())
+            .with_log_level(log_level),
+    );
+    config.spawn_watcher();
 
-            // This will never return for stdio transport
-            server.waiting().await?;
+    let stdio = settings.transport.contains(&Transport::Stdio);
+    let http = settings
+        .transport
+        .iter()
+        .any(|transport| transport.uses_port());
+    // Serves until the process exits; stdio and HTTP decide when that is.
+    let tcp = settings.transport.contains(&Transport::Tcp);
+    if tcp {
+        let listener = tokio::net::TcpListener::bind(settings.tcp_addr)
+            .await
+            .inspect_err(|error| {
+                tracing::error!(addr = %settings.tcp_addr, %error, "cannot bind the tcp transport");
+            })?;
+        let addr = listener.local_addr()?;
+        tracing::info!(%addr, port = addr.port(), "tcp ready");
+        tokio::spawn(tcp::serve(listener, service.clone()));
+    }
+    if !stdio && !http {
+        shutdown_requested().await;
+        tracing::info!("shutdown requested");
+    } else if !stdio {
+        start_http_server(service, &settings, config).await?;
+    } else if !http {
+        let served = serve_stdio(service).await;
+        if !tcp {
+            served?;
+        } else {
+            if let Err(error) = served {
+                tracing::warn!(%error, "stdio transport stopped");
+            }
+            tracing::info!("stdio client disconnected; still serving TCP");
+            shutdown_requested().await;
+        }
+    } else {
+        // Both transports share the service, and with it tools, state, and
+        // sessions. A shutdown signal stops the HTTP server and the process;
+        // a stdio client going away leaves the HTTP clients served.
+        let stdio = serve_stdio(service.clone());
+        let http = start_http_server(service, &settings, config);
+        tokio::pin!(stdio, http);
+        tokio::select! {
+            served = &mut http => served?,
+            served = &mut stdio => {
+                // Including a client that left before the handshake.
+                if let Err(error) = served {
+                    tracing::warn!(%error, "stdio transport stopped");
+                }
+                tracing::info!("stdio client disconnected; still serving HTTP");
+                http.await?;
+            }
         }
     }
 
+    if let Some(dir) = &settings.cache_dir {
+        match caches.spill(dir) {
+            Ok(spilled) => tracing::info!(dir = %dir.display(), ?spilled, "caches saved"),
+            Err(error) => tracing::warn!(%error, "failed to save caches"),
+        }
+    }
     Ok(())
 }
@@ -0,0 +1,115 @@
+use chrono::Utc;
+use rmcp::{model::ErrorCode, ErrorData as McpError};
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+
+/// JSON-RPC error code returned by `tools/call` while in maintenance.
+pub const MAINTENANCE_ERROR_CODE: ErrorCode = ErrorCode(-32003);
+
+const DEFAULT_MESSAGE: &str = "server in maintenance";
+
+/// Requested maintenance settings, as accepted by the admin API.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceState {
+    pub enabled: bool,
+    /// Keep serving tools annotated as read-only.
+    #[serde(default)]
+    pub allow_read_only: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// When maintenance was last switched on (RFC 3339).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+}
+
+/// Runtime maintenance switch.
+///
+/// The state lives outside the configuration on purpose, so that reloading
+/// configuration never silently takes a server out of maintenance.
+#[derive(Debug, Default)]
+pub struct MaintenanceMode {
+    state: RwLock<MaintenanceState>,
+}
+
+impl MaintenanceMode {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn state(&self) -> MaintenanceState {
+        self.state
+            .read()
+            .expect("maintenance lock poisoned")
+            .clone()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.state
+            .read()
+            .expect("maintenance lock poisoned")
+            .enabled
+    }
+
+    /// Apply a new state, returning the previous one.
+    pub fn set(&self, mut state: MaintenanceState) -> MaintenanceState {
+        let mut current = self.state.write().expect("maintenance lock poisoned");
+        state.since = match (state.enabled, current.enabled) {
+            (false, _) => None,
+            (true, true) => current.since.clone(),
+            (true, false) => Some(Utc::now().to_rfc3339()),
+        };
+        tracing::warn!(
+            enabled = state.enabled,
+            allow_read_only = state.allow_read_only,
+            "maintenance mode updated"
+        );
+        std::mem::replace(&mut *current, state)
+    }
+
+    /// Reject a call to `tool` if maintenance forbids it.
+    pub fn check(&self, tool: &str, read_only: bool) -> Result<(), McpError> {
+        let state = self.state.read().expect("maintenance lock poisoned");
+        if !state.enabled || (state.allow_read_only && read_only) {
+            return Ok(());
+        }
+        Err(McpError::new(
+            MAINTENANCE_ERROR_CODE,
+            state
+                .message
+                .as_deref()
+                .unwrap_or(DEFAULT_MESSAGE)
+                .to_string(),
+            Some(serde_json::json!({
+                "maintenance": true,
+                "tool": tool,
+                "since": state.since,
+                "read_only_allowed": state.allow_read_only,
+            })),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_calls_except_allowed_read_only_tools() {
+        let mode = MaintenanceMode::new();
+        assert!(mode.check("increment", false).is_ok());
+
+        mode.set(MaintenanceState {
+            enabled: true,
+            allow_read_only: true,
+            ..Default::default()
+        });
+        let error = mode.check("increment", false).expect_err("writes blocked");
+        assert_eq!(error.code, MAINTENANCE_ERROR_CODE);
+        assert_eq!(error.message, DEFAULT_MESSAGE);
+        assert!(mode.check("echo", true).is_ok());
+        assert!(mode.state().since.is_some());
+
+        mode.set(MaintenanceState::default());
+        assert!(mode.check("increment", false).is_ok());
+    }
+}
@@ -0,0 +1,402 @@
+use crate::{
+    auth::FORBIDDEN_ERROR_CODE, limiter::BUSY_ERROR_CODE, maintenance::MAINTENANCE_ERROR_CODE,
+};
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use rmcp::{model::ErrorCode, ErrorData as McpError};
+use std::time::Duration;
+
+/// Latency buckets (seconds) tuned for tools that range from in-memory
+/// lookups to outbound fetches.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// Session age buckets (seconds), from quick probes to day-long connections.
+const SESSION_AGE_BUCKETS: &[f64] = &[
+    1.0,
+    10.0,
+    60.0,
+    300.0,
+    900.0,
+    3600.0,
+    4.0 * 3600.0,
+    12.0 * 3600.0,
+    24.0 * 3600.0,
+];
+
+/// Why a streaming session ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEnd {
+    /// The client went away.
+    Disconnected,
+    /// The server closed the session (e.g. it outlived its maximum age).
+    Evicted,
+}
+
+/// Prometheus registry for the server, rendered by the `/metrics` endpoint.
+pub struct Metrics {
+    registry: Registry,
+    tool_calls: IntCounterVec,
+    tool_errors: IntCounterVec,
+    tool_duration: HistogramVec,
+    tool_in_flight: IntGaugeVec,
+    calls_queued: IntGauge,
+    queue_wait: Histogram,
+    calls_rejected: IntCounter,
+    rate_limited: IntCounterVec,
+    sessions_open: IntGauge,
+    session_connects: IntCounter,
+    session_disconnects: IntCounter,
+    session_evictions: IntCounter,
+    session_age: Histogram,
+    cache_lookups: IntCounterVec,
+    cache_evictions: IntCounterVec,
+    cache_entries: IntGaugeVec,
+    cache_bytes: IntGaugeVec,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new_custom(Some("inferenco_mcp".to_string()), None)
+            .expect("metric prefix is valid");
+
+        let tool_calls = IntCounterVec::new(
+            Opts::new("tool_calls_total", "Completed tool calls by outcome."),
+            &["tool", "outcome"],
+        )
+        .expect("valid metric definition");
+        let tool_errors = IntCounterVec::new(
+            Opts::new("tool_errors_total", "Failed tool calls by error class."),
+            &["tool", "error_class"],
+        )
+        .expect("valid metric definition");
+        let tool_duration = HistogramVec::new(
+            HistogramOpts::new("tool_duration_seconds", "Tool call latency in seconds.")
+                .buckets(LATENCY_BUCKETS.to_vec()),
+            &["tool"],
+        )
+        .expect("valid metric definition");
+        let tool_in_flight = IntGaugeVec::new(
+            Opts::new("tool_in_flight", "Tool calls currently executing."),
+            &["tool"],
+        )
+        .expect("valid metric definition");
+
+        let calls_queued = IntGauge::new(
+            "tool_calls_queued",
+            "Tool calls waiting for a concurrency slot.",
+        )
+        .expect("valid metric definition");
+        let queue_wait = Histogram::with_opts(
+            HistogramOpts::new(
+                "tool_queue_wait_seconds",
+                "Time tool calls waited for a concurrency slot.",
+            )
+            .buckets(LATENCY_BUCKETS.to_vec()),
+        )
+        .expect("valid metric definition");
+        let calls_rejected = IntCounter::new(
+            "tool_calls_rejected_total",
+            "Tool calls turned away because the queue or their session was full.",
+        )
+        .expect("valid metric definition");
+        let rate_limited = IntCounterVec::new(
+            Opts::new(
+                "requests_rate_limited_total",
+                "Transport requests refused by a rate limit.",
+            ),
+            &["limit"],
+        )
+        .expect("valid metric definition");
+
+        let sessions_open = IntGauge::new("sse_sessions_open", "Open SSE sessions.")
+            .expect("valid metric definition");
+        let session_connects =
+            IntCounter::new("sse_session_connects_total", "SSE sessions opened.")
+                .expect("valid metric definition");
+        let session_disconnects = IntCounter::new(
+            "sse_session_disconnects_total",
+            "SSE sessions closed by the client.",
+        )
+        .expect("valid metric definition");
+        let session_evictions = IntCounter::new(
+            "sse_session_evictions_total",
+            "SSE sessions closed by the server.",
+        )
+        .expect("valid metric definition");
+        let session_age = Histogram::with_opts(
+            HistogramOpts::new(
+                "sse_session_age_seconds",
+                "Age of SSE sessions when they closed.",
+            )
+            .buckets(SESSION_AGE_BUCKETS.to_vec()),
+        )
+        .expect("valid metric definition");
+
+        let cache_lookups = IntCounterVec::new(
+            Opts::new("cache_lookups_total", "Cache lookups by result."),
+            &["cache", "result"],
+        )
+        .expect("valid metric definition");
+        let cache_evictions = IntCounterVec::new(
+            Opts::new(
+                "cache_evictions_total",
+                "Entries evicted to keep caches within their limits.",
+            ),
+            &["cache"],
+        )
+        .expect("valid metric definition");
+        let cache_entries = IntGaugeVec::new(
+            Opts::new("cache_entries", "Entries held by each cache."),
+            &["cache"],
+        )
+        .expect("valid metric definition");
+        let cache_bytes = IntGaugeVec::new(
+            Opts::new(
+                "cache_bytes",
+                "Bytes of keys and values held by each cache.",
+            ),
+            &["cache"],
+        )
+        .expect("valid metric definition");
+
+        for collector in [
+            Box::new(tool_calls.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(tool_errors.clone()),
+            Box::new(tool_duration.clone()),
+            Box::new(tool_in_flight.clone()),
+            Box::new(calls_queued.clone()),
+            Box::new(queue_wait.clone()),
+            Box::new(calls_rejected.clone()),
+            Box::new(rate_limited.clone()),
+            Box::new(sessions_open.clone()),
+            Box::new(session_connects.clone()),
+            Box::new(session_disconnects.clone()),
+            Box::new(session_evictions.clone()),
+            Box::new(session_age.clone()),
+            Box::new(cache_lookups.clone()),
+            Box::new(cache_evictions.clone()),
+            Box::new(cache_entries.clone()),
+            Box::new(cache_bytes.clone()),
+        ] {
+            registry
+                .register(collector)
+                .expect("metric registered once");
+        }
+
+        Self {
+            registry,
+            tool_calls,
+            tool_errors,
+            tool_duration,
+            tool_in_flight,
+            calls_queued,
+            queue_wait,
+            calls_rejected,
+            rate_limited,
+            sessions_open,
+            session_connects,
+            session_disconnects,
+            session_evictions,
+            session_age,
+            cache_lookups,
+            cache_evictions,
+            cache_entries,
+            cache_bytes,
+        }
+    }
+
+    /// Metrics for the cache called `name`.
+    pub fn cache(&self, name: &str) -> CacheMetrics {
+        CacheMetrics {
+            hits: self.cache_lookups.with_label_values(&[name, "hit"]),
+            misses: self.cache_lookups.with_label_values(&[name, "miss"]),
+            evictions: self.cache_evictions.with_label_values(&[name]),
+            entries: self.cache_entries.with_label_values(&[name]),
+            bytes: self.cache_bytes.with_label_values(&[name]),
+        }
+    }
+
+    pub fn session_opened(&self) {
+        self.session_connects.inc();
+        self.sessions_open.inc();
+    }
+
+    pub fn session_closed(&self, age: Duration, end: SessionEnd) {
+        self.sessions_open.dec();
+        self.session_age.observe(age.as_secs_f64());
+        match end {
+            SessionEnd::Disconnected => self.session_disconnects.inc(),
+            SessionEnd::Evicted => self.session_evictions.inc(),
+        }
+    }
+
+    /// Mark a tool call as in flight until the returned guard is dropped.
+    pub fn tool_started(&self, tool: &str) -> InFlightGuard {
+        let gauge = self.tool_in_flight.with_label_values(&[tool]);
+        gauge.inc();
+        InFlightGuard { gauge }
+    }
+
+    /// Count a call as queued until the returned guard is dropped.
+    pub fn call_queued(&self) -> InFlightGuard {
+        self.calls_queued.inc();
+        InFlightGuard {
+            gauge: self.calls_queued.clone(),
+        }
+    }
+
+    /// Record how long an admitted call waited for a slot.
+    pub fn record_queue_wait(&self, waited: Duration) {
+        self.queue_wait.observe(waited.as_secs_f64());
+    }
+
+    pub fn call_rejected(&self) {
+        self.calls_rejected.inc();
+    }
+
+    /// Count a request refused by the rate limit named `limit`.
+    pub fn rate_limited(&self, limit: &str) {
+        self.rate_limited.with_label_values(&[limit]).inc();
+    }
+
+    /// Record a finished tool call. `error_class` is set for failed calls.
+    pub fn record_tool_call(
+        &self,
+        tool: &str,
+        duration: Duration,
+        outcome: &str,
+        error_class: Option<&str>,
+    ) {
+        self.tool_duration
+            .with_label_values(&[tool])
+            .observe(duration.as_secs_f64());
+        self.tool_calls.with_label_values(&[tool, outcome]).inc();
+        if let Some(class) = error_class {
+            self.tool_errors.with_label_values(&[tool, class]).inc();
+        }
+    }
+
+    /// Render every registered metric in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("text encoding cannot fail");
+        String::from_utf8(buffer).expect("prometheus text output is UTF-8")
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decrements an in-flight or queued gauge when dropped, including when the
+/// call is cancelled mid-flight.
+pub struct InFlightGuard {
+    gauge: prometheus::IntGauge,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.gauge.dec();
+    }
+}
+
+/// One cache's series, labelled once when the cache is created.
+pub struct CacheMetrics {
+    hits: IntCounter,
+    misses: IntCounter,
+    evictions: IntCounter,
+    entries: IntGauge,
+    bytes: IntGauge,
+}
+
+impl CacheMetrics {
+    pub fn lookup(&self, hit: bool) {
+        if hit {
+            self.hits.inc();
+        } else {
+            self.misses.inc();
+        }
+    }
+
+    pub fn evicted(&self, entries: u64) {
+        self.evictions.inc_by(entries);
+    }
+
+    pub fn resized(&self, entries: usize, bytes: usize) {
+        self.entries.set(entries as i64);
+        self.bytes.set(bytes as i64);
+    }
+}
+
+/// Low-cardinality label for an MCP error.
+pub fn error_class(error: &McpError) -> &'static str {
+    match error.code {
+        ErrorCode::PARSE_ERROR => "parse_error",
+        ErrorCode::INVALID_REQUEST => "invalid_request",
+        ErrorCode::METHOD_NOT_FOUND => "method_not_found",
+        ErrorCode::INVALID_PARAMS => "invalid_params",
+        ErrorCode::INTERNAL_ERROR => "internal_error",
+        ErrorCode::RESOURCE_NOT_FOUND => "resource_not_found",
+        MAINTENANCE_ERROR_CODE => "maintenance",
+        BUSY_ERROR_CODE => "busy",
+        FORBIDDEN_ERROR_CODE => "forbidden",
+        _ => "other",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_calls_errors_and_in_flight() {
+        let metrics = Metrics::new();
+        {
+            let _guard = metrics.tool_started("echo");
+            assert!(metrics
+                .render()
+                .contains("inferenco_mcp_tool_in_flight{tool=\"echo\"} 1"));
+            metrics.record_tool_call("echo", Duration::from_millis(3), "success", None);
+        }
+        metrics.record_tool_call(
+            "echo",
+            Duration::from_millis(1),
+            "error",
+            Some("invalid_params"),
+        );
+
+        let output = metrics.render();
+        assert!(output.contains("inferenco_mcp_tool_in_flight{tool=\"echo\"} 0"));
+        assert!(
+            output.contains("inferenco_mcp_tool_calls_total{outcome=\"success\",tool=\"echo\"} 1")
+        );
+        assert!(output.contains(
+            "inferenco_mcp_tool_errors_total{error_class=\"invalid_params\",tool=\"echo\"} 1"
+        ));
+        assert!(output.contains("inferenco_mcp_tool_duration_seconds_count{tool=\"echo\"} 2"));
+    }
+
+    #[test]
+    fn tracks_session_lifecycle() {
+        let metrics = Metrics::new();
+        metrics.session_opened();
+        metrics.session_opened();
+        metrics.session_closed(Duration::from_secs(5), SessionEnd::Disconnected);
+        metrics.session_closed(Duration::from_secs(7200), SessionEnd::Evicted);
+
+        let output = metrics.render();
+        assert!(output.contains("inferenco_mcp_sse_sessions_open 0"));
+        assert!(output.contains("inferenco_mcp_sse_session_connects_total 2"));
+        assert!(output.contains("inferenco_mcp_sse_session_disconnects_total 1"));
+        assert!(output.contains("inferenco_mcp_sse_session_evictions_total 1"));
+        assert!(output.contains("inferenco_mcp_sse_session_age_seconds_count 2"));
+    }
+}
@@ -0,0 +1,122 @@
+//! Incremental output from long-running tools.
+//!
+//! Tools call [`emit`] as they make progress. When the transport can push
+//! messages in the middle of a call and the client asked for progress (a
+//! `progressToken` in the request's `_meta`), each chunk is sent as a
+//! `notifications/progress` message as soon as it is emitted. Everywhere
+//! else the chunks are collected and put in front of the tool's result, so
+//! clients on plain request/response transports see the same text, just
+//! all at once. [`report`] is for status lines that only mean something
+//! while the call is running, and drops them when they cannot be streamed.
+
+use std::{cell::RefCell, future::Future};
+use tokio::sync::mpsc::{self, error::SendError, UnboundedSender};
+
+tokio::task_local! {
+    static SINK: UnboundedSender<String>;
+    static OUTPUT: RefCell<Vec<String>>;
+}
+
+/// Emit `chunk` of the current tool call's output. Does nothing outside a
+/// tool call.
+pub fn emit(chunk: impl Into<String>) {
+    let chunk = chunk.into();
+    let chunk = match SINK.try_with(Clone::clone) {
+        Ok(sink) => match sink.send(chunk) {
+            Ok(()) => return,
+            Err(SendError(chunk)) => chunk,
+        },
+        Err(_) => chunk,
+    };
+    let _ = OUTPUT.try_with(|output| output.borrow_mut().push(chunk));
+}
+
+/// Report what the current tool call is doing, to callers that are
+/// streaming its progress. Unlike [`emit`], the message never becomes part
+/// of the result.
+pub fn report(message: impl Into<String>) {
+    let _ = SINK.try_with(|sink| sink.send(message.into()));
+}
+
+/// Run `future` while collecting the chunks it emits that were not
+/// streamed.
+pub async fn collect_output<F: Future>(future: F) -> (F::Output, Vec<String>) {
+    OUTPUT
+        .scope(RefCell::new(Vec::new()), async move {
+            let output = future.await;
+            let chunks = OUTPUT.with(|chunks| chunks.take());
+            (output, chunks)
+        })
+        .await
+}
+
+/// Run `future`, handing each chunk it emits to `send` in order. Returns
+/// once the future is done and every chunk has been sent.
+pub async fn stream_to<F, S, Sent>(future: F, mut send: S) -> F::Output
+where
+    F: Future,
+    S: FnMut(String) -> Sent,
+    Sent: Future<Output = ()>,
+{
+    let (sink, mut chunks) = mpsc::unbounded_channel();
+    let forward = async move {
+        while let Some(chunk) = chunks.recv().await {
+            send(chunk).await;
+        }
+    };
+    // The sink is dropped with the finished call, which ends `forward`.
+    let (output, ()) = tokio::join!(SINK.scope(sink, future), forward);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    async fn crawl() -> &'static str {
+        emit("page 1\n");
+        tokio::task::yield_now().await;
+        emit("page 2\n");
+        "done"
+    }
+
+    #[tokio::test]
+    async fn chunks_are_streamed_when_possible_and_collected_otherwise() {
+        assert_eq!(
+            collect_output(crawl()).await,
+            ("done", vec!["page 1\n".to_string(), "page 2\n".to_string()])
+        );
+
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let send = |chunk| {
+            sent.lock().unwrap().push(chunk);
+            async {}
+        };
+        let output = stream_to(collect_output(crawl()), send).await;
+        assert_eq!(output, ("done", Vec::new()));
+        assert_eq!(*sent.lock().unwrap(), ["page 1\n", "page 2\n"]);
+
+        // Reports are streamed, but never collected.
+        let reporting = async {
+            report("working\n");
+            "done"
+        };
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let send = |chunk| {
+            sent.lock().unwrap().push(chunk);
+            async {}
+        };
+        let output = stream_to(collect_output(reporting), send).await;
+        assert_eq!(output, ("done", Vec::new()));
+        assert_eq!(*sent.lock().unwrap(), ["working\n"]);
+        assert_eq!(
+            collect_output(async { report("working\n") }).await,
+            ((), Vec::new())
+        );
+
+        // Outside a call, output goes nowhere.
+        emit("ignored");
+        report("ignored");
+    }
+}
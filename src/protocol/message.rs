@@ -0,0 +1,79 @@
+use rmcp::{model::ErrorCode, ErrorData as McpError};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+/// A JSON-RPC request, or a notification when it has no `id`. An `id` of
+/// `null` is still a request, answered with `"id": null`.
+#[derive(Debug, Deserialize)]
+pub struct Request {
+    pub jsonrpc: String,
+    #[serde(default, deserialize_with = "present")]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Option<Value>,
+}
+
+impl Request {
+    /// The parameter `name`, if the request has one.
+    pub fn param(&self, name: &str) -> Option<&Value> {
+        self.params.as_ref()?.get(name)
+    }
+
+    /// What the request is accounted as: the tool name for `tools/call`,
+    /// otherwise the method name.
+    pub fn operation(&self) -> String {
+        match self.method.as_str() {
+            "tools/call" => self
+                .param("name")
+                .and_then(Value::as_str)
+                .unwrap_or("tools/call")
+                .to_string(),
+            method => method.to_string(),
+        }
+    }
+}
+
+/// `Some` whenever the field is there, even as `null`; `#[serde(default)]`
+/// leaves it `None` when it is absent.
+fn present<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Value>, D::Error> {
+    Value::deserialize(deserializer).map(Some)
+}
+
+/// A JSON-RPC response, with either a `result` or an `error`.
+#[derive(Debug, Serialize)]
+pub struct Response {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Value>,
+}
+
+impl Response {
+    pub fn result(id: Value, result: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    /// An error response keeping `error`'s code and data, so structured
+    /// errors (e.g. maintenance) reach clients intact.
+    pub fn error(id: Value, error: McpError) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: None,
+            error: Some(serde_json::to_value(error).expect("ErrorData serializes")),
+        }
+    }
+
+    /// An error response with just `code` and `message`.
+    pub fn error_code(id: Value, code: i32, message: &'static str) -> Self {
+        Self::error(id, McpError::new(ErrorCode(code), message, None))
+    }
+}
@@ -0,0 +1,352 @@
+//! The MCP methods, answered once for every transport that frames JSON-RPC
+//! itself.
+//!
+//! [`dispatch`] takes one parsed [`Request`] and returns its [`Response`];
+//! [`notify`] acts on a notification. `/rpc` (and through it `once` and
+//! `replay`), `/sse`, `/mcp`, and `/ws` all answer through here, so a new
+//! method is added in one place. Stdio and raw TCP are served by rmcp,
+//! whose `ServerHandler` methods on [`ToolService`] call the same service
+//! methods, because only rmcp can also send requests to the client
+//! (sampling, roots).
+
+mod message;
+
+pub use message::{Request, Response};
+
+use crate::{cancellation, progress, server::ToolService, sessions::SessionState};
+use rmcp::{
+    model::{CompleteRequestParam, GetPromptRequestParam, SetLevelRequestParam},
+    ErrorData as McpError,
+};
+use serde_json::Value;
+
+/// Answer `request` on behalf of `session`, the `mcp-session-id` it came
+/// with, if any. Requests in a session can be stopped by a
+/// `notifications/cancelled` from it; requests outside one cannot, as their
+/// IDs could collide with another client's.
+pub async fn dispatch(service: &ToolService, session: Option<&str>, request: Request) -> Response {
+    let id = request.id.clone().unwrap_or(Value::Null);
+    let Some(session) = session.filter(|_| request.id.is_some()) else {
+        return answer(service, None, id, request).await;
+    };
+    let call = answer(service, Some(session), id.clone(), request);
+    match service.cancellations().run(session, &id, call).await {
+        Some(response) => response,
+        None => Response::error(id, cancellation::cancelled()),
+    }
+}
+
+/// Act on a notification: `notifications/initialized` marks the session
+/// initialized and `notifications/cancelled` stops the request it names.
+/// The rest, and any outside a session, are acknowledged and dropped.
+pub fn notify(service: &ToolService, session: Option<&str>, request: &Request) {
+    let Some(session) = session else {
+        return;
+    };
+    match request.method.as_str() {
+        "notifications/initialized" if service.sessions().mark_initialized(session) => {
+            tracing::info!("client initialized");
+        }
+        "notifications/cancelled" => {
+            let Some(id) = request.param("requestId") else {
+                return;
+            };
+            if service.cancellations().cancel(session, id) {
+                let reason = request.param("reason").and_then(Value::as_str);
+                tracing::info!(request_id = %id, reason = reason.unwrap_or("-"), "request cancelled");
+            }
+        }
+        _ => {}
+    }
+}
+
+async fn answer(
+    service: &ToolService,
+    session: Option<&str>,
+    id: Value,
+    request: Request,
+) -> Response {
+    if request.jsonrpc != "2.0" {
+        return Response::error_code(id, -32600, "Invalid Request");
+    }
+    match handle(service, session, request).await {
+        Ok(result) => Response::result(id, result),
+        Err(error) => Response::error(id, error),
+    }
+}
+
+fn invalid_params() -> McpError {
+    McpError::invalid_params("Invalid params", None)
+}
+
+/// `params` as the parameter type of the method, or an invalid-params
+/// error.
+fn parse_params<T: serde::de::DeserializeOwned>(params: Option<Value>) -> Result<T, McpError> {
+    params
+        .and_then(|params| serde_json::from_value(params).ok())
+        .ok_or_else(invalid_params)
+}
+
+async fn handle(
+    service: &ToolService,
+    session: Option<&str>,
+    request: Request,
+) -> Result<Value, McpError> {
+    match request.method.as_str() {
+        "initialize" => {
+            // Unlike rmcp, which needs one, a missing version gets ours so
+            // plain HTTP clients can initialize with `{}`.
+            let requested = match request.param("protocolVersion") {
+                None => None,
+                Some(Value::String(version)) => Some(version.as_str()),
+                Some(_) => {
+                    return Err(McpError::invalid_params(
+                        "protocolVersion must be a string",
+                        None,
+                    ))
+                }
+            };
+            let result = service.initialize_result(requested)?;
+            Ok(serde_json::to_value(result).expect("InitializeResult serializes"))
+        }
+        "ping" => Ok(serde_json::json!({})),
+        "logging/setLevel" => {
+            let params: SetLevelRequestParam = parse_params(request.params)?;
+            // Messages are delivered on the session's event stream.
+            let state = SessionState::current().ok_or_else(|| {
+                McpError::invalid_params(
+                    "Log messages need the mcp-session-id of an /sse or /mcp session",
+                    None,
+                )
+            })?;
+            state.log().set_level(params.level);
+            tracing::info!(level = ?params.level, "client log level set");
+            Ok(serde_json::json!({}))
+        }
+        "tools/list" => {
+            let cursor = request.param("cursor").and_then(Value::as_str);
+            let (tools, next_cursor) = service.tools_page(cursor)?;
+            let mut result = serde_json::json!({ "tools": tools });
+            if let Some(next_cursor) = next_cursor {
+                result["nextCursor"] = next_cursor.into();
+            }
+            Ok(result)
+        }
+        "tools/call" => {
+            let params = request.params.ok_or_else(invalid_params)?;
+            let name = params
+                .get("name")
+                .and_then(Value::as_str)
+                .ok_or_else(invalid_params)?;
+            let arguments = params
+                .get("arguments")
+                .cloned()
+                .unwrap_or(serde_json::json!({}));
+            let result =
+                call_tool_with_progress(service, session, &params, name, arguments).await?;
+            Ok(tool_result(result))
+        }
+        "resources/list" => Ok(serde_json::json!({ "resources": service.resources().list() })),
+        "resources/templates/list" => Ok(serde_json::json!({
+            "resourceTemplates": service.resources().templates()
+        })),
+        "resources/read" => {
+            let uri = request
+                .param("uri")
+                .and_then(Value::as_str)
+                .ok_or_else(invalid_params)?;
+            let contents = service.resources().read(uri).await?;
+            Ok(serde_json::to_value(contents).expect("resources serialize"))
+        }
+        method @ ("resources/subscribe" | "resources/unsubscribe") => {
+            let uri = request
+                .param("uri")
+                .and_then(Value::as_str)
+                .ok_or_else(invalid_params)?;
+            // Updates are delivered on the session's event stream.
+            let state = SessionState::current().ok_or_else(|| {
+                McpError::invalid_params(
+                    "Subscriptions need the mcp-session-id of an /sse or /mcp session",
+                    None,
+                )
+            })?;
+            let resources = service.resources();
+            if method == "resources/subscribe" {
+                resources.subscribe(&state, uri).await?;
+            } else {
+                resources.unsubscribe(&state, uri)?;
+            }
+            Ok(serde_json::json!({}))
+        }
+        "completion/complete" => {
+            let params: CompleteRequestParam = parse_params(request.params)?;
+            let completion = service.complete(&params)?;
+            Ok(serde_json::to_value(completion).expect("completions serialize"))
+        }
+        "prompts/list" => Ok(serde_json::json!({ "prompts": service.prompts().list() })),
+        "prompts/get" => {
+            let params: GetPromptRequestParam = parse_params(request.params)?;
+            let prompt = service.prompts().get(&params.name, params.arguments)?;
+            Ok(serde_json::to_value(prompt).expect("prompts serialize"))
+        }
+        _ => Err(McpError::new(
+            rmcp::model::ErrorCode::METHOD_NOT_FOUND,
+            "Method not found",
+            None,
+        )),
+    }
+}
+
+/// Call `name`. When the request carries a `progressToken` and its session
+/// has an event stream open, the tool's output chunks are pushed to that
+/// session's stream as `notifications/progress` while it runs; otherwise
+/// they lead the result.
+async fn call_tool_with_progress(
+    service: &ToolService,
+    session: Option<&str>,
+    params: &Value,
+    name: &str,
+    arguments: Value,
+) -> Result<rmcp::model::CallToolResult, McpError> {
+    let call = service.call_tool(name, arguments);
+    let token = params
+        .get("_meta")
+        .and_then(|meta| meta.get("progressToken"));
+    let (Some(token), Some(session)) = (token, session) else {
+        return call.await;
+    };
+    let sessions = service.sessions();
+    if !sessions.is_listening(session) {
+        return call.await;
+    }
+    let mut sent = 0;
+    progress::stream_to(call, |chunk| {
+        sent += 1;
+        sessions.notify(
+            session,
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/progress",
+                "params": { "progressToken": token, "progress": sent, "message": chunk }
+            }),
+        );
+        async {}
+    })
+    .await
+}
+
+/// A tool result in MCP response format. Content items of every kind
+/// (text, images, audio, embedded resources, and resource links) are
+/// serialized as rmcp does for the other transports.
+fn tool_result(result: rmcp::model::CallToolResult) -> Value {
+    let content: Vec<Value> = result
+        .content
+        .iter()
+        .map(|content| serde_json::to_value(content).expect("content serializes"))
+        .collect();
+    // Built by hand: `json!` would deep-copy `content`.
+    let mut response = serde_json::Map::new();
+    response.insert("content".to_string(), content.into());
+    if let Some(structured) = result.structured_content {
+        response.insert("structuredContent".to_string(), structured);
+    }
+    if result.is_error == Some(true) {
+        response.insert("isError".to_string(), true.into());
+    }
+    response.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::{AnnotateAble, CallToolResult, Content, RawAudioContent, RawContent};
+    use serde_json::json;
+
+    fn request(method: &str, params: Value) -> Request {
+        Request {
+            jsonrpc: "2.0".to_string(),
+            id: Some(json!(1)),
+            method: method.to_string(),
+            params: Some(params),
+        }
+    }
+
+    #[tokio::test]
+    async fn answers_methods_and_reports_errors_with_their_codes() {
+        let service = ToolService::new();
+        let init = dispatch(&service, None, request("initialize", json!({}))).await;
+        let result = init.result.expect("initialize succeeds");
+        assert_eq!(result["protocolVersion"], "2025-03-26");
+        assert_eq!(result["capabilities"]["tools"]["listChanged"], true);
+
+        let call = request(
+            "tools/call",
+            json!({ "name": "echo", "arguments": { "message": "hi" } }),
+        );
+        assert_eq!(call.operation(), "echo");
+        let echoed = dispatch(&service, None, call).await;
+        assert!(echoed.result.is_some(), "{echoed:?}");
+
+        for (method, params, code) in [
+            ("tools/call", json!({}), -32602),
+            (
+                "resources/subscribe",
+                json!({ "uri": "schedule://x" }),
+                -32602,
+            ),
+            ("prompts/get", json!({ "nope": 1 }), -32602),
+            ("no/such/method", json!({}), -32601),
+        ] {
+            let response = dispatch(&service, None, request(method, params)).await;
+            assert_eq!(response.error.expect("an error")["code"], code, "{method}");
+        }
+        let mut old = request("ping", json!({}));
+        old.jsonrpc = "1.0".to_string();
+        let response = dispatch(&service, None, old).await;
+        assert_eq!(response.error.expect("an error")["code"], -32600);
+    }
+
+    #[test]
+    fn only_requests_without_an_id_are_notifications() {
+        let parse = |body: &str| serde_json::from_str::<Request>(body).expect("a request");
+        assert_eq!(
+            parse(r#"{"jsonrpc":"2.0","id":7,"method":"ping"}"#).id,
+            Some(json!(7))
+        );
+        assert_eq!(
+            parse(r#"{"jsonrpc":"2.0","id":null,"method":"ping"}"#).id,
+            Some(Value::Null)
+        );
+        assert_eq!(parse(r#"{"jsonrpc":"2.0","method":"ping"}"#).id, None);
+    }
+
+    #[test]
+    fn tool_results_keep_every_content_type() {
+        let audio = RawContent::Audio(RawAudioContent {
+            data: "UklGRg==".to_string(),
+            mime_type: "audio/wav".to_string(),
+        });
+        let result = CallToolResult::error(vec![
+            Content::text("caption"),
+            Content::image("iVBORw0KGgo=", "image/png"),
+            Content::embedded_text("schedule://clock", "12:00"),
+            audio.no_annotation(),
+        ]);
+
+        assert_eq!(
+            tool_result(result),
+            json!({
+                "content": [
+                    { "type": "text", "text": "caption" },
+                    { "type": "image", "data": "iVBORw0KGgo=", "mimeType": "image/png" },
+                    {
+                        "type": "resource",
+                        "resource": { "uri": "schedule://clock", "mimeType": "text", "text": "12:00" }
+                    },
+                    { "type": "audio", "data": "UklGRg==", "mimeType": "audio/wav" }
+                ],
+                "isError": true
+            })
+        );
+    }
+}
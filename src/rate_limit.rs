@@ -0,0 +1,279 @@
+//! Token-bucket rate limits on the transport endpoints.
+//!
+//! Each limit is a rate like `120/min`: a bucket holds that many requests
+//! and refills continuously at that rate, so a client may burst up to the
+//! full amount and then settle at the rate. There is one bucket per
+//! credential (the API key or bearer token presented, by fingerprint), one
+//! per client IP, and one shared by everyone; a request must find a token
+//! in each bucket that applies, and takes one from each only then.
+
+use rmcp::model::ErrorCode;
+use std::{
+    collections::HashMap,
+    env,
+    hash::Hash,
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// JSON-RPC error code of requests refused for exceeding a limit, over HTTP
+/// with status 429.
+pub const RATE_LIMITED_ERROR_CODE: ErrorCode = ErrorCode(-32001);
+
+/// Buckets kept per kind before full ones, which carry no state, are
+/// dropped.
+const MAX_BUCKETS: usize = 10_000;
+
+/// Requests allowed per period.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rate {
+    pub requests: u32,
+    pub per: Duration,
+}
+
+impl Rate {
+    /// Parse `<requests>/<s|min|h>`, as in `10/s` or `600/min`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let (requests, unit) = value
+            .split_once('/')
+            .ok_or_else(|| format!("'{value}' is not a rate like 60/min"))?;
+        let requests: u32 = requests
+            .trim()
+            .parse()
+            .ok()
+            .filter(|requests| *requests > 0)
+            .ok_or_else(|| format!("'{value}' needs a positive number of requests"))?;
+        let per = match unit.trim() {
+            "s" | "sec" => Duration::from_secs(1),
+            "m" | "min" => Duration::from_secs(60),
+            "h" | "hour" => Duration::from_secs(60 * 60),
+            unit => return Err(format!("'{unit}' is not s, min, or h")),
+        };
+        Ok(Self { requests, per })
+    }
+
+    fn per_second(self) -> f64 {
+        f64::from(self.requests) / self.per.as_secs_f64()
+    }
+}
+
+/// Which buckets requests are counted in; a `None` limit is not enforced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimits {
+    pub per_key: Option<Rate>,
+    pub per_ip: Option<Rate>,
+    pub global: Option<Rate>,
+}
+
+impl RateLimits {
+    /// Read `INFERENCO_MCP_RATE_LIMIT_KEY`, `INFERENCO_MCP_RATE_LIMIT_IP`,
+    /// and `INFERENCO_MCP_RATE_LIMIT_GLOBAL`.
+    pub fn from_env() -> Result<Self, String> {
+        let rate = |name: &str| match env::var(name) {
+            Ok(value) if !value.trim().is_empty() => Rate::parse(&value)
+                .map(Some)
+                .map_err(|error| format!("{name}: {error}")),
+            _ => Ok(None),
+        };
+        Ok(Self {
+            per_key: rate("INFERENCO_MCP_RATE_LIMIT_KEY")?,
+            per_ip: rate("INFERENCO_MCP_RATE_LIMIT_IP")?,
+            global: rate("INFERENCO_MCP_RATE_LIMIT_GLOBAL")?,
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.per_key.is_some() || self.per_ip.is_some() || self.global.is_some()
+    }
+}
+
+/// The limit a refused request ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Key,
+    Ip,
+    Global,
+}
+
+impl Limit {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Limit::Key => "key",
+            Limit::Ip => "ip",
+            Limit::Global => "global",
+        }
+    }
+}
+
+/// Why a request was refused, and when a retry could succeed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Exceeded {
+    pub limit: Limit,
+    pub retry_after: Duration,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn full(rate: Rate, now: Instant) -> Self {
+        Self {
+            tokens: f64::from(rate.requests),
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, rate: Rate, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate.per_second()).min(f64::from(rate.requests));
+        self.updated = now;
+    }
+
+    /// How long until the bucket holds a token; zero when it does.
+    fn wait(&self, rate: Rate) -> Duration {
+        Duration::from_secs_f64(((1.0 - self.tokens) / rate.per_second()).max(0.0))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Buckets {
+    keys: HashMap<String, Bucket>,
+    ips: HashMap<IpAddr, Bucket>,
+    global: Option<Bucket>,
+}
+
+/// Admits requests within [`RateLimits`].
+#[derive(Debug)]
+pub struct RateLimiter {
+    limits: RateLimits,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: RateLimits) -> Self {
+        Self {
+            limits,
+            buckets: Mutex::default(),
+        }
+    }
+
+    pub fn limits(&self) -> RateLimits {
+        self.limits
+    }
+
+    /// Count a request with credential fingerprint `key` from `ip`, or say
+    /// which limit it exceeds.
+    pub fn check(&self, key: Option<&str>, ip: Option<IpAddr>) -> Result<(), Exceeded> {
+        self.check_at(key, ip, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        key: Option<&str>,
+        ip: Option<IpAddr>,
+        now: Instant,
+    ) -> Result<(), Exceeded> {
+        let mut buckets = self.buckets.lock().expect("rate limit lock poisoned");
+        let Buckets { keys, ips, global } = &mut *buckets;
+        let mut applying: Vec<(Limit, Rate, &mut Bucket)> = Vec::with_capacity(3);
+        if let (Some(rate), Some(key)) = (self.limits.per_key, key) {
+            applying.push((Limit::Key, rate, bucket(keys, key.to_string(), rate, now)));
+        }
+        if let (Some(rate), Some(ip)) = (self.limits.per_ip, ip) {
+            applying.push((Limit::Ip, rate, bucket(ips, ip, rate, now)));
+        }
+        if let Some(rate) = self.limits.global {
+            let global = global.get_or_insert_with(|| Bucket::full(rate, now));
+            applying.push((Limit::Global, rate, global));
+        }
+        for (_, rate, bucket) in &mut applying {
+            bucket.refill(*rate, now);
+        }
+        // The longest wait is the one a retry has to sit out.
+        let exceeded = applying
+            .iter()
+            .filter(|(_, _, bucket)| bucket.tokens < 1.0)
+            .map(|(limit, rate, bucket)| Exceeded {
+                limit: *limit,
+                retry_after: bucket.wait(*rate),
+            })
+            .max_by_key(|exceeded| exceeded.retry_after);
+        if let Some(exceeded) = exceeded {
+            return Err(exceeded);
+        }
+        for (_, _, bucket) in applying {
+            bucket.tokens -= 1.0;
+        }
+        Ok(())
+    }
+}
+
+/// The bucket for `id`, made full if it is new. A crowded map first drops
+/// the buckets that have refilled, as a new one would be no different.
+fn bucket<K: Hash + Eq>(
+    buckets: &mut HashMap<K, Bucket>,
+    id: K,
+    rate: Rate,
+    now: Instant,
+) -> &mut Bucket {
+    if buckets.len() >= MAX_BUCKETS && !buckets.contains_key(&id) {
+        buckets.retain(|_, bucket| {
+            bucket.refill(rate, now);
+            bucket.tokens < f64::from(rate.requests)
+        });
+    }
+    buckets.entry(id).or_insert_with(|| Bucket::full(rate, now))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates_parse_with_their_unit() {
+        assert_eq!(
+            Rate::parse("600/min"),
+            Ok(Rate {
+                requests: 600,
+                per: Duration::from_secs(60)
+            })
+        );
+        assert_eq!(Rate::parse(" 5 / s ").map(|rate| rate.requests), Ok(5));
+        for bad in ["60", "0/s", "x/min", "10/day"] {
+            assert!(Rate::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn buckets_refuse_bursts_beyond_their_size_and_refill() {
+        let limiter = RateLimiter::new(RateLimits {
+            per_key: Some(Rate::parse("2/s").unwrap()),
+            per_ip: Some(Rate::parse("3/s").unwrap()),
+            global: None,
+        });
+        let ip: IpAddr = "192.0.2.7".parse().unwrap();
+        let start = Instant::now();
+        assert!(limiter.check_at(Some("a"), Some(ip), start).is_ok());
+        assert!(limiter.check_at(Some("a"), Some(ip), start).is_ok());
+        let exceeded = limiter
+            .check_at(Some("a"), Some(ip), start)
+            .expect_err("key bucket is empty");
+        assert_eq!(exceeded.limit, Limit::Key);
+        assert_eq!(exceeded.retry_after, Duration::from_millis(500));
+
+        // The refused request took nothing from the IP bucket.
+        assert!(limiter.check_at(Some("b"), Some(ip), start).is_ok());
+        let exceeded = limiter
+            .check_at(Some("b"), Some(ip), start)
+            .expect_err("IP bucket is empty");
+        assert_eq!(exceeded.limit, Limit::Ip);
+
+        let later = start + Duration::from_millis(500);
+        assert!(limiter.check_at(Some("a"), Some(ip), later).is_ok());
+        assert!(limiter.check_at(None, None, later).is_ok());
+    }
+}
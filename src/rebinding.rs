@@ -0,0 +1,156 @@
+//! Protection of the HTTP transports against DNS rebinding.
+//!
+//! A page that rebinds its own domain to the server's address can make a
+//! browser send it requests, but they carry the page's `Origin` and, as
+//! `Host`, the page's domain. [`RebindingGuard`] refuses requests from
+//! browser origins not listed in `INFERENCO_MCP_CORS_ORIGINS` (requests
+//! without `Origin`, as from non-browser clients, pass), and requests for
+//! hosts not listed in `INFERENCO_MCP_ALLOWED_HOSTS`. A server bound to a
+//! loopback address only answers to `localhost`, `127.0.0.1`, and `[::1]`
+//! unless hosts are listed; otherwise any host is allowed by default.
+
+use crate::cors::{AllowedOrigins, CorsSettings};
+use std::{env, net::IpAddr};
+
+/// Hosts a loopback-bound server answers to when none are configured.
+const LOOPBACK_HOSTS: &[&str] = &["localhost", "127.0.0.1", "[::1]"];
+
+/// Which browser origins and hosts may reach the transport endpoints.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RebindingGuard {
+    origins: AllowedOrigins,
+    /// Lowercase host names without ports; any host when `None`.
+    hosts: Option<Vec<String>>,
+}
+
+impl RebindingGuard {
+    /// Allow the origins `cors` allows, none if CORS is off, and `hosts`.
+    pub fn new(cors: Option<&CorsSettings>, hosts: Option<Vec<String>>) -> Self {
+        Self {
+            origins: cors.map_or(AllowedOrigins::List(Vec::new()), |cors| {
+                cors.origins.clone()
+            }),
+            hosts,
+        }
+    }
+
+    /// Why a request with these `Origin` and `Host` headers is refused, if
+    /// it is.
+    pub fn check(&self, origin: Option<&str>, host: Option<&str>) -> Result<(), &'static str> {
+        if let (Some(origin), AllowedOrigins::List(allowed)) = (origin, &self.origins) {
+            if !allowed
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(origin))
+            {
+                return Err("Origin not allowed");
+            }
+        }
+        if let Some(hosts) = &self.hosts {
+            let host = host.map(host_name).unwrap_or_default();
+            if !hosts.contains(&host) {
+                return Err("Host not allowed");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Read `INFERENCO_MCP_ALLOWED_HOSTS` for a server bound to `bind`: the
+/// listed hosts, any host for `*`, and when unset, the loopback names if
+/// `bind` is loopback and any host otherwise.
+pub fn allowed_hosts_from_env(bind: IpAddr) -> Result<Option<Vec<String>>, String> {
+    allowed_hosts(env::var("INFERENCO_MCP_ALLOWED_HOSTS").ok(), bind)
+}
+
+fn allowed_hosts(configured: Option<String>, bind: IpAddr) -> Result<Option<Vec<String>>, String> {
+    let entries: Vec<&str> = configured
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .collect();
+    match entries.as_slice() {
+        [] if bind.is_loopback() => Ok(Some(
+            LOOPBACK_HOSTS.iter().map(|host| host.to_string()).collect(),
+        )),
+        [] | ["*"] => Ok(None),
+        _ => entries
+            .iter()
+            .map(|entry| {
+                let host = host_name(entry);
+                if host.is_empty() || entry.contains(['/', '@', ' ', '*']) {
+                    Err(format!(
+                        "INFERENCO_MCP_ALLOWED_HOSTS entry '{entry}' is not a host name"
+                    ))
+                } else {
+                    Ok(host)
+                }
+            })
+            .collect::<Result<_, _>>()
+            .map(Some),
+    }
+}
+
+/// The lowercase host of a `Host` header value, without its port.
+fn host_name(host: &str) -> String {
+    let host = host.trim().to_ascii_lowercase();
+    let end = match host.rfind(':') {
+        // A colon inside brackets belongs to an IPv6 address.
+        Some(colon) if !host[colon..].contains(']') => colon,
+        _ => host.len(),
+    };
+    host[..end].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn foreign_origins_and_hosts_are_refused() {
+        let cors = CorsSettings {
+            origins: AllowedOrigins::List(vec!["https://app.example.com".to_string()]),
+            headers: Vec::new(),
+            methods: Vec::new(),
+        };
+        let loopback = IpAddr::from([127, 0, 0, 1]);
+        let hosts = allowed_hosts(None, loopback).unwrap();
+        let guard = RebindingGuard::new(Some(&cors), hosts);
+
+        assert_eq!(guard.check(None, Some("localhost:8080")), Ok(()));
+        assert_eq!(guard.check(None, Some("[::1]:8080")), Ok(()));
+        assert_eq!(
+            guard.check(Some("https://app.example.com"), Some("127.0.0.1")),
+            Ok(())
+        );
+        assert_eq!(
+            guard.check(Some("https://evil.example.com"), Some("localhost")),
+            Err("Origin not allowed")
+        );
+        assert_eq!(
+            guard.check(None, Some("evil.example.com:8080")),
+            Err("Host not allowed")
+        );
+        assert_eq!(guard.check(None, None), Err("Host not allowed"));
+
+        let open = RebindingGuard::new(None, None);
+        assert_eq!(open.check(None, Some("anything")), Ok(()));
+        assert_eq!(
+            open.check(Some("https://app.example.com"), None),
+            Err("Origin not allowed")
+        );
+
+        let everywhere = IpAddr::from([0, 0, 0, 0]);
+        assert_eq!(allowed_hosts(None, everywhere), Ok(None));
+        assert_eq!(allowed_hosts(Some("*".to_string()), loopback), Ok(None));
+        assert_eq!(
+            allowed_hosts(Some("MCP.example.com:443, [::1]".to_string()), everywhere),
+            Ok(Some(vec![
+                "mcp.example.com".to_string(),
+                "[::1]".to_string()
+            ]))
+        );
+        assert!(allowed_hosts(Some("https://mcp.example.com".to_string()), everywhere).is_err());
+    }
+}
@@ -0,0 +1,252 @@
+//! Record-and-replay of HTTP JSON-RPC traffic, for reproducing bugs that
+//! agents report against a running server.
+//!
+//! With `INFERENCO_MCP_RECORD_PATH` set, every `/rpc` exchange is appended
+//! to that file as one JSON line, with credentials in arguments and results
+//! redacted. `inferenco-mcp replay <file>` runs the recorded requests again
+//! against the current build and reports where the responses differ.
+
+use crate::http_client::REDACTED;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Object keys whose values are never recorded. `key` is deliberately absent:
+/// the key-value tools use it for names, which replays need.
+const SENSITIVE_FIELDS: &[&str] = &[
+    "access_token",
+    "api_key",
+    "apikey",
+    "authorization",
+    "password",
+    "secret",
+    "token",
+];
+
+/// One recorded request and the response the server gave.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Exchange {
+    pub recorded_at: String,
+    /// The `x-request-id` the server logged the exchange under.
+    pub request_id: String,
+    /// Key-value namespace the request ran in.
+    pub namespace: String,
+    /// `mcp-session-id` sent with the request, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    pub request: Value,
+    pub response: Value,
+}
+
+/// Appends exchanges to a recording file.
+pub struct Recorder {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl Recorder {
+    /// Open `path` for appending, creating it if needed.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|error| format!("cannot open recording {}: {error}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Redact and append `exchange`. Failures are logged, never returned, so
+    /// recording cannot break the traffic it observes.
+    pub fn record(&self, mut exchange: Exchange) {
+        redact(&mut exchange.request);
+        redact(&mut exchange.response);
+        let mut line = serde_json::to_vec(&exchange).expect("exchanges serialize");
+        line.push(b'\n');
+        let mut file = self.file.lock().expect("recording lock poisoned");
+        if let Err(error) = file.write_all(&line) {
+            tracing::warn!(%error, path = %self.path.display(), "failed to record exchange");
+        }
+    }
+}
+
+fn is_sensitive_field(name: &str) -> bool {
+    let name = name.to_ascii_lowercase().replace('-', "_");
+    SENSITIVE_FIELDS.contains(&name.as_str())
+        || name.ends_with("_token")
+        || name.ends_with("_secret")
+        || name.ends_with("_password")
+}
+
+/// Replace the values of credential-like object keys, at any depth,
+/// including inside strings that hold JSON (as tool results do).
+pub fn redact(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (name, value) in object.iter_mut() {
+                if is_sensitive_field(name) {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        Value::String(text) => {
+            if let Ok(mut parsed @ (Value::Object(_) | Value::Array(_))) =
+                serde_json::from_str(text)
+            {
+                redact(&mut parsed);
+                *text = parsed.to_string();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Read a recording, reporting the line of the first malformed entry.
+pub fn read(path: &Path) -> Result<Vec<Exchange>, String> {
+    let file = File::open(path)
+        .map_err(|error| format!("cannot open recording {}: {error}", path.display()))?;
+    let mut exchanges = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|error| format!("cannot read {}: {error}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let exchange = serde_json::from_str(&line)
+            .map_err(|error| format!("{}:{}: {error}", path.display(), index + 1))?;
+        exchanges.push(exchange);
+    }
+    Ok(exchanges)
+}
+
+/// JSON Pointers at which `actual` differs from `expected`, each with both
+/// values. Values that were redacted when recording match anything.
+pub fn diff(expected: &Value, actual: &Value) -> Vec<String> {
+    let mut differences = Vec::new();
+    diff_at(String::new(), expected, actual, &mut differences);
+    differences
+}
+
+fn diff_at(pointer: String, expected: &Value, actual: &Value, out: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::String(redacted), _) if redacted == REDACTED => {}
+        (Value::Object(expected), Value::Object(actual)) => {
+            let mut names: Vec<&String> = expected.keys().chain(actual.keys()).collect();
+            names.sort();
+            names.dedup();
+            for name in names {
+                let child = format!("{pointer}/{}", name.replace('~', "~0").replace('/', "~1"));
+                match (expected.get(name), actual.get(name)) {
+                    (Some(expected), Some(actual)) => diff_at(child, expected, actual, out),
+                    (Some(expected), None) => out.push(format!("{child}: {expected} -> missing")),
+                    (None, Some(actual)) => out.push(format!("{child}: missing -> {actual}")),
+                    (None, None) => unreachable!("name comes from one of the objects"),
+                }
+            }
+        }
+        (Value::Array(expected_items), Value::Array(actual_items))
+            if expected_items.len() == actual_items.len() =>
+        {
+            for (index, (expected, actual)) in expected_items.iter().zip(actual_items).enumerate() {
+                diff_at(format!("{pointer}/{index}"), expected, actual, out);
+            }
+        }
+        // Tool results carry JSON as text; compare it structurally so key
+        // order and spacing don't count as differences.
+        (Value::String(expected_text), Value::String(actual_text)) => {
+            match (
+                serde_json::from_str::<Value>(expected_text),
+                serde_json::from_str::<Value>(actual_text),
+            ) {
+                (Ok(parsed @ (Value::Object(_) | Value::Array(_))), Ok(actual_parsed)) => {
+                    diff_at(pointer, &parsed, &actual_parsed, out)
+                }
+                _ if expected_text != actual_text => {
+                    out.push(format!("{pointer}: {expected} -> {actual}"))
+                }
+                _ => {}
+            }
+        }
+        _ if expected != actual => {
+            let pointer = if pointer.is_empty() { "/" } else { &pointer };
+            out.push(format!("{pointer}: {expected} -> {actual}"));
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn redacts_credentials_but_keeps_kv_keys() {
+        let mut request = json!({
+            "params": {
+                "name": "kv_set",
+                "arguments": {
+                    "key": "draft",
+                    "value": { "API-Key": "k", "oauth_token": "t", "items": [{ "password": "p" }] },
+                },
+            },
+        });
+        redact(&mut request);
+        let mut result = json!({ "text": "{\"secret\":\"s\",\"key\":\"k\"}" });
+        redact(&mut result);
+        assert_eq!(result["text"], r#"{"key":"k","secret":"REDACTED"}"#);
+        assert_eq!(
+            request["params"]["arguments"],
+            json!({
+                "key": "draft",
+                "value": {
+                    "API-Key": REDACTED,
+                    "oauth_token": REDACTED,
+                    "items": [{ "password": REDACTED }],
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn diffs_report_pointers_and_look_inside_json_text() {
+        let expected = json!({
+            "id": 1,
+            "result": { "content": [{ "type": "text", "text": "{\"value\":1,\"key\":\"a\"}" }] },
+            "token": REDACTED,
+        });
+        let same = json!({
+            "id": 1,
+            "result": { "content": [{ "type": "text", "text": "{\"key\":\"a\",\"value\":1}" }] },
+            "token": "anything",
+        });
+        assert!(diff(&expected, &same).is_empty());
+
+        let changed = json!({
+            "id": 1,
+            "result": { "content": [{ "type": "text", "text": "{\"key\":\"a\",\"value\":2}" }] },
+            "error": null,
+        });
+        assert_eq!(
+            diff(&expected, &changed),
+            vec![
+                "/error: missing -> null".to_string(),
+                "/result/content/0/text/value: 1 -> 2".to_string(),
+                "/token: \"REDACTED\" -> missing".to_string(),
+            ]
+        );
+    }
+}
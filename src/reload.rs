@@ -0,0 +1,689 @@
+//! Settings that can change without a restart.
+//!
+//! The `.env` (or TOML) file is re-read on `SIGHUP` and whenever it changes
+//! on disk, which is watched with `notify` (polled where the platform cannot
+//! watch). Reloadable settings are swapped in atomically; changes to any
+//! other `INFERENCO_MCP_*` variable are logged as requiring a restart.
+//! Variables set in the process environment keep precedence over the file,
+//! as they do at startup, and profile presets only fill what neither sets.
+
+use crate::{
+    config::Profile,
+    config_file,
+    logging::{self, LevelHandle},
+    server::ToolService,
+};
+use arc_swap::ArcSwap;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher as _};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    env,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+use tokio::sync::mpsc;
+
+/// How often the config file's modification time is checked where it
+/// cannot be watched.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Editors write a file in several steps; reload once they are done.
+const SETTLE_DELAY: Duration = Duration::from_millis(100);
+
+/// Variables applied by a reload.
+pub const RELOADABLE: &[&str] = &[
+    "INFERENCO_MCP_AUTH_ENABLED",
+    "INFERENCO_MCP_API_KEYS",
+    "INFERENCO_MCP_AUTH_HEADER",
+    "INFERENCO_MCP_ADMIN_TOKEN",
+    "INFERENCO_MCP_SSE_MAX_AGE_SECS",
+    "INFERENCO_MCP_BATCH_PARALLELISM",
+    "INFERENCO_MCP_LOG_LEVEL",
+    "INFERENCO_MCP_DISABLED_TOOLS",
+];
+
+/// Calls of a JSON-RPC batch run at once unless configured otherwise.
+pub const DEFAULT_BATCH_PARALLELISM: usize = 8;
+
+/// The `.env` or TOML file the process was started with.
+pub struct EnvFile {
+    path: Option<PathBuf>,
+    /// Variables set before the file was loaded; the file never overrides them.
+    external: HashSet<String>,
+    /// Profile values applied to variables nothing else set.
+    presets: BTreeMap<String, String>,
+}
+
+impl EnvFile {
+    /// Load `path`, or when no path is given `.env` or else
+    /// `inferenco-mcp.toml` if present, into the environment, remembering
+    /// which variables were already set so reloads keep the same precedence.
+    /// Paths ending in `.toml` are read as TOML.
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        let external = env::vars().map(|(name, _)| name).collect();
+        let default_toml = Path::new(config_file::DEFAULT_PATH);
+        let path = match path {
+            Some(path) if config_file::is_toml(path) => {
+                set_unset(config_file::read(path)?);
+                Some(path.to_path_buf())
+            }
+            Some(path) => {
+                dotenvy::from_path(path)
+                    .map_err(|error| format!("cannot load {}: {error}", path.display()))?;
+                Some(path.to_path_buf())
+            }
+            None => match dotenvy::dotenv() {
+                Ok(path) => Some(path),
+                Err(_) if default_toml.is_file() => {
+                    set_unset(config_file::read(default_toml)?);
+                    Some(default_toml.to_path_buf())
+                }
+                Err(_) => None,
+            },
+        };
+        Ok(Self {
+            path,
+            external,
+            presets: BTreeMap::new(),
+        })
+    }
+
+    /// Set `profile`'s presets for variables that are still unset.
+    pub fn apply_profile(&mut self, profile: Profile) {
+        for (name, value) in profile.presets() {
+            if env::var_os(name).is_none() {
+                env::set_var(name, value);
+                self.presets.insert(name.to_string(), value.to_string());
+            }
+        }
+    }
+
+    /// Where the current value of `name` came from.
+    pub fn source(&self, name: &str) -> &'static str {
+        if self.external.contains(name) {
+            "environment"
+        } else if self.presets.contains_key(name) {
+            "profile"
+        } else if env::var_os(name).is_none() {
+            "default"
+        } else if self.is_toml() {
+            "config file"
+        } else {
+            ".env"
+        }
+    }
+
+    /// The file that was loaded, if any.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    fn is_toml(&self) -> bool {
+        self.path.as_deref().is_some_and(config_file::is_toml)
+    }
+
+    fn read(&self) -> Result<BTreeMap<String, String>, String> {
+        let Some(path) = &self.path else {
+            return Ok(BTreeMap::new());
+        };
+        if self.is_toml() {
+            return config_file::read(path);
+        }
+        dotenvy::from_path_iter(path)
+            .and_then(|iter| iter.collect())
+            .map_err(|error| format!("cannot read {}: {error}", path.display()))
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        std::fs::metadata(self.path.as_ref()?).ok()?.modified().ok()
+    }
+}
+
+/// Set the variables in `values` that are still unset, as `dotenvy` does.
+fn set_unset(values: BTreeMap<String, String>) {
+    for (name, value) in values {
+        if env::var_os(&name).is_none() {
+            env::set_var(name, value);
+        }
+    }
+}
+
+/// Settings read per request that a reload may change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReloadableConfig {
+    pub auth_enabled: bool,
+    pub auth_header: String,
+    pub api_keys: Vec<String>,
+    pub admin_token: Option<String>,
+    pub sse_max_age: Option<Duration>,
+    /// Calls of one JSON-RPC batch that run at once.
+    pub batch_parallelism: usize,
+    /// `EnvFilter` directives for the server's own logs.
+    pub log_level: Option<String>,
+    /// Tools left out of `tools/list` and refused by `tools/call`.
+    pub disabled_tools: BTreeSet<String>,
+}
+
+/// The settings with none of their variables set.
+impl Default for ReloadableConfig {
+    fn default() -> Self {
+        Self::from_lookup(|_| None)
+    }
+}
+
+impl ReloadableConfig {
+    pub fn from_env() -> Self {
+        Self::from_lookup(|name| env::var(name).ok())
+    }
+
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        Self {
+            auth_enabled: lookup("INFERENCO_MCP_AUTH_ENABLED").as_deref() == Some("true"),
+            auth_header: lookup("INFERENCO_MCP_AUTH_HEADER")
+                .unwrap_or_else(|| "x-api-key".to_string()),
+            api_keys: list(lookup("INFERENCO_MCP_API_KEYS")),
+            admin_token: lookup("INFERENCO_MCP_ADMIN_TOKEN").filter(|token| !token.is_empty()),
+            sse_max_age: lookup("INFERENCO_MCP_SSE_MAX_AGE_SECS")
+                .and_then(|value| value.parse().ok())
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
+            batch_parallelism: lookup("INFERENCO_MCP_BATCH_PARALLELISM")
+                .and_then(|value| value.trim().parse().ok())
+                .filter(|calls| *calls > 0)
+                .unwrap_or(DEFAULT_BATCH_PARALLELISM),
+            log_level: lookup("INFERENCO_MCP_LOG_LEVEL")
+                .map(|level| level.trim().to_string())
+                .filter(|level| !level.is_empty()),
+            disabled_tools: list(lookup("INFERENCO_MCP_DISABLED_TOOLS")),
+        }
+    }
+
+    pub fn is_valid_key(&self, key: &str) -> bool {
+        self.api_keys.iter().any(|candidate| candidate == key)
+    }
+
+    /// What changed from `self` to `new`. Keys and tokens are summarized,
+    /// never printed.
+    pub fn diff(&self, new: &Self) -> Vec<String> {
+        let mut changes = Vec::new();
+        if self.auth_enabled != new.auth_enabled {
+            changes.push(format!(
+                "auth_enabled: {} -> {}",
+                self.auth_enabled, new.auth_enabled
+            ));
+        }
+        if self.auth_header != new.auth_header {
+            changes.push(format!(
+                "auth_header: {} -> {}",
+                self.auth_header, new.auth_header
+            ));
+        }
+        if self.api_keys != new.api_keys {
+            let added = new
+                .api_keys
+                .iter()
+                .filter(|key| !self.is_valid_key(key))
+                .count();
+            let removed = self
+                .api_keys
+                .iter()
+                .filter(|key| !new.is_valid_key(key))
+                .count();
+            changes.push(format!(
+                "api_keys: {} keys ({added} added, {removed} removed)",
+                new.api_keys.len()
+            ));
+        }
+        if self.admin_token != new.admin_token {
+            changes.push(
+                match &new.admin_token {
+                    Some(_) if self.admin_token.is_some() => "admin_token: rotated",
+                    Some(_) => "admin_token: enabled",
+                    None => "admin_token: disabled",
+                }
+                .to_string(),
+            );
+        }
+        if self.sse_max_age != new.sse_max_age {
+            changes.push(format!(
+                "sse_max_age: {:?} -> {:?}",
+                self.sse_max_age, new.sse_max_age
+            ));
+        }
+        if self.batch_parallelism != new.batch_parallelism {
+            changes.push(format!(
+                "batch_parallelism: {} -> {}",
+                self.batch_parallelism, new.batch_parallelism
+            ));
+        }
+        if self.log_level != new.log_level {
+            changes.push(format!(
+                "log_level: {} -> {}",
+                self.log_level.as_deref().unwrap_or("default"),
+                new.log_level.as_deref().unwrap_or("default")
+            ));
+        }
+        if self.disabled_tools != new.disabled_tools {
+            changes.push(format!(
+                "disabled_tools: {:?} -> {:?}",
+                self.disabled_tools, new.disabled_tools
+            ));
+        }
+        changes
+    }
+}
+
+/// The items of a comma-separated list, trimmed, without empty ones.
+fn list<C: FromIterator<String>>(value: Option<String>) -> C {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Outcome of one reload.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ReloadReport {
+    pub changes: Vec<String>,
+    /// Changed variables that only take effect after a restart.
+    pub restart_required: Vec<String>,
+}
+
+/// The current [`ReloadableConfig`], swapped atomically on reload.
+pub struct LiveConfig {
+    current: ArcSwap<ReloadableConfig>,
+    env_file: EnvFile,
+    /// The file's values as of the last reload. Held while reloading, so
+    /// reloads apply one at a time.
+    file_values: Mutex<BTreeMap<String, String>>,
+    /// Where disabled tools are switched off.
+    service: Option<ToolService>,
+    log_level: Option<LevelHandle>,
+}
+
+impl LiveConfig {
+    /// Serve `initial` until the first reload.
+    pub fn new(env_file: EnvFile, initial: ReloadableConfig) -> Self {
+        let file_values = env_file.read().unwrap_or_default();
+        Self {
+            current: ArcSwap::from_pointee(initial),
+            env_file,
+            file_values: Mutex::new(file_values),
+            service: None,
+            log_level: None,
+        }
+    }
+
+    /// Switch the configured tools off in `service` now and keep them in
+    /// step with reloads. Only tools whose setting changed are touched, so
+    /// tools switched through `/admin/tools` stay as they are.
+    pub fn with_service(mut self, service: ToolService) -> Self {
+        switch_tools(
+            &service,
+            &ReloadableConfig::from_lookup(|_| None),
+            &self.get(),
+        );
+        self.service = Some(service);
+        self
+    }
+
+    /// Apply reloaded log levels through `handle`.
+    pub fn with_log_level(mut self, handle: LevelHandle) -> Self {
+        self.log_level = Some(handle);
+        self
+    }
+
+    pub fn get(&self) -> Arc<ReloadableConfig> {
+        self.current.load_full()
+    }
+
+    /// Re-read the config file and apply reloadable settings.
+    pub fn reload(&self) -> Result<ReloadReport, String> {
+        let values = self.env_file.read()?;
+        let mut file_values = self.file_values.lock().expect("config lock poisoned");
+
+        let external = &self.env_file.external;
+        let presets = &self.env_file.presets;
+        let next = ReloadableConfig::from_lookup(|name| {
+            if external.contains(name) {
+                env::var(name).ok()
+            } else {
+                values.get(name).or_else(|| presets.get(name)).cloned()
+            }
+        });
+        if let Some(level) = &next.log_level {
+            logging::level_filter(level)?;
+        }
+        let current = self.get();
+        let report = ReloadReport {
+            changes: current.diff(&next),
+            restart_required: file_values
+                .keys()
+                .chain(values.keys())
+                .filter(|name| {
+                    name.starts_with("INFERENCO_MCP_")
+                        && !RELOADABLE.contains(&name.as_str())
+                        && !external.contains(*name)
+                        && file_values.get(*name) != values.get(*name)
+                })
+                .cloned()
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect(),
+        };
+        let next = Arc::new(next);
+        self.current.store(next.clone());
+        *file_values = values;
+
+        if let Some(service) = &self.service {
+            switch_tools(service, &current, &next);
+        }
+        if let (Some(handle), true) = (&self.log_level, current.log_level != next.log_level) {
+            handle.set(next.log_level.as_deref())?;
+        }
+        Ok(report)
+    }
+
+    /// Reload on `SIGHUP` (Unix) and whenever the config file changes.
+    pub fn spawn_watcher(self: &Arc<Self>) {
+        let config = self.clone();
+        let mut changes = FileChanges::of(config.env_file.path());
+        tokio::spawn(async move {
+            let mut last_modified = config.env_file.modified();
+            #[cfg(unix)]
+            let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+                .expect("failed to install SIGHUP handler");
+            loop {
+                #[cfg(unix)]
+                let trigger = tokio::select! {
+                    _ = hangup.recv() => "SIGHUP",
+                    () = changes.next() => "file change",
+                };
+                #[cfg(not(unix))]
+                let trigger = {
+                    changes.next().await;
+                    "file change"
+                };
+                let modified = config.env_file.modified();
+                if trigger == "file change" && modified == last_modified {
+                    continue;
+                }
+                last_modified = modified;
+                config.log_reload(trigger);
+            }
+        });
+    }
+
+    fn log_reload(&self, trigger: &str) {
+        match self.reload() {
+            Ok(report) => {
+                if report.changes.is_empty() {
+                    tracing::info!(trigger, "configuration reloaded, no changes");
+                } else {
+                    tracing::info!(
+                        trigger,
+                        changes = %report.changes.join("; "),
+                        "configuration reloaded"
+                    );
+                }
+                if !report.restart_required.is_empty() {
+                    tracing::warn!(
+                        variables = %report.restart_required.join(", "),
+                        "changed settings require a restart to take effect"
+                    );
+                }
+            }
+            Err(error) => tracing::warn!(trigger, %error, "configuration reload failed"),
+        }
+    }
+}
+
+/// Switch tools on and off in `service` as the disabled tools went from
+/// `old` to `new`. Unknown names are logged and skipped.
+fn switch_tools(service: &ToolService, old: &ReloadableConfig, new: &ReloadableConfig) {
+    let enabled = old.disabled_tools.difference(&new.disabled_tools);
+    let disabled = new.disabled_tools.difference(&old.disabled_tools);
+    for (name, enable) in enabled
+        .map(|name| (name, true))
+        .chain(disabled.map(|name| (name, false)))
+    {
+        if service.set_tool_enabled(name, enable).is_none() {
+            tracing::warn!(tool = %name, "INFERENCO_MCP_DISABLED_TOOLS names an unknown tool");
+        }
+    }
+}
+
+/// Tells the watcher that the config file may have changed.
+enum FileChanges {
+    /// Events for the file's directory, which also sees the file being
+    /// replaced rather than written (editors, Kubernetes ConfigMaps). The
+    /// watcher stops when dropped.
+    Watched(RecommendedWatcher, mpsc::Receiver<()>),
+    Polled(tokio::time::Interval),
+    /// No file to watch; only `SIGHUP` reloads.
+    Never,
+}
+
+impl FileChanges {
+    fn of(path: Option<&Path>) -> Self {
+        let Some(path) = path else {
+            return Self::Never;
+        };
+        match watch(path) {
+            Ok((watcher, events)) => Self::Watched(watcher, events),
+            Err(error) => {
+                tracing::warn!(path = %path.display(), %error, "cannot watch the config file; polling it");
+                Self::Polled(tokio::time::interval(POLL_INTERVAL))
+            }
+        }
+    }
+
+    /// Wait for the next possible change.
+    async fn next(&mut self) {
+        match self {
+            Self::Watched(_, events) => {
+                if events.recv().await.is_none() {
+                    std::future::pending::<()>().await;
+                }
+                tokio::time::sleep(SETTLE_DELAY).await;
+                while events.try_recv().is_ok() {}
+            }
+            Self::Polled(poll) => {
+                poll.tick().await;
+            }
+            Self::Never => std::future::pending().await,
+        }
+    }
+}
+
+fn watch(path: &Path) -> notify::Result<(RecommendedWatcher, mpsc::Receiver<()>)> {
+    let (sender, events) = mpsc::channel(1);
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if event.is_ok_and(|event| !event.kind.is_access()) {
+            // A full channel already has a change waiting.
+            let _ = sender.try_send(());
+        }
+    })?;
+    let directory = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    watcher.watch(directory, RecursiveMode::NonRecursive)?;
+    Ok((watcher, events))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reload_applies_file_changes_and_reports_restart_only_settings() {
+        let file = tempfile::NamedTempFile::new().expect("temp file");
+        std::fs::write(
+            file.path(),
+            "INFERENCO_MCP_PORT=8080
+",
+        )
+        .unwrap();
+        let live = LiveConfig::new(
+            EnvFile {
+                path: Some(file.path().to_path_buf()),
+                external: HashSet::new(),
+                presets: BTreeMap::new(),
+            },
+            ReloadableConfig::from_lookup(|_| None),
+        );
+        let before = live.get();
+
+        std::fs::write(
+            file.path(),
+            "INFERENCO_MCP_PORT=9090\nINFERENCO_MCP_API_KEYS=a,b\nINFERENCO_MCP_ADMIN_TOKEN=secret\n",
+        )
+        .unwrap();
+        let report = live.reload().expect("reload succeeds");
+
+        let after = live.get();
+        assert!(after.is_valid_key("b"));
+        assert_eq!(after.admin_token.as_deref(), Some("secret"));
+        assert_eq!(after.batch_parallelism, DEFAULT_BATCH_PARALLELISM);
+        assert_eq!(report.changes, before.diff(&after));
+        assert!(report
+            .changes
+            .contains(&"api_keys: 2 keys (2 added, 0 removed)".to_string()));
+        assert!(report
+            .changes
+            .iter()
+            .all(|change| !change.contains("secret")));
+        assert_eq!(
+            report.restart_required,
+            vec!["INFERENCO_MCP_PORT".to_string()]
+        );
+    }
+
+    #[test]
+    fn reload_reads_toml_files() {
+        let file = tempfile::Builder::new()
+            .suffix(".toml")
+            .tempfile()
+            .expect("temp file");
+        std::fs::write(file.path(), "[auth]\nenabled = false\n").unwrap();
+        let live = LiveConfig::new(
+            EnvFile {
+                path: Some(file.path().to_path_buf()),
+                external: HashSet::new(),
+                presets: BTreeMap::new(),
+            },
+            ReloadableConfig::from_lookup(|_| None),
+        );
+
+        std::fs::write(
+            file.path(),
+            "api_keys = [\"a\", \"b\"]\n[auth]\nenabled = true\n",
+        )
+        .unwrap();
+        live.reload().expect("reload succeeds");
+        assert!(live.get().auth_enabled);
+        assert!(live.get().is_valid_key("b"));
+
+        std::fs::write(file.path(), "[auth]\nenabld = true\n").unwrap();
+        assert!(live
+            .reload()
+            .unwrap_err()
+            .contains("did you mean auth.enabled?"));
+    }
+
+    fn env_file(path: &Path) -> EnvFile {
+        EnvFile {
+            path: Some(path.to_path_buf()),
+            external: HashSet::new(),
+            presets: BTreeMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn reload_switches_tools_and_checks_the_log_level() {
+        use futures::StreamExt as _;
+
+        let file = tempfile::NamedTempFile::new().expect("temp file");
+        std::fs::write(file.path(), "INFERENCO_MCP_DISABLED_TOOLS=roll_dice\n").unwrap();
+        let service = ToolService::new();
+        let live = LiveConfig::new(
+            env_file(file.path()),
+            ReloadableConfig::from_lookup(|_| None),
+        );
+        live.reload().expect("reload succeeds");
+        let live = live.with_service(service.clone());
+        assert!(!service.is_tool_enabled("roll_dice"));
+        let mut changes = Box::pin(service.tool_switches().changes());
+
+        std::fs::write(
+            file.path(),
+            "INFERENCO_MCP_DISABLED_TOOLS=echo, nope\nINFERENCO_MCP_LOG_LEVEL=debug\n",
+        )
+        .unwrap();
+        let report = live.reload().expect("reload succeeds");
+        assert!(service.is_tool_enabled("roll_dice"));
+        assert!(!service.is_tool_enabled("echo"));
+        assert_eq!(changes.next().await, Some(()));
+        assert!(report
+            .changes
+            .contains(&"log_level: default -> debug".to_string()));
+        assert!(report.restart_required.is_empty());
+
+        std::fs::write(file.path(), "INFERENCO_MCP_LOG_LEVEL=[oops\n").unwrap();
+        assert!(live
+            .reload()
+            .unwrap_err()
+            .contains("INFERENCO_MCP_LOG_LEVEL"));
+        assert_eq!(live.get().log_level.as_deref(), Some("debug"));
+        assert!(!service.is_tool_enabled("echo"));
+    }
+
+    #[tokio::test]
+    async fn watcher_reloads_when_the_file_changes() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let path = dir.path().join(".env");
+        std::fs::write(&path, "INFERENCO_MCP_API_KEYS=a\n").unwrap();
+        let live = Arc::new(LiveConfig::new(
+            env_file(&path),
+            ReloadableConfig::from_lookup(|_| None),
+        ));
+        live.spawn_watcher();
+        // Let the watcher start before the change it should see.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        // Replaced, as editors and ConfigMap updates do, not written in place.
+        let replacement = dir.path().join(".env.new");
+        std::fs::write(&replacement, "INFERENCO_MCP_API_KEYS=b\n").unwrap();
+        std::fs::rename(&replacement, &path).unwrap();
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !live.get().is_valid_key("b") {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("the change is picked up");
+    }
+
+    #[test]
+    fn reload_falls_back_to_profile_presets() {
+        let file = tempfile::NamedTempFile::new().expect("temp file");
+        std::fs::write(file.path(), "INFERENCO_MCP_API_KEYS=a\n").unwrap();
+        let env_file = EnvFile {
+            path: Some(file.path().to_path_buf()),
+            external: HashSet::new(),
+            presets: Profile::Prod
+                .presets()
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        };
+        let live = LiveConfig::new(env_file, ReloadableConfig::from_lookup(|_| None));
+
+        live.reload().expect("reload succeeds");
+        assert!(live.get().auth_enabled);
+        assert!(live.get().is_valid_key("a"));
+    }
+}
@@ -0,0 +1,327 @@
+//! Interactive prompt behind `inferenco-mcp repl` for exercising tools while
+//! developing them. Tools run in-process through [`ToolService::call_tool`],
+//! so calls go through the same metrics and slow-call paths as real traffic.
+
+use crate::server::ToolService;
+use rmcp::model::{CallToolResult, RawContent, Tool};
+use rustyline::{
+    completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter,
+    history::DefaultHistory, validate::Validator, Context, Editor, Helper,
+};
+use std::time::{Duration, Instant};
+
+const HELP: &str = "\
+<tool> [json]   call a tool; without arguments, edit a template built from its schema
+!<n>            edit and re-run call <n> from :history
+:tools          list tools
+:history        list previous calls
+:help           show this help
+:quit           exit (or Ctrl-D)";
+
+/// One line of input.
+#[derive(Debug, PartialEq, Eq)]
+enum Input {
+    Empty,
+    Quit,
+    Help,
+    Tools,
+    History,
+    Recall(usize),
+    Call { tool: String, args: Option<String> },
+}
+
+fn parse(line: &str) -> Result<Input, String> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(Input::Empty);
+    }
+    if let Some(index) = line.strip_prefix('!') {
+        return index
+            .trim()
+            .parse()
+            .map(Input::Recall)
+            .map_err(|_| format!("expected a history number after '!', got '{index}'"));
+    }
+    match line {
+        ":quit" | ":q" | ":exit" => return Ok(Input::Quit),
+        ":help" | ":h" | "?" => return Ok(Input::Help),
+        ":tools" => return Ok(Input::Tools),
+        ":history" => return Ok(Input::History),
+        other if other.starts_with(':') => {
+            return Err(format!("unknown command '{other}'; try :help"));
+        }
+        _ => {}
+    }
+    let (tool, args) = match line.split_once(char::is_whitespace) {
+        Some((tool, args)) => (tool, Some(args.trim().to_string())),
+        None => (line, None),
+    };
+    Ok(Input::Call {
+        tool: tool.to_string(),
+        args,
+    })
+}
+
+/// A JSON object with a placeholder for every property in `tool`'s input
+/// schema, ready to be edited.
+fn args_template(tool: &Tool) -> String {
+    let Some(properties) = tool
+        .input_schema
+        .get("properties")
+        .and_then(|properties| properties.as_object())
+    else {
+        return "{}".to_string();
+    };
+    let template: serde_json::Map<String, serde_json::Value> = properties
+        .iter()
+        .map(|(name, schema)| {
+            let types = match &schema["type"] {
+                serde_json::Value::Array(types) => types.clone(),
+                other => vec![other.clone()],
+            };
+            let placeholder = match types.first().and_then(|kind| kind.as_str()) {
+                Some("string") => serde_json::json!(""),
+                Some("integer" | "number") => serde_json::json!(0),
+                Some("boolean") => serde_json::json!(false),
+                Some("array") => serde_json::json!([]),
+                Some("object") => serde_json::json!({}),
+                _ => serde_json::Value::Null,
+            };
+            (name.clone(), placeholder)
+        })
+        .collect();
+    serde_json::Value::Object(template).to_string()
+}
+
+/// Text content, with JSON payloads pretty-printed.
+fn render(result: &CallToolResult) -> String {
+    if let Some(structured) = &result.structured_content {
+        return serde_json::to_string_pretty(structured).expect("JSON values serialize");
+    }
+    result
+        .content
+        .iter()
+        .map(|content| match &content.raw {
+            RawContent::Text(text) => serde_json::from_str::<serde_json::Value>(&text.text)
+                .ok()
+                .filter(|value| value.is_object() || value.is_array())
+                .map(|value| serde_json::to_string_pretty(&value).expect("JSON values serialize"))
+                .unwrap_or_else(|| text.text.clone()),
+            other => serde_json::to_string_pretty(other).expect("content serializes"),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+struct HistoryEntry {
+    tool: String,
+    args: String,
+    ok: bool,
+    duration: Duration,
+}
+
+/// Completes tool names and commands at the start of the line.
+struct ReplHelper {
+    words: Vec<String>,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let prefix = &line[..pos];
+        if prefix.contains(char::is_whitespace) {
+            return Ok((pos, Vec::new()));
+        }
+        let candidates = self
+            .words
+            .iter()
+            .filter(|word| word.starts_with(prefix))
+            .cloned()
+            .collect();
+        Ok((0, candidates))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+/// Run the prompt until `:quit` or end of input.
+pub async fn run(service: ToolService) -> Result<(), String> {
+    let mut tools = service.available_tools();
+    tools.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut words: Vec<String> = tools.iter().map(|tool| tool.name.to_string()).collect();
+    words.extend([":tools", ":history", ":help", ":quit"].map(String::from));
+
+    let mut editor: Editor<ReplHelper, DefaultHistory> =
+        Editor::new().map_err(|error| format!("cannot start the prompt: {error}"))?;
+    editor.set_helper(Some(ReplHelper { words }));
+    let mut history: Vec<HistoryEntry> = Vec::new();
+
+    println!("{} tools loaded; :help for commands", tools.len());
+    let mut recalled: Option<String> = None;
+    loop {
+        let read = tokio::task::block_in_place(|| match recalled.take() {
+            Some(line) => editor.readline_with_initial("mcp> ", (&line, "")),
+            None => editor.readline("mcp> "),
+        });
+        let line = match read {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(error) => return Err(format!("cannot read input: {error}")),
+        };
+        let _ = editor.add_history_entry(line.as_str());
+
+        let (tool, args) = match parse(&line) {
+            Ok(Input::Empty) => continue,
+            Ok(Input::Quit) => break,
+            Ok(Input::Help) => {
+                println!("{HELP}");
+                continue;
+            }
+            Ok(Input::Tools) => {
+                println!("{}", crate::cli::list_tools(&service, false));
+                continue;
+            }
+            Ok(Input::History) => {
+                for (index, entry) in history.iter().enumerate() {
+                    println!(
+                        "{:>3}  {} {}  [{}, {} ms]",
+                        index + 1,
+                        entry.tool,
+                        entry.args,
+                        if entry.ok { "ok" } else { "error" },
+                        entry.duration.as_millis()
+                    );
+                }
+                continue;
+            }
+            Ok(Input::Recall(index)) => {
+                match index.checked_sub(1).and_then(|index| history.get(index)) {
+                    Some(entry) => recalled = Some(format!("{} {}", entry.tool, entry.args)),
+                    None => eprintln!("no call {index} in history"),
+                }
+                continue;
+            }
+            Ok(Input::Call { tool, args }) => (tool, args),
+            Err(error) => {
+                eprintln!("{error}");
+                continue;
+            }
+        };
+
+        let Some(definition) = tools.iter().find(|candidate| candidate.name == tool) else {
+            eprintln!("unknown tool '{tool}'; :tools lists what is available");
+            continue;
+        };
+        let args = match args {
+            Some(args) => args,
+            None => {
+                let template = args_template(definition);
+                if template == "{}" {
+                    template
+                } else {
+                    let edited = tokio::task::block_in_place(|| {
+                        editor.readline_with_initial("args> ", (&template, ""))
+                    });
+                    match edited {
+                        Ok(args) if !args.trim().is_empty() => args.trim().to_string(),
+                        _ => continue,
+                    }
+                }
+            }
+        };
+        let arguments: serde_json::Value = match serde_json::from_str(&args) {
+            Ok(value @ serde_json::Value::Object(_)) => value,
+            Ok(_) => {
+                eprintln!("arguments must be a JSON object");
+                continue;
+            }
+            Err(error) => {
+                eprintln!("arguments are not valid JSON: {error}");
+                continue;
+            }
+        };
+
+        let started = Instant::now();
+        let outcome = service.call_tool(&tool, arguments).await;
+        let duration = started.elapsed();
+        let ok = match &outcome {
+            Ok(result) => {
+                let failed = result.is_error == Some(true);
+                let output = render(result);
+                if failed {
+                    eprintln!("tool error:\n{output}");
+                } else {
+                    println!("{output}");
+                }
+                !failed
+            }
+            Err(error) => {
+                eprintln!("{tool} failed: {}", error.message);
+                false
+            }
+        };
+        println!("({} ms)", duration.as_millis());
+        history.push(HistoryEntry {
+            tool,
+            args,
+            ok,
+            duration,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_calls_and_commands() {
+        assert_eq!(
+            parse(r#"roll_dice {"sides": 6}"#),
+            Ok(Input::Call {
+                tool: "roll_dice".to_string(),
+                args: Some(r#"{"sides": 6}"#.to_string()),
+            })
+        );
+        assert_eq!(
+            parse("current_time"),
+            Ok(Input::Call {
+                tool: "current_time".to_string(),
+                args: None,
+            })
+        );
+        assert_eq!(parse(" !2 "), Ok(Input::Recall(2)));
+        assert_eq!(parse(":q"), Ok(Input::Quit));
+        assert!(parse(":nope").is_err());
+        assert!(parse("!x").is_err());
+    }
+
+    #[test]
+    fn templates_cover_every_input_property() {
+        let service = ToolService::new();
+        let tools = service.available_tools();
+        let echo = tools
+            .iter()
+            .find(|tool| tool.name == "echo")
+            .expect("echo is registered");
+        let template: serde_json::Value =
+            serde_json::from_str(&args_template(echo)).expect("template is JSON");
+        assert_eq!(template, serde_json::json!({ "message": "" }));
+    }
+}
@@ -0,0 +1,280 @@
+//! JSON-RPC bodies for `/rpc`, shared by the HTTP handler, `once`, and
+//! `replay`.
+//!
+//! [`process_rpc_body`] takes a raw body, single request or batch,
+//! authenticates it, and answers each request through [`protocol::dispatch`]
+//! with the caller's [`AuthContext`] in force;
+//! the HTTP framing (status codes, request IDs, usage accounting,
+//! recording) stays with the caller.
+
+use crate::{
+    auth::{self, jwt, AuthContext, AuthMethod},
+    protocol::{self, Request, Response},
+    reload::ReloadableConfig,
+    server::ToolService,
+};
+use axum::{
+    body::Bytes,
+    http::{header, HeaderMap},
+};
+use serde_json::value::RawValue;
+
+/// The MCP session a request belongs to, from its `mcp-session-id` header.
+pub fn session_id(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get("mcp-session-id")
+        .and_then(|value| value.to_str().ok())
+}
+
+/// A JSON-RPC request that was answered, with the operation it was
+/// accounted as and the size of the request.
+pub struct RpcCall {
+    pub operation: String,
+    pub response: Response,
+    pub bytes_in: u64,
+}
+
+/// Everything a JSON-RPC body was answered with.
+pub struct RpcReply {
+    /// Whether the body was a batch, which is answered with an array.
+    pub batch: bool,
+    /// Responses in request order; notifications have none.
+    pub calls: Vec<RpcCall>,
+}
+
+/// Why a JSON-RPC body was turned away before dispatch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    /// Auth is on and the request has no valid, unexpired, unrevoked API
+    /// key or bearer token.
+    Unauthorized,
+    /// The bearer token is valid but lacks a scope the OAuth resource
+    /// requires.
+    Forbidden,
+    /// The body is not JSON.
+    ParseError,
+    /// The body is JSON, but not a request or a non-empty batch.
+    InvalidRequest,
+}
+
+impl Rejection {
+    fn from_json(error: serde_json::Error) -> Self {
+        match error.classify() {
+            serde_json::error::Category::Data => Rejection::InvalidRequest,
+            _ => Rejection::ParseError,
+        }
+    }
+
+    /// The JSON-RPC error a rejected body is answered with.
+    pub fn response(self) -> Response {
+        let id = serde_json::Value::Null;
+        match self {
+            Rejection::Unauthorized => {
+                Response::error_code(id, auth::UNAUTHORIZED_ERROR_CODE.0, "Unauthorized")
+            }
+            Rejection::Forbidden => {
+                Response::error_code(id, auth::FORBIDDEN_ERROR_CODE.0, "Forbidden")
+            }
+            Rejection::ParseError => Response::error_code(id, -32700, "Parse error"),
+            Rejection::InvalidRequest => Response::error_code(id, -32600, "Invalid Request"),
+        }
+    }
+}
+
+impl RpcReply {
+    pub fn to_value(&self) -> serde_json::Value {
+        let value = |call: &RpcCall| serde_json::to_value(&call.response).expect("serializes");
+        match (self.batch, self.calls.as_slice()) {
+            (false, [call]) => value(call),
+            (false, []) => serde_json::Value::Null,
+            _ => self.calls.iter().map(value).collect(),
+        }
+    }
+}
+
+/// Dispatch a JSON-RPC body, which may be a single request or a batch.
+/// The body is parsed in place, once: a batch is split into raw entries
+/// that are each parsed when they are dispatched.
+pub async fn process_rpc_body(
+    service: &ToolService,
+    config: &ReloadableConfig,
+    headers: &HeaderMap,
+    body: Bytes,
+) -> Result<RpcReply, Rejection> {
+    let auth = authenticate(service, config, headers).await?;
+    process_authenticated(service, config, headers, auth, body).await
+}
+
+/// [`process_rpc_body`] for a request that already authenticated as `auth`.
+pub async fn process_authenticated(
+    service: &ToolService,
+    config: &ReloadableConfig,
+    headers: &HeaderMap,
+    auth: Option<AuthContext>,
+    body: Bytes,
+) -> Result<RpcReply, Rejection> {
+    auth::with_context(auth, process_authorized(service, config, headers, body)).await
+}
+
+async fn process_authorized(
+    service: &ToolService,
+    config: &ReloadableConfig,
+    headers: &HeaderMap,
+    body: Bytes,
+) -> Result<RpcReply, Rejection> {
+    let batch = body.iter().find(|byte| !byte.is_ascii_whitespace()) == Some(&b'[');
+    if batch {
+        let requests: Vec<&RawValue> =
+            serde_json::from_slice(&body).map_err(Rejection::from_json)?;
+        if requests.is_empty() {
+            return Err(Rejection::InvalidRequest);
+        }
+        return Ok(RpcReply {
+            batch,
+            calls: process_batch(service, config, headers, requests).await,
+        });
+    }
+    let request: Request = serde_json::from_slice(&body).map_err(Rejection::from_json)?;
+    if request.id.is_none() {
+        protocol::notify(service, session_id(headers), &request);
+        return Ok(RpcReply {
+            batch,
+            calls: Vec::new(),
+        });
+    }
+    let operation = request.operation();
+    let response = protocol::dispatch(service, session_id(headers), request).await;
+    Ok(RpcReply {
+        batch,
+        calls: vec![RpcCall {
+            operation,
+            response,
+            bytes_in: body.len() as u64,
+        }],
+    })
+}
+
+/// Reject the request unless auth is off or it carries a valid API key or,
+/// in `Authorization: Bearer`, a valid JWT. Returns who it comes from. A
+/// client certificate, verified when the connection was made, is enough on
+/// its own.
+pub async fn authenticate(
+    service: &ToolService,
+    config: &ReloadableConfig,
+    headers: &HeaderMap,
+) -> Result<Option<AuthContext>, Rejection> {
+    if let Some(certificate) = auth::certificate() {
+        return Ok(Some(certificate));
+    }
+    if !config.auth_enabled {
+        return Ok(None);
+    }
+    let token = credential(config, headers).ok_or(Rejection::Unauthorized)?;
+    authenticate_token(service, config, token).await.map(Some)
+}
+
+/// The API key in the auth header, or else the `Authorization: Bearer`
+/// token, that a request presents.
+pub fn credential<'a>(config: &ReloadableConfig, headers: &'a HeaderMap) -> Option<&'a str> {
+    match headers.get(&config.auth_header) {
+        Some(key) => Some(key.to_str().unwrap_or("")),
+        None => headers
+            .get(header::AUTHORIZATION)?
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer "),
+    }
+}
+
+/// Check `token` as [`authenticate`] checks the auth header, for transports
+/// that take it elsewhere (the `/sse` query string).
+pub async fn authenticate_token(
+    service: &ToolService,
+    config: &ReloadableConfig,
+    token: &str,
+) -> Result<AuthContext, Rejection> {
+    if let Some(validator) = service.jwt().filter(|_| jwt::looks_like_jwt(token)) {
+        let auth = validator.verify(token).await.map_err(|error| {
+            tracing::info!(%error, "bearer token refused");
+            Rejection::Unauthorized
+        })?;
+        if let Some(oauth) = service.oauth() {
+            let missing = oauth.missing_scopes(&auth);
+            if !missing.is_empty() {
+                tracing::info!(subject = %auth.subject, ?missing, "bearer token lacks scopes");
+                return Err(Rejection::Forbidden);
+            }
+        }
+        return Ok(auth);
+    }
+    if config.is_valid_key(token) {
+        return Ok(AuthContext::for_key(
+            token,
+            AuthMethod::ConfiguredKey,
+            Vec::new(),
+        ));
+    }
+    service
+        .key_store()
+        .and_then(|store| store.verify(token, chrono::Utc::now().timestamp()))
+        .ok_or(Rejection::Unauthorized)
+}
+
+/// Dispatch the requests of a batch, up to `config.batch_parallelism` at a
+/// time. Responses come back in request order, whatever order the calls
+/// finish in; notifications get none, and malformed entries get an
+/// `Invalid Request` error.
+async fn process_batch(
+    service: &ToolService,
+    config: &ReloadableConfig,
+    headers: &HeaderMap,
+    requests: Vec<&RawValue>,
+) -> Vec<RpcCall> {
+    // Collected first: a lazy `map` over borrowed entries would make the
+    // handler future fail axum's `Send` bound.
+    let calls: Vec<_> = requests
+        .into_iter()
+        .map(|raw| process_batch_entry(service, headers, raw))
+        .collect();
+    // `buffered` polls the calls concurrently on this task, so they keep
+    // the caller's namespace and session.
+    let results: Vec<_> = futures::StreamExt::collect(futures::StreamExt::buffered(
+        futures::stream::iter(calls),
+        config.batch_parallelism,
+    ))
+    .await;
+    results.into_iter().flatten().collect()
+}
+
+/// Dispatch one entry of a batch; notifications get no response.
+async fn process_batch_entry(
+    service: &ToolService,
+    headers: &HeaderMap,
+    raw: &RawValue,
+) -> Option<RpcCall> {
+    let (operation, response) = match serde_json::from_str::<Request>(raw.get()) {
+        Ok(request) if request.id.is_none() => {
+            protocol::notify(service, session_id(headers), &request);
+            return None;
+        }
+        Ok(request) => (
+            request.operation(),
+            protocol::dispatch(service, session_id(headers), request).await,
+        ),
+        Err(_) => {
+            let id = serde_json::from_str::<serde_json::Value>(raw.get())
+                .ok()
+                .and_then(|entry| entry.get("id").cloned())
+                .unwrap_or(serde_json::Value::Null);
+            (
+                "invalid".to_string(),
+                Response::error_code(id, -32600, "Invalid Request"),
+            )
+        }
+    };
+    Some(RpcCall {
+        operation,
+        response,
+        bytes_in: raw.get().len() as u64,
+    })
+}
@@ -0,0 +1,142 @@
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tokio::runtime::Handle;
+
+/// Longest sampling window accepted from the admin API.
+pub const MAX_SAMPLE: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkerStats {
+    pub worker: usize,
+    /// Fraction of the sampling window this worker spent busy (0.0-1.0).
+    pub utilization: f64,
+    pub park_count: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RuntimeStats {
+    pub sample_ms: u64,
+    pub workers: Vec<WorkerStats>,
+    pub alive_tasks: usize,
+    pub global_queue_depth: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessStats {
+    /// Resident set size; only available on Linux.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rss_bytes: Option<u64>,
+    /// Open file descriptors; only available on Linux.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_fds: Option<usize>,
+}
+
+/// Measure tokio worker utilization over `sample` (capped at [`MAX_SAMPLE`])
+/// along with the current task and queue counts.
+pub async fn sample_runtime(sample: Duration) -> RuntimeStats {
+    let sample = sample.min(MAX_SAMPLE);
+    let metrics = Handle::current().metrics();
+    let busy = |metrics: &tokio::runtime::RuntimeMetrics| -> Vec<Duration> {
+        (0..metrics.num_workers())
+            .map(|worker| metrics.worker_total_busy_duration(worker))
+            .collect()
+    };
+
+    let before = busy(&metrics);
+    let started = Instant::now();
+    tokio::time::sleep(sample).await;
+    let after = busy(&metrics);
+    let elapsed = started.elapsed().as_secs_f64();
+
+    let workers = before
+        .iter()
+        .zip(&after)
+        .enumerate()
+        .map(|(worker, (before, after))| WorkerStats {
+            worker,
+            utilization: if elapsed > 0.0 {
+                ((*after - *before).as_secs_f64() / elapsed).min(1.0)
+            } else {
+                0.0
+            },
+            park_count: metrics.worker_park_count(worker),
+        })
+        .collect();
+
+    RuntimeStats {
+        sample_ms: sample.as_millis() as u64,
+        workers,
+        alive_tasks: metrics.num_alive_tasks(),
+        global_queue_depth: metrics.global_queue_depth(),
+    }
+}
+
+pub fn process_stats() -> ProcessStats {
+    ProcessStats {
+        rss_bytes: rss_bytes(),
+        open_fds: open_fds(),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    parse_vm_rss(&status)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn rss_bytes() -> Option<u64> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn open_fds() -> Option<usize> {
+    // The directory handle used for listing shows up as one extra entry.
+    Some(
+        std::fs::read_dir("/proc/self/fd")
+            .ok()?
+            .count()
+            .saturating_sub(1),
+    )
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_fds() -> Option<usize> {
+    None
+}
+
+/// Extract `VmRSS` (reported in kB) from `/proc/<pid>/status`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_vm_rss(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kb: u64 = line
+        .trim_start_matches("VmRSS:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_vm_rss_from_proc_status() {
+        let status = "Name:\tinferenco\nVmPeak:\t  20000 kB\nVmRSS:\t   1234 kB\n";
+        assert_eq!(parse_vm_rss(status), Some(1234 * 1024));
+        assert_eq!(parse_vm_rss("Name:\tinferenco\n"), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn samples_every_worker() {
+        let stats = sample_runtime(Duration::from_millis(10)).await;
+        assert_eq!(stats.workers.len(), 2);
+        assert!(stats
+            .workers
+            .iter()
+            .all(|worker| (0.0..=1.0).contains(&worker.utilization)));
+    }
+}
@@ -0,0 +1,326 @@
+//! Tool calls run on a cron schedule, with each job's latest result exposed
+//! as an MCP resource.
+//!
+//! `INFERENCO_MCP_SCHEDULE_FILE` names a JSON file listing the jobs:
+//!
+//! ```json
+//! [{ "name": "hourly-dice", "cron": "0 * * * *", "tool": "roll_dice", "arguments": { "sides": 6 } }]
+//! ```
+//!
+//! Jobs run through [`ToolService::call_tool`], so maintenance mode, metrics,
+//! and slow-call logging apply as for client calls. The last result of job
+//! `<name>` is readable as `schedule://<name>`, and sessions subscribed to it
+//! are sent `notifications/resources/updated` after every run.
+
+use crate::server::{subscriptions::Subscriptions, ToolService};
+use chrono::{DateTime, SecondsFormat, Utc};
+use croner::Cron;
+use rmcp::{
+    model::{
+        AnnotateAble, CallToolResult, RawResource, ReadResourceResult, Resource, ResourceContents,
+    },
+    ErrorData as McpError,
+};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::{
+    collections::{BTreeSet, HashMap},
+    env, fs,
+    sync::Mutex,
+    time::Instant,
+};
+
+pub const URI_SCHEME: &str = "schedule://";
+
+/// One job as written in the schedule file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JobSpec {
+    name: String,
+    cron: String,
+    tool: String,
+    #[serde(default)]
+    arguments: Map<String, Value>,
+}
+
+/// A validated schedule entry.
+#[derive(Debug, Clone)]
+pub struct ScheduledJob {
+    pub name: String,
+    /// The cron expression as configured.
+    pub expression: String,
+    schedule: Cron,
+    pub tool: String,
+    pub arguments: Value,
+}
+
+impl ScheduledJob {
+    pub fn uri(&self) -> String {
+        format!("{URI_SCHEME}{}", self.name)
+    }
+
+    /// The first run strictly after `after`, in UTC.
+    pub fn next_run(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.schedule.find_next_occurrence(&after, false).ok()
+    }
+}
+
+/// Read the jobs listed in `INFERENCO_MCP_SCHEDULE_FILE`; none when unset.
+pub fn jobs_from_env() -> Result<Vec<ScheduledJob>, String> {
+    let Some(path) = env::var_os("INFERENCO_MCP_SCHEDULE_FILE").filter(|path| !path.is_empty())
+    else {
+        return Ok(Vec::new());
+    };
+    let path = std::path::PathBuf::from(path);
+    let json = fs::read_to_string(&path).map_err(|error| {
+        format!(
+            "INFERENCO_MCP_SCHEDULE_FILE: cannot read {}: {error}",
+            path.display()
+        )
+    })?;
+    parse_jobs(&json).map_err(|error| format!("INFERENCO_MCP_SCHEDULE_FILE: {error}"))
+}
+
+/// Parse and validate a schedule file's contents. Cron expressions have five
+/// fields (minute to weekday, in UTC) or six with leading seconds.
+pub fn parse_jobs(json: &str) -> Result<Vec<ScheduledJob>, String> {
+    let specs: Vec<JobSpec> = serde_json::from_str(json).map_err(|error| error.to_string())?;
+    let mut names = BTreeSet::new();
+    specs
+        .into_iter()
+        .map(|spec| {
+            let valid_name = !spec.name.is_empty()
+                && spec
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+            if !valid_name {
+                return Err(format!(
+                    "job name '{}' must be letters, digits, '-', '_', or '.'",
+                    spec.name
+                ));
+            }
+            if !names.insert(spec.name.clone()) {
+                return Err(format!("job '{}' is listed twice", spec.name));
+            }
+            let schedule = Cron::new(&spec.cron)
+                .with_seconds_optional()
+                .parse()
+                .map_err(|error| {
+                    format!("job '{}': invalid cron '{}': {error}", spec.name, spec.cron)
+                })?;
+            Ok(ScheduledJob {
+                name: spec.name,
+                expression: spec.cron,
+                schedule,
+                tool: spec.tool,
+                arguments: Value::Object(spec.arguments),
+            })
+        })
+        .collect()
+}
+
+struct LastRun {
+    finished_at: DateTime<Utc>,
+    duration_ms: u64,
+    /// The tool result, or the protocol error the call failed with.
+    outcome: Value,
+    failed: bool,
+}
+
+/// The configured jobs and the latest result of each.
+pub struct ScheduleBoard {
+    jobs: Vec<ScheduledJob>,
+    results: Mutex<HashMap<String, LastRun>>,
+    updates: Subscriptions,
+}
+
+impl ScheduleBoard {
+    /// A board for `jobs` that announces results through `updates`.
+    pub fn new(jobs: Vec<ScheduledJob>, updates: Subscriptions) -> Self {
+        Self {
+            jobs,
+            results: Mutex::new(HashMap::new()),
+            updates,
+        }
+    }
+
+    pub fn jobs(&self) -> &[ScheduledJob] {
+        &self.jobs
+    }
+
+    /// One resource per job, whether or not it has run yet.
+    pub fn resources(&self) -> Vec<Resource> {
+        self.jobs
+            .iter()
+            .map(|job| {
+                let mut resource = RawResource::new(job.uri(), job.name.clone());
+                resource.description = Some(format!(
+                    "Latest result of {} on schedule '{}'",
+                    job.tool, job.expression
+                ));
+                resource.mime_type = Some("application/json".to_string());
+                resource.no_annotation()
+            })
+            .collect()
+    }
+
+    /// The resource for `uri` as JSON, with `last_run` null until the job has
+    /// finished once.
+    pub fn read(&self, uri: &str) -> Option<ReadResourceResult> {
+        let job = self.jobs.iter().find(|job| job.uri() == uri)?;
+        let results = self.results.lock().expect("schedule lock poisoned");
+        let last_run = results.get(&job.name).map(|run| {
+            json!({
+                "finished_at": run.finished_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                "duration_ms": run.duration_ms,
+                "failed": run.failed,
+                "result": run.outcome,
+            })
+        });
+        let body = json!({
+            "job": job.name,
+            "tool": job.tool,
+            "arguments": job.arguments,
+            "cron": job.expression,
+            "next_run": job
+                .next_run(Utc::now())
+                .map(|at| at.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            "last_run": last_run,
+        });
+        Some(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri: uri.to_string(),
+                mime_type: Some("application/json".to_string()),
+                text: body.to_string(),
+                meta: None,
+            }],
+        })
+    }
+
+    /// Store the outcome of a run and announce the update.
+    pub fn record(&self, name: &str, duration_ms: u64, result: &Result<CallToolResult, McpError>) {
+        let (outcome, failed) = match result {
+            Ok(result) => (
+                serde_json::to_value(result).expect("tool results serialize"),
+                result.is_error == Some(true),
+            ),
+            Err(error) => (
+                json!({ "error": serde_json::to_value(error).expect("errors serialize") }),
+                true,
+            ),
+        };
+        self.results.lock().expect("schedule lock poisoned").insert(
+            name.to_string(),
+            LastRun {
+                finished_at: Utc::now(),
+                duration_ms,
+                outcome,
+                failed,
+            },
+        );
+        self.updates.publish(format!("{URI_SCHEME}{name}"));
+    }
+}
+
+/// Start one task per job that calls its tool at every scheduled time, for
+/// as long as the runtime lives. Fails if a job names an unknown tool.
+pub fn spawn(service: &ToolService) -> Result<(), String> {
+    let tools: BTreeSet<String> = service
+        .available_tools()
+        .into_iter()
+        .map(|tool| tool.name.into_owned())
+        .collect();
+    let board = service.schedule();
+    if let Some(job) = board.jobs().iter().find(|job| !tools.contains(&job.tool)) {
+        return Err(format!(
+            "scheduled job '{}' calls unknown tool '{}'",
+            job.name, job.tool
+        ));
+    }
+    for job in board.jobs().iter().cloned() {
+        let service = service.clone();
+        tokio::spawn(async move {
+            while let Some(next) = job.next_run(Utc::now()) {
+                let wait = (next - Utc::now()).to_std().unwrap_or_default();
+                tokio::time::sleep(wait).await;
+                let started = Instant::now();
+                let result = service.call_tool(&job.tool, job.arguments.clone()).await;
+                let duration_ms = started.elapsed().as_millis() as u64;
+                match &result {
+                    Ok(result) if result.is_error != Some(true) => {
+                        tracing::info!(job = %job.name, duration_ms, "scheduled job finished")
+                    }
+                    _ => tracing::warn!(job = %job.name, duration_ms, "scheduled job failed"),
+                }
+                service.schedule().record(&job.name, duration_ms, &result);
+            }
+            tracing::warn!(job = %job.name, "scheduled job has no future runs");
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::Content;
+
+    #[test]
+    fn parses_jobs_and_rejects_bad_entries() {
+        let jobs = parse_jobs(
+            r#"[{"name": "dice", "cron": "30 2 * * *", "tool": "roll_dice", "arguments": {"sides": 6}}]"#,
+        )
+        .expect("valid schedule");
+        let after = DateTime::parse_from_rfc3339("2026-01-01T03:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            jobs[0].next_run(after).map(|at| at.to_rfc3339()),
+            Some("2026-01-02T02:30:00+00:00".to_string())
+        );
+
+        for bad in [
+            r#"[{"name": "x", "cron": "every hour", "tool": "echo"}]"#,
+            r#"[{"name": "a b", "cron": "* * * * *", "tool": "echo"}]"#,
+            r#"[{"name": "x", "cron": "* * * * *", "tool": "echo"}, {"name": "x", "cron": "* * * * *", "tool": "echo"}]"#,
+            r#"[{"name": "x", "cron": "* * * * *", "tool": "echo", "args": {}}]"#,
+        ] {
+            assert!(parse_jobs(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[tokio::test]
+    async fn records_results_and_announces_updates() {
+        let jobs =
+            parse_jobs(r#"[{"name": "clock", "cron": "* * * * *", "tool": "current_time"}]"#)
+                .expect("valid schedule");
+        let subscriptions = Subscriptions::new();
+        let board = ScheduleBoard::new(jobs, subscriptions.clone());
+        assert_eq!(board.resources()[0].uri, "schedule://clock");
+        let unread = board.read("schedule://clock").expect("job exists");
+        let ResourceContents::TextResourceContents { text, .. } = &unread.contents[0] else {
+            panic!("schedule resources are text");
+        };
+        assert_eq!(
+            serde_json::from_str::<Value>(text).unwrap()["last_run"],
+            Value::Null
+        );
+
+        let mut updates = subscriptions.updates();
+        board.record(
+            "clock",
+            3,
+            &Ok(CallToolResult::success(vec![Content::text("now")])),
+        );
+        assert_eq!(updates.recv().await.unwrap(), "schedule://clock");
+        let read = board.read("schedule://clock").expect("job exists");
+        let ResourceContents::TextResourceContents { text, .. } = &read.contents[0] else {
+            panic!("schedule resources are text");
+        };
+        let body: Value = serde_json::from_str(text).unwrap();
+        assert_eq!(body["last_run"]["result"]["content"][0]["text"], "now");
+        assert_eq!(body["last_run"]["failed"], false);
+        assert!(board.read("schedule://missing").is_none());
+    }
+}
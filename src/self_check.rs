@@ -0,0 +1,185 @@
+//! Startup self-test behind `serve --check`: validate configuration, make sure the
+//! port can be bound, probe upstreams, and build the tool registry, without
+//! serving any traffic.
+
+use crate::{
+    config::{Listeners, Settings, Transport},
+    health::{HealthChecker, HealthStatus},
+    server::ToolService,
+    usage::UsageTracker,
+};
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub ok: bool,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckReport {
+    pub ok: bool,
+    pub checks: Vec<CheckResult>,
+}
+
+impl fmt::Display for CheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            let status = if check.ok { "ok" } else { "FAIL" };
+            writeln!(f, "[{status:>4}] {:<10} {}", check.name, check.detail)?;
+        }
+        let verdict = if self.ok { "passed" } else { "failed" };
+        write!(f, "self-check {verdict}")
+    }
+}
+
+/// Run every check. Probes that cannot run because configuration is invalid
+/// are reported as failures rather than skipped.
+pub async fn run(args: &Listeners) -> CheckReport {
+    let mut checks = Vec::new();
+
+    let settings = Settings::load(args).and_then(|settings| {
+        let mut errors = Vec::new();
+        if let Err(error) = UsageTracker::open(settings.usage_path.as_deref()) {
+            errors.push(format!("INFERENCO_MCP_USAGE_PATH: {error}"));
+        }
+        if let Err(error) = settings.state.open() {
+            errors.push(format!("INFERENCO_MCP_STATE_PATH: {error}"));
+        }
+        if errors.is_empty() {
+            Ok(settings)
+        } else {
+            Err(errors)
+        }
+    });
+    checks.push(match &settings {
+        Ok(_) => CheckResult {
+            name: "config",
+            ok: true,
+            detail: "configuration is valid".to_string(),
+        },
+        Err(errors) => CheckResult {
+            name: "config",
+            ok: false,
+            detail: errors.join("; "),
+        },
+    });
+
+    if args.uses_port() {
+        checks.push(match args.http_port() {
+            Ok(port) => {
+                check_bind(std::net::SocketAddr::new(args.bind_addr, port).to_string()).await
+            }
+            Err(error) => CheckResult {
+                name: "bind",
+                ok: false,
+                detail: error,
+            },
+        });
+    }
+    if args.transport.contains(&Transport::Tcp) {
+        checks.push(check_bind(args.tcp_addr.to_string()).await);
+    }
+
+    checks.push(match &settings {
+        Ok(settings) => check_upstreams(&HealthChecker::from_settings(&settings.health)).await,
+        Err(_) => CheckResult {
+            name: "upstreams",
+            ok: false,
+            detail: "skipped: configuration is invalid".to_string(),
+        },
+    });
+
+    let tools = match &settings {
+        Ok(settings) => ToolService::from_settings(settings),
+        Err(_) => ToolService::new(),
+    }
+    .available_tools();
+    checks.push(CheckResult {
+        name: "registry",
+        ok: !tools.is_empty(),
+        detail: format!("{} tools registered", tools.len()),
+    });
+
+    CheckReport {
+        ok: checks.iter().all(|check| check.ok),
+        checks,
+    }
+}
+
+async fn check_bind(address: String) -> CheckResult {
+    match tokio::net::TcpListener::bind(&address).await {
+        Ok(_) => CheckResult {
+            name: "bind",
+            ok: true,
+            detail: format!("{address} is available"),
+        },
+        Err(error) => CheckResult {
+            name: "bind",
+            ok: false,
+            detail: format!("cannot bind {address}: {error}"),
+        },
+    }
+}
+
+async fn check_upstreams(health: &HealthChecker) -> CheckResult {
+    if health.probes().is_empty() {
+        return CheckResult {
+            name: "upstreams",
+            ok: true,
+            detail: "no probes configured".to_string(),
+        };
+    }
+    let report = health.check().await;
+    let failing: Vec<String> = report
+        .dependencies
+        .iter()
+        .filter(|dep| dep.status != HealthStatus::Ok)
+        .map(|dep| {
+            format!(
+                "{} ({})",
+                dep.name,
+                dep.detail.as_deref().unwrap_or("failed")
+            )
+        })
+        .collect();
+    CheckResult {
+        name: "upstreams",
+        ok: report.status != HealthStatus::Unhealthy,
+        detail: if failing.is_empty() {
+            format!("{} probes reachable", report.dependencies.len())
+        } else {
+            format!("unreachable: {}", failing.join(", "))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_lists_failures_and_verdict() {
+        let report = CheckReport {
+            ok: false,
+            checks: vec![
+                CheckResult {
+                    name: "config",
+                    ok: true,
+                    detail: "configuration is valid".to_string(),
+                },
+                CheckResult {
+                    name: "bind",
+                    ok: false,
+                    detail: "cannot bind 0.0.0.0:80".to_string(),
+                },
+            ],
+        };
+        let text = report.to_string();
+        assert!(text.contains("[  ok] config"));
+        assert!(text.contains("[FAIL] bind"));
+        assert!(text.ends_with("self-check failed"));
+    }
+}
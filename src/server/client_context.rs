@@ -0,0 +1,143 @@
+//! What the connected client told the server about itself, for tools.
+//!
+//! A client that advertises the `roots` capability names the directories
+//! (as `file://` URIs) the server should work within. They are asked for with
+//! `roots/list` once the client is initialized and again on every
+//! `notifications/roots/list_changed`, and kept in the connection's
+//! [`ClientContext`], which tools pick up with [`ClientContext::current`].
+//! Only stdio and TCP clients can be asked; over HTTP there are no roots.
+
+use percent_encoding::percent_decode_str;
+use rmcp::model::Root;
+use std::{
+    future::Future,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+use tokio::sync::watch;
+
+tokio::task_local! {
+    static CURRENT_CONTEXT: Option<ClientContext>;
+}
+
+/// Run `future` with `context` as the [`ClientContext::current`] of its
+/// tool calls.
+pub async fn with_context<F: Future>(context: Option<ClientContext>, future: F) -> F::Output {
+    CURRENT_CONTEXT.scope(context, future).await
+}
+
+/// The roots of one client connection. Clones share them.
+#[derive(Clone)]
+pub struct ClientContext {
+    roots: Arc<watch::Sender<Option<Arc<[Root]>>>>,
+}
+
+impl Default for ClientContext {
+    fn default() -> Self {
+        Self {
+            roots: Arc::new(watch::channel(None).0),
+        }
+    }
+}
+
+impl ClientContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The context of the client the current tool call came from, if any.
+    pub fn current() -> Option<Self> {
+        CURRENT_CONTEXT.try_with(Clone::clone).ok().flatten()
+    }
+
+    /// The roots the client declared, or `None` when it declared none (or
+    /// has not answered `roots/list` yet).
+    pub fn roots(&self) -> Option<Arc<[Root]>> {
+        self.roots.borrow().clone()
+    }
+
+    /// Replace the roots, as after the client answered `roots/list`.
+    pub fn set_roots(&self, roots: Vec<Root>) {
+        self.roots.send_replace(Some(roots.into()));
+    }
+
+    /// Watch the roots, to react when the client changes them.
+    pub fn root_changes(&self) -> watch::Receiver<Option<Arc<[Root]>>> {
+        self.roots.subscribe()
+    }
+
+    /// Whether `path` lies within one of the client's `file://` roots. A
+    /// client without roots places no restriction. Relative paths and paths
+    /// with `..` are never within a root.
+    pub fn allows_path(&self, path: &Path) -> bool {
+        let Some(roots) = self.roots() else {
+            return true;
+        };
+        if !path.is_absolute() || path.components().any(|part| part == Component::ParentDir) {
+            return false;
+        }
+        roots
+            .iter()
+            .filter_map(|root| root_path(&root.uri))
+            .any(|root| path.starts_with(root))
+    }
+}
+
+/// The directory a `file://` root URI names.
+fn root_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://")?;
+    // Only local files; `file://host/...` names another machine.
+    let path = rest.strip_prefix("localhost").unwrap_or(rest);
+    if !path.starts_with('/') {
+        return None;
+    }
+    let path = percent_decode_str(path).decode_utf8().ok()?;
+    Some(PathBuf::from(path.as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root(uri: &str) -> Root {
+        Root {
+            uri: uri.to_string(),
+            name: None,
+        }
+    }
+
+    #[test]
+    fn paths_must_lie_within_a_file_root() {
+        let context = ClientContext::new();
+        assert!(context.allows_path(Path::new("/anywhere")));
+
+        context.set_roots(vec![
+            root("file:///home/dev/my%20project"),
+            root("file://localhost/srv/app"),
+            root("https://example.com/repo"),
+        ]);
+        assert!(context.allows_path(Path::new("/home/dev/my project/src/lib.rs")));
+        assert!(context.allows_path(Path::new("/srv/app")));
+        assert!(!context.allows_path(Path::new("/home/dev/my project2")));
+        assert!(!context.allows_path(Path::new("/srv/app/../etc/passwd")));
+        assert!(!context.allows_path(Path::new("src/lib.rs")));
+
+        context.set_roots(Vec::new());
+        assert!(!context.allows_path(Path::new("/srv/app")));
+    }
+
+    #[tokio::test]
+    async fn changes_are_watched_and_scoped() {
+        let context = ClientContext::new();
+        let mut changes = context.root_changes();
+        assert!(ClientContext::current().is_none());
+        with_context(Some(context.clone()), async {
+            let current = ClientContext::current().expect("context is in scope");
+            current.set_roots(vec![root("file:///srv/app")]);
+        })
+        .await;
+        assert!(changes.has_changed().unwrap());
+        let roots = changes.borrow_and_update().clone().expect("roots were set");
+        assert_eq!(roots[0].uri, "file:///srv/app");
+    }
+}
@@ -0,0 +1,118 @@
+//! Argument completion (`completion/complete`).
+//!
+//! A client asks for values of one argument of a prompt or of a resource
+//! template, given what has been typed so far. Each [`CompletionProvider`]
+//! answers for the references it owns: the resource router for the
+//! `cedra-docs://{path}` template and the `summarize_doc_page` prompt, whose
+//! paths come from the docs pages read so far and the pages they link to,
+//! and the prompt registry for every other prompt. Further providers are
+//! added with `ToolService::with_completion_provider`. MCP only lets clients
+//! reference prompts and resource templates, so tool arguments have no
+//! completions.
+
+use rmcp::{
+    model::{ArgumentInfo, CompleteRequestParam, CompleteResult, CompletionInfo, Reference},
+    ErrorData as McpError,
+};
+
+/// Suggests values for the arguments of some prompts or resource templates.
+pub trait CompletionProvider: Send + Sync {
+    /// Values for `argument` of `reference` that fit what the client typed,
+    /// or `None` when `reference` is not this provider's.
+    fn complete(&self, reference: &Reference, argument: &ArgumentInfo) -> Option<Vec<String>>;
+}
+
+/// Answer `request` from the first of `providers` that owns its reference.
+/// Values are sorted, and at most [`CompletionInfo::MAX_VALUES`] are sent.
+pub fn complete(
+    providers: &[&dyn CompletionProvider],
+    request: &CompleteRequestParam,
+) -> Result<CompleteResult, McpError> {
+    let mut values = providers
+        .iter()
+        .find_map(|provider| provider.complete(&request.r#ref, &request.argument))
+        .ok_or_else(|| {
+            let reference = match &request.r#ref {
+                Reference::Prompt(prompt) => format!("prompt {}", prompt.name),
+                Reference::Resource(resource) => format!("resource template {}", resource.uri),
+            };
+            McpError::invalid_params(format!("unknown {reference}"), None)
+        })?;
+    values.sort();
+    values.dedup();
+    let total = values.len();
+    values.truncate(CompletionInfo::MAX_VALUES);
+    Ok(CompleteResult {
+        completion: CompletionInfo {
+            has_more: Some(total > values.len()),
+            total: Some(total as u32),
+            values,
+        },
+    })
+}
+
+/// The `candidates` starting with `typed`, ignoring case.
+pub fn starting_with(candidates: impl IntoIterator<Item = String>, typed: &str) -> Vec<String> {
+    let typed = typed.to_lowercase();
+    candidates
+        .into_iter()
+        .filter(|candidate| candidate.to_lowercase().starts_with(&typed))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::prompts::PromptRegistry;
+
+    struct Colors;
+
+    impl CompletionProvider for Colors {
+        fn complete(&self, reference: &Reference, argument: &ArgumentInfo) -> Option<Vec<String>> {
+            let Reference::Resource(resource) = reference else {
+                return None;
+            };
+            (resource.uri == "color://{name}").then(|| {
+                let colors = ["red", "Rose", "green", "red"].map(String::from);
+                starting_with(colors, &argument.value)
+            })
+        }
+    }
+
+    fn request(reference: Reference, name: &str, value: &str) -> CompleteRequestParam {
+        CompleteRequestParam {
+            r#ref: reference,
+            argument: ArgumentInfo {
+                name: name.to_string(),
+                value: value.to_string(),
+            },
+            context: None,
+        }
+    }
+
+    #[test]
+    fn the_owner_of_a_reference_answers() {
+        let prompts = PromptRegistry::new();
+        let providers: [&dyn CompletionProvider; 2] = [&Colors, &prompts];
+        let colors = Reference::for_resource("color://{name}");
+
+        let completion = complete(&providers, &request(colors.clone(), "name", "R"))
+            .unwrap()
+            .completion;
+        assert_eq!(completion.values, ["Rose", "red"]);
+        assert_eq!(completion.total, Some(2));
+        assert_eq!(completion.has_more, Some(false));
+
+        let module = request(Reference::for_prompt("explain_move_module"), "module", "0x");
+        assert!(complete(&providers, &module)
+            .unwrap()
+            .completion
+            .values
+            .is_empty());
+        let unknown = request(Reference::for_prompt("summary"), "topic", "");
+        assert!(complete(&providers, &unknown)
+            .unwrap_err()
+            .message
+            .contains("unknown prompt summary"));
+    }
+}
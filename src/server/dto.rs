@@ -10,10 +10,232 @@ pub struct ReverseArgs {
     pub text: String,
 }
 
+/// What `counter` does to the named counter.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum CounterOp {
+    /// Read the value without changing it.
+    Get,
+    /// Add one.
+    Increment,
+    /// Subtract one.
+    Decrement,
+    /// Add `amount`, which may be negative.
+    Add,
+    /// Set back to zero and stop listing it.
+    Reset,
+    /// Every counter with its value; `name` is ignored.
+    List,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CounterArgs {
+    pub op: CounterOp,
+    /// Counter to operate on; required for everything but `list`.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Amount for `add`.
+    #[serde(default)]
+    pub amount: Option<i64>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct KvSetArgs {
+    pub key: String,
+    /// Any JSON value.
+    pub value: serde_json::Value,
+    /// Forget the value after this many seconds.
+    #[serde(default)]
+    pub ttl_seconds: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct KvKeyArgs {
+    pub key: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct KvListArgs {
+    /// Only list keys starting with this.
+    #[serde(default)]
+    pub prefix: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct NoteAddArgs {
+    pub text: String,
+    /// Labels to file the note under; stored lowercased.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct NoteSearchArgs {
+    /// Words to look for in note text and tags; every word must match the
+    /// start of some word in the note. Omit to list notes.
+    #[serde(default)]
+    pub query: Option<String>,
+    /// Only notes with this tag.
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// Notes to return (default 10, at most 50).
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct NoteGetArgs {
+    pub id: u64,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CallHistoryArgs {
+    /// Only return calls to this tool.
+    #[serde(default)]
+    pub tool: Option<String>,
+    /// Most recent calls to return (default 20, at most 100).
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CalculateArgs {
+    /// Arithmetic such as `(1.5 + 2) * 3`, `2^64`, `200 + 10%`, or
+    /// `15% of 80`.
+    pub expression: String,
+}
+
+/// What `json_query` does with `document`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JsonQueryMode {
+    /// Select values with `query`.
+    #[default]
+    Query,
+    /// List the differences from `document` to `other`.
+    Diff,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct JsonQueryArgs {
+    #[serde(default)]
+    pub mode: JsonQueryMode,
+    /// Any JSON value. A string holding a JSON object or array is parsed
+    /// first, so another tool's output can be passed as is.
+    pub document: serde_json::Value,
+    /// JSONPath such as `$.items[*].name`, `$..id`, `$.items[-1]`, or
+    /// `$.items[?(@.price < 10)]`; required for `query`.
+    #[serde(default)]
+    pub query: Option<String>,
+    /// The document to compare against; required for `diff`.
+    #[serde(default)]
+    pub other: Option<serde_json::Value>,
+}
+
+/// What `codec` does with its input.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum CodecOp {
+    Base64Encode,
+    /// Standard or URL-safe alphabet, padding optional.
+    Base64Decode,
+    HexEncode,
+    /// Accepts a `0x` prefix.
+    HexDecode,
+    /// Percent-encode everything but `A-Z a-z 0-9 - _ . ~`.
+    UrlEncode,
+    /// Percent-decode, reading `+` as a space.
+    UrlDecode,
+    /// SHA-256, as hex.
+    Sha256,
+    /// Keccak-256 as used by Ethereum (not SHA3-256), as hex.
+    Keccak256,
+}
+
+/// How `codec` reads `input` for encode and hash operations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CodecInputFormat {
+    /// The UTF-8 bytes of the text.
+    #[default]
+    Text,
+    /// Hex digits, optionally prefixed with `0x`.
+    Hex,
+    Base64,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CodecArgs {
+    pub op: CodecOp,
+    pub input: String,
+    /// How to read `input` for encode and hash operations; decoders always
+    /// take the encoded text.
+    #[serde(default)]
+    pub input_format: CodecInputFormat,
+}
+
+#[derive(Debug, Default, serde::Deserialize, schemars::JsonSchema)]
+pub struct CurrentTimeArgs {
+    /// IANA zone such as `Europe/Berlin`; defaults to UTC.
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// strftime format such as `%Y-%m-%d %H:%M %Z`; defaults to RFC 3339.
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+/// Structured result of `current_time`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, schemars::JsonSchema)]
+pub struct CurrentTime {
+    /// RFC 3339 in the requested zone.
+    pub iso8601: String,
+    /// Unix epoch in seconds.
+    pub unix: i64,
+    /// IANA zone the time is expressed in.
+    pub timezone: String,
+    /// The time rendered with the requested format string.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub formatted: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ConvertTimeArgs {
+    /// RFC 3339, `YYYY-MM-DD[ HH:MM[:SS]]`, a Unix epoch in seconds or
+    /// milliseconds, or a relative expression like `3 hours ago`, `in 2
+    /// days`, or `now`.
+    pub time: String,
+    /// IANA zone to express the result in; defaults to UTC.
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// IANA zone for inputs without an offset; defaults to UTC.
+    #[serde(default)]
+    pub from_timezone: Option<String>,
+    /// strftime format to parse `time` with instead of guessing.
+    #[serde(default)]
+    pub input_format: Option<String>,
+    /// strftime format to also render the result with.
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct DiceArgs {
+    /// Dice notation such as `3d6+2`, `d20`, or `2d%`; overrides `sides`.
+    #[serde(default)]
+    pub notation: Option<String>,
+    /// Sides of a single die when no notation is given.
     #[serde(default = "DiceArgs::default_sides")]
     pub sides: u8,
+    /// Times to roll (default 1, at most 100).
+    #[serde(default)]
+    pub count: Option<u32>,
+    /// Seed for rolls that repeat exactly.
+    #[serde(default)]
+    pub seed: Option<u64>,
 }
 
 impl DiceArgs {
@@ -21,3 +243,25 @@ impl DiceArgs {
         6
     }
 }
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct FetchUrlArgs {
+    /// Absolute http(s) URL on an allowed host.
+    pub url: String,
+    /// Characters of text to return (default 20000, at most 100000).
+    #[serde(default)]
+    pub max_chars: Option<usize>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct SummarizeDocsArgs {
+    /// Path of the page under the docs root, such as `move/modules`, or its
+    /// `cedra-docs://` URI.
+    pub page: String,
+    /// Who the summary is for (default: a developer new to Cedra).
+    #[serde(default)]
+    pub audience: Option<String>,
+    /// Longest summary wanted, in words (default 150, at most 1000).
+    #[serde(default)]
+    pub max_words: Option<u32>,
+}
@@ -1,71 +1,977 @@
-use crate::server::{DiceArgs, EchoArgs, ReverseArgs};
+use crate::{
+    alerts::AlertEngine,
+    audit::{AuditRecord, AuditSink},
+    auth::{self, jwt::JwtValidator, keys::KeyStore, oauth::OAuthSettings, policy::Policy},
+    cache::{CacheLimits, CacheRegistry},
+    calc,
+    cancellation::{self, CancellationRegistry},
+    clock, codec,
+    config::Settings,
+    crash_report,
+    dice::{self, Dice},
+    fetch::{self, Fetcher},
+    json_query,
+    limiter::{CallLimiter, ConcurrencyLimits},
+    maintenance::MaintenanceMode,
+    metrics::{self, Metrics},
+    progress, recording,
+    scheduler::{ScheduleBoard, ScheduledJob},
+    server::{
+        client_context::{self, ClientContext},
+        completions::{self, CompletionProvider},
+        prompts::{self, PromptRegistry},
+        resources::{DocsPages, ResourceRouter, DOCS_CACHE, DOCS_SCHEME},
+        sampling::{self, SamplingClient},
+        tool_switches::ToolSwitches,
+        CalculateArgs, CallHistoryArgs, CodecArgs, CodecInputFormat, CodecOp, ConvertTimeArgs,
+        CounterArgs, CounterOp, CurrentTime, CurrentTimeArgs, DiceArgs, EchoArgs, FetchUrlArgs,
+        JsonQueryArgs, JsonQueryMode, KvKeyArgs, KvListArgs, KvSetArgs, NoteAddArgs, NoteGetArgs,
+        NoteSearchArgs, ReverseArgs, SummarizeDocsArgs,
+    },
+    sessions::{self, SessionRegistry, SessionState},
+    slow_calls::{self, SlowCallLog},
+    store::{
+        self, HistoryEntry, HistoryLimits, KvEntry, KvLimits, MemoryStore, Note, NoteLimits,
+        StateLimits, StateStore,
+    },
+    version,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
 use chrono::Utc;
-use rand::Rng;
+use futures::StreamExt as _;
 use rmcp::{
-    handler::server::{router::tool::ToolRouter, wrapper::Parameters, ServerHandler},
+    handler::server::{
+        router::tool::ToolRouter, tool::cached_schema_for_type, wrapper::Parameters, ServerHandler,
+    },
     model::{
-        CallToolResult, Content, Implementation, ProtocolVersion, ServerCapabilities, ServerInfo,
-        Tool,
+        CallToolRequestParam, CallToolResult, CancelledNotificationParam, CompleteRequestParam,
+        CompleteResult, Content, CreateMessageRequestParam, GetPromptRequestParam, GetPromptResult,
+        Implementation, InitializeRequestParam, InitializeResult, ListPromptsResult,
+        ListResourceTemplatesResult, ListResourcesResult, ListToolsResult,
+        LoggingMessageNotificationParam, PaginatedRequestParam, ProgressNotificationParam,
+        ProtocolVersion, RawContent, ReadResourceRequestParam, ReadResourceResult,
+        ResourceContents, ResourceUpdatedNotificationParam, Role, SamplingMessage,
+        ServerCapabilities, ServerInfo, SetLevelRequestParam, SubscribeRequestParam, Tool,
+        UnsubscribeRequestParam,
     },
-    tool, tool_handler, tool_router, ErrorData as McpError,
+    service::{NotificationContext, Peer, RequestContext},
+    tool, tool_router, ErrorData as McpError, RoleServer,
 };
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use serde::Deserialize;
+use std::{sync::Arc, time::Instant};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::Instrument;
 
 #[derive(Clone)]
 pub struct ToolService {
-    counter: Arc<Mutex<u32>>,
+    state: Arc<dyn StateStore>,
+    kv_limits: KvLimits,
+    history_limits: HistoryLimits,
+    note_limits: NoteLimits,
+    metrics: Arc<Metrics>,
+    slow_calls: Arc<SlowCallLog>,
+    sessions: Arc<SessionRegistry>,
+    /// Session state for the connection rmcp serves: stdio, or one TCP
+    /// connection in a copy from [`Self::for_connection`].
+    connection_state: SessionState,
+    /// Cancellation scope of that connection's requests.
+    connection_scope: Arc<str>,
+    /// Key-value namespace of that connection's tool calls.
+    connection_namespace: Arc<str>,
+    /// What that connection's client declared, such as its roots.
+    connection_client: ClientContext,
+    caches: Arc<CacheRegistry>,
+    cancellations: Arc<CancellationRegistry>,
+    resources: ResourceRouter,
+    prompts: PromptRegistry,
+    maintenance: Arc<MaintenanceMode>,
+    limiter: Arc<CallLimiter>,
+    alerts: Option<Arc<AlertEngine>>,
+    /// Set when `fetch_url` is enabled.
+    fetcher: Option<Fetcher>,
+    /// Most tools one `tools/list` page holds.
+    tools_page_size: usize,
+    tool_switches: Arc<ToolSwitches>,
+    /// Issued API keys, when `INFERENCO_MCP_KEYS_FILE` is set.
+    keys: Option<Arc<KeyStore>>,
+    /// Checks bearer tokens, when `INFERENCO_MCP_JWT_JWKS_URL` is set.
+    jwt: Option<Arc<JwtValidator>>,
+    /// Set when `INFERENCO_MCP_OAUTH_RESOURCE` is.
+    oauth: Option<Arc<OAuthSettings>>,
+    /// Who may call which tools, when `INFERENCO_MCP_POLICY_FILE` is set.
+    policy: Option<Arc<Policy>>,
+    /// Where every tool call is recorded, when `INFERENCO_MCP_AUDIT_PATH`
+    /// is set.
+    audit: Option<Arc<dyn AuditSink>>,
+    /// Asked before the resource router and the prompts.
+    completion_providers: Vec<Arc<dyn CompletionProvider>>,
     tool_router: ToolRouter<Self>,
 }
 
 impl ToolService {
     pub fn new() -> Self {
+        let metrics = Arc::new(Metrics::new());
+        Self {
+            state: Arc::new(MemoryStore::default()),
+            kv_limits: KvLimits::default(),
+            history_limits: HistoryLimits::default(),
+            note_limits: NoteLimits::default(),
+            caches: Arc::new(CacheRegistry::new().with_metrics(Arc::clone(&metrics))),
+            cancellations: Arc::new(CancellationRegistry::new()),
+            metrics,
+            slow_calls: Arc::new(SlowCallLog::default()),
+            sessions: Arc::new(SessionRegistry::new()),
+            connection_state: SessionState::default(),
+            connection_scope: Arc::from(cancellation::STDIO_SCOPE),
+            connection_namespace: Arc::from(store::DEFAULT_NAMESPACE),
+            connection_client: ClientContext::new(),
+            resources: ResourceRouter::new(),
+            prompts: PromptRegistry::new(),
+            maintenance: Arc::new(MaintenanceMode::new()),
+            limiter: Arc::new(CallLimiter::default()),
+            alerts: None,
+            fetcher: None,
+            tools_page_size: DEFAULT_TOOLS_PAGE_SIZE,
+            tool_switches: Arc::new(ToolSwitches::new()),
+            keys: None,
+            jwt: None,
+            oauth: None,
+            policy: None,
+            audit: None,
+            completion_providers: Vec::new(),
+            tool_router: Self::enabled_routes(Self::tool_router(), false, false),
+        }
+    }
+
+    /// A service configured from startup settings.
+    pub fn from_settings(settings: &Settings) -> Self {
+        let service = Self::new()
+            .with_slow_call_log(SlowCallLog::from_settings(&settings.slow_calls))
+            .with_kv_limits(settings.kv_limits)
+            .with_history_limits(settings.history_limits)
+            .with_note_limits(settings.note_limits)
+            .with_concurrency_limits(settings.concurrency)
+            .with_cache_limits(settings.cache_limits)
+            .with_tools_page_size(settings.tools_page_size)
+            .with_schedule(settings.schedule.clone());
+        let service = match &settings.alerts {
+            Some(config) => service.with_alerts(Arc::new(AlertEngine::new(config.clone()))),
+            None => service,
+        };
+        let service = match &settings.jwt {
+            Some(jwt) => service.with_jwt(Arc::new(JwtValidator::new(jwt.clone()))),
+            None => service,
+        };
+        let service = match &settings.oauth {
+            Some(oauth) => service.with_oauth(Arc::new(oauth.clone())),
+            None => service,
+        };
+        let service = match &settings.policy {
+            Some(policy) => service.with_policy(Arc::new(policy.clone())),
+            None => service,
+        };
+        let service = match &settings.docs_url {
+            Some(url) => match DocsPages::new(url.clone(), &settings.fetch) {
+                Ok(docs) => {
+                    tracing::info!(url = %url, "cedra-docs resources enabled");
+                    service.with_docs(docs.with_refresh(settings.docs_refresh))
+                }
+                Err(error) => {
+                    tracing::error!(%error, "cedra-docs resources stay disabled");
+                    service
+                }
+            },
+            None => service,
+        };
+        if !settings.fetch.enabled() {
+            return service;
+        }
+        match Fetcher::new(settings.fetch.clone()) {
+            Ok(fetcher) => {
+                tracing::info!(hosts = ?settings.fetch.allowed_hosts, "fetch_url enabled");
+                service.with_fetcher(fetcher)
+            }
+            Err(error) => {
+                tracing::error!(%error, "fetch_url stays disabled");
+                service
+            }
+        }
+    }
+
+    /// `router` without the tools only listed once enabled: `fetch_url`
+    /// needs a fetcher and `summarize_docs` the docs resources.
+    fn enabled_routes(mut router: ToolRouter<Self>, fetch: bool, docs: bool) -> ToolRouter<Self> {
+        if !fetch {
+            router.remove_route("fetch_url");
+        }
+        if !docs {
+            router.remove_route("summarize_docs");
+        }
+        router
+    }
+
+    /// Replace the default slow-call threshold and buffer size.
+    pub fn with_slow_call_log(mut self, slow_calls: SlowCallLog) -> Self {
+        self.slow_calls = Arc::new(slow_calls);
+        self
+    }
+
+    /// Keep tool state in `store` instead of in memory.
+    pub fn with_state_store(mut self, store: Arc<dyn StateStore>) -> Self {
+        self.state = store;
+        self
+    }
+
+    /// Accept the API keys issued in `keys` as well as the configured ones.
+    pub fn with_key_store(mut self, keys: Arc<KeyStore>) -> Self {
+        self.keys = Some(keys);
+        self
+    }
+
+    /// Record every tool call in `sink`.
+    pub fn with_audit_sink(mut self, sink: Arc<dyn AuditSink>) -> Self {
+        self.audit = Some(sink);
+        self
+    }
+
+    /// Let only the callers `policy` names use the tools it lists.
+    pub fn with_policy(mut self, policy: Arc<Policy>) -> Self {
+        let router = Self::tool_router();
+        for tool in policy.tools.keys().filter(|tool| !router.has_route(tool)) {
+            tracing::warn!(tool = %tool, "access policy names an unknown tool");
+        }
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Accept bearer JWTs that `validator` accepts.
+    pub fn with_jwt(mut self, validator: Arc<JwtValidator>) -> Self {
+        self.jwt = Some(validator);
+        self
+    }
+
+    /// Advertise `oauth` to OAuth clients and require its scopes of bearer
+    /// tokens.
+    pub fn with_oauth(mut self, oauth: Arc<OAuthSettings>) -> Self {
+        self.oauth = Some(oauth);
+        self
+    }
+
+    /// Replace the default per-namespace key-value limits.
+    pub fn with_kv_limits(mut self, limits: KvLimits) -> Self {
+        self.kv_limits = limits;
+        self
+    }
+
+    /// Replace the default per-namespace note limits.
+    pub fn with_note_limits(mut self, limits: NoteLimits) -> Self {
+        self.note_limits = limits;
+        self
+    }
+
+    /// Replace the default call history retention.
+    pub fn with_history_limits(mut self, limits: HistoryLimits) -> Self {
+        self.history_limits = limits;
+        self
+    }
+
+    /// Replace the default cap on concurrent and queued tool calls.
+    pub fn with_concurrency_limits(mut self, limits: ConcurrencyLimits) -> Self {
+        self.limiter = Arc::new(CallLimiter::new(limits));
+        self
+    }
+
+    /// Replace the default limits of caches created through
+    /// [`CacheRegistry::lru`]. Caches registered before this call are
+    /// dropped from the registry, so set limits first.
+    pub fn with_cache_limits(mut self, limits: CacheLimits) -> Self {
+        self.caches = Arc::new(
+            CacheRegistry::new()
+                .with_limits(limits)
+                .with_metrics(Arc::clone(&self.metrics)),
+        );
+        self
+    }
+
+    /// Split `tools/list` into pages of at most `size` tools.
+    pub fn with_tools_page_size(mut self, size: usize) -> Self {
+        self.tools_page_size = size.max(1);
+        self
+    }
+
+    /// Expose `jobs` as resources; [`crate::scheduler::spawn`] runs them.
+    pub fn with_schedule(mut self, jobs: Vec<ScheduledJob>) -> Self {
+        self.resources = self.resources.with_schedule(jobs);
+        self
+    }
+
+    /// Feed tool call outcomes to `alerts`.
+    pub fn with_alerts(mut self, alerts: Arc<AlertEngine>) -> Self {
+        self.alerts = Some(alerts);
+        self
+    }
+
+    /// Serve `cedra-docs://` resources from `docs`, and the prompts that
+    /// read them. Read pages are kept in the `cedra-docs` cache.
+    pub fn with_docs(mut self, docs: DocsPages) -> Self {
+        let docs = docs.with_cache(self.caches.lru(DOCS_CACHE));
+        self.resources = self.resources.with_docs(docs);
+        self.prompts.register(prompts::summarize_doc_page());
+        self.tool_router = Self::enabled_routes(Self::tool_router(), self.fetcher.is_some(), true);
+        self
+    }
+
+    /// Complete the arguments `provider` knows, ahead of the built-in
+    /// providers.
+    pub fn with_completion_provider(mut self, provider: Arc<dyn CompletionProvider>) -> Self {
+        self.completion_providers.push(provider);
+        self
+    }
+
+    /// Enable and list `fetch_url`, retrieving pages through `fetcher`.
+    pub fn with_fetcher(mut self, fetcher: Fetcher) -> Self {
+        self.fetcher = Some(fetcher);
+        self.tool_router =
+            Self::enabled_routes(Self::tool_router(), true, self.resources.has_docs());
+        self
+    }
+
+    /// A copy sharing everything but the connection rmcp serves: one with
+    /// session state `state`, whose requests are cancelled under `scope`
+    /// and keep key-value state in the namespace `session:{scope}`.
+    pub fn for_connection(&self, scope: &str, state: SessionState) -> Self {
         Self {
-            counter: Arc::new(Mutex::new(0)),
-            tool_router: Self::tool_router(),
+            connection_state: state,
+            connection_scope: Arc::from(scope),
+            connection_namespace: Arc::from(format!("session:{scope}")),
+            connection_client: ClientContext::new(),
+            ..self.clone()
         }
     }
 
+    /// Metrics registry shared by every clone of this service.
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Recently observed slow tool calls.
+    pub fn slow_calls(&self) -> &SlowCallLog {
+        &self.slow_calls
+    }
+
+    /// Streaming sessions currently connected to this service.
+    pub fn sessions(&self) -> &Arc<SessionRegistry> {
+        &self.sessions
+    }
+
+    /// Issued API keys, when a key file is configured.
+    pub fn key_store(&self) -> Option<&Arc<KeyStore>> {
+        self.keys.as_ref()
+    }
+
+    /// The bearer token validator, when JWTs are accepted.
+    pub fn jwt(&self) -> Option<&Arc<JwtValidator>> {
+        self.jwt.as_ref()
+    }
+
+    /// The OAuth resource this server is, if configured.
+    pub fn oauth(&self) -> Option<&OAuthSettings> {
+        self.oauth.as_deref()
+    }
+
+    /// The store behind stateful tools, for exports and imports.
+    pub fn state_store(&self) -> &Arc<dyn StateStore> {
+        &self.state
+    }
+
+    /// The per-namespace limits of stateful tools, which imports enforce too.
+    pub fn state_limits(&self) -> StateLimits {
+        StateLimits {
+            kv: self.kv_limits,
+            notes: self.note_limits,
+            history: self.history_limits,
+        }
+    }
+
+    /// Caches registered by tools, for admin inspection and flushing.
+    pub fn caches(&self) -> &Arc<CacheRegistry> {
+        &self.caches
+    }
+
+    /// Requests in flight on every transport, for `notifications/cancelled`.
+    pub fn cancellations(&self) -> &Arc<CancellationRegistry> {
+        &self.cancellations
+    }
+
+    /// Scheduled jobs and their latest results.
+    pub fn schedule(&self) -> &Arc<ScheduleBoard> {
+        self.resources.schedule()
+    }
+
+    /// The resources both transports serve.
+    pub fn resources(&self) -> &ResourceRouter {
+        &self.resources
+    }
+
+    /// The prompts both transports serve.
+    pub fn prompts(&self) -> &PromptRegistry {
+        &self.prompts
+    }
+
+    /// Maintenance switch consulted before every tool call.
+    pub fn maintenance(&self) -> &MaintenanceMode {
+        &self.maintenance
+    }
+
+    /// Alert engine, when alerting is configured.
+    pub fn alerts(&self) -> Option<&Arc<AlertEngine>> {
+        self.alerts.as_ref()
+    }
+
+    /// Whether `name` is annotated as not modifying any state.
+    pub fn is_read_only(&self, name: &str) -> bool {
+        self.tool_router
+            .map
+            .get(name)
+            .and_then(|route| route.attr.annotations.as_ref())
+            .and_then(|annotations| annotations.read_only_hint)
+            .unwrap_or(false)
+    }
+
     /// Return the list of tools this service exposes.
     pub fn available_tools(&self) -> Vec<Tool> {
+        let mut tools = self.tool_router.list_all();
+        tools.retain(|tool| self.tool_switches.is_enabled(&tool.name));
+        tools
+    }
+
+    /// Every tool this service has, enabled or not.
+    pub fn registered_tools(&self) -> Vec<Tool> {
         self.tool_router.list_all()
     }
 
+    /// Whether `name` is listed and callable; false for unknown tools.
+    pub fn is_tool_enabled(&self, name: &str) -> bool {
+        self.tool_router.has_route(name) && self.tool_switches.is_enabled(name)
+    }
+
+    /// Enable or disable tool `name` on every transport, telling connected
+    /// clients when the list changes. `None` for a tool that doesn't exist.
+    pub fn set_tool_enabled(&self, name: &str, enabled: bool) -> Option<bool> {
+        if !self.tool_router.has_route(name) {
+            return None;
+        }
+        let changed = self.tool_switches.set_enabled(name, enabled);
+        if changed {
+            tracing::info!(tool = name, enabled, "tool list changed");
+        }
+        Some(changed)
+    }
+
+    /// Runtime tool switches, whose changes transports announce.
+    pub fn tool_switches(&self) -> &Arc<ToolSwitches> {
+        &self.tool_switches
+    }
+
+    /// Answer `completion/complete` from the added providers, then the
+    /// resource router and the prompts.
+    pub fn complete(&self, request: &CompleteRequestParam) -> Result<CompleteResult, McpError> {
+        let mut providers: Vec<&dyn CompletionProvider> = self
+            .completion_providers
+            .iter()
+            .map(|provider| provider.as_ref())
+            .collect();
+        providers.push(&self.resources);
+        providers.push(&self.prompts);
+        completions::complete(&providers, request)
+    }
+
+    /// One `tools/list` page: the tools after `cursor` by name, and the
+    /// cursor of the next page if there is one. Cursors are opaque to
+    /// clients; one that was not handed out is `invalid_params`.
+    pub fn tools_page(
+        &self,
+        cursor: Option<&str>,
+    ) -> Result<(Vec<Tool>, Option<String>), McpError> {
+        let after = cursor
+            .map(|cursor| {
+                URL_SAFE_NO_PAD
+                    .decode(cursor)
+                    .ok()
+                    .and_then(|name| String::from_utf8(name).ok())
+                    .ok_or_else(|| McpError::invalid_params("Invalid cursor", None))
+            })
+            .transpose()?;
+        let mut tools = self.available_tools();
+        // Callers only see the tools the access policy lets them call.
+        if let (Some(policy), Some(auth)) = (&self.policy, auth::AuthContext::current()) {
+            tools.retain(|tool| policy.allows(&auth, &tool.name));
+        }
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut page: Vec<Tool> = tools
+            .into_iter()
+            .filter(|tool| after.as_deref().is_none_or(|after| *tool.name > *after))
+            .take(self.tools_page_size + 1)
+            .collect();
+        let next_cursor = if page.len() > self.tools_page_size {
+            page.truncate(self.tools_page_size);
+            page.last()
+                .map(|tool| URL_SAFE_NO_PAD.encode(tool.name.as_bytes()))
+        } else {
+            None
+        };
+        Ok((page, next_cursor))
+    }
+
     /// Get server info for initialization.
     pub fn get_server_info(&self) -> ServerInfo {
         self.get_info()
     }
 
+    /// The answer to `initialize` for a client that asked for `requested`,
+    /// or for the newest version when it named none.
+    pub fn initialize_result(&self, requested: Option<&str>) -> Result<InitializeResult, McpError> {
+        let info = self.get_info();
+        let protocol_version = match requested {
+            Some(requested) => version::negotiate_protocol_version(requested)?,
+            None => info.protocol_version.clone(),
+        };
+        Ok(InitializeResult {
+            protocol_version,
+            ..info
+        })
+    }
+
     /// Call a tool by name with the provided arguments.
+    ///
+    /// Every transport funnels through here, so this is where per-call
+    /// telemetry is emitted.
     pub async fn call_tool(
         &self,
         name: &str,
         arguments: serde_json::Value,
+    ) -> Result<CallToolResult, McpError> {
+        // Unknown names are folded into one label to keep metric cardinality bounded.
+        let label = if self.is_tool_enabled(name) {
+            name
+        } else {
+            "unknown"
+        };
+        let in_flight = self.metrics.tool_started(label);
+        let started = Instant::now();
+        let ((mut result, upstream), output) =
+            progress::collect_output(crash_report::with_tool_context(
+                name,
+                slow_calls::collect_upstream_timings(async {
+                    if !self.tool_switches.is_enabled(name) {
+                        return Err(McpError::invalid_params(
+                            format!("Tool '{name}' is disabled"),
+                            None,
+                        ));
+                    }
+                    auth::check_tool(name, self.policy.as_deref())?;
+                    self.maintenance.check(name, self.is_read_only(name))?;
+                    let _session = self
+                        .limiter
+                        .admit_session(SessionState::current().as_ref(), &self.metrics)?;
+                    let _permit = self.limiter.acquire(&self.metrics).await?;
+                    self.dispatch_tool(name, &arguments).await
+                }),
+            ))
+            .await;
+        let elapsed = started.elapsed();
+        drop(in_flight);
+        // Output that could not be streamed leads the result instead.
+        if let (Ok(result), false) = (&mut result, output.is_empty()) {
+            result.content.insert(0, Content::text(output.concat()));
+        }
+
+        let (outcome, error_class) = match &result {
+            Ok(result) if result.is_error == Some(true) => ("tool_error", Some("tool_error")),
+            Ok(_) => ("success", None),
+            Err(error) => ("error", Some(metrics::error_class(error))),
+        };
+        self.metrics
+            .record_tool_call(label, elapsed, outcome, error_class);
+        if let Some(alerts) = &self.alerts {
+            // Only failures on our side count towards the error rate; bad
+            // requests, maintenance and busy rejections are not degradation.
+            alerts.record_tool_call(matches!(
+                error_class,
+                Some("tool_error" | "internal_error" | "other")
+            ));
+        }
+        tracing::info!(
+            tool = name,
+            duration_ms = elapsed.as_millis() as u64,
+            outcome,
+            "tool call completed"
+        );
+        self.slow_calls
+            .observe(label, &arguments, elapsed, outcome, upstream);
+        if let Some(audit) = &self.audit {
+            let session = SessionState::current();
+            let record = AuditRecord {
+                duration_ms: elapsed.as_millis() as u64,
+                outcome: outcome.to_string(),
+                error_class: error_class.map(str::to_string),
+                ..AuditRecord::new(
+                    name,
+                    arguments.clone(),
+                    session.as_ref().and_then(SessionState::id),
+                    auth::AuthContext::current().as_ref(),
+                )
+            };
+            // A lost record must not fail a call that already ran.
+            if let Err(error) = audit.append(&record) {
+                tracing::error!(%error, tool = name, "audit record lost");
+            }
+        }
+        // Reading the history is not itself history.
+        if label != "unknown" && name != "call_history" && self.history_limits.max_entries > 0 {
+            self.record_history(name, arguments, &result, outcome).await;
+        }
+        result
+    }
+
+    /// Append a redacted summary of a call to the caller's history.
+    async fn record_history(
+        &self,
+        tool: &str,
+        mut arguments: serde_json::Value,
+        result: &Result<CallToolResult, McpError>,
+        outcome: &str,
+    ) {
+        recording::redact(&mut arguments);
+        let text = match result {
+            Ok(result) => result
+                .content
+                .iter()
+                .find_map(|content| match &content.raw {
+                    RawContent::Text(text) => Some(text.text.clone()),
+                    _ => None,
+                })
+                .unwrap_or_default(),
+            Err(error) => error.message.to_string(),
+        };
+        let mut summary = serde_json::Value::String(text);
+        recording::redact(&mut summary);
+        let summary = summary.as_str().unwrap_or_default();
+        let now = Utc::now().timestamp_millis();
+        let entry = HistoryEntry {
+            at: now,
+            tool: tool.to_string(),
+            arguments: arguments.to_string(),
+            outcome: outcome.to_string(),
+            summary: match summary.char_indices().nth(MAX_HISTORY_SUMMARY_CHARS) {
+                Some((end, _)) => format!("{}…", &summary[..end]),
+                None => summary.to_string(),
+            },
+        };
+        let namespace = store::current_namespace();
+        let limits = self.history_limits;
+        let appended = self
+            .with_state(move |history| {
+                history.append(&namespace, entry, limits.max_entries, limits.min_at(now))
+            })
+            .await;
+        if let Err(error) = appended {
+            tracing::warn!(error = %error.message, "failed to record call history");
+        }
+    }
+
+    async fn dispatch_tool(
+        &self,
+        name: &str,
+        arguments: &serde_json::Value,
     ) -> Result<CallToolResult, McpError> {
         match name {
             "echo" => {
-                let args: EchoArgs = serde_json::from_value(arguments)
+                let args = EchoArgs::deserialize(arguments)
                     .map_err(|_| McpError::invalid_params("Invalid echo arguments", None))?;
                 self.echo(Parameters(args)).await
             }
             "reverse_text" => {
-                let args: ReverseArgs = serde_json::from_value(arguments).map_err(|_| {
+                let args = ReverseArgs::deserialize(arguments).map_err(|_| {
                     McpError::invalid_params("Invalid reverse_text arguments", None)
                 })?;
                 self.reverse_text(Parameters(args)).await
             }
             "increment" => self.increment().await,
-            "current_time" => self.current_time().await,
+            "calculate" => {
+                let args = CalculateArgs::deserialize(arguments).map_err(|error| {
+                    McpError::invalid_params(format!("Invalid calculate arguments: {error}"), None)
+                })?;
+                self.calculate(Parameters(args)).await
+            }
+            "json_query" => {
+                let args = JsonQueryArgs::deserialize(arguments).map_err(|error| {
+                    McpError::invalid_params(format!("Invalid json_query arguments: {error}"), None)
+                })?;
+                self.json_query(Parameters(args)).await
+            }
+            "codec" => {
+                let args = CodecArgs::deserialize(arguments).map_err(|error| {
+                    McpError::invalid_params(format!("Invalid codec arguments: {error}"), None)
+                })?;
+                self.codec(Parameters(args)).await
+            }
+            "counter" => {
+                let args = CounterArgs::deserialize(arguments).map_err(|error| {
+                    McpError::invalid_params(format!("Invalid counter arguments: {error}"), None)
+                })?;
+                self.counter(Parameters(args)).await
+            }
+            "server_version" => self.server_version().await,
+            "current_time" => {
+                let args = CurrentTimeArgs::deserialize(arguments).map_err(|error| {
+                    McpError::invalid_params(
+                        format!("Invalid current_time arguments: {error}"),
+                        None,
+                    )
+                })?;
+                self.current_time(Parameters(args)).await
+            }
+            "convert_time" => {
+                let args = ConvertTimeArgs::deserialize(arguments).map_err(|error| {
+                    McpError::invalid_params(
+                        format!("Invalid convert_time arguments: {error}"),
+                        None,
+                    )
+                })?;
+                self.convert_time(Parameters(args)).await
+            }
             "roll_dice" => {
-                let args: DiceArgs = serde_json::from_value(arguments)
-                    .map_err(|_| McpError::invalid_params("Invalid roll_dice arguments", None))?;
+                let args = DiceArgs::deserialize(arguments).map_err(|error| {
+                    McpError::invalid_params(format!("Invalid roll_dice arguments: {error}"), None)
+                })?;
                 self.roll_dice(Parameters(args)).await
             }
+            "fetch_url" => {
+                let args = FetchUrlArgs::deserialize(arguments).map_err(|error| {
+                    McpError::invalid_params(format!("Invalid fetch_url arguments: {error}"), None)
+                })?;
+                self.fetch_url(Parameters(args)).await
+            }
+            "summarize_docs" => {
+                let args = SummarizeDocsArgs::deserialize(arguments).map_err(|error| {
+                    McpError::invalid_params(
+                        format!("Invalid summarize_docs arguments: {error}"),
+                        None,
+                    )
+                })?;
+                self.summarize_docs(Parameters(args)).await
+            }
+            "kv_set" => {
+                let args = KvSetArgs::deserialize(arguments).map_err(|error| {
+                    McpError::invalid_params(format!("Invalid kv_set arguments: {error}"), None)
+                })?;
+                self.kv_set(Parameters(args)).await
+            }
+            "kv_get" => {
+                let args = KvKeyArgs::deserialize(arguments).map_err(|error| {
+                    McpError::invalid_params(format!("Invalid kv_get arguments: {error}"), None)
+                })?;
+                self.kv_get(Parameters(args)).await
+            }
+            "kv_delete" => {
+                let args = KvKeyArgs::deserialize(arguments).map_err(|error| {
+                    McpError::invalid_params(format!("Invalid kv_delete arguments: {error}"), None)
+                })?;
+                self.kv_delete(Parameters(args)).await
+            }
+            "kv_list" => {
+                let args = KvListArgs::deserialize(arguments).map_err(|error| {
+                    McpError::invalid_params(format!("Invalid kv_list arguments: {error}"), None)
+                })?;
+                self.kv_list(Parameters(args)).await
+            }
+            "note_add" => {
+                let args = NoteAddArgs::deserialize(arguments).map_err(|error| {
+                    McpError::invalid_params(format!("Invalid note_add arguments: {error}"), None)
+                })?;
+                self.note_add(Parameters(args)).await
+            }
+            "note_search" => {
+                let args = NoteSearchArgs::deserialize(arguments).map_err(|error| {
+                    McpError::invalid_params(
+                        format!("Invalid note_search arguments: {error}"),
+                        None,
+                    )
+                })?;
+                self.note_search(Parameters(args)).await
+            }
+            "note_get" => {
+                let args = NoteGetArgs::deserialize(arguments).map_err(|error| {
+                    McpError::invalid_params(format!("Invalid note_get arguments: {error}"), None)
+                })?;
+                self.note_get(Parameters(args)).await
+            }
+            "call_history" => {
+                let args = CallHistoryArgs::deserialize(arguments).map_err(|error| {
+                    McpError::invalid_params(
+                        format!("Invalid call_history arguments: {error}"),
+                        None,
+                    )
+                })?;
+                self.call_history(Parameters(args)).await
+            }
             _ => Err(McpError::invalid_params("Tool not found", None)),
         }
     }
 }
 
+/// Session state key of the `increment` counter.
+const INCREMENT_KEY: &str = "increment.count";
+/// Default of `INFERENCO_MCP_TOOLS_PAGE_SIZE`.
+pub const DEFAULT_TOOLS_PAGE_SIZE: usize = 100;
+/// Longest accepted counter name.
+const MAX_COUNTER_NAME: usize = 128;
+/// Result text kept per call history entry, in characters.
+const MAX_HISTORY_SUMMARY_CHARS: usize = 200;
+/// Call history entries returned when no limit is given, and the cap on one.
+const DEFAULT_HISTORY_LIMIT: u32 = 20;
+const MAX_HISTORY_LIMIT: u32 = 100;
+/// Longest accepted key-value key, in bytes.
+const MAX_KV_KEY_BYTES: usize = 256;
+
+fn validate_kv_key(key: &str) -> Result<(), McpError> {
+    if key.is_empty() || key.len() > MAX_KV_KEY_BYTES {
+        return Err(McpError::invalid_params(
+            format!("key must be 1 to {MAX_KV_KEY_BYTES} bytes"),
+            None,
+        ));
+    }
+    Ok(())
+}
+
+/// Largest document `json_query` takes, serialized, in bytes.
+const MAX_JSON_DOCUMENT_BYTES: usize = 1024 * 1024;
+/// Matches or differences `json_query` returns before truncating.
+const MAX_JSON_QUERY_RESULTS: usize = 500;
+
+/// Unwrap a document passed as JSON text and enforce the size limit.
+fn json_document(value: serde_json::Value, field: &str) -> Result<serde_json::Value, McpError> {
+    let value = match value {
+        serde_json::Value::String(text) if text.trim_start().starts_with(['{', '[']) => {
+            serde_json::from_str(&text).map_err(|error| {
+                McpError::invalid_params(format!("{field} is not valid JSON: {error}"), None)
+            })?
+        }
+        value => value,
+    };
+    let size = value.to_string().len();
+    if size > MAX_JSON_DOCUMENT_BYTES {
+        return Err(McpError::invalid_params(
+            format!("{field} is {size} bytes; the limit is {MAX_JSON_DOCUMENT_BYTES}"),
+            None,
+        ));
+    }
+    Ok(value)
+}
+
+/// The named IANA zone, or UTC when none is given.
+fn optional_zone(name: Option<&str>) -> Result<chrono_tz::Tz, McpError> {
+    name.map_or(Ok(chrono_tz::Tz::UTC), |name| {
+        clock::parse_zone(name).map_err(|error| McpError::invalid_params(error, None))
+    })
+}
+
+/// Summary length `summarize_docs` asks for when none is given, and the cap
+/// on one, in words.
+const DEFAULT_SUMMARY_WORDS: u32 = 150;
+const MAX_SUMMARY_WORDS: u32 = 1000;
+
+/// Most rolls one `roll_dice` call makes.
+const MAX_DICE_ROLLS: u32 = 100;
+
+/// Longest `codec` input, in bytes.
+const MAX_CODEC_INPUT_BYTES: usize = 1024 * 1024;
+
+/// Tags accepted per note, and the longest tag in bytes.
+const MAX_NOTE_TAGS: usize = 16;
+const MAX_NOTE_TAG_BYTES: usize = 64;
+/// Notes returned by a search when no limit is given, and the cap on one.
+const DEFAULT_NOTE_LIMIT: u32 = 10;
+const MAX_NOTE_LIMIT: u32 = 50;
+
+/// Trim, lowercase, and deduplicate tags, rejecting empty or oversized ones.
+fn normalize_tags(tags: Vec<String>) -> Result<Vec<String>, McpError> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if tag.is_empty() || tag.len() > MAX_NOTE_TAG_BYTES {
+            return Err(McpError::invalid_params(
+                format!("tags must be 1 to {MAX_NOTE_TAG_BYTES} bytes"),
+                None,
+            ));
+        }
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    if normalized.len() > MAX_NOTE_TAGS {
+        return Err(McpError::invalid_params(
+            format!("a note takes at most {MAX_NOTE_TAGS} tags"),
+            None,
+        ));
+    }
+    Ok(normalized)
+}
+
+/// Lowercased alphanumeric words of `text`.
+fn search_words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// How many words of the note's text and tags start with one of `terms`, or
+/// `None` when some term matches no word at all.
+fn note_score(note: &Note, terms: &[String]) -> Option<usize> {
+    let words: Vec<String> = search_words(&note.text)
+        .chain(note.tags.iter().flat_map(|tag| search_words(tag)))
+        .collect();
+    terms.iter().try_fold(0, |score, term| {
+        let hits = words.iter().filter(|word| word.starts_with(term)).count();
+        (hits > 0).then_some(score + hits)
+    })
+}
+
+fn note_json(note: Note) -> serde_json::Value {
+    serde_json::json!({
+        "id": note.id,
+        "text": note.text,
+        "tags": note.tags,
+        "created_at": chrono::DateTime::from_timestamp_millis(note.created_at),
+    })
+}
+
+fn json_result(value: serde_json::Value) -> CallToolResult {
+    CallToolResult::success(vec![Content::text(value.to_string())])
+}
+
+/// `value` as the `structuredContent` of a tool declaring its type as
+/// `output_schema`, and as JSON text for clients that only read content.
+fn structured_result<T: serde::Serialize>(value: &T) -> CallToolResult {
+    CallToolResult::structured(serde_json::json!(value))
+}
+
+impl ToolService {
+    /// Run `operation` against the state store off the async runtime, since
+    /// persistent backends block on disk.
+    async fn with_state<T: Send + 'static>(
+        &self,
+        operation: impl FnOnce(&dyn StateStore) -> Result<T, String> + Send + 'static,
+    ) -> Result<T, McpError> {
+        let state = self.state.clone();
+        tokio::task::spawn_blocking(move || operation(state.as_ref()))
+            .await
+            .map_err(|error| McpError::internal_error(error.to_string(), None))?
+            .map_err(|error| McpError::internal_error(error, None))
+    }
+}
+
 impl Default for ToolService {
     fn default() -> Self {
         Self::new()
@@ -74,7 +980,10 @@ impl Default for ToolService {
 
 #[tool_router(vis = "pub")]
 impl ToolService {
-    #[tool(description = "Echo back the provided message.")]
+    #[tool(
+        description = "Echo back the provided message.",
+        annotations(read_only_hint = true)
+    )]
     pub async fn echo(
         &self,
         Parameters(args): Parameters<EchoArgs>,
@@ -82,7 +991,10 @@ impl ToolService {
         Ok(CallToolResult::success(vec![Content::text(args.message)]))
     }
 
-    #[tool(description = "Reverse a piece of text.")]
+    #[tool(
+        description = "Reverse a piece of text.",
+        annotations(read_only_hint = true)
+    )]
     pub async fn reverse_text(
         &self,
         Parameters(args): Parameters<ReverseArgs>,
@@ -91,51 +1003,974 @@ impl ToolService {
         Ok(CallToolResult::success(vec![Content::text(reversed)]))
     }
 
-    #[tool(description = "Increment an in-memory counter and return the new value.")]
+    #[tool(
+        description = "Increment this session's counter and return the new value.",
+        annotations(read_only_hint = false)
+    )]
     pub async fn increment(&self) -> Result<CallToolResult, McpError> {
-        let mut counter = self.counter.lock().await;
-        *counter += 1;
+        // Each session counts on its own; calls outside one share the
+        // stored counter named "increment".
+        let value = match SessionState::current() {
+            Some(session) => session
+                .update(INCREMENT_KEY, |value: Option<i64>| {
+                    value.unwrap_or(0).saturating_add(1)
+                })
+                .map_err(|error| McpError::internal_error(error, None))?,
+            None => {
+                self.with_state(|counters| counters.add("increment", 1))
+                    .await?
+            }
+        };
         Ok(CallToolResult::success(vec![Content::text(
-            counter.to_string(),
+            value.to_string(),
         )]))
     }
 
-    #[tool(description = "Return the current UTC time in RFC3339 format.")]
-    pub async fn current_time(&self) -> Result<CallToolResult, McpError> {
+    #[tool(
+        description = "Get, increment, decrement, add to, or reset a named counter, or list every counter. Each operation is atomic. Counters are shared by every client; the counter named \"increment\" is the one `increment` uses outside a session.",
+        annotations(read_only_hint = false)
+    )]
+    pub async fn counter(
+        &self,
+        Parameters(args): Parameters<CounterArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        if args.op == CounterOp::List {
+            let counters = self.with_state(|counters| counters.list()).await?;
+            let counters: serde_json::Map<String, serde_json::Value> = counters
+                .into_iter()
+                .map(|(name, value)| (name, value.into()))
+                .collect();
+            return Ok(CallToolResult::success(vec![Content::text(
+                serde_json::json!({ "counters": counters }).to_string(),
+            )]));
+        }
+
+        let name = match args.name.as_deref().map(str::trim) {
+            Some(name) if !name.is_empty() && name.chars().count() <= MAX_COUNTER_NAME => {
+                name.to_string()
+            }
+            Some(name) if !name.is_empty() => {
+                return Err(McpError::invalid_params(
+                    format!("counter names are limited to {MAX_COUNTER_NAME} characters"),
+                    None,
+                ));
+            }
+            _ => {
+                return Err(McpError::invalid_params(
+                    "name is required unless op is 'list'",
+                    None,
+                ));
+            }
+        };
+        let delta = match args.op {
+            CounterOp::Increment => Some(1),
+            CounterOp::Decrement => Some(-1),
+            CounterOp::Add => Some(args.amount.ok_or_else(|| {
+                McpError::invalid_params("amount is required when op is 'add'", None)
+            })?),
+            CounterOp::Get | CounterOp::Reset | CounterOp::List => None,
+        };
+        let reset = args.op == CounterOp::Reset;
+        let key = name.clone();
+        let value = self
+            .with_state(move |counters| match delta {
+                Some(delta) => counters.add(&key, delta),
+                None if reset => counters.reset(&key).map(|()| 0),
+                None => counters.get(&key),
+            })
+            .await?;
         Ok(CallToolResult::success(vec![Content::text(
-            Utc::now().to_rfc3339(),
+            serde_json::json!({ "name": name, "value": value }).to_string(),
         )]))
     }
 
-    #[tool(description = "Roll a die with the provided number of sides (defaults to six-sided).")]
+    #[tool(
+        description = "Store a JSON value under a key, optionally expiring after ttl_seconds. Keys are private to the caller's API key or session.",
+        annotations(read_only_hint = false)
+    )]
+    pub async fn kv_set(
+        &self,
+        Parameters(args): Parameters<KvSetArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        validate_kv_key(&args.key)?;
+        let value = args.value.to_string();
+        let limits = self.kv_limits;
+        if value.len() > limits.max_value_bytes {
+            return Err(McpError::invalid_params(
+                format!(
+                    "value is {} bytes; the limit is {}",
+                    value.len(),
+                    limits.max_value_bytes
+                ),
+                None,
+            ));
+        }
+        let now = Utc::now().timestamp_millis();
+        let expires_at = match args.ttl_seconds {
+            Some(0) => {
+                return Err(McpError::invalid_params(
+                    "ttl_seconds must be positive",
+                    None,
+                ))
+            }
+            Some(ttl) => Some(
+                now.saturating_add(i64::try_from(ttl.saturating_mul(1000)).unwrap_or(i64::MAX)),
+            ),
+            None => None,
+        };
+        let namespace = store::current_namespace();
+        let key = args.key.clone();
+        let stored = self
+            .with_state(move |kv| {
+                kv.set(
+                    &namespace,
+                    &key,
+                    KvEntry { value, expires_at },
+                    limits.max_keys,
+                    now,
+                )
+            })
+            .await?;
+        if !stored {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "already storing the maximum of {} keys; delete some first",
+                limits.max_keys
+            ))]));
+        }
+        Ok(json_result(serde_json::json!({
+            "key": args.key,
+            "expires_at": expires_at.and_then(chrono::DateTime::from_timestamp_millis),
+        })))
+    }
+
+    #[tool(
+        description = "Read the value stored under a key by kv_set.",
+        annotations(read_only_hint = true)
+    )]
+    pub async fn kv_get(
+        &self,
+        Parameters(args): Parameters<KvKeyArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        validate_kv_key(&args.key)?;
+        let namespace = store::current_namespace();
+        let now = Utc::now().timestamp_millis();
+        let key = args.key.clone();
+        let entry = self
+            .with_state(move |kv| kv.lookup(&namespace, &key, now))
+            .await?;
+        Ok(json_result(match entry {
+            Some(entry) => serde_json::json!({
+                "key": args.key,
+                "found": true,
+                "value": serde_json::from_str::<serde_json::Value>(&entry.value)
+                    .unwrap_or(serde_json::Value::String(entry.value)),
+                "expires_at": entry.expires_at.and_then(chrono::DateTime::from_timestamp_millis),
+            }),
+            None => serde_json::json!({ "key": args.key, "found": false }),
+        }))
+    }
+
+    #[tool(
+        description = "Delete a key stored by kv_set.",
+        annotations(read_only_hint = false)
+    )]
+    pub async fn kv_delete(
+        &self,
+        Parameters(args): Parameters<KvKeyArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        validate_kv_key(&args.key)?;
+        let namespace = store::current_namespace();
+        let now = Utc::now().timestamp_millis();
+        let key = args.key.clone();
+        let deleted = self
+            .with_state(move |kv| kv.delete(&namespace, &key, now))
+            .await?;
+        Ok(json_result(
+            serde_json::json!({ "key": args.key, "deleted": deleted }),
+        ))
+    }
+
+    #[tool(
+        description = "List the keys stored by kv_set, optionally only those starting with a prefix.",
+        annotations(read_only_hint = true)
+    )]
+    pub async fn kv_list(
+        &self,
+        Parameters(args): Parameters<KvListArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let namespace = store::current_namespace();
+        let now = Utc::now().timestamp_millis();
+        let prefix = args.prefix.unwrap_or_default();
+        let keys = self
+            .with_state(move |kv| kv.keys(&namespace, &prefix, now))
+            .await?;
+        Ok(json_result(serde_json::json!({ "keys": keys })))
+    }
+
+    #[tool(
+        description = "Save a short note with optional tags for later note_search/note_get. Notes are private to the caller's API key or session.",
+        annotations(read_only_hint = false)
+    )]
+    pub async fn note_add(
+        &self,
+        Parameters(args): Parameters<NoteAddArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let limits = self.note_limits;
+        let text = args.text.trim().to_string();
+        if text.is_empty() || text.len() > limits.max_note_bytes {
+            return Err(McpError::invalid_params(
+                format!("text must be 1 to {} bytes", limits.max_note_bytes),
+                None,
+            ));
+        }
+        let tags = normalize_tags(args.tags)?;
+        let namespace = store::current_namespace();
+        let now = Utc::now().timestamp_millis();
+        let note = self
+            .with_state(move |notes| {
+                notes.add_note(&namespace, &text, &tags, now, limits.max_notes)
+            })
+            .await?;
+        match note {
+            Some(note) => Ok(json_result(note_json(note))),
+            None => Ok(CallToolResult::error(vec![Content::text(format!(
+                "already storing the maximum of {} notes",
+                limits.max_notes
+            ))])),
+        }
+    }
+
+    #[tool(
+        description = "Search your notes by words in their text and tags, optionally only those with a tag. Best matches first, then newest.",
+        annotations(read_only_hint = true)
+    )]
+    pub async fn note_search(
+        &self,
+        Parameters(args): Parameters<NoteSearchArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let limit = args
+            .limit
+            .unwrap_or(DEFAULT_NOTE_LIMIT)
+            .clamp(1, MAX_NOTE_LIMIT) as usize;
+        let terms: Vec<String> = search_words(args.query.as_deref().unwrap_or_default()).collect();
+        let tag = args.tag.map(|tag| tag.trim().to_lowercase());
+        let namespace = store::current_namespace();
+        let notes = self
+            .with_state(move |notes| notes.notes(&namespace))
+            .await?;
+        let mut matches: Vec<(usize, Note)> = notes
+            .into_iter()
+            .filter(|note| tag.as_ref().is_none_or(|tag| note.tags.contains(tag)))
+            .filter_map(|note| note_score(&note, &terms).map(|score| (score, note)))
+            .collect();
+        matches.sort_by(|(a_score, a), (b_score, b)| {
+            b_score.cmp(a_score).then_with(|| b.id.cmp(&a.id))
+        });
+        let total = matches.len();
+        let notes: Vec<serde_json::Value> = matches
+            .into_iter()
+            .take(limit)
+            .map(|(_, note)| note_json(note))
+            .collect();
+        Ok(json_result(
+            serde_json::json!({ "notes": notes, "total": total }),
+        ))
+    }
+
+    #[tool(
+        description = "Read one of your notes by the id note_add or note_search returned.",
+        annotations(read_only_hint = true)
+    )]
+    pub async fn note_get(
+        &self,
+        Parameters(args): Parameters<NoteGetArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let namespace = store::current_namespace();
+        let id = args.id;
+        let note = self
+            .with_state(move |notes| notes.note(&namespace, id))
+            .await?;
+        Ok(json_result(match note {
+            Some(note) => {
+                let mut found = note_json(note);
+                found["found"] = true.into();
+                found
+            }
+            None => serde_json::json!({ "id": id, "found": false }),
+        }))
+    }
+
+    #[tool(
+        description = "List your most recent tool calls, newest first, with redacted arguments and the start of each result. Useful to check what was already looked up.",
+        annotations(read_only_hint = true)
+    )]
+    pub async fn call_history(
+        &self,
+        Parameters(args): Parameters<CallHistoryArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let limit = args
+            .limit
+            .unwrap_or(DEFAULT_HISTORY_LIMIT)
+            .clamp(1, MAX_HISTORY_LIMIT) as usize;
+        let namespace = store::current_namespace();
+        let min_at = self.history_limits.min_at(Utc::now().timestamp_millis());
+        let calls = self
+            .with_state(move |history| {
+                history.recent(&namespace, args.tool.as_deref(), limit, min_at)
+            })
+            .await?;
+        let calls: Vec<serde_json::Value> = calls
+            .into_iter()
+            .map(|call| {
+                serde_json::json!({
+                    "at": chrono::DateTime::from_timestamp_millis(call.at),
+                    "tool": call.tool,
+                    "arguments": serde_json::from_str::<serde_json::Value>(&call.arguments)
+                        .unwrap_or(serde_json::Value::String(call.arguments)),
+                    "outcome": call.outcome,
+                    "summary": call.summary,
+                })
+            })
+            .collect();
+        Ok(json_result(serde_json::json!({ "calls": calls })))
+    }
+
+    #[tool(
+        description = "Return the current time in RFC3339 format, in UTC unless an IANA timezone is given, or rendered with a strftime format.",
+        annotations(read_only_hint = true),
+        output_schema = cached_schema_for_type::<CurrentTime>()
+    )]
+    pub async fn current_time(
+        &self,
+        Parameters(args): Parameters<CurrentTimeArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let now = Utc::now();
+        let zone = optional_zone(args.timezone.as_deref())?;
+        let local = now.with_timezone(&zone);
+        let formatted = args
+            .format
+            .as_deref()
+            .map(|format| clock::format_time(&local, format))
+            .transpose()
+            .map_err(|error| McpError::invalid_params(error, None))?;
+        let time = CurrentTime {
+            iso8601: local.to_rfc3339(),
+            unix: now.timestamp(),
+            timezone: zone.name().to_string(),
+            formatted,
+        };
+        // The text stays the bare time clients have always parsed.
+        let text = time
+            .formatted
+            .clone()
+            .unwrap_or_else(|| time.iso8601.clone());
+        Ok(CallToolResult {
+            content: vec![Content::text(text)],
+            ..structured_result(&time)
+        })
+    }
+
+    #[tool(
+        description = "Convert a time between epochs, RFC3339, and IANA timezones. Accepts RFC3339, `YYYY-MM-DD HH:MM`, Unix seconds or milliseconds, and relative expressions like `3 hours ago`; returns it in the target zone with epoch values, a relative description, and an optional strftime rendering.",
+        annotations(read_only_hint = true),
+        output_schema = cached_schema_for_type::<clock::Conversion>()
+    )]
+    pub async fn convert_time(
+        &self,
+        Parameters(args): Parameters<ConvertTimeArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let invalid = |error: String| McpError::invalid_params(error, None);
+        let now = Utc::now();
+        let from = optional_zone(args.from_timezone.as_deref())?;
+        let zone = optional_zone(args.timezone.as_deref())?;
+        let time = clock::parse_time(&args.time, from, args.input_format.as_deref(), now)
+            .map_err(invalid)?;
+        let conversion =
+            clock::convert(time, zone, args.format.as_deref(), now).map_err(invalid)?;
+        Ok(structured_result(&conversion))
+    }
+
+    #[tool(
+        description = "Report the server's version, git commit, build time, enabled features, and protocol versions.",
+        annotations(read_only_hint = true)
+    )]
+    pub async fn server_version(&self) -> Result<CallToolResult, McpError> {
+        let info = serde_json::to_string_pretty(&version::build_info())
+            .map_err(|error| McpError::internal_error(error.to_string(), None))?;
+        Ok(CallToolResult::success(vec![Content::text(info)]))
+    }
+
+    #[tool(
+        description = "Evaluate arithmetic exactly in decimal: + - * / ^ and parentheses, plus percentages (`200 + 10%` is 220, `15% of 80` is 12). Use this rather than doing arithmetic yourself.",
+        annotations(read_only_hint = true)
+    )]
+    pub async fn calculate(
+        &self,
+        Parameters(args): Parameters<CalculateArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let value = calc::evaluate(&args.expression)
+            .map_err(|error| McpError::invalid_params(error, None))?;
+        Ok(json_result(serde_json::json!({
+            "expression": args.expression,
+            "result": value.to_string(),
+        })))
+    }
+
+    #[tool(
+        description = "Select values from a JSON document with a JSONPath query (`$.items[*].name`, `$..id`, `$.items[?(@.price < 10)]`), or with mode `diff` list what changed between `document` and `other` as JSON Pointers.",
+        annotations(read_only_hint = true)
+    )]
+    pub async fn json_query(
+        &self,
+        Parameters(args): Parameters<JsonQueryArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let document = json_document(args.document, "document")?;
+        match args.mode {
+            JsonQueryMode::Query => {
+                let query = args.query.ok_or_else(|| {
+                    McpError::invalid_params("query is required for mode query", None)
+                })?;
+                // A broad query over a large document can take a while.
+                let (query, found) = tokio::task::spawn_blocking(move || {
+                    let found = json_query::query(&document, &query, MAX_JSON_QUERY_RESULTS);
+                    (query, found)
+                })
+                .await
+                .map_err(|error| McpError::internal_error(error.to_string(), None))?;
+                let (matches, truncated) =
+                    found.map_err(|error| McpError::invalid_params(error, None))?;
+                Ok(json_result(serde_json::json!({
+                    "query": query,
+                    "count": matches.len(),
+                    "truncated": truncated,
+                    "matches": matches,
+                })))
+            }
+            JsonQueryMode::Diff => {
+                let other = args.other.ok_or_else(|| {
+                    McpError::invalid_params("other is required for mode diff", None)
+                })?;
+                let other = json_document(other, "other")?;
+                // Two documents of up to 1 MiB each are a long walk too.
+                let (differences, truncated) = tokio::task::spawn_blocking(move || {
+                    json_query::diff(&document, &other, MAX_JSON_QUERY_RESULTS)
+                })
+                .await
+                .map_err(|error| McpError::internal_error(error.to_string(), None))?;
+                Ok(json_result(serde_json::json!({
+                    "equal": differences.is_empty(),
+                    "truncated": truncated,
+                    "differences": differences,
+                })))
+            }
+        }
+    }
+
+    #[tool(
+        description = "Encode or decode base64, hex, and URL percent-encoding, or hash with SHA-256 or Keccak-256 (Ethereum). Set input_format to hex or base64 to encode or hash raw bytes instead of text.",
+        annotations(read_only_hint = true)
+    )]
+    pub async fn codec(
+        &self,
+        Parameters(args): Parameters<CodecArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        if args.input.len() > MAX_CODEC_INPUT_BYTES {
+            return Err(McpError::invalid_params(
+                format!(
+                    "input is {} bytes; the limit is {MAX_CODEC_INPUT_BYTES}",
+                    args.input.len()
+                ),
+                None,
+            ));
+        }
+        let invalid = |error: String| McpError::invalid_params(error, None);
+        let input = || match args.input_format {
+            CodecInputFormat::Text => Ok(args.input.as_bytes().to_vec()),
+            CodecInputFormat::Hex => codec::decode_hex(&args.input).map_err(invalid),
+            CodecInputFormat::Base64 => codec::decode_base64(&args.input).map_err(invalid),
+        };
+        // Decoded bytes come back as text when they are UTF-8, else as hex.
+        let decoded = |bytes: Vec<u8>| {
+            let len = bytes.len();
+            match String::from_utf8(bytes) {
+                Ok(text) => (text, "text", len),
+                Err(error) => (codec::encode_hex(error.as_bytes()), "hex", len),
+            }
+        };
+        let (output, output_format, bytes) = match args.op {
+            CodecOp::Base64Encode => {
+                let input = input()?;
+                (codec::encode_base64(&input), "base64", input.len())
+            }
+            CodecOp::HexEncode => {
+                let input = input()?;
+                (codec::encode_hex(&input), "hex", input.len())
+            }
+            CodecOp::UrlEncode => {
+                let input = input()?;
+                (codec::encode_url(&input), "url", input.len())
+            }
+            CodecOp::Sha256 => {
+                let input = input()?;
+                (
+                    codec::encode_hex(&codec::sha256(&input)),
+                    "hex",
+                    input.len(),
+                )
+            }
+            CodecOp::Keccak256 => {
+                let input = input()?;
+                (
+                    codec::encode_hex(&codec::keccak256(&input)),
+                    "hex",
+                    input.len(),
+                )
+            }
+            CodecOp::Base64Decode => decoded(codec::decode_base64(&args.input).map_err(invalid)?),
+            CodecOp::HexDecode => decoded(codec::decode_hex(&args.input).map_err(invalid)?),
+            CodecOp::UrlDecode => decoded(codec::decode_url(&args.input)),
+        };
+        Ok(json_result(serde_json::json!({
+            "op": args.op,
+            "output": output,
+            "output_format": output_format,
+            "bytes": bytes,
+        })))
+    }
+
+    #[tool(
+        description = "Roll dice given in notation such as `3d6+2` (or one die with `sides`, default six), `count` times, optionally from a `seed` for repeatable results. Returns each die, each roll's total, and the grand total.",
+        annotations(read_only_hint = true),
+        output_schema = cached_schema_for_type::<dice::Outcome>()
+    )]
     pub async fn roll_dice(
         &self,
         Parameters(args): Parameters<DiceArgs>,
     ) -> Result<CallToolResult, McpError> {
-        let sides = args.sides.max(2);
-        let mut rng = rand::thread_rng();
-        let value = rng.gen_range(1..=sides);
+        let dice = match &args.notation {
+            Some(notation) => {
+                Dice::parse(notation).map_err(|error| McpError::invalid_params(error, None))?
+            }
+            None => Dice {
+                count: 1,
+                sides: args.sides.max(2).into(),
+                modifier: 0,
+            },
+        };
+        let times = args.count.unwrap_or(1);
+        if !(1..=MAX_DICE_ROLLS).contains(&times) {
+            return Err(McpError::invalid_params(
+                format!("count must be 1 to {MAX_DICE_ROLLS}"),
+                None,
+            ));
+        }
+        Ok(structured_result(&dice::roll(&dice, times, args.seed)))
+    }
+
+    #[tool(
+        description = "Fetch a web page or API response from an allowlisted host. HTML comes back as readable text with its title and links, JSON parsed, and plain text as is, capped at `max_chars` characters. Only listed when the server enables it.",
+        annotations(read_only_hint = true, open_world_hint = true)
+    )]
+    pub async fn fetch_url(
+        &self,
+        Parameters(args): Parameters<FetchUrlArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let Some(fetcher) = &self.fetcher else {
+            return Err(McpError::invalid_request(
+                "fetch_url is disabled; set INFERENCO_MCP_FETCH_ALLOWED_HOSTS to enable it",
+                None,
+            ));
+        };
+        let max_chars = args.max_chars.unwrap_or(fetch::DEFAULT_MAX_CHARS);
+        if !(1..=fetch::MAX_CHARS).contains(&max_chars) {
+            return Err(McpError::invalid_params(
+                format!("max_chars must be 1 to {}", fetch::MAX_CHARS),
+                None,
+            ));
+        }
+        let url = fetcher
+            .check(&args.url)
+            .map_err(|error| McpError::invalid_params(error, None))?;
+        progress::report(format!("fetching {url}"));
+        match fetcher.fetch(url, max_chars).await {
+            Ok(page) => Ok(json_result(serde_json::json!(page))),
+            Err(error) => Ok(CallToolResult::error(vec![Content::text(error)])),
+        }
+    }
+
+    #[tool(
+        description = "Summarize a page of the Cedra documentation, read as a cedra-docs:// resource, with the client's own model. Needs a client that supports sampling, connected over stdio or TCP. Only listed when the docs resources are enabled.",
+        annotations(read_only_hint = true, open_world_hint = true)
+    )]
+    pub async fn summarize_docs(
+        &self,
+        Parameters(args): Parameters<SummarizeDocsArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        if !self.resources.has_docs() {
+            return Err(McpError::invalid_request(
+                "summarize_docs is disabled; set INFERENCO_MCP_DOCS_URL to enable it",
+                None,
+            ));
+        }
+        let max_words = args.max_words.unwrap_or(DEFAULT_SUMMARY_WORDS);
+        if !(1..=MAX_SUMMARY_WORDS).contains(&max_words) {
+            return Err(McpError::invalid_params(
+                format!("max_words must be 1 to {MAX_SUMMARY_WORDS}"),
+                None,
+            ));
+        }
+        let Some(client) = SamplingClient::current() else {
+            return Err(McpError::invalid_request(
+                "summarize_docs needs a client that supports sampling",
+                None,
+            ));
+        };
+        let page = args.page.trim();
+        let uri = format!(
+            "{DOCS_SCHEME}{}",
+            page.strip_prefix(DOCS_SCHEME).unwrap_or(page)
+        );
+        progress::report(format!("reading {uri}"));
+        let text = self
+            .resources
+            .read(&uri)
+            .await?
+            .contents
+            .into_iter()
+            .find_map(|contents| match contents {
+                ResourceContents::TextResourceContents { text, .. } => Some(text),
+                _ => None,
+            })
+            .unwrap_or_default();
+        let audience = args
+            .audience
+            .as_deref()
+            .map(str::trim)
+            .filter(|audience| !audience.is_empty())
+            .unwrap_or("a developer new to Cedra");
+        let request = CreateMessageRequestParam {
+            messages: vec![SamplingMessage {
+                role: Role::User,
+                content: Content::text(format!(
+                    "Summarize this page of the Cedra documentation, {uri}, for {audience} \
+                     in at most {max_words} words.\n\n{text}"
+                )),
+            }],
+            model_preferences: None,
+            system_prompt: Some(
+                "You summarize technical documentation accurately and concisely, adding \
+                 nothing that is not on the page."
+                    .to_string(),
+            ),
+            include_context: None,
+            temperature: None,
+            // Words run to more than one token each.
+            max_tokens: max_words * 2,
+            stop_sequences: None,
+            metadata: None,
+        };
+        progress::report("asking the client's model for a summary");
+        let answer = client.create_message(request).await?;
+        match answer.message.content.raw {
+            RawContent::Text(summary) => {
+                Ok(CallToolResult::success(vec![Content::text(summary.text)]))
+            }
+            _ => Ok(CallToolResult::error(vec![Content::text(
+                "the client's model did not answer with text",
+            )])),
+        }
+    }
+}
 
-        Ok(CallToolResult::success(vec![Content::text(format!(
-            "Rolled {value} on a d{sides}"
-        ))]))
+impl ToolService {
+    /// Ask `peer` for its roots, if it declared the capability, and keep
+    /// them in the connection's [`ClientContext`]. The answer arrives in the
+    /// background, since the peer may only reply after this returns.
+    fn refresh_roots(&self, peer: &Peer<RoleServer>) {
+        let declared = peer
+            .peer_info()
+            .is_some_and(|info| info.capabilities.roots.is_some());
+        if !declared {
+            return;
+        }
+        let peer = peer.clone();
+        let client = self.connection_client.clone();
+        tokio::spawn(
+            async move {
+                match peer.list_roots().await {
+                    Ok(result) => {
+                        tracing::info!(roots = result.roots.len(), "client roots listed");
+                        client.set_roots(result.roots);
+                    }
+                    Err(error) => tracing::warn!(%error, "client did not list its roots"),
+                }
+            }
+            .in_current_span(),
+        );
     }
 }
 
-#[tool_handler]
 impl rmcp::ServerHandler for ToolService {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             protocol_version: ProtocolVersion::LATEST,
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            capabilities: ServerCapabilities::builder()
+                .enable_logging()
+                .enable_completions()
+                .enable_tools()
+                .enable_tool_list_changed()
+                .enable_resources()
+                .enable_resources_subscribe()
+                .enable_prompts()
+                .build(),
             server_info: Implementation::from_build_env(),
             instructions: Some(
                 "A minimal MCP tool server built with the official Rust SDK. ".to_string()
-                    + "Provides echo, text transformation, dice roll, clock, and counter tools "
-                    + "without any API key requirements.",
+                    + "Provides echo, text transformation, calculator, JSON query and diff, encoding and hashing, dice roll, clock and time conversion, counter, key-value, notes, call history, and version tools, plus allowlisted URL fetching when enabled. "
+                    + "API keys are required only when the operator turns authentication on. Scheduled tool results and, when configured, Cedra docs pages (cedra-docs://) are resources.",
             ),
         }
     }
+
+    async fn initialize(
+        &self,
+        request: InitializeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<InitializeResult, McpError> {
+        let result = self.initialize_result(Some(&request.protocol_version.to_string()))?;
+        if context.peer.peer_info().is_none() {
+            context.peer.set_peer_info(request);
+        }
+        Ok(result)
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let arguments = serde_json::Value::Object(request.arguments.unwrap_or_default());
+        let call = ToolService::call_tool(self, &request.name, arguments);
+        let call = sampling::with_client(SamplingClient::for_peer(&context.peer), call);
+        let call = client_context::with_context(Some(self.connection_client.clone()), call);
+        let call = store::in_namespace(self.connection_namespace.to_string(), call);
+        let call = sessions::in_session(Some(self.connection_state.clone()), call)
+            .instrument(rpc_span(&context));
+        let id = serde_json::to_value(&context.id).expect("request IDs serialize");
+        let call = self.cancellations.run(&self.connection_scope, &id, call);
+        let call = async { call.await.unwrap_or_else(|| Err(cancellation::cancelled())) };
+        let Some(progress_token) = context.meta.get_progress_token() else {
+            return call.await;
+        };
+        let peer = context.peer.clone();
+        let mut sent = 0.0;
+        progress::stream_to(call, move |chunk| {
+            sent += 1.0;
+            let notification = ProgressNotificationParam {
+                progress_token: progress_token.clone(),
+                progress: sent,
+                total: None,
+                message: Some(chunk),
+            };
+            let peer = peer.clone();
+            async move {
+                // A client that went away misses the rest; the result is
+                // still returned as usual.
+                let _ = peer.notify_progress(notification).await;
+            }
+        })
+        .await
+    }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        let _span = rpc_span(&context).entered();
+        Ok(ListResourcesResult::with_all_items(self.resources.list()))
+    }
+
+    async fn list_resource_templates(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, McpError> {
+        let _span = rpc_span(&context).entered();
+        Ok(ListResourceTemplatesResult::with_all_items(
+            self.resources.templates(),
+        ))
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        let id = serde_json::to_value(&context.id).expect("request IDs serialize");
+        let read = self.resources.read(&request.uri);
+        self.cancellations
+            .run(&self.connection_scope, &id, read)
+            .instrument(rpc_span(&context))
+            .await
+            .unwrap_or_else(|| Err(cancellation::cancelled()))
+    }
+
+    async fn subscribe(
+        &self,
+        request: SubscribeRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        self.resources
+            .subscribe(&self.connection_state, &request.uri)
+            .await
+    }
+
+    async fn unsubscribe(
+        &self,
+        request: UnsubscribeRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        self.resources
+            .unsubscribe(&self.connection_state, &request.uri)
+    }
+
+    async fn list_prompts(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListPromptsResult, McpError> {
+        let _span = rpc_span(&context).entered();
+        Ok(ListPromptsResult::with_all_items(self.prompts.list()))
+    }
+
+    async fn get_prompt(
+        &self,
+        request: GetPromptRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<GetPromptResult, McpError> {
+        let _span = rpc_span(&context).entered();
+        self.prompts.get(&request.name, request.arguments)
+    }
+
+    async fn complete(
+        &self,
+        request: CompleteRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CompleteResult, McpError> {
+        let _span = rpc_span(&context).entered();
+        ToolService::complete(self, &request)
+    }
+
+    async fn on_cancelled(
+        &self,
+        notification: CancelledNotificationParam,
+        _context: NotificationContext<RoleServer>,
+    ) {
+        let id = serde_json::to_value(&notification.request_id).expect("request IDs serialize");
+        if self.cancellations.cancel(&self.connection_scope, &id) {
+            tracing::info!(
+                request_id = %id,
+                reason = notification.reason.as_deref().unwrap_or("-"),
+                "request cancelled"
+            );
+        }
+    }
+
+    async fn on_roots_list_changed(&self, context: NotificationContext<RoleServer>) {
+        self.refresh_roots(&context.peer);
+    }
+
+    async fn on_initialized(&self, context: NotificationContext<RoleServer>) {
+        tracing::info!("client initialized");
+        // Only TCP connections are registered sessions.
+        self.sessions.mark_initialized(&self.connection_scope);
+        self.refresh_roots(&context.peer);
+        let changes = self.tool_switches.changes();
+        let peer = context.peer.clone();
+        tokio::spawn(async move {
+            let mut changes = std::pin::pin!(changes);
+            while changes.next().await.is_some() {
+                if peer.notify_tool_list_changed().await.is_err() {
+                    break;
+                }
+            }
+        });
+        // Forward the log messages the connection asked for.
+        if let Some(mut messages) = self.connection_state.log().messages() {
+            let peer = context.peer.clone();
+            tokio::spawn(async move {
+                loop {
+                    let message = match messages.recv().await {
+                        Ok(message) => message,
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => break,
+                    };
+                    // A TCP session's channel carries its other notifications too.
+                    if message["method"] != "notifications/message" {
+                        continue;
+                    }
+                    let Ok(params) =
+                        LoggingMessageNotificationParam::deserialize(&message["params"])
+                    else {
+                        continue;
+                    };
+                    if peer.notify_logging_message(params).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        // Forward updates of the resources the connection subscribed to.
+        let updates = self
+            .resources
+            .subscriptions()
+            .updates_for(self.connection_state.clone());
+        tokio::spawn(async move {
+            let mut updates = std::pin::pin!(updates);
+            while let Some(uri) = updates.next().await {
+                let update = ResourceUpdatedNotificationParam { uri };
+                if context.peer.notify_resource_updated(update).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    async fn set_level(
+        &self,
+        request: SetLevelRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        let _span = rpc_span(&context).entered();
+        self.connection_state.log().set_level(request.level);
+        tracing::info!(level = ?request.level, "client log level set");
+        Ok(())
+    }
+
+    async fn list_tools(
+        &self,
+        request: Option<PaginatedRequestParam>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        let _span = rpc_span(&context).entered();
+        let cursor = request.and_then(|request| request.cursor);
+        let (tools, next_cursor) = self.tools_page(cursor.as_deref())?;
+        let mut result = ListToolsResult::with_all_items(tools);
+        result.next_cursor = next_cursor;
+        Ok(result)
+    }
+}
+
+/// Span mirroring the HTTP bridge's `rpc` span for requests arriving through
+/// rmcp, so logs from every transport share the same correlation fields.
+fn rpc_span(context: &RequestContext<RoleServer>) -> tracing::Span {
+    tracing::info_span!(
+        "rpc",
+        request_id = %context.id,
+        session_id = "stdio",
+        key_fingerprint = "-",
+    )
 }
 
 #[cfg(test)]
@@ -157,6 +1992,205 @@ mod tests {
             .expect("tool result to contain text")
     }
 
+    #[tokio::test]
+    async fn calculate_answers_exactly_and_rejects_bad_expressions() {
+        let service = ToolService::new();
+        let result = service
+            .call_tool(
+                "calculate",
+                serde_json::json!({ "expression": "(19.99 * 3) - 10%" }),
+            )
+            .await
+            .expect("tool to succeed");
+        let answer: serde_json::Value = serde_json::from_str(&text_output(result)).unwrap();
+        assert_eq!(answer["result"], "53.973");
+
+        let error = service
+            .call_tool("calculate", serde_json::json!({ "expression": "1 / 0" }))
+            .await
+            .unwrap_err();
+        assert_eq!(error.message, "division by zero at position 3");
+    }
+
+    #[tokio::test]
+    async fn json_query_selects_from_text_documents_and_diffs() {
+        let service = ToolService::new();
+        let document = r#"{"items": [{"id": 1, "price": 4}, {"id": 2, "price": 40}]}"#;
+        let result = service
+            .call_tool(
+                "json_query",
+                serde_json::json!({ "document": document, "query": "$.items[?(@.price < 10)].id" }),
+            )
+            .await
+            .expect("query to succeed");
+        let answer: serde_json::Value = serde_json::from_str(&text_output(result)).unwrap();
+        assert_eq!(
+            answer["matches"],
+            serde_json::json!([{ "path": "$.items[0].id", "value": 1 }])
+        );
+
+        let result = service
+            .call_tool(
+                "json_query",
+                serde_json::json!({
+                    "mode": "diff",
+                    "document": { "a": 1 },
+                    "other": { "a": 1, "b": [true] },
+                }),
+            )
+            .await
+            .expect("diff to succeed");
+        let answer: serde_json::Value = serde_json::from_str(&text_output(result)).unwrap();
+        assert_eq!(answer["equal"], false);
+        assert_eq!(
+            answer["differences"],
+            serde_json::json!([{ "path": "/b", "change": "added", "to": [true] }])
+        );
+
+        let error = service
+            .call_tool("json_query", serde_json::json!({ "document": {} }))
+            .await
+            .unwrap_err();
+        assert_eq!(error.message, "query is required for mode query");
+    }
+
+    #[tokio::test]
+    async fn codec_hashes_raw_bytes_and_decodes_to_text_or_hex() {
+        let service = ToolService::new();
+        let run = |arguments: serde_json::Value| {
+            let service = service.clone();
+            async move {
+                let result = service.call_tool("codec", arguments).await?;
+                Ok::<_, McpError>(
+                    serde_json::from_str::<serde_json::Value>(&text_output(result)).unwrap(),
+                )
+            }
+        };
+        let hash =
+            run(serde_json::json!({ "op": "keccak256", "input": "0x", "input_format": "hex" }))
+                .await
+                .unwrap();
+        assert_eq!(
+            hash["output"],
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        assert_eq!(hash["bytes"], 0);
+
+        let text = run(serde_json::json!({ "op": "base64_decode", "input": "aGk=" }))
+            .await
+            .unwrap();
+        assert_eq!(
+            (&text["output"], &text["output_format"]),
+            (&"hi".into(), &"text".into())
+        );
+        let binary = run(serde_json::json!({ "op": "base64_decode", "input": "//8=" }))
+            .await
+            .unwrap();
+        assert_eq!(
+            (&binary["output"], &binary["output_format"]),
+            (&"ffff".into(), &"hex".into())
+        );
+
+        let error = run(serde_json::json!({ "op": "hex_decode", "input": "0xz1" }))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.message,
+            "invalid hex: Invalid character 'z' at position 0"
+        );
+    }
+
+    #[test]
+    fn tools_are_listed_in_pages() {
+        let service = ToolService::new().with_tools_page_size(4);
+        let mut names = Vec::new();
+        let mut cursor = None;
+        loop {
+            let (tools, next) = service.tools_page(cursor.as_deref()).expect("valid cursor");
+            assert!(tools.len() <= 4);
+            names.extend(tools.into_iter().map(|tool| tool.name.to_string()));
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        let mut all: Vec<String> = service
+            .available_tools()
+            .into_iter()
+            .map(|tool| tool.name.to_string())
+            .collect();
+        all.sort();
+        assert_eq!(names, all);
+
+        let (_, next) = ToolService::new().tools_page(None).unwrap();
+        assert_eq!(next, None);
+        assert!(service.tools_page(Some("not a cursor!")).is_err());
+    }
+
+    #[tokio::test]
+    async fn fetch_url_is_hidden_until_a_fetcher_is_configured() {
+        let listed = |service: &ToolService| {
+            service
+                .available_tools()
+                .iter()
+                .any(|tool| tool.name == "fetch_url")
+        };
+        let service = ToolService::new();
+        assert!(!listed(&service));
+        let error = service
+            .call_tool(
+                "fetch_url",
+                serde_json::json!({ "url": "https://docs.rs/" }),
+            )
+            .await
+            .unwrap_err();
+        assert!(error.message.starts_with("fetch_url is disabled"));
+
+        let fetcher = Fetcher::new(fetch::FetchSettings {
+            allowed_hosts: vec!["docs.rs".to_string()],
+            ..fetch::FetchSettings::default()
+        })
+        .unwrap();
+        let service = ToolService::new().with_fetcher(fetcher);
+        assert!(listed(&service));
+        let error = service
+            .call_tool(
+                "fetch_url",
+                serde_json::json!({ "url": "https://example.com/" }),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(error.message, "example.com is not on the fetch allowlist");
+    }
+
+    #[tokio::test]
+    async fn convert_time_reads_epochs_into_zones() {
+        let service = ToolService::new();
+        let result = service
+            .call_tool(
+                "convert_time",
+                serde_json::json!({ "time": "1700000000", "timezone": "Asia/Tokyo" }),
+            )
+            .await
+            .expect("conversion to succeed");
+        let converted: serde_json::Value = serde_json::from_str(&text_output(result)).unwrap();
+        assert_eq!(converted["rfc3339"], "2023-11-15T07:13:20+09:00");
+        assert_eq!(converted["epoch_millis"], 1_700_000_000_000_i64);
+
+        let error = service
+            .call_tool(
+                "current_time",
+                serde_json::json!({ "timezone": "Nowhere/Special" }),
+            )
+            .await
+            .unwrap_err();
+        assert!(
+            error.message.starts_with("unknown timezone"),
+            "{}",
+            error.message
+        );
+    }
+
     #[tokio::test]
     async fn reverse_text_returns_reversed_string() {
         let service = ToolService::new();
@@ -174,11 +2208,15 @@ mod tests {
     async fn current_time_emits_rfc3339_timestamp() {
         let service = ToolService::new();
         let output = service
-            .current_time()
+            .current_time(Parameters(CurrentTimeArgs::default()))
             .await
             .expect("tool to produce a timestamp");
 
+        let structured = output.structured_content.clone().expect("structured time");
         let text = text_output(output);
+        assert_eq!(structured["iso8601"], text);
+        assert_eq!(structured["timezone"], "UTC");
+        assert!(structured["unix"].as_i64().is_some_and(|unix| unix > 0));
         assert!(
             text.contains('T'),
             "timestamp missing RFC3339 separator: {text}"
@@ -195,40 +2233,271 @@ mod tests {
     #[tokio::test]
     async fn roll_dice_respects_requested_sides() {
         let service = ToolService::new();
-        let sides = 12;
         let output = service
-            .roll_dice(Parameters(DiceArgs { sides }))
+            .roll_dice(Parameters(DiceArgs {
+                notation: Some("1d12".to_string()),
+                sides: 6,
+                count: Some(50),
+                seed: None,
+            }))
             .await
             .expect("tool to roll successfully");
 
-        let text = text_output(output);
-        let parts: Vec<_> = text.split_whitespace().collect();
-        assert_eq!(parts.len(), 5, "unexpected dice output format: {text}");
-        assert_eq!(parts[0], "Rolled");
-        let value: u8 = parts[1].parse().expect("rolled value should be a number");
-        let reported_sides: u8 = parts[4]
-            .trim_start_matches('d')
-            .parse()
-            .expect("sides suffix should parse");
-
-        assert_eq!(reported_sides, sides);
-        assert!((1..=sides).contains(&value), "roll {value} outside bounds");
+        let outcome: dice::Outcome = serde_json::from_str(&text_output(output)).unwrap();
+        assert_eq!(
+            (outcome.notation.as_str(), outcome.rolls.len()),
+            ("1d12", 50)
+        );
+        for roll in &outcome.rolls {
+            assert_eq!(roll.dice.len(), 1, "{roll:?}");
+            assert!((1..=12).contains(&roll.dice[0]), "{roll:?}");
+            assert_eq!(roll.total, i64::from(roll.dice[0]));
+        }
     }
 
     #[tokio::test]
     async fn roll_dice_enforces_minimum_of_two_sides() {
         let service = ToolService::new();
         let output = service
-            .roll_dice(Parameters(DiceArgs { sides: 1 }))
+            .roll_dice(Parameters(DiceArgs {
+                notation: None,
+                sides: 1,
+                count: None,
+                seed: None,
+            }))
             .await
             .expect("tool to roll successfully");
 
-        let text = text_output(output);
-        let reported_sides: u8 = text
-            .split_whitespace()
-            .last()
-            .and_then(|suffix| suffix.trim_start_matches('d').parse().ok())
-            .expect("output should contain die size");
-        assert_eq!(reported_sides, 2);
+        let outcome: dice::Outcome = serde_json::from_str(&text_output(output)).unwrap();
+        assert_eq!(outcome.notation, "1d2");
+        assert!((1..=2).contains(&outcome.total), "{outcome:?}");
+    }
+
+    #[tokio::test]
+    async fn roll_dice_takes_notation_counts_and_seeds() {
+        let service = ToolService::new();
+        let roll = || async {
+            let result = service
+                .call_tool(
+                    "roll_dice",
+                    serde_json::json!({ "notation": "3d6+2", "count": 4, "seed": 42 }),
+                )
+                .await
+                .expect("tool to roll successfully");
+            serde_json::from_str::<dice::Outcome>(&text_output(result)).unwrap()
+        };
+        let outcome = roll().await;
+        assert_eq!(outcome, roll().await);
+        assert_eq!(
+            (outcome.notation.as_str(), outcome.rolls.len()),
+            ("3d6+2", 4)
+        );
+        assert!(outcome.rolls.iter().all(|roll| roll.dice.len() == 3));
+
+        let error = service
+            .call_tool(
+                "roll_dice",
+                serde_json::json!({ "notation": "3d6", "count": 0 }),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(error.message, "count must be 1 to 100");
+    }
+
+    #[tokio::test]
+    async fn counter_operations_share_named_state() {
+        let service = ToolService::new();
+        let call = |args: serde_json::Value| {
+            let service = service.clone();
+            async move { service.call_tool("counter", args).await.map(text_output) }
+        };
+
+        service.increment().await.expect("increment succeeds");
+        call(serde_json::json!({ "name": "visits", "op": "add", "amount": 5 }))
+            .await
+            .expect("add succeeds");
+        assert_eq!(
+            call(serde_json::json!({ "name": "visits", "op": "decrement" })).await,
+            Ok(r#"{"name":"visits","value":4}"#.to_string())
+        );
+        assert_eq!(
+            call(serde_json::json!({ "op": "list" })).await,
+            Ok(r#"{"counters":{"increment":1,"visits":4}}"#.to_string())
+        );
+        assert_eq!(
+            call(serde_json::json!({ "name": "visits", "op": "reset" })).await,
+            Ok(r#"{"name":"visits","value":0}"#.to_string())
+        );
+        assert!(call(serde_json::json!({ "op": "get" })).await.is_err());
+        assert!(call(serde_json::json!({ "name": "visits", "op": "add" }))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn sessions_count_increments_on_their_own() {
+        let service = ToolService::new();
+        let increment = |state: Option<SessionState>| {
+            let service = service.clone();
+            sessions::in_session(state, async move {
+                service
+                    .call_tool("increment", serde_json::json!({}))
+                    .await
+                    .map(text_output)
+            })
+        };
+        let (first, second) = (SessionState::default(), SessionState::default());
+
+        assert_eq!(increment(Some(first.clone())).await, Ok("1".to_string()));
+        assert_eq!(increment(Some(first)).await, Ok("2".to_string()));
+        assert_eq!(increment(Some(second)).await, Ok("1".to_string()));
+        assert_eq!(increment(None).await, Ok("1".to_string()));
+    }
+
+    #[tokio::test]
+    async fn kv_keys_are_private_to_their_namespace() {
+        let service = ToolService::new().with_kv_limits(store::KvLimits {
+            max_keys: 1,
+            max_value_bytes: 16,
+        });
+        let call = |namespace: &str, tool: &'static str, args: serde_json::Value| {
+            let service = service.clone();
+            store::in_namespace(namespace.to_string(), async move {
+                service.call_tool(tool, args).await.map(text_output)
+            })
+        };
+
+        call(
+            "a",
+            "kv_set",
+            serde_json::json!({ "key": "k", "value": [1, 2] }),
+        )
+        .await
+        .expect("set succeeds");
+        assert_eq!(
+            call("a", "kv_get", serde_json::json!({ "key": "k" })).await,
+            Ok(r#"{"expires_at":null,"found":true,"key":"k","value":[1,2]}"#.to_string())
+        );
+        assert_eq!(
+            call("b", "kv_get", serde_json::json!({ "key": "k" })).await,
+            Ok(r#"{"found":false,"key":"k"}"#.to_string())
+        );
+        assert_eq!(
+            call("a", "kv_set", serde_json::json!({ "key": "x", "value": 1 })).await,
+            Ok("already storing the maximum of 1 keys; delete some first".to_string())
+        );
+        assert!(call(
+            "b",
+            "kv_set",
+            serde_json::json!({ "key": "x", "value": "0123456789abcdef" })
+        )
+        .await
+        .is_err());
+        assert_eq!(
+            call("a", "kv_list", serde_json::json!({})).await,
+            Ok(r#"{"keys":["k"]}"#.to_string())
+        );
+        assert_eq!(
+            call("a", "kv_delete", serde_json::json!({ "key": "k" })).await,
+            Ok(r#"{"deleted":true,"key":"k"}"#.to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn call_history_is_per_namespace_and_redacted() {
+        let service = ToolService::new();
+        let call = |namespace: &str, tool: &'static str, args: serde_json::Value| {
+            let service = service.clone();
+            store::in_namespace(namespace.to_string(), async move {
+                service.call_tool(tool, args).await.map(text_output)
+            })
+        };
+
+        call(
+            "a",
+            "echo",
+            serde_json::json!({ "message": "{\"api_key\":\"k\"}" }),
+        )
+        .await
+        .expect("echo succeeds");
+        call("a", "reverse_text", serde_json::json!({ "text": "ab" }))
+            .await
+            .expect("reverse succeeds");
+        call("a", "call_history", serde_json::json!({}))
+            .await
+            .expect("history succeeds");
+
+        let history: serde_json::Value = serde_json::from_str(
+            &call("a", "call_history", serde_json::json!({}))
+                .await
+                .expect("history succeeds"),
+        )
+        .expect("history is JSON");
+        let calls = history["calls"].as_array().expect("calls");
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0]["tool"], "reverse_text");
+        assert_eq!(calls[0]["summary"], "ba");
+        assert_eq!(
+            calls[1]["arguments"],
+            serde_json::json!({ "message": "{\"api_key\":\"REDACTED\"}" })
+        );
+        assert_eq!(
+            call("b", "call_history", serde_json::json!({})).await,
+            Ok(r#"{"calls":[]}"#.to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn notes_are_searchable_and_private_to_their_namespace() {
+        let service = ToolService::new();
+        let call = |namespace: &str, tool: &'static str, args: serde_json::Value| {
+            let service = service.clone();
+            store::in_namespace(namespace.to_string(), async move {
+                let text = service.call_tool(tool, args).await.map(text_output);
+                serde_json::from_str::<serde_json::Value>(&text.expect("call succeeds"))
+                    .expect("result is JSON")
+            })
+        };
+        let ids = |result: serde_json::Value| -> Vec<u64> {
+            result["notes"]
+                .as_array()
+                .expect("notes")
+                .iter()
+                .map(|note| note["id"].as_u64().expect("id"))
+                .collect()
+        };
+
+        call(
+            "a",
+            "note_add",
+            serde_json::json!({ "text": "Tokio runtime tuning", "tags": ["Rust", "rust"] }),
+        )
+        .await;
+        call(
+            "a",
+            "note_add",
+            serde_json::json!({ "text": "Rust runtimes compared: tokio, smol" }),
+        )
+        .await;
+        call("a", "note_add", serde_json::json!({ "text": "Groceries" })).await;
+
+        let search = |args| call("a", "note_search", args);
+        assert_eq!(
+            ids(search(serde_json::json!({ "query": "RUNTIME tok" })).await),
+            vec![2, 1]
+        );
+        assert_eq!(
+            ids(search(serde_json::json!({ "tag": "rust" })).await),
+            vec![1]
+        );
+        assert_eq!(ids(search(serde_json::json!({})).await), vec![3, 2, 1]);
+        assert_eq!(
+            call("a", "note_get", serde_json::json!({ "id": 1 })).await["tags"],
+            serde_json::json!(["rust"])
+        );
+        assert_eq!(
+            call("b", "note_get", serde_json::json!({ "id": 1 })).await,
+            serde_json::json!({ "id": 1, "found": false })
+        );
     }
 }
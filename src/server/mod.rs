@@ -1,5 +1,17 @@
+pub mod client_context;
+pub mod completions;
 mod dto;
 mod implementation;
+pub mod prompts;
+pub mod resources;
+pub mod sampling;
+pub mod subscriptions;
+pub mod tool_switches;
 
-pub use dto::{DiceArgs, EchoArgs, ReverseArgs};
-pub use implementation::ToolService;
+pub use dto::{
+    CalculateArgs, CallHistoryArgs, CodecArgs, CodecInputFormat, CodecOp, ConvertTimeArgs,
+    CounterArgs, CounterOp, CurrentTime, CurrentTimeArgs, DiceArgs, EchoArgs, FetchUrlArgs,
+    JsonQueryArgs, JsonQueryMode, KvKeyArgs, KvListArgs, KvSetArgs, NoteAddArgs, NoteGetArgs,
+    NoteSearchArgs, ReverseArgs, SummarizeDocsArgs,
+};
+pub use implementation::{ToolService, DEFAULT_TOOLS_PAGE_SIZE};
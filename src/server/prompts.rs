@@ -0,0 +1,271 @@
+//! MCP prompts: reusable message templates a client can list and fill in.
+//!
+//! Each [`PromptTemplate`] names its string arguments and renders them into
+//! the messages `prompts/get` returns. [`PromptRegistry`] holds the templates
+//! the server offers; stdio and HTTP both answer `prompts/list` and
+//! `prompts/get` from it.
+
+use super::{completions::CompletionProvider, resources::DOCS_SCHEME};
+use rmcp::{
+    model::{
+        AnnotateAble, ArgumentInfo, GetPromptResult, JsonObject, Prompt, PromptArgument,
+        PromptMessage, PromptMessageRole, RawResource, Reference,
+    },
+    ErrorData as McpError,
+};
+use std::collections::BTreeMap;
+
+/// The arguments of one `prompts/get`, by name.
+pub type PromptArguments = BTreeMap<String, String>;
+
+/// A prompt and how to render it.
+#[derive(Clone)]
+pub struct PromptTemplate {
+    pub name: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+    /// Name, description, and whether it is required, for each argument.
+    pub arguments: &'static [(&'static str, &'static str, bool)],
+    /// Builds the messages; required arguments are present.
+    pub render: fn(&PromptArguments) -> Vec<PromptMessage>,
+}
+
+impl PromptTemplate {
+    fn prompt(&self) -> Prompt {
+        let arguments = self
+            .arguments
+            .iter()
+            .map(|&(name, description, required)| PromptArgument {
+                name: name.to_string(),
+                title: None,
+                description: Some(description.to_string()),
+                required: Some(required),
+            })
+            .collect();
+        let mut prompt = Prompt::new(self.name, Some(self.description), Some(arguments));
+        prompt.title = Some(self.title.to_string());
+        prompt
+    }
+}
+
+/// The prompts the server offers, by name.
+#[derive(Clone)]
+pub struct PromptRegistry {
+    templates: BTreeMap<&'static str, PromptTemplate>,
+}
+
+impl Default for PromptRegistry {
+    /// The prompts that need no configuration.
+    fn default() -> Self {
+        let mut registry = Self {
+            templates: BTreeMap::new(),
+        };
+        registry.register(explain_move_module());
+        registry
+    }
+}
+
+impl PromptRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Offer `template`, replacing any prompt of the same name.
+    pub fn register(&mut self, template: PromptTemplate) {
+        self.templates.insert(template.name, template);
+    }
+
+    /// Every prompt, for `prompts/list`.
+    pub fn list(&self) -> Vec<Prompt> {
+        self.templates
+            .values()
+            .map(PromptTemplate::prompt)
+            .collect()
+    }
+
+    /// Render prompt `name` with `arguments`, for `prompts/get`.
+    pub fn get(
+        &self,
+        name: &str,
+        arguments: Option<JsonObject>,
+    ) -> Result<GetPromptResult, McpError> {
+        let template = self
+            .templates
+            .get(name)
+            .ok_or_else(|| McpError::invalid_params(format!("unknown prompt {name}"), None))?;
+        let mut values = PromptArguments::new();
+        for (key, value) in arguments.unwrap_or_default() {
+            let serde_json::Value::String(value) = value else {
+                return Err(McpError::invalid_params(
+                    format!("argument {key} must be a string"),
+                    None,
+                ));
+            };
+            values.insert(key, value);
+        }
+        for &(argument, _, required) in template.arguments {
+            if required
+                && values
+                    .get(argument)
+                    .is_none_or(|value| value.trim().is_empty())
+            {
+                return Err(McpError::invalid_params(
+                    format!("prompt {name} needs argument {argument}"),
+                    None,
+                ));
+            }
+        }
+        Ok(GetPromptResult {
+            description: Some(template.description.to_string()),
+            messages: (template.render)(&values),
+        })
+    }
+}
+
+/// Prompt arguments are free text, so known prompts complete to nothing.
+impl CompletionProvider for PromptRegistry {
+    fn complete(&self, reference: &Reference, _argument: &ArgumentInfo) -> Option<Vec<String>> {
+        let name = reference.as_prompt_name()?;
+        self.templates.contains_key(name).then(Vec::new)
+    }
+}
+
+/// Explain a Move module from its source or its on-chain ID.
+pub fn explain_move_module() -> PromptTemplate {
+    PromptTemplate {
+        name: "explain_move_module",
+        title: "Explain a Move module",
+        description: "Walk through what a Move module does: its structs, resources, public \
+                      functions, and the abilities and access rules that govern them.",
+        arguments: &[
+            (
+                "module",
+                "Move source of the module, or its ID such as 0x1::coin",
+                true,
+            ),
+            (
+                "focus",
+                "Function, struct, or concern to concentrate on",
+                false,
+            ),
+        ],
+        render: |arguments| {
+            let module = arguments["module"].trim();
+            let mut text = if module.contains('\n') || module.contains('{') {
+                format!("Explain this Move module.\n\n```move\n{module}\n```")
+            } else {
+                format!("Explain the Move module {module}.")
+            };
+            text.push_str(
+                "\n\nCover its structs and resources and their abilities, each public and \
+                 entry function and who may call it, the events it emits, and anything a \
+                 caller could get wrong.",
+            );
+            if let Some(focus) = arguments
+                .get("focus")
+                .filter(|focus| !focus.trim().is_empty())
+            {
+                text.push_str(&format!(" Concentrate on {}.", focus.trim()));
+            }
+            vec![PromptMessage::new_text(PromptMessageRole::User, text)]
+        },
+    }
+}
+
+/// Summarize a `cedra-docs://` page; offered when docs resources are.
+pub fn summarize_doc_page() -> PromptTemplate {
+    PromptTemplate {
+        name: "summarize_doc_page",
+        title: "Summarize a Cedra docs page",
+        description: "Summarize one page of the Cedra documentation, read as a cedra-docs:// \
+                      resource.",
+        arguments: &[
+            (
+                "page",
+                "Path of the page under the docs root, such as move/modules, or its \
+                 cedra-docs:// URI",
+                true,
+            ),
+            (
+                "audience",
+                "Who the summary is for, such as a new dApp developer",
+                false,
+            ),
+        ],
+        render: |arguments| {
+            let page = arguments["page"].trim();
+            let uri = format!(
+                "{DOCS_SCHEME}{}",
+                page.strip_prefix(DOCS_SCHEME).unwrap_or(page)
+            );
+            let audience = arguments
+                .get("audience")
+                .map(|audience| audience.trim())
+                .filter(|audience| !audience.is_empty())
+                .unwrap_or("a developer new to Cedra");
+            let text = format!(
+                "Read the Cedra documentation page {uri} and summarize it for {audience}: \
+                 what it covers, the key concepts and steps, and which linked pages to read \
+                 next."
+            );
+            vec![
+                PromptMessage::new_text(PromptMessageRole::User, text),
+                PromptMessage::new_resource_link(
+                    PromptMessageRole::User,
+                    RawResource::new(uri, "cedra-docs-page").no_annotation(),
+                ),
+            ]
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::PromptMessageContent;
+
+    #[test]
+    fn prompts_render_their_arguments_and_require_the_required_ones() {
+        let mut registry = PromptRegistry::new();
+        registry.register(summarize_doc_page());
+        let names: Vec<String> = registry.list().into_iter().map(|p| p.name).collect();
+        assert_eq!(names, ["explain_move_module", "summarize_doc_page"]);
+
+        let arguments = |pairs: &[(&str, &str)]| {
+            Some(
+                pairs
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), (*value).into()))
+                    .collect::<JsonObject>(),
+            )
+        };
+        let result = registry
+            .get(
+                "summarize_doc_page",
+                arguments(&[("page", "cedra-docs://move/modules")]),
+            )
+            .unwrap();
+        let PromptMessageContent::Text { text } = &result.messages[0].content else {
+            panic!("text first");
+        };
+        assert!(
+            text.contains("page cedra-docs://move/modules and"),
+            "{text}"
+        );
+        assert!(matches!(
+            &result.messages[1].content,
+            PromptMessageContent::ResourceLink { link } if link.uri == "cedra-docs://move/modules"
+        ));
+
+        for (name, args) in [
+            ("explain_move_module", arguments(&[("focus", "transfer")])),
+            ("explain_move_module", arguments(&[("module", " ")])),
+            ("no_such_prompt", None),
+        ] {
+            assert!(registry.get(name, args).is_err(), "{name}");
+        }
+        let mut numeric = JsonObject::new();
+        numeric.insert("module".to_string(), 1.into());
+        assert!(registry.get("explain_move_module", Some(numeric)).is_err());
+    }
+}
@@ -0,0 +1,484 @@
+//! MCP resources, routed by URI scheme.
+//!
+//! `schedule://<job>` resources hold the latest result of each scheduled job
+//! (see [`crate::scheduler`]). With `INFERENCO_MCP_DOCS_URL` set,
+//! `cedra-docs://<path>` resources are pages of the Cedra documentation under
+//! that URL, fetched when read and returned as readable text; the docs root
+//! is listed, and every other page is reachable through the
+//! `cedra-docs://{path}` template. stdio and HTTP both serve resources
+//! through [`ResourceRouter`].
+//!
+//! The last text read of each docs page is kept in the `cedra-docs` cache.
+//! The paths of the pages read and of the pages they link to complete the
+//! template's `path` and the `summarize_doc_page` prompt's `page`.
+//! Reading a page, or the periodic refresh of every cached page, announces it
+//! to subscribers when its text differs from the cached copy; a page that
+//! cannot be fetched is served from the cache.
+
+use super::{
+    completions::{starting_with, CompletionProvider},
+    prompts,
+    subscriptions::Subscriptions,
+};
+use crate::{
+    cache::{CacheLimits, LruCache},
+    fetch::{self, FetchSettings, Fetcher},
+    scheduler::{self, ScheduleBoard, ScheduledJob},
+    sessions::SessionState,
+};
+use reqwest::Url;
+use rmcp::{
+    model::{
+        AnnotateAble, ArgumentInfo, RawResource, RawResourceTemplate, ReadResourceResult,
+        Reference, Resource, ResourceContents, ResourceTemplate,
+    },
+    ErrorData as McpError,
+};
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+pub const DOCS_SCHEME: &str = "cedra-docs://";
+const DOCS_MIME_TYPE: &str = "text/markdown";
+/// Name of the docs page cache in `/admin/caches`.
+pub const DOCS_CACHE: &str = "cedra-docs";
+
+/// Finds the provider of a resource URI.
+#[derive(Clone)]
+pub struct ResourceRouter {
+    subscriptions: Subscriptions,
+    schedule: Arc<ScheduleBoard>,
+    docs: Option<Arc<DocsPages>>,
+}
+
+impl Default for ResourceRouter {
+    fn default() -> Self {
+        let subscriptions = Subscriptions::new();
+        Self {
+            schedule: Arc::new(ScheduleBoard::new(Vec::new(), subscriptions.clone())),
+            subscriptions,
+            docs: None,
+        }
+    }
+}
+
+impl ResourceRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_schedule(mut self, jobs: Vec<ScheduledJob>) -> Self {
+        self.schedule = Arc::new(ScheduleBoard::new(jobs, self.subscriptions.clone()));
+        self
+    }
+
+    pub fn with_docs(mut self, mut docs: DocsPages) -> Self {
+        docs.updates = self.subscriptions.clone();
+        self.docs = Some(Arc::new(docs));
+        self
+    }
+
+    pub fn schedule(&self) -> &Arc<ScheduleBoard> {
+        &self.schedule
+    }
+
+    pub fn subscriptions(&self) -> &Subscriptions {
+        &self.subscriptions
+    }
+
+    /// Whether the `cedra-docs://` pages are served.
+    pub fn has_docs(&self) -> bool {
+        self.docs.is_some()
+    }
+
+    /// Every concrete resource, for `resources/list`.
+    pub fn list(&self) -> Vec<Resource> {
+        let mut resources = self.schedule.resources();
+        resources.extend(self.docs.iter().map(|docs| docs.index()));
+        resources
+    }
+
+    /// URI templates for resources too many to list, for
+    /// `resources/templates/list`.
+    pub fn templates(&self) -> Vec<ResourceTemplate> {
+        self.docs.iter().map(|docs| docs.template()).collect()
+    }
+
+    pub async fn read(&self, uri: &str) -> Result<ReadResourceResult, McpError> {
+        let not_found = || McpError::resource_not_found(format!("no resource {uri}"), None);
+        if uri.starts_with(scheduler::URI_SCHEME) {
+            return self.schedule.read(uri).ok_or_else(not_found);
+        }
+        match &self.docs {
+            Some(docs) if uri.starts_with(DOCS_SCHEME) => docs.read(uri).await,
+            _ => Err(not_found()),
+        }
+    }
+
+    /// Subscribe `state`'s session to `uri`. A docs page is read once so
+    /// later changes can be told apart.
+    pub async fn subscribe(&self, state: &SessionState, uri: &str) -> Result<(), McpError> {
+        if let Some(docs) = self.docs.as_ref().filter(|_| uri.starts_with(DOCS_SCHEME)) {
+            docs.watch(uri).await?;
+        }
+        self.subscriptions
+            .subscribe(state, uri)
+            .map_err(|error| McpError::internal_error(error, None))
+    }
+
+    pub fn unsubscribe(&self, state: &SessionState, uri: &str) -> Result<(), McpError> {
+        self.subscriptions
+            .unsubscribe(state, uri)
+            .map_err(|error| McpError::internal_error(error, None))
+    }
+
+    /// Refresh cached docs pages in the background, if configured to.
+    pub fn spawn_refresh(&self) {
+        if let Some(docs) = &self.docs {
+            docs.spawn_refresh();
+        }
+    }
+}
+
+/// Completes the docs template's `path` and the docs prompt's `page` with
+/// the paths of known docs pages.
+impl CompletionProvider for ResourceRouter {
+    fn complete(&self, reference: &Reference, argument: &ArgumentInfo) -> Option<Vec<String>> {
+        let docs = self.docs.as_ref()?;
+        let completes = match reference {
+            Reference::Resource(template) if template.uri == DocsPages::template_uri() => {
+                argument.name == "path"
+            }
+            Reference::Prompt(prompt) if prompt.name == prompts::summarize_doc_page().name => {
+                argument.name == "page"
+            }
+            _ => return None,
+        };
+        if !completes {
+            return Some(Vec::new());
+        }
+        // The prompt takes the page's URI as well as its path.
+        let (scheme, typed) = match argument.value.strip_prefix(DOCS_SCHEME) {
+            Some(path) => (DOCS_SCHEME, path),
+            None => ("", argument.value.as_str()),
+        };
+        Some(
+            starting_with(docs.paths(), typed)
+                .into_iter()
+                .map(|path| format!("{scheme}{path}"))
+                .collect(),
+        )
+    }
+}
+
+/// The `cedra-docs://` pages: paths under a base URL, read through a
+/// [`Fetcher`] that may only reach the base URL's host.
+pub struct DocsPages {
+    base: Url,
+    fetcher: Fetcher,
+    /// Rendered text of each page last read, by URI.
+    cache: Arc<LruCache<String>>,
+    updates: Subscriptions,
+    refresh: Option<Duration>,
+    /// Paths of the pages read and of the pages they link to.
+    paths: RwLock<BTreeSet<String>>,
+}
+
+impl DocsPages {
+    /// Pages under `base`, fetched within the byte and time limits of
+    /// `limits`; its allowlist is replaced by the base URL's host.
+    pub fn new(base: Url, limits: &FetchSettings) -> Result<Self, String> {
+        let host = base
+            .host_str()
+            .ok_or("the docs URL needs a host")?
+            .to_ascii_lowercase();
+        let mut base = base;
+        // Without the trailing slash, joining would replace the last segment.
+        if !base.path().ends_with('/') {
+            base.set_path(&format!("{}/", base.path()));
+        }
+        base.set_query(None);
+        base.set_fragment(None);
+        let fetcher = Fetcher::new(FetchSettings {
+            allowed_hosts: vec![host],
+            ..limits.clone()
+        })?;
+        Ok(Self {
+            base,
+            fetcher,
+            cache: Arc::new(LruCache::new(DOCS_CACHE, CacheLimits::default())),
+            updates: Subscriptions::new(),
+            refresh: None,
+            paths: RwLock::default(),
+        })
+    }
+
+    /// Keep read pages in `cache`, such as one from the service's
+    /// [`CacheRegistry`](crate::cache::CacheRegistry).
+    pub fn with_cache(mut self, cache: Arc<LruCache<String>>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Re-read every cached page each `period`; never when `None`.
+    pub fn with_refresh(mut self, period: Option<Duration>) -> Self {
+        self.refresh = period;
+        self
+    }
+
+    /// The page behind `uri`, if it names one under the base URL.
+    fn url(&self, uri: &str) -> Option<Url> {
+        let path = uri.strip_prefix(DOCS_SCHEME)?;
+        if path.starts_with('/') || path.contains("://") {
+            return None;
+        }
+        let url = self.base.join(path).ok()?;
+        url.as_str().starts_with(self.base.as_str()).then_some(url)
+    }
+
+    /// The `cedra-docs://` URI of `url`, if it is a page under the base URL.
+    fn uri(&self, url: &str) -> Option<String> {
+        let url = Url::parse(url).ok()?;
+        let path = url.as_str().strip_prefix(self.base.as_str())?;
+        let path = path.split('#').next().unwrap_or_default();
+        Some(format!("{DOCS_SCHEME}{path}"))
+    }
+
+    fn index(&self) -> Resource {
+        let mut resource = RawResource::new(DOCS_SCHEME, "cedra-docs");
+        resource.title = Some("Cedra documentation".to_string());
+        resource.description = Some(format!("Start page of the docs at {}", self.base));
+        resource.mime_type = Some(DOCS_MIME_TYPE.to_string());
+        resource.no_annotation()
+    }
+
+    fn template_uri() -> String {
+        format!("{DOCS_SCHEME}{{path}}")
+    }
+
+    fn template(&self) -> ResourceTemplate {
+        RawResourceTemplate {
+            uri_template: Self::template_uri(),
+            name: "cedra-docs-page".to_string(),
+            title: Some("Cedra documentation page".to_string()),
+            description: Some(format!(
+                "The page at {}{{path}} as text, with links to other pages as {DOCS_SCHEME} URIs",
+                self.base
+            )),
+            mime_type: Some(DOCS_MIME_TYPE.to_string()),
+        }
+        .no_annotation()
+    }
+
+    async fn read(&self, uri: &str) -> Result<ReadResourceResult, McpError> {
+        let url = self.page_url(uri)?;
+        let text = match self.fetch(uri, url).await {
+            Ok(text) => text,
+            Err(error) => match self.cache.get(uri) {
+                Some(cached) => {
+                    tracing::warn!(%uri, %error, "serving cached docs page");
+                    cached
+                }
+                None => return Err(McpError::internal_error(error, None)),
+            },
+        };
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri: uri.to_string(),
+                mime_type: Some(DOCS_MIME_TYPE.to_string()),
+                text,
+                meta: None,
+            }],
+        })
+    }
+
+    /// Make sure `uri` is cached, so the refresh can notice it change.
+    async fn watch(&self, uri: &str) -> Result<(), McpError> {
+        let url = self.page_url(uri)?;
+        if self.cache.keys().iter().any(|key| key == uri) {
+            return Ok(());
+        }
+        if let Err(error) = self.fetch(uri, url).await {
+            tracing::warn!(%uri, %error, "subscribed docs page could not be read");
+        }
+        Ok(())
+    }
+
+    fn page_url(&self, uri: &str) -> Result<Url, McpError> {
+        self.url(uri).ok_or_else(|| {
+            McpError::resource_not_found(format!("{uri} is not a page under {}", self.base), None)
+        })
+    }
+
+    /// Fetch and render page `uri` from `url`, caching the text and
+    /// announcing it when it changed.
+    async fn fetch(&self, uri: &str, url: Url) -> Result<String, String> {
+        let page = self.fetcher.fetch(url, fetch::MAX_CHARS).await?;
+        self.remember(uri, &page);
+        let text = self.render(&page);
+        let previous = self.cache.get(uri);
+        self.cache.insert(uri, text.clone());
+        if previous.is_some_and(|previous| previous != text) {
+            tracing::info!(%uri, "docs page changed");
+            self.updates.publish(uri);
+        }
+        Ok(text)
+    }
+
+    /// Note the paths of page `uri` and of the docs pages it links to.
+    fn remember(&self, uri: &str, page: &fetch::Page) {
+        let uris = page.links.iter().filter_map(|link| self.uri(link));
+        let mut paths = self.paths.write().expect("docs paths lock poisoned");
+        for uri in std::iter::once(uri.to_string()).chain(uris) {
+            match uri.strip_prefix(DOCS_SCHEME) {
+                Some(path) if !path.is_empty() => paths.insert(path.to_string()),
+                _ => false,
+            };
+        }
+    }
+
+    /// Paths of every page known so far, for completion.
+    fn paths(&self) -> Vec<String> {
+        let paths = self.paths.read().expect("docs paths lock poisoned");
+        paths.iter().cloned().collect()
+    }
+
+    /// Re-read every cached page, announcing the ones that changed.
+    async fn refresh(&self) {
+        for uri in self.cache.keys() {
+            let Some(url) = self.url(&uri) else {
+                continue;
+            };
+            if let Err(error) = self.fetch(&uri, url).await {
+                tracing::debug!(%uri, %error, "docs page refresh failed");
+            }
+        }
+    }
+
+    /// Run [`Self::refresh`] every refresh period for as long as `self`
+    /// lives.
+    fn spawn_refresh(self: &Arc<Self>) {
+        let Some(period) = self.refresh else {
+            return;
+        };
+        let docs = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let Some(docs) = docs.upgrade() else {
+                    return;
+                };
+                docs.refresh().await;
+            }
+        });
+    }
+
+    /// The page's title and text, followed by its links to other pages.
+    fn render(&self, page: &fetch::Page) -> String {
+        let mut text = String::new();
+        if let Some(title) = &page.title {
+            text.push_str(&format!("# {title}\n\n"));
+        }
+        text.push_str(page.text.as_deref().unwrap_or_default());
+        let mut links: Vec<String> = page
+            .links
+            .iter()
+            .filter_map(|link| self.uri(link))
+            .collect();
+        links.dedup();
+        if !links.is_empty() {
+            text.push_str("\n\n## Pages linked from here\n");
+            for link in links {
+                text.push_str(&format!("\n- {link}"));
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn docs_uris_stay_under_the_base_url() {
+        let base = Url::parse("https://docs.example.com/guide?x=1").unwrap();
+        let docs = DocsPages::new(base, &FetchSettings::default()).unwrap();
+        let url = |uri: &str| docs.url(uri).map(String::from);
+
+        assert_eq!(
+            url("cedra-docs://").as_deref(),
+            Some("https://docs.example.com/guide/")
+        );
+        assert_eq!(
+            url("cedra-docs://move/modules").as_deref(),
+            Some("https://docs.example.com/guide/move/modules")
+        );
+        for outside in [
+            "cedra-docs://../admin",
+            "cedra-docs:///etc/passwd",
+            "cedra-docs://https://elsewhere.example.com/",
+            "schedule://job",
+        ] {
+            assert_eq!(url(outside), None, "{outside}");
+        }
+
+        assert_eq!(
+            docs.uri("https://docs.example.com/guide/move#top")
+                .as_deref(),
+            Some("cedra-docs://move")
+        );
+        assert_eq!(docs.uri("https://docs.example.com/blog"), None);
+    }
+
+    #[test]
+    fn docs_paths_complete_from_the_pages_read() {
+        let base = Url::parse("https://docs.example.com/guide/").unwrap();
+        let docs = DocsPages::new(base, &FetchSettings::default()).unwrap();
+        let page = fetch::Page {
+            url: "https://docs.example.com/guide/move".to_string(),
+            status: 200,
+            content_type: None,
+            kind: fetch::PageKind::Html,
+            title: None,
+            text: None,
+            json: None,
+            links: vec![
+                "https://docs.example.com/guide/move/modules#structs".to_string(),
+                "https://docs.example.com/guide/".to_string(),
+                "https://elsewhere.example.com/move/objects".to_string(),
+            ],
+            bytes: 0,
+            truncated: false,
+        };
+        docs.remember("cedra-docs://move", &page);
+        let router = ResourceRouter::new().with_docs(docs);
+        let complete = |reference: Reference, name: &str, value: &str| {
+            let argument = ArgumentInfo {
+                name: name.to_string(),
+                value: value.to_string(),
+            };
+            router.complete(&reference, &argument)
+        };
+        let template = Reference::for_resource("cedra-docs://{path}");
+        let prompt = Reference::for_prompt("summarize_doc_page");
+
+        assert_eq!(
+            complete(template, "path", "Mo").unwrap(),
+            ["move", "move/modules"]
+        );
+        assert_eq!(
+            complete(prompt.clone(), "page", "cedra-docs://move/").unwrap(),
+            ["cedra-docs://move/modules"]
+        );
+        assert_eq!(complete(prompt, "audience", ""), Some(Vec::new()));
+        assert_eq!(
+            complete(Reference::for_prompt("explain_move_module"), "module", ""),
+            None
+        );
+    }
+}
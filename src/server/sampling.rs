@@ -0,0 +1,68 @@
+//! Asking the client's model for completions (`sampling/createMessage`).
+//!
+//! A client that advertises the `sampling` capability can run prompts on the
+//! server's behalf. While rmcp serves such a client's tool call, the call
+//! runs with a [`SamplingClient`] for it, which tools pick up with
+//! [`SamplingClient::current`]. The HTTP transports cannot carry requests
+//! from the server to the client, so tool calls over them never have one.
+
+use rmcp::{
+    model::{CreateMessageRequestParam, CreateMessageResult},
+    service::Peer,
+    ErrorData as McpError, RoleServer,
+};
+use std::{future::Future, time::Duration};
+
+/// How long the client's model may take to answer.
+const SAMPLING_TIMEOUT: Duration = Duration::from_secs(120);
+
+tokio::task_local! {
+    static CURRENT_CLIENT: Option<SamplingClient>;
+}
+
+/// Run `future` with `client` as the [`SamplingClient::current`] of its
+/// tool calls.
+pub async fn with_client<F: Future>(client: Option<SamplingClient>, future: F) -> F::Output {
+    CURRENT_CLIENT.scope(client, future).await
+}
+
+/// The connected client, for asking its model for completions.
+#[derive(Clone)]
+pub struct SamplingClient {
+    peer: Peer<RoleServer>,
+}
+
+impl SamplingClient {
+    /// The client of the tool call being served, if it supports sampling.
+    pub fn current() -> Option<Self> {
+        CURRENT_CLIENT.try_with(Clone::clone).ok().flatten()
+    }
+
+    /// A handle on `peer`, or `None` when it did not advertise sampling.
+    pub fn for_peer(peer: &Peer<RoleServer>) -> Option<Self> {
+        let capabilities = &peer.peer_info()?.capabilities;
+        capabilities
+            .sampling
+            .is_some()
+            .then(|| Self { peer: peer.clone() })
+    }
+
+    /// Ask the client's model to answer `request`. A client that refuses or
+    /// takes longer than two minutes is an internal error.
+    pub async fn create_message(
+        &self,
+        request: CreateMessageRequestParam,
+    ) -> Result<CreateMessageResult, McpError> {
+        match tokio::time::timeout(SAMPLING_TIMEOUT, self.peer.create_message(request)).await {
+            Ok(Ok(result)) => Ok(result),
+            Ok(Err(error)) => Err(McpError::internal_error(
+                format!("the client's model did not answer: {error}"),
+                None,
+            )),
+            Err(_) => Err(McpError::internal_error(
+                "the client's model did not answer in time",
+                None,
+            )),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Resource subscriptions, shared by every resource provider and transport.
+//!
+//! Providers [`publish`](Subscriptions::publish) the URI of a resource when
+//! its contents change. Each session keeps the URIs it subscribed to in its
+//! [`SessionState`], so they go away with the session, and each transport
+//! turns [`Subscriptions::updates_for`] into `notifications/resources/updated`
+//! messages on the session's own channel: the stdio peer, an `/sse` or `/mcp`
+//! event stream, or a `/ws` connection.
+
+use crate::sessions::SessionState;
+use futures::Stream;
+use std::collections::BTreeSet;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Session state key holding the resource URIs a session subscribed to.
+const SUBSCRIPTIONS_KEY: &str = "resources.subscribed";
+/// Updates a slow listener may fall behind by before it skips some.
+const UPDATE_BUFFER: usize = 64;
+
+/// Which sessions want updates for which resources, and the channel updates
+/// travel on. Clones share the channel.
+#[derive(Clone)]
+pub struct Subscriptions {
+    updates: broadcast::Sender<String>,
+}
+
+impl Default for Subscriptions {
+    fn default() -> Self {
+        Self {
+            updates: broadcast::channel(UPDATE_BUFFER).0,
+        }
+    }
+}
+
+impl Subscriptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `uri` to the resources `state`'s session is subscribed to.
+    pub fn subscribe(&self, state: &SessionState, uri: &str) -> Result<(), String> {
+        let mut uris: BTreeSet<String> = state.get(SUBSCRIPTIONS_KEY)?.unwrap_or_default();
+        uris.insert(uri.to_string());
+        state.set(SUBSCRIPTIONS_KEY, &uris)
+    }
+
+    /// Remove `uri` from the resources `state`'s session is subscribed to.
+    pub fn unsubscribe(&self, state: &SessionState, uri: &str) -> Result<(), String> {
+        let mut uris: BTreeSet<String> = state.get(SUBSCRIPTIONS_KEY)?.unwrap_or_default();
+        uris.remove(uri);
+        state.set(SUBSCRIPTIONS_KEY, &uris)
+    }
+
+    /// Whether `state`'s session wants updates for `uri`.
+    pub fn is_subscribed(&self, state: &SessionState, uri: &str) -> bool {
+        state
+            .get::<BTreeSet<String>>(SUBSCRIPTIONS_KEY)
+            .ok()
+            .flatten()
+            .is_some_and(|uris| uris.contains(uri))
+    }
+
+    /// Announce that the resource at `uri` changed.
+    pub fn publish(&self, uri: impl Into<String>) {
+        // Nobody listening is fine; readers poll or subscribe later.
+        let _ = self.updates.send(uri.into());
+    }
+
+    /// URIs of every resource as it changes.
+    pub fn updates(&self) -> broadcast::Receiver<String> {
+        self.updates.subscribe()
+    }
+
+    /// URIs of the resources `state`'s session is subscribed to, as they
+    /// change. Updates the session falls too far behind on are skipped.
+    pub fn updates_for(&self, state: SessionState) -> impl Stream<Item = String> + Send + 'static {
+        let subscriptions = self.clone();
+        futures::stream::unfold(self.updates(), move |mut updates| {
+            let subscriptions = subscriptions.clone();
+            let state = state.clone();
+            async move {
+                loop {
+                    match updates.recv().await {
+                        Ok(uri) if subscriptions.is_subscribed(&state, &uri) => {
+                            return Some((uri, updates));
+                        }
+                        Ok(_) | Err(RecvError::Lagged(_)) => {}
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt as _;
+
+    #[tokio::test]
+    async fn sessions_hear_only_about_what_they_subscribed_to() {
+        let subscriptions = Subscriptions::new();
+        let state = SessionState::default();
+        assert!(!subscriptions.is_subscribed(&state, "schedule://clock"));
+        subscriptions.subscribe(&state, "schedule://clock").unwrap();
+        subscriptions
+            .subscribe(&state, "cedra-docs://move")
+            .unwrap();
+        subscriptions
+            .unsubscribe(&state, "cedra-docs://move")
+            .unwrap();
+        assert!(subscriptions.is_subscribed(&state, "schedule://clock"));
+
+        let mut updates = Box::pin(subscriptions.updates_for(state));
+        subscriptions.publish("cedra-docs://move");
+        subscriptions.publish("schedule://clock");
+        assert_eq!(updates.next().await.as_deref(), Some("schedule://clock"));
+    }
+}
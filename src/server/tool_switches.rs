@@ -0,0 +1,94 @@
+//! Tools switched off at runtime, and the news that the tool list changed.
+//!
+//! A disabled tool is left out of `tools/list` and refused by `tools/call`
+//! until it is enabled again. Every change is announced on
+//! [`ToolSwitches::changes`], which each transport turns into a
+//! `notifications/tools/list_changed` message on its own channel: the stdio
+//! or TCP peer, an `/sse` or `/mcp` event stream, or a `/ws` connection.
+
+use futures::Stream;
+use std::{collections::BTreeSet, sync::RwLock};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Changes a slow listener may fall behind by; one is enough to re-list.
+const CHANGE_BUFFER: usize = 16;
+
+/// Which tools are disabled. Shared by every clone of the service.
+pub struct ToolSwitches {
+    disabled: RwLock<BTreeSet<String>>,
+    changes: broadcast::Sender<()>,
+}
+
+impl Default for ToolSwitches {
+    fn default() -> Self {
+        Self {
+            disabled: RwLock::default(),
+            changes: broadcast::channel(CHANGE_BUFFER).0,
+        }
+    }
+}
+
+impl ToolSwitches {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        !self
+            .disabled
+            .read()
+            .expect("tool switches lock poisoned")
+            .contains(name)
+    }
+
+    /// Enable or disable `name`, returning whether that changed anything.
+    /// Changes are announced to every listener.
+    pub fn set_enabled(&self, name: &str, enabled: bool) -> bool {
+        let mut disabled = self.disabled.write().expect("tool switches lock poisoned");
+        let changed = if enabled {
+            disabled.remove(name)
+        } else {
+            disabled.insert(name.to_string())
+        };
+        drop(disabled);
+        if changed {
+            // Nobody listening is fine; clients list tools when they connect.
+            let _ = self.changes.send(());
+        }
+        changed
+    }
+
+    /// One item per change of the tool list, for as long as the service
+    /// lives. Changes a listener falls behind on are merged into one.
+    pub fn changes(&self) -> impl Stream<Item = ()> + Send + 'static {
+        futures::stream::unfold(self.changes.subscribe(), |mut changes| async move {
+            match changes.recv().await {
+                Ok(()) | Err(RecvError::Lagged(_)) => Some(((), changes)),
+                Err(RecvError::Closed) => None,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt as _;
+
+    #[tokio::test]
+    async fn only_real_changes_are_announced() {
+        let switches = ToolSwitches::new();
+        let mut changes = Box::pin(switches.changes());
+        assert!(switches.is_enabled("roll_dice"));
+
+        assert!(switches.set_enabled("roll_dice", false));
+        assert!(!switches.set_enabled("roll_dice", false));
+        assert!(!switches.is_enabled("roll_dice"));
+        assert!(switches.set_enabled("roll_dice", true));
+        assert!(!switches.set_enabled("echo", true));
+
+        assert_eq!(changes.next().await, Some(()));
+        assert_eq!(changes.next().await, Some(()));
+        assert!(futures::FutureExt::now_or_never(changes.next()).is_none());
+    }
+}
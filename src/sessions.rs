@@ -0,0 +1,390 @@
+use crate::client_log::ClientLog;
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::Instant,
+};
+use tokio::sync::broadcast;
+
+/// Messages a session's event stream may fall behind by before it skips
+/// some.
+const NOTIFICATION_BUFFER: usize = 256;
+
+/// What the server knows about the client behind a session.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ClientInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_fingerprint: Option<String>,
+}
+
+/// Snapshot of one active session for the admin API.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionInfo {
+    pub id: String,
+    pub transport: &'static str,
+    pub opened_at: String,
+    pub age_secs: u64,
+    pub in_flight_calls: u64,
+    /// Whether the client sent `notifications/initialized`.
+    pub initialized: bool,
+    pub client: ClientInfo,
+}
+
+struct SessionEntry {
+    transport: &'static str,
+    opened_at: DateTime<Utc>,
+    opened: Instant,
+    in_flight: Arc<AtomicU64>,
+    initialized: AtomicBool,
+    client: ClientInfo,
+    state: SessionState,
+    notifications: broadcast::Sender<Value>,
+}
+
+tokio::task_local! {
+    static CURRENT_STATE: Option<SessionState>;
+}
+
+/// Run `future` with `state` as the [`SessionState::current`] of its tool
+/// calls.
+pub async fn in_session<F: Future>(state: Option<SessionState>, future: F) -> F::Output {
+    CURRENT_STATE.scope(state, future).await
+}
+
+/// Conversation-scoped values for one session, so tools need no globals.
+/// Values are stored as JSON and dropped when the session ends.
+#[derive(Clone, Default)]
+pub struct SessionState {
+    /// The session's ID; `None` for stdio and TCP connections.
+    id: Option<Arc<str>>,
+    values: Arc<Mutex<HashMap<String, Value>>>,
+    log: Arc<ClientLog>,
+    /// Tool calls running or queued; see [`SessionState::start_call`].
+    calls: Arc<AtomicUsize>,
+}
+
+impl fmt::Debug for SessionState {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("SessionState")
+            .field("values", &self.values)
+            .field("log_level", &self.log.level())
+            .finish()
+    }
+}
+
+impl SessionState {
+    /// Empty state whose log messages are sent with `log`.
+    pub fn with_log(log: ClientLog) -> Self {
+        Self {
+            id: None,
+            values: Arc::default(),
+            log: Arc::new(log),
+            calls: Arc::default(),
+        }
+    }
+
+    /// The state of the session the current tool call belongs to, if any.
+    /// Calls over HTTP without a known `mcp-session-id` have none.
+    pub fn current() -> Option<Self> {
+        CURRENT_STATE.try_with(Clone::clone).ok().flatten()
+    }
+
+    /// The ID of the session, if it was registered with one.
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// The value under `key`, or `None` if it was never set.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, String> {
+        self.lock()
+            .get(key)
+            .map(|value| T::deserialize(value))
+            .transpose()
+            .map_err(|error| format!("session value '{key}' has another type: {error}"))
+    }
+
+    pub fn set<T: Serialize>(&self, key: &str, value: &T) -> Result<(), String> {
+        let value = serde_json::to_value(value)
+            .map_err(|error| format!("cannot store session value '{key}': {error}"))?;
+        self.lock().insert(key.to_string(), value);
+        Ok(())
+    }
+
+    /// Replace the value under `key` with `update` of the current one, as one
+    /// step, and return the new value.
+    pub fn update<T: Serialize + DeserializeOwned>(
+        &self,
+        key: &str,
+        update: impl FnOnce(Option<T>) -> T,
+    ) -> Result<T, String> {
+        let mut values = self.lock();
+        let current = values
+            .get(key)
+            .map(|value| T::deserialize(value))
+            .transpose()
+            .map_err(|error| format!("session value '{key}' has another type: {error}"))?;
+        let updated = update(current);
+        let value = serde_json::to_value(&updated)
+            .map_err(|error| format!("cannot store session value '{key}': {error}"))?;
+        values.insert(key.to_string(), value);
+        Ok(updated)
+    }
+
+    /// Remove `key`, returning whether it was set.
+    pub fn remove(&self, key: &str) -> bool {
+        self.lock().remove(key).is_some()
+    }
+
+    /// Log messages the session asked for with `logging/setLevel`.
+    pub fn log(&self) -> &ClientLog {
+        &self.log
+    }
+
+    /// Count a tool call against the session while the returned guard
+    /// lives, unless `max` calls are already counted (0 means no limit).
+    pub fn start_call(&self, max: usize) -> Option<SessionCall> {
+        self.calls
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |calls| {
+                (max == 0 || calls < max).then_some(calls + 1)
+            })
+            .ok()?;
+        Some(SessionCall(Arc::clone(&self.calls)))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Value>> {
+        self.values.lock().expect("session state lock poisoned")
+    }
+}
+
+/// Active streaming sessions, keyed by session ID.
+///
+/// Every request looks its session up, while only connects and disconnects
+/// change the map, so lookups share a read lock.
+#[derive(Default)]
+pub struct SessionRegistry {
+    sessions: RwLock<BTreeMap<String, SessionEntry>>,
+}
+
+impl SessionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a session; it is removed again when the returned handle drops.
+    pub fn register(
+        self: &Arc<Self>,
+        id: String,
+        transport: &'static str,
+        client: ClientInfo,
+    ) -> SessionHandle {
+        let notifications = broadcast::channel(NOTIFICATION_BUFFER).0;
+        let entry = SessionEntry {
+            transport,
+            opened_at: Utc::now(),
+            opened: Instant::now(),
+            in_flight: Arc::new(AtomicU64::new(0)),
+            initialized: AtomicBool::new(false),
+            client,
+            state: SessionState {
+                id: Some(Arc::from(id.as_str())),
+                ..SessionState::with_log(ClientLog::sent_on(&notifications))
+            },
+            notifications,
+        };
+        self.write().insert(id.clone(), entry);
+        SessionHandle {
+            id,
+            registry: self.clone(),
+        }
+    }
+
+    /// Count a call as in flight for `id` until the guard drops. Unknown
+    /// sessions are ignored.
+    pub fn track_call(&self, id: &str) -> Option<CallGuard> {
+        let in_flight = self.read().get(id)?.in_flight.clone();
+        in_flight.fetch_add(1, Ordering::AcqRel);
+        Some(CallGuard(in_flight))
+    }
+
+    /// Record that session `id`'s client finished initializing. Returns
+    /// whether the session is known.
+    pub fn mark_initialized(&self, id: &str) -> bool {
+        self.read()
+            .get(id)
+            .map(|entry| entry.initialized.store(true, Ordering::Release))
+            .is_some()
+    }
+
+    /// State of session `id`, or `None` for unknown sessions.
+    pub fn state(&self, id: &str) -> Option<SessionState> {
+        Some(self.read().get(id)?.state.clone())
+    }
+
+    /// Messages pushed to session `id` with [`Self::notify`], or `None` for
+    /// unknown sessions.
+    pub fn notifications(&self, id: &str) -> Option<broadcast::Receiver<Value>> {
+        Some(self.read().get(id)?.notifications.subscribe())
+    }
+
+    /// Push `message` to session `id`'s event stream. Returns whether anyone
+    /// was listening.
+    pub fn notify(&self, id: &str, message: Value) -> bool {
+        self.read()
+            .get(id)
+            .is_some_and(|entry| entry.notifications.send(message).is_ok())
+    }
+
+    /// Whether an event stream is open on session `id` to receive what
+    /// [`Self::notify`] pushes.
+    pub fn is_listening(&self, id: &str) -> bool {
+        self.read()
+            .get(id)
+            .is_some_and(|entry| entry.notifications.receiver_count() > 0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    /// Active sessions, oldest first.
+    pub fn list(&self) -> Vec<SessionInfo> {
+        let mut sessions: Vec<SessionInfo> = self
+            .read()
+            .iter()
+            .map(|(id, entry)| SessionInfo {
+                id: id.clone(),
+                transport: entry.transport,
+                opened_at: entry.opened_at.to_rfc3339(),
+                age_secs: entry.opened.elapsed().as_secs(),
+                in_flight_calls: entry.in_flight.load(Ordering::Acquire),
+                initialized: entry.initialized.load(Ordering::Acquire),
+                client: entry.client.clone(),
+            })
+            .collect();
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.age_secs));
+        sessions
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, BTreeMap<String, SessionEntry>> {
+        self.sessions
+            .read()
+            .expect("session registry lock poisoned")
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, BTreeMap<String, SessionEntry>> {
+        self.sessions
+            .write()
+            .expect("session registry lock poisoned")
+    }
+}
+
+/// Keeps a session registered; see [`SessionRegistry::register`].
+pub struct SessionHandle {
+    id: String,
+    registry: Arc<SessionRegistry>,
+}
+
+impl SessionHandle {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Drop for SessionHandle {
+    fn drop(&mut self) {
+        self.registry.write().remove(&self.id);
+    }
+}
+
+/// One of a session's calls; see [`SessionState::start_call`].
+pub struct SessionCall(Arc<AtomicUsize>);
+
+impl Drop for SessionCall {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Marks one call in flight; see [`SessionRegistry::track_call`].
+pub struct CallGuard(Arc<AtomicU64>);
+
+impl Drop for CallGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_sessions_and_in_flight_calls() {
+        let registry = Arc::new(SessionRegistry::new());
+        let handle = registry.register("abc".to_string(), "sse", ClientInfo::default());
+        assert_eq!(registry.len(), 1);
+
+        let call = registry.track_call("abc").expect("session is registered");
+        assert!(registry.track_call("missing").is_none());
+        assert_eq!(registry.list()[0].in_flight_calls, 1);
+        drop(call);
+        assert_eq!(registry.list()[0].in_flight_calls, 0);
+        assert!(!registry.list()[0].initialized);
+        assert!(registry.mark_initialized("abc"));
+        assert!(!registry.mark_initialized("missing"));
+        assert!(registry.list()[0].initialized);
+
+        assert!(!registry.notify("abc", Value::Null));
+        assert!(!registry.is_listening("abc"));
+        let mut notifications = registry.notifications("abc").expect("registered");
+        assert!(registry.is_listening("abc"));
+        assert!(registry.notify("abc", serde_json::json!({ "n": 1 })));
+        assert_eq!(notifications.try_recv(), Ok(serde_json::json!({ "n": 1 })));
+
+        drop(handle);
+        assert!(registry.is_empty());
+    }
+
+    #[tokio::test]
+    async fn session_state_is_scoped_and_dropped_with_the_session() {
+        let registry = Arc::new(SessionRegistry::new());
+        let handle = registry.register("abc".to_string(), "sse", ClientInfo::default());
+        let state = registry.state("abc").expect("session is registered");
+        assert!(SessionState::current().is_none());
+
+        in_session(Some(state.clone()), async {
+            let current = SessionState::current().expect("state is in scope");
+            current.set("turns", &3u32).expect("u32 serializes");
+        })
+        .await;
+        assert_eq!(state.get::<u32>("turns"), Ok(Some(3)));
+        assert_eq!(
+            state.update("turns", |turns: Option<u32>| turns.unwrap_or(0) + 1),
+            Ok(4)
+        );
+        assert!(state.get::<String>("turns").is_err());
+        assert!(state.remove("turns"));
+        assert_eq!(state.get::<u32>("turns"), Ok(None));
+
+        let weak = Arc::downgrade(&state.values);
+        drop(state);
+        drop(handle);
+        assert!(registry.state("abc").is_none());
+        assert!(weak.upgrade().is_none());
+    }
+}
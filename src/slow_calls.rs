@@ -0,0 +1,224 @@
+use chrono::Utc;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::{cell::RefCell, collections::VecDeque, env, future::Future, sync::Mutex, time::Duration};
+
+const DEFAULT_THRESHOLD: Duration = Duration::from_millis(1000);
+const DEFAULT_CAPACITY: usize = 50;
+
+tokio::task_local! {
+    static UPSTREAM_TIMINGS: RefCell<Vec<UpstreamTiming>>;
+}
+
+/// Time spent in one outbound call made while serving a tool call.
+#[derive(Debug, Clone, Serialize)]
+pub struct UpstreamTiming {
+    pub name: String,
+    pub duration_ms: u64,
+}
+
+/// A tool call that exceeded the slow-call threshold.
+#[derive(Debug, Clone, Serialize)]
+pub struct SlowCall {
+    pub timestamp: String,
+    pub tool: String,
+    pub args_digest: String,
+    pub duration_ms: u64,
+    pub outcome: &'static str,
+    pub upstream: Vec<UpstreamTiming>,
+}
+
+/// Threshold and buffer size for [`SlowCallLog`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlowCallSettings {
+    pub threshold: Duration,
+    pub capacity: usize,
+}
+
+impl SlowCallSettings {
+    /// Read `INFERENCO_MCP_SLOW_CALL_MS` and `INFERENCO_MCP_SLOW_CALL_BUFFER`.
+    pub fn from_env() -> Result<Self, String> {
+        let threshold = match env::var("INFERENCO_MCP_SLOW_CALL_MS") {
+            Ok(value) => Duration::from_millis(value.parse().map_err(|_| {
+                format!(
+                    "INFERENCO_MCP_SLOW_CALL_MS must be a number of milliseconds, got '{value}'"
+                )
+            })?),
+            Err(_) => DEFAULT_THRESHOLD,
+        };
+        let capacity = match env::var("INFERENCO_MCP_SLOW_CALL_BUFFER") {
+            Ok(value) => value.parse().map_err(|_| {
+                format!("INFERENCO_MCP_SLOW_CALL_BUFFER must be a number of entries, got '{value}'")
+            })?,
+            Err(_) => DEFAULT_CAPACITY,
+        };
+        Ok(Self {
+            threshold,
+            capacity,
+        })
+    }
+}
+
+impl Default for SlowCallSettings {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+            capacity: DEFAULT_CAPACITY,
+        }
+    }
+}
+
+/// Logs tool calls slower than a threshold and keeps the most recent ones in
+/// a ring buffer for the admin API.
+pub struct SlowCallLog {
+    threshold: Duration,
+    capacity: usize,
+    recent: Mutex<VecDeque<SlowCall>>,
+}
+
+impl SlowCallLog {
+    pub fn new(threshold: Duration, capacity: usize) -> Self {
+        Self {
+            threshold,
+            capacity,
+            recent: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    pub fn from_settings(settings: &SlowCallSettings) -> Self {
+        Self::new(settings.threshold, settings.capacity)
+    }
+
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+
+    /// Record a finished call, logging and buffering it when it was slow.
+    pub fn observe(
+        &self,
+        tool: &str,
+        arguments: &serde_json::Value,
+        duration: Duration,
+        outcome: &'static str,
+        upstream: Vec<UpstreamTiming>,
+    ) {
+        if duration < self.threshold {
+            return;
+        }
+        let call = SlowCall {
+            timestamp: Utc::now().to_rfc3339(),
+            tool: tool.to_string(),
+            args_digest: args_digest(arguments),
+            duration_ms: duration.as_millis() as u64,
+            outcome,
+            upstream,
+        };
+        tracing::warn!(
+            tool = %call.tool,
+            args_digest = %call.args_digest,
+            duration_ms = call.duration_ms,
+            threshold_ms = self.threshold.as_millis() as u64,
+            outcome,
+            upstream = ?call.upstream,
+            "slow tool call"
+        );
+
+        if self.capacity == 0 {
+            return;
+        }
+        let mut recent = self.recent.lock().expect("slow call lock poisoned");
+        if recent.len() == self.capacity {
+            recent.pop_front();
+        }
+        recent.push_back(call);
+    }
+
+    /// Buffered slow calls, newest first.
+    pub fn recent(&self) -> Vec<SlowCall> {
+        let recent = self.recent.lock().expect("slow call lock poisoned");
+        recent.iter().rev().cloned().collect()
+    }
+}
+
+impl Default for SlowCallLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_THRESHOLD, DEFAULT_CAPACITY)
+    }
+}
+
+/// Run `future` while collecting the upstream timings it reports.
+pub async fn collect_upstream_timings<F: Future>(future: F) -> (F::Output, Vec<UpstreamTiming>) {
+    UPSTREAM_TIMINGS
+        .scope(RefCell::new(Vec::new()), async move {
+            let output = future.await;
+            let timings = UPSTREAM_TIMINGS.with(|timings| timings.take());
+            (output, timings)
+        })
+        .await
+}
+
+/// Attribute time spent on an outbound call to the current tool call. Does
+/// nothing outside [`collect_upstream_timings`].
+pub fn record_upstream_timing(name: &str, duration: Duration) {
+    let _ = UPSTREAM_TIMINGS.try_with(|timings| {
+        timings.borrow_mut().push(UpstreamTiming {
+            name: name.to_string(),
+            duration_ms: duration.as_millis() as u64,
+        })
+    });
+}
+
+/// Short digest identifying a set of arguments without logging their values.
+pub fn args_digest(arguments: &serde_json::Value) -> String {
+    let digest = Sha256::digest(arguments.to_string().as_bytes());
+    digest[..6]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn buffers_only_slow_calls_up_to_capacity() {
+        let log = SlowCallLog::new(Duration::from_millis(100), 2);
+        let args = json!({ "message": "hi" });
+        log.observe(
+            "echo",
+            &args,
+            Duration::from_millis(5),
+            "success",
+            Vec::new(),
+        );
+        for tool in ["first", "second", "third"] {
+            log.observe(
+                tool,
+                &args,
+                Duration::from_millis(150),
+                "success",
+                Vec::new(),
+            );
+        }
+
+        let recent = log.recent();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].tool, "third");
+        assert_eq!(recent[1].tool, "second");
+        assert_eq!(recent[0].args_digest, args_digest(&args));
+    }
+
+    #[tokio::test]
+    async fn collects_upstream_timings_within_scope() {
+        record_upstream_timing("ignored", Duration::from_millis(1));
+        let ((), timings) = collect_upstream_timings(async {
+            record_upstream_timing("docs", Duration::from_millis(42));
+        })
+        .await;
+        assert_eq!(timings.len(), 1);
+        assert_eq!(timings[0].name, "docs");
+        assert_eq!(timings[0].duration_ms, 42);
+    }
+}
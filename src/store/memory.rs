@@ -0,0 +1,288 @@
+use super::{
+    overflow, CounterStore, HistoryEntry, HistoryRecord, HistoryStore, KvEntry, KvRecord, KvStore,
+    Note, NoteRecord, NoteStore, StateDump, StateSnapshot,
+};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Mutex, RwLock,
+    },
+};
+
+/// State that lives as long as the process.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    /// Existing counters are updated under the shared read lock; only
+    /// creating and resetting counters takes the write lock.
+    counters: RwLock<HashMap<String, AtomicI64>>,
+    /// namespace -> key -> entry
+    kv: Mutex<HashMap<String, BTreeMap<String, KvEntry>>>,
+    /// namespace -> calls, oldest first
+    history: Mutex<HashMap<String, VecDeque<HistoryEntry>>>,
+    /// namespace -> id -> note
+    notes: Mutex<HashMap<String, BTreeMap<u64, Note>>>,
+}
+
+impl CounterStore for MemoryStore {
+    fn add(&self, name: &str, delta: i64) -> Result<i64, String> {
+        let add = |value: &AtomicI64| {
+            value
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |current| {
+                    current.checked_add(delta)
+                })
+                .map(|previous| previous + delta)
+                .map_err(|_| overflow(name))
+        };
+        if let Some(value) = self
+            .counters
+            .read()
+            .expect("counter lock poisoned")
+            .get(name)
+        {
+            return add(value);
+        }
+        let mut counters = self.counters.write().expect("counter lock poisoned");
+        add(counters.entry(name.to_string()).or_default())
+    }
+
+    fn get(&self, name: &str) -> Result<i64, String> {
+        let counters = self.counters.read().expect("counter lock poisoned");
+        Ok(counters
+            .get(name)
+            .map_or(0, |value| value.load(Ordering::Acquire)))
+    }
+
+    fn reset(&self, name: &str) -> Result<(), String> {
+        let mut counters = self.counters.write().expect("counter lock poisoned");
+        counters.remove(name);
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<(String, i64)>, String> {
+        let counters = self.counters.read().expect("counter lock poisoned");
+        let mut list: Vec<(String, i64)> = counters
+            .iter()
+            .map(|(name, value)| (name.clone(), value.load(Ordering::Acquire)))
+            .collect();
+        list.sort();
+        Ok(list)
+    }
+}
+
+impl KvStore for MemoryStore {
+    fn set(
+        &self,
+        namespace: &str,
+        key: &str,
+        entry: KvEntry,
+        max_keys: usize,
+        now: i64,
+    ) -> Result<bool, String> {
+        let mut kv = self.kv.lock().expect("kv lock poisoned");
+        let entries = kv.entry(namespace.to_string()).or_default();
+        entries.retain(|_, entry| entry.is_live(now));
+        if !entries.contains_key(key) && entries.len() >= max_keys {
+            return Ok(false);
+        }
+        entries.insert(key.to_string(), entry);
+        Ok(true)
+    }
+
+    fn lookup(&self, namespace: &str, key: &str, now: i64) -> Result<Option<KvEntry>, String> {
+        let mut kv = self.kv.lock().expect("kv lock poisoned");
+        let Some(entries) = kv.get_mut(namespace) else {
+            return Ok(None);
+        };
+        match entries.get(key) {
+            Some(entry) if entry.is_live(now) => Ok(Some(entry.clone())),
+            Some(_) => {
+                entries.remove(key);
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn delete(&self, namespace: &str, key: &str, now: i64) -> Result<bool, String> {
+        let mut kv = self.kv.lock().expect("kv lock poisoned");
+        Ok(kv
+            .get_mut(namespace)
+            .and_then(|entries| entries.remove(key))
+            .is_some_and(|entry| entry.is_live(now)))
+    }
+
+    fn keys(&self, namespace: &str, prefix: &str, now: i64) -> Result<Vec<String>, String> {
+        let kv = self.kv.lock().expect("kv lock poisoned");
+        Ok(kv
+            .get(namespace)
+            .into_iter()
+            .flat_map(|entries| entries.range(prefix.to_string()..))
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .filter(|(_, entry)| entry.is_live(now))
+            .map(|(key, _)| key.clone())
+            .collect())
+    }
+}
+
+impl HistoryStore for MemoryStore {
+    fn append(
+        &self,
+        namespace: &str,
+        entry: HistoryEntry,
+        max_entries: usize,
+        min_at: i64,
+    ) -> Result<(), String> {
+        let mut history = self.history.lock().expect("history lock poisoned");
+        let calls = history.entry(namespace.to_string()).or_default();
+        calls.push_back(entry);
+        while calls.len() > max_entries || calls.front().is_some_and(|call| call.at < min_at) {
+            calls.pop_front();
+        }
+        Ok(())
+    }
+
+    fn recent(
+        &self,
+        namespace: &str,
+        tool: Option<&str>,
+        limit: usize,
+        min_at: i64,
+    ) -> Result<Vec<HistoryEntry>, String> {
+        let history = self.history.lock().expect("history lock poisoned");
+        Ok(history
+            .get(namespace)
+            .into_iter()
+            .flat_map(|calls| calls.iter().rev())
+            .filter(|call| call.at >= min_at && tool.is_none_or(|tool| call.tool == tool))
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+}
+
+impl NoteStore for MemoryStore {
+    fn add_note(
+        &self,
+        namespace: &str,
+        text: &str,
+        tags: &[String],
+        created_at: i64,
+        max_notes: usize,
+    ) -> Result<Option<Note>, String> {
+        let mut notes = self.notes.lock().expect("notes lock poisoned");
+        let notes = notes.entry(namespace.to_string()).or_default();
+        if notes.len() >= max_notes {
+            return Ok(None);
+        }
+        let note = Note {
+            id: notes.keys().next_back().map_or(1, |id| id + 1),
+            text: text.to_string(),
+            tags: tags.to_vec(),
+            created_at,
+        };
+        notes.insert(note.id, note.clone());
+        Ok(Some(note))
+    }
+
+    fn note(&self, namespace: &str, id: u64) -> Result<Option<Note>, String> {
+        let notes = self.notes.lock().expect("notes lock poisoned");
+        Ok(notes
+            .get(namespace)
+            .and_then(|notes| notes.get(&id))
+            .cloned())
+    }
+
+    fn notes(&self, namespace: &str) -> Result<Vec<Note>, String> {
+        let notes = self.notes.lock().expect("notes lock poisoned");
+        Ok(notes
+            .get(namespace)
+            .map(|notes| notes.values().cloned().collect())
+            .unwrap_or_default())
+    }
+}
+
+impl StateDump for MemoryStore {
+    fn dump(&self) -> Result<StateSnapshot, String> {
+        fn sorted<V>(map: &HashMap<String, V>) -> Vec<(&String, &V)> {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by_key(|(namespace, _)| *namespace);
+            entries
+        }
+        let kv = self.kv.lock().expect("kv lock poisoned");
+        let notes = self.notes.lock().expect("notes lock poisoned");
+        let history = self.history.lock().expect("history lock poisoned");
+        Ok(StateSnapshot {
+            counters: self.list()?.into_iter().collect(),
+            kv: sorted(&kv)
+                .into_iter()
+                .flat_map(|(namespace, entries)| {
+                    entries.iter().map(|(key, entry)| KvRecord {
+                        namespace: namespace.clone(),
+                        key: key.clone(),
+                        value: entry.value.clone(),
+                        expires_at: entry.expires_at,
+                    })
+                })
+                .collect(),
+            notes: sorted(&notes)
+                .into_iter()
+                .flat_map(|(namespace, notes)| {
+                    notes.values().map(|note| NoteRecord {
+                        namespace: namespace.clone(),
+                        note: note.clone(),
+                    })
+                })
+                .collect(),
+            history: sorted(&history)
+                .into_iter()
+                .flat_map(|(namespace, calls)| {
+                    calls.iter().map(|entry| HistoryRecord {
+                        namespace: namespace.clone(),
+                        entry: entry.clone(),
+                    })
+                })
+                .collect(),
+            ..StateSnapshot::default()
+        })
+    }
+
+    fn restore(
+        &self,
+        staged: &StateSnapshot,
+        max_history: usize,
+        min_at: i64,
+    ) -> Result<(), String> {
+        // Taken in the order `dump` takes them.
+        let mut kv = self.kv.lock().expect("kv lock poisoned");
+        let mut notes = self.notes.lock().expect("notes lock poisoned");
+        let mut history = self.history.lock().expect("history lock poisoned");
+        let mut counters = self.counters.write().expect("counter lock poisoned");
+        for (name, value) in &staged.counters {
+            counters.insert(name.clone(), AtomicI64::new(*value));
+        }
+        for record in &staged.kv {
+            kv.entry(record.namespace.clone()).or_default().insert(
+                record.key.clone(),
+                KvEntry {
+                    value: record.value.clone(),
+                    expires_at: record.expires_at,
+                },
+            );
+        }
+        for NoteRecord { namespace, note } in &staged.notes {
+            notes
+                .entry(namespace.clone())
+                .or_default()
+                .insert(note.id, note.clone());
+        }
+        for HistoryRecord { namespace, entry } in &staged.history {
+            let calls = history.entry(namespace.clone()).or_default();
+            calls.push_back(entry.clone());
+            while calls.len() > max_history || calls.front().is_some_and(|call| call.at < min_at) {
+                calls.pop_front();
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,586 @@
+//! State kept by stateful tools, optionally persisted across restarts.
+//!
+//! Tools read and write through [`CounterStore`], [`KvStore`],
+//! [`HistoryStore`], and [`NoteStore`] rather than holding their own
+//! `Mutex`es, so the backend is chosen at startup: in memory by default, or
+//! SQLite / sled when built with the `sqlite` / `sled` feature and selected
+//! with `INFERENCO_MCP_STATE_BACKEND`.
+//!
+//! Key-value entries, call history, and notes are isolated by namespace.
+//! Transports run each request inside [`in_namespace`] with an identity for
+//! the caller, and tools read it back with [`current_namespace`].
+
+mod memory;
+#[cfg(feature = "sled")]
+mod sled;
+mod snapshot;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+pub use memory::MemoryStore;
+#[cfg(feature = "sled")]
+pub use sled::SledStore;
+pub use snapshot::{
+    export, import, HistoryRecord, ImportError, ImportSummary, KvRecord, NoteRecord, StateSnapshot,
+    SNAPSHOT_FORMAT,
+};
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
+use crate::config::parse_env;
+use serde::{Deserialize, Serialize};
+use std::{env, future::Future, path::PathBuf, sync::Arc, time::Duration};
+
+/// Namespace for callers without an API key or session, such as stdio.
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+tokio::task_local! {
+    static NAMESPACE: String;
+}
+
+/// Run `future` with `namespace` as the key-value namespace of its tool calls.
+pub async fn in_namespace<F: Future>(namespace: String, future: F) -> F::Output {
+    NAMESPACE.scope(namespace, future).await
+}
+
+/// The namespace set by [`in_namespace`], or [`DEFAULT_NAMESPACE`].
+pub fn current_namespace() -> String {
+    NAMESPACE
+        .try_with(Clone::clone)
+        .unwrap_or_else(|_| DEFAULT_NAMESPACE.to_string())
+}
+
+/// Named integer counters, each starting at zero.
+pub trait CounterStore: Send + Sync {
+    /// Add `delta` to counter `name` and return the new value. Concurrent
+    /// calls never lose an update.
+    fn add(&self, name: &str, delta: i64) -> Result<i64, String>;
+
+    /// Current value of `name`; zero if it was never set.
+    fn get(&self, name: &str) -> Result<i64, String>;
+
+    /// Forget `name`, so it reads as zero and is no longer listed.
+    fn reset(&self, name: &str) -> Result<(), String>;
+
+    /// Every counter with its value, sorted by name.
+    fn list(&self) -> Result<Vec<(String, i64)>, String>;
+}
+
+/// A stored value and its expiry in Unix milliseconds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KvEntry {
+    pub value: String,
+    pub expires_at: Option<i64>,
+}
+
+impl KvEntry {
+    pub fn is_live(&self, now: i64) -> bool {
+        self.expires_at.is_none_or(|at| at > now)
+    }
+}
+
+/// String values by key, isolated per namespace. Expired entries behave as
+/// if they were never set; `now` is Unix milliseconds.
+pub trait KvStore: Send + Sync {
+    /// Store `entry` under `key`, replacing any previous value. Returns
+    /// `false` without storing when `key` is new and `namespace` already
+    /// holds `max_keys` live entries.
+    fn set(
+        &self,
+        namespace: &str,
+        key: &str,
+        entry: KvEntry,
+        max_keys: usize,
+        now: i64,
+    ) -> Result<bool, String>;
+
+    fn lookup(&self, namespace: &str, key: &str, now: i64) -> Result<Option<KvEntry>, String>;
+
+    /// Remove `key`; whether a live entry was removed.
+    fn delete(&self, namespace: &str, key: &str, now: i64) -> Result<bool, String>;
+
+    /// Live keys starting with `prefix`, sorted.
+    fn keys(&self, namespace: &str, prefix: &str, now: i64) -> Result<Vec<String>, String>;
+}
+
+/// One completed tool call; `at` is Unix milliseconds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub at: i64,
+    pub tool: String,
+    /// Arguments as redacted JSON text.
+    pub arguments: String,
+    /// `success`, `tool_error`, or `error`, as in the call metrics.
+    pub outcome: String,
+    /// The start of the result text, redacted.
+    pub summary: String,
+}
+
+/// Recent tool calls per namespace, oldest dropped first.
+pub trait HistoryStore: Send + Sync {
+    /// Append `entry`, then drop all but the newest `max_entries` and any
+    /// entry older than `min_at`.
+    fn append(
+        &self,
+        namespace: &str,
+        entry: HistoryEntry,
+        max_entries: usize,
+        min_at: i64,
+    ) -> Result<(), String>;
+
+    /// Up to `limit` entries no older than `min_at`, newest first, optionally
+    /// only calls to `tool`.
+    fn recent(
+        &self,
+        namespace: &str,
+        tool: Option<&str>,
+        limit: usize,
+        min_at: i64,
+    ) -> Result<Vec<HistoryEntry>, String>;
+}
+
+/// A short note; `created_at` is Unix milliseconds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Note {
+    /// Numbered from 1 within each namespace, in the order notes were added.
+    pub id: u64,
+    pub text: String,
+    pub tags: Vec<String>,
+    pub created_at: i64,
+}
+
+/// Notes per namespace.
+pub trait NoteStore: Send + Sync {
+    /// Store a note under the next id. Returns `None` without storing when
+    /// `namespace` already holds `max_notes` notes.
+    fn add_note(
+        &self,
+        namespace: &str,
+        text: &str,
+        tags: &[String],
+        created_at: i64,
+        max_notes: usize,
+    ) -> Result<Option<Note>, String>;
+
+    fn note(&self, namespace: &str, id: u64) -> Result<Option<Note>, String>;
+
+    /// Every note in `namespace`, oldest first.
+    fn notes(&self, namespace: &str) -> Result<Vec<Note>, String>;
+}
+
+/// Enumeration and bulk restore of everything a backend holds, across
+/// namespaces.
+pub trait StateDump: Send + Sync {
+    /// Counters, key-value entries (expired ones included), notes, and
+    /// history, in [`StateSnapshot`] order; the other fields are left empty.
+    fn dump(&self) -> Result<StateSnapshot, String>;
+
+    /// Write all of `staged` or, on error, none of it: counters are set,
+    /// key-value entries replace those under the same key, notes are stored
+    /// under their ids, and history is appended, then trimmed as by
+    /// [`HistoryStore::append`]. Limits are the caller's to check.
+    fn restore(
+        &self,
+        staged: &StateSnapshot,
+        max_history: usize,
+        min_at: i64,
+    ) -> Result<(), String>;
+}
+
+/// Every kind of tool state, as provided by each backend.
+pub trait StateStore: CounterStore + KvStore + HistoryStore + NoteStore + StateDump {}
+
+impl<T: CounterStore + KvStore + HistoryStore + NoteStore + StateDump> StateStore for T {}
+
+fn overflow(name: &str) -> String {
+    format!("counter '{name}' would overflow")
+}
+
+/// Bounds on what one namespace may store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KvLimits {
+    pub max_keys: usize,
+    pub max_value_bytes: usize,
+}
+
+impl Default for KvLimits {
+    fn default() -> Self {
+        Self {
+            max_keys: 1000,
+            max_value_bytes: 64 * 1024,
+        }
+    }
+}
+
+impl KvLimits {
+    /// Read `INFERENCO_MCP_KV_MAX_KEYS` and `INFERENCO_MCP_KV_MAX_VALUE_BYTES`.
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = Self::default();
+        Ok(Self {
+            max_keys: parse_env("INFERENCO_MCP_KV_MAX_KEYS", "a number of keys")?
+                .unwrap_or(defaults.max_keys),
+            max_value_bytes: parse_env("INFERENCO_MCP_KV_MAX_VALUE_BYTES", "a number of bytes")?
+                .unwrap_or(defaults.max_value_bytes),
+        })
+    }
+}
+
+/// Bounds on the notes one namespace may keep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoteLimits {
+    pub max_notes: usize,
+    pub max_note_bytes: usize,
+}
+
+impl Default for NoteLimits {
+    fn default() -> Self {
+        Self {
+            max_notes: 1000,
+            max_note_bytes: 4096,
+        }
+    }
+}
+
+impl NoteLimits {
+    /// Read `INFERENCO_MCP_NOTES_MAX_COUNT` and `INFERENCO_MCP_NOTES_MAX_BYTES`.
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = Self::default();
+        Ok(Self {
+            max_notes: parse_env("INFERENCO_MCP_NOTES_MAX_COUNT", "a number of notes")?
+                .unwrap_or(defaults.max_notes),
+            max_note_bytes: parse_env("INFERENCO_MCP_NOTES_MAX_BYTES", "a number of bytes")?
+                .unwrap_or(defaults.max_note_bytes),
+        })
+    }
+}
+
+/// Retention of the call history kept per namespace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoryLimits {
+    /// Calls kept per namespace; 0 turns history off.
+    pub max_entries: usize,
+    pub max_age: Option<Duration>,
+}
+
+impl Default for HistoryLimits {
+    fn default() -> Self {
+        Self {
+            max_entries: 100,
+            max_age: None,
+        }
+    }
+}
+
+impl HistoryLimits {
+    /// Read `INFERENCO_MCP_HISTORY_MAX_ENTRIES` and
+    /// `INFERENCO_MCP_HISTORY_MAX_AGE_SECS`.
+    pub fn from_env() -> Result<Self, String> {
+        Ok(Self {
+            max_entries: parse_env("INFERENCO_MCP_HISTORY_MAX_ENTRIES", "a number of calls")?
+                .unwrap_or(Self::default().max_entries),
+            max_age: parse_env("INFERENCO_MCP_HISTORY_MAX_AGE_SECS", "a number of seconds")?
+                .map(Duration::from_secs),
+        })
+    }
+
+    /// Oldest `at` still retained when the time is `now`.
+    pub fn min_at(&self, now: i64) -> i64 {
+        self.max_age.map_or(i64::MIN, |age| {
+            now.saturating_sub(i64::try_from(age.as_millis()).unwrap_or(i64::MAX))
+        })
+    }
+}
+
+/// Every per-namespace bound, as checked by [`import`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StateLimits {
+    pub kv: KvLimits,
+    pub notes: NoteLimits,
+    pub history: HistoryLimits,
+}
+
+impl StateLimits {
+    /// Read the key-value, note, and history limits.
+    pub fn from_env() -> Result<Self, String> {
+        Ok(Self {
+            kv: KvLimits::from_env()?,
+            notes: NoteLimits::from_env()?,
+            history: HistoryLimits::from_env()?,
+        })
+    }
+}
+
+/// Where tool state is kept.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum StateBackend {
+    #[default]
+    Memory,
+    #[cfg(feature = "sqlite")]
+    Sqlite(PathBuf),
+    #[cfg(feature = "sled")]
+    Sled(PathBuf),
+}
+
+impl StateBackend {
+    /// Read `INFERENCO_MCP_STATE_BACKEND` and `INFERENCO_MCP_STATE_PATH`.
+    pub fn from_env() -> Result<Self, String> {
+        let backend = env::var("INFERENCO_MCP_STATE_BACKEND")
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        let path = || match env::var("INFERENCO_MCP_STATE_PATH") {
+            Ok(path) if !path.trim().is_empty() => Ok(PathBuf::from(path.trim())),
+            _ => Err(format!(
+                "INFERENCO_MCP_STATE_BACKEND={backend} needs INFERENCO_MCP_STATE_PATH"
+            )),
+        };
+        match backend.as_str() {
+            "" | "memory" => Ok(Self::Memory),
+            #[cfg(feature = "sqlite")]
+            "sqlite" => Ok(Self::Sqlite(path()?)),
+            #[cfg(feature = "sled")]
+            "sled" => Ok(Self::Sled(path()?)),
+            #[allow(unreachable_patterns)]
+            "sqlite" | "sled" => {
+                let _ = path;
+                Err(format!(
+                    "INFERENCO_MCP_STATE_BACKEND={backend} requires building with `--features {backend}`"
+                ))
+            }
+            other => Err(format!(
+                "INFERENCO_MCP_STATE_BACKEND must be memory, sqlite, or sled, got '{other}'"
+            )),
+        }
+    }
+
+    /// Open the configured store.
+    pub fn open(&self) -> Result<Arc<dyn StateStore>, String> {
+        match self {
+            Self::Memory => Ok(Arc::new(MemoryStore::default())),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(path) => Ok(Arc::new(SqliteStore::open(path)?)),
+            #[cfg(feature = "sled")]
+            Self::Sled(path) => Ok(Arc::new(SledStore::open(path)?)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exercise(store: &dyn StateStore) {
+        assert_eq!(store.add("a", 1), Ok(1));
+        assert_eq!(store.add("a", 2), Ok(3));
+        assert_eq!(store.add("b", -1), Ok(-1));
+        assert_eq!(store.add("b", i64::MIN), Err(overflow("b")));
+        assert_eq!(store.add("b", 0), Ok(-1));
+        assert_eq!(store.get("c"), Ok(0));
+        assert_eq!(
+            store.list(),
+            Ok(vec![("a".to_string(), 3), ("b".to_string(), -1)])
+        );
+        assert_eq!(store.reset("b"), Ok(()));
+        assert_eq!(store.get("b"), Ok(0));
+        assert_eq!(store.list(), Ok(vec![("a".to_string(), 3)]));
+
+        let entry = |value: &str, expires_at| KvEntry {
+            value: value.to_string(),
+            expires_at,
+        };
+        assert_eq!(store.set("n1", "k1", entry("1", None), 2, 0), Ok(true));
+        assert_eq!(store.set("n1", "k2", entry("2", Some(10)), 2, 0), Ok(true));
+        assert_eq!(store.set("n2", "k1", entry("other", None), 2, 0), Ok(true));
+        assert_eq!(store.set("n1", "k3", entry("3", None), 2, 5), Ok(false));
+        assert_eq!(store.set("n1", "k1", entry("1b", None), 2, 5), Ok(true));
+        assert_eq!(store.lookup("n1", "k1", 5), Ok(Some(entry("1b", None))));
+        assert_eq!(store.lookup("n2", "k1", 5), Ok(Some(entry("other", None))));
+        assert_eq!(store.keys("n1", "k", 5), Ok(vec!["k1".into(), "k2".into()]));
+        // k2 expires at 10, freeing a slot.
+        assert_eq!(store.lookup("n1", "k2", 10), Ok(None));
+        assert_eq!(store.keys("n1", "", 10), Ok(vec!["k1".into()]));
+        assert_eq!(store.set("n1", "k3", entry("3", None), 2, 10), Ok(true));
+        assert_eq!(store.delete("n1", "k3", 10), Ok(true));
+        assert_eq!(store.delete("n1", "k3", 10), Ok(false));
+
+        let call = |at, tool: &str| HistoryEntry {
+            at,
+            tool: tool.to_string(),
+            arguments: "{}".to_string(),
+            outcome: "success".to_string(),
+            summary: String::new(),
+        };
+        for (at, tool) in [(1, "echo"), (2, "roll_dice"), (3, "echo"), (4, "echo")] {
+            assert_eq!(store.append("n1", call(at, tool), 3, 0), Ok(()));
+        }
+        assert_eq!(store.append("n2", call(5, "echo"), 3, 0), Ok(()));
+        assert_eq!(
+            store.recent("n1", None, 10, 0),
+            Ok(vec![call(4, "echo"), call(3, "echo"), call(2, "roll_dice")])
+        );
+        assert_eq!(
+            store.recent("n1", Some("echo"), 1, 0),
+            Ok(vec![call(4, "echo")])
+        );
+        // Entries older than min_at are neither returned nor kept.
+        assert_eq!(store.recent("n1", None, 10, 4), Ok(vec![call(4, "echo")]));
+        assert_eq!(store.append("n1", call(6, "echo"), 3, 5), Ok(()));
+        assert_eq!(store.recent("n1", None, 10, 0), Ok(vec![call(6, "echo")]));
+
+        let tags = vec!["rust".to_string()];
+        let first = store.add_note("n1", "first", &tags, 7, 2).expect("add");
+        assert_eq!(
+            first,
+            Some(Note {
+                id: 1,
+                text: "first".to_string(),
+                tags: tags.clone(),
+                created_at: 7,
+            })
+        );
+        assert_eq!(
+            store
+                .add_note("n2", "other", &[], 8, 2)
+                .map(|note| note.map(|note| note.id)),
+            Ok(Some(1))
+        );
+        assert_eq!(
+            store
+                .add_note("n1", "second", &[], 9, 2)
+                .map(|note| note.map(|note| note.id)),
+            Ok(Some(2))
+        );
+        assert_eq!(store.add_note("n1", "third", &[], 10, 2), Ok(None));
+        assert_eq!(store.note("n1", 1), Ok(first));
+        assert_eq!(store.note("n2", 2), Ok(None));
+        assert_eq!(
+            store
+                .notes("n1")
+                .map(|notes| notes.into_iter().map(|note| note.text).collect::<Vec<_>>()),
+            Ok(vec!["first".to_string(), "second".to_string()])
+        );
+
+        let snapshot = store.dump().expect("dump");
+        assert_eq!(snapshot.counters, [("a".to_string(), 3)].into());
+        assert_eq!(
+            snapshot
+                .notes
+                .iter()
+                .map(|record| (record.namespace.as_str(), record.note.id))
+                .collect::<Vec<_>>(),
+            vec![("n1", 1), ("n1", 2), ("n2", 1)]
+        );
+        assert_eq!(
+            snapshot
+                .history
+                .iter()
+                .map(|record| (record.namespace.as_str(), record.entry.at))
+                .collect::<Vec<_>>(),
+            vec![("n1", 6), ("n2", 5)]
+        );
+
+        let staged = StateSnapshot {
+            counters: [("a".to_string(), 10)].into(),
+            kv: vec![KvRecord {
+                namespace: "n3".to_string(),
+                key: "k".to_string(),
+                value: "v".to_string(),
+                expires_at: None,
+            }],
+            notes: vec![NoteRecord {
+                namespace: "n3".to_string(),
+                note: Note {
+                    id: 5,
+                    text: "restored".to_string(),
+                    tags: Vec::new(),
+                    created_at: 11,
+                },
+            }],
+            history: [11, 12]
+                .map(|at| HistoryRecord {
+                    namespace: "n2".to_string(),
+                    entry: call(at, "echo"),
+                })
+                .into(),
+            ..StateSnapshot::default()
+        };
+        assert_eq!(store.restore(&staged, 2, 0), Ok(()));
+        assert_eq!(store.get("a"), Ok(10));
+        assert_eq!(store.lookup("n3", "k", 11), Ok(Some(entry("v", None))));
+        assert_eq!(
+            store
+                .add_note("n3", "next", &[], 12, 10)
+                .map(|note| note.map(|note| note.id)),
+            Ok(Some(6))
+        );
+        assert_eq!(
+            store.recent("n2", None, 10, 0),
+            Ok(vec![call(12, "echo"), call(11, "echo")])
+        );
+    }
+
+    #[test]
+    fn memory_state_is_independent_per_name_and_namespace() {
+        exercise(&MemoryStore::default());
+    }
+
+    #[test]
+    fn concurrent_counter_updates_are_linearizable() {
+        const THREADS: i64 = 8;
+        const ADDS: i64 = 500;
+        let store = MemoryStore::default();
+        let mut seen: Vec<i64> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..THREADS)
+                .map(|_| {
+                    scope.spawn(|| {
+                        (0..ADDS)
+                            .map(|_| {
+                                // Readers and other counters run alongside.
+                                store.get("hits").expect("get");
+                                store.add("misses", -1).expect("add");
+                                store.add("hits", 1).expect("add")
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("worker"))
+                .collect()
+        });
+        // Every increment observed a distinct value, so none was lost or
+        // applied twice.
+        seen.sort_unstable();
+        assert_eq!(seen, (1..=THREADS * ADDS).collect::<Vec<_>>());
+        assert_eq!(store.get("hits"), Ok(THREADS * ADDS));
+        assert_eq!(store.get("misses"), Ok(-THREADS * ADDS));
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite_state_survives_reopening() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let path = dir.path().join("state.db");
+        exercise(&SqliteStore::open(&path).expect("open"));
+        assert_eq!(
+            SqliteStore::open(&path).expect("reopen").add("a", 1),
+            Ok(11)
+        );
+    }
+
+    #[cfg(feature = "sled")]
+    #[test]
+    fn sled_state_survives_reopening() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let path = dir.path().join("state");
+        exercise(&SledStore::open(&path).expect("open"));
+        let reopened = SledStore::open(&path).expect("reopen");
+        assert_eq!(reopened.add("a", 1), Ok(11));
+        // History is not flushed per call, but closing the store flushes it.
+        assert_eq!(
+            reopened.recent("n2", None, 10, 0).map(|calls| calls.len()),
+            Ok(2)
+        );
+    }
+}
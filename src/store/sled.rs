@@ -0,0 +1,426 @@
+use super::{
+    overflow, CounterStore, HistoryEntry, HistoryRecord, HistoryStore, KvEntry, KvRecord, KvStore,
+    Note, NoteRecord, NoteStore, StateDump, StateSnapshot,
+};
+use sled::{transaction::ConflictableTransactionError, Transactional};
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    path::Path,
+    sync::Mutex,
+};
+
+/// State in a sled database. sled locks its directory, so only one process
+/// can use it at a time.
+pub struct SledStore {
+    counters: sled::Tree,
+    /// Keys are `<namespace> 0x00 <key>`; values are the expiry (big-endian
+    /// Unix milliseconds, `i64::MAX` for none) followed by the value.
+    kv: sled::Tree,
+    /// Serializes `set` so the key limit holds under concurrent writers.
+    kv_writes: Mutex<()>,
+    /// Keys are `<namespace> 0x00 <big-endian id>`, so a namespace's calls
+    /// iterate oldest first; values are JSON [`HistoryEntry`]s.
+    history: sled::Tree,
+    /// Keys are `<namespace> 0x00 <big-endian id>`; values are JSON [`Note`]s.
+    notes: sled::Tree,
+    /// Serializes `add_note` so ids and the note limit hold.
+    note_writes: Mutex<()>,
+    db: sled::Db,
+}
+
+impl SledStore {
+    /// Open or create the database directory at `path`.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let fail = |error: sled::Error| format!("cannot open {}: {error}", path.display());
+        let db = sled::open(path).map_err(fail)?;
+        Ok(Self {
+            counters: db.open_tree("counters").map_err(fail)?,
+            kv: db.open_tree("kv").map_err(fail)?,
+            kv_writes: Mutex::new(()),
+            history: db.open_tree("history").map_err(fail)?,
+            notes: db.open_tree("notes").map_err(fail)?,
+            note_writes: Mutex::new(()),
+            db,
+        })
+    }
+}
+
+fn decode(bytes: &[u8]) -> i64 {
+    bytes.try_into().map(i64::from_be_bytes).unwrap_or(0)
+}
+
+impl CounterStore for SledStore {
+    fn add(&self, name: &str, delta: i64) -> Result<i64, String> {
+        let mut overflowed = false;
+        let updated = self
+            .counters
+            .update_and_fetch(name, |old| {
+                let current = old.map(decode).unwrap_or(0);
+                match current.checked_add(delta) {
+                    Some(value) => Some(value.to_be_bytes().to_vec()),
+                    None => {
+                        overflowed = true;
+                        old.map(<[u8]>::to_vec)
+                    }
+                }
+            })
+            .and_then(|updated| self.counters.flush().map(|_| updated))
+            .map_err(|error| format!("cannot update counter '{name}': {error}"))?;
+        if overflowed {
+            return Err(overflow(name));
+        }
+        Ok(updated.as_deref().map(decode).unwrap_or(0))
+    }
+
+    fn get(&self, name: &str) -> Result<i64, String> {
+        self.counters
+            .get(name)
+            .map(|value| value.as_deref().map(decode).unwrap_or(0))
+            .map_err(|error| format!("cannot read counter '{name}': {error}"))
+    }
+
+    fn reset(&self, name: &str) -> Result<(), String> {
+        self.counters
+            .remove(name)
+            .and_then(|_| self.counters.flush())
+            .map(|_| ())
+            .map_err(|error| format!("cannot reset counter '{name}': {error}"))
+    }
+
+    fn list(&self) -> Result<Vec<(String, i64)>, String> {
+        self.counters
+            .iter()
+            .map(|entry| {
+                let (name, value) = entry?;
+                Ok((String::from_utf8_lossy(&name).into_owned(), decode(&value)))
+            })
+            .collect::<Result<_, sled::Error>>()
+            .map_err(|error| format!("cannot list counters: {error}"))
+    }
+}
+
+fn kv_key(namespace: &str, key: &str) -> Vec<u8> {
+    [namespace.as_bytes(), &[0], key.as_bytes()].concat()
+}
+
+fn encode_entry(entry: &KvEntry) -> Vec<u8> {
+    let expires_at = entry.expires_at.unwrap_or(i64::MAX);
+    [&expires_at.to_be_bytes()[..], entry.value.as_bytes()].concat()
+}
+
+fn decode_entry(bytes: &[u8]) -> KvEntry {
+    let (expires_at, value) = bytes.split_at(bytes.len().min(8));
+    KvEntry {
+        value: String::from_utf8_lossy(value).into_owned(),
+        expires_at: Some(decode(expires_at)).filter(|at| *at != i64::MAX),
+    }
+}
+
+impl KvStore for SledStore {
+    fn set(
+        &self,
+        namespace: &str,
+        key: &str,
+        entry: KvEntry,
+        max_keys: usize,
+        now: i64,
+    ) -> Result<bool, String> {
+        let fail = |error: sled::Error| format!("cannot store '{key}': {error}");
+        let _write = self.kv_writes.lock().expect("kv lock poisoned");
+        let full_key = kv_key(namespace, key);
+        let mut live = 0;
+        let mut exists = false;
+        for item in self.kv.scan_prefix(kv_key(namespace, "")) {
+            let (stored_key, value) = item.map_err(fail)?;
+            if decode_entry(&value).is_live(now) {
+                live += 1;
+                exists |= *stored_key == *full_key;
+            } else {
+                self.kv.remove(stored_key).map_err(fail)?;
+            }
+        }
+        if !exists && live >= max_keys {
+            return Ok(false);
+        }
+        self.kv
+            .insert(full_key, encode_entry(&entry))
+            .and_then(|_| self.kv.flush())
+            .map(|_| true)
+            .map_err(fail)
+    }
+
+    fn lookup(&self, namespace: &str, key: &str, now: i64) -> Result<Option<KvEntry>, String> {
+        self.kv
+            .get(kv_key(namespace, key))
+            .map(|value| {
+                value
+                    .map(|value| decode_entry(&value))
+                    .filter(|entry| entry.is_live(now))
+            })
+            .map_err(|error| format!("cannot read '{key}': {error}"))
+    }
+
+    fn delete(&self, namespace: &str, key: &str, now: i64) -> Result<bool, String> {
+        self.kv
+            .remove(kv_key(namespace, key))
+            .and_then(|removed| self.kv.flush().map(|_| removed))
+            .map(|removed| removed.is_some_and(|value| decode_entry(&value).is_live(now)))
+            .map_err(|error| format!("cannot delete '{key}': {error}"))
+    }
+
+    fn keys(&self, namespace: &str, prefix: &str, now: i64) -> Result<Vec<String>, String> {
+        let skip = namespace.len() + 1;
+        self.kv
+            .scan_prefix(kv_key(namespace, prefix))
+            .filter_map(|item| match item {
+                Ok((key, value)) => decode_entry(&value)
+                    .is_live(now)
+                    .then(|| Ok(String::from_utf8_lossy(&key[skip..]).into_owned())),
+                Err(error) => Some(Err(error)),
+            })
+            .collect::<Result<_, sled::Error>>()
+            .map_err(|error| format!("cannot list keys: {error}"))
+    }
+}
+
+impl HistoryStore for SledStore {
+    fn append(
+        &self,
+        namespace: &str,
+        entry: HistoryEntry,
+        max_entries: usize,
+        min_at: i64,
+    ) -> Result<(), String> {
+        let fail = |error: sled::Error| format!("cannot record call history: {error}");
+        let id = self.db.generate_id().map_err(fail)?;
+        let key = [namespace.as_bytes(), &[0], &id.to_be_bytes()].concat();
+        let value = serde_json::to_vec(&entry).expect("history entries serialize");
+        self.history.insert(key, value).map_err(fail)?;
+        let prefix = kv_key(namespace, "");
+        let keys: Vec<_> = self
+            .history
+            .scan_prefix(&prefix)
+            .keys()
+            .collect::<Result<_, _>>()
+            .map_err(fail)?;
+        let excess = keys.len().saturating_sub(max_entries);
+        for (index, key) in keys.into_iter().enumerate() {
+            let stale = index < excess
+                || self
+                    .history
+                    .get(&key)
+                    .map_err(fail)?
+                    .and_then(|value| serde_json::from_slice::<HistoryEntry>(&value).ok())
+                    .is_none_or(|call| call.at < min_at);
+            if !stale {
+                break;
+            }
+            self.history.remove(key).map_err(fail)?;
+        }
+        // Every tool call lands here, so instead of flushing per call, leave
+        // the write to sled's background flush (every 500 ms) and the one on
+        // close.
+        Ok(())
+    }
+
+    fn recent(
+        &self,
+        namespace: &str,
+        tool: Option<&str>,
+        limit: usize,
+        min_at: i64,
+    ) -> Result<Vec<HistoryEntry>, String> {
+        let mut calls = Vec::new();
+        for item in self
+            .history
+            .scan_prefix(kv_key(namespace, ""))
+            .values()
+            .rev()
+        {
+            let value = item.map_err(|error| format!("cannot read call history: {error}"))?;
+            let Ok(call) = serde_json::from_slice::<HistoryEntry>(&value) else {
+                continue;
+            };
+            if call.at < min_at || calls.len() == limit {
+                break;
+            }
+            if tool.is_none_or(|tool| call.tool == tool) {
+                calls.push(call);
+            }
+        }
+        Ok(calls)
+    }
+}
+
+fn note_key(namespace: &str, id: u64) -> Vec<u8> {
+    [namespace.as_bytes(), &[0], &id.to_be_bytes()].concat()
+}
+
+impl NoteStore for SledStore {
+    fn add_note(
+        &self,
+        namespace: &str,
+        text: &str,
+        tags: &[String],
+        created_at: i64,
+        max_notes: usize,
+    ) -> Result<Option<Note>, String> {
+        let fail = |error: sled::Error| format!("cannot store note: {error}");
+        let _write = self.note_writes.lock().expect("notes lock poisoned");
+        let prefix = kv_key(namespace, "");
+        if self.notes.scan_prefix(&prefix).count() >= max_notes {
+            return Ok(None);
+        }
+        let last_id = match self.notes.scan_prefix(&prefix).keys().next_back() {
+            Some(key) => key.map_err(fail)?[prefix.len()..]
+                .try_into()
+                .map(u64::from_be_bytes)
+                .unwrap_or(0),
+            None => 0,
+        };
+        let note = Note {
+            id: last_id + 1,
+            text: text.to_string(),
+            tags: tags.to_vec(),
+            created_at,
+        };
+        let value = serde_json::to_vec(&note).expect("notes serialize");
+        self.notes
+            .insert(note_key(namespace, note.id), value)
+            .and_then(|_| self.notes.flush())
+            .map(|_| Some(note))
+            .map_err(fail)
+    }
+
+    fn note(&self, namespace: &str, id: u64) -> Result<Option<Note>, String> {
+        self.notes
+            .get(note_key(namespace, id))
+            .map(|value| value.and_then(|value| serde_json::from_slice(&value).ok()))
+            .map_err(|error| format!("cannot read note {id}: {error}"))
+    }
+
+    fn notes(&self, namespace: &str) -> Result<Vec<Note>, String> {
+        self.notes
+            .scan_prefix(kv_key(namespace, ""))
+            .values()
+            .filter_map(|value| match value {
+                Ok(value) => serde_json::from_slice(&value).ok().map(Ok),
+                Err(error) => Some(Err(error)),
+            })
+            .collect::<Result<_, sled::Error>>()
+            .map_err(|error| format!("cannot read notes: {error}"))
+    }
+}
+
+/// Split a `<namespace> 0x00 <rest>` key.
+fn split_key(key: &[u8]) -> (String, &[u8]) {
+    let split = key.iter().position(|byte| *byte == 0).unwrap_or(key.len());
+    let rest = key.get(split + 1..).unwrap_or_default();
+    (String::from_utf8_lossy(&key[..split]).into_owned(), rest)
+}
+
+impl StateDump for SledStore {
+    fn dump(&self) -> Result<StateSnapshot, String> {
+        let fail = |error: sled::Error| format!("cannot export state: {error}");
+        let mut snapshot = StateSnapshot {
+            counters: self.list()?.into_iter().collect(),
+            ..StateSnapshot::default()
+        };
+        for item in self.kv.iter() {
+            let (key, value) = item.map_err(fail)?;
+            let (namespace, key) = split_key(&key);
+            let entry = decode_entry(&value);
+            snapshot.kv.push(KvRecord {
+                namespace,
+                key: String::from_utf8_lossy(key).into_owned(),
+                value: entry.value,
+                expires_at: entry.expires_at,
+            });
+        }
+        for item in self.notes.iter() {
+            let (key, value) = item.map_err(fail)?;
+            if let Ok(note) = serde_json::from_slice::<Note>(&value) {
+                let (namespace, _) = split_key(&key);
+                snapshot.notes.push(NoteRecord { namespace, note });
+            }
+        }
+        for item in self.history.iter() {
+            let (key, value) = item.map_err(fail)?;
+            if let Ok(entry) = serde_json::from_slice::<HistoryEntry>(&value) {
+                let (namespace, _) = split_key(&key);
+                snapshot.history.push(HistoryRecord { namespace, entry });
+            }
+        }
+        Ok(snapshot)
+    }
+
+    fn restore(
+        &self,
+        staged: &StateSnapshot,
+        max_history: usize,
+        min_at: i64,
+    ) -> Result<(), String> {
+        let fail = |error: sled::Error| format!("cannot import state: {error}");
+        let _kv_write = self.kv_writes.lock().expect("kv lock poisoned");
+        let _note_write = self.note_writes.lock().expect("notes lock poisoned");
+        // Transactions cannot scan, so work out the history to keep first.
+        let mut calls: BTreeMap<&str, Vec<(Vec<u8>, HistoryEntry)>> = BTreeMap::new();
+        for HistoryRecord { namespace, entry } in &staged.history {
+            let namespace_calls = match calls.entry(namespace.as_str()) {
+                Entry::Occupied(calls) => calls.into_mut(),
+                Entry::Vacant(calls) => {
+                    let mut stored = Vec::new();
+                    for item in self.history.scan_prefix(kv_key(namespace, "")) {
+                        let (key, value) = item.map_err(fail)?;
+                        if let Ok(call) = serde_json::from_slice(&value) {
+                            stored.push((key.to_vec(), call));
+                        }
+                    }
+                    calls.insert(stored)
+                }
+            };
+            let id = self.db.generate_id().map_err(fail)?;
+            let key = [namespace.as_bytes(), &[0], &id.to_be_bytes()].concat();
+            namespace_calls.push((key, entry.clone()));
+        }
+        let mut added = Vec::new();
+        let mut removed = Vec::new();
+        for namespace_calls in calls.values() {
+            let excess = namespace_calls.len().saturating_sub(max_history);
+            for (index, (key, call)) in namespace_calls.iter().enumerate() {
+                if index < excess || call.at < min_at {
+                    removed.push(key.clone());
+                } else {
+                    added.push((key.clone(), call));
+                }
+            }
+        }
+        (&self.counters, &self.kv, &self.notes, &self.history)
+            .transaction(|(counters, kv, notes, history)| {
+                for (name, value) in &staged.counters {
+                    counters.insert(name.as_bytes(), value.to_be_bytes().to_vec())?;
+                }
+                for record in &staged.kv {
+                    let entry = KvEntry {
+                        value: record.value.clone(),
+                        expires_at: record.expires_at,
+                    };
+                    kv.insert(kv_key(&record.namespace, &record.key), encode_entry(&entry))?;
+                }
+                for NoteRecord { namespace, note } in &staged.notes {
+                    let value = serde_json::to_vec(note).expect("notes serialize");
+                    notes.insert(note_key(namespace, note.id), value)?;
+                }
+                for key in &removed {
+                    history.remove(key.as_slice())?;
+                }
+                for (key, call) in &added {
+                    let value = serde_json::to_vec(call).expect("history entries serialize");
+                    history.insert(key.as_slice(), value)?;
+                }
+                Ok::<_, ConflictableTransactionError<sled::Error>>(())
+            })
+            .map_err(|error| format!("cannot import state: {error}"))?;
+        self.db.flush().map(|_| ()).map_err(fail)
+    }
+}
@@ -0,0 +1,319 @@
+//! Portable copies of everything a state backend holds, for moving state
+//! between hosts and backends or keeping disaster-recovery backups.
+
+use super::{HistoryEntry, Note, StateLimits, StateStore};
+use crate::cache::CacheReport;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt};
+
+/// Snapshot layout written by this build; imports reject any other.
+pub const SNAPSHOT_FORMAT: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KvRecord {
+    pub namespace: String,
+    pub key: String,
+    /// The stored JSON text.
+    pub value: String,
+    pub expires_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoteRecord {
+    pub namespace: String,
+    #[serde(flatten)]
+    pub note: Note,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryRecord {
+    pub namespace: String,
+    #[serde(flatten)]
+    pub entry: HistoryEntry,
+}
+
+/// All tool state, sorted by namespace and then key, id, or age.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    pub format: u32,
+    pub exported_at: String,
+    pub counters: BTreeMap<String, i64>,
+    pub kv: Vec<KvRecord>,
+    pub notes: Vec<NoteRecord>,
+    /// Call history per namespace, oldest first.
+    pub history: Vec<HistoryRecord>,
+    /// Cache sizes when the snapshot was taken. Cache contents are not part
+    /// of snapshots; see `INFERENCO_MCP_CACHE_DIR` for those.
+    #[serde(default)]
+    pub caches: Vec<CacheReport>,
+}
+
+/// Records restored by [`import`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ImportSummary {
+    pub counters: usize,
+    pub kv: usize,
+    pub notes: usize,
+    pub history: usize,
+}
+
+/// Why [`import`] wrote nothing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportError {
+    /// The snapshot cannot be applied, such as one over a configured limit.
+    Rejected(String),
+    /// The store failed.
+    Store(String),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rejected(reason) | Self::Store(reason) => f.write_str(reason),
+        }
+    }
+}
+
+/// Snapshot `store`, recording `caches` alongside.
+pub fn export(store: &dyn StateStore, caches: Vec<CacheReport>) -> Result<StateSnapshot, String> {
+    Ok(StateSnapshot {
+        format: SNAPSHOT_FORMAT,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        caches,
+        ..store.dump()?
+    })
+}
+
+/// Merge `snapshot` into `store` in one step: counters take the snapshot's
+/// values, key-value entries replace those under the same key (entries
+/// expired by `now` are skipped), notes are added under their ids unless
+/// the caller already has a note with that id, and history is appended and
+/// trimmed as on every call. Nothing is written when the result would break
+/// `limits`.
+pub fn import(
+    store: &dyn StateStore,
+    snapshot: &StateSnapshot,
+    limits: StateLimits,
+    now: i64,
+) -> Result<ImportSummary, ImportError> {
+    if snapshot.format != SNAPSHOT_FORMAT {
+        return Err(ImportError::Rejected(format!(
+            "unsupported snapshot format {}; this build reads format {SNAPSHOT_FORMAT}",
+            snapshot.format
+        )));
+    }
+    let staged = stage(store, snapshot, limits, now)?;
+    let min_at = limits.history.min_at(now);
+    store
+        .restore(&staged, limits.history.max_entries, min_at)
+        .map_err(ImportError::Store)?;
+    Ok(ImportSummary {
+        counters: staged.counters.len(),
+        kv: staged.kv.len(),
+        notes: staged.notes.len(),
+        history: staged.history.len(),
+    })
+}
+
+/// The records of `snapshot` to write into `store`, checked against `limits`.
+fn stage(
+    store: &dyn StateStore,
+    snapshot: &StateSnapshot,
+    limits: StateLimits,
+    now: i64,
+) -> Result<StateSnapshot, ImportError> {
+    let failed = ImportError::Store;
+    // namespace -> key -> record; a later record for the same key wins.
+    let mut kv: BTreeMap<&str, BTreeMap<&str, &KvRecord>> = BTreeMap::new();
+    for record in &snapshot.kv {
+        if record.expires_at.is_some_and(|at| at <= now) {
+            continue;
+        }
+        if record.value.len() > limits.kv.max_value_bytes {
+            return Err(ImportError::Rejected(format!(
+                "key '{}' of {} holds {} bytes; the limit is {}",
+                record.key,
+                record.namespace,
+                record.value.len(),
+                limits.kv.max_value_bytes
+            )));
+        }
+        kv.entry(record.namespace.as_str())
+            .or_default()
+            .insert(record.key.as_str(), record);
+    }
+    for (namespace, records) in &kv {
+        let existing = store.keys(namespace, "", now).map_err(failed)?;
+        let added = records
+            .keys()
+            .filter(|key| !existing.iter().any(|stored| stored == **key))
+            .count();
+        if existing.len() + added > limits.kv.max_keys {
+            return Err(ImportError::Rejected(format!(
+                "{namespace} would hold {} keys; the limit is {}",
+                existing.len() + added,
+                limits.kv.max_keys
+            )));
+        }
+    }
+
+    // namespace -> id -> record; notes the store already has are skipped.
+    let mut notes: BTreeMap<&str, BTreeMap<u64, &NoteRecord>> = BTreeMap::new();
+    for record in &snapshot.notes {
+        if record.note.text.len() > limits.notes.max_note_bytes {
+            return Err(ImportError::Rejected(format!(
+                "note {} of {} holds {} bytes; the limit is {}",
+                record.note.id,
+                record.namespace,
+                record.note.text.len(),
+                limits.notes.max_note_bytes
+            )));
+        }
+        notes
+            .entry(record.namespace.as_str())
+            .or_default()
+            .entry(record.note.id)
+            .or_insert(record);
+    }
+    for (namespace, records) in &mut notes {
+        let existing = store.notes(namespace).map_err(failed)?;
+        records.retain(|id, _| !existing.iter().any(|note| note.id == *id));
+        if existing.len() + records.len() > limits.notes.max_notes {
+            return Err(ImportError::Rejected(format!(
+                "{namespace} would hold {} notes; the limit is {}",
+                existing.len() + records.len(),
+                limits.notes.max_notes
+            )));
+        }
+    }
+
+    Ok(StateSnapshot {
+        counters: snapshot.counters.clone(),
+        kv: kv
+            .into_values()
+            .flat_map(BTreeMap::into_values)
+            .cloned()
+            .collect(),
+        notes: notes
+            .into_values()
+            .flat_map(BTreeMap::into_values)
+            .cloned()
+            .collect(),
+        history: snapshot.history.clone(),
+        ..StateSnapshot::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{CounterStore, KvEntry, KvStore, MemoryStore, NoteLimits, NoteStore};
+
+    #[test]
+    fn snapshots_round_trip_between_stores() {
+        let source = MemoryStore::default();
+        source.add("visits", 7).expect("add");
+        let entry = |value: &str, expires_at| KvEntry {
+            value: value.to_string(),
+            expires_at,
+        };
+        source
+            .set("key:a", "draft", entry("{\"x\":1}", None), 10, 0)
+            .expect("set");
+        source
+            .set("key:a", "gone", entry("1", Some(5)), 10, 0)
+            .expect("set");
+        source
+            .add_note("key:a", "remember", &["todo".to_string()], 3, 10)
+            .expect("add note");
+
+        let snapshot = export(&source, Vec::new()).expect("export");
+        assert_eq!(
+            snapshot.counters,
+            BTreeMap::from([("visits".to_string(), 7)])
+        );
+        assert_eq!(snapshot.kv.len(), 2);
+        let text = serde_json::to_string(&snapshot).expect("serialize");
+        let snapshot: StateSnapshot = serde_json::from_str(&text).expect("parse");
+
+        let target = MemoryStore::default();
+        target.add("visits", 100).expect("add");
+        assert_eq!(
+            import(&target, &snapshot, StateLimits::default(), 10),
+            Ok(ImportSummary {
+                counters: 1,
+                kv: 1,
+                notes: 1,
+                history: 0,
+            })
+        );
+        assert_eq!(target.get("visits"), Ok(7));
+        assert_eq!(
+            target.lookup("key:a", "draft", 10),
+            Ok(Some(entry("{\"x\":1}", None)))
+        );
+        assert_eq!(target.note("key:a", 1), source.note("key:a", 1));
+
+        let future = StateSnapshot {
+            format: SNAPSHOT_FORMAT + 1,
+            ..StateSnapshot::default()
+        };
+        assert!(import(&target, &future, StateLimits::default(), 10).is_err());
+    }
+
+    #[test]
+    fn imports_skip_known_notes_and_write_nothing_over_a_limit() {
+        let source = MemoryStore::default();
+        for text in ["one", "two"] {
+            source
+                .add_note("key:a", text, &[], 1, 10)
+                .expect("add note");
+        }
+        source.add("visits", 3).expect("add");
+        let snapshot = export(&source, Vec::new()).expect("export");
+
+        let target = MemoryStore::default();
+        let imported = |notes| ImportSummary {
+            counters: 1,
+            notes,
+            ..ImportSummary::default()
+        };
+        assert_eq!(
+            import(&target, &snapshot, StateLimits::default(), 10),
+            Ok(imported(2))
+        );
+        // Importing again adds nothing new.
+        assert_eq!(
+            import(&target, &snapshot, StateLimits::default(), 10),
+            Ok(imported(0))
+        );
+        assert_eq!(target.notes("key:a").map(|notes| notes.len()), Ok(2));
+
+        let mut grown = snapshot.clone();
+        grown.counters.insert("visits".to_string(), 9);
+        grown.notes.push(NoteRecord {
+            namespace: "key:a".to_string(),
+            note: Note {
+                id: 3,
+                text: "three".to_string(),
+                tags: Vec::new(),
+                created_at: 2,
+            },
+        });
+        let limits = StateLimits {
+            notes: NoteLimits {
+                max_notes: 2,
+                ..NoteLimits::default()
+            },
+            ..StateLimits::default()
+        };
+        assert_eq!(
+            import(&target, &grown, limits, 10),
+            Err(ImportError::Rejected(
+                "key:a would hold 3 notes; the limit is 2".to_string()
+            ))
+        );
+        assert_eq!(target.get("visits"), Ok(3), "nothing was written");
+        assert_eq!(target.note("key:a", 3), Ok(None));
+    }
+}
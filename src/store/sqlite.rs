@@ -0,0 +1,547 @@
+use super::{
+    overflow, CounterStore, HistoryEntry, HistoryRecord, HistoryStore, KvEntry, KvRecord, KvStore,
+    Note, NoteRecord, NoteStore, StateDump, StateSnapshot,
+};
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use std::{collections::BTreeSet, path::Path, sync::Mutex, time::Duration};
+
+/// State in a SQLite database, which several processes may share (as during
+/// a zero-downtime restart).
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Open or create the database at `path`.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let fail = |error: rusqlite::Error| format!("cannot open {}: {error}", path.display());
+        let connection = Connection::open(path).map_err(fail)?;
+        connection
+            .busy_timeout(Duration::from_secs(5))
+            .map_err(fail)?;
+        connection
+            .execute_batch(
+                "PRAGMA journal_mode = WAL;
+                 CREATE TABLE IF NOT EXISTS counters (
+                     name TEXT PRIMARY KEY,
+                     value INTEGER NOT NULL
+                 );
+                 CREATE TABLE IF NOT EXISTS kv (
+                     namespace TEXT NOT NULL,
+                     key TEXT NOT NULL,
+                     value TEXT NOT NULL,
+                     expires_at INTEGER,
+                     PRIMARY KEY (namespace, key)
+                 );
+                 CREATE TABLE IF NOT EXISTS history (
+                     id INTEGER PRIMARY KEY AUTOINCREMENT,
+                     namespace TEXT NOT NULL,
+                     at INTEGER NOT NULL,
+                     tool TEXT NOT NULL,
+                     arguments TEXT NOT NULL,
+                     outcome TEXT NOT NULL,
+                     summary TEXT NOT NULL
+                 );
+                 CREATE INDEX IF NOT EXISTS history_by_namespace ON history (namespace, id);
+                 CREATE TABLE IF NOT EXISTS notes (
+                     namespace TEXT NOT NULL,
+                     id INTEGER NOT NULL,
+                     text TEXT NOT NULL,
+                     tags TEXT NOT NULL,
+                     created_at INTEGER NOT NULL,
+                     PRIMARY KEY (namespace, id)
+                 );",
+            )
+            .map_err(fail)?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn read(connection: &Connection, name: &str) -> Result<i64, String> {
+        connection
+            .query_row(
+                "SELECT value FROM counters WHERE name = ?1",
+                [name],
+                |row| row.get(0),
+            )
+            .optional()
+            .map(Option::unwrap_or_default)
+            .map_err(|error| format!("cannot read counter '{name}': {error}"))
+    }
+}
+
+impl CounterStore for SqliteStore {
+    fn add(&self, name: &str, delta: i64) -> Result<i64, String> {
+        let connection = self.connection.lock().expect("counter lock poisoned");
+        // Checked here because SQLite silently turns overflowing integers
+        // into floats; the upsert stays atomic across processes.
+        Self::read(&connection, name)?
+            .checked_add(delta)
+            .ok_or_else(|| overflow(name))?;
+        connection
+            .query_row(
+                "INSERT INTO counters (name, value) VALUES (?1, ?2)
+                 ON CONFLICT (name) DO UPDATE SET value = value + excluded.value
+                 RETURNING value",
+                params![name, delta],
+                |row| row.get(0),
+            )
+            .map_err(|error| format!("cannot update counter '{name}': {error}"))
+    }
+
+    fn get(&self, name: &str) -> Result<i64, String> {
+        Self::read(
+            &self.connection.lock().expect("counter lock poisoned"),
+            name,
+        )
+    }
+
+    fn reset(&self, name: &str) -> Result<(), String> {
+        let connection = self.connection.lock().expect("counter lock poisoned");
+        connection
+            .execute("DELETE FROM counters WHERE name = ?1", [name])
+            .map(|_| ())
+            .map_err(|error| format!("cannot reset counter '{name}': {error}"))
+    }
+
+    fn list(&self) -> Result<Vec<(String, i64)>, String> {
+        let connection = self.connection.lock().expect("counter lock poisoned");
+        let fail = |error: rusqlite::Error| format!("cannot list counters: {error}");
+        let mut statement = connection
+            .prepare("SELECT name, value FROM counters ORDER BY name")
+            .map_err(fail)?;
+        let rows = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(fail)?;
+        rows.collect::<Result<_, _>>().map_err(fail)
+    }
+}
+
+const LIVE: &str = "(expires_at IS NULL OR expires_at > ?2)";
+
+impl KvStore for SqliteStore {
+    fn set(
+        &self,
+        namespace: &str,
+        key: &str,
+        entry: KvEntry,
+        max_keys: usize,
+        now: i64,
+    ) -> Result<bool, String> {
+        let fail = |error: rusqlite::Error| format!("cannot store '{key}': {error}");
+        let mut connection = self.connection.lock().expect("kv lock poisoned");
+        // IMMEDIATE takes the write lock up front, so the limit check and the
+        // insert are atomic across processes too.
+        let transaction = connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(fail)?;
+        transaction
+            .execute(
+                "DELETE FROM kv WHERE namespace = ?1 AND expires_at <= ?2",
+                params![namespace, now],
+            )
+            .map_err(fail)?;
+        let (count, exists): (i64, bool) = transaction
+            .query_row(
+                "SELECT COUNT(*), COALESCE(SUM(key = ?2), 0) FROM kv WHERE namespace = ?1",
+                params![namespace, key],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(fail)?;
+        if !exists && usize::try_from(count).unwrap_or(usize::MAX) >= max_keys {
+            return Ok(false);
+        }
+        transaction
+            .execute(
+                "INSERT INTO kv (namespace, key, value, expires_at) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (namespace, key)
+                 DO UPDATE SET value = excluded.value, expires_at = excluded.expires_at",
+                params![namespace, key, entry.value, entry.expires_at],
+            )
+            .map_err(fail)?;
+        transaction.commit().map(|()| true).map_err(fail)
+    }
+
+    fn lookup(&self, namespace: &str, key: &str, now: i64) -> Result<Option<KvEntry>, String> {
+        let connection = self.connection.lock().expect("kv lock poisoned");
+        connection
+            .query_row(
+                &format!(
+                    "SELECT value, expires_at FROM kv WHERE namespace = ?1 AND {LIVE} AND key = ?3"
+                ),
+                params![namespace, now, key],
+                |row| {
+                    Ok(KvEntry {
+                        value: row.get(0)?,
+                        expires_at: row.get(1)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(|error| format!("cannot read '{key}': {error}"))
+    }
+
+    fn delete(&self, namespace: &str, key: &str, now: i64) -> Result<bool, String> {
+        let connection = self.connection.lock().expect("kv lock poisoned");
+        connection
+            .query_row(
+                "DELETE FROM kv WHERE namespace = ?1 AND key = ?2 RETURNING expires_at",
+                params![namespace, key],
+                |row| row.get::<_, Option<i64>>(0),
+            )
+            .optional()
+            .map(|deleted| deleted.is_some_and(|expires_at| expires_at.is_none_or(|at| at > now)))
+            .map_err(|error| format!("cannot delete '{key}': {error}"))
+    }
+
+    fn keys(&self, namespace: &str, prefix: &str, now: i64) -> Result<Vec<String>, String> {
+        let connection = self.connection.lock().expect("kv lock poisoned");
+        let fail = |error: rusqlite::Error| format!("cannot list keys: {error}");
+        let mut statement = connection
+            .prepare(&format!(
+                "SELECT key FROM kv WHERE namespace = ?1 AND {LIVE}
+                 AND substr(key, 1, length(?3)) = ?3 ORDER BY key"
+            ))
+            .map_err(fail)?;
+        let rows = statement
+            .query_map(params![namespace, now, prefix], |row| row.get(0))
+            .map_err(fail)?;
+        rows.collect::<Result<_, _>>().map_err(fail)
+    }
+}
+
+impl HistoryStore for SqliteStore {
+    fn append(
+        &self,
+        namespace: &str,
+        entry: HistoryEntry,
+        max_entries: usize,
+        min_at: i64,
+    ) -> Result<(), String> {
+        let fail = |error: rusqlite::Error| format!("cannot record call history: {error}");
+        let mut connection = self.connection.lock().expect("history lock poisoned");
+        let transaction = connection.transaction().map_err(fail)?;
+        transaction
+            .execute(
+                "INSERT INTO history (namespace, at, tool, arguments, outcome, summary)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    namespace,
+                    entry.at,
+                    entry.tool,
+                    entry.arguments,
+                    entry.outcome,
+                    entry.summary
+                ],
+            )
+            .map_err(fail)?;
+        transaction
+            .execute(
+                "DELETE FROM history WHERE namespace = ?1 AND (at < ?2 OR id NOT IN (
+                     SELECT id FROM history WHERE namespace = ?1 ORDER BY id DESC LIMIT ?3
+                 ))",
+                params![
+                    namespace,
+                    min_at,
+                    i64::try_from(max_entries).unwrap_or(i64::MAX)
+                ],
+            )
+            .map_err(fail)?;
+        transaction.commit().map_err(fail)
+    }
+
+    fn recent(
+        &self,
+        namespace: &str,
+        tool: Option<&str>,
+        limit: usize,
+        min_at: i64,
+    ) -> Result<Vec<HistoryEntry>, String> {
+        let connection = self.connection.lock().expect("history lock poisoned");
+        let fail = |error: rusqlite::Error| format!("cannot read call history: {error}");
+        let mut statement = connection
+            .prepare(
+                "SELECT at, tool, arguments, outcome, summary FROM history
+                 WHERE namespace = ?1 AND at >= ?2 AND (?3 IS NULL OR tool = ?3)
+                 ORDER BY id DESC LIMIT ?4",
+            )
+            .map_err(fail)?;
+        let rows = statement
+            .query_map(
+                params![
+                    namespace,
+                    min_at,
+                    tool,
+                    i64::try_from(limit).unwrap_or(i64::MAX)
+                ],
+                |row| {
+                    Ok(HistoryEntry {
+                        at: row.get(0)?,
+                        tool: row.get(1)?,
+                        arguments: row.get(2)?,
+                        outcome: row.get(3)?,
+                        summary: row.get(4)?,
+                    })
+                },
+            )
+            .map_err(fail)?;
+        rows.collect::<Result<_, _>>().map_err(fail)
+    }
+}
+
+fn note_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Note> {
+    let tags: String = row.get(2)?;
+    Ok(Note {
+        id: row.get(0)?,
+        text: row.get(1)?,
+        tags: serde_json::from_str(&tags).unwrap_or_default(),
+        created_at: row.get(3)?,
+    })
+}
+
+impl NoteStore for SqliteStore {
+    fn add_note(
+        &self,
+        namespace: &str,
+        text: &str,
+        tags: &[String],
+        created_at: i64,
+        max_notes: usize,
+    ) -> Result<Option<Note>, String> {
+        let fail = |error: rusqlite::Error| format!("cannot store note: {error}");
+        let mut connection = self.connection.lock().expect("notes lock poisoned");
+        // IMMEDIATE so the limit check and the next id hold across processes.
+        let transaction = connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(fail)?;
+        let (count, last_id): (i64, u64) = transaction
+            .query_row(
+                "SELECT COUNT(*), COALESCE(MAX(id), 0) FROM notes WHERE namespace = ?1",
+                [namespace],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(fail)?;
+        if usize::try_from(count).unwrap_or(usize::MAX) >= max_notes {
+            return Ok(None);
+        }
+        let note = Note {
+            id: last_id + 1,
+            text: text.to_string(),
+            tags: tags.to_vec(),
+            created_at,
+        };
+        transaction
+            .execute(
+                "INSERT INTO notes (namespace, id, text, tags, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    namespace,
+                    note.id,
+                    note.text,
+                    serde_json::to_string(&note.tags).expect("tags serialize"),
+                    note.created_at
+                ],
+            )
+            .map_err(fail)?;
+        transaction.commit().map(|()| Some(note)).map_err(fail)
+    }
+
+    fn note(&self, namespace: &str, id: u64) -> Result<Option<Note>, String> {
+        let connection = self.connection.lock().expect("notes lock poisoned");
+        connection
+            .query_row(
+                "SELECT id, text, tags, created_at FROM notes WHERE namespace = ?1 AND id = ?2",
+                params![namespace, id],
+                note_from_row,
+            )
+            .optional()
+            .map_err(|error| format!("cannot read note {id}: {error}"))
+    }
+
+    fn notes(&self, namespace: &str) -> Result<Vec<Note>, String> {
+        let connection = self.connection.lock().expect("notes lock poisoned");
+        let fail = |error: rusqlite::Error| format!("cannot read notes: {error}");
+        let mut statement = connection
+            .prepare(
+                "SELECT id, text, tags, created_at FROM notes WHERE namespace = ?1 ORDER BY id",
+            )
+            .map_err(fail)?;
+        let rows = statement
+            .query_map([namespace], note_from_row)
+            .map_err(fail)?;
+        rows.collect::<Result<_, _>>().map_err(fail)
+    }
+}
+
+impl StateDump for SqliteStore {
+    fn dump(&self) -> Result<StateSnapshot, String> {
+        let mut connection = self.connection.lock().expect("state lock poisoned");
+        let fail = |error: rusqlite::Error| format!("cannot export state: {error}");
+        // One read transaction, so the snapshot is consistent even while
+        // other processes write.
+        let transaction = connection.transaction().map_err(fail)?;
+        let counters = transaction
+            .prepare("SELECT name, value FROM counters ORDER BY name")
+            .and_then(|mut statement| {
+                statement
+                    .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .collect()
+            })
+            .map_err(fail)?;
+        let kv = transaction
+            .prepare("SELECT namespace, key, value, expires_at FROM kv ORDER BY namespace, key")
+            .and_then(|mut statement| {
+                statement
+                    .query_map([], |row| {
+                        Ok(KvRecord {
+                            namespace: row.get(0)?,
+                            key: row.get(1)?,
+                            value: row.get(2)?,
+                            expires_at: row.get(3)?,
+                        })
+                    })?
+                    .collect()
+            })
+            .map_err(fail)?;
+        let notes = transaction
+            .prepare(
+                "SELECT namespace, id, text, tags, created_at FROM notes ORDER BY namespace, id",
+            )
+            .and_then(|mut statement| {
+                statement
+                    .query_map([], |row| {
+                        Ok(NoteRecord {
+                            namespace: row.get(0)?,
+                            note: Note {
+                                id: row.get(1)?,
+                                text: row.get(2)?,
+                                tags: serde_json::from_str(&row.get::<_, String>(3)?)
+                                    .unwrap_or_default(),
+                                created_at: row.get(4)?,
+                            },
+                        })
+                    })?
+                    .collect()
+            })
+            .map_err(fail)?;
+        let history = transaction
+            .prepare(
+                "SELECT namespace, at, tool, arguments, outcome, summary FROM history
+                 ORDER BY namespace, id",
+            )
+            .and_then(|mut statement| {
+                statement
+                    .query_map([], |row| {
+                        Ok(HistoryRecord {
+                            namespace: row.get(0)?,
+                            entry: HistoryEntry {
+                                at: row.get(1)?,
+                                tool: row.get(2)?,
+                                arguments: row.get(3)?,
+                                outcome: row.get(4)?,
+                                summary: row.get(5)?,
+                            },
+                        })
+                    })?
+                    .collect()
+            })
+            .map_err(fail)?;
+        Ok(StateSnapshot {
+            counters,
+            kv,
+            notes,
+            history,
+            ..StateSnapshot::default()
+        })
+    }
+
+    fn restore(
+        &self,
+        staged: &StateSnapshot,
+        max_history: usize,
+        min_at: i64,
+    ) -> Result<(), String> {
+        let mut connection = self.connection.lock().expect("state lock poisoned");
+        let fail = |error: rusqlite::Error| format!("cannot import state: {error}");
+        // Dropping the transaction on an error rolls every write back.
+        let transaction = connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(fail)?;
+        for (name, value) in &staged.counters {
+            transaction
+                .execute(
+                    "INSERT INTO counters (name, value) VALUES (?1, ?2)
+                     ON CONFLICT (name) DO UPDATE SET value = excluded.value",
+                    params![name, value],
+                )
+                .map_err(fail)?;
+        }
+        for record in &staged.kv {
+            transaction
+                .execute(
+                    "INSERT INTO kv (namespace, key, value, expires_at) VALUES (?1, ?2, ?3, ?4)
+                     ON CONFLICT (namespace, key)
+                     DO UPDATE SET value = excluded.value, expires_at = excluded.expires_at",
+                    params![
+                        record.namespace,
+                        record.key,
+                        record.value,
+                        record.expires_at
+                    ],
+                )
+                .map_err(fail)?;
+        }
+        for NoteRecord { namespace, note } in &staged.notes {
+            transaction
+                .execute(
+                    "INSERT INTO notes (namespace, id, text, tags, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        namespace,
+                        note.id,
+                        note.text,
+                        serde_json::to_string(&note.tags).expect("tags serialize"),
+                        note.created_at
+                    ],
+                )
+                .map_err(fail)?;
+        }
+        for HistoryRecord { namespace, entry } in &staged.history {
+            transaction
+                .execute(
+                    "INSERT INTO history (namespace, at, tool, arguments, outcome, summary)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        namespace,
+                        entry.at,
+                        entry.tool,
+                        entry.arguments,
+                        entry.outcome,
+                        entry.summary
+                    ],
+                )
+                .map_err(fail)?;
+        }
+        let namespaces: BTreeSet<&str> = staged
+            .history
+            .iter()
+            .map(|record| record.namespace.as_str())
+            .collect();
+        for namespace in namespaces {
+            transaction
+                .execute(
+                    "DELETE FROM history WHERE namespace = ?1 AND (at < ?2 OR id NOT IN (
+                         SELECT id FROM history WHERE namespace = ?1 ORDER BY id DESC LIMIT ?3
+                     ))",
+                    params![
+                        namespace,
+                        min_at,
+                        i64::try_from(max_history).unwrap_or(i64::MAX)
+                    ],
+                )
+                .map_err(fail)?;
+        }
+        transaction.commit().map_err(fail)
+    }
+}
@@ -0,0 +1,251 @@
+//! HTTPS for the HTTP transports, optionally with client certificates.
+//!
+//! With `INFERENCO_MCP_TLS_CERT` and `INFERENCO_MCP_TLS_KEY` set, the HTTP
+//! server speaks TLS only. `INFERENCO_MCP_TLS_CLIENT_CA` adds mutual TLS:
+//! clients must present a certificate signed by one of its CAs, unless
+//! `INFERENCO_MCP_TLS_CLIENT_AUTH=optional` lets the others fall back to
+//! API keys and tokens. A verified certificate authenticates its requests
+//! by itself, as [`AuthMethod::ClientCertificate`](crate::auth::AuthMethod)
+//! with the certificate's subject and subject alternative names.
+//!
+//! Serving TLS needs the `tls` feature; without it the variables are
+//! refused at startup.
+
+use std::{env, path::PathBuf};
+
+/// Whether clients of a mutual TLS server must present a certificate.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ClientAuth {
+    #[default]
+    Required,
+    /// Clients without one authenticate as they would over plain HTTP.
+    Optional,
+}
+
+/// The server's certificate, and the CAs client certificates are checked
+/// against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsSettings {
+    /// PEM certificate chain, leaf first.
+    pub cert: PathBuf,
+    /// PEM private key of the leaf certificate.
+    pub key: PathBuf,
+    /// PEM bundle of CAs that sign client certificates; no client
+    /// certificates are asked for if `None`.
+    pub client_ca: Option<PathBuf>,
+    pub client_auth: ClientAuth,
+}
+
+impl TlsSettings {
+    /// Read `INFERENCO_MCP_TLS_CERT`, `INFERENCO_MCP_TLS_KEY`,
+    /// `INFERENCO_MCP_TLS_CLIENT_CA`, and `INFERENCO_MCP_TLS_CLIENT_AUTH`;
+    /// `None` when TLS is not configured.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let path = |name: &str| {
+            env::var(name)
+                .ok()
+                .map(|path| path.trim().to_string())
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from)
+        };
+        let client_ca = path("INFERENCO_MCP_TLS_CLIENT_CA");
+        let client_auth = match env::var("INFERENCO_MCP_TLS_CLIENT_AUTH")
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "" | "required" => ClientAuth::Required,
+            "optional" => ClientAuth::Optional,
+            other => {
+                return Err(format!(
+                    "INFERENCO_MCP_TLS_CLIENT_AUTH must be required or optional, got '{other}'"
+                ))
+            }
+        };
+        let (cert, key) = match (
+            path("INFERENCO_MCP_TLS_CERT"),
+            path("INFERENCO_MCP_TLS_KEY"),
+        ) {
+            (Some(cert), Some(key)) => (cert, key),
+            (None, None) if client_ca.is_none() => return Ok(None),
+            (None, None) => {
+                return Err(
+                    "INFERENCO_MCP_TLS_CLIENT_CA needs INFERENCO_MCP_TLS_CERT and INFERENCO_MCP_TLS_KEY"
+                        .to_string(),
+                )
+            }
+            _ => {
+                return Err(
+                    "INFERENCO_MCP_TLS_CERT and INFERENCO_MCP_TLS_KEY must be set together"
+                        .to_string(),
+                )
+            }
+        };
+        if cfg!(not(feature = "tls")) {
+            return Err(
+                "INFERENCO_MCP_TLS_CERT requires building with `--features tls`".to_string(),
+            );
+        }
+        Ok(Some(Self {
+            cert,
+            key,
+            client_ca,
+            client_auth,
+        }))
+    }
+
+    /// The rustls configuration these settings describe, offering HTTP/2
+    /// and HTTP/1.1.
+    #[cfg(feature = "tls")]
+    pub fn server_config(&self) -> Result<std::sync::Arc<rustls::ServerConfig>, String> {
+        use rustls::{server::WebPkiClientVerifier, RootCertStore, ServerConfig};
+        use std::sync::Arc;
+
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|error| format!("cannot set up TLS: {error}"))?;
+        let builder = match &self.client_ca {
+            Some(path) => {
+                let mut roots = RootCertStore::empty();
+                for certificate in read_certificates(path)? {
+                    roots
+                        .add(certificate)
+                        .map_err(|error| format!("{}: {error}", path.display()))?;
+                }
+                let verifier =
+                    WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+                let verifier = match self.client_auth {
+                    ClientAuth::Required => verifier,
+                    ClientAuth::Optional => verifier.allow_unauthenticated(),
+                };
+                let verifier = verifier
+                    .build()
+                    .map_err(|error| format!("{}: {error}", path.display()))?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let mut config = builder
+            .with_single_cert(read_certificates(&self.cert)?, read_key(&self.key)?)
+            .map_err(|error| format!("{}: {error}", self.cert.display()))?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(Arc::new(config))
+    }
+}
+
+/// The identity in a verified client certificate: its subject, as in
+/// `CN=build-agent, O=Inferenco`, and its DNS, URI, email, and IP subject
+/// alternative names.
+#[cfg(feature = "tls")]
+pub fn identify(der: &[u8]) -> Result<crate::auth::AuthContext, String> {
+    use x509_parser::{certificate::X509Certificate, extensions::GeneralName, prelude::FromDer};
+
+    let (_, certificate) = X509Certificate::from_der(der)
+        .map_err(|error| format!("unreadable client certificate: {error}"))?;
+    let sans = certificate
+        .subject_alternative_name()
+        .map_err(|error| format!("unreadable subject alternative names: {error}"))?
+        .map(|extension| {
+            extension
+                .value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(name) => Some(format!("DNS:{name}")),
+                    GeneralName::URI(uri) => Some(format!("URI:{uri}")),
+                    GeneralName::RFC822Name(email) => Some(format!("email:{email}")),
+                    GeneralName::IPAddress(bytes) => match bytes.len() {
+                        4 => Some(format!(
+                            "IP:{}",
+                            std::net::Ipv4Addr::from(<[u8; 4]>::try_from(*bytes).ok()?)
+                        )),
+                        16 => Some(format!(
+                            "IP:{}",
+                            std::net::Ipv6Addr::from(<[u8; 16]>::try_from(*bytes).ok()?)
+                        )),
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(crate::auth::AuthContext::for_certificate(
+        certificate.subject().to_string(),
+        sans,
+    ))
+}
+
+#[cfg(feature = "tls")]
+fn read_certificates(
+    path: &std::path::Path,
+) -> Result<Vec<rustls::pki_types::CertificateDer<'static>>, String> {
+    let pem =
+        std::fs::read(path).map_err(|error| format!("cannot read {}: {error}", path.display()))?;
+    let certificates = rustls_pemfile::certs(&mut pem.as_slice())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| format!("{}: {error}", path.display()))?;
+    if certificates.is_empty() {
+        return Err(format!("{} holds no PEM certificates", path.display()));
+    }
+    Ok(certificates)
+}
+
+#[cfg(feature = "tls")]
+fn read_key(path: &std::path::Path) -> Result<rustls::pki_types::PrivateKeyDer<'static>, String> {
+    let pem =
+        std::fs::read(path).map_err(|error| format!("cannot read {}: {error}", path.display()))?;
+    rustls_pemfile::private_key(&mut pem.as_slice())
+        .map_err(|error| format!("{}: {error}", path.display()))?
+        .ok_or_else(|| format!("{} holds no PEM private key", path.display()))
+}
+
+#[cfg(all(test, feature = "tls"))]
+mod tests {
+    use super::*;
+    use crate::auth::AuthMethod;
+
+    #[test]
+    fn client_certificates_identify_their_subject_and_names() {
+        let mut params =
+            rcgen::CertificateParams::new(vec!["agent.internal".to_string()]).expect("valid names");
+        params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, "build-agent");
+        params
+            .subject_alt_names
+            .push(rcgen::SanType::IpAddress([10, 0, 0, 7].into()));
+        let key = rcgen::KeyPair::generate().expect("key pair");
+        let certificate = params.self_signed(&key).expect("certificate");
+
+        let auth = identify(certificate.der()).expect("identified");
+        assert_eq!(auth.subject, "CN=build-agent");
+        assert_eq!(
+            auth.method,
+            AuthMethod::ClientCertificate {
+                sans: vec!["DNS:agent.internal".to_string(), "IP:10.0.0.7".to_string()]
+            }
+        );
+        assert_eq!(auth.namespace(), "cert:CN=build-agent");
+        assert!(identify(b"not a certificate").is_err());
+
+        let dir = tempfile::tempdir().expect("temp dir");
+        let (cert, key_path) = (dir.path().join("cert.pem"), dir.path().join("key.pem"));
+        std::fs::write(&cert, certificate.pem()).unwrap();
+        std::fs::write(&key_path, key.serialize_pem()).unwrap();
+        let settings = TlsSettings {
+            cert: cert.clone(),
+            key: key_path.clone(),
+            client_ca: Some(cert),
+            client_auth: ClientAuth::Required,
+        };
+        assert!(settings.server_config().is_ok());
+        let swapped = TlsSettings {
+            cert: key_path,
+            ..settings
+        };
+        assert!(swapped.server_config().is_err());
+    }
+}
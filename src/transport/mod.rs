@@ -0,0 +1,93 @@
+//! Pieces of the network transports that live outside the binary: session
+//! bookkeeping, the WebSocket connection loop, the TCP listener, the HTTPS
+//! listener, and the messages the server pushes to a session, as JSON or as
+//! SSE events.
+
+pub mod sse;
+pub mod streamable_http;
+pub mod tcp;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod ws;
+
+use crate::{server::ToolService, sessions::SessionState};
+use axum::response::sse::Event;
+use futures::{Stream, StreamExt as _};
+use std::convert::Infallible;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// `notifications/resources/updated` messages for the resources the
+/// session behind `state` subscribed to.
+pub fn resource_updates(
+    service: &ToolService,
+    state: SessionState,
+) -> impl Stream<Item = serde_json::Value> + Send + 'static {
+    service
+        .resources()
+        .subscriptions()
+        .updates_for(state)
+        .map(|uri| {
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/resources/updated",
+                "params": { "uri": uri }
+            })
+        })
+}
+
+/// `notifications/tools/list_changed` messages, one per change of the tool
+/// list, for as long as the service lives.
+pub fn tool_list_changes(
+    service: &ToolService,
+) -> impl Stream<Item = serde_json::Value> + Send + 'static {
+    service.tool_switches().changes().map(|()| {
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/tools/list_changed"
+        })
+    })
+}
+
+/// Messages pushed to a session, such as progress notifications for the
+/// tool calls it makes. Ends when the session does.
+pub fn session_notifications(
+    notifications: broadcast::Receiver<serde_json::Value>,
+) -> impl Stream<Item = serde_json::Value> + Send + 'static {
+    futures::stream::unfold(notifications, |mut notifications| async move {
+        loop {
+            match notifications.recv().await {
+                Ok(message) => return Some((message, notifications)),
+                Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+/// [`resource_updates`] as SSE events.
+pub fn resource_update_stream(
+    service: &ToolService,
+    state: SessionState,
+) -> impl Stream<Item = Result<Event, Infallible>> + Send + 'static {
+    resource_updates(service, state).map(event)
+}
+
+/// [`tool_list_changes`] as SSE events.
+pub fn tool_list_change_stream(
+    service: &ToolService,
+) -> impl Stream<Item = Result<Event, Infallible>> + Send + 'static {
+    tool_list_changes(service).map(event)
+}
+
+/// [`session_notifications`] as SSE events.
+pub fn session_notification_stream(
+    notifications: broadcast::Receiver<serde_json::Value>,
+) -> impl Stream<Item = Result<Event, Infallible>> + Send + 'static {
+    session_notifications(notifications).map(event)
+}
+
+fn event(message: serde_json::Value) -> Result<Event, Infallible> {
+    Ok(Event::default()
+        .json_data(message)
+        .expect("notifications serialize"))
+}
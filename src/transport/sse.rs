@@ -0,0 +1,140 @@
+//! Response delivery for the HTTP+SSE transport (`/sse`).
+//!
+//! In this transport the client holds `GET /sse` open and POSTs its
+//! messages separately; the POST is only acknowledged, and the JSON-RPC
+//! response travels back as a `message` event on the stream. Each stream
+//! opens an [`Outbox`] under its session ID, and [`SseStreams::deliver`]
+//! finds it again for the POSTs that name the session.
+
+use axum::response::sse::Event;
+use futures::Stream;
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    sync::{Arc, RwLock},
+};
+use tokio::sync::mpsc;
+
+/// Responses a stream may fall behind by before POSTs wait for it.
+const RESPONSE_BUFFER: usize = 64;
+
+/// The event that tells a client where to POST: the `/sse` endpoint with
+/// its session in the query, as the 2024-11-05 protocol revision expects.
+pub fn endpoint_event(session_id: &str) -> Event {
+    Event::default()
+        .event("endpoint")
+        .data(format!("/sse?sessionId={session_id}"))
+}
+
+/// Why a response could not be delivered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Undeliverable {
+    /// No stream is open for the session.
+    NoStream,
+}
+
+/// Open `/sse` streams, keyed by session ID.
+///
+/// As with [`SessionRegistry`](crate::sessions::SessionRegistry), every
+/// POST looks its stream up while only connects and disconnects change the
+/// map, so lookups share a read lock.
+#[derive(Default)]
+pub struct SseStreams {
+    outboxes: RwLock<BTreeMap<String, mpsc::Sender<Event>>>,
+}
+
+impl SseStreams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open the outbox of session `id`; it is removed when dropped.
+    pub fn open(self: &Arc<Self>, id: &str) -> Outbox {
+        let (sender, receiver) = mpsc::channel(RESPONSE_BUFFER);
+        self.write().insert(id.to_string(), sender);
+        Outbox {
+            id: id.to_string(),
+            streams: self.clone(),
+            receiver,
+        }
+    }
+
+    /// Send `message`, a serialized JSON-RPC response or batch, down the
+    /// stream of session `id`. Waits while the stream is behind.
+    pub async fn deliver(&self, id: &str, message: String) -> Result<(), Undeliverable> {
+        let sender = self
+            .read()
+            .get(id)
+            .cloned()
+            .ok_or(Undeliverable::NoStream)?;
+        sender
+            .send(Event::default().event("message").data(message))
+            .await
+            .map_err(|_| Undeliverable::NoStream)
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.read().contains_key(id)
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, BTreeMap<String, mpsc::Sender<Event>>> {
+        self.outboxes.read().expect("sse stream lock poisoned")
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, BTreeMap<String, mpsc::Sender<Event>>> {
+        self.outboxes.write().expect("sse stream lock poisoned")
+    }
+}
+
+/// Responses waiting to go out on one `/sse` stream.
+pub struct Outbox {
+    id: String,
+    streams: Arc<SseStreams>,
+    receiver: mpsc::Receiver<Event>,
+}
+
+impl Outbox {
+    /// The responses as SSE events; the outbox closes when this is dropped.
+    pub fn into_stream(self) -> impl Stream<Item = Result<Event, Infallible>> + Send + 'static {
+        futures::stream::unfold(self, |mut outbox| async move {
+            let event = outbox.receiver.recv().await?;
+            Some((Ok(event), outbox))
+        })
+    }
+}
+
+impl Drop for Outbox {
+    fn drop(&mut self) {
+        self.streams.write().remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt as _;
+
+    #[tokio::test]
+    async fn delivers_to_the_open_stream_until_it_closes() {
+        let streams = Arc::new(SseStreams::new());
+        assert_eq!(
+            streams.deliver("abc", "{}".to_string()).await,
+            Err(Undeliverable::NoStream)
+        );
+
+        let mut events = Box::pin(streams.open("abc").into_stream());
+        assert!(streams.contains("abc"));
+        streams
+            .deliver("abc", r#"{"id":1}"#.to_string())
+            .await
+            .expect("stream is open");
+        assert!(events.next().await.is_some());
+
+        drop(events);
+        assert!(!streams.contains("abc"));
+        assert_eq!(
+            streams.deliver("abc", "{}".to_string()).await,
+            Err(Undeliverable::NoStream)
+        );
+    }
+}
@@ -0,0 +1,464 @@
+//! Session management for the MCP Streamable HTTP transport (`/mcp`).
+//!
+//! One endpoint carries the whole conversation. A POST whose body holds
+//! `initialize` opens a session, and the response names it in
+//! `Mcp-Session-Id`; every later POST, GET, and DELETE sends that ID back.
+//! Requests without one get 400, and unknown or expired IDs get 404 so the
+//! client knows to initialize again. POSTs are answered with
+//! `application/json`; messages the server sends on its own, such as
+//! progress and resource updates, go out on the event stream a GET opens.
+//! A DELETE ends the session, and sessions left unused for the idle timeout
+//! are closed by [`StreamableSessions::spawn_sweeper`]. At most
+//! `max_sessions` are kept: opening one more evicts the least recently used
+//! session without an open event stream, and is refused with 503 when every
+//! session has one.
+//!
+//! The HTTP handlers stay with the binary's router; this module decides
+//! which session a request belongs to.
+
+use super::{resource_update_stream, session_notification_stream, tool_list_change_stream};
+use crate::{
+    metrics::SessionEnd,
+    protocol::Response,
+    rpc::session_id,
+    server::ToolService,
+    sessions::{ClientInfo, SessionHandle},
+};
+use axum::{http::StatusCode, response::sse::Event};
+use futures::{future, Stream, StreamExt as _};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+pub const SESSION_HEADER: &str = "mcp-session-id";
+/// How sessions of this transport are labelled in `/admin/sessions`.
+pub const TRANSPORT: &str = "streamable_http";
+/// Longest gap between sweeps for idle sessions.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Sessions opened over `/mcp`. Unlike an `/sse` session, which lives as
+/// long as its connection, these outlive every request, so they are kept
+/// here until deleted or idle.
+pub struct StreamableSessions {
+    service: Arc<ToolService>,
+    idle_timeout: Duration,
+    /// Sessions kept before the least recently used is evicted; 0 for no
+    /// limit.
+    max_sessions: usize,
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+struct Session {
+    /// Keeps the session in the service's registry.
+    _handle: SessionHandle,
+    opened: Instant,
+    activity: Arc<Activity>,
+}
+
+#[derive(Debug)]
+struct Activity {
+    last_seen: Mutex<Instant>,
+    /// Event streams open on the session, which is never idle while any are.
+    streams: AtomicUsize,
+}
+
+impl Activity {
+    fn touch(&self) {
+        *self
+            .last_seen
+            .lock()
+            .expect("session activity lock poisoned") = Instant::now();
+    }
+
+    /// Whether nothing has used the session for `timeout`.
+    fn idle(&self, timeout: Duration) -> bool {
+        self.unused_for().is_some_and(|unused| unused >= timeout)
+    }
+
+    /// How long since the session was last used, or `None` while an event
+    /// stream is open on it.
+    fn unused_for(&self) -> Option<Duration> {
+        (self.streams.load(Ordering::Acquire) == 0).then(|| {
+            self.last_seen
+                .lock()
+                .expect("session activity lock poisoned")
+                .elapsed()
+        })
+    }
+}
+
+/// Counts one open event stream; see [`Activity::streams`].
+struct StreamGuard(Arc<Activity>);
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        self.0.streams.fetch_sub(1, Ordering::AcqRel);
+        self.0.touch();
+    }
+}
+
+/// Which session a POST belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Admission {
+    /// The body initializes; answer it in a new session.
+    Initialize,
+    /// The body continues the open session with this ID.
+    Session(String),
+}
+
+/// Why a request was turned away before dispatch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Refusal {
+    /// The request names no session and does not initialize one.
+    MissingSession,
+    /// The named session does not exist, or no longer does.
+    UnknownSession,
+    /// `max_sessions` are open and all of them are in use.
+    TooManySessions,
+}
+
+impl Refusal {
+    pub fn status(self) -> StatusCode {
+        match self {
+            Refusal::MissingSession => StatusCode::BAD_REQUEST,
+            Refusal::UnknownSession => StatusCode::NOT_FOUND,
+            Refusal::TooManySessions => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    /// The JSON-RPC error the request is answered with.
+    pub fn response(self) -> Response {
+        let message = match self {
+            Refusal::MissingSession => "Bad Request: Mcp-Session-Id header is required",
+            Refusal::UnknownSession => "Session not found; initialize a new one",
+            Refusal::TooManySessions => "Too many open sessions; try again later",
+        };
+        Response::error_code(serde_json::Value::Null, -32000, message)
+    }
+}
+
+impl StreamableSessions {
+    pub fn new(service: Arc<ToolService>, idle_timeout: Duration, max_sessions: usize) -> Self {
+        Self {
+            service,
+            idle_timeout,
+            max_sessions,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Decide which session a POST of `body` belongs to, marking it active.
+    pub fn admit(
+        &self,
+        headers: &axum::http::HeaderMap,
+        body: &[u8],
+    ) -> Result<Admission, Refusal> {
+        if initializes(body) {
+            return Ok(Admission::Initialize);
+        }
+        let id = session_id(headers).ok_or(Refusal::MissingSession)?;
+        if self.touch(id) {
+            Ok(Admission::Session(id.to_string()))
+        } else {
+            Err(Refusal::UnknownSession)
+        }
+    }
+
+    /// Open a session for `client` and return its ID, evicting the least
+    /// recently used session without an event stream if there are already
+    /// `max_sessions`. Refused when there is none to evict.
+    pub fn open(&self, client: ClientInfo) -> Result<String, Refusal> {
+        if self.max_sessions > 0
+            && self.len() >= self.max_sessions
+            && !self.evict_least_recently_used()
+        {
+            tracing::warn!(
+                max_sessions = self.max_sessions,
+                "refused a streamable http session; all are in use"
+            );
+            return Err(Refusal::TooManySessions);
+        }
+        let id = Uuid::new_v4().to_string();
+        let handle = self
+            .service
+            .sessions()
+            .register(id.clone(), TRANSPORT, client);
+        let now = Instant::now();
+        self.lock().insert(
+            id.clone(),
+            Session {
+                _handle: handle,
+                opened: now,
+                activity: Arc::new(Activity {
+                    last_seen: Mutex::new(now),
+                    streams: AtomicUsize::new(0),
+                }),
+            },
+        );
+        self.service.metrics().session_opened();
+        tracing::info!(session_id = %id, event = "opened", "streamable http session opened");
+        Ok(id)
+    }
+
+    /// Mark session `id` as just used. Returns whether it exists.
+    pub fn touch(&self, id: &str) -> bool {
+        match self.lock().get(id) {
+            Some(session) => {
+                session.activity.touch();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// End session `id` at the client's request. Returns whether it existed.
+    pub fn close(&self, id: &str) -> bool {
+        let session = self.lock().remove(id);
+        match session {
+            Some(session) => {
+                self.ended(id, session, SessionEnd::Disconnected);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Close every session idle for longer than the idle timeout, returning
+    /// how many were.
+    pub fn evict_idle(&self) -> usize {
+        let idle: Vec<(String, Session)> = {
+            let mut sessions = self.lock();
+            let ids: Vec<String> = sessions
+                .iter()
+                .filter(|(_, session)| session.activity.idle(self.idle_timeout))
+                .map(|(id, _)| id.clone())
+                .collect();
+            ids.into_iter()
+                .filter_map(|id| sessions.remove(&id).map(|session| (id, session)))
+                .collect()
+        };
+        let evicted = idle.len();
+        for (id, session) in idle {
+            self.ended(&id, session, SessionEnd::Evicted);
+        }
+        evicted
+    }
+
+    /// Returns whether a session was evicted.
+    fn evict_least_recently_used(&self) -> bool {
+        let evicted = {
+            let mut sessions = self.lock();
+            let oldest = sessions
+                .iter()
+                .filter_map(|(id, session)| Some((session.activity.unused_for()?, id)))
+                .max()
+                .map(|(_, id)| id.clone());
+            oldest.and_then(|id| sessions.remove(&id).map(|session| (id, session)))
+        };
+        match evicted {
+            Some((id, session)) => {
+                self.ended(&id, session, SessionEnd::Evicted);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Evict idle sessions in the background for as long as `self` lives.
+    pub fn spawn_sweeper(self: &Arc<Self>) {
+        let sessions = Arc::downgrade(self);
+        let period = self
+            .idle_timeout
+            .clamp(Duration::from_secs(1), SWEEP_INTERVAL);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                let Some(sessions) = sessions.upgrade() else {
+                    return;
+                };
+                sessions.evict_idle();
+            }
+        });
+    }
+
+    /// Messages for session `id`, or `None` when there is no such session.
+    /// The stream ends with the session, and the session does not idle out
+    /// while it is open.
+    pub fn events(
+        &self,
+        id: &str,
+    ) -> Option<impl Stream<Item = Result<Event, Infallible>> + Send + 'static> {
+        let activity = self.lock().get(id)?.activity.clone();
+        let registry = self.service.sessions();
+        let state = registry.state(id)?;
+        let notifications = registry.notifications(id)?;
+        activity.streams.fetch_add(1, Ordering::AcqRel);
+        let guard = StreamGuard(activity);
+
+        // Resource updates and tool list changes never end on their own, so
+        // the stream is over once the session's notifications are.
+        let notifications = session_notification_stream(notifications)
+            .map(Some)
+            .chain(futures::stream::once(future::ready(None)));
+        let updates = futures::stream::select(
+            resource_update_stream(&self.service, state),
+            tool_list_change_stream(&self.service),
+        )
+        .map(Some);
+        Some(
+            futures::stream::select(notifications, updates)
+                .take_while(|event| future::ready(event.is_some()))
+                .filter_map(move |event| {
+                    let _ = &guard;
+                    future::ready(event)
+                }),
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn ended(&self, id: &str, session: Session, end: SessionEnd) {
+        let age = session.opened.elapsed();
+        // Dropping the handle unregisters the session and ends its streams.
+        drop(session);
+        self.service.metrics().session_closed(age, end);
+        tracing::info!(
+            session_id = %id,
+            event = if end == SessionEnd::Evicted { "evicted" } else { "deleted" },
+            age_secs = age.as_secs(),
+            "streamable http session closed"
+        );
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Session>> {
+        self.sessions
+            .lock()
+            .expect("streamable session lock poisoned")
+    }
+}
+
+/// Whether `body`, a single message or a batch, holds an `initialize`
+/// request.
+fn initializes(body: &[u8]) -> bool {
+    #[derive(Deserialize)]
+    struct Message {
+        #[serde(default)]
+        method: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Body {
+        Single(Message),
+        Batch(Vec<Message>),
+    }
+
+    let is_initialize = |message: &Message| message.method.as_deref() == Some("initialize");
+    match serde_json::from_slice(body) {
+        Ok(Body::Single(message)) => is_initialize(&message),
+        Ok(Body::Batch(messages)) => messages.iter().any(is_initialize),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{HeaderMap, HeaderValue};
+
+    fn with_session(id: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(SESSION_HEADER, HeaderValue::from_str(id).unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn sessions_open_on_initialize_and_close_when_deleted_or_idle() {
+        let service = Arc::new(ToolService::new());
+        let sessions = StreamableSessions::new(service.clone(), Duration::ZERO, 0);
+        let init = br#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#;
+        let list = br#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#;
+
+        assert_eq!(
+            sessions.admit(&HeaderMap::new(), init),
+            Ok(Admission::Initialize)
+        );
+        assert_eq!(
+            sessions.admit(&HeaderMap::new(), list),
+            Err(Refusal::MissingSession)
+        );
+        assert_eq!(
+            sessions.admit(&with_session("nope"), list),
+            Err(Refusal::UnknownSession)
+        );
+
+        let id = sessions.open(ClientInfo::default()).expect("room to open");
+        assert_eq!(
+            sessions.admit(&with_session(&id), list),
+            Ok(Admission::Session(id.clone()))
+        );
+        assert_eq!(service.sessions().list()[0].transport, TRANSPORT);
+
+        // An open event stream keeps even a zero idle timeout at bay.
+        let events = sessions.events(&id).expect("session exists");
+        assert_eq!(sessions.evict_idle(), 0);
+        drop(events);
+        assert_eq!(sessions.evict_idle(), 1);
+        assert!(service.sessions().is_empty());
+
+        let id = sessions.open(ClientInfo::default()).expect("room to open");
+        let events = sessions.events(&id).expect("session exists");
+        assert!(sessions.close(&id));
+        assert!(!sessions.close(&id));
+        assert_eq!(events.collect::<Vec<_>>().await.len(), 0);
+    }
+
+    #[test]
+    fn the_least_recently_used_session_makes_room_for_a_new_one() {
+        let service = Arc::new(ToolService::new());
+        let sessions = StreamableSessions::new(service.clone(), Duration::from_secs(60), 2);
+        let first = sessions.open(ClientInfo::default()).expect("room to open");
+        let second = sessions.open(ClientInfo::default()).expect("room to open");
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(sessions.touch(&first));
+
+        let third = sessions.open(ClientInfo::default()).expect("room to open");
+        assert_eq!(sessions.len(), 2);
+        assert!(!sessions.touch(&second), "the oldest unused session goes");
+        assert!(sessions.touch(&first) && sessions.touch(&third));
+        assert_eq!(service.sessions().len(), 2);
+
+        // A session with an open event stream is in use, however old.
+        let _first_events = sessions.events(&first).expect("session exists");
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(sessions.touch(&third));
+        let fourth = sessions.open(ClientInfo::default()).expect("room to open");
+        assert!(sessions.touch(&first));
+        assert!(!sessions.touch(&third));
+
+        // With every session in use, new ones are turned away.
+        let _fourth_events = sessions.events(&fourth).expect("session exists");
+        let refused = sessions.open(ClientInfo::default());
+        assert_eq!(refused, Err(Refusal::TooManySessions));
+        assert_eq!(
+            Refusal::TooManySessions.status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(sessions.len(), 2);
+    }
+}
@@ -0,0 +1,73 @@
+//! The raw TCP transport.
+//!
+//! Each connection carries newline-delimited JSON-RPC, framed exactly like
+//! stdio, and is served by rmcp the same way. A connection is a session of
+//! its own: it has its own session state, key-value namespace
+//! (`session:<id>`), resource subscriptions, and cancellation scope, and is
+//! listed in `/admin/sessions` with transport
+//! `tcp` while it stays open. There is no authentication, so the listener
+//! binds to loopback unless configured otherwise.
+
+use crate::{metrics::SessionEnd, server::ToolService, sessions::ClientInfo};
+use rmcp::ServiceExt;
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+use tokio::net::{TcpListener, TcpStream};
+use uuid::Uuid;
+
+/// How sessions of this transport are labelled in `/admin/sessions`.
+pub const TRANSPORT: &str = "tcp";
+/// Pause after a failed accept, such as when out of file descriptors.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Serve every connection `listener` accepts. Runs until the task is
+/// dropped; connections already open keep running on their own tasks.
+pub async fn serve(listener: TcpListener, service: ToolService) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                tokio::spawn(serve_connection(stream, peer, service.clone()));
+            }
+            Err(error) => {
+                tracing::warn!(%error, "cannot accept TCP connection");
+                tokio::time::sleep(ACCEPT_BACKOFF).await;
+            }
+        }
+    }
+}
+
+async fn serve_connection(stream: TcpStream, peer: SocketAddr, service: ToolService) {
+    let session =
+        service
+            .sessions()
+            .register(Uuid::new_v4().to_string(), TRANSPORT, ClientInfo::default());
+    let state = service
+        .sessions()
+        .state(session.id())
+        .expect("session was just registered");
+    let connection = service.for_connection(session.id(), state);
+    let opened = Instant::now();
+    service.metrics().session_opened();
+    tracing::info!(session_id = %session.id(), %peer, event = "opened", "tcp session opened");
+    match connection.serve(stream).await {
+        Ok(server) => {
+            if let Err(error) = server.waiting().await {
+                tracing::warn!(session_id = %session.id(), %error, "tcp session failed");
+            }
+        }
+        // Including a client that left before the handshake.
+        Err(error) => tracing::info!(session_id = %session.id(), %error, "tcp handshake failed"),
+    }
+    let age = opened.elapsed();
+    service
+        .metrics()
+        .session_closed(age, SessionEnd::Disconnected);
+    tracing::info!(
+        session_id = %session.id(),
+        event = "disconnected",
+        age_secs = age.as_secs(),
+        "tcp session closed"
+    );
+}
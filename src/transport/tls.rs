@@ -0,0 +1,107 @@
+//! The HTTP server over TLS.
+//!
+//! `axum::serve` only speaks plain HTTP, so this accepts connections
+//! itself: each one is handshaken with rustls and then served by hyper
+//! with the same router. The peer address is attached to every request as
+//! axum's `ConnectInfo`, and the identity in a verified client certificate
+//! is put in scope for its requests with [`auth::with_certificate`].
+
+use crate::{auth, tls};
+use axum::{extract::ConnectInfo, Router};
+use hyper::{body::Incoming, Request};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::{
+        conn::auto,
+        graceful::{GracefulShutdown, Watcher},
+    },
+};
+use rustls::ServerConfig;
+use std::{future::Future, net::SocketAddr, sync::Arc, time::Duration};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::TlsAcceptor;
+use tower::Service;
+
+/// Pause after a failed accept, such as when out of file descriptors.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+/// How long a client may take to finish the TLS handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Serve `app` on every connection `listener` accepts until `shutdown`
+/// resolves, then wait for the open connections to finish.
+pub async fn serve(
+    listener: TcpListener,
+    config: Arc<ServerConfig>,
+    app: Router,
+    shutdown: impl Future<Output = ()>,
+) {
+    let acceptor = TlsAcceptor::from(config);
+    let graceful = GracefulShutdown::new();
+    let mut shutdown = std::pin::pin!(shutdown);
+    loop {
+        let (stream, peer) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(error) => {
+                    tracing::warn!(%error, "cannot accept HTTPS connection");
+                    tokio::time::sleep(ACCEPT_BACKOFF).await;
+                    continue;
+                }
+            },
+            () = &mut shutdown => break,
+        };
+        tokio::spawn(serve_connection(
+            stream,
+            peer,
+            acceptor.clone(),
+            app.clone(),
+            graceful.watcher(),
+        ));
+    }
+    graceful.shutdown().await;
+}
+
+/// Finish the handshake with `peer`, then serve its requests until it
+/// disconnects or the server shuts down.
+async fn serve_connection(
+    stream: TcpStream,
+    peer: SocketAddr,
+    acceptor: TlsAcceptor,
+    app: Router,
+    watcher: Watcher,
+) {
+    let stream = match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(error)) => {
+            tracing::debug!(%peer, %error, "TLS handshake failed");
+            return;
+        }
+        Err(_) => {
+            tracing::debug!(%peer, "TLS handshake timed out");
+            return;
+        }
+    };
+    // rustls has already checked the chain against the client CAs.
+    let certificate = match stream.get_ref().1.peer_certificates() {
+        Some([leaf, ..]) => match tls::identify(leaf) {
+            Ok(certificate) => Some(certificate),
+            Err(error) => {
+                tracing::warn!(%peer, %error, "refused client certificate");
+                return;
+            }
+        },
+        _ => None,
+    };
+    if let Some(certificate) = &certificate {
+        tracing::debug!(%peer, subject = %certificate.subject, "client certificate accepted");
+    }
+    let service = hyper::service::service_fn(move |mut request: Request<Incoming>| {
+        request.extensions_mut().insert(ConnectInfo(peer));
+        auth::with_certificate(certificate.clone(), app.clone().call(request))
+    });
+    let builder = auto::Builder::new(TokioExecutor::new());
+    let connection = builder.serve_connection_with_upgrades(TokioIo::new(stream), service);
+    if let Err(error) = watcher.watch(connection).await {
+        tracing::debug!(%peer, %error, "HTTPS connection failed");
+    }
+}
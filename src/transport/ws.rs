@@ -0,0 +1,70 @@
+//! The WebSocket transport (`/ws`).
+//!
+//! One connection carries the whole conversation in both directions. Each
+//! text frame from the client holds a JSON-RPC message or batch and is
+//! answered with a text frame holding the response, or nothing for
+//! notifications; messages the server sends on its own, such as progress
+//! and resource updates, go out as frames of their own. Requests are
+//! handled concurrently, so a slow tool call does not hold up the rest, and
+//! responses may arrive in any order, matched to requests by their IDs.
+//!
+//! The binary's handler authenticates the upgrade and keeps the session;
+//! [`serve`] runs the connection until either side closes it.
+
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket};
+use futures::{stream::FuturesUnordered, Future, Stream, StreamExt as _};
+use std::time::Duration;
+use tokio::time::{interval_at, Instant};
+
+/// How sessions of this transport are labelled in `/admin/sessions`.
+pub const TRANSPORT: &str = "ws";
+/// Gap between pings, which keep proxies from closing idle connections.
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+/// Requests one connection may have in flight; later frames wait unread.
+const MAX_IN_FLIGHT: usize = 32;
+
+/// Run the connection: hand each message to `handle`, send back what it
+/// returns, and send every message from `pushes`. Returns once the client
+/// disconnects or `pushes` ends with the session.
+pub async fn serve<H, F>(
+    mut socket: WebSocket,
+    pushes: impl Stream<Item = serde_json::Value> + Send,
+    handle: H,
+) where
+    H: Fn(String) -> F,
+    F: Future<Output = Option<String>> + Send,
+{
+    let mut pushes = std::pin::pin!(pushes);
+    let mut in_flight = FuturesUnordered::new();
+    let mut ping = interval_at(Instant::now() + PING_INTERVAL, PING_INTERVAL);
+    loop {
+        let outgoing = tokio::select! {
+            frame = socket.recv(), if in_flight.len() < MAX_IN_FLIGHT => match frame {
+                Some(Ok(Message::Text(text))) => {
+                    in_flight.push(handle(text.to_string()));
+                    continue;
+                }
+                Some(Ok(Message::Binary(_))) => Message::Close(Some(CloseFrame {
+                    code: close_code::UNSUPPORTED,
+                    reason: "send JSON-RPC messages as text frames".into(),
+                })),
+                // Pongs are answered by the socket itself.
+                Some(Ok(Message::Ping(_) | Message::Pong(_))) => continue,
+                Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+            },
+            Some(reply) = in_flight.next(), if !in_flight.is_empty() => match reply {
+                Some(reply) => Message::Text(reply.into()),
+                None => continue,
+            },
+            push = pushes.next() => match push {
+                Some(message) => Message::Text(message.to_string().into()),
+                None => break,
+            },
+            _ = ping.tick() => Message::Ping(Default::default()),
+        };
+        let closing = matches!(outgoing, Message::Close(_));
+        if socket.send(outgoing).await.is_err() || closing {
+            break;
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Zero-downtime restarts.
+//!
+//! On `SIGUSR2` the running server re-executes its own binary and hands the
+//! listening socket to the new process as an inherited file descriptor. The
+//! successor starts accepting on the same socket, then sends its parent
+//! `SIGTERM`, which triggers the parent's normal drain-and-exit sequence. No
+//! connection is refused at any point because the socket is never closed.
+
+#[cfg(unix)]
+use std::sync::Mutex;
+use std::{env, io, net::TcpListener};
+
+pub use crate::config::{LISTEN_FD_VAR, PARENT_PID_VAR};
+
+/// What a predecessor passed in the environment, taken by [`take_handover`].
+#[cfg(unix)]
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Handover {
+    listen_fd: Option<String>,
+    parent_pid: Option<String>,
+}
+
+#[cfg(unix)]
+impl Handover {
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        Self {
+            listen_fd: lookup(LISTEN_FD_VAR),
+            parent_pid: lookup(PARENT_PID_VAR),
+        }
+    }
+}
+
+#[cfg(unix)]
+static HANDOVER: Mutex<Handover> = Mutex::new(Handover {
+    listen_fd: None,
+    parent_pid: None,
+});
+
+/// Read and clear the handover variables, so processes started later do not
+/// inherit them. Call before the runtime starts any threads: changing the
+/// environment is only sound while nothing else can be reading it.
+#[cfg(unix)]
+pub fn take_handover() {
+    let taken = Handover::from_lookup(|name| env::var(name).ok());
+    for name in [LISTEN_FD_VAR, PARENT_PID_VAR] {
+        env::remove_var(name);
+    }
+    *handover() = taken;
+}
+
+#[cfg(not(unix))]
+pub fn take_handover() {}
+
+#[cfg(unix)]
+fn handover() -> std::sync::MutexGuard<'static, Handover> {
+    HANDOVER.lock().expect("handover lock poisoned")
+}
+
+/// Take over the listening socket passed by a predecessor, if any.
+#[cfg(unix)]
+pub fn inherited_listener() -> io::Result<Option<TcpListener>> {
+    use std::os::fd::FromRawFd;
+
+    let Some(fd) = handover().listen_fd.take() else {
+        return Ok(None);
+    };
+    let fd = parse_fd(&fd)?;
+    // SAFETY: the predecessor passed us ownership of this open listening socket.
+    let listener = unsafe { TcpListener::from_raw_fd(fd) };
+    listener.set_nonblocking(true)?;
+    Ok(Some(listener))
+}
+
+#[cfg(not(unix))]
+pub fn inherited_listener() -> io::Result<Option<TcpListener>> {
+    Ok(None)
+}
+
+#[cfg(unix)]
+fn parse_fd(value: &str) -> io::Result<std::os::fd::RawFd> {
+    value.parse().ok().filter(|fd| *fd >= 0).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{LISTEN_FD_VAR} must be a file descriptor number, got '{value}'"),
+        )
+    })
+}
+
+/// Start a copy of this binary, with the same arguments and environment, that
+/// inherits `listener`.
+#[cfg(unix)]
+pub fn spawn_successor(listener: std::os::fd::RawFd) -> io::Result<std::process::Child> {
+    // `dup` clears FD_CLOEXEC on the copy, so only this descriptor is inherited.
+    // SAFETY: `listener` is a valid open descriptor owned by the caller.
+    let inherited = unsafe { libc::dup(listener) };
+    if inherited < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let child = std::process::Command::new(env::current_exe()?)
+        .args(env::args_os().skip(1))
+        .env(LISTEN_FD_VAR, inherited.to_string())
+        .env(PARENT_PID_VAR, std::process::id().to_string())
+        .spawn();
+    // SAFETY: `inherited` was opened above and is not used after this point.
+    unsafe { libc::close(inherited) };
+    child
+}
+
+/// Tell the predecessor that this process is serving, so it can drain.
+#[cfg(unix)]
+pub fn notify_parent_ready() {
+    let Some(value) = handover().parent_pid.take() else {
+        return;
+    };
+    match parse_parent_pid(&value) {
+        // SAFETY: sending a signal has no memory-safety preconditions.
+        Some(pid) => {
+            if unsafe { libc::kill(pid, libc::SIGTERM) } != 0 {
+                tracing::warn!(
+                    pid,
+                    error = %io::Error::last_os_error(),
+                    "failed to signal previous process"
+                );
+            }
+        }
+        None => tracing::warn!(%value, "ignoring invalid {PARENT_PID_VAR}"),
+    }
+}
+
+/// A predecessor's PID. Zero and negative values would signal a process
+/// group, so they are refused.
+#[cfg(unix)]
+fn parse_parent_pid(value: &str) -> Option<libc::pid_t> {
+    value.parse().ok().filter(|pid| *pid > 0)
+}
+
+#[cfg(not(unix))]
+pub fn notify_parent_ready() {}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handover_variables_are_parsed_strictly() {
+        let handover = Handover::from_lookup(|name| match name {
+            LISTEN_FD_VAR => Some("7".to_string()),
+            _ => None,
+        });
+        assert_eq!(handover.listen_fd.as_deref(), Some("7"));
+        assert_eq!(handover.parent_pid, None);
+
+        assert_eq!(parse_fd("3").unwrap(), 3);
+        for bad in ["", "-1", "three", "3.0"] {
+            let error = parse_fd(bad).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput, "{bad}");
+        }
+        assert_eq!(parse_parent_pid("4242"), Some(4242));
+        for bad in ["0", "-1", "", "pid"] {
+            assert_eq!(parse_parent_pid(bad), None, "{bad}");
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+/// Identity recorded for requests made without an API key.
+pub const ANONYMOUS: &str = "anonymous";
+
+/// Usage is aggregated into hourly buckets...
+const BUCKET_SECS: i64 = 3600;
+/// ...and buckets older than thirty days are discarded.
+const RETENTION_SECS: i64 = 30 * 24 * 3600;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageCounts {
+    pub calls: u64,
+    pub errors: u64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+}
+
+impl UsageCounts {
+    fn add(&mut self, other: &UsageCounts) {
+        self.calls += other.calls;
+        self.errors += other.errors;
+        self.bytes_in += other.bytes_in;
+        self.bytes_out += other.bytes_out;
+    }
+}
+
+/// Usage summary for one API key over a window. `operations` is keyed by
+/// tool name for `tools/call` and by JSON-RPC method otherwise.
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyUsage {
+    pub key: String,
+    pub totals: UsageCounts,
+    pub operations: BTreeMap<String, UsageCounts>,
+}
+
+/// key fingerprint -> bucket start (unix seconds) -> operation -> counts
+type UsageBuckets = BTreeMap<String, BTreeMap<i64, BTreeMap<String, UsageCounts>>>;
+
+/// Rolling per-API-key usage accounting, optionally persisted as JSON.
+///
+/// Keys are identified by their fingerprint; raw API keys are never stored.
+pub struct UsageTracker {
+    buckets: Mutex<UsageBuckets>,
+    path: Option<PathBuf>,
+}
+
+impl UsageTracker {
+    /// In-memory tracker that is never persisted.
+    pub fn in_memory() -> Self {
+        Self {
+            buckets: Mutex::new(BTreeMap::new()),
+            path: None,
+        }
+    }
+
+    /// Tracker persisted at `path`, warmed from any existing file.
+    pub fn load(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let buckets = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(error) => return Err(error),
+        };
+        Ok(Self {
+            buckets: Mutex::new(buckets),
+            path: Some(path),
+        })
+    }
+
+    /// Persist to `path` when set, otherwise keep usage in memory only.
+    pub fn open(path: Option<&Path>) -> io::Result<Self> {
+        match path {
+            Some(path) => Self::load(path),
+            None => Ok(Self::in_memory()),
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Record one request made with `key`.
+    pub fn record(
+        &self,
+        key: &str,
+        operation: &str,
+        is_error: bool,
+        bytes_in: u64,
+        bytes_out: u64,
+    ) {
+        self.record_at(
+            Utc::now().timestamp(),
+            key,
+            operation,
+            UsageCounts {
+                calls: 1,
+                errors: u64::from(is_error),
+                bytes_in,
+                bytes_out,
+            },
+        );
+    }
+
+    fn record_at(&self, now: i64, key: &str, operation: &str, counts: UsageCounts) {
+        let bucket = now - now.rem_euclid(BUCKET_SECS);
+        let mut buckets = self.buckets.lock().expect("usage lock poisoned");
+        let per_key = buckets.entry(key.to_string()).or_default();
+        if !per_key.contains_key(&bucket) {
+            // Prune lazily when a key starts a new bucket, so retention needs
+            // no background task and most writes touch a single bucket.
+            per_key.retain(|start, _| *start > now - RETENTION_SECS);
+        }
+        per_key
+            .entry(bucket)
+            .or_default()
+            .entry(operation.to_string())
+            .or_default()
+            .add(&counts);
+    }
+
+    /// Summarize usage for every key over the trailing `window`, at hourly
+    /// granularity.
+    pub fn summary(&self, window: Duration) -> Vec<KeyUsage> {
+        self.summary_at(Utc::now().timestamp(), window)
+    }
+
+    fn summary_at(&self, now: i64, window: Duration) -> Vec<KeyUsage> {
+        let since = now - window.as_secs() as i64;
+        let buckets = self.buckets.lock().expect("usage lock poisoned");
+        buckets
+            .iter()
+            .filter_map(|(key, per_key)| {
+                let mut usage = KeyUsage {
+                    key: key.clone(),
+                    totals: UsageCounts::default(),
+                    operations: BTreeMap::new(),
+                };
+                for (_, operations) in per_key.range(since - since.rem_euclid(BUCKET_SECS)..) {
+                    for (operation, counts) in operations {
+                        usage.totals.add(counts);
+                        usage
+                            .operations
+                            .entry(operation.clone())
+                            .or_default()
+                            .add(counts);
+                    }
+                }
+                (usage.totals.calls > 0).then_some(usage)
+            })
+            .collect()
+    }
+
+    /// Write the current state to disk (no-op for in-memory trackers).
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let bytes = {
+            let buckets = self.buckets.lock().expect("usage lock poisoned");
+            serde_json::to_vec(&*buckets)?
+        };
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)?;
+        }
+        // Write then rename so a crash never leaves a truncated file behind.
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, bytes)?;
+        fs::rename(tmp, path)
+    }
+}
+
+/// Render a usage summary as CSV with one row per key and operation.
+pub fn to_csv(summary: &[KeyUsage]) -> String {
+    let mut csv = String::from("key,operation,calls,errors,bytes_in,bytes_out\n");
+    for usage in summary {
+        for (operation, counts) in &usage.operations {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{}\n",
+                usage.key,
+                csv_field(operation),
+                counts.calls,
+                counts.errors,
+                counts.bytes_in,
+                counts.bytes_out
+            ));
+        }
+    }
+    csv
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Parse a window such as `90m`, `24h`, or `7d`.
+pub fn parse_window(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (unit_start, _) = value.char_indices().last()?;
+    let (amount, unit) = value.split_at(unit_start);
+    let amount: u64 = amount.parse().ok()?;
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        _ => return None,
+    };
+    amount.checked_mul(multiplier).map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(errors: u64) -> UsageCounts {
+        UsageCounts {
+            calls: 1,
+            errors,
+            bytes_in: 10,
+            bytes_out: 20,
+        }
+    }
+
+    #[test]
+    fn summarizes_per_key_and_operation_within_window() {
+        let tracker = UsageTracker::in_memory();
+        let now = 10 * 86_400;
+        tracker.record_at(now - 2 * 86_400, "abc", "echo", call(0));
+        tracker.record_at(now - 60, "abc", "echo", call(0));
+        tracker.record_at(now - 30, "abc", "roll_dice", call(1));
+        tracker.record_at(now - 30, "def", "tools/list", call(0));
+
+        let day = tracker.summary_at(now, Duration::from_secs(86_400));
+        assert_eq!(day.len(), 2);
+        let abc = &day[0];
+        assert_eq!(abc.key, "abc");
+        assert_eq!(abc.totals.calls, 2);
+        assert_eq!(abc.totals.errors, 1);
+        assert_eq!(abc.totals.bytes_out, 40);
+        assert_eq!(abc.operations["echo"].calls, 1);
+
+        let week = tracker.summary_at(now, Duration::from_secs(7 * 86_400));
+        assert_eq!(week[0].operations["echo"].calls, 2);
+
+        let csv = to_csv(&day);
+        assert!(csv.starts_with("key,operation,calls,errors,bytes_in,bytes_out\n"));
+        assert!(csv.contains("abc,roll_dice,1,1,10,20\n"));
+    }
+
+    #[test]
+    fn persists_and_reloads() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let path = dir.path().join("usage.json");
+        let tracker = UsageTracker::load(&path).expect("missing file is fine");
+        tracker.record("abc", "echo", false, 5, 7);
+        tracker.save().expect("save usage");
+
+        let reloaded = UsageTracker::load(&path).expect("reload usage");
+        let summary = reloaded.summary(Duration::from_secs(3600));
+        assert_eq!(summary[0].totals.bytes_in, 5);
+    }
+
+    #[test]
+    fn parses_windows() {
+        assert_eq!(parse_window("24h"), Some(Duration::from_secs(86_400)));
+        assert_eq!(parse_window("7d"), Some(Duration::from_secs(604_800)));
+        assert_eq!(parse_window("h"), None);
+        assert_eq!(parse_window(""), None);
+        assert_eq!(parse_window("1é"), None);
+    }
+}
@@ -0,0 +1,111 @@
+use chrono::{DateTime, NaiveDate};
+use rmcp::{model::ProtocolVersion, ErrorData as McpError};
+use serde::Serialize;
+
+/// Protocol versions `initialize` agrees to, oldest first. The last is the
+/// one the server advertises.
+pub const SUPPORTED_PROTOCOL_VERSIONS: [ProtocolVersion; 2] =
+    [ProtocolVersion::V_2024_11_05, ProtocolVersion::V_2025_03_26];
+
+/// What this binary is, for bug reports. Populated by `build.rs`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildInfo {
+    pub name: &'static str,
+    pub version: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_commit: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_timestamp: Option<String>,
+    pub features: Vec<&'static str>,
+    /// Protocol version advertised in `initialize`.
+    pub protocol_version: String,
+    /// Protocol versions `initialize` agrees to.
+    pub supported_protocol_versions: Vec<String>,
+}
+
+pub fn build_info() -> BuildInfo {
+    let commit = env!("INFERENCO_MCP_GIT_COMMIT");
+    let build_timestamp = env!("INFERENCO_MCP_BUILD_EPOCH")
+        .parse()
+        .ok()
+        .and_then(|epoch| DateTime::from_timestamp(epoch, 0))
+        .map(|built_at| built_at.to_rfc3339());
+    BuildInfo {
+        name: env!("CARGO_PKG_NAME"),
+        version: env!("CARGO_PKG_VERSION"),
+        git_commit: (!commit.is_empty()).then_some(commit),
+        build_timestamp,
+        features: env!("INFERENCO_MCP_FEATURES")
+            .split(',')
+            .filter(|feature| !feature.is_empty())
+            .collect(),
+        protocol_version: ProtocolVersion::LATEST.to_string(),
+        supported_protocol_versions: SUPPORTED_PROTOCOL_VERSIONS
+            .iter()
+            .map(ToString::to_string)
+            .collect(),
+    }
+}
+
+/// The protocol version to answer a client's `initialize` with: the one it
+/// asked for when supported, and the newest supported one when it asked for
+/// a later revision than this server knows. Anything else (an older
+/// revision, or something that is no revision date) is an invalid-params
+/// error listing the supported versions.
+pub fn negotiate_protocol_version(requested: &str) -> Result<ProtocolVersion, McpError> {
+    if let Some(version) = SUPPORTED_PROTOCOL_VERSIONS
+        .iter()
+        .find(|version| version.to_string() == requested)
+    {
+        return Ok(version.clone());
+    }
+    let latest = SUPPORTED_PROTOCOL_VERSIONS[SUPPORTED_PROTOCOL_VERSIONS.len() - 1].clone();
+    // Revisions are dates, so a later date is a newer revision.
+    let is_newer = NaiveDate::parse_from_str(requested, "%Y-%m-%d")
+        .is_ok_and(|_| requested > latest.to_string().as_str());
+    if is_newer {
+        return Ok(latest);
+    }
+    let supported: Vec<String> = SUPPORTED_PROTOCOL_VERSIONS
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    Err(McpError::invalid_params(
+        format!(
+            "Unsupported protocol version '{requested}'; this server supports {}",
+            supported.join(", ")
+        ),
+        Some(serde_json::json!({ "supported": supported, "requested": requested })),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_crate_version_and_build_time() {
+        let info = build_info();
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert!(info.build_timestamp.is_some());
+        assert!(info
+            .supported_protocol_versions
+            .contains(&info.protocol_version));
+    }
+
+    #[test]
+    fn negotiates_supported_and_newer_versions_only() {
+        let negotiated = |requested| negotiate_protocol_version(requested).map(|v| v.to_string());
+        assert_eq!(negotiated("2024-11-05").unwrap(), "2024-11-05");
+        assert_eq!(negotiated("2025-03-26").unwrap(), "2025-03-26");
+        assert_eq!(negotiated("2099-01-01").unwrap(), "2025-03-26");
+        for unsupported in ["2024-10-07", "2025-01-01", "latest", ""] {
+            let error = negotiated(unsupported).unwrap_err();
+            assert_eq!(error.code.0, -32602, "{unsupported}");
+            assert!(
+                error.message.contains("2024-11-05, 2025-03-26"),
+                "{error:?}"
+            );
+        }
+    }
+}
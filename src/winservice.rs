@@ -0,0 +1,139 @@
+//! Windows service registration and the service control manager entry point.
+//!
+//! `service install` registers the binary to start as `service run`, which
+//! hands control to the service dispatcher. A stop request from the service
+//! control manager drains the server like `SIGTERM` does on Unix.
+
+use crate::daemon;
+use std::{ffi::OsString, sync::Mutex, time::Duration};
+use windows_service::{
+    define_windows_service,
+    service::{
+        ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode,
+        ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+    },
+    service_control_handler::{self, ServiceControlHandlerResult},
+    service_dispatcher,
+    service_manager::{ServiceManager, ServiceManagerAccess},
+};
+
+pub const SERVICE_NAME: &str = "inferenco-mcp";
+const DISPLAY_NAME: &str = "Inferenco MCP server";
+const SERVICE_TYPE: ServiceType = ServiceType::OWN_PROCESS;
+
+type Serve = Box<dyn FnOnce() -> Result<(), String> + Send>;
+
+/// The server, handed to the dispatcher thread by [`run`].
+static SERVE: Mutex<Option<Serve>> = Mutex::new(None);
+
+/// Whether this process was started by the service control manager, which
+/// runs services from the system directory rather than the install location.
+pub fn is_service_invocation() -> bool {
+    let args: Vec<OsString> = std::env::args_os().skip(1).take(2).collect();
+    args == ["service", "run"]
+}
+
+/// Register this binary to start automatically as `service run <arguments>`.
+pub fn install(arguments: Vec<OsString>) -> Result<(), String> {
+    let manager = ServiceManager::local_computer(
+        None::<&str>,
+        ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+    )
+    .map_err(|error| format!("cannot connect to the service manager: {error}"))?;
+    let mut launch_arguments = vec![OsString::from("service"), OsString::from("run")];
+    launch_arguments.extend(arguments);
+    let info = ServiceInfo {
+        name: OsString::from(SERVICE_NAME),
+        display_name: OsString::from(DISPLAY_NAME),
+        service_type: SERVICE_TYPE,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path: std::env::current_exe()
+            .map_err(|error| format!("cannot locate the executable: {error}"))?,
+        launch_arguments,
+        dependencies: vec![],
+        account_name: None,
+        account_password: None,
+    };
+    let service = manager
+        .create_service(&info, ServiceAccess::CHANGE_CONFIG)
+        .map_err(|error| format!("cannot register {SERVICE_NAME}: {error}"))?;
+    service
+        .set_description("Model Context Protocol server exposing Inferenco tools over HTTP")
+        .map_err(|error| format!("cannot describe {SERVICE_NAME}: {error}"))
+}
+
+/// Stop the service if it is running and remove its registration.
+pub fn uninstall() -> Result<(), String> {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+        .map_err(|error| format!("cannot connect to the service manager: {error}"))?;
+    let service = manager
+        .open_service(
+            SERVICE_NAME,
+            ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE,
+        )
+        .map_err(|error| format!("cannot open {SERVICE_NAME}: {error}"))?;
+    service
+        .delete()
+        .map_err(|error| format!("cannot remove {SERVICE_NAME}: {error}"))?;
+    let running = service
+        .query_status()
+        .is_ok_and(|status| status.current_state != ServiceState::Stopped);
+    if running {
+        service
+            .stop()
+            .map_err(|error| format!("cannot stop {SERVICE_NAME}: {error}"))?;
+    }
+    Ok(())
+}
+
+/// Hand control to the service dispatcher, which calls `serve` on its own
+/// thread and blocks until the service stops.
+pub fn run(serve: Serve) -> Result<(), String> {
+    *SERVE.lock().expect("service lock poisoned") = Some(serve);
+    service_dispatcher::start(SERVICE_NAME, ffi_service_main)
+        .map_err(|error| format!("not started by the service manager: {error}"))
+}
+
+define_windows_service!(ffi_service_main, service_main);
+
+fn service_main(_arguments: Vec<OsString>) {
+    let handler = |control| match control {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            daemon::request_stop();
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    };
+    let Ok(status) = service_control_handler::register(SERVICE_NAME, handler) else {
+        return;
+    };
+    let report = |state: ServiceState, exit_code: u32| {
+        let _ = status.set_service_status(ServiceStatus {
+            service_type: SERVICE_TYPE,
+            current_state: state,
+            controls_accepted: match state {
+                ServiceState::Running => {
+                    ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN
+                }
+                _ => ServiceControlAccept::empty(),
+            },
+            exit_code: ServiceExitCode::Win32(exit_code),
+            checkpoint: 0,
+            wait_hint: Duration::default(),
+            process_id: None,
+        });
+    };
+
+    report(ServiceState::Running, 0);
+    let serve = SERVE.lock().expect("service lock poisoned").take();
+    let outcome = match serve {
+        Some(serve) => serve(),
+        None => Err("service started twice".to_string()),
+    };
+    if let Err(error) = &outcome {
+        tracing::error!(%error, "service stopped");
+    }
+    report(ServiceState::Stopped, u32::from(outcome.is_err()));
+}
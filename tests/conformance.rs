@@ -0,0 +1,564 @@
+//! MCP protocol conformance: the same lifecycle, error codes, pagination,
+//! notification and capability checks run against every transport.
+
+mod support;
+
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use support::{run_once, StdioServer, TestServer};
+
+/// The newest protocol version the server speaks.
+const LATEST: &str = "2025-03-26";
+
+#[derive(Debug, Clone, Copy)]
+enum Transport {
+    Http,
+    Stdio,
+    Tcp,
+}
+
+const TRANSPORTS: [Transport; 3] = [Transport::Http, Transport::Stdio, Transport::Tcp];
+
+/// A connection to a fresh server over one transport. TCP is framed like
+/// stdio, so it is driven the same way.
+enum Conn {
+    Http(TestServer),
+    Stdio(StdioServer),
+}
+
+impl Conn {
+    fn open(transport: Transport) -> Self {
+        match transport {
+            Transport::Http => Conn::Http(TestServer::start(&[])),
+            Transport::Stdio => Conn::Stdio(StdioServer::start()),
+            Transport::Tcp => Conn::Stdio(StdioServer::start_tcp(&[])),
+        }
+    }
+
+    /// Open a connection and complete the initialize handshake.
+    async fn ready(transport: Transport) -> Self {
+        let mut conn = Conn::open(transport);
+        let init = conn.request("initialize", initialize_params(LATEST)).await;
+        assert!(init["result"].is_object(), "{transport:?}: {init}");
+        conn.notify("notifications/initialized").await;
+        conn
+    }
+
+    async fn request(&mut self, method: &str, params: Value) -> Value {
+        match self {
+            Conn::Http(server) => server.call(method, params).await,
+            Conn::Stdio(server) => server.request(method, params),
+        }
+    }
+
+    /// Send a notification. Over HTTP it must be accepted with no body;
+    /// over stdio, a stray reply fails the next request.
+    async fn notify(&mut self, method: &str) {
+        match self {
+            Conn::Http(server) => {
+                let body = json!({ "jsonrpc": "2.0", "method": method }).to_string();
+                let (status, reply) = server.rpc_with(server.post("/rpc"), &body).await;
+                assert_eq!(status, StatusCode::ACCEPTED, "{reply}");
+                assert!(reply.is_null(), "{reply}");
+            }
+            Conn::Stdio(server) => server.notify(method),
+        }
+    }
+}
+
+fn initialize_params(version: &str) -> Value {
+    json!({
+        "protocolVersion": version,
+        "capabilities": {},
+        "clientInfo": { "name": "conformance", "version": "0" }
+    })
+}
+
+fn error_code(reply: &Value) -> i64 {
+    reply["error"]["code"]
+        .as_i64()
+        .unwrap_or_else(|| panic!("not an error: {reply}"))
+}
+
+#[tokio::test]
+async fn initialize_negotiates_version_and_advertises_capabilities() {
+    for transport in TRANSPORTS {
+        // A supported version is kept; one newer than ours falls back to ours.
+        for (requested, expected) in [("2024-11-05", "2024-11-05"), ("2099-01-01", LATEST)] {
+            let mut conn = Conn::open(transport);
+            let init = conn
+                .request("initialize", initialize_params(requested))
+                .await;
+            let result = &init["result"];
+            assert_eq!(result["protocolVersion"], expected, "{transport:?}: {init}");
+            assert!(result["serverInfo"]["name"].is_string(), "{init}");
+            assert!(result["serverInfo"]["version"].is_string(), "{init}");
+            assert_eq!(
+                result["capabilities"]["tools"]["listChanged"], true,
+                "{transport:?}: {init}"
+            );
+            assert_eq!(
+                result["capabilities"]["resources"]["subscribe"], true,
+                "{init}"
+            );
+            assert!(result["capabilities"]["prompts"].is_object(), "{init}");
+            assert!(
+                result["capabilities"]["logging"].is_object(),
+                "{transport:?}: {init}"
+            );
+            assert!(
+                result["capabilities"]["completions"].is_object(),
+                "{transport:?}: {init}"
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn initialize_rejects_unsupported_versions() {
+    for transport in TRANSPORTS {
+        for requested in ["2024-10-07", "draft"] {
+            let mut conn = Conn::open(transport);
+            let init = conn
+                .request("initialize", initialize_params(requested))
+                .await;
+            assert_eq!(error_code(&init), -32602, "{transport:?}: {init}");
+            assert_eq!(init["error"]["data"]["requested"], requested, "{init}");
+            assert_eq!(
+                init["error"]["data"]["supported"],
+                json!(["2024-11-05", LATEST]),
+                "{transport:?}: {init}"
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn notifications_get_no_reply_and_ping_answers_empty() {
+    for transport in TRANSPORTS {
+        let mut conn = Conn::ready(transport).await;
+        conn.notify("notifications/cancelled").await;
+        conn.notify("notifications/unknown").await;
+        let pong = conn.request("ping", json!({})).await;
+        assert_eq!(pong["result"], json!({}), "{transport:?}: {pong}");
+    }
+}
+
+#[tokio::test]
+async fn errors_use_json_rpc_codes() {
+    for transport in TRANSPORTS {
+        let mut conn = Conn::ready(transport).await;
+        let cases = [
+            ("tools/call", json!({ "name": "no_such_tool" }), -32602),
+            (
+                "resources/read",
+                json!({ "uri": "schedule://no-such-job" }),
+                -32002,
+            ),
+            ("prompts/get", json!({ "name": "summary" }), -32602),
+            (
+                "prompts/get",
+                json!({ "name": "explain_move_module", "arguments": {} }),
+                -32602,
+            ),
+            (
+                "completion/complete",
+                json!({
+                    "ref": { "type": "ref/prompt", "name": "summary" },
+                    "argument": { "name": "topic", "value": "" }
+                }),
+                -32602,
+            ),
+        ];
+        for (method, params, code) in cases {
+            let reply = conn.request(method, params).await;
+            assert_eq!(error_code(&reply), code, "{transport:?} {method}: {reply}");
+        }
+    }
+}
+
+#[tokio::test]
+async fn prompt_arguments_complete_on_every_transport() {
+    for transport in TRANSPORTS {
+        let mut conn = Conn::ready(transport).await;
+        let reply = conn
+            .request(
+                "completion/complete",
+                json!({
+                    "ref": { "type": "ref/prompt", "name": "explain_move_module" },
+                    "argument": { "name": "module", "value": "0x1" }
+                }),
+            )
+            .await;
+        assert_eq!(
+            reply["result"]["completion"],
+            json!({ "values": [], "total": 0, "hasMore": false }),
+            "{transport:?}: {reply}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn lists_fit_on_one_page() {
+    for transport in TRANSPORTS {
+        let mut conn = Conn::ready(transport).await;
+        let tools = conn.request("tools/list", json!({})).await;
+        assert!(
+            !tools["result"]["tools"]
+                .as_array()
+                .expect("tools")
+                .is_empty(),
+            "{tools}"
+        );
+        let resources = conn.request("resources/list", json!({})).await;
+        assert!(resources["result"]["resources"].is_array(), "{resources}");
+        let templates = conn.request("resources/templates/list", json!({})).await;
+        assert_eq!(
+            templates["result"]["resourceTemplates"],
+            json!([]),
+            "{templates}"
+        );
+        let prompts = conn.request("prompts/list", json!({})).await;
+        assert_eq!(
+            prompts["result"]["prompts"][0]["name"], "explain_move_module",
+            "{transport:?}: {prompts}"
+        );
+        for reply in [&tools, &resources, &templates, &prompts] {
+            assert!(
+                reply["result"].get("nextCursor").is_none(),
+                "{transport:?}: {reply}"
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn prompts_render_the_same_on_every_transport() {
+    let mut messages = Vec::new();
+    for transport in TRANSPORTS {
+        let mut conn = Conn::ready(transport).await;
+        let prompt = conn
+            .request(
+                "prompts/get",
+                json!({ "name": "explain_move_module", "arguments": { "module": "0x1::coin" } }),
+            )
+            .await;
+        assert_eq!(
+            prompt["result"]["messages"][0]["role"], "user",
+            "{transport:?}: {prompt}"
+        );
+        messages.push(prompt["result"]["messages"].clone());
+    }
+    assert!(
+        messages.windows(2).all(|pair| pair[0] == pair[1]),
+        "{messages:?}"
+    );
+}
+
+#[tokio::test]
+async fn structured_results_match_on_every_transport() {
+    let mut results = Vec::new();
+    for transport in TRANSPORTS {
+        let mut conn = Conn::ready(transport).await;
+        let tools = conn.request("tools/list", json!({})).await;
+        let roll_dice = tools["result"]["tools"]
+            .as_array()
+            .expect("tools")
+            .iter()
+            .find(|tool| tool["name"] == "roll_dice")
+            .expect("roll_dice is listed");
+        assert_eq!(
+            roll_dice["outputSchema"]["type"], "object",
+            "{transport:?}: {roll_dice}"
+        );
+
+        let reply = conn
+            .request(
+                "tools/call",
+                json!({ "name": "roll_dice", "arguments": { "notation": "2d6", "seed": 7 } }),
+            )
+            .await;
+        let result = &reply["result"];
+        let text: Value = serde_json::from_str(result["content"][0]["text"].as_str().unwrap())
+            .expect("text is the JSON result");
+        assert_eq!(result["structuredContent"], text, "{transport:?}: {reply}");
+        results.push((
+            result["content"].clone(),
+            result["structuredContent"].clone(),
+        ));
+    }
+    assert!(
+        results.windows(2).all(|pair| pair[0] == pair[1]),
+        "{results:?}"
+    );
+}
+
+#[tokio::test]
+async fn cancelled_requests_are_stopped_on_every_transport() {
+    // Connections to this port are never accepted, so docs reads hang.
+    let silent = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let docs_url = format!("http://{}/", silent.local_addr().unwrap());
+    let env = [
+        ("INFERENCO_MCP_DOCS_URL", docs_url.as_str()),
+        ("INFERENCO_MCP_FETCH_TIMEOUT_MS", "60000"),
+    ];
+    let read = |id: u64| {
+        json!({
+            "jsonrpc": "2.0", "id": id, "method": "resources/read",
+            "params": { "uri": "cedra-docs://slow" }
+        })
+    };
+    let cancel = |id: u64| {
+        json!({
+            "jsonrpc": "2.0", "method": "notifications/cancelled",
+            "params": { "requestId": id, "reason": "changed my mind" }
+        })
+    };
+
+    let server = TestServer::start(&env);
+    let init = server
+        .post("/mcp")
+        .header("content-type", "application/json")
+        .body(
+            json!({
+                "jsonrpc": "2.0", "id": 1, "method": "initialize",
+                "params": initialize_params(LATEST)
+            })
+            .to_string(),
+        )
+        .send()
+        .await
+        .expect("server answers");
+    let session = init.headers()["mcp-session-id"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let in_session = || server.post("/mcp").header("mcp-session-id", &session);
+    let (slow, stop) = (read(7).to_string(), cancel(7).to_string());
+    let ((_, reply), (status, _)) = tokio::join!(server.rpc_with(in_session(), &slow), async {
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+        server.rpc_with(in_session(), &stop).await
+    });
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(error_code(&reply), -32800, "{reply}");
+
+    for mut server in [StdioServer::start_with(&env), StdioServer::start_tcp(&env)] {
+        server.request("initialize", initialize_params(LATEST));
+        server.notify("notifications/initialized");
+        server.send(&read(70));
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        server.send(&cancel(70));
+        let reply = server.reply(&json!(70));
+        assert_eq!(error_code(&reply), -32800, "{reply}");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn tools_sample_the_model_of_clients_that_offer_it() {
+    let docs = axum::Router::new().route(
+        "/docs/move",
+        axum::routing::get(|| async { axum::response::Html("<p>Modules hold code.</p>") }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let docs_url = format!("http://{}/docs/", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, docs).await });
+    let env = [("INFERENCO_MCP_DOCS_URL", docs_url.as_str())];
+    let summarize = json!({ "name": "summarize_docs", "arguments": { "page": "move" } });
+
+    for mut server in [StdioServer::start_with(&env), StdioServer::start_tcp(&env)] {
+        let mut params = initialize_params(LATEST);
+        params["capabilities"]["sampling"] = json!({});
+        server.request("initialize", params);
+        server.notify("notifications/initialized");
+        server.send(&json!({
+            "jsonrpc": "2.0", "id": 9, "method": "tools/call", "params": summarize
+        }));
+        let request = server.next_message();
+        assert_eq!(request["method"], "sampling/createMessage", "{request}");
+        let prompt = request["params"]["messages"][0]["content"]["text"]
+            .as_str()
+            .unwrap_or_default();
+        assert!(prompt.contains("Modules hold code."), "{request}");
+        server.send(&json!({
+            "jsonrpc": "2.0", "id": request["id"], "result": {
+                "model": "test-model", "role": "assistant",
+                "content": { "type": "text", "text": "Move code lives in modules." }
+            }
+        }));
+        let reply = server.reply(&json!(9));
+        assert_eq!(
+            reply["result"]["content"][0]["text"], "Move code lives in modules.",
+            "{reply}"
+        );
+    }
+
+    // Neither a client without sampling nor one over HTTP can be asked.
+    let mut server = StdioServer::start_with(&env);
+    server.request("initialize", initialize_params(LATEST));
+    server.notify("notifications/initialized");
+    let refused = server.request("tools/call", summarize.clone());
+    assert_eq!(error_code(&refused), -32600, "{refused}");
+    let server = TestServer::start(&env);
+    let refused = server.call("tools/call", summarize).await;
+    assert_eq!(error_code(&refused), -32600, "{refused}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn long_tool_calls_report_progress_to_callers_that_ask() {
+    let docs = axum::Router::new().route(
+        "/docs/move",
+        axum::routing::get(|| async { axum::response::Html("<p>Modules hold code.</p>") }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let docs_url = format!("http://{}/docs/", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, docs).await });
+
+    let mut server = StdioServer::start_with(&[("INFERENCO_MCP_DOCS_URL", docs_url.as_str())]);
+    let mut params = initialize_params(LATEST);
+    params["capabilities"]["sampling"] = json!({});
+    server.request("initialize", params);
+    server.notify("notifications/initialized");
+    server.send(&json!({
+        "jsonrpc": "2.0", "id": 9, "method": "tools/call", "params": {
+            "name": "summarize_docs", "arguments": { "page": "move" },
+            "_meta": { "progressToken": "summary" }
+        }
+    }));
+    let mut progress = Vec::new();
+    let request = loop {
+        let message = server.next_message();
+        if message["method"] == "notifications/progress" {
+            assert_eq!(message["params"]["progressToken"], "summary", "{message}");
+            progress.push(message["params"]["message"].clone());
+        } else {
+            break message;
+        }
+    };
+    assert_eq!(request["method"], "sampling/createMessage", "{request}");
+    assert_eq!(progress[0], "reading cedra-docs://move", "{progress:?}");
+    server.send(&json!({
+        "jsonrpc": "2.0", "id": request["id"], "result": {
+            "model": "test-model", "role": "assistant",
+            "content": { "type": "text", "text": "Move code lives in modules." }
+        }
+    }));
+    let reply = server.reply(&json!(9));
+    // Reports are not repeated in the result.
+    let content = reply["result"]["content"].as_array().expect("content");
+    assert_eq!(content.len(), 1, "{reply}");
+    assert_eq!(content[0]["text"], "Move code lives in modules.", "{reply}");
+}
+
+#[test]
+fn once_answers_requests_but_not_notifications() {
+    let call = r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"echo","arguments":{"message":"hi"}}}"#;
+    let (code, output) = run_once(call);
+    assert_eq!(code, 0, "{output}");
+    let reply: Value = serde_json::from_str(&output).expect("one JSON response");
+    assert_eq!(reply["id"], 1);
+    assert_eq!(reply["result"]["content"][0]["text"], "hi", "{reply}");
+
+    let (code, output) = run_once(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#);
+    assert_eq!((code, output.as_str()), (0, ""));
+
+    // A null id is a request like any other.
+    let (code, output) = run_once(r#"{"jsonrpc":"2.0","id":null,"method":"ping"}"#);
+    assert_eq!(code, 0, "{output}");
+    let reply: Value = serde_json::from_str(&output).expect("one JSON response");
+    assert_eq!(reply, json!({ "jsonrpc": "2.0", "id": null, "result": {} }));
+
+    for (input, code) in [
+        ("{not json", -32700),
+        (r#"{"id":1}"#, -32600),
+        ("[]", -32600),
+    ] {
+        let (status, output) = run_once(input);
+        assert_eq!(status, 1, "{input}: {output}");
+        let reply: Value = serde_json::from_str(&output).expect("one JSON response");
+        assert_eq!(error_code(&reply), code, "{input}: {reply}");
+    }
+
+    let batch = r#"[{"jsonrpc":"2.0","id":1,"method":"ping"},{"jsonrpc":"2.0","method":"notifications/initialized"},{"jsonrpc":"2.0","id":2,"method":"no/such/method"}]"#;
+    let (code, output) = run_once(batch);
+    assert_eq!(code, 1, "{output}");
+    let replies: Value = serde_json::from_str(&output).expect("a JSON array");
+    assert_eq!(replies[0]["id"], 1, "{replies}");
+    assert_eq!(error_code(&replies[1]), -32601, "{replies}");
+    assert_eq!(replies.as_array().map(Vec::len), Some(2));
+}
+
+#[test]
+fn tcp_connections_keep_their_own_key_value_state() {
+    let mut first = StdioServer::start_tcp(&[]);
+    let mut second = first.connect_again();
+    for server in [&mut first, &mut second] {
+        server.request("initialize", initialize_params(LATEST));
+        server.notify("notifications/initialized");
+    }
+    let set = json!({ "name": "kv_set", "arguments": { "key": "k", "value": "v" } });
+    let reply = first.request("tools/call", set);
+    assert!(reply["result"].is_object(), "{reply}");
+
+    let get = json!({ "name": "kv_get", "arguments": { "key": "k" } });
+    let found = |reply: &Value| {
+        let text = reply["result"]["content"][0]["text"]
+            .as_str()
+            .unwrap_or("{}");
+        serde_json::from_str::<Value>(text).expect("JSON lookup")["found"] == true
+    };
+    assert!(found(&first.request("tools/call", get.clone())));
+    assert!(!found(&second.request("tools/call", get)));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn clients_that_declare_roots_are_asked_for_them() {
+    for mut server in [StdioServer::start(), StdioServer::start_tcp(&[])] {
+        let mut params = initialize_params(LATEST);
+        params["capabilities"]["roots"] = json!({ "listChanged": true });
+        server.request("initialize", params);
+        server.notify("notifications/initialized");
+        for (n, uri) in ["file:///srv/app", "file:///srv/other"]
+            .into_iter()
+            .enumerate()
+        {
+            let request = server.next_message();
+            assert_eq!(request["method"], "roots/list", "{request}");
+            server.send(&json!({
+                "jsonrpc": "2.0", "id": request["id"],
+                "result": { "roots": [{ "uri": uri, "name": "app" }] }
+            }));
+            if n == 0 {
+                server.notify("notifications/roots/list_changed");
+            }
+        }
+        // Still answering after the exchange.
+        let reply = server.request("tools/list", json!({}));
+        assert!(reply["result"]["tools"].is_array(), "{reply}");
+    }
+}
+
+/// Stdio and TCP leave these to the SDK, which drops unknown methods and
+/// closes the session on unparseable input, so they are only checked over
+/// HTTP.
+#[tokio::test]
+async fn http_rejects_unknown_methods_and_malformed_bodies() {
+    let server = TestServer::start(&[]);
+    let unknown = server.call("no/such/method", json!({})).await;
+    assert_eq!(error_code(&unknown), -32601, "{unknown}");
+
+    for (body, code) in [
+        ("not json", -32700),
+        (r#"{"id":3}"#, -32600),
+        ("[]", -32600),
+    ] {
+        let (status, reply) = server.rpc_with(server.post("/rpc"), body).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+        assert_eq!(error_code(&reply), code, "{body}: {reply}");
+        assert!(reply["id"].is_null(), "{reply}");
+    }
+
+    let notifications = r#"[{"jsonrpc":"2.0","method":"notifications/initialized"}]"#;
+    let (status, reply) = server.rpc_with(server.post("/rpc"), notifications).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert!(reply.is_null(), "{reply}");
+}
@@ -0,0 +1,1308 @@
+//! End-to-end tests of the HTTP transport against the real binary.
+
+mod support;
+
+use inferenco_mcp::client::InferencoClient;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use std::time::Duration;
+use support::TestServer;
+
+const ADMIN_TOKEN: &str = "admin-secret";
+
+fn text(reply: &Value) -> &str {
+    reply["result"]["content"][0]["text"]
+        .as_str()
+        .unwrap_or_else(|| panic!("no text content in {reply}"))
+}
+
+#[tokio::test]
+async fn initialize_list_and_call_tools_over_rpc() {
+    let server = TestServer::start(&[]);
+
+    let init = server.call("initialize", json!({})).await;
+    assert!(init["result"]["protocolVersion"].is_string(), "{init}");
+    assert!(
+        init["result"]["capabilities"]["tools"].is_object(),
+        "{init}"
+    );
+
+    let list = server.call("tools/list", json!({})).await;
+    let tools: Vec<&str> = list["result"]["tools"]
+        .as_array()
+        .expect("tool list")
+        .iter()
+        .filter_map(|tool| tool["name"].as_str())
+        .collect();
+    assert!(
+        tools.contains(&"echo") && tools.contains(&"kv_set"),
+        "{tools:?}"
+    );
+
+    let echo = server
+        .call(
+            "tools/call",
+            json!({ "name": "echo", "arguments": { "message": "over http" } }),
+        )
+        .await;
+    assert_eq!(text(&echo), "over http");
+
+    let missing = server
+        .call(
+            "tools/call",
+            json!({ "name": "no_such_tool", "arguments": {} }),
+        )
+        .await;
+    assert!(missing["error"]["code"].is_i64(), "{missing}");
+
+    let batch = json!([
+        { "jsonrpc": "2.0", "id": "a", "method": "tools/call",
+          "params": { "name": "reverse_text", "arguments": { "text": "abc" } } },
+        { "jsonrpc": "2.0", "method": "notifications/initialized" },
+        { "jsonrpc": "2.0", "id": "b", "method": "tools/list" },
+    ]);
+    let (status, replies) = server
+        .rpc_with(server.post("/rpc"), &batch.to_string())
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let ids: Vec<&Value> = replies
+        .as_array()
+        .expect("batch reply")
+        .iter()
+        .map(|reply| &reply["id"])
+        .collect();
+    assert_eq!(ids, [&json!("a"), &json!("b")]);
+    assert_eq!(text(&replies[0]), "cba");
+}
+
+#[tokio::test]
+async fn rejects_missing_credentials_and_malformed_bodies() {
+    let server = TestServer::start(&[
+        ("INFERENCO_MCP_AUTH_ENABLED", "true"),
+        ("INFERENCO_MCP_API_KEYS", "good-key"),
+    ]);
+    let list = r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#;
+
+    let (status, _) = server.rpc_with(server.post("/rpc"), list).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let wrong = server.post("/rpc").header("x-api-key", "bad-key");
+    assert_eq!(
+        server.rpc_with(wrong, list).await.0,
+        StatusCode::UNAUTHORIZED
+    );
+    let authorized = || server.post("/rpc").header("x-api-key", "good-key");
+    let (status, reply) = server.rpc_with(authorized(), list).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(reply["result"]["tools"].is_array(), "{reply}");
+
+    for body in ["not json", r#"{"id":3}"#, "[]"] {
+        let (status, _) = server.rpc_with(authorized(), body).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    }
+
+    let events = server.get("/sse").send().await.expect("server answers");
+    let first = first_event(events).await;
+    assert_eq!(first["error"]["message"], "Authentication required");
+}
+
+#[tokio::test]
+async fn bearer_clients_are_pointed_at_the_oauth_metadata() {
+    let server = TestServer::start(&[
+        ("INFERENCO_MCP_AUTH_ENABLED", "true"),
+        ("INFERENCO_MCP_JWT_ISSUER", "https://id.example.com/"),
+        // Never fetched successfully; no token here is valid.
+        ("INFERENCO_MCP_JWT_JWKS_URL", "http://127.0.0.1:9/jwks.json"),
+        (
+            "INFERENCO_MCP_OAUTH_RESOURCE",
+            "https://mcp.example.com/mcp",
+        ),
+        ("INFERENCO_MCP_OAUTH_SCOPES", "mcp:tools"),
+    ]);
+    let metadata_url = "https://mcp.example.com/.well-known/oauth-protected-resource/mcp";
+    for path in [
+        "/.well-known/oauth-protected-resource",
+        "/.well-known/oauth-protected-resource/mcp",
+    ] {
+        let metadata: Value = server
+            .get(path)
+            .send()
+            .await
+            .expect("server answers")
+            .json()
+            .await
+            .expect("metadata is JSON");
+        assert_eq!(metadata["resource"], "https://mcp.example.com/mcp");
+        assert_eq!(
+            metadata["authorization_servers"][0],
+            "https://id.example.com/"
+        );
+        assert_eq!(metadata["scopes_supported"][0], "mcp:tools");
+    }
+
+    let list = r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#;
+    let challenge = |response: &reqwest::Response| {
+        response.headers()[reqwest::header::WWW_AUTHENTICATE]
+            .to_str()
+            .expect("ASCII header")
+            .to_string()
+    };
+    let anonymous = server
+        .post("/rpc")
+        .body(list)
+        .send()
+        .await
+        .expect("answers");
+    assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        challenge(&anonymous),
+        format!(r#"Bearer resource_metadata="{metadata_url}""#)
+    );
+    let forged = server
+        .post("/rpc")
+        .bearer_auth("eyJhbGciOiJFUzI1NiJ9.eyJzdWIiOiJ4In0.c2ln")
+        .body(list)
+        .send()
+        .await
+        .expect("answers");
+    assert_eq!(forged.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        challenge(&forged),
+        format!(r#"Bearer error="invalid_token", resource_metadata="{metadata_url}""#)
+    );
+
+    let plain = TestServer::start(&[]);
+    let missing = plain
+        .get("/.well-known/oauth-protected-resource")
+        .send()
+        .await
+        .expect("answers");
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn responses_are_compressed_and_large_bodies_refused() {
+    let server = TestServer::start(&[("INFERENCO_MCP_MAX_BODY_BYTES", "1024")]);
+    let list = r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#;
+    let encoding = |response: &reqwest::Response| {
+        response
+            .headers()
+            .get("content-encoding")
+            .map(|value| value.to_str().unwrap().to_string())
+    };
+
+    let gzipped = server
+        .post("/rpc")
+        .header("content-type", "application/json")
+        .header("accept-encoding", "gzip")
+        .body(list)
+        .send()
+        .await
+        .expect("server answers");
+    assert_eq!(gzipped.status(), StatusCode::OK);
+    assert_eq!(encoding(&gzipped).as_deref(), Some("gzip"));
+    let plain = server
+        .post("/rpc")
+        .header("content-type", "application/json")
+        .body(list)
+        .send()
+        .await
+        .expect("server answers");
+    assert_eq!(encoding(&plain), None);
+    // Compressing would hold events back until the buffer fills.
+    let events = server
+        .get("/sse")
+        .header("accept-encoding", "gzip, br")
+        .send()
+        .await
+        .expect("server answers");
+    assert_eq!(encoding(&events), None);
+
+    let echo = json!({
+        "jsonrpc": "2.0", "id": 2, "method": "tools/call",
+        "params": { "name": "echo", "arguments": { "message": "x".repeat(2048) } }
+    });
+    let refused = server
+        .post("/rpc")
+        .header("content-type", "application/json")
+        .body(echo.to_string())
+        .send()
+        .await
+        .expect("server answers");
+    assert_eq!(refused.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn requests_beyond_the_rate_limit_are_told_when_to_retry() {
+    let server = TestServer::start(&[
+        ("INFERENCO_MCP_RATE_LIMIT_KEY", "2/min"),
+        ("INFERENCO_MCP_RATE_LIMIT_IP", "3/min"),
+    ]);
+    let list = r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#;
+    let keyed = |key: &str| server.post("/rpc").header("x-api-key", key.to_string());
+    for _ in 0..2 {
+        assert_eq!(server.rpc_with(keyed("a"), list).await.0, StatusCode::OK);
+    }
+    let limited = keyed("a")
+        .header("content-type", "application/json")
+        .body(list)
+        .send()
+        .await
+        .expect("server answers");
+    assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = limited.headers()[reqwest::header::RETRY_AFTER]
+        .to_str()
+        .expect("ASCII header")
+        .parse()
+        .expect("whole seconds");
+    assert!((1..=30).contains(&retry_after), "{retry_after}");
+    let reply: Value = limited.json().await.expect("JSON-RPC error");
+    assert_eq!(reply["error"]["code"], -32001);
+    assert_eq!(reply["error"]["data"]["limit"], "key");
+
+    // Another key still shares the IP's bucket, of which one request is left.
+    assert_eq!(server.rpc_with(keyed("b"), list).await.0, StatusCode::OK);
+    let (status, reply) = server.rpc_with(keyed("c"), list).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(reply["error"]["data"]["limit"], "ip");
+
+    // Endpoints outside the transports are not limited.
+    let health = server.get("/health").send().await.expect("server answers");
+    assert_eq!(health.status(), StatusCode::OK);
+    let metrics = server.get("/metrics").send().await.expect("server answers");
+    let metrics = metrics.text().await.expect("metrics text");
+    assert!(
+        metrics.contains(r#"inferenco_mcp_requests_rate_limited_total{limit="ip"} 1"#),
+        "{metrics}"
+    );
+}
+
+#[tokio::test]
+async fn client_addresses_are_filtered_after_looking_past_trusted_proxies() {
+    let server = TestServer::start(&[
+        ("INFERENCO_MCP_ALLOWED_IPS", "10.0.0.0/8"),
+        ("INFERENCO_MCP_DENIED_IPS", "10.6.6.6"),
+        ("INFERENCO_MCP_TRUSTED_PROXIES", "127.0.0.1"),
+    ]);
+    let list = r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#;
+    let via_proxy = |chain: &str| {
+        server
+            .post("/rpc")
+            .header("x-forwarded-for", chain.to_string())
+    };
+
+    let (status, reply) = server.rpc_with(server.post("/rpc"), list).await;
+    assert_eq!(
+        status,
+        StatusCode::FORBIDDEN,
+        "the proxy itself is no client"
+    );
+    assert_eq!(reply["error"]["message"], "Client address not allowed");
+    assert_eq!(
+        server.rpc_with(via_proxy("10.1.2.3"), list).await.0,
+        StatusCode::OK
+    );
+    assert_eq!(
+        server.rpc_with(via_proxy("10.6.6.6"), list).await.0,
+        StatusCode::FORBIDDEN
+    );
+    // Only the hop the trusted proxy saw counts, not what the client claims.
+    assert_eq!(
+        server
+            .rpc_with(via_proxy("10.1.2.3, 192.0.2.1"), list)
+            .await
+            .0,
+        StatusCode::FORBIDDEN
+    );
+    // The proxy appends to X-Forwarded-For and passes Forwarded through.
+    let spoofed = via_proxy("192.0.2.1").header("forwarded", "for=10.1.2.3");
+    assert_eq!(
+        server.rpc_with(spoofed, list).await.0,
+        StatusCode::FORBIDDEN,
+        "a client-sent Forwarded header is not believed"
+    );
+
+    let health = server.get("/health").send().await.expect("server answers");
+    assert_eq!(health.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn proxies_that_write_forwarded_are_believed_only_there() {
+    let server = TestServer::start(&[
+        ("INFERENCO_MCP_ALLOWED_IPS", "10.0.0.0/8"),
+        ("INFERENCO_MCP_TRUSTED_PROXIES", "127.0.0.1"),
+        ("INFERENCO_MCP_TRUSTED_PROXY_HEADER", "forwarded"),
+    ]);
+    let list = r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#;
+    let forwarded = server
+        .post("/rpc")
+        .header("forwarded", r#"for="10.1.2.3:4711";proto=https"#);
+    assert_eq!(server.rpc_with(forwarded, list).await.0, StatusCode::OK);
+    let spoofed = server
+        .post("/rpc")
+        .header("forwarded", "for=192.0.2.1")
+        .header("x-forwarded-for", "10.1.2.3");
+    assert_eq!(
+        server.rpc_with(spoofed, list).await.0,
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[tokio::test]
+async fn allowed_origins_pass_cors_preflights_and_read_the_session_id() {
+    const APP: &str = "https://app.example.com";
+    let server = TestServer::start(&[
+        ("INFERENCO_MCP_CORS_ORIGINS", APP),
+        ("INFERENCO_MCP_AUTH_ENABLED", "true"),
+        ("INFERENCO_MCP_API_KEYS", "good-key"),
+    ]);
+    let header = |response: &reqwest::Response, name: &str| {
+        response
+            .headers()
+            .get(name)
+            .map(|value| value.to_str().unwrap().to_ascii_lowercase())
+    };
+
+    // Preflights carry no API key, so they are answered before auth.
+    let preflight = |origin: &str| {
+        server
+            .client()
+            .request(reqwest::Method::OPTIONS, server.url("/mcp"))
+            .header("origin", origin)
+            .header("access-control-request-method", "POST")
+            .header(
+                "access-control-request-headers",
+                "content-type,x-api-key,mcp-session-id",
+            )
+            .send()
+    };
+    let allowed = preflight(APP).await.expect("server answers");
+    assert_eq!(allowed.status(), StatusCode::OK);
+    assert_eq!(
+        header(&allowed, "access-control-allow-origin").as_deref(),
+        Some(APP)
+    );
+    let headers = header(&allowed, "access-control-allow-headers").unwrap_or_default();
+    for name in ["content-type", "x-api-key", "mcp-session-id"] {
+        assert!(headers.contains(name), "{name} not in {headers}");
+    }
+    let other = preflight("https://elsewhere.example.com")
+        .await
+        .expect("server answers");
+    assert_eq!(header(&other, "access-control-allow-origin"), None);
+
+    let init = server
+        .post("/mcp")
+        .header("origin", APP)
+        .header("x-api-key", "good-key")
+        .header("content-type", "application/json")
+        .body(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#)
+        .send()
+        .await
+        .expect("server answers");
+    assert_eq!(init.status(), StatusCode::OK);
+    assert!(header(&init, "mcp-session-id").is_some());
+    let exposed = header(&init, "access-control-expose-headers").unwrap_or_default();
+    assert!(exposed.contains("mcp-session-id"), "{exposed}");
+}
+
+#[tokio::test]
+async fn loopback_servers_refuse_foreign_hosts_and_origins() {
+    let server = TestServer::start(&[
+        ("INFERENCO_MCP_BIND_ADDR", "127.0.0.1"),
+        ("INFERENCO_MCP_CORS_ORIGINS", "http://localhost:5173"),
+    ]);
+    let list_tools = |headers: &[(&str, &str)]| {
+        let mut request = server
+            .post("/rpc")
+            .header("content-type", "application/json")
+            .body(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        request.send()
+    };
+
+    for headers in [
+        &[][..],
+        &[("host", "localhost:8080")],
+        &[("origin", "http://localhost:5173")],
+    ] {
+        let response = list_tools(headers).await.expect("server answers");
+        assert_eq!(response.status(), StatusCode::OK, "{headers:?}");
+    }
+    for headers in [
+        &[("host", "attacker.example.com")][..],
+        &[("origin", "http://attacker.example.com")],
+    ] {
+        let response = list_tools(headers).await.expect("server answers");
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{headers:?}");
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["error"]["code"], -32000);
+    }
+
+    // Probes come from orchestrators addressing the pod by any name.
+    let health = server
+        .get("/health")
+        .header("host", "attacker.example.com")
+        .send()
+        .await
+        .expect("server answers");
+    assert_eq!(health.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn disabling_a_tool_tells_connected_clients() {
+    let server = TestServer::start(&[("INFERENCO_MCP_ADMIN_TOKEN", ADMIN_TOKEN)]);
+    let events = server.get("/sse").send().await.expect("server answers");
+    let (events, endpoint) = next_event(events).await;
+    assert_eq!(endpoint.name, "endpoint");
+    let switch = |name: &str, enabled: bool| {
+        server
+            .client()
+            .put(server.url(&format!("/admin/tools/{name}")))
+            .bearer_auth(ADMIN_TOKEN)
+            .json(&json!({ "enabled": enabled }))
+            .send()
+    };
+    let listed = |list: &Value| {
+        list["result"]["tools"]
+            .as_array()
+            .expect("tool list")
+            .iter()
+            .any(|tool| tool["name"] == "roll_dice")
+    };
+
+    let response = switch("roll_dice", false).await.expect("server answers");
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["changed"], true);
+    let (_events, changed) = next_event(events).await;
+    assert_eq!(changed.json()["method"], "notifications/tools/list_changed");
+
+    assert!(!listed(&server.call("tools/list", json!({})).await));
+    let refused = server
+        .call(
+            "tools/call",
+            json!({ "name": "roll_dice", "arguments": {} }),
+        )
+        .await;
+    assert_eq!(refused["error"]["code"], -32602, "{refused}");
+    let admin: Value = server
+        .get("/admin/tools")
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .expect("server answers")
+        .json()
+        .await
+        .unwrap();
+    let roll_dice = admin["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|tool| tool["name"] == "roll_dice")
+        .expect("disabled tools stay in the admin list");
+    assert_eq!(roll_dice["enabled"], false);
+
+    switch("roll_dice", true).await.expect("server answers");
+    assert!(listed(&server.call("tools/list", json!({})).await));
+    let missing = switch("no_such_tool", false).await.expect("server answers");
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn issued_keys_are_scoped_and_can_be_revoked() {
+    let server = TestServer::start(&[
+        ("INFERENCO_MCP_AUTH_ENABLED", "true"),
+        ("INFERENCO_MCP_KEYS_FILE", "keys.json"),
+        ("INFERENCO_MCP_ADMIN_TOKEN", ADMIN_TOKEN),
+    ]);
+    let response = server
+        .post("/admin/keys")
+        .bearer_auth(ADMIN_TOKEN)
+        .json(&json!({ "name": "ci", "scopes": ["echo"] }))
+        .send()
+        .await
+        .expect("server answers");
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let created: Value = response.json().await.unwrap();
+    let secret = created["key"].as_str().expect("the secret is shown once");
+    let id = created["id"].as_str().expect("key id");
+    let call = |tool: &str| {
+        server.rpc_with(
+            server.post("/rpc").header("x-api-key", secret),
+            &json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "tools/call",
+                "params": { "name": tool, "arguments": { "message": "hi" } }
+            })
+            .to_string(),
+        )
+    };
+
+    let (status, echoed) = call("echo").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(echoed["result"].is_object(), "{echoed}");
+    let (_, refused) = call("roll_dice").await;
+    assert_eq!(refused["error"]["code"], -32002, "{refused}");
+
+    let listed: Value = server
+        .get("/admin/keys")
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .expect("server answers")
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(listed["keys"][0]["status"], "active");
+    assert!(listed["keys"][0].get("sha256").is_none());
+
+    let revoked = server
+        .client()
+        .delete(server.url(&format!("/admin/keys/{id}")))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .expect("server answers");
+    assert_eq!(revoked.status(), StatusCode::OK);
+    let (status, _) = call("echo").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn the_access_policy_limits_tools_to_their_groups() {
+    let dir = tempfile::tempdir().expect("temp dir");
+    let policy = dir.path().join("policy.json");
+    let admin = format!("key:{}", inferenco_mcp::auth::fingerprint("admin-key"));
+    let policy_json = json!({
+        "groups": { "admins": [admin] },
+        "tools": { "roll_dice": ["admins"] }
+    });
+    std::fs::write(&policy, policy_json.to_string()).unwrap();
+    let server = TestServer::start(&[
+        ("INFERENCO_MCP_AUTH_ENABLED", "true"),
+        ("INFERENCO_MCP_API_KEYS", "admin-key,reader-key"),
+        ("INFERENCO_MCP_POLICY_FILE", policy.to_str().unwrap()),
+    ]);
+    let rpc = |key: &str, method: &str, params: Value| {
+        let request = server.post("/rpc").header("x-api-key", key);
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let server = &server;
+        async move { server.rpc_with(request, &body.to_string()).await }
+    };
+    let lists_dice = |listed: &Value| {
+        listed["result"]["tools"]
+            .as_array()
+            .expect("tool list")
+            .iter()
+            .any(|tool| tool["name"] == "roll_dice")
+    };
+    let roll = json!({ "name": "roll_dice", "arguments": { "notation": "1d6" } });
+
+    let (_, listed) = rpc("admin-key", "tools/list", json!({})).await;
+    assert!(lists_dice(&listed), "{listed}");
+    let (_, rolled) = rpc("admin-key", "tools/call", roll.clone()).await;
+    assert!(rolled["result"].is_object(), "{rolled}");
+
+    let (_, listed) = rpc("reader-key", "tools/list", json!({})).await;
+    assert!(!lists_dice(&listed), "{listed}");
+    let (_, refused) = rpc("reader-key", "tools/call", roll).await;
+    assert_eq!(refused["error"]["code"], -32002, "{refused}");
+    let echo = json!({ "name": "echo", "arguments": { "message": "hi" } });
+    let (_, echoed) = rpc("reader-key", "tools/call", echo).await;
+    assert!(
+        echoed["result"].is_object(),
+        "unlisted tools stay open: {echoed}"
+    );
+}
+
+#[tokio::test]
+async fn tool_calls_are_audited_with_their_caller_and_session() {
+    let dir = tempfile::tempdir().expect("temp dir");
+    let audit = dir.path().join("audit.jsonl");
+    let server = TestServer::start(&[
+        ("INFERENCO_MCP_AUTH_ENABLED", "true"),
+        ("INFERENCO_MCP_API_KEYS", "good-key"),
+        ("INFERENCO_MCP_AUDIT_PATH", audit.to_str().unwrap()),
+    ]);
+    let response = server
+        .post("/mcp")
+        .header("x-api-key", "good-key")
+        .header("content-type", "application/json")
+        .body(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#)
+        .send()
+        .await
+        .expect("server answers");
+    let session = response.headers()["mcp-session-id"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    for (tool, arguments) in [
+        ("echo", json!({ "message": "hi" })),
+        ("no_such_tool", json!({ "password": "hunter2" })),
+    ] {
+        let call = json!({
+            "jsonrpc": "2.0", "id": 2, "method": "tools/call",
+            "params": { "name": tool, "arguments": arguments }
+        });
+        server
+            .rpc_with(
+                server
+                    .post("/mcp")
+                    .header("x-api-key", "good-key")
+                    .header("mcp-session-id", &session),
+                &call.to_string(),
+            )
+            .await;
+    }
+
+    let records: Vec<Value> = std::fs::read_to_string(&audit)
+        .expect("audit log written")
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("JSON record"))
+        .collect();
+    assert_eq!(records.len(), 2, "{records:?}");
+    assert_eq!(records[0]["tool"], "echo");
+    assert_eq!(records[0]["outcome"], "success");
+    assert_eq!(records[0]["session"], session.as_str());
+    assert_eq!(
+        records[0]["subject"],
+        inferenco_mcp::auth::fingerprint("good-key")
+    );
+    assert_eq!(records[0]["auth_method"], "configured_key");
+    assert_eq!(records[1]["outcome"], "error");
+    assert_eq!(records[1]["arguments"]["password"], "REDACTED");
+}
+
+#[tokio::test]
+async fn sessions_receive_log_messages_at_the_level_they_set() {
+    let server = TestServer::start(&[]);
+    let events = server.get("/sse").send().await.expect("server answers");
+    let (mut events, endpoint) = next_event(events).await;
+    let request = |id: u64, method: &str, params: Value| {
+        json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }).to_string()
+    };
+
+    // Without a session there is nowhere to send them.
+    let refused = server
+        .call("logging/setLevel", json!({ "level": "info" }))
+        .await;
+    assert_eq!(refused["error"]["code"], -32602, "{refused}");
+
+    let set_level = request(1, "logging/setLevel", json!({ "level": "info" }));
+    let echo = request(
+        2,
+        "tools/call",
+        json!({ "name": "echo", "arguments": { "message": "hi" } }),
+    );
+    for body in [set_level, echo] {
+        let (status, _) = server.rpc_with(server.post(&endpoint.data), &body).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+    }
+    let (mut logged, mut answered) = (None, false);
+    while logged.is_none() || !answered {
+        let (rest, event) = next_event(events).await;
+        events = rest;
+        let message = event.json();
+        if message["method"] == "notifications/message"
+            && message["params"]["data"]["tool"] == "echo"
+        {
+            logged = Some(message);
+        } else if message["id"] == 2 {
+            answered = true;
+        }
+    }
+    let logged = logged.unwrap();
+    assert_eq!(logged["params"]["level"], "info", "{logged}");
+    assert_eq!(
+        logged["params"]["data"]["message"], "tool call completed",
+        "{logged}"
+    );
+}
+
+#[tokio::test]
+async fn sse_sessions_scope_state_and_close_with_the_stream() {
+    let server = TestServer::start(&[("INFERENCO_MCP_ADMIN_TOKEN", ADMIN_TOKEN)]);
+    let events = server.get("/sse").send().await.expect("server answers");
+    assert_eq!(events.status(), StatusCode::OK);
+    let session = events
+        .headers()
+        .get("mcp-session-id")
+        .and_then(|value| value.to_str().ok())
+        .expect("session id header")
+        .to_string();
+    let (events, endpoint) = next_event(events).await;
+    assert_eq!(endpoint.name, "endpoint");
+    assert_eq!(endpoint.data, format!("/sse?sessionId={session}"));
+    assert_eq!(open_sessions(&server).await, vec![session.clone()]);
+
+    // Messages are only acknowledged; the responses arrive on the stream.
+    let call = |id: u64, name: &str, arguments: Value| {
+        json!({ "jsonrpc": "2.0", "id": id, "method": "tools/call",
+                "params": { "name": name, "arguments": arguments } })
+        .to_string()
+    };
+    let in_session = || server.post(&endpoint.data);
+    let (status, reply) = server
+        .rpc_with(
+            in_session(),
+            &call(1, "kv_set", json!({ "key": "k", "value": "v" })),
+        )
+        .await;
+    assert_eq!((status, reply), (StatusCode::ACCEPTED, Value::Null));
+    let (events, set) = next_event(events).await;
+    assert_eq!(set.name, "message");
+    assert_eq!(set.json()["id"], 1);
+
+    let get = call(2, "kv_get", json!({ "key": "k" }));
+    let found = |reply: &Value| {
+        let lookup: Value = serde_json::from_str(text(reply)).expect("JSON lookup");
+        lookup["found"] == true
+    };
+    let (status, _) = server.rpc_with(in_session(), &get).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let (events, reply) = next_event(events).await;
+    let reply = reply.json();
+    assert_eq!(reply["id"], 2);
+    assert!(found(&reply), "{reply}");
+    // Outside the session the key doesn't exist.
+    assert!(!found(&server.rpc_with(server.post("/rpc"), &get).await.1));
+
+    // Without an open stream there is nowhere to answer.
+    let (status, _) = server
+        .rpc_with(server.post("/sse?sessionId=unknown"), &get)
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = server.rpc_with(server.post("/sse"), &get).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    drop(events);
+    let mut waited = Duration::ZERO;
+    while !open_sessions(&server).await.is_empty() {
+        assert!(waited < Duration::from_secs(10), "session never closed");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        waited += Duration::from_millis(50);
+    }
+}
+
+#[tokio::test]
+async fn streamable_http_sessions_start_with_initialize_and_end_with_delete() {
+    let server = TestServer::start(&[("INFERENCO_MCP_ADMIN_TOKEN", ADMIN_TOKEN)]);
+    let message = |id: u64, method: &str, params: Value| {
+        json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }).to_string()
+    };
+
+    let response = server
+        .post("/mcp")
+        .header("content-type", "application/json")
+        .header("accept", "application/json, text/event-stream")
+        .body(message(
+            1,
+            "initialize",
+            json!({ "protocolVersion": "2025-03-26" }),
+        ))
+        .send()
+        .await
+        .expect("server answers");
+    assert_eq!(response.status(), StatusCode::OK);
+    let session = response
+        .headers()
+        .get("mcp-session-id")
+        .and_then(|value| value.to_str().ok())
+        .expect("session id header")
+        .to_string();
+    let init: Value = response.json().await.expect("JSON reply");
+    assert_eq!(init["result"]["protocolVersion"], "2025-03-26");
+    assert_eq!(open_sessions(&server).await, vec![session.clone()]);
+
+    let in_session = || server.post("/mcp").header("mcp-session-id", &session);
+    let initialized = r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#;
+    assert_eq!(
+        server.rpc_with(in_session(), initialized).await,
+        (StatusCode::ACCEPTED, Value::Null)
+    );
+    let sessions: Value = server
+        .get("/admin/sessions")
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .expect("server answers")
+        .json()
+        .await
+        .expect("JSON sessions");
+    assert_eq!(sessions["sessions"][0]["initialized"], true, "{sessions}");
+    let list = message(2, "tools/list", json!({}));
+    let (status, reply) = server.rpc_with(in_session(), &list).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(reply["result"]["tools"].is_array(), "{reply}");
+
+    let (status, _) = server.rpc_with(server.post("/mcp"), &list).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let stranger = server
+        .post("/mcp")
+        .header("mcp-session-id", "no-such-session");
+    assert_eq!(
+        server.rpc_with(stranger, &list).await.0,
+        StatusCode::NOT_FOUND
+    );
+
+    let events = server
+        .get("/mcp")
+        .header("mcp-session-id", &session)
+        .header("accept", "text/event-stream")
+        .send()
+        .await
+        .expect("server answers");
+    assert_eq!(events.status(), StatusCode::OK);
+    assert_eq!(
+        events.headers()["content-type"].to_str().unwrap(),
+        "text/event-stream"
+    );
+
+    let deleted = server
+        .client()
+        .delete(server.url("/mcp"))
+        .header("mcp-session-id", &session)
+        .send()
+        .await
+        .expect("server answers");
+    assert_eq!(deleted.status(), StatusCode::NO_CONTENT);
+    assert!(open_sessions(&server).await.is_empty());
+    assert_eq!(
+        server.rpc_with(in_session(), &list).await.0,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn streamable_http_refuses_sessions_past_the_limit_while_all_are_in_use() {
+    let server = TestServer::start(&[("INFERENCO_MCP_MAX_SESSIONS", "1")]);
+    let server = &server;
+    let initialize = || async move {
+        server
+            .post("/mcp")
+            .header("content-type", "application/json")
+            .body(
+                json!({
+                    "jsonrpc": "2.0", "id": 1, "method": "initialize",
+                    "params": { "protocolVersion": "2025-03-26" }
+                })
+                .to_string(),
+            )
+            .send()
+            .await
+            .expect("server answers")
+    };
+    let response = initialize().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let session = response.headers()["mcp-session-id"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let events = server
+        .get("/mcp")
+        .header("mcp-session-id", &session)
+        .send()
+        .await
+        .expect("server answers");
+    assert_eq!(events.status(), StatusCode::OK);
+
+    let refused = initialize().await;
+    assert_eq!(refused.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert!(!refused.headers().contains_key("mcp-session-id"));
+    let reply: Value = refused.json().await.expect("JSON reply");
+    assert_eq!(reply["error"]["code"], -32000, "{reply}");
+
+    let deleted = server
+        .client()
+        .delete(server.url("/mcp"))
+        .header("mcp-session-id", &session)
+        .send()
+        .await
+        .expect("server answers");
+    assert_eq!(deleted.status(), StatusCode::NO_CONTENT);
+    drop(events);
+    assert_eq!(initialize().await.status(), StatusCode::OK);
+}
+
+async fn open_sessions(server: &TestServer) -> Vec<String> {
+    let sessions: Value = server
+        .get("/admin/sessions")
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .expect("server answers")
+        .json()
+        .await
+        .expect("JSON sessions");
+    sessions["sessions"]
+        .as_array()
+        .expect("session list")
+        .iter()
+        .filter_map(|session| session["id"].as_str().map(str::to_string))
+        .collect()
+}
+
+async fn first_event(response: reqwest::Response) -> Value {
+    next_event(response).await.1.json()
+}
+
+struct SseEvent {
+    name: String,
+    data: String,
+}
+
+impl SseEvent {
+    fn json(&self) -> Value {
+        serde_json::from_str(&self.data).expect("JSON event")
+    }
+}
+
+/// Read up to the next `data:` event, returning the still-open stream with it.
+async fn next_event(mut response: reqwest::Response) -> (reqwest::Response, SseEvent) {
+    let mut buffer = String::new();
+    loop {
+        while let Some(end) = buffer.find("\n\n") {
+            let event: String = buffer.drain(..end + 2).collect();
+            if let Some(data) = event.lines().find_map(|line| line.strip_prefix("data: ")) {
+                let name = event
+                    .lines()
+                    .find_map(|line| line.strip_prefix("event: "))
+                    .unwrap_or("message");
+                let event = SseEvent {
+                    name: name.to_string(),
+                    data: data.to_string(),
+                };
+                return (response, event);
+            }
+        }
+        let chunk = tokio::time::timeout(Duration::from_secs(10), response.chunk())
+            .await
+            .expect("event within 10s")
+            .expect("stream readable")
+            .expect("stream still open");
+        buffer.push_str(&String::from_utf8_lossy(&chunk));
+    }
+}
+
+#[tokio::test]
+async fn websocket_sessions_carry_requests_and_responses_both_ways() {
+    use tokio_tungstenite::tungstenite;
+
+    let server = TestServer::start_transport(
+        "ws",
+        &[
+            ("INFERENCO_MCP_AUTH_ENABLED", "true"),
+            ("INFERENCO_MCP_API_KEYS", "good-key"),
+            ("INFERENCO_MCP_ADMIN_TOKEN", ADMIN_TOKEN),
+        ],
+    );
+    let url = server.url("/ws").replacen("http", "ws", 1);
+    match tokio_tungstenite::connect_async(&url).await {
+        Err(tungstenite::Error::Http(response)) => {
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED.as_u16());
+        }
+        other => panic!("expected 401, got {:?}", other.map(|_| ())),
+    }
+    // The HTTP endpoints belong to the http transport.
+    let (status, _) = server.rpc_with(server.post("/rpc"), "{}").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("{url}?token=good-key"))
+        .await
+        .expect("upgrade accepted");
+    let init = exchange(
+        &mut socket,
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize",
+        "params": { "protocolVersion": "2025-03-26", "capabilities": {},
+                    "clientInfo": { "name": "ws-test", "version": "0" } } }),
+    )
+    .await;
+    assert_eq!(init["result"]["protocolVersion"], "2025-03-26", "{init}");
+    // Notifications are not answered, so the next frame is the echo's.
+    let echo = exchange(
+        &mut socket,
+        json!([
+            { "jsonrpc": "2.0", "method": "notifications/initialized" },
+            { "jsonrpc": "2.0", "id": 2, "method": "tools/call",
+              "params": { "name": "echo", "arguments": { "message": "over ws" } } }
+        ]),
+    )
+    .await;
+    assert_eq!(echo[0]["id"], 2, "{echo}");
+    assert_eq!(text(&echo[0]), "over ws");
+    let invalid = exchange(&mut socket, json!({ "id": 3 })).await;
+    assert_eq!(invalid["error"]["code"], -32600, "{invalid}");
+    assert_eq!(open_sessions(&server).await.len(), 1);
+
+    socket.close(None).await.expect("close sent");
+    let mut waited = Duration::ZERO;
+    while !open_sessions(&server).await.is_empty() {
+        assert!(waited < Duration::from_secs(10), "session never closed");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        waited += Duration::from_millis(50);
+    }
+}
+
+#[tokio::test]
+async fn websocket_messages_after_revoking_the_key_are_unauthorized() {
+    let server = TestServer::start_transport(
+        "ws",
+        &[
+            ("INFERENCO_MCP_AUTH_ENABLED", "true"),
+            ("INFERENCO_MCP_KEYS_FILE", "keys.json"),
+            ("INFERENCO_MCP_ADMIN_TOKEN", ADMIN_TOKEN),
+        ],
+    );
+    let created: Value = server
+        .post("/admin/keys")
+        .bearer_auth(ADMIN_TOKEN)
+        .json(&json!({ "name": "ws" }))
+        .send()
+        .await
+        .expect("server answers")
+        .json()
+        .await
+        .unwrap();
+    let secret = created["key"].as_str().expect("the secret is shown once");
+    let id = created["id"].as_str().expect("key id");
+    let url = server.url("/ws").replacen("http", "ws", 1);
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("{url}?token={secret}"))
+        .await
+        .expect("upgrade accepted");
+    let echo = json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/call",
+                       "params": { "name": "echo", "arguments": { "message": "hi" } } });
+    assert_eq!(text(&exchange(&mut socket, echo.clone()).await), "hi");
+
+    let revoked = server
+        .client()
+        .delete(server.url(&format!("/admin/keys/{id}")))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .expect("server answers");
+    assert_eq!(revoked.status(), StatusCode::OK);
+    // Unlike the rate limit's -32001, which says to slow down.
+    let refused = exchange(&mut socket, echo).await;
+    assert_eq!(refused["error"]["code"], -32005, "{refused}");
+}
+
+#[tokio::test]
+async fn websocket_messages_count_against_the_rate_limit() {
+    let server = TestServer::start_transport("ws", &[("INFERENCO_MCP_RATE_LIMIT_KEY", "3/min")]);
+    let url = server.url("/ws").replacen("http", "ws", 1);
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("{url}?token=a"))
+        .await
+        .expect("upgrade accepted");
+    // The upgrade took one token, leaving two for messages.
+    for id in 1..=2 {
+        let echo = exchange(
+            &mut socket,
+            json!({ "jsonrpc": "2.0", "id": id, "method": "tools/call",
+                    "params": { "name": "echo", "arguments": { "message": "hi" } } }),
+        )
+        .await;
+        assert_eq!(text(&echo), "hi");
+    }
+    let limited = exchange(
+        &mut socket,
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "tools/call",
+                "params": { "name": "echo", "arguments": { "message": "hi" } } }),
+    )
+    .await;
+    assert_eq!(limited["id"], 3, "{limited}");
+    assert_eq!(limited["error"]["code"], -32001);
+    assert_eq!(limited["error"]["data"]["limit"], "key");
+}
+
+#[tokio::test]
+async fn transports_are_served_side_by_side() {
+    // Stdin is closed, so the stdio client is gone at once; HTTP carries on.
+    let server = TestServer::start_transport("stdio,http,ws", &[]);
+    let echo = server
+        .call(
+            "tools/call",
+            json!({ "name": "echo", "arguments": { "message": "over http" } }),
+        )
+        .await;
+    assert_eq!(text(&echo), "over http");
+
+    let url = server.url("/ws").replacen("http", "ws", 1);
+    let (mut socket, _) = tokio_tungstenite::connect_async(url)
+        .await
+        .expect("upgrade accepted");
+    let echo = exchange(
+        &mut socket,
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/call",
+                "params": { "name": "echo", "arguments": { "message": "over ws" } } }),
+    )
+    .await;
+    assert_eq!(text(&echo), "over ws");
+}
+
+type WebSocket =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// Send `message` and return the next text frame.
+async fn exchange(socket: &mut WebSocket, message: Value) -> Value {
+    use futures::{SinkExt as _, StreamExt as _};
+    use tokio_tungstenite::tungstenite::Message;
+
+    socket
+        .send(Message::text(message.to_string()))
+        .await
+        .expect("frame sent");
+    loop {
+        let frame = tokio::time::timeout(Duration::from_secs(10), socket.next())
+            .await
+            .expect("reply within 10s")
+            .expect("socket open")
+            .expect("frame readable");
+        if let Message::Text(text) = frame {
+            return serde_json::from_str(&text).expect("JSON frame");
+        }
+    }
+}
+
+#[tokio::test]
+async fn typed_client_calls_tools_remotely() {
+    let server = TestServer::start(&[
+        ("INFERENCO_MCP_AUTH_ENABLED", "true"),
+        ("INFERENCO_MCP_API_KEYS", "good-key"),
+    ]);
+    let client = InferencoClient::remote(server.url("")).with_api_key("good-key");
+    assert_eq!(client.echo("over http").await.unwrap(), "over http");
+    client.kv_set("k", &"v", None).await.unwrap();
+    let value = client.kv_get::<String>("k").await.unwrap().expect("stored");
+    assert_eq!(value.value, "v");
+
+    let unknown = client
+        .call_tool("no_such_tool", json!({}))
+        .await
+        .unwrap_err();
+    assert_eq!(unknown.code.0, -32602);
+    let denied = InferencoClient::remote(server.url("")).with_api_key("bad-key");
+    let error = denied.echo("hi").await.unwrap_err();
+    assert!(error.message.contains("401"), "{error:?}");
+}
+
+#[tokio::test]
+async fn docs_pages_are_read_as_resources() {
+    let docs = axum::Router::new()
+        .route(
+            "/docs/",
+            axum::routing::get(|| async {
+                axum::response::Html(
+                    "<html><head><title>Cedra</title></head><body><p>Welcome.</p>\
+                     <a href=\"move\">Move</a><a href=\"https://elsewhere.example.com/\">Away</a>\
+                     </body></html>",
+                )
+            }),
+        )
+        .route(
+            "/docs/move",
+            axum::routing::get(|| async { axum::response::Html("<p>Modules.</p>") }),
+        );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let docs_url = format!("http://{}/docs", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, docs).await });
+    let server = TestServer::start(&[("INFERENCO_MCP_DOCS_URL", &docs_url)]);
+
+    let list = server.call("resources/list", json!({})).await;
+    let uris: Vec<&str> = list["result"]["resources"]
+        .as_array()
+        .expect("resource list")
+        .iter()
+        .filter_map(|resource| resource["uri"].as_str())
+        .collect();
+    assert_eq!(uris, ["cedra-docs://"]);
+    let templates = server.call("resources/templates/list", json!({})).await;
+    assert_eq!(
+        templates["result"]["resourceTemplates"][0]["uriTemplate"],
+        "cedra-docs://{path}"
+    );
+
+    let index = server
+        .call("resources/read", json!({ "uri": "cedra-docs://" }))
+        .await;
+    let page = index["result"]["contents"][0]["text"]
+        .as_str()
+        .unwrap_or_else(|| panic!("no page text in {index}"));
+    assert!(page.starts_with("# Cedra"), "{page}");
+    assert!(page.contains("- cedra-docs://move"), "{page}");
+    assert!(!page.contains("elsewhere"), "{page}");
+
+    let outside = server
+        .call("resources/read", json!({ "uri": "cedra-docs://../admin" }))
+        .await;
+    assert_eq!(outside["error"]["code"], -32002, "{outside}");
+
+    let prompt = server
+        .call(
+            "prompts/get",
+            json!({ "name": "summarize_doc_page", "arguments": { "page": "move" } }),
+        )
+        .await;
+    assert_eq!(
+        prompt["result"]["messages"][1]["content"]["uri"], "cedra-docs://move",
+        "{prompt}"
+    );
+}
+
+#[tokio::test]
+async fn subscribers_hear_when_a_docs_page_changes() {
+    let text = std::sync::Arc::new(std::sync::Mutex::new("First draft.".to_string()));
+    let page = text.clone();
+    let docs = axum::Router::new().route(
+        "/docs/move",
+        axum::routing::get(move || {
+            let text = page.lock().unwrap().clone();
+            async move { axum::response::Html(format!("<p>{text}</p>")) }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let docs_url = format!("http://{}/docs/", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, docs).await });
+    let server = TestServer::start(&[
+        ("INFERENCO_MCP_DOCS_URL", &docs_url),
+        ("INFERENCO_MCP_DOCS_REFRESH_SECS", "1"),
+    ]);
+
+    let init = server
+        .post("/mcp")
+        .header("content-type", "application/json")
+        .body(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#)
+        .send()
+        .await
+        .expect("server answers");
+    let session = init.headers()["mcp-session-id"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let subscribe = json!({
+        "jsonrpc": "2.0", "id": 2, "method": "resources/subscribe",
+        "params": { "uri": "cedra-docs://move" }
+    });
+    let (status, reply) = server
+        .rpc_with(
+            server.post("/mcp").header("mcp-session-id", &session),
+            &subscribe.to_string(),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(reply["result"], json!({}), "{reply}");
+    let events = server
+        .get("/mcp")
+        .header("mcp-session-id", &session)
+        .header("accept", "text/event-stream")
+        .send()
+        .await
+        .expect("server answers");
+
+    *text.lock().unwrap() = "Second draft.".to_string();
+    let update = first_event(events).await;
+    assert_eq!(update["method"], "notifications/resources/updated");
+    assert_eq!(update["params"]["uri"], "cedra-docs://move");
+}
@@ -0,0 +1,331 @@
+//! Boots the real server binary for end-to-end tests.
+//!
+//! Each [`TestServer`] runs `serve --transport http --port 0` (or another
+//! HTTP transport, with [`TestServer::start_transport`]) in an empty
+//! working directory with a clean environment (so a local `.env` or
+//! exported `INFERENCO_MCP_*` variables never leak in), waits for its
+//! `server ready` log line to learn the port, and kills the process when
+//! dropped. A [`StdioServer`] runs `serve` the same way and talks
+//! newline-delimited JSON-RPC over its stdin and stdout, or over a TCP
+//! connection to `serve --transport tcp`, which frames messages the same.
+
+// Each test crate uses its own part of the harness.
+#![allow(dead_code)]
+
+use reqwest::{RequestBuilder, StatusCode};
+use serde_json::{json, Value};
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    process::{Child, Command, Stdio},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+/// How long a server may take to start before the test fails.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long a stdio server may take to answer a request.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct TestServer {
+    child: Child,
+    base_url: String,
+    client: reqwest::Client,
+    _dir: tempfile::TempDir,
+}
+
+impl TestServer {
+    /// Start a server with `env` on top of a clean environment.
+    pub fn start(env: &[(&str, &str)]) -> Self {
+        Self::start_transport("http", env)
+    }
+
+    /// Start a server for `transport`, which listens on the HTTP port.
+    pub fn start_transport(transport: &str, env: &[(&str, &str)]) -> Self {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let mut child = Command::new(env!("CARGO_BIN_EXE_inferenco-mcp-stdio"))
+            .args(["serve", "--transport", transport, "--port", "0"])
+            .current_dir(dir.path())
+            .env_clear()
+            .env("INFERENCO_MCP_LOG_FORMAT", "json")
+            .envs(env.iter().copied())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("server binary starts");
+
+        // Logs keep flowing after startup; drain them so the pipe never fills.
+        let logs = BufReader::new(child.stderr.take().expect("piped stderr"));
+        let (ready, port) = mpsc::channel();
+        thread::spawn(move || {
+            for line in logs.lines().map_while(Result::ok) {
+                let Ok(event) = serde_json::from_str::<Value>(&line) else {
+                    continue;
+                };
+                if event["message"] == "server ready" {
+                    let _ = ready.send(event["port"].as_u64());
+                }
+            }
+        });
+        let port = match port.recv_timeout(STARTUP_TIMEOUT) {
+            Ok(Some(port)) => port,
+            outcome => {
+                let _ = child.kill();
+                panic!("server did not report ready: {outcome:?}");
+            }
+        };
+        Self {
+            child,
+            base_url: format!("http://127.0.0.1:{port}"),
+            client: reqwest::Client::new(),
+            _dir: dir,
+        }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.base_url)
+    }
+
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    pub fn get(&self, path: &str) -> RequestBuilder {
+        self.client.get(self.url(path))
+    }
+
+    pub fn post(&self, path: &str) -> RequestBuilder {
+        self.client.post(self.url(path))
+    }
+
+    /// POST `body` to `/rpc`, returning the status and the parsed reply
+    /// (`Null` for an empty body).
+    pub async fn rpc_with(&self, request: RequestBuilder, body: &str) -> (StatusCode, Value) {
+        let response = request
+            .header("content-type", "application/json")
+            .body(body.to_string())
+            .send()
+            .await
+            .expect("server answers");
+        let status = response.status();
+        let text = response.text().await.expect("response body");
+        let reply = if text.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_str(&text).expect("JSON reply")
+        };
+        (status, reply)
+    }
+
+    /// Call `method` with `params` and return the reply.
+    pub async fn call(&self, method: &str, params: Value) -> Value {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let (status, reply) = self.rpc_with(self.post("/rpc"), &request.to_string()).await;
+        assert_eq!(status, StatusCode::OK, "{reply}");
+        reply
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+pub struct StdioServer {
+    /// `None` for a further connection from [`Self::connect_again`].
+    child: Option<Child>,
+    input: Box<dyn Write + Send>,
+    messages: mpsc::Receiver<Value>,
+    next_id: u64,
+    /// The port of a TCP server.
+    port: Option<u16>,
+    _dir: Option<tempfile::TempDir>,
+}
+
+/// Run `once` on a clean environment with `input` on stdin. Returns its
+/// exit code and what it printed.
+pub fn run_once(input: &str) -> (i32, String) {
+    let dir = tempfile::tempdir().expect("temp dir");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_inferenco-mcp-stdio"))
+        .arg("once")
+        .current_dir(dir.path())
+        .env_clear()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("binary starts");
+    let mut stdin = child.stdin.take().expect("piped stdin");
+    stdin.write_all(input.as_bytes()).expect("once reads stdin");
+    drop(stdin);
+    let output = child.wait_with_output().expect("once exits");
+    let code = output.status.code().expect("exited normally");
+    (
+        code,
+        String::from_utf8(output.stdout).expect("UTF-8 output"),
+    )
+}
+
+impl StdioServer {
+    /// Start a stdio server on a clean environment.
+    pub fn start() -> Self {
+        Self::start_with(&[])
+    }
+
+    /// Start a stdio server with only `env` set.
+    pub fn start_with(env: &[(&str, &str)]) -> Self {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let mut child = Command::new(env!("CARGO_BIN_EXE_inferenco-mcp-stdio"))
+            .arg("serve")
+            .current_dir(dir.path())
+            .env_clear()
+            .envs(env.iter().copied())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("server binary starts");
+        let input = child.stdin.take().expect("piped stdin");
+        let output = child.stdout.take().expect("piped stdout");
+        Self::connect(Some(child), input, output, Some(dir))
+    }
+
+    /// Start `serve --transport tcp` on a free port with only `env` set, and
+    /// connect to it.
+    pub fn start_tcp(env: &[(&str, &str)]) -> Self {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let mut child = Command::new(env!("CARGO_BIN_EXE_inferenco-mcp-stdio"))
+            .args(["serve", "--transport", "tcp", "--tcp-addr", "127.0.0.1:0"])
+            .current_dir(dir.path())
+            .env_clear()
+            .env("INFERENCO_MCP_LOG_FORMAT", "json")
+            .envs(env.iter().copied())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("server binary starts");
+
+        let logs = BufReader::new(child.stderr.take().expect("piped stderr"));
+        let (ready, port) = mpsc::channel();
+        thread::spawn(move || {
+            for line in logs.lines().map_while(Result::ok) {
+                let Ok(event) = serde_json::from_str::<Value>(&line) else {
+                    continue;
+                };
+                if event["message"] == "tcp ready" {
+                    let _ = ready.send(event["port"].as_u64());
+                }
+            }
+        });
+        let port = match port.recv_timeout(STARTUP_TIMEOUT) {
+            Ok(Some(port)) => port,
+            outcome => {
+                let _ = child.kill();
+                panic!("server did not report tcp ready: {outcome:?}");
+            }
+        };
+        let mut server = Self::connect_tcp(port as u16);
+        server.child = Some(child);
+        server._dir = Some(dir);
+        server
+    }
+
+    /// Open another connection to this TCP server, a session of its own.
+    /// The server stops with the connection that started it.
+    pub fn connect_again(&self) -> Self {
+        Self::connect_tcp(self.port.expect("a TCP server"))
+    }
+
+    fn connect_tcp(port: u16) -> Self {
+        let stream = TcpStream::connect(("127.0.0.1", port)).expect("server accepts");
+        let output = stream.try_clone().expect("socket clones");
+        let mut server = Self::connect(None, stream, output, None);
+        server.port = Some(port);
+        server
+    }
+
+    fn connect(
+        child: Option<Child>,
+        input: impl Write + Send + 'static,
+        output: impl Read + Send + 'static,
+        dir: Option<tempfile::TempDir>,
+    ) -> Self {
+        let output = BufReader::new(output);
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            for line in output.lines().map_while(Result::ok) {
+                let message = serde_json::from_str(&line).expect("JSON-RPC message per line");
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            child,
+            input: Box::new(input),
+            messages,
+            next_id: 0,
+            port: None,
+            _dir: dir,
+        }
+    }
+
+    pub fn send(&mut self, message: &Value) {
+        writeln!(self.input, "{message}").expect("server reads its input");
+        self.input.flush().expect("server reads its input");
+    }
+
+    /// Send `method` with `params` and return the reply to it. Server
+    /// notifications in between are skipped; any other reply fails the
+    /// test, since nothing else was asked.
+    pub fn request(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.send(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        self.reply(&json!(id))
+    }
+
+    /// The reply to the request with `id`, which was sent with
+    /// [`Self::send`]. Server notifications in between are skipped.
+    pub fn reply(&mut self, id: &Value) -> Value {
+        loop {
+            let message = self
+                .messages
+                .recv_timeout(REPLY_TIMEOUT)
+                .unwrap_or_else(|_| panic!("no reply to request {id}"));
+            match message.get("id") {
+                Some(reply_id) if reply_id == id => return message,
+                Some(_) => panic!("unexpected reply while waiting on request {id}: {message}"),
+                None => continue,
+            }
+        }
+    }
+
+    /// The next message from the server, such as a request it makes of the
+    /// client.
+    pub fn next_message(&mut self) -> Value {
+        self.messages
+            .recv_timeout(REPLY_TIMEOUT)
+            .expect("a message from the server")
+    }
+
+    /// Send the notification `method`, which is never replied to.
+    pub fn notify(&mut self, method: &str) {
+        self.send(&json!({ "jsonrpc": "2.0", "method": method }));
+    }
+}
+
+impl Drop for StdioServer {
+    fn drop(&mut self) {
+        if let Some(child) = &mut self.child {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}